@@ -0,0 +1,117 @@
+//! A minimal encoder for the external term format, used to store constant terms which cannot be
+//! encoded as immediates, e.g. map literals, in the binary table of the generated bytecode. The
+//! runtime decodes them when the bytecode is loaded.
+use std::borrow::Borrow;
+
+use firefly_binary::Bitstring;
+use firefly_number::{Int, Sign};
+use firefly_syntax_ssa::ConstantItem;
+
+const VERSION: u8 = 131;
+const NEW_FLOAT_EXT: u8 = 70;
+const BIT_BINARY_EXT: u8 = 77;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+/// Encodes a map constant with the given key/value pairs
+pub fn encode_map(pairs: &[(ConstantItem, ConstantItem)]) -> Vec<u8> {
+    let mut buffer = vec![VERSION, MAP_EXT];
+    put_u32(&mut buffer, pairs.len());
+    for (key, value) in pairs {
+        encode_constant(&mut buffer, key);
+        encode_constant(&mut buffer, value);
+    }
+    buffer
+}
+
+fn encode_constant(buffer: &mut Vec<u8>, constant: &ConstantItem) {
+    match constant {
+        ConstantItem::Integer(Int::Small(i)) => encode_integer(buffer, *i),
+        ConstantItem::Integer(Int::Big(i)) => {
+            let (sign, digits) = i.to_bytes_le();
+            if digits.len() < 256 {
+                buffer.push(SMALL_BIG_EXT);
+                buffer.push(digits.len() as u8);
+            } else {
+                buffer.push(LARGE_BIG_EXT);
+                put_u32(buffer, digits.len());
+            }
+            buffer.push((sign == Sign::Minus) as u8);
+            buffer.extend_from_slice(&digits);
+        }
+        ConstantItem::Float(f) => {
+            buffer.push(NEW_FLOAT_EXT);
+            buffer.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+        ConstantItem::Bool(true) => encode_atom(buffer, "true"),
+        ConstantItem::Bool(false) => encode_atom(buffer, "false"),
+        ConstantItem::Atom(a) => encode_atom(buffer, a.as_str().get()),
+        ConstantItem::Bytes(data) => encode_binary(buffer, data.as_slice(), 0),
+        ConstantItem::Bitstring(bitvec) => {
+            let selection = bitvec.select();
+            let bytes = selection.to_bytes();
+            encode_binary(buffer, bytes.borrow(), selection.trailing_bits())
+        }
+        ConstantItem::String(s) => encode_binary(buffer, s.as_bytes(), 0),
+        ConstantItem::InternedStr(s) => encode_binary(buffer, s.as_str().get().as_bytes(), 0),
+        ConstantItem::Map(pairs) => {
+            buffer.push(MAP_EXT);
+            put_u32(buffer, pairs.len());
+            for (key, value) in pairs {
+                encode_constant(buffer, key);
+                encode_constant(buffer, value);
+            }
+        }
+    }
+}
+
+fn encode_integer(buffer: &mut Vec<u8>, i: i64) {
+    if let Ok(byte) = u8::try_from(i) {
+        buffer.push(SMALL_INTEGER_EXT);
+        buffer.push(byte);
+    } else if let Ok(word) = i32::try_from(i) {
+        buffer.push(INTEGER_EXT);
+        buffer.extend_from_slice(&word.to_be_bytes());
+    } else {
+        let magnitude = i.unsigned_abs();
+        let len = 8 - (magnitude.leading_zeros() as usize / 8);
+        buffer.push(SMALL_BIG_EXT);
+        buffer.push(len as u8);
+        buffer.push((i < 0) as u8);
+        buffer.extend_from_slice(&magnitude.to_le_bytes()[..len]);
+    }
+}
+
+fn encode_atom(buffer: &mut Vec<u8>, name: &str) {
+    if let Ok(len) = u8::try_from(name.len()) {
+        buffer.push(SMALL_ATOM_UTF8_EXT);
+        buffer.push(len);
+    } else {
+        buffer.push(ATOM_UTF8_EXT);
+        buffer.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    }
+    buffer.extend_from_slice(name.as_bytes());
+}
+
+fn encode_binary(buffer: &mut Vec<u8>, bytes: &[u8], trailing_bits: u8) {
+    if trailing_bits == 0 {
+        buffer.push(BINARY_EXT);
+        put_u32(buffer, bytes.len());
+    } else {
+        buffer.push(BIT_BINARY_EXT);
+        put_u32(buffer, bytes.len());
+        buffer.push(trailing_bits);
+    }
+    buffer.extend_from_slice(bytes);
+}
+
+fn put_u32(buffer: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("constant is too large to encode");
+    buffer.extend_from_slice(&len.to_be_bytes());
+}
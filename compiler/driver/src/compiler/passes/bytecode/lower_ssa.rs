@@ -297,6 +297,9 @@ impl<'a> BytecodeBuilder<'a> {
             ConstantItem::InternedStr(ident) => {
                 builder.build_utf8_binary(ident.as_str().get(), loc)
             }
+            ConstantItem::Map(ref pairs) => {
+                builder.build_map_literal(&super::etf::encode_map(pairs), loc)
+            }
         }
     }
 
@@ -694,6 +697,46 @@ impl<'a> BytecodeBuilder<'a> {
                         }
                         _ => unreachable!(),
                     },
+                    (symbols::SpawnMonitor, 1) => match op.op {
+                        Opcode::Call => {
+                            let results = dfg.inst_results(inst);
+                            assert_eq!(results.len(), 1);
+                            let pid = builder.build_spawn2(args[0], SpawnOpts::MONITOR, loc);
+                            self.values.insert(results[0], pid);
+                            return Ok(());
+                        }
+                        Opcode::Enter => {
+                            builder.build_spawn2(args[0], SpawnOpts::MONITOR, loc);
+                            return Ok(());
+                        }
+                        _ => unreachable!(),
+                    },
+                    (symbols::SpawnMonitor, 3) => match op.op {
+                        Opcode::Call => {
+                            let results = dfg.inst_results(inst);
+                            assert_eq!(results.len(), 1);
+                            let pid = builder.build_spawn3_indirect(
+                                args[0],
+                                args[1],
+                                args[2],
+                                SpawnOpts::MONITOR,
+                                loc,
+                            );
+                            self.values.insert(results[0], pid);
+                            return Ok(());
+                        }
+                        Opcode::Enter => {
+                            builder.build_spawn3_indirect(
+                                args[0],
+                                args[1],
+                                args[2],
+                                SpawnOpts::MONITOR,
+                                loc,
+                            );
+                            return Ok(());
+                        }
+                        _ => unreachable!(),
+                    },
                     (symbols::Yield, 0) => {
                         let results = dfg.inst_results(inst);
                         assert_eq!(results.len(), 1);
@@ -964,6 +1007,12 @@ impl<'a> BytecodeBuilder<'a> {
                 self.values.insert(result, i);
                 Ok(())
             }
+            Opcode::ConstMap => {
+                let map = self.load_constant(builder, &dfg.constant(op.imm), loc);
+                let result = dfg.first_result(inst);
+                self.values.insert(result, map);
+                Ok(())
+            }
             _ => {
                 let arg = self.load_constant(builder, &dfg.constant(op.imm), loc);
                 self.do_build_unary_op(builder, dfg, inst, loc, op.op, arg)
@@ -1,3 +1,4 @@
+mod etf;
 mod lower_bytecode;
 mod lower_ssa;
 
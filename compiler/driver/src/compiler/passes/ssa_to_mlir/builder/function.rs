@@ -275,6 +275,9 @@ impl<'m> ModuleBuilder<'m> {
             ConstantItem::InternedStr(ident) => {
                 self.bitstring_to_constant(loc, ident.as_str().get())
             }
+            ConstantItem::Map(_) => {
+                unimplemented!("map constants are not supported by this backend")
+            }
         }
     }
 
@@ -107,6 +107,7 @@ lazy_static! {
             bif!(pub erlang:halt/1(term) -> !),
             bif!(pub erlang:halt/2(term, list) -> !),
             bif!(guard erlang:hd/1(list) -> term),
+            bif!(pub erlang:hibernate/3(module, function, list) -> !),
             bif!(pub erlang:integer_to_binary/1(integer) -> binary),
             bif!(pub erlang:integer_to_binary/2(integer, pos_integer) -> binary),
             bif!(pub erlang:integer_to_list/1(integer) -> string),
@@ -219,6 +220,9 @@ lazy_static! {
             bif!(pub erlang:term_to_iovec/2(term, list) -> list),
             bif!(pub erlang:throw/1(any) -> term),
             bif!(pub erlang:time/0() -> time),
+            bif!(pub erlang:trace/3(term, boolean, list) -> non_neg_integer),
+            bif!(pub erlang:trace_info/2(term, atom) -> tuple),
+            bif!(pub erlang:trace_pattern/3(tuple, term, list) -> non_neg_integer),
             bif!(guard erlang:tl/1(nonempty_maybe_improper_list) -> term),
             bif!(guard erlang:trunc/1(number) -> integer),
             bif!(guard erlang:tuple_size/1(tuple) -> non_neg_integer),
@@ -229,6 +233,7 @@ lazy_static! {
             bif!(pub erlang:build_stacktrace/1(any) -> list),
             bif!(pub erlang:remove_message/0()),
             bif!(pub erlang:recv_next/0()),
+            bif!(pub erlang:recv_marker_use/1(term) -> atom),
             bif!(pub erlang:recv_peek_message/0() -> bool, any),
             bif!(pub erlang:recv_wait_timeout/1(timeout) -> bool),
             bif!(pub erlang:yield/0() -> bool),
@@ -55,6 +55,12 @@
 ///! As you can see, the receive no longer exists, having been rewritten into
 ///! a `letrec` expression with calls to various BIFs that implement the receive
 ///! primitives.
+///!
+///! When every clause of a receive only matches messages containing a reference
+///! created earlier in the function, by `make_ref/0` or `monitor/2,3`, the `letrec`
+///! is preceded by a call to `erlang:recv_marker_use/1` with that reference. This
+///! lets the runtime skip the messages which were already queued when the reference
+///! was created, none of which can contain it.
 use std::cell::UnsafeCell;
 use std::rc::Rc;
 
@@ -62,7 +68,7 @@ use rpds::{rbt_set, RedBlackTreeSet};
 
 use firefly_binary::BinaryEntrySpecifier;
 use firefly_diagnostics::*;
-use firefly_intern::{symbols, Ident};
+use firefly_intern::{symbols, Ident, Symbol};
 use firefly_pass::Pass;
 use firefly_syntax_base::*;
 
@@ -70,10 +76,15 @@ use crate::{passes::FunctionContext, *};
 
 pub struct RewriteReceivePrimitives {
     context: Rc<UnsafeCell<FunctionContext>>,
+    /// The variables in scope which are bound to newly created references
+    refs: RedBlackTreeSet<Symbol>,
 }
 impl RewriteReceivePrimitives {
     pub fn new(context: Rc<UnsafeCell<FunctionContext>>) -> Self {
-        Self { context }
+        Self {
+            context,
+            refs: RedBlackTreeSet::new(),
+        }
     }
 
     #[inline(always)]
@@ -264,6 +275,7 @@ impl RewriteReceivePrimitives {
                     args: vec![],
                 }));
 
+                let marker = self.receive_marker(recv.clauses.as_slice());
                 let mut clauses = Vec::with_capacity(recv.clauses.len());
                 clauses.append(&mut recv.clauses);
                 let mut clauses = self.rewrite_clauses(clauses);
@@ -352,6 +364,24 @@ impl RewriteReceivePrimitives {
                     defs: vec![(loop_fun, fun)],
                     body: apply_loop,
                 });
+                // Start the receive after the messages queued before the reference was created
+                let lr = match marker {
+                    None => lr,
+                    Some(reference) => {
+                        let reference = Var::new(Ident::new(reference, recv_span));
+                        Expr::Seq(Seq {
+                            span: recv_span,
+                            annotations: Annotations::default(),
+                            arg: Box::new(Expr::Call(Call::new(
+                                recv_span,
+                                symbols::Erlang,
+                                symbols::RecvMarkerUse,
+                                vec![Expr::Var(reference)],
+                            ))),
+                            body: Box::new(lr),
+                        })
+                    }
+                };
 
                 // If the 'after' expression is unsafe, evaluate it in an outer 'let'
                 let outer = match outer {
@@ -377,7 +407,16 @@ impl RewriteReceivePrimitives {
             }
             Expr::Let(ref mut expr) => {
                 self.lexpr(expr.arg.as_mut())?;
-                self.lexpr(expr.body.as_mut())
+                match (expr.vars.as_slice(), expr.arg.as_ref()) {
+                    ([var], Expr::Call(call)) if creates_reference(call) => {
+                        let refs = self.refs.clone();
+                        self.refs.insert_mut(var.name());
+                        let result = self.lexpr(expr.body.as_mut());
+                        self.refs = refs;
+                        result
+                    }
+                    _ => self.lexpr(expr.body.as_mut()),
+                }
             }
             Expr::LetRec(ref mut expr) => {
                 for (_, ref mut def) in expr.defs.iter_mut() {
@@ -416,6 +455,67 @@ impl RewriteReceivePrimitives {
         Ok(())
     }
 
+    /// Returns the reference variable which every one of `clauses` requires the message to
+    /// contain, if there is one which is bound to a newly created reference
+    fn receive_marker(&self, clauses: &[Clause]) -> Option<Symbol> {
+        let mut marker = None;
+        for clause in clauses.iter() {
+            let guard = clause.guard.as_deref()?;
+            let reference = self.required_reference(clause.patterns.as_slice(), guard)?;
+            match marker {
+                None => marker = Some(reference),
+                Some(marked) if marked == reference => (),
+                Some(_) => return None,
+            }
+        }
+        marker
+    }
+
+    /// Returns the reference variable `guard` requires to be exactly equal to a variable bound by
+    /// `patterns`, as in the guard generated for a pattern using an already bound variable
+    ///
+    /// Guards combine tests by binding each of them to a variable and passing those to `and/2`, so
+    /// any test which is an operand of the final `and/2`, or of an `and/2` bound to one of its
+    /// operands, is required. Protected guard expressions are not looked into.
+    fn required_reference(&self, patterns: &[Expr], guard: &Expr) -> Option<Symbol> {
+        let mut bindings = Vec::new();
+        let mut expr = guard;
+        while let Expr::Let(Let { vars, arg, body, .. }) = expr {
+            if let [var] = vars.as_slice() {
+                bindings.push((var.name(), arg.as_ref()));
+            }
+            expr = body.as_ref();
+        }
+
+        let mut required = vec![expr];
+        while let Some(expr) = required.pop() {
+            let Expr::Call(call) = expr else { continue; };
+            if call.is_static(symbols::Erlang, symbols::And, 2) {
+                for arg in call.args.iter() {
+                    match arg {
+                        Expr::Var(v) => required.extend(
+                            bindings
+                                .iter()
+                                .filter(|(name, _)| *name == v.name())
+                                .map(|(_, bound)| *bound),
+                        ),
+                        arg => required.push(arg),
+                    }
+                }
+            } else if call.is_static(symbols::Erlang, symbols::EqualStrict, 2) {
+                let [Expr::Var(lhs), Expr::Var(rhs)] = call.args.as_slice() else { continue; };
+                let (lhs, rhs) = (lhs.name(), rhs.name());
+                if self.refs.contains(&rhs) && patterns.iter().any(|p| binds(p, lhs)) {
+                    return Some(rhs);
+                }
+                if self.refs.contains(&lhs) && patterns.iter().any(|p| binds(p, rhs)) {
+                    return Some(lhs);
+                }
+            }
+        }
+        None
+    }
+
     fn rewrite_clauses(&mut self, mut clauses: Vec<Clause>) -> Vec<Clause> {
         clauses
             .drain(..)
@@ -1002,3 +1102,22 @@ fn split_letify(mut vs: Vec<Expr>, mut args: Vec<Expr>, body: Box<Expr>) -> Expr
         body,
     })
 }
+
+/// Returns true if `call` always returns a reference which did not exist before the call
+fn creates_reference(call: &Call) -> bool {
+    call.is_static(symbols::Erlang, symbols::MakeRef, 0)
+        || call.is_static(symbols::Erlang, symbols::Monitor, 2)
+        || call.is_static(symbols::Erlang, symbols::Monitor, 3)
+}
+
+/// Returns true if `pattern` binds the variable `name`
+fn binds(pattern: &Expr, name: Symbol) -> bool {
+    match pattern {
+        Expr::Var(v) => v.name() == name,
+        Expr::Alias(alias) => alias.var.name() == name || binds(alias.pattern.as_ref(), name),
+        Expr::Cons(cons) => binds(cons.head.as_ref(), name) || binds(cons.tail.as_ref(), name),
+        Expr::Tuple(tuple) => tuple.elements.iter().any(|e| binds(e, name)),
+        Expr::Values(values) => values.values.iter().any(|v| binds(v, name)),
+        _ => false,
+    }
+}
@@ -411,28 +411,33 @@ impl<'m> ExpandRecordsVisitor<'m> {
         // Generate vars for use in the pattern match phase
         let bound_var = self.next_var(Some(span));
         let catch_all_var = self.next_var(Some(span));
-        // Expand the updates to a sequence of nested setelement calls such that they evaluate in the correct order
-        let expanded_updates = record_update
-            .updates
+        let mut updates = Vec::with_capacity(record_update.updates.len());
+        for update in record_update.updates.iter() {
+            let field_name = update.name;
+            let position = definition
+                .fields
+                .iter()
+                .position(|f| f.name == field_name)
+                .map(ControlFlow::Continue)
+                .unwrap_or_else(|| {
+                    ControlFlow::Break(anyhow!(
+                        "reference to undefined field '{}' of record '{}'",
+                        field_name,
+                        name
+                    ))
+                })?;
+            updates.push((position, update));
+        }
+        // Expand the updates to a sequence of nested setelement calls, with the highest index
+        // innermost, so that the bounds check it performs covers the rest of the chain, allowing
+        // those calls to update the tuple in place
+        updates.sort_by_key(|(position, _)| *position);
+        let expanded_updates = updates
             .iter()
             .rev()
             .try_fold::<_, _, ControlFlow<anyhow::Error, Expr>>(
                 Expr::Var(bound_var.into()),
-                |acc, update| {
-                    let field_name = update.name;
-                    let position = definition
-                        .fields
-                        .iter()
-                        .position(|f| f.name == field_name)
-                        .map(ControlFlow::Continue)
-                        .unwrap_or_else(|| {
-                            ControlFlow::Break(anyhow!(
-                                "reference to undefined field '{}' of record '{}'",
-                                field_name,
-                                name
-                            ))
-                        })?;
-
+                |acc, (position, update)| {
                     let callee = Expr::FunctionVar(FunctionVar::Resolved(Span::new(
                         span,
                         FunctionName::new(symbols::Erlang, symbols::Setelement, 3),
@@ -124,6 +124,9 @@ struct TranslateCore<'p> {
     diagnostics: &'p DiagnosticsHandler,
     context: FunctionContext,
     module_name: Symbol,
+    /// The variable bound by the previous expression in the body being tagged, if it is a tuple
+    /// freshly produced by `setelement/3`, along with a lower bound on its size, see `ubody`
+    fresh_tuple: Option<(Ident, usize)>,
 }
 impl<'p> TranslateCore<'p> {
     fn new(
@@ -135,6 +138,7 @@ impl<'p> TranslateCore<'p> {
             diagnostics,
             context,
             module_name,
+            fresh_tuple: None,
         }
     }
 }
@@ -1663,6 +1667,37 @@ fn call_type(module: &core::Expr, function: &core::Expr, args: &[core::Expr]) ->
     }
 }
 
+/// If `expr` is a call to `setelement/3` with a literal index, returns the size which the
+/// resulting tuple is known to have at least, and whether the call can update its tuple in place
+/// rather than copying it, provided nothing else refers to the tuple.
+///
+/// The latter is the case when the tuple was produced by the previous expression, `fresh_tuple`,
+/// which proves that the index is in bounds, as in the nested calls produced by record updates.
+/// This mirrors the `setelement` chain optimization in BEAM.
+fn setelement_chain(expr: &Expr, fresh_tuple: Option<(Ident, usize)>) -> Option<(bool, usize)> {
+    let Expr::Bif(bif) = expr else { return None; };
+    if bif.op != FunctionName::new(symbols::Erlang, symbols::Setelement, 3) {
+        return None;
+    }
+    let index = match bif.args[0] {
+        Expr::Literal(Literal {
+            value: Lit::Integer(Int::Small(index)),
+            ..
+        }) if index > 0 => index as usize,
+        _ => return None,
+    };
+    match (bif.args[1].as_var(), fresh_tuple) {
+        (Some(tuple), Some((fresh, size)))
+            if tuple.name == fresh
+                && index <= size
+                && bif.args[2].as_var().map(|v| v.name) != Some(fresh) =>
+        {
+            Some((true, size))
+        }
+        _ => Some((false, index)),
+    }
+}
+
 fn same_args(args: &[core::Expr], fargs: &[Var], sub: &BiMap) -> bool {
     if args.len() != fargs.len() {
         return false;
@@ -3023,7 +3058,9 @@ impl<'p> TranslateCore<'p> {
                 body: Some(box body),
                 ..
             }) if vars.is_empty() => {
+                let fresh_tuple = self.fresh_tuple.take();
                 self.iletrec_funs(letr)?;
+                self.fresh_tuple = fresh_tuple;
                 self.ubody(body, brk)
             }
             Expr::Set(ISet {
@@ -3039,10 +3076,24 @@ impl<'p> TranslateCore<'p> {
                 box arg,
                 body: Some(box body),
             }) => {
+                let fresh_tuple = self.fresh_tuple.take();
                 let ns = vars.iter().map(|v| v.name).collect();
                 let vs = vars.iter().cloned().map(Expr::Var).collect();
-                let (arg, au) = self.uexpr(arg, Brk::Break(vs))?;
+                let (mut arg, au) = self.uexpr(arg, Brk::Break(vs))?;
+                let setelement = setelement_chain(&arg, fresh_tuple);
+                self.fresh_tuple = match (setelement, vars.as_slice()) {
+                    (Some((_, size)), [var]) => Some((var.name, size)),
+                    _ => None,
+                };
                 let (body, bu) = self.ubody(body, brk)?;
+                // The tuple updated by a setelement chained to the previous one was freshly
+                // allocated by it, so if nothing else refers to it, it can be updated in place
+                if let (Some((true, _)), Expr::Bif(bif)) = (setelement, &mut arg) {
+                    let tuple = bif.args[1].as_var().unwrap().name;
+                    if !bu.contains(&tuple) {
+                        bif.annotations.set(symbols::InPlace);
+                    }
+                }
                 let used = sets::union(au, sets::subtract(bu, ns)); // used external vars
                 Ok((
                     Expr::Seq(Seq {
@@ -1,5 +1,5 @@
 use std::assert_matches::assert_matches;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use anyhow::anyhow;
@@ -613,6 +613,14 @@ impl<'m> LowerFunctionToSsa<'m> {
                 let tuple = self.ssa_value(builder, args.remove(0))?;
                 self.lower_test_is_record(builder, span, tuple, tag, arity, fail)
             }
+            (symbols::IsMapKey, [_, _]) => {
+                // A missing key and a map argument which is not a map both fail the test, so there
+                // is no need to call the bif
+                let args = self.ssa_values(builder, args)?;
+                let (is_err, _) = builder.ins().map_try_get(args[1], args[0], span);
+                builder.ins().br_if(is_err, fail, &[], span);
+                Ok(())
+            }
             _ if op.is_type_test() => {
                 let arg = self.ssa_value(builder, args.pop().unwrap())?;
                 let result = match op.function {
@@ -921,6 +929,18 @@ impl<'m> LowerFunctionToSsa<'m> {
                 let arity = arity.to_usize().unwrap();
                 self.lower_is_record_bif(builder, bif, tag, arity)
             }
+            (
+                symbols::Setelement,
+                [KExpr::Literal(Literal {
+                    value: Lit::Integer(index),
+                    ..
+                }), _, _],
+            ) if bif.annotations.contains(symbols::InPlace) => {
+                let index = index.to_usize().unwrap();
+                self.lower_setelement_in_place(builder, bif, index)
+            }
+            (symbols::IsMapKey, [_, _]) => self.lower_is_map_key_bif(builder, bif),
+            (symbols::MapGet, [_, _]) => self.lower_map_get_bif(builder, bif),
             _ if bif.op.is_type_test() => self.lower_type_test(builder, bif),
             _ if bif.op.is_safe() => {
                 // This bif can never fail, and has no side effects
@@ -997,6 +1017,112 @@ impl<'m> LowerFunctionToSsa<'m> {
         Ok(())
     }
 
+    /// Lowers a call to `setelement/3` which core_to_kernel has determined can update its tuple
+    /// in place, i.e. the tuple is not referenced elsewhere and the index is known to be in bounds
+    fn lower_setelement_in_place<'a>(
+        &mut self,
+        builder: &'a mut IrBuilder,
+        mut bif: k::Bif,
+        index: usize,
+    ) -> anyhow::Result<()> {
+        let span = bif.span();
+        let value = self.ssa_value(builder, bif.args.pop().unwrap())?;
+        let tuple = self.ssa_value(builder, bif.args.pop().unwrap())?;
+        // Indices of tuple elements in SSA are zero-based
+        let result = builder.ins().set_element_mut(tuple, index - 1, value, span);
+        if let Some(ret) = bif.ret.first() {
+            builder.define_var(ret.as_var().map(|v| v.name()).unwrap(), result);
+        }
+        Ok(())
+    }
+
+    /// Lowers a call to `is_map_key/2` to an inline lookup, only calling the bif when its map
+    /// argument is not a map, so that it raises `badmap`, or fails the guard it is in
+    fn lower_is_map_key_bif<'a>(
+        &mut self,
+        builder: &'a mut IrBuilder,
+        bif: k::Bif,
+    ) -> anyhow::Result<()> {
+        let span = bif.span();
+        let callee = self.module.get_or_register_builtin(bif.op);
+        let args = self.ssa_values(builder, bif.args)?;
+
+        // Construct a flow control structure that goes something like this:
+        //
+        //     $is_err, _ = map_try_get($map, $key)
+        //     if $is_err {
+        //         if is_type($map, map) {
+        //             $0 = false
+        //         } else {
+        //             $0 = call erlang:is_map_key/2($key, $map)
+        //         }
+        //     } else {
+        //         $0 = true
+        //     }
+        //     ...
+        let (is_err, _) = builder.ins().map_try_get(args[1], args[0], span);
+        let missing_block = builder.create_block();
+        let badmap_block = builder.create_block();
+        let final_block = builder.create_block();
+        let result = builder.append_block_param(final_block, Type::Term(TermType::Bool), span);
+        builder.ins().br_if(is_err, missing_block, &[], span);
+        let found = builder.ins().bool(true, span);
+        builder.ins().br(final_block, &[found], span);
+        builder.switch_to_block(missing_block);
+        let is_map = builder
+            .ins()
+            .is_type(Type::Term(TermType::Map), args[1], span);
+        builder.ins().br_unless(is_map, badmap_block, &[], span);
+        let missing = builder.ins().bool(false, span);
+        builder.ins().br(final_block, &[missing], span);
+        builder.switch_to_block(badmap_block);
+        let inst = builder.ins().call(callee, args.as_slice(), span);
+        let badmap = builder.first_result(inst);
+        builder.ins().br(final_block, &[badmap], span);
+        builder.switch_to_block(final_block);
+        if let Some(ret) = bif.ret.first() {
+            builder.define_var(ret.as_var().map(|v| v.name()).unwrap(), result);
+        }
+        Ok(())
+    }
+
+    /// Lowers a call to `map_get/2` to an inline lookup, only calling the bif when the lookup
+    /// fails, so that it raises `badmap` or `{badkey, Key}`, or fails the guard it is in
+    fn lower_map_get_bif<'a>(
+        &mut self,
+        builder: &'a mut IrBuilder,
+        bif: k::Bif,
+    ) -> anyhow::Result<()> {
+        let span = bif.span();
+        let callee = self.module.get_or_register_builtin(bif.op);
+        let args = self.ssa_values(builder, bif.args)?;
+
+        // Construct a flow control structure that goes something like this:
+        //
+        //     $is_err, $value = map_try_get($map, $key)
+        //     if $is_err {
+        //         $0 = call erlang:map_get/2($key, $map)
+        //     } else {
+        //         $0 = $value
+        //     }
+        //     ...
+        let (is_err, value) = builder.ins().map_try_get(args[1], args[0], span);
+        let fail_block = builder.create_block();
+        let final_block = builder.create_block();
+        let result = builder.append_block_param(final_block, Type::Term(TermType::Any), span);
+        builder.ins().br_if(is_err, fail_block, &[], span);
+        builder.ins().br(final_block, &[value], span);
+        builder.switch_to_block(fail_block);
+        let inst = builder.ins().call(callee, args.as_slice(), span);
+        let raised = builder.first_result(inst);
+        builder.ins().br(final_block, &[raised], span);
+        builder.switch_to_block(final_block);
+        if let Some(ret) = bif.ret.first() {
+            builder.define_var(ret.as_var().map(|v| v.name()).unwrap(), result);
+        }
+        Ok(())
+    }
+
     fn lower_type_test<'a>(
         &mut self,
         builder: &'a mut IrBuilder,
@@ -1765,6 +1891,9 @@ impl<'m> LowerFunctionToSsa<'m> {
                 Ok(tup)
             }
             Lit::Map(mut lmap) => {
+                if let Some(pairs) = constant_map(&lmap) {
+                    return Ok(builder.ins().map_literal(pairs, span));
+                }
                 let map = builder.ins().map(lmap.len(), span);
                 while let Some((k, v)) = lmap.pop_first() {
                     let k = self.lower_literal(builder, k)?;
@@ -1823,6 +1952,34 @@ impl<'m> LowerFunctionToSsa<'m> {
     }
 }
 
+/// The largest map which can be placed in the constant pool, this corresponds to the largest
+/// flatmap supported by the runtime
+const MAX_CONSTANT_MAP_SIZE: usize = 64;
+
+/// Returns the key/value pairs of `map` as constants, if it is small enough to be a flatmap, and
+/// every key and value in it is an atom, integer, float or binary
+///
+/// Such maps are placed in the constant pool and built once when the module is loaded, rather than
+/// every time the expression is evaluated.
+fn constant_map(map: &BTreeMap<Literal, Literal>) -> Option<Vec<(ConstantItem, ConstantItem)>> {
+    if map.is_empty() || map.len() > MAX_CONSTANT_MAP_SIZE {
+        return None;
+    }
+    map.iter()
+        .map(|(k, v)| Some((constant_item(k)?, constant_item(v)?)))
+        .collect()
+}
+
+fn constant_item(literal: &Literal) -> Option<ConstantItem> {
+    match literal.value {
+        Lit::Atom(value) => Some(ConstantItem::Atom(value)),
+        Lit::Integer(Int::Small(value)) => Some(ConstantItem::Integer(Int::Small(value))),
+        Lit::Float(value) => Some(ConstantItem::Float(value.inner())),
+        Lit::Binary(ref value) => Some(ConstantItem::Bitstring(value.clone())),
+        _ => None,
+    }
+}
+
 // Select
 impl<'m> LowerFunctionToSsa<'m> {
     fn select_binary<'a>(
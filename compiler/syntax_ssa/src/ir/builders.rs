@@ -354,6 +354,20 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
         dfg.first_result(inst)
     }
 
+    fn map_literal(mut self, pairs: Vec<(ConstantItem, ConstantItem)>, span: SourceSpan) -> Value {
+        let constant = {
+            self.data_flow_graph_mut()
+                .make_constant(ConstantItem::Map(pairs))
+        };
+        let (inst, dfg) = self.UnaryConst(
+            Opcode::ConstMap,
+            Type::Term(TermType::Map),
+            constant,
+            span,
+        );
+        dfg.first_result(inst)
+    }
+
     fn is_null(self, arg: Value, span: SourceSpan) -> Value {
         let (inst, dfg) = self.Unary(
             Opcode::IsNull,
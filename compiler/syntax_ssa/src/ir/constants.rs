@@ -495,6 +495,8 @@ pub enum ConstantItem {
     Bitstring(BitVec),
     String(String),
     InternedStr(Symbol),
+    /// A map whose keys and values are all constants, stored as its key/value pairs
+    Map(Vec<(ConstantItem, ConstantItem)>),
 }
 impl Eq for ConstantItem {}
 impl PartialEq for ConstantItem {
@@ -536,6 +538,8 @@ impl PartialEq for ConstantItem {
                 Self::InternedStr(y) => x.eq(y),
                 _ => false,
             },
+            (Self::Map(x), Self::Map(y)) => x.eq(y),
+            (Self::Map(_), _) => false,
         }
     }
 }
@@ -555,6 +559,7 @@ impl Hash for ConstantItem {
             Self::Bitstring(b) => b.hash(state),
             Self::String(b) => b.as_bytes().hash(state),
             Self::InternedStr(b) => b.as_str().get().as_bytes().hash(state),
+            Self::Map(pairs) => pairs.hash(state),
         }
     }
 }
@@ -568,6 +573,7 @@ impl ConstantItem {
             Self::Bitstring(_) | Self::Bytes(_) | Self::String(_) | Self::InternedStr(_) => {
                 Type::Term(TermType::Bitstring)
             }
+            Self::Map(_) => Type::Term(TermType::Map),
         }
     }
 
@@ -582,6 +588,10 @@ impl ConstantItem {
             Self::Bitstring(b) => b.byte_size(),
             Self::String(b) => b.as_bytes().len(),
             Self::InternedStr(b) => b.as_str().get().as_bytes().len(),
+            Self::Map(pairs) => pairs
+                .iter()
+                .map(|(k, v)| k.byte_size() + v.byte_size())
+                .sum(),
         }
     }
 }
@@ -619,6 +629,16 @@ impl fmt::Display for ConstantItem {
                 }
                 write!(f, "\"")
             }
+            Self::Map(pairs) => {
+                write!(f, "#{{")?;
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} => {}", k, v)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
@@ -223,7 +223,8 @@ impl DataFlowGraph {
                 | Opcode::ImmNone
                 | Opcode::ImmNull
                 | Opcode::ConstBigInt
-                | Opcode::ConstBinary => {
+                | Opcode::ConstBinary
+                | Opcode::ConstMap => {
                     self.append_result(inst, ty);
                     1
                 }
@@ -245,6 +245,7 @@ pub enum Opcode {
     ImmNull,
     ConstBigInt,
     ConstBinary,
+    ConstMap,
     IsNull,
     Cast,
     Trunc,
@@ -383,7 +384,8 @@ impl Opcode {
             | Self::ImmNone
             | Self::ImmNull
             | Self::ConstBigInt
-            | Self::ConstBinary => 0,
+            | Self::ConstBinary
+            | Self::ConstMap => 0,
             // Binary ops always have two
             Self::Add
             | Self::Sub
@@ -504,6 +506,7 @@ impl fmt::Display for Opcode {
             Self::ImmNull => f.write_str("null"),
             Self::ConstBigInt => f.write_str("const.bigint"),
             Self::ConstBinary => f.write_str("const.binary"),
+            Self::ConstMap => f.write_str("const.map"),
             Self::IsNull => f.write_str("is_null"),
             Self::Cast => f.write_str("cast"),
             Self::Trunc => f.write_str("trunc"),
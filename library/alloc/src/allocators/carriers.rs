@@ -0,0 +1,301 @@
+//! Large allocations, i.e. those of at least the huge page size, can be placed in carriers which
+//! are mapped directly from the operating system rather than obtained from malloc, so that they
+//! can be backed by huge pages. For processes with large heaps, this significantly reduces TLB
+//! pressure.
+//!
+//! Optionally, a super carrier of a fixed size can be reserved up front (see `+MMscs` in ERTS),
+//! from which large carriers are carved out, falling back to mapping new carriers once it is
+//! exhausted. Reserving it at startup makes it much more likely that explicit huge pages are
+//! available, as they tend to become fragmented over the lifetime of the system.
+use alloc::alloc::{AllocError, Allocator, Layout};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ptr::{self, NonNull};
+
+use firefly_system::alloc::PageKind;
+use firefly_system::sync::{const_mutex, Mutex, OnceLock};
+
+use super::System;
+use crate::mmap;
+
+/// Whether, and how, large carriers are backed by huge pages
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum HugePages {
+    /// Large allocations are made using the system allocator, unless a super carrier is used
+    #[default]
+    Off,
+    /// Large carriers are mapped such that they are eligible for transparent huge pages
+    Transparent,
+    /// Large carriers use explicitly reserved huge pages, falling back to transparent huge pages
+    /// when none are available
+    Explicit,
+}
+
+/// The configuration for large carriers, see [`init`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CarrierConfig {
+    pub huge_pages: HugePages,
+    /// The size in bytes of the super carrier to reserve, or zero if none should be used
+    pub super_carrier_size: usize,
+}
+
+/// Statistics about the large carriers currently allocated, see [`stats`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CarrierStats {
+    pub huge_pages: HugePages,
+    /// The number of large carriers
+    pub carriers: usize,
+    /// The number of bytes of large carriers backed by explicitly reserved huge pages
+    pub huge: usize,
+    /// The number of bytes of large carriers eligible for transparent huge pages
+    pub transparent_huge: usize,
+    /// The number of bytes of large carriers backed by regular pages
+    pub normal: usize,
+    /// The size of the super carrier in bytes, zero if none is in use
+    pub super_carrier_size: usize,
+    /// The number of bytes of the super carrier in use by large carriers
+    pub super_carrier_used: usize,
+}
+
+struct Config {
+    huge_pages: HugePages,
+    super_carrier: Option<SuperCarrier>,
+}
+impl Config {
+    #[inline]
+    fn is_enabled(&self) -> bool {
+        self.huge_pages != HugePages::Off || self.super_carrier.is_some()
+    }
+}
+
+/// A large carrier which is currently allocated
+struct Carrier {
+    /// The size of the underlying mapping, which may be larger than requested
+    size: usize,
+    kind: PageKind,
+    /// True if this carrier was carved out of the super carrier
+    in_super_carrier: bool,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+/// All large carriers currently allocated, keyed by address
+static CARRIERS: Mutex<BTreeMap<usize, Carrier>> = const_mutex(BTreeMap::new());
+
+/// Configures how large carriers are allocated.
+///
+/// This must be done at startup, before any large allocations are made via [`LargeCarriers`],
+/// otherwise the default configuration, which disables large carriers, is locked in and
+/// `Err` is returned.
+///
+/// If the super carrier cannot be reserved, large carriers are still configured, but without a
+/// super carrier, and `Ok(false)` is returned.
+pub fn init(config: CarrierConfig) -> Result<bool, CarrierConfig> {
+    if CONFIG.get().is_some() {
+        return Err(config);
+    }
+
+    let super_carrier = if config.super_carrier_size > 0 {
+        SuperCarrier::reserve(config.super_carrier_size, config.huge_pages)
+    } else {
+        None
+    };
+    let reserved = config.super_carrier_size == 0 || super_carrier.is_some();
+
+    CONFIG
+        .set(Config {
+            huge_pages: config.huge_pages,
+            super_carrier,
+        })
+        .map_err(|_| config)?;
+    Ok(reserved)
+}
+
+/// Returns statistics about the large carriers currently allocated
+pub fn stats() -> CarrierStats {
+    let config = config();
+    let mut stats = CarrierStats {
+        huge_pages: config.huge_pages,
+        super_carrier_size: config.super_carrier.as_ref().map(|sc| sc.size).unwrap_or(0),
+        ..CarrierStats::default()
+    };
+    for carrier in CARRIERS.lock().values() {
+        stats.carriers += 1;
+        match carrier.kind {
+            PageKind::Huge => stats.huge += carrier.size,
+            PageKind::TransparentHuge => stats.transparent_huge += carrier.size,
+            PageKind::Normal => stats.normal += carrier.size,
+        }
+        if carrier.in_super_carrier {
+            stats.super_carrier_used += carrier.size;
+        }
+    }
+    stats
+}
+
+/// Touches every page of the super carrier which is not in use, so that carriers carved out of it
+/// later don't stall on page faults, returning the number of bytes touched
+///
+/// This is meant to be called at startup, by services which would rather pay for faulting in the
+/// super carrier up front than while serving their first requests.
+pub fn prefault_super_carrier() -> usize {
+    config()
+        .super_carrier
+        .as_ref()
+        .map(|sc| sc.prefault())
+        .unwrap_or(0)
+}
+
+#[inline]
+fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config {
+        huge_pages: HugePages::Off,
+        super_carrier: None,
+    })
+}
+
+/// An allocator which places allocations of at least the huge page size in large carriers, as
+/// configured by [`init`], and delegates all other allocations to [`System`].
+#[derive(Debug, Copy, Clone)]
+pub struct LargeCarriers;
+unsafe impl Sync for LargeCarriers {}
+unsafe impl Send for LargeCarriers {}
+
+unsafe impl Allocator for LargeCarriers {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let config = config();
+        if !config.is_enabled() || layout.size() < firefly_system::mem::huge_page_size() {
+            return System.allocate(layout);
+        }
+
+        let (ptr, carrier) = match config
+            .super_carrier
+            .as_ref()
+            .and_then(|sc| sc.allocate(layout))
+        {
+            Some(allocated) => allocated,
+            None if config.huge_pages == HugePages::Off => return System.allocate(layout),
+            None => {
+                let explicit = config.huge_pages == HugePages::Explicit;
+                let (ptr, size, kind) = unsafe { mmap::map_huge(layout, explicit)? };
+                let carrier = Carrier {
+                    size,
+                    kind,
+                    in_super_carrier: false,
+                };
+                (ptr, carrier)
+            }
+        };
+        CARRIERS.lock().insert(ptr.as_ptr() as usize, carrier);
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let config = config();
+        if config.is_enabled() {
+            let carrier = CARRIERS.lock().remove(&(ptr.as_ptr() as usize));
+            if let Some(carrier) = carrier {
+                if carrier.in_super_carrier {
+                    config
+                        .super_carrier
+                        .as_ref()
+                        .unwrap()
+                        .deallocate(ptr, carrier.size);
+                } else {
+                    let layout = Layout::from_size_align_unchecked(carrier.size, layout.align());
+                    mmap::unmap(ptr.as_ptr(), layout);
+                }
+                return;
+            }
+        }
+        System.deallocate(ptr, layout)
+    }
+}
+
+/// A region of memory reserved at startup, which is divided into chunks of the huge page size
+/// from which large carriers are allocated first-fit.
+struct SuperCarrier {
+    base: usize,
+    size: usize,
+    chunk_size: usize,
+    kind: PageKind,
+    /// Whether or not each chunk is in use
+    chunks: Mutex<Vec<bool>>,
+}
+impl SuperCarrier {
+    fn reserve(size: usize, huge_pages: HugePages) -> Option<Self> {
+        let chunk_size = firefly_system::mem::huge_page_size();
+        let layout = Layout::from_size_align(size, chunk_size)
+            .ok()?
+            .pad_to_align();
+        let (ptr, size, kind) = match huge_pages {
+            HugePages::Off => unsafe {
+                let ptr = mmap::map(layout).ok()?;
+                (ptr, layout.size(), PageKind::Normal)
+            },
+            _ => unsafe { mmap::map_huge(layout, huge_pages == HugePages::Explicit).ok()? },
+        };
+        Some(Self {
+            base: ptr.as_ptr() as usize,
+            size,
+            chunk_size,
+            kind,
+            chunks: Mutex::new(vec![false; size / chunk_size]),
+        })
+    }
+
+    fn allocate(&self, layout: Layout) -> Option<(NonNull<u8>, Carrier)> {
+        if layout.align() > self.chunk_size {
+            return None;
+        }
+        let needed = (layout.size() + self.chunk_size - 1) / self.chunk_size;
+        let mut chunks = self.chunks.lock();
+        let mut start = 0;
+        let mut free = 0;
+        for i in 0..chunks.len() {
+            if chunks[i] {
+                start = i + 1;
+                free = 0;
+                continue;
+            }
+            free += 1;
+            if free == needed {
+                chunks[start..(start + needed)].fill(true);
+                let ptr = (self.base + start * self.chunk_size) as *mut u8;
+                let carrier = Carrier {
+                    size: needed * self.chunk_size,
+                    kind: self.kind,
+                    in_super_carrier: true,
+                };
+                return Some((unsafe { NonNull::new_unchecked(ptr) }, carrier));
+            }
+        }
+        None
+    }
+
+    fn prefault(&self) -> usize {
+        let page_size = match self.kind {
+            PageKind::Huge => self.chunk_size,
+            _ => firefly_system::mem::page_size(),
+        };
+        // Holding the lock ensures that no chunk is allocated, and written to, while touched
+        let chunks = self.chunks.lock();
+        let mut touched = 0;
+        for (i, _) in chunks.iter().enumerate().filter(|(_, used)| !**used) {
+            let chunk = (self.base + i * self.chunk_size) as *mut u8;
+            for offset in (0..self.chunk_size).step_by(page_size) {
+                unsafe {
+                    ptr::write_volatile(chunk.add(offset), 0);
+                }
+            }
+            touched += self.chunk_size;
+        }
+        touched
+    }
+
+    fn deallocate(&self, ptr: NonNull<u8>, size: usize) {
+        let start = (ptr.as_ptr() as usize - self.base) / self.chunk_size;
+        let len = size / self.chunk_size;
+        self.chunks.lock()[start..(start + len)].fill(false);
+    }
+}
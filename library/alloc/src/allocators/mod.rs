@@ -1,3 +1,5 @@
+pub mod carriers;
 mod system;
 
+pub use self::carriers::LargeCarriers;
 pub use self::system::System;
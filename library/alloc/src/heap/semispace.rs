@@ -58,6 +58,18 @@ where
     pub fn new(immature: A, mature: B) -> Self {
         Self { immature, mature }
     }
+
+    /// Returns a reference to the immature (young) heap
+    #[inline]
+    pub fn immature(&self) -> &A {
+        &self.immature
+    }
+
+    /// Returns a reference to the mature (old) heap
+    #[inline]
+    pub fn mature(&self) -> &B {
+        &self.mature
+    }
 }
 unsafe impl<A, B> Allocator for SemispaceHeap<A, B>
 where
@@ -4,6 +4,7 @@ mod fallback {
     use alloc::alloc::{AllocError, Layout};
     use core::ptr::NonNull;
 
+    use firefly_system::alloc::PageKind;
     use firefly_system::arch as sys;
 
     /// Creates a memory mapping for the given `Layout`
@@ -12,6 +13,18 @@ mod fallback {
         sys::alloc::allocate(layout).map(|ptr| ptr.cast())
     }
 
+    /// Creates a memory mapping for the given `Layout`, preferring huge pages
+    ///
+    /// NOTE: This is a fallback implementation, so huge pages are never used, and the mapping has
+    /// exactly the size of `layout`
+    #[inline]
+    pub unsafe fn map_huge(
+        layout: Layout,
+        _explicit: bool,
+    ) -> Result<(NonNull<u8>, usize, PageKind), AllocError> {
+        sys::alloc::allocate(layout).map(|ptr| (ptr.cast(), layout.size(), PageKind::Normal))
+    }
+
     /// Creates a memory mapping specifically set up to behave like a stack
     ///
     /// NOTE: This is a fallback implementation, so no guard page is present,
@@ -59,6 +72,7 @@ mod real {
     use alloc::alloc::{AllocError, Layout};
     use core::ptr::NonNull;
 
+    use firefly_system::alloc::PageKind;
     use firefly_system::arch as sys;
 
     /// Creates a memory mapping for the given `Layout`
@@ -67,6 +81,18 @@ mod real {
         sys::mmap::map(layout).map(|(ptr, _)| ptr)
     }
 
+    /// Creates a memory mapping for the given `Layout`, preferring huge pages
+    ///
+    /// Returns the actual size of the mapping, which must be used when unmapping it, along with
+    /// the kind of pages backing it. See `firefly_system::arch::mmap::map_huge`.
+    #[inline]
+    pub unsafe fn map_huge(
+        layout: Layout,
+        explicit: bool,
+    ) -> Result<(NonNull<u8>, usize, PageKind), AllocError> {
+        sys::mmap::map_huge(layout, explicit)
+    }
+
     /// Creates a memory mapping specifically set up to behave like a stack
     #[inline]
     pub unsafe fn map_stack(pages: usize) -> Result<NonNull<u8>, AllocError> {
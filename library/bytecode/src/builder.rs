@@ -1015,6 +1015,14 @@ where
         dest
     }
 
+    /// Loads a map literal, given as the external term format encoding of the map
+    pub fn build_map_literal(&mut self, encoded: &[u8], loc: Option<LocationId>) -> Register {
+        let dest = self.alloc_register();
+        let bin = self.insert_binary(encoded, Encoding::Raw);
+        self.push(Opcode::LoadMap(LoadMap { dest, value: bin }), loc);
+        dest
+    }
+
     pub fn build_not(&mut self, value: Register, loc: Option<LocationId>) -> Register {
         let dest = self.alloc_register();
         self.push(Opcode::Not(Not { dest, cond: value }), loc);
@@ -440,7 +440,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
     /// Validation ensures that no function definition conflicts with those defined in other modules
     pub fn link(&mut self, mut other: Vec<Self>) -> Result<(), InvalidBytecodeError<A>> {
         use self::ops::{Call, CallNative, CallStatic, Enter, EnterNative, EnterStatic};
-        use self::ops::{Closure, FuncInfo, LoadAtom, LoadBinary, LoadBitstring};
+        use self::ops::{Closure, FuncInfo, LoadAtom, LoadBinary, LoadBitstring, LoadMap};
 
         struct ModuleMap {
             range: core::ops::Range<usize>,
@@ -642,7 +642,8 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                     *value = new_value;
                 }
                 Opcode::LoadBinary(LoadBinary { ref mut value, .. })
-                | Opcode::LoadBitstring(LoadBitstring { ref mut value, .. }) => {
+                | Opcode::LoadBitstring(LoadBitstring { ref mut value, .. })
+                | Opcode::LoadMap(LoadMap { ref mut value, .. }) => {
                     let bin = unsafe { &**value };
                     let new_bin = self
                         .binaries
@@ -60,6 +60,7 @@ pub enum Opcode<A: Atom> {
     LoadFloat(LoadFloat),
     LoadBinary(LoadBinary),
     LoadBitstring(LoadBitstring),
+    LoadMap(LoadMap),
     Not(Not),
     And(And),
     AndAlso(AndAlso),
@@ -1207,6 +1208,18 @@ pub struct LoadBitstring {
 }
 encoding_impl!(LoadBitstring, dest, value);
 
+/// Loads a map literal into `dest`
+///
+/// The map is stored in the binary table in external term format, so that it is independent of
+/// the term representation of the runtime. Implementations are expected to decode each map once,
+/// when the code is loaded, into a literal area shared by all processes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LoadMap {
+    pub dest: Register,
+    pub value: *const BinaryData,
+}
+encoding_impl!(LoadMap, dest, value);
+
 /// Inverts the boolean value in `cond` and place it in `dest`
 ///
 /// NOTE: It is not guaranteed that `cond` is a boolean, implementations must validate this and
@@ -2353,6 +2366,7 @@ impl<A: Atom> Opcode<A> {
             Self::Spawn3(_) => 123,
             Self::Spawn3Indirect(_) => 124,
             Self::Trap(_) => 125,
+            Self::LoadMap(_) => 126,
         }
     }
 }
@@ -2486,6 +2500,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> Decode<A, T> for Opcode<A> {
             123 => Ok(Self::Spawn3(Decode::decode(reader)?)),
             124 => Ok(Self::Spawn3Indirect(Decode::decode(reader)?)),
             125 => Ok(Self::Trap(Trap)),
+            126 => Ok(Self::LoadMap(Decode::decode(reader)?)),
             _ => Err(ReadError::Invalid),
         }
     }
@@ -2622,6 +2637,7 @@ impl<A: Atom> Encode<A> for Opcode<A> {
             Self::Spawn3(op) => op.encode(writer),
             Self::Spawn3Indirect(op) => op.encode(writer),
             Self::Trap(op) => op.encode(writer),
+            Self::LoadMap(op) => op.encode(writer),
         }
     }
 }
@@ -42,6 +42,25 @@ macro_rules! assert_opcode_match {
                     }
                 }
             }
+            (
+                Opcode::LoadMap(LoadMap {
+                    dest: dest1,
+                    value: value1,
+                }),
+                Opcode::LoadMap(LoadMap {
+                    dest: dest2,
+                    value: value2,
+                }),
+            ) => {
+                if core::ptr::eq(*value1, *value2) {
+                    assert_eq!($lhs, $rhs)
+                } else {
+                    unsafe {
+                        assert_eq!(&**value1, &**value2, "mismatched map literals");
+                        assert_eq!(dest1, dest2);
+                    }
+                }
+            }
             (lhs, rhs) => assert_eq!(lhs, rhs),
         }
     };
@@ -151,6 +170,42 @@ fn bytecode_integration_test() {
     }
 }
 
+#[test]
+fn bytecode_map_literal_encoding_test() {
+    // #{a => 1} in external term format
+    const MAP: &[u8] = &[131, 116, 0, 0, 0, 1, 119, 1, b'a', 97, 1];
+
+    let mut builder = Builder::new(StandardByteCode::new());
+    let test_map_0 = ModuleFunctionArity {
+        module: builder.insert_atom("test"),
+        function: builder.insert_atom("map"),
+        arity: 0,
+    };
+    let mut function = builder.build_function(test_map_0, None).unwrap();
+    let entry = function.create_block(0);
+    function.switch_to_block(entry);
+    let map = function.build_map_literal(MAP, None);
+    function.build_ret(map, None);
+    function.finish();
+    let code = builder.finish();
+
+    let mut buffer = Vec::new();
+    let writer = BytecodeWriter::new(&mut buffer);
+    writer.write(&code).unwrap();
+    let reader = BytecodeReader::new(buffer.as_slice());
+    let code2: ByteCode<AtomicStr, LocalAtomTable> = reader.read().unwrap();
+
+    let load_map = code2
+        .code
+        .iter()
+        .find_map(|op| match op {
+            Opcode::LoadMap(op) => Some(op),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(unsafe { (*load_map.value).as_bytes() }, MAP);
+}
+
 fn generate_code() -> ByteCode<AtomicStr, LocalAtomTable> {
     let mut builder = Builder::new(ByteCode::new());
     let test_main_1 = ModuleFunctionArity {
@@ -209,6 +209,9 @@ where
                 &*op.value
             }))
         }
+        Opcode::LoadMap(op) => {
+            w.write_fmt(format_args!("load_map ${}, {}", op.dest, unsafe { &*op.value }))
+        }
         Opcode::Not(op) => w.write_fmt(format_args!("not ${}, ${}", op.dest, op.cond)),
         Opcode::And(op) => w.write_fmt(format_args!("and ${}, ${}, ${}", op.dest, op.lhs, op.rhs)),
         Opcode::AndAlso(op) => w.write_fmt(format_args!(
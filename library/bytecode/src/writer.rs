@@ -163,7 +163,7 @@ impl<'a, A: Atom> BytecodeWriter<'a, A> {
     where
         T: AtomTable<Atom = A>,
     {
-        use super::ops::{LoadAtom, LoadBinary, LoadBitstring};
+        use super::ops::{LoadAtom, LoadBinary, LoadBitstring, LoadMap};
 
         // Instruction encoding is delegated to Opcode
         for op in code.code.iter() {
@@ -186,6 +186,12 @@ impl<'a, A: Atom> BytecodeWriter<'a, A> {
                     let op = Opcode::LoadBitstring(LoadBitstring { dest: *dest, value });
                     op.encode(self)?;
                 }
+                Opcode::LoadMap(LoadMap { dest, value }) => {
+                    let offset = self.binary_offsets[value];
+                    let value = ptr::from_raw_parts(offset as *const (), ptr::metadata(*value));
+                    let op = Opcode::LoadMap(LoadMap { dest: *dest, value });
+                    op.encode(self)?;
+                }
                 op => {
                     op.encode(self)?;
                 }
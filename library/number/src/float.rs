@@ -112,13 +112,50 @@ impl Float {
 impl FromStr for Float {
     type Err = ParseFloatError;
 
+    /// Parses a float using the syntax accepted by `list_to_float/1`, i.e. an optional sign, at
+    /// least one digit on both sides of the decimal point, and an optional exponent
+    ///
+    /// The result is correctly rounded, as it is in OTP, and does not depend on the locale. Like
+    /// OTP, values too large to be represented are rejected, as are non-zero values so small that
+    /// they would round to zero, while values which can only be represented as subnormals are not.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !is_float_syntax(s) {
+            return Err(ParseFloatError::ParseFailed);
+        }
         match s.parse::<f64>() {
+            Ok(f) if f == 0.0 && has_nonzero_mantissa(s) => Err(ParseFloatError::ParseFailed),
             Ok(f) => Self::new(f).map_err(ParseFloatError::Invalid),
             Err(_) => Err(ParseFloatError::ParseFailed),
         }
     }
 }
+fn is_float_syntax(s: &str) -> bool {
+    fn digits(s: &str) -> (&str, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s.split_at(end)
+    }
+
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let (int, rest) = digits(s);
+    let Some(rest) = rest.strip_prefix('.') else { return false; };
+    let (frac, rest) = digits(rest);
+    if int.is_empty() || frac.is_empty() {
+        return false;
+    }
+    match rest.strip_prefix(['e', 'E']) {
+        None => rest.is_empty(),
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            let (exponent, rest) = digits(exponent);
+            !exponent.is_empty() && rest.is_empty()
+        }
+    }
+}
+fn has_nonzero_mantissa(s: &str) -> bool {
+    s.bytes()
+        .take_while(|b| !matches!(b, b'e' | b'E'))
+        .any(|b| matches!(b, b'1'..=b'9'))
+}
 impl fmt::Debug for Float {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(&self.0, f)
@@ -510,3 +547,107 @@ impl Rem<&Int> for Float {
         self % rhs.to_efloat().map_err(|_| DivisionError)?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::String;
+
+    use proptest::num::f64 as any_f64;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::FloatFormat;
+
+    /// The grammar of `list_to_float/1` as a state machine, for comparison with `is_float_syntax`
+    fn reference_syntax(s: &str) -> bool {
+        #[derive(Copy, Clone, PartialEq)]
+        enum State {
+            Start,
+            Sign,
+            Int,
+            Point,
+            Frac,
+            Exp,
+            ExpSign,
+            ExpDigits,
+        }
+
+        let mut state = State::Start;
+        for b in s.bytes() {
+            state = match (state, b) {
+                (State::Start, b'+' | b'-') => State::Sign,
+                (State::Start | State::Sign | State::Int, b'0'..=b'9') => State::Int,
+                (State::Int, b'.') => State::Point,
+                (State::Point | State::Frac, b'0'..=b'9') => State::Frac,
+                (State::Frac, b'e' | b'E') => State::Exp,
+                (State::Exp, b'+' | b'-') => State::ExpSign,
+                (State::Exp | State::ExpSign | State::ExpDigits, b'0'..=b'9') => State::ExpDigits,
+                _ => return false,
+            };
+        }
+        state == State::Frac || state == State::ExpDigits
+    }
+
+    /// The result OTP produces for `s`, using the standard library as the reference parser
+    fn reference_parse(s: &str) -> Option<f64> {
+        if !reference_syntax(s) {
+            return None;
+        }
+        let f = s.parse::<f64>().ok()?;
+        let mantissa = s.split(['e', 'E']).next().unwrap();
+        let zero = mantissa.bytes().all(|b| !(b'1'..=b'9').contains(&b));
+        if !f.is_finite() || (f == 0.0 && !zero) {
+            None
+        } else {
+            Some(f)
+        }
+    }
+
+    fn float_literal() -> impl Strategy<Value = String> {
+        (
+            "[+-]?",
+            "[0-9]{1,20}",
+            "[0-9]{1,20}",
+            proptest::option::of(("[eE]", "[+-]?", 0u32..400)),
+        )
+            .prop_map(|(sign, int, frac, exp)| match exp {
+                None => format!("{}{}.{}", sign, int, frac),
+                Some((e, exp_sign, exp)) => {
+                    format!("{}{}.{}{}{}{}", sign, int, frac, e, exp_sign, exp)
+                }
+            })
+    }
+
+    #[test]
+    fn float_parse_range_test() {
+        assert_eq!("4.9e-324".parse::<Float>().unwrap().inner(), 5.0e-324);
+        assert_eq!("0.0e-400".parse::<Float>().unwrap().inner(), 0.0);
+        assert!("1.0e-400".parse::<Float>().is_err());
+        assert!("-1.0e309".parse::<Float>().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn float_parse_matches_reference(s in "[0-9.eE+\\-_ a]{0,12}") {
+            let parsed = s.parse::<Float>().ok().map(|f| f.inner());
+            prop_assert_eq!(parsed, reference_parse(&s));
+        }
+
+        #[test]
+        fn float_parse_literal_matches_reference(s in float_literal()) {
+            let parsed = s.parse::<Float>().ok().map(|f| f.inner());
+            prop_assert_eq!(parsed, reference_parse(&s));
+        }
+
+        #[test]
+        fn float_format_round_trips(f in any_f64::NORMAL | any_f64::SUBNORMAL | any_f64::ZERO) {
+            let float = Float::new(f).unwrap();
+            for format in [FloatFormat::Short, FloatFormat::Scientific(17)] {
+                let s = float.format(format).unwrap();
+                prop_assert!(reference_syntax(&s), "{}", s);
+                prop_assert_eq!(s.parse::<Float>().unwrap().raw(), float.raw());
+            }
+        }
+    }
+}
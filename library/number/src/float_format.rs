@@ -0,0 +1,233 @@
+//! Formatting of floats the way `float_to_list/2` and `float_to_binary/2` do it.
+//!
+//! The output of each format matches OTP byte-for-byte, including where OTP's choice of rounding
+//! differs from the correctly rounded result, see [`FloatFormat::Decimals`].
+use alloc::format;
+use alloc::string::String;
+
+use crate::Float;
+
+/// OTP formats floats into a fixed-size buffer, and raises `badarg` if the result does not fit
+const MAX_LEN: usize = 256;
+
+/// The largest float below which every integer is exactly representable, i.e. 2^53
+const MAX_EXACT: f64 = 9007199254740992.0;
+
+/// The formats supported by `float_to_list/2` and `float_to_binary/2`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// `[short]`, the fewest digits which still parse back to the same float
+    ///
+    /// This is also how floats are printed by `~p` and `~w`.
+    Short,
+    /// `[{decimals, Digits}]`, with `compact` also given if `compact` is true
+    ///
+    /// Like OTP, when the float scaled by `10^digits` is small enough to be represented exactly,
+    /// it is rounded half up, rather than using the correctly rounded result.
+    Decimals { digits: u8, compact: bool },
+    /// `[{scientific, Digits}]`
+    Scientific(u8),
+}
+impl FloatFormat {
+    /// The largest number of digits accepted for [`FloatFormat::Decimals`]
+    pub const MAX_DECIMALS: u8 = 253;
+    /// The largest number of digits accepted for [`FloatFormat::Scientific`]
+    pub const MAX_SCIENTIFIC: u8 = 249;
+}
+impl Default for FloatFormat {
+    /// The format used by `float_to_list/1` and `float_to_binary/1`
+    fn default() -> Self {
+        Self::Scientific(20)
+    }
+}
+
+impl Float {
+    /// Formats this float using `format`
+    ///
+    /// Returns `None` if the result is too large for OTP to format, in which case it raises
+    /// `badarg`. This can only happen with [`FloatFormat::Decimals`].
+    pub fn format(&self, format: FloatFormat) -> Option<String> {
+        let f = self.inner();
+        let s = match format {
+            FloatFormat::Short => short(f),
+            FloatFormat::Decimals { digits, compact } => decimals(f, digits as usize, compact),
+            FloatFormat::Scientific(digits) => scientific(f, digits as usize),
+        };
+        if s.len() < MAX_LEN {
+            Some(s)
+        } else {
+            None
+        }
+    }
+}
+
+fn short(f: f64) -> String {
+    let sign = if f.is_sign_negative() { "-" } else { "" };
+    let abs = abs(f);
+    // The `LowerExp` implementation produces the shortest digits which round-trip, as `d.ddde-x`
+    let exp = format!("{:e}", abs);
+    let (mantissa, exponent) = exp.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let (first, rest) = mantissa.split_at(1);
+    let rest = rest.strip_prefix('.').unwrap_or("0");
+    let scientific = format!("{}{}.{}e{}", sign, first, rest, exponent);
+    // Floats which cannot be represented exactly as integers are always printed in scientific
+    // notation, otherwise the shorter notation is used
+    if abs >= MAX_EXACT {
+        return scientific;
+    }
+
+    let digits = format!("{}{}", first, rest.trim_end_matches('0'));
+    let decimal = if exponent < 0 {
+        let zeros = (-exponent - 1) as usize;
+        format!(
+            "{}0.{:0>width$}",
+            sign,
+            digits,
+            width = zeros + digits.len()
+        )
+    } else {
+        let point = exponent as usize + 1;
+        if digits.len() > point {
+            format!("{}{}.{}", sign, &digits[..point], &digits[point..])
+        } else {
+            format!("{}{:0<width$}.0", sign, digits, width = point)
+        }
+    };
+    if decimal.len() <= scientific.len() {
+        decimal
+    } else {
+        scientific
+    }
+}
+
+fn decimals(f: f64, digits: usize, compact: bool) -> String {
+    let mut s = decimals_exact_or_fast(f, digits);
+    if compact && digits > 0 {
+        let trimmed = s.trim_end_matches('0').len();
+        // Keep at least one digit after the decimal point
+        let point = s.find('.').unwrap();
+        s.truncate(trimmed.max(point + 2));
+    }
+    s
+}
+
+/// Like `sys_double_to_chars_fast` in ERTS, rounds `f * 10^digits` half up when that can be done
+/// exactly, and falls back to the correctly rounded result otherwise
+fn decimals_exact_or_fast(f: f64, digits: usize) -> String {
+    // The powers of ten which are exactly representable
+    const POW10: [f64; 23] = [
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+        1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+    ];
+
+    let sign = if f < 0.0 { "-" } else { "" };
+    let abs = abs(f);
+    if digits < POW10.len() && abs < MAX_EXACT {
+        let scaled = abs * POW10[digits] + 0.5;
+        if scaled < MAX_EXACT {
+            let scaled = scaled as u64;
+            let pow = 10u64.pow(digits as u32);
+            let (int, frac) = (scaled / pow, scaled % pow);
+            return if digits == 0 {
+                format!("{}{}", sign, int)
+            } else {
+                format!("{}{}.{:0>width$}", sign, int, frac, width = digits)
+            };
+        }
+    }
+    format!("{}{:.*}", sign, digits, abs)
+}
+
+fn scientific(f: f64, digits: usize) -> String {
+    // Rust neither signs the exponent nor pads it to two digits, as C does
+    let s = format!("{:.*e}", digits, f);
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    let (sign, exponent) = match exponent.strip_prefix('-') {
+        Some(exponent) => ('-', exponent),
+        None => ('+', exponent),
+    };
+    format!("{}e{}{:0>2}", mantissa, sign, exponent)
+}
+
+/// `f64::abs` is not available without `std`
+#[inline]
+fn abs(f: f64) -> f64 {
+    if f.is_sign_negative() {
+        -f
+    } else {
+        f
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(f: f64, format: FloatFormat) -> String {
+        Float::new(f).unwrap().format(format).unwrap()
+    }
+
+    #[test]
+    fn float_format_short_test() {
+        let short = |f| format(f, FloatFormat::Short);
+        assert_eq!(short(0.0), "0.0");
+        assert_eq!(short(-0.0), "-0.0");
+        assert_eq!(short(1.0), "1.0");
+        assert_eq!(short(7.12), "7.12");
+        assert_eq!(short(-0.5), "-0.5");
+        assert_eq!(short(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(short(100.0), "100.0");
+        assert_eq!(short(1000.0), "1.0e3");
+        assert_eq!(short(0.001), "0.001");
+        assert_eq!(short(1.0e-10), "1.0e-10");
+        assert_eq!(short(123456789.0), "123456789.0");
+        assert_eq!(short(1.0e100), "1.0e100");
+        assert_eq!(short(9007199254740991.0), "9007199254740991.0");
+        assert_eq!(short(9007199254740992.0), "9.007199254740992e15");
+    }
+
+    #[test]
+    fn float_format_decimals_test() {
+        let decimals = |f, digits, compact| format(f, FloatFormat::Decimals { digits, compact });
+        assert_eq!(decimals(7.12, 4, false), "7.1200");
+        assert_eq!(decimals(7.12, 4, true), "7.12");
+        assert_eq!(decimals(7.0, 4, true), "7.0");
+        assert_eq!(decimals(7.5, 0, false), "8");
+        assert_eq!(decimals(-1.25, 1, false), "-1.3");
+        assert_eq!(decimals(0.125, 2, false), "0.13");
+        assert_eq!(decimals(1.0e20, 2, false), "100000000000000000000.00");
+        assert_eq!(
+            Float::new(1.0e300).unwrap().format(FloatFormat::Decimals {
+                digits: 0,
+                compact: false
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn float_format_scientific_test() {
+        assert_eq!(format(7.12, FloatFormat::Scientific(3)), "7.120e+00");
+        assert_eq!(format(1.0e-100, FloatFormat::Scientific(1)), "1.0e-100");
+        assert_eq!(
+            format(0.1 + 0.2, FloatFormat::default()),
+            "3.00000000000000044409e-01"
+        );
+    }
+
+    #[test]
+    fn float_format_round_trip_test() {
+        for f in [0.1 + 0.2, 1.0e-10, 1000.0, 123456789.0, 5.0e-324, f64::MAX] {
+            let s = format(f, FloatFormat::Short);
+            assert_eq!(s.parse::<Float>().unwrap().inner(), f);
+        }
+        // Only the syntax accepted by `list_to_float/1` is parsed
+        for s in [
+            "1", "1.", ".5", "1e3", "1.0e", "inf", "NaN", " 1.0", "1_0.0", "1.0e400",
+        ] {
+            assert!(s.parse::<Float>().is_err(), "{}", s);
+        }
+        assert_eq!("+1.5E-2".parse::<Float>().unwrap().inner(), 0.015);
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::fmt;
 use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub};
@@ -69,6 +71,50 @@ impl Int {
         Some(Self::Big(bi))
     }
 
+    /// Parses `string` as an integer in `radix`, like `list_to_integer/2` and `binary_to_integer/2`
+    ///
+    /// Unlike [`Int::from_string_radix`], only an optional sign followed by one or more digits is
+    /// accepted, so e.g. digit separators or surrounding whitespace are rejected.
+    pub fn parse_radix(string: &str, radix: u32) -> Option<Self> {
+        assert!((2..=36).contains(&radix), "invalid radix {}", radix);
+        let digits = string
+            .strip_prefix(|c| c == '+' || c == '-')
+            .unwrap_or(string);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+        Self::from_string_radix(string, radix)
+    }
+
+    /// Formats this integer in `radix`, using upper case letters for digits above 9, like
+    /// `integer_to_list/2` and `integer_to_binary/2`
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix), "invalid radix {}", radix);
+        match self {
+            Self::Small(i) => {
+                let mut digits = Vec::with_capacity(65);
+                let mut n = i.unsigned_abs();
+                loop {
+                    let digit = (n % radix as u64) as u32;
+                    digits.push(char::from_digit(digit, radix).unwrap().to_ascii_uppercase());
+                    n /= radix as u64;
+                    if n == 0 {
+                        break;
+                    }
+                }
+                if *i < 0 {
+                    digits.push('-');
+                }
+                digits.iter().rev().collect()
+            }
+            Self::Big(i) => {
+                let mut s = i.to_str_radix(radix);
+                s.make_ascii_uppercase();
+                s
+            }
+        }
+    }
+
     pub fn to_arity(&self) -> u8 {
         match self {
             Self::Small(i) => (*i).try_into().unwrap(),
@@ -307,14 +353,8 @@ impl Add<i64> for Int {
 
     fn add(self, rhs: i64) -> Self::Output {
         match self {
-            Self::Small(i) => match i.checked_add(rhs) {
-                None => {
-                    let i = BigInt::from(i);
-                    Self::Big(i + rhs)
-                }
-                Some(i) => Self::new(i),
-            },
-            Self::Big(i) => Self::Big(i + rhs),
+            Self::Small(i) => (i as i128 + rhs as i128).into(),
+            Self::Big(i) => (i + rhs).into(),
         }
     }
 }
@@ -368,13 +408,7 @@ impl Sub<i64> for Int {
 
     fn sub(self, rhs: i64) -> Self::Output {
         match self {
-            Self::Small(lhs) => match lhs.checked_sub(rhs) {
-                None => {
-                    let lhs = BigInt::from(lhs);
-                    (lhs - rhs).into()
-                }
-                Some(result) => result.into(),
-            },
+            Self::Small(lhs) => (lhs as i128 - rhs as i128).into(),
             Self::Big(lhs) => (lhs - rhs).into(),
         }
     }
@@ -427,25 +461,9 @@ impl Mul<&Int> for Int {
 impl Mul<usize> for &Int {
     type Output = Int;
     fn mul(self, rhs: usize) -> Self::Output {
-        let rhs64: Result<i64, _> = rhs.try_into();
-        match rhs64 {
-            Ok(rhs) => match self {
-                Int::Small(lhs) => match lhs.checked_mul(rhs) {
-                    None => {
-                        let lhs = BigInt::from(*lhs);
-                        (lhs * rhs).into()
-                    }
-                    Some(result) => result.into(),
-                },
-                Int::Big(lhs) => (lhs * rhs).into(),
-            },
-            Err(_) => match self {
-                Int::Small(lhs) => {
-                    let lhs = BigInt::from(*lhs);
-                    (lhs * rhs).into()
-                }
-                Int::Big(lhs) => (lhs * rhs).into(),
-            },
+        match self {
+            Int::Small(lhs) => (*lhs as i128 * rhs as i128).into(),
+            Int::Big(lhs) => (lhs * rhs).into(),
         }
     }
 }
@@ -453,13 +471,7 @@ impl Mul<i64> for Int {
     type Output = Int;
     fn mul(self, rhs: i64) -> Self::Output {
         match self {
-            Self::Small(lhs) => match lhs.checked_mul(rhs) {
-                None => {
-                    let lhs = BigInt::from(lhs);
-                    (lhs * rhs).into()
-                }
-                Some(result) => result.into(),
-            },
+            Self::Small(lhs) => (lhs as i128 * rhs as i128).into(),
             Self::Big(lhs) => (lhs * rhs).into(),
         }
     }
@@ -1003,6 +1015,7 @@ impl From<usize> for Int {
     }
 }
 impl From<i128> for Int {
+    #[inline]
     fn from(i: i128) -> Self {
         if i > Self::MAX_SMALL as i128 || i < Self::MIN_SMALL as i128 {
             Self::Big(i.into())
@@ -1053,3 +1066,94 @@ impl TryInto<usize> for Int {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use test::{black_box, Bencher};
+
+    use super::*;
+
+    #[test]
+    fn int_parse_radix_test() {
+        assert_eq!(Int::parse_radix("ff", 16), Some(Int::Small(255)));
+        assert_eq!(Int::parse_radix("-FF", 16), Some(Int::Small(-255)));
+        assert_eq!(Int::parse_radix("+101", 2), Some(Int::Small(5)));
+        assert_eq!(Int::parse_radix("Zz", 36), Some(Int::Small(1295)));
+        let big = Int::parse_radix("123456789012345678901234567890", 10).unwrap();
+        assert!(matches!(big, Int::Big(_)));
+        assert_eq!(big.to_string(), "123456789012345678901234567890");
+        for invalid in ["", "-", "+", "12", "1_0", " 1", "1 ", "--1", "0x1"] {
+            assert_eq!(Int::parse_radix(invalid, 2), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn int_to_string_radix_test() {
+        assert_eq!(Int::Small(255).to_string_radix(16), "FF");
+        assert_eq!(Int::Small(-255).to_string_radix(16), "-FF");
+        assert_eq!(Int::Small(0).to_string_radix(2), "0");
+        assert_eq!(Int::Small(i64::MIN).to_string_radix(10), i64::MIN.to_string());
+        assert_eq!(Int::Small(1295).to_string_radix(36), "ZZ");
+        let big = Int::parse_radix("-zzzzzzzzzzzzzzzzzzzzzzzz", 36).unwrap();
+        assert_eq!(big.to_string_radix(36), "-ZZZZZZZZZZZZZZZZZZZZZZZZ");
+    }
+    #[test]
+    fn int_arith_overflow_test() {
+        let max = Int::Small(Int::MAX_SMALL);
+        let min = Int::Small(Int::MIN_SMALL);
+        assert_eq!(max.clone() + 0i64, Int::Small(Int::MAX_SMALL));
+        assert!(matches!(max.clone() + 1i64, Int::Big(_)));
+        assert!(matches!(min.clone() - 1i64, Int::Big(_)));
+        assert_eq!(
+            (max.clone() * Int::MAX_SMALL).to_string(),
+            (BigInt::from(Int::MAX_SMALL) * Int::MAX_SMALL).to_string()
+        );
+        assert_eq!(
+            (Int::Small(i64::MIN) * -1i64).to_string(),
+            (-BigInt::from(i64::MIN)).to_string()
+        );
+        assert_eq!(&Int::Small(-3) * usize::MAX, Int::from(-3 * usize::MAX as i128));
+        // Big results which fit in a small integer are normalized
+        assert!(matches!((max + 1i64) - 1i64, Int::Small(Int::MAX_SMALL)));
+        assert!(matches!((min - 1i64) + 1i64, Int::Small(Int::MIN_SMALL)));
+    }
+
+    /// An Adler-32 style checksum, as written in Erlang
+    #[bench]
+    fn bench_int_arith_checksum(b: &mut Bencher) {
+        b.iter(|| {
+            let (mut a, mut s) = (Int::Small(1), Int::Small(0));
+            for byte in 0..black_box(4096i64) {
+                a = ((a + (byte & 0xff)) % 65521i64).unwrap();
+                s = ((s + &a) % 65521i64).unwrap();
+            }
+            (s << Int::Small(16)) | a
+        })
+    }
+
+    /// An FNV-1a style hash, which multiplies past the range of small integers before masking
+    #[bench]
+    fn bench_int_arith_hash(b: &mut Bencher) {
+        b.iter(|| {
+            let mut hash = Int::Small(0x811c9dc5);
+            for byte in 0..black_box(4096i64) {
+                hash = ((hash ^ (byte & 0xff)) * 0x01000193i64) & Int::Small(0xffffffff);
+            }
+            hash
+        })
+    }
+
+    /// Products which always overflow to big integers
+    #[bench]
+    fn bench_int_arith_overflow(b: &mut Bencher) {
+        b.iter(|| {
+            let mut acc = Int::Small(0);
+            for n in 0..black_box(4096i64) {
+                acc = acc + (Int::Small(Int::MAX_SMALL - n) * (Int::MAX_SMALL - n));
+            }
+            acc
+        })
+    }
+}
@@ -1,4 +1,5 @@
 #![no_std]
+#![cfg_attr(test, feature(test))]
 
 extern crate alloc;
 #[cfg(any(test, feature = "std"))]
@@ -15,6 +16,9 @@ pub use integer::*;
 mod float;
 pub use float::{f16, Float, FloatError};
 
+mod float_format;
+pub use float_format::FloatFormat;
+
 mod number;
 pub use number::Number;
 
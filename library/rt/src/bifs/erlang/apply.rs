@@ -0,0 +1,74 @@
+use crate::error::ExceptionFlags;
+use crate::function::{list_to_args, ArgumentListError, ErlangResult, MAX_ARGS};
+use crate::gc::{garbage_collect, RootSet};
+use crate::process::ProcessLock;
+use crate::term::*;
+
+/// The maximum number of arguments which can be applied to a closure dynamically
+///
+/// See `Closure::apply`
+const MAX_APPLY_ARGS: usize = 10;
+
+#[export_name = "erlang:apply/2"]
+pub extern "C-unwind" fn apply2(
+    process: &mut ProcessLock,
+    mut fun_term: OpaqueTerm,
+    mut argv: OpaqueTerm,
+) -> ErlangResult {
+    // Make sure we have enough space to construct the error reasons we may raise,
+    // the largest of which is `{badarity, {Fun, Args}}`
+    let needed = {
+        let mut layout = LayoutBuilder::new();
+        layout.build_tuple(2).build_tuple(2);
+        layout.finish().size()
+    };
+    if needed > process.heap_available() {
+        let mut roots = RootSet::default();
+        roots += &mut fun_term as *mut OpaqueTerm;
+        roots += &mut argv as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let Term::Closure(fun) = fun_term.into() else {
+        let reason = Tuple::from_slice(&[atoms::Badfun.into(), fun_term], process).unwrap();
+        return raise(process, reason.into(), argv);
+    };
+
+    let args = match list_to_args(argv, MAX_ARGS) {
+        Ok(args) => args,
+        Err(ArgumentListError::Improper) => badarg!(process, argv),
+        Err(ArgumentListError::TooManyArguments) => {
+            return raise(process, atoms::SystemLimit.into(), argv)
+        }
+    };
+
+    // Bytecoded closures are applied by the emulator, we can only apply native functions here
+    if !fun.is_native() {
+        badarg!(process, fun_term);
+    }
+
+    // Closures with an environment receive the closure itself as an implicit extra argument
+    let arity = fun.arity as usize - (!fun.is_thin() as usize);
+    if args.len() != arity {
+        let fun_and_args = Tuple::from_slice(&[fun_term, argv], process).unwrap();
+        let reason =
+            Tuple::from_slice(&[atoms::Badarity.into(), fun_and_args.into()], process).unwrap();
+        return raise(process, reason.into(), argv);
+    }
+
+    if args.len() > MAX_APPLY_ARGS {
+        return raise(process, atoms::SystemLimit.into(), argv);
+    }
+
+    fun.apply(process, args.as_slice())
+}
+
+fn raise(process: &mut ProcessLock, reason: OpaqueTerm, args: OpaqueTerm) -> ErlangResult {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.reason = reason;
+    process.exception_info.value = reason;
+    process.exception_info.args = Some(args);
+    process.exception_info.trace = None;
+    process.exception_info.cause = None;
+    ErlangResult::Err
+}
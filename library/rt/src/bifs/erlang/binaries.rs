@@ -1,4 +1,3 @@
-use alloc::collections::vec_deque::VecDeque;
 use alloc::vec::Vec;
 use core::intrinsics::{likely, unlikely};
 use core::mem;
@@ -102,6 +101,14 @@ pub extern "C-unwind" fn atom_to_binary2(
     }
 }
 
+#[export_name = "erlang:binary_to_atom/1"]
+pub extern "C-unwind" fn binary_to_atom1(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+) -> ErlangResult {
+    binary_to_atom2(process, binary, atoms::Utf8.into())
+}
+
 #[export_name = "erlang:binary_to_atom/2"]
 pub extern "C-unwind" fn binary_to_atom2(
     process: &mut ProcessLock,
@@ -111,25 +118,36 @@ pub extern "C-unwind" fn binary_to_atom2(
     let bin: Term = binary.into();
     let bin = binary_or_badarg!(process, bin);
     let encoding = atom_or_badarg!(process, encoding_term.into());
-    if let Err(_) = Encoding::from_str(encoding.as_str()) {
-        badarg!(process, encoding_term);
-    }
+    let Ok(encoding) = Encoding::from_str(encoding.as_str()) else { badarg!(process, encoding_term); };
 
-    if bin.is_aligned() {
-        if let Some(s) = bin.as_str() {
-            if let Ok(atom) = Atom::try_from(s) {
-                return ErlangResult::Ok(atom.into());
-            }
+    let result = match encoding {
+        Encoding::Latin1 if bin.is_aligned() => {
+            Atom::try_from_latin1_bytes(unsafe { bin.as_bytes_unchecked() })
         }
-    } else {
-        let bytes = bin.bytes().collect::<Vec<_>>();
-        if let Ok(atom) = Atom::try_from(bytes.as_slice()) {
-            return ErlangResult::Ok(atom.into());
+        Encoding::Latin1 => Atom::try_from_latin1_bytes(&bin.bytes().collect::<Vec<_>>()),
+        Encoding::Utf8 if bin.is_aligned() => Atom::try_from(unsafe { bin.as_bytes_unchecked() }),
+        Encoding::Utf8 => Atom::try_from(bin.bytes().collect::<Vec<_>>().as_slice()),
+        _ => badarg!(process, encoding_term),
+    };
+    match result {
+        Ok(atom) => ErlangResult::Ok(atom.into()),
+        Err(AtomError::InvalidLength(_) | AtomError::TableFull(_)) => {
+            system_limit!(process, binary)
         }
+        Err(_) => badarg!(process, binary),
     }
-    badarg!(process, binary);
 }
 
+#[export_name = "erlang:binary_to_existing_atom/1"]
+pub extern "C-unwind" fn binary_to_existing_atom1(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+) -> ErlangResult {
+    binary_to_existing_atom2(process, binary, atoms::Utf8.into())
+}
+
+/// Unlike `binary_to_atom/2`, this never creates a new atom, so it is safe to use with untrusted
+/// input, as it cannot be used to exhaust the atom table
 #[export_name = "erlang:binary_to_existing_atom/2"]
 pub extern "C-unwind" fn binary_to_existing_atom2(
     process: &mut ProcessLock,
@@ -139,23 +157,23 @@ pub extern "C-unwind" fn binary_to_existing_atom2(
     let bin: Term = binary.into();
     let bin = binary_or_badarg!(process, bin);
     let encoding = atom_or_badarg!(process, encoding_term.into());
-    if let Err(_) = Encoding::from_str(encoding.as_str()) {
-        badarg!(process, encoding_term);
-    }
+    let Ok(encoding) = Encoding::from_str(encoding.as_str()) else { badarg!(process, encoding_term); };
 
-    if bin.is_aligned() {
-        if let Some(s) = bin.as_str() {
-            if let Ok(atom) = Atom::try_from_str_existing(s) {
-                return ErlangResult::Ok(atom.into());
-            }
+    let result = match encoding {
+        Encoding::Latin1 if bin.is_aligned() => {
+            Atom::try_from_latin1_bytes_existing(unsafe { bin.as_bytes_unchecked() })
         }
-    } else {
-        let bytes = bin.bytes().collect::<Vec<_>>();
-        if let Ok(atom) = Atom::try_from_latin1_bytes_existing(bytes.as_slice()) {
-            return ErlangResult::Ok(atom.into());
+        Encoding::Latin1 => Atom::try_from_latin1_bytes_existing(&bin.bytes().collect::<Vec<_>>()),
+        Encoding::Utf8 if bin.is_aligned() => {
+            Atom::try_from_utf8_bytes_existing(unsafe { bin.as_bytes_unchecked() })
         }
+        Encoding::Utf8 => Atom::try_from_utf8_bytes_existing(&bin.bytes().collect::<Vec<_>>()),
+        _ => badarg!(process, encoding_term),
+    };
+    match result {
+        Ok(atom) => ErlangResult::Ok(atom.into()),
+        Err(_) => badarg!(process, binary),
     }
-    badarg!(process, binary);
 }
 
 #[export_name = "erlang:binary_to_float/1"]
@@ -174,71 +192,6 @@ pub extern "C-unwind" fn binary_to_float1(
     badarg!(process, binary);
 }
 
-#[export_name = "erlang:binary_to_integer/1"]
-pub extern "C-unwind" fn binary_to_integer1(
-    process: &mut ProcessLock,
-    binary: OpaqueTerm,
-) -> ErlangResult {
-    binary_to_integer2(process, binary, Term::Int(10).into())
-}
-
-#[export_name = "erlang:binary_to_integer/2"]
-pub extern "C-unwind" fn binary_to_integer2(
-    process: &mut ProcessLock,
-    binary: OpaqueTerm,
-    base_term: OpaqueTerm,
-) -> ErlangResult {
-    use core::num::IntErrorKind;
-
-    let base = usize_or_badarg!(process, base_term.into());
-    if !(2..=36).contains(&base) {
-        badarg!(process, base_term);
-    }
-    let base = base as u32;
-
-    let bin: Term = binary.into();
-    let bin = binary_or_badarg!(process, bin);
-    if let Some(s) = bin.as_str() {
-        match i64::from_str_radix(s, base) {
-            Ok(i) if OpaqueTerm::is_small_integer(i) => {
-                return ErlangResult::Ok(Term::Int(i).into())
-            }
-            Ok(i) => match Gc::<BigInt>::new_uninit_in(process) {
-                Ok(mut boxed) => unsafe {
-                    boxed.write(BigInt::from(i));
-                    return ErlangResult::Ok(boxed.assume_init().into());
-                },
-                Err(_) => {
-                    assert!(garbage_collect(process, Default::default()).is_ok());
-                    let boxed = Gc::new_in(BigInt::from(i), process).unwrap();
-                    return ErlangResult::Ok(boxed.into());
-                }
-            },
-            Err(e) => match e.kind() {
-                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
-                    use firefly_number::Num;
-                    if let Ok(i) = BigInt::from_str_radix(s, base) {
-                        match Gc::<BigInt>::new_uninit_in(process) {
-                            Ok(mut boxed) => unsafe {
-                                boxed.write(i);
-                                return ErlangResult::Ok(boxed.assume_init().into());
-                            },
-                            Err(_) => {
-                                assert!(garbage_collect(process, Default::default()).is_ok());
-                                let boxed = Gc::new_in(i, process).unwrap();
-                                return ErlangResult::Ok(boxed.into());
-                            }
-                        }
-                    }
-                }
-                _ => badarg!(process, binary),
-            },
-        }
-    }
-
-    badarg!(process, binary);
-}
-
 #[export_name = "erlang:bitstring_to_list/1"]
 pub extern "C-unwind" fn bitstring_to_list1(
     process: &mut ProcessLock,
@@ -434,7 +387,7 @@ pub extern "C-unwind" fn split_binary2(
     if needed > process.heap_available() {
         let mut roots = RootSet::default();
         roots += &mut binary as *mut OpaqueTerm;
-        assert!(garbage_collect(process, Default::default()).is_ok());
+        assert!(garbage_collect(process, roots).is_ok());
     }
 
     let bin: Term = binary.into();
@@ -474,7 +427,7 @@ pub extern "C-unwind" fn split_binary2(
 
 #[export_name = "erlang:iolist_size/1"]
 pub extern "C-unwind" fn iolist_size1(process: &mut ProcessLock, item: OpaqueTerm) -> ErlangResult {
-    let Ok(size) = iolist_size(item.into()) else { badarg!(process, item); };
+    let Ok(size) = iodata_size(&item.into()) else { badarg!(process, item); };
 
     let result: Result<i64, _> = size.try_into();
     if unlikely(result.is_err()) {
@@ -494,36 +447,28 @@ pub extern "C-unwind" fn iolist_to_binary1(
     process: &mut ProcessLock,
     item: OpaqueTerm,
 ) -> ErlangResult {
-    let mut bitvec = BitVec::with_capacity(1024);
-    let mut worklist = VecDeque::new();
-    worklist.push_back(item.into());
-    while let Some(term) = worklist.pop_front() {
-        match term {
-            Term::Nil => continue,
-            Term::Cons(cons) => match cons.tail.into() {
-                Term::Nil => {
-                    worklist.push_front(cons.head.into());
-                }
-                tail => {
-                    worklist.push_front(tail);
-                    worklist.push_front(cons.head.into());
-                }
-            },
-            Term::Int(i) if (0..256).contains(&i) => {
-                bitvec.push_byte(i as u8);
-            }
-            term @ (Term::HeapBinary(_)
-            | Term::RcBinary(_)
-            | Term::RefBinary(_)
-            | Term::ConstantBinary(_)) => {
-                let bin = term.as_binary().ok_or(())?;
-                bitvec.push_selection(bin.select_all());
-            }
-            _ => badarg!(process, item),
-        }
+    let term: Term = item.into();
+    if term.as_binary().is_some() {
+        return ErlangResult::Ok(item);
+    }
+    iodata_to_binary_or_badarg(process, item)
+}
+
+#[export_name = "erlang:list_to_binary/1"]
+pub extern "C-unwind" fn list_to_binary1(
+    process: &mut ProcessLock,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    if !item.is_list() {
+        badarg!(process, item);
     }
+    iodata_to_binary_or_badarg(process, item)
+}
 
-    let byte_size = bitvec.byte_size();
+/// Converts the iodata `item` into a binary, sized before any data is copied
+fn iodata_to_binary_or_badarg(process: &mut ProcessLock, item: OpaqueTerm) -> ErlangResult {
+    let mut term: Term = item.into();
+    let Ok(byte_size) = iodata_size(&term) else { badarg!(process, item); };
 
     if unlikely(byte_size == 0) {
         return ErlangResult::Ok(Term::ConstantBinary(EMPTY_BIN).into());
@@ -533,15 +478,13 @@ pub extern "C-unwind" fn iolist_to_binary1(
     layout.build_binary(byte_size);
     let needed = layout.finish().size();
     if needed > process.heap_available() {
-        assert!(garbage_collect(process, Default::default()).is_ok());
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut term as *mut Term;
+        assert!(garbage_collect(process, roots).is_ok());
     }
 
-    let bytes = unsafe { bitvec.as_bytes_unchecked() };
-    if byte_size > BinaryData::MAX_HEAP_BYTES {
-        ErlangResult::Ok(BinaryData::from_bytes(bytes).into())
-    } else {
-        ErlangResult::Ok(BinaryData::from_small_bytes(bytes, process).unwrap().into())
-    }
+    ErlangResult::Ok(iodata_to_binary(&term, byte_size, process).unwrap().into())
 }
 
 #[export_name = "erlang:iolist_to_iovec/1"]
@@ -709,39 +652,6 @@ fn extend_iovec_parts(
     Ok(())
 }
 
-fn iolist_size(item: Term) -> Result<usize, ()> {
-    let mut size = 0;
-
-    let mut worklist = VecDeque::new();
-    worklist.push_back(item);
-    while let Some(term) = worklist.pop_front() {
-        match term {
-            Term::Nil => continue,
-            Term::Cons(cons) => {
-                for maybe_improper in cons.iter() {
-                    match maybe_improper {
-                        Ok(element) => worklist.push_back(element),
-                        Err(improper) => worklist.push_back(improper.tail),
-                    }
-                }
-            }
-            Term::Int(i) if (0..256).contains(&i) => {
-                size += 1;
-            }
-            term @ (Term::HeapBinary(_)
-            | Term::RcBinary(_)
-            | Term::RefBinary(_)
-            | Term::ConstantBinary(_)) => {
-                let bin = term.as_binary().ok_or(())?;
-                size += bin.byte_size();
-            }
-            _ => return Err(()),
-        }
-    }
-
-    Ok(size)
-}
-
 #[inline(never)]
 #[cold]
 fn convert_to_bigint<I: Into<BigInt>>(process: &mut ProcessLock, integer: I) -> Gc<BigInt> {
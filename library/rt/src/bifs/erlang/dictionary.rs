@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+
+use crate::function::ErlangResult;
+use crate::gc::{garbage_collect, RootSet};
+use crate::process::ProcessLock;
+use crate::term::*;
+
+#[export_name = "erlang:put/2"]
+pub extern "C-unwind" fn put2(
+    process: &mut ProcessLock,
+    key: OpaqueTerm,
+    value: OpaqueTerm,
+) -> ErlangResult {
+    let previous = process.dictionary.put(key, value);
+    ErlangResult::Ok(previous.unwrap_or_else(|| atoms::Undefined.into()))
+}
+
+#[export_name = "erlang:get/1"]
+pub extern "C-unwind" fn get1(process: &mut ProcessLock, key: OpaqueTerm) -> ErlangResult {
+    let value = process.dictionary.get(key);
+    ErlangResult::Ok(value.unwrap_or_else(|| atoms::Undefined.into()))
+}
+
+/// Returns the contents of the process dictionary as a list of `{Key, Value}` tuples
+#[export_name = "erlang:get/0"]
+pub extern "C-unwind" fn get0(process: &mut ProcessLock) -> ErlangResult {
+    reserve_entries(process);
+    let entries = process.dictionary.iter().collect::<Vec<_>>();
+    ErlangResult::Ok(entries_to_list(process, &entries))
+}
+
+#[export_name = "erlang:get_keys/0"]
+pub extern "C-unwind" fn get_keys0(process: &mut ProcessLock) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(process.dictionary.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let keys = process
+        .dictionary
+        .iter()
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    ErlangResult::Ok(terms_to_list(process, &keys))
+}
+
+/// Returns the keys of all entries whose value is exactly equal to `value`
+#[export_name = "erlang:get_keys/1"]
+pub extern "C-unwind" fn get_keys1(process: &mut ProcessLock, value: OpaqueTerm) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(process.dictionary.keys_for(value).len());
+    let needed = layout.finish().size();
+    let mut value = value;
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut value as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+    let keys = process.dictionary.keys_for(value);
+    ErlangResult::Ok(terms_to_list(process, &keys))
+}
+
+#[export_name = "erlang:erase/1"]
+pub extern "C-unwind" fn erase1(process: &mut ProcessLock, key: OpaqueTerm) -> ErlangResult {
+    let value = process.dictionary.erase(key);
+    ErlangResult::Ok(value.unwrap_or_else(|| atoms::Undefined.into()))
+}
+
+/// Clears the process dictionary, returning its previous contents as a list of `{Key, Value}`
+#[export_name = "erlang:erase/0"]
+pub extern "C-unwind" fn erase0(process: &mut ProcessLock) -> ErlangResult {
+    // The entries must remain in the dictionary until after any collection, so that they are rooted
+    reserve_entries(process);
+    let entries = process.dictionary.clear();
+    ErlangResult::Ok(entries_to_list(process, &entries))
+}
+
+/// Ensures the heap has enough space for a list of `{Key, Value}` tuples for every entry
+fn reserve_entries(process: &mut ProcessLock) {
+    let len = process.dictionary.len();
+    let mut layout = LayoutBuilder::new();
+    for _ in 0..len {
+        layout.build_tuple(2);
+    }
+    layout.build_list(len);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+}
+
+/// NOTE: This assumes that the heap has enough space, see `reserve_entries`
+fn entries_to_list(process: &mut ProcessLock, entries: &[(OpaqueTerm, OpaqueTerm)]) -> OpaqueTerm {
+    let tuples = entries
+        .iter()
+        .map(|(key, value)| Tuple::from_slice(&[*key, *value], process).unwrap().into())
+        .collect::<Vec<OpaqueTerm>>();
+    terms_to_list(process, &tuples)
+}
+
+fn terms_to_list(process: &mut ProcessLock, terms: &[OpaqueTerm]) -> OpaqueTerm {
+    // Lists are constructed back to front
+    let mut builder = ListBuilder::new(process);
+    for term in terms.iter().rev() {
+        builder.push((*term).into()).unwrap();
+    }
+    builder
+        .finish()
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL)
+}
@@ -0,0 +1,256 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::function::ErlangResult;
+use crate::gc::garbage_collect;
+use crate::process::ProcessLock;
+use crate::services::distribution::{self, DistFlags, NodeConnection, NodeStatus};
+use crate::services::registry::WeakAddress;
+use crate::term::*;
+
+/// Resolves a distribution handle, as created by `erlang:setnode/3`, to its connection
+fn connection_from_handle(handle: OpaqueTerm) -> Option<Arc<NodeConnection>> {
+    match handle.into() {
+        Term::Reference(reference) => NodeConnection::from_handle(&reference),
+        _ => None,
+    }
+}
+
+/// Resolves `handle` to its connection, but only if the calling process is its controller
+fn controlled_connection(process: &ProcessLock, handle: OpaqueTerm) -> Option<Arc<NodeConnection>> {
+    let connection = connection_from_handle(handle)?;
+    if connection.is_controller(&WeakAddress::Process(process.pid())) {
+        Some(connection)
+    } else {
+        None
+    }
+}
+
+#[export_name = "erlang:dist_ctrl_get_data/1"]
+pub extern "C-unwind" fn dist_ctrl_get_data1(
+    process: &mut ProcessLock,
+    handle: OpaqueTerm,
+) -> ErlangResult {
+    let Some(connection) = controlled_connection(process, handle) else { badarg!(process, handle); };
+    let Some(data) = connection.dequeue() else { return ErlangResult::Ok(atoms::None.into()); };
+
+    if data.len() > BinaryData::MAX_HEAP_BYTES {
+        return ErlangResult::Ok(BinaryData::from_bytes(&data).into());
+    }
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_heap_binary(data.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(BinaryData::from_small_bytes(&data, process).unwrap().into())
+}
+
+#[export_name = "erlang:dist_ctrl_put_data/2"]
+pub extern "C-unwind" fn dist_ctrl_put_data2(
+    process: &mut ProcessLock,
+    handle: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(connection) = connection_from_handle(handle) else { badarg!(process, handle); };
+    if connection.input_handler() != Some(process.pid()) {
+        badarg!(process, handle);
+    }
+    let Ok(bytes) = iodata_to_vec(&data.into()) else { badarg!(process, data); };
+    match distribution::put_data(&connection, &bytes) {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(_) => badarg!(process, data),
+    }
+}
+
+#[export_name = "erlang:dist_ctrl_get_data_notification/1"]
+pub extern "C-unwind" fn dist_ctrl_get_data_notification1(
+    process: &mut ProcessLock,
+    handle: OpaqueTerm,
+) -> ErlangResult {
+    let Some(connection) = controlled_connection(process, handle) else { badarg!(process, handle); };
+    connection.request_data_notification();
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+#[export_name = "erlang:dist_ctrl_input_handler/2"]
+pub extern "C-unwind" fn dist_ctrl_input_handler2(
+    process: &mut ProcessLock,
+    handle: OpaqueTerm,
+    input_handler: OpaqueTerm,
+) -> ErlangResult {
+    let Some(connection) = controlled_connection(process, handle) else { badarg!(process, handle); };
+    match input_handler.into() {
+        Term::Pid(pid) if pid.is_local() => {
+            connection.set_input_handler((*pid).clone());
+            ErlangResult::Ok(atoms::Ok.into())
+        }
+        _ => badarg!(process, input_handler),
+    }
+}
+
+#[export_name = "erlang:is_alive/0"]
+pub extern "C-unwind" fn is_alive0(_process: &mut ProcessLock) -> ErlangResult {
+    ErlangResult::Ok(distribution::is_started().into())
+}
+
+#[export_name = "erlang:node/0"]
+pub extern "C-unwind" fn node0(_process: &mut ProcessLock) -> ErlangResult {
+    ErlangResult::Ok(distribution::current_node().name().into())
+}
+
+/// Returns the name of the node `term` originates from, which must be a pid, port or reference
+#[export_name = "erlang:node/1"]
+pub extern "C-unwind" fn node1(process: &mut ProcessLock, term: OpaqueTerm) -> ErlangResult {
+    let node = match term.into() {
+        Term::Pid(pid) => pid.node(),
+        Term::Port(port) => port.node(),
+        Term::Reference(reference) => reference.node(),
+        _ => badarg!(process, term),
+    };
+    let node = node.unwrap_or_else(distribution::current_node);
+    ErlangResult::Ok(node.name().into())
+}
+
+/// Makes the current node alive as `name`, with `creation`
+///
+/// This is used by `net_kernel` once it has settled on a name, and raises `badarg` if the node is
+/// already alive, `name` is not a valid node name, or `creation` is not a non-zero 32-bit integer.
+#[export_name = "erlang:setnode/2"]
+pub extern "C-unwind" fn setnode2(
+    process: &mut ProcessLock,
+    name: OpaqueTerm,
+    creation: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Atom(node) = name.into() else { badarg!(process, name); };
+    if !distribution::is_node_name(node.as_str()) {
+        badarg!(process, name);
+    }
+    let cre = match creation.into() {
+        Term::Int(i) => u32::try_from(i).ok().filter(|cre| *cre != 0),
+        _ => None,
+    };
+    let Some(cre) = cre else { badarg!(process, creation); };
+    match distribution::start(node, cre) {
+        Ok(_) => ErlangResult::Ok(true.into()),
+        Err(_) => badarg!(process, name),
+    }
+}
+
+/// Returns `{erts_dflags, Default, Mandatory, Addable, Rejectable, StrictOrder}`
+///
+/// This is used by the distribution handshake to determine the capabilities to advertise to, and
+/// require of, other nodes, which reflect the distribution configuration rather than the fixed
+/// defaults of this runtime.
+#[export_name = "erts_internal:get_dflags/0"]
+pub extern "C-unwind" fn get_dflags0(process: &mut ProcessLock) -> ErlangResult {
+    let config = distribution::config();
+    let flags = [
+        config.flags,
+        config.required_flags,
+        config.flags,
+        config.flags & DistFlags::REJECTABLE,
+        DistFlags::STRICT_ORDER,
+    ];
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(flags.len() + 1);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let mut elements = [OpaqueTerm::NONE; 6];
+    elements[0] = atoms::ErtsDflags.into();
+    for (element, flags) in elements[1..].iter_mut().zip(flags) {
+        *element = Term::Int(flags.bits() as i64).into();
+    }
+    ErlangResult::Ok(Tuple::from_slice(&elements, process).unwrap().into())
+}
+
+#[export_name = "erlang:nodes/0"]
+pub extern "C-unwind" fn nodes0(process: &mut ProcessLock) -> ErlangResult {
+    nodes(process, &[NodeStatus::Visible], false)
+}
+
+#[export_name = "erlang:nodes/1"]
+pub extern "C-unwind" fn nodes1(process: &mut ProcessLock, arg: OpaqueTerm) -> ErlangResult {
+    let mut statuses = Vec::new();
+    let mut include_this = false;
+    let mut add = |ty: OpaqueTerm| -> Result<(), ()> {
+        match ty.into() {
+            Term::Atom(a) if a == atoms::Visible => statuses.push(NodeStatus::Visible),
+            Term::Atom(a) if a == atoms::Hidden => statuses.push(NodeStatus::Hidden),
+            Term::Atom(a) if a == atoms::Connected => {
+                statuses.extend_from_slice(&[NodeStatus::Visible, NodeStatus::Hidden]);
+            }
+            Term::Atom(a) if a == atoms::Known => {
+                statuses.extend_from_slice(&[
+                    NodeStatus::Visible,
+                    NodeStatus::Hidden,
+                    NodeStatus::Pending,
+                    NodeStatus::Disconnected,
+                ]);
+                include_this = true;
+            }
+            Term::Atom(a) if a == atoms::This => include_this = true,
+            _ => return Err(()),
+        }
+        Ok(())
+    };
+    let valid = match arg.into() {
+        Term::Cons(cons) => cons
+            .iter_raw()
+            .try_for_each(|ty| ty.map_err(|_| ()).and_then(&mut add)),
+        Term::Nil => Ok(()),
+        _ => add(arg),
+    };
+    if valid.is_err() {
+        badarg!(process, arg);
+    }
+    statuses.sort_by_key(|status| *status as u8);
+    statuses.dedup();
+
+    nodes(process, statuses.as_slice(), include_this)
+}
+
+/// Returns a list of the names of all nodes in any of `statuses`, which never includes the
+/// current node unless `include_this` is set, in which case it is always first.
+fn nodes(process: &mut ProcessLock, statuses: &[NodeStatus], include_this: bool) -> ErlangResult {
+    let current = distribution::current_node();
+    let mut names = Vec::new();
+    if include_this {
+        names.push(current.name());
+    }
+    for status in statuses.iter().copied() {
+        let nodes = distribution::list_by_status(status);
+        names.extend(
+            nodes
+                .iter()
+                .filter(|node| !Arc::ptr_eq(node, &current))
+                .map(|node| node.name()),
+        );
+    }
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(names.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    // Lists are constructed back to front
+    let mut builder = ListBuilder::new(process);
+    for name in names.iter().rev().copied() {
+        builder.push(Term::Atom(name)).unwrap();
+    }
+    ErlangResult::Ok(
+        builder
+            .finish()
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL),
+    )
+}
@@ -0,0 +1,277 @@
+use alloc::vec::Vec;
+
+use firefly_binary::Bitstring;
+
+use crate::error::ExceptionFlags;
+use crate::etf::{self, DecodeOptions, Decoder, EncodeError, EncodeOptions, IoVecChunk};
+use crate::function::ErlangResult;
+use crate::gc::{garbage_collect, RootSet};
+use crate::process::ProcessLock;
+use crate::term::*;
+
+#[export_name = "erlang:term_to_binary/1"]
+pub extern "C-unwind" fn term_to_binary1(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+) -> ErlangResult {
+    term_to_binary(process, term, EncodeOptions::default())
+}
+
+#[export_name = "erlang:term_to_binary/2"]
+pub extern "C-unwind" fn term_to_binary2(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(options) = parse_encode_options(options) else { badarg!(process, options); };
+    term_to_binary(process, term, options)
+}
+
+/// Like `term_to_binary/1`, but returns a list of binaries, in which large binaries from `term`
+/// are referenced rather than copied
+#[export_name = "erlang:term_to_iovec/1"]
+pub extern "C-unwind" fn term_to_iovec1(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+) -> ErlangResult {
+    term_to_iovec(process, term, EncodeOptions::default())
+}
+
+#[export_name = "erlang:term_to_iovec/2"]
+pub extern "C-unwind" fn term_to_iovec2(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(options) = parse_encode_options(options) else { badarg!(process, options); };
+    term_to_iovec(process, term, options)
+}
+
+/// Parses the options list given to `term_to_binary/2`
+///
+/// The `deterministic` option is accepted, but has no effect, as map keys are always encoded in
+/// term order, so the output is deterministic regardless.
+fn parse_encode_options(options: OpaqueTerm) -> Result<EncodeOptions, ()> {
+    let mut parsed = EncodeOptions::default();
+    match options.into() {
+        Term::Nil => Ok(parsed),
+        Term::Cons(cons) => {
+            for option in cons.iter_raw() {
+                match option.map_err(|_| ())?.into() {
+                    Term::Atom(a) if a == atoms::Deterministic => continue,
+                    Term::Atom(a) if a == atoms::Compressed => {
+                        parsed.compressed = EncodeOptions::DEFAULT_COMPRESSION;
+                    }
+                    Term::Tuple(tuple) if tuple.len() == 2 && tuple[0] == atoms::Compressed => {
+                        match tuple[1].into() {
+                            Term::Int(level) if (0..=9).contains(&level) => {
+                                parsed.compressed = level as u8;
+                            }
+                            _ => return Err(()),
+                        }
+                    }
+                    Term::Tuple(tuple) if tuple.len() == 2 && tuple[0] == atoms::MinorVersion => {
+                        match tuple[1].into() {
+                            Term::Int(version) if (0..=2).contains(&version) => {
+                                parsed.minor_version = version as u8;
+                            }
+                            _ => return Err(()),
+                        }
+                    }
+                    _ => return Err(()),
+                }
+            }
+            Ok(parsed)
+        }
+        _ => Err(()),
+    }
+}
+
+fn term_to_binary(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    options: EncodeOptions,
+) -> ErlangResult {
+    let encoded = match etf::encode_with_options(term.into(), options) {
+        Ok(encoded) => encoded,
+        Err(EncodeError::SystemLimit) => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::SystemLimit.into();
+            process.exception_info.value = atoms::SystemLimit.into();
+            process.exception_info.args = Some(term);
+            process.exception_info.trace = None;
+            process.exception_info.cause = None;
+            return ErlangResult::Err;
+        }
+        Err(EncodeError::Unencodable) => badarg!(process, term),
+    };
+
+    if encoded.len() > BinaryData::MAX_HEAP_BYTES {
+        return ErlangResult::Ok(BinaryData::from_bytes(&encoded).into());
+    }
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_heap_binary(encoded.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(
+        BinaryData::from_small_bytes(&encoded, process)
+            .unwrap()
+            .into(),
+    )
+}
+
+fn term_to_iovec(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    options: EncodeOptions,
+) -> ErlangResult {
+    let mut chunks = match etf::encode_iovec(term.into(), options) {
+        Ok(chunks) => chunks,
+        Err(EncodeError::SystemLimit) => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::SystemLimit.into();
+            process.exception_info.value = atoms::SystemLimit.into();
+            process.exception_info.args = Some(term);
+            process.exception_info.trace = None;
+            process.exception_info.cause = None;
+            return ErlangResult::Err;
+        }
+        Err(EncodeError::Unencodable) => badarg!(process, term),
+    };
+
+    let mut layout = LayoutBuilder::new();
+    for chunk in chunks.iter() {
+        if let IoVecChunk::Bytes(bytes) = chunk {
+            layout.build_binary(bytes.len());
+        }
+    }
+    layout.build_list(chunks.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        // Referenced binaries may be sub-binaries on the process heap
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        for chunk in chunks.iter_mut() {
+            if let IoVecChunk::Binary(ref mut term) = chunk {
+                roots += term as *mut Term;
+            }
+        }
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let parts = chunks
+        .drain(..)
+        .map(|chunk| match chunk {
+            IoVecChunk::Binary(term) => term,
+            IoVecChunk::Bytes(bytes) if bytes.len() > BinaryData::MAX_HEAP_BYTES => {
+                Term::RcBinary(BinaryData::from_bytes(&bytes))
+            }
+            IoVecChunk::Bytes(bytes) => {
+                Term::HeapBinary(BinaryData::from_small_bytes(&bytes, process).unwrap())
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut builder = ListBuilder::new(process);
+    for term in parts.into_iter().rev() {
+        unsafe {
+            builder.push_unsafe(term).unwrap();
+        }
+    }
+    let list = builder.finish().map(Term::Cons).unwrap_or(Term::Nil);
+    ErlangResult::Ok(list.into())
+}
+
+#[export_name = "erlang:binary_to_term/1"]
+pub extern "C-unwind" fn binary_to_term1(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+) -> ErlangResult {
+    binary_to_term(process, binary, DecodeOptions::default(), false)
+}
+
+#[export_name = "erlang:binary_to_term/2"]
+pub extern "C-unwind" fn binary_to_term2(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Ok((options, used)) = parse_decode_options(options) else { badarg!(process, options); };
+    binary_to_term(process, binary, options, used)
+}
+
+/// Parses the options list given to `binary_to_term/2`
+///
+/// Returns the decoder options, and whether or not the `used` option was present.
+fn parse_decode_options(options: OpaqueTerm) -> Result<(DecodeOptions, bool), ()> {
+    let mut parsed = DecodeOptions::default();
+    let mut used = false;
+    match options.into() {
+        Term::Nil => Ok((parsed, used)),
+        Term::Cons(cons) => {
+            for option in cons.iter_raw() {
+                match option.map_err(|_| ())?.into() {
+                    Term::Atom(a) if a == atoms::Safe => {
+                        parsed.safe = true;
+                    }
+                    Term::Atom(a) if a == atoms::Used => {
+                        used = true;
+                    }
+                    _ => return Err(()),
+                }
+            }
+            Ok((parsed, used))
+        }
+        _ => Err(()),
+    }
+}
+
+fn binary_to_term(
+    process: &mut ProcessLock,
+    mut binary: OpaqueTerm,
+    options: DecodeOptions,
+    used: bool,
+) -> ErlangResult {
+    // Validate the input first, so that we know how much heap space the result requires
+    let needed = {
+        let bin: Term = binary.into();
+        let bin = binary_or_badarg!(process, bin);
+        let bytes = bin.select_all().to_bytes();
+        match Decoder::with_options(&bytes, options) {
+            Ok(decoder) => {
+                let mut layout = LayoutBuilder::new();
+                layout += decoder.layout();
+                if used {
+                    layout.build_tuple(2);
+                }
+                layout.finish().size()
+            }
+            Err(_) => badarg!(process, binary),
+        }
+    };
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut binary as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    // The binary may have been moved by a collection, so we must reload it
+    let bin: Term = binary.into();
+    let bin = bin.as_binary().unwrap();
+    let bytes = bin.select_all().to_bytes();
+    let decoder = Decoder::with_options(&bytes, options).unwrap();
+    let term = match decoder.decode(process) {
+        Ok(term) => term,
+        Err(_) => badarg!(process, binary),
+    };
+    if !used {
+        return ErlangResult::Ok(term.into());
+    }
+    let consumed = Term::Int(decoder.used() as i64);
+    let result = Tuple::from_slice(&[term.into(), consumed.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
@@ -0,0 +1,146 @@
+use alloc::string::String;
+
+use firefly_number::FloatFormat;
+
+use crate::function::ErlangResult;
+use crate::gc::garbage_collect;
+use crate::process::ProcessLock;
+use crate::term::*;
+
+#[export_name = "erlang:float_to_list/1"]
+pub extern "C-unwind" fn float_to_list1(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+) -> ErlangResult {
+    float_to_list(process, float, FloatFormat::default())
+}
+
+#[export_name = "erlang:float_to_list/2"]
+pub extern "C-unwind" fn float_to_list2(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(format) = parse_format_options(options) else { badarg!(process, options); };
+    float_to_list(process, float, format)
+}
+
+#[export_name = "erlang:float_to_binary/1"]
+pub extern "C-unwind" fn float_to_binary1(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+) -> ErlangResult {
+    float_to_binary(process, float, FloatFormat::default())
+}
+
+#[export_name = "erlang:float_to_binary/2"]
+pub extern "C-unwind" fn float_to_binary2(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(format) = parse_format_options(options) else { badarg!(process, options); };
+    float_to_binary(process, float, format)
+}
+
+#[export_name = "erlang:list_to_float/1"]
+pub extern "C-unwind" fn list_to_float1(
+    process: &mut ProcessLock,
+    list: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Cons(cons) = list.into() else { badarg!(process, list); };
+    let Some(s) = cons.to_string() else { badarg!(process, list); };
+    match s.parse::<Float>() {
+        Ok(f) => ErlangResult::Ok(f.into()),
+        Err(_) => badarg!(process, list),
+    }
+}
+
+/// Parses the options list given to `float_to_list/2` and `float_to_binary/2`
+///
+/// Like OTP, the last of `short`, `{decimals, _}` and `{scientific, _}` wins, and `compact` only
+/// has an effect on `{decimals, _}`, regardless of where it appears.
+fn parse_format_options(options: OpaqueTerm) -> Result<FloatFormat, ()> {
+    let mut format = FloatFormat::default();
+    let mut compact = false;
+    match options.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for option in cons.iter_raw() {
+                match option.map_err(|_| ())?.into() {
+                    Term::Atom(a) if a == atoms::Compact => compact = true,
+                    Term::Atom(a) if a == atoms::Short => format = FloatFormat::Short,
+                    Term::Tuple(tuple) if tuple.len() == 2 => {
+                        let Term::Int(digits) = tuple[1].into() else { return Err(()); };
+                        let digits = u8::try_from(digits).map_err(|_| ())?;
+                        format = match tuple[0].into() {
+                            Term::Atom(a)
+                                if a == atoms::Decimals && digits <= FloatFormat::MAX_DECIMALS =>
+                            {
+                                FloatFormat::Decimals {
+                                    digits,
+                                    compact: false,
+                                }
+                            }
+                            Term::Atom(a)
+                                if a == atoms::Scientific
+                                    && digits <= FloatFormat::MAX_SCIENTIFIC =>
+                            {
+                                FloatFormat::Scientific(digits)
+                            }
+                            _ => return Err(()),
+                        };
+                    }
+                    _ => return Err(()),
+                }
+            }
+        }
+        _ => return Err(()),
+    }
+    if let FloatFormat::Decimals { digits, .. } = format {
+        format = FloatFormat::Decimals { digits, compact };
+    }
+    Ok(format)
+}
+
+fn format_float(float: OpaqueTerm, format: FloatFormat) -> Option<String> {
+    match float.into() {
+        Term::Float(f) => f.format(format),
+        _ => None,
+    }
+}
+
+fn float_to_list(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+    format: FloatFormat,
+) -> ErlangResult {
+    let Some(s) = format_float(float, format) else { badarg!(process, float); };
+
+    // The result is always ASCII, so there is one element per byte
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(s.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Term::charlist_from_str_in(&s, process).unwrap().into())
+}
+
+fn float_to_binary(
+    process: &mut ProcessLock,
+    float: OpaqueTerm,
+    format: FloatFormat,
+) -> ErlangResult {
+    let Some(s) = format_float(float, format) else { badarg!(process, float); };
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_binary(s.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Term::from_str_in(&s, process).unwrap().into())
+}
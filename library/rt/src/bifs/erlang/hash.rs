@@ -0,0 +1,25 @@
+use crate::function::ErlangResult;
+use crate::process::ProcessLock;
+use crate::term::hash::phash2;
+use crate::term::*;
+
+#[export_name = "erlang:phash2/1"]
+pub extern "C-unwind" fn phash2_1(_process: &mut ProcessLock, term: OpaqueTerm) -> ErlangResult {
+    let hash = phash2(term.into()) & ((1 << 27) - 1);
+    ErlangResult::Ok(Term::Int(hash as i64).into())
+}
+
+#[export_name = "erlang:phash2/2"]
+pub extern "C-unwind" fn phash2_2(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    range: OpaqueTerm,
+) -> ErlangResult {
+    // The range may be at most 2^32, in which case the full hash is returned
+    let Term::Int(modulus) = range.into() else { badarg!(process, range); };
+    if !(1..=(1 << 32)).contains(&modulus) {
+        badarg!(process, range);
+    }
+    let hash = phash2(term.into()) as i64;
+    ErlangResult::Ok(Term::Int(hash % modulus).into())
+}
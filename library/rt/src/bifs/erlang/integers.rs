@@ -0,0 +1,135 @@
+use firefly_number::Int;
+
+use crate::function::ErlangResult;
+use crate::gc::{garbage_collect, Gc};
+use crate::process::ProcessLock;
+use crate::term::*;
+
+#[export_name = "erlang:integer_to_list/1"]
+pub extern "C-unwind" fn integer_to_list1(
+    process: &mut ProcessLock,
+    integer: OpaqueTerm,
+) -> ErlangResult {
+    integer_to_list2(process, integer, Term::Int(10).into())
+}
+
+#[export_name = "erlang:integer_to_list/2"]
+pub extern "C-unwind" fn integer_to_list2(
+    process: &mut ProcessLock,
+    integer: OpaqueTerm,
+    base: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(i) = integer_to_int(integer) else { badarg!(process, integer); };
+    let Some(base) = to_base(base) else { badarg!(process, base); };
+    let s = i.to_string_radix(base);
+
+    // The result is always ASCII, so there is one element per byte
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(s.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Term::charlist_from_str_in(&s, process).unwrap().into())
+}
+
+#[export_name = "erlang:integer_to_binary/1"]
+pub extern "C-unwind" fn integer_to_binary1(
+    process: &mut ProcessLock,
+    integer: OpaqueTerm,
+) -> ErlangResult {
+    integer_to_binary2(process, integer, Term::Int(10).into())
+}
+
+#[export_name = "erlang:integer_to_binary/2"]
+pub extern "C-unwind" fn integer_to_binary2(
+    process: &mut ProcessLock,
+    integer: OpaqueTerm,
+    base: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(i) = integer_to_int(integer) else { badarg!(process, integer); };
+    let Some(base) = to_base(base) else { badarg!(process, base); };
+    let s = i.to_string_radix(base);
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_binary(s.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Term::from_str_in(&s, process).unwrap().into())
+}
+
+#[export_name = "erlang:list_to_integer/1"]
+pub extern "C-unwind" fn list_to_integer1(
+    process: &mut ProcessLock,
+    list: OpaqueTerm,
+) -> ErlangResult {
+    list_to_integer2(process, list, Term::Int(10).into())
+}
+
+#[export_name = "erlang:list_to_integer/2"]
+pub extern "C-unwind" fn list_to_integer2(
+    process: &mut ProcessLock,
+    list: OpaqueTerm,
+    base: OpaqueTerm,
+) -> ErlangResult {
+    let Some(base) = to_base(base) else { badarg!(process, base); };
+    let Term::Cons(cons) = list.into() else { badarg!(process, list); };
+    let Some(s) = cons.to_string() else { badarg!(process, list); };
+    let Some(i) = Int::parse_radix(&s, base) else { badarg!(process, list); };
+    int_to_term(process, i)
+}
+
+#[export_name = "erlang:binary_to_integer/1"]
+pub extern "C-unwind" fn binary_to_integer1(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+) -> ErlangResult {
+    binary_to_integer2(process, binary, Term::Int(10).into())
+}
+
+#[export_name = "erlang:binary_to_integer/2"]
+pub extern "C-unwind" fn binary_to_integer2(
+    process: &mut ProcessLock,
+    binary: OpaqueTerm,
+    base: OpaqueTerm,
+) -> ErlangResult {
+    let Some(base) = to_base(base) else { badarg!(process, base); };
+    let bin: Term = binary.into();
+    let bin = binary_or_badarg!(process, bin);
+    let Some(s) = bin.as_str() else { badarg!(process, binary); };
+    let Some(i) = Int::parse_radix(s, base) else { badarg!(process, binary); };
+    int_to_term(process, i)
+}
+
+/// Bases between 2 and 36 are supported, using the letters A-Z for digits above 9
+fn to_base(base: OpaqueTerm) -> Option<u32> {
+    match base.into() {
+        Term::Int(base) if (2..=36).contains(&base) => Some(base as u32),
+        _ => None,
+    }
+}
+
+fn integer_to_int(integer: OpaqueTerm) -> Result<Int, ()> {
+    let term: Term = integer.into();
+    term.try_into()
+}
+
+/// Returns `i` as a term, allocating it on the process heap if it is not a small integer
+fn int_to_term(process: &mut ProcessLock, i: Int) -> ErlangResult {
+    let i = match i {
+        Int::Small(i) => return ErlangResult::Ok(Term::Int(i).into()),
+        Int::Big(i) => i,
+    };
+    let mut layout = LayoutBuilder::new();
+    layout.build_bigint();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Gc::new_in(i, process).unwrap().into())
+}
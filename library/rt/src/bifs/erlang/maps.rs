@@ -0,0 +1,60 @@
+//! The map BIFs which are allowed in guards
+//!
+//! Compiled guards test for a key, or fetch its value, inline with `map_try_get`, and only call
+//! these on the slow path, i.e. when the map argument is not a map, or the key is missing, so
+//! that the right exception is raised outside of a guard.
+use crate::error::ExceptionFlags;
+use crate::function::ErlangResult;
+use crate::process::ProcessLock;
+use crate::term::*;
+
+macro_rules! badmap {
+    ($process:expr, $term:expr) => {
+        return {
+            $process.exception_info.flags = ExceptionFlags::ERROR;
+            $process.exception_info.reason = atoms::Badmap.into();
+            $process.exception_info.value = $term;
+            $process.exception_info.args = None;
+            $process.exception_info.trace = None;
+            $process.exception_info.cause = None;
+            ErlangResult::Err
+        }
+    };
+}
+
+#[export_name = "erlang:map_size/1"]
+pub extern "C-unwind" fn map_size1(process: &mut ProcessLock, map: OpaqueTerm) -> ErlangResult {
+    let Term::Map(map_ref) = map.into() else { badmap!(process, map); };
+    ErlangResult::Ok(Term::Int(map_ref.size() as i64).into())
+}
+
+#[export_name = "erlang:is_map_key/2"]
+pub extern "C-unwind" fn is_map_key2(
+    process: &mut ProcessLock,
+    key: OpaqueTerm,
+    map: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Map(map_ref) = map.into() else { badmap!(process, map); };
+    ErlangResult::Ok(map_ref.contains_key(key).into())
+}
+
+#[export_name = "erlang:map_get/2"]
+pub extern "C-unwind" fn map_get2(
+    process: &mut ProcessLock,
+    key: OpaqueTerm,
+    map: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Map(map_ref) = map.into() else { badmap!(process, map); };
+    match map_ref.get(key) {
+        Some(value) => ErlangResult::Ok(value),
+        None => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::BadKey.into();
+            process.exception_info.value = key;
+            process.exception_info.args = None;
+            process.exception_info.trace = None;
+            process.exception_info.cause = None;
+            ErlangResult::Err
+        }
+    }
+}
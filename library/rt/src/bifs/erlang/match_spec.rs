@@ -0,0 +1,90 @@
+use alloc::string::ToString;
+
+use smallvec::SmallVec;
+
+use crate::function::ErlangResult;
+use crate::gc::garbage_collect;
+use crate::match_spec::{Evaluator, MatchSpec, MatchSpecKind};
+use crate::process::ProcessLock;
+use crate::term::*;
+
+/// Tests `spec` against `term`, as a match spec of the given `kind`, which is `table` or `trace`
+///
+/// Returns `{ok, Result, Flags, Warnings}` if the spec compiles, where `Result` is `false` if no
+/// clause matches. For table specs, `Result` is otherwise the result of the matching clause, while
+/// for trace specs, it is the trace message, i.e. `true` unless `message/1` was called, and `Flags`
+/// lists the tracing actions requested. If the spec does not compile, returns `{error, Errors}`.
+#[export_name = "erlang:match_spec_test/3"]
+pub extern "C-unwind" fn match_spec_test3(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+    spec: OpaqueTerm,
+    kind: OpaqueTerm,
+) -> ErlangResult {
+    let kind = match kind.into() {
+        Term::Atom(a) if a == atoms::Table => MatchSpecKind::Table,
+        Term::Atom(a) if a == atoms::Trace => MatchSpecKind::Trace,
+        _ => badarg!(process, kind),
+    };
+    // Trace match specs are matched against argument lists
+    if kind == MatchSpecKind::Trace && !term.is_list() {
+        badarg!(process, term);
+    }
+    let spec = match MatchSpec::compile(spec.into(), kind) {
+        Ok(spec) => spec,
+        Err(err) => return compile_error(process, &err.to_string()),
+    };
+
+    let mut evaluator = Evaluator::new(Some(process.pid()));
+    let mut flags = SmallVec::<[OpaqueTerm; 2]>::new();
+    let result = match evaluator.run(&spec, term) {
+        None => OpaqueTerm::FALSE,
+        Some(matched) if kind == MatchSpecKind::Table => matched.result,
+        Some(matched) => {
+            if matched.actions.return_trace {
+                flags.push(atoms::ReturnTrace.into());
+            }
+            if matched.actions.exception_trace {
+                flags.push(atoms::ExceptionTrace.into());
+            }
+            matched.actions.message.unwrap_or(OpaqueTerm::TRUE)
+        }
+    };
+    let flags = evaluator.list(&flags).unwrap();
+    let result = evaluator
+        .tuple(&[atoms::Ok.into(), result, flags, OpaqueTerm::NIL])
+        .unwrap();
+
+    // The result may reference both the arguments and the match spec, so it is first copied into a
+    // fragment which is independent of both, as well as of the process heap, which we may need to
+    // collect before copying it there
+    let fragment = TermFragment::clone_from(&result.into()).unwrap();
+    drop(evaluator);
+    drop(spec);
+
+    let result: Term = fragment.term.into();
+    let needed = copy_layout(&result, process, CopyMode::Flat).size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(unsafe { unsafe_copy_term(&result, process, CopyMode::Flat) }.into())
+}
+
+/// Returns `{error, [{error, Message}]}`
+fn compile_error(process: &mut ProcessLock, message: &str) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2).build_list(1).build_tuple(2);
+    layout.build_list(message.chars().count());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let message = Term::charlist_from_str_in(message, process).unwrap();
+    let error = Tuple::from_slice(&[atoms::Error.into(), message.into()], process).unwrap();
+    let errors = Cons::new_in(Cons::cons(Term::Tuple(error), Term::Nil), process).unwrap();
+    let result = Tuple::from_slice(&[atoms::Error.into(), errors.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
@@ -1,2 +1,12 @@
+pub mod apply;
 pub mod binaries;
+pub mod dictionary;
+pub mod distribution;
+pub mod etf;
+pub mod floats;
+pub mod hash;
+pub mod integers;
+pub mod maps;
+pub mod match_spec;
+pub mod ports;
 pub mod tuples;
@@ -0,0 +1,286 @@
+use alloc::sync::Arc;
+
+use smallvec::SmallVec;
+
+use crate::drivers::PortControlFlags;
+use crate::etf::{self, Decoder};
+use crate::function::ErlangResult;
+use crate::gc::{garbage_collect, Gc};
+use crate::process::ProcessLock;
+use crate::services::registry::{self, Registrant};
+use crate::term::*;
+
+
+/// Sends `data` to `port`, as if by `Port ! {PortOwner, {command, Data}}`, but synchronously
+#[export_name = "erlang:port_command/2"]
+pub extern "C-unwind" fn port_command2(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    port_command3(process, port, data, OpaqueTerm::NIL)
+}
+
+/// Like `port_command/2`, but accepting the options `force` and `nosuspend`
+///
+/// Ports are never busy, so both options are accepted but have no effect.
+#[export_name = "erlang:port_command/3"]
+pub extern "C-unwind" fn port_command3(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    data: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Some(port) = resolve_port(port) else { badarg!(process, port); };
+    match options.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for option in cons.iter() {
+                match option {
+                    Ok(Term::Atom(a)) if a == atoms::Force || a == atoms::Nosuspend => continue,
+                    _ => badarg!(process, options),
+                }
+            }
+        }
+        _ => badarg!(process, options),
+    }
+    let Ok(bytes) = iodata_to_vec(&data.into()) else { badarg!(process, data); };
+    match port.command(&bytes) {
+        Ok(_) => ErlangResult::Ok(OpaqueTerm::TRUE),
+        Err(_) => badarg!(process, data),
+    }
+}
+
+/// Returns information about `port`, or `undefined` if it is not open
+#[export_name = "erlang:port_info/1"]
+pub extern "C-unwind" fn port_info1(process: &mut ProcessLock, port: OpaqueTerm) -> ErlangResult {
+    let Some(port) = to_local_port(port) else { badarg!(process, port); };
+    let Some(port) = registry::get_by_port_id(port.id()) else { return ErlangResult::Ok(atoms::Undefined.into()); };
+
+    let mut items = SmallVec::<[(Atom, PortInfoValue<'_>); 6]>::new();
+    if let Some(name) = port.registered_name() {
+        items.push((atoms::RegisteredName, PortInfoValue::Immediate(name.into())));
+    }
+    for item in [
+        atoms::Name,
+        atoms::Id,
+        atoms::Connected,
+        atoms::Input,
+        atoms::Output,
+    ] {
+        items.push((item, port_info_value(&port, item).unwrap()));
+    }
+
+    let mut layout = LayoutBuilder::new();
+    for (_, value) in items.iter() {
+        value.layout(&mut layout);
+        layout.build_tuple(2);
+    }
+    layout.build_list(items.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let items = items
+        .into_iter()
+        .map(|(item, value)| {
+            let value = value.into_term(process);
+            Tuple::from_slice(&[item.into(), value], process)
+                .unwrap()
+                .into()
+        })
+        .collect::<SmallVec<[OpaqueTerm; 6]>>();
+    let list = Cons::from_slice(&items, process)
+        .unwrap()
+        .map(Term::Cons)
+        .unwrap_or(Term::Nil);
+    ErlangResult::Ok(list.into())
+}
+
+/// Returns `{Item, Value}` for the given item of information about `port`, or `undefined` if it
+/// is not open
+///
+/// The supported items are `registered_name`, `name`, `id`, `connected`, `input`, `output` and
+/// `queue_size`.
+#[export_name = "erlang:port_info/2"]
+pub extern "C-unwind" fn port_info2(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    let Some(port) = to_local_port(port) else { badarg!(process, port); };
+    let Term::Atom(name) = item.into() else { badarg!(process, item); };
+    let Some(port) = registry::get_by_port_id(port.id()) else { return ErlangResult::Ok(atoms::Undefined.into()); };
+    let Some(value) = port_info_value(&port, name) else { badarg!(process, item); };
+
+    let mut layout = LayoutBuilder::new();
+    value.layout(&mut layout);
+    layout.build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let value = value.into_term(process);
+    ErlangResult::Ok(Tuple::from_slice(&[item, value], process).unwrap().into())
+}
+
+/// A value returned by `port_info/1,2`, before it has been allocated on the process heap
+enum PortInfoValue<'a> {
+    Immediate(OpaqueTerm),
+    Pid(Pid),
+    Charlist(&'a str),
+}
+impl<'a> PortInfoValue<'a> {
+    fn layout(&self, layout: &mut LayoutBuilder) {
+        match self {
+            Self::Immediate(_) => (),
+            Self::Pid(_) => {
+                layout.build_pid();
+            }
+            Self::Charlist(s) => {
+                layout.build_list(s.chars().count());
+            }
+        }
+    }
+
+    fn into_term(self, process: &mut ProcessLock) -> OpaqueTerm {
+        match self {
+            Self::Immediate(term) => term,
+            Self::Pid(pid) => Gc::new_in(pid, process).unwrap().into(),
+            Self::Charlist(s) => Term::charlist_from_str_in(s, process).unwrap().into(),
+        }
+    }
+}
+
+fn port_info_value(port: &Port, item: Atom) -> Option<PortInfoValue<'_>> {
+    let value = match item {
+        // Unlike `process_info/2`, an unregistered port has an empty list as its name
+        a if a == atoms::RegisteredName => port
+            .registered_name()
+            .map(|name| name.into())
+            .unwrap_or(OpaqueTerm::NIL),
+        a if a == atoms::Name => return Some(PortInfoValue::Charlist(port.name().unwrap_or(""))),
+        a if a == atoms::Connected => return Some(PortInfoValue::Pid(port.owner())),
+        a if a == atoms::Id => counter(port.id().into_raw()),
+        a if a == atoms::Input => counter(port.input()),
+        a if a == atoms::Output => counter(port.output()),
+        a if a == atoms::QueueSize => counter(port.stats().get().send_pend),
+        _ => return None,
+    };
+    Some(PortInfoValue::Immediate(value))
+}
+
+/// Converts a counter to an integer term
+///
+/// Counters saturate at the largest immediate integer, which is not reachable in practice.
+pub(crate) fn counter(value: u64) -> OpaqueTerm {
+    Term::Int(value.min(Int::MAX_SMALL as u64) as i64).into()
+}
+
+/// Synchronously performs `operation` on the driver of `port`, passing it `data`
+///
+/// The result is returned as a list of bytes, or as a binary if the driver has set
+/// [`PortControlFlags::BINARY`] on the port.
+#[export_name = "erlang:port_control/3"]
+pub extern "C-unwind" fn port_control3(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    operation: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(port) = resolve_port(port) else { badarg!(process, port); };
+    let Some(operation) = to_operation(operation) else { badarg!(process, operation); };
+    let Ok(bytes) = iodata_to_vec(&data.into()) else { badarg!(process, data); };
+    let Ok(result) = port.control(operation, &bytes) else { badarg!(process, data); };
+
+    let binary = port.control_flags().contains(PortControlFlags::BINARY);
+    let mut layout = LayoutBuilder::new();
+    if binary {
+        layout.build_binary(result.len());
+    } else {
+        layout.build_list(result.len());
+    }
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    if !binary {
+        let list = Cons::from_bytes(&result, process)
+            .unwrap()
+            .map(Term::Cons)
+            .unwrap_or(Term::Nil);
+        return ErlangResult::Ok(list.into());
+    }
+    if result.len() > BinaryData::MAX_HEAP_BYTES {
+        ErlangResult::Ok(BinaryData::from_bytes(&result).into())
+    } else {
+        ErlangResult::Ok(
+            BinaryData::from_small_bytes(&result, process)
+                .unwrap()
+                .into(),
+        )
+    }
+}
+
+/// Synchronously performs `operation` on the driver of `port`, passing it `data`
+///
+/// Unlike `port_control/3`, both `data` and the result can be any term, which are exchanged with
+/// the driver in the external term format.
+#[export_name = "erlang:port_call/3"]
+pub extern "C-unwind" fn port_call3(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    operation: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(port) = resolve_port(port) else { badarg!(process, port); };
+    let Some(operation) = to_operation(operation) else { badarg!(process, operation); };
+    let Ok(encoded) = etf::encode(data.into()) else { badarg!(process, data); };
+    let Ok(result) = port.call(operation, &encoded) else { badarg!(process, data); };
+    let Ok(decoder) = Decoder::new(&result) else { badarg!(process, data); };
+
+    let needed = decoder.layout().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    match decoder.decode(process) {
+        Ok(term) => ErlangResult::Ok(term.into()),
+        Err(_) => badarg!(process, data),
+    }
+}
+
+/// Resolves `port` to an open local port, given either the port itself or its registered name
+fn resolve_port(port: OpaqueTerm) -> Option<Arc<Port>> {
+    match port.into() {
+        // The port may have been closed, in which case it is no longer registered
+        Term::Port(port) if port.is_local() => registry::get_by_port_id(port.id()),
+        Term::Atom(name) => match registry::get_by_name(name)? {
+            Registrant::Port(port) => Some(port),
+            Registrant::Process(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `port` if it is a local port, whether open or not
+fn to_local_port(port: OpaqueTerm) -> Option<Arc<Port>> {
+    match port.into() {
+        Term::Port(port) if port.is_local() => Some(port),
+        _ => None,
+    }
+}
+
+/// Operations are identified by unsigned 32-bit integers
+fn to_operation(operation: OpaqueTerm) -> Option<u32> {
+    match operation.into() {
+        Term::Int(i) => u32::try_from(i).ok(),
+        _ => None,
+    }
+}
@@ -38,8 +38,8 @@ pub extern "C" fn element2(
 pub extern "C" fn setelement3(
     process: &mut ProcessLock,
     index_term: OpaqueTerm,
-    tuple_term: OpaqueTerm,
-    value: OpaqueTerm,
+    mut tuple_term: OpaqueTerm,
+    mut value: OpaqueTerm,
 ) -> ErlangResult {
     let Ok(index) = OneBasedIndex::try_from(index_term) else { badarg!(process, index_term); };
     let Term::Tuple(tuple) = tuple_term.into() else { badarg!(process, tuple_term); };
@@ -47,17 +47,20 @@ pub extern "C" fn setelement3(
         badarg!(process, index_term);
     }
 
-    match tuple.set_element(index, value, process) {
-        Ok(new_tuple) => ErlangResult::Ok(new_tuple.into()),
-        Err(_) => {
-            let mut roots = RootSet::default();
-            let mut tuple = Term::Tuple(tuple);
-            roots += &mut tuple as *mut Term;
-            assert!(garbage_collect(process, roots).is_ok());
-            let Term::Tuple(tuple) = tuple else { unreachable!() };
-            ErlangResult::Ok(tuple.set_element(index, value, process).unwrap().into())
-        }
+    if let Ok(new_tuple) = tuple.set_element(index, value, process) {
+        return ErlangResult::Ok(new_tuple.into());
     }
+
+    // Both the tuple and the new value may be moved by the collection
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(tuple.len());
+    process.gc_needed = layout.finish().size();
+    let mut roots = RootSet::default();
+    roots += &mut tuple_term as *mut OpaqueTerm;
+    roots += &mut value as *mut OpaqueTerm;
+    assert!(garbage_collect(process, roots).is_ok());
+    let Term::Tuple(tuple) = tuple_term.into() else { unreachable!() };
+    ErlangResult::Ok(tuple.set_element(index, value, process).unwrap().into())
 }
 
 #[export_name = "erlang:tuple_to_list/1"]
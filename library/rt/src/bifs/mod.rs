@@ -24,6 +24,20 @@ macro_rules! badarg {
     };
 }
 
+macro_rules! system_limit {
+    ($process:expr, $term:expr) => {
+        return {
+            $process.exception_info.flags = crate::error::ExceptionFlags::ERROR;
+            $process.exception_info.reason = crate::term::atoms::SystemLimit.into();
+            $process.exception_info.value = crate::term::atoms::SystemLimit.into();
+            $process.exception_info.args = Some($term);
+            $process.exception_info.trace = None;
+            $process.exception_info.cause = None;
+            crate::function::ErlangResult::Err
+        }
+    };
+}
+
 macro_rules! unwrap_or_badarg {
     ($process:expr, $term:expr, $value:expr) => {
         match $value {
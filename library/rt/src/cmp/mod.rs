@@ -1,3 +1,5 @@
 mod exact_eq;
+mod order;
 
 pub use self::exact_eq::ExactEq;
+pub use self::order::{cmp_terms, cmp_terms_exact};
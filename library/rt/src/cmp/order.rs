@@ -0,0 +1,293 @@
+///! This module implements the total ordering of Erlang terms.
+///!
+///! Terms of different types are ordered as follows:
+///!
+///! number < atom < reference < fun < port < pid < tuple < map < nil < list < bitstring
+///!
+///! There are two variants of this ordering, which differ only in how integers and floats are
+///! compared with one another:
+///!
+///! * [`cmp_terms`] implements the ordering used by the comparison operators, as well as by
+///! sorting. Integers and floats are compared by value, so `1` and `1.0` are considered equal.
+///! * [`cmp_terms_exact`] implements the ordering used for map keys, and the `=:=` operator. All
+///! integers are ordered before all floats, so `1` and `1.0` are considered distinct.
+///!
+///! The `Ord` implementations of the various term types are defined in terms of their own
+///! representation, and are not guaranteed to agree with the above. Anything which needs to
+///! respect Erlang semantics should use these functions instead.
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use firefly_binary::Bitstring;
+use firefly_number::Sign;
+
+use crate::term::*;
+
+/// Compares `lhs` and `rhs` using the standard term order, in which numbers are compared by value
+pub fn cmp_terms(lhs: Term, rhs: Term) -> Ordering {
+    compare(lhs, rhs, false)
+}
+
+/// Compares `lhs` and `rhs` using the map key order, in which integers are ordered before floats
+pub fn cmp_terms_exact(lhs: Term, rhs: Term) -> Ordering {
+    compare(lhs, rhs, true)
+}
+
+/// Returns the position of the type of `term` in the term order
+fn type_order(term: &Term) -> u8 {
+    match term {
+        Term::Int(_) | Term::BigInt(_) | Term::Float(_) => 0,
+        Term::Bool(_) | Term::Atom(_) => 1,
+        Term::Reference(_) => 2,
+        Term::Closure(_) => 3,
+        Term::Port(_) => 4,
+        Term::Pid(_) => 5,
+        Term::Tuple(_) => 6,
+        Term::Map(_) => 7,
+        Term::Nil => 8,
+        Term::Cons(_) => 9,
+        Term::HeapBinary(_) | Term::RcBinary(_) | Term::RefBinary(_) | Term::ConstantBinary(_) => {
+            10
+        }
+        Term::None | Term::Catch(_) | Term::Code(_) => {
+            panic!("invalid term given to term comparison")
+        }
+    }
+}
+
+/// Compares two terms, using an explicit stack of pending element pairs so that deeply nested
+/// terms, or long lists, do not overflow the native stack
+///
+/// Compound terms are compared lexicographically, so the first element pair which does not
+/// compare equal determines the result.
+fn compare(lhs: Term, rhs: Term, exact: bool) -> Ordering {
+    let mut stack = Vec::new();
+    match compare_immediate(lhs, rhs, exact, &mut stack) {
+        Ordering::Equal => (),
+        other => return other,
+    }
+    while let Some((lhs, rhs)) = stack.pop() {
+        match compare_immediate(lhs.into(), rhs.into(), exact, &mut stack) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compares `lhs` and `rhs` directly if possible, otherwise compares the parts of the terms which
+/// determine their order before their elements, and pushes any remaining element pairs on to
+/// `stack`, in reverse order, to be compared by the caller
+fn compare_immediate(
+    lhs: Term,
+    rhs: Term,
+    exact: bool,
+    stack: &mut Vec<(OpaqueTerm, OpaqueTerm)>,
+) -> Ordering {
+    match (lhs, rhs) {
+        (Term::Bool(x), Term::Bool(y)) => x.cmp(&y),
+        (Term::Bool(x), Term::Atom(y)) => Atom::from(x).cmp(&y),
+        (Term::Atom(x), Term::Bool(y)) => x.cmp(&Atom::from(y)),
+        (Term::Atom(x), Term::Atom(y)) => x.cmp(&y),
+        (Term::Reference(x), Term::Reference(y)) => x.cmp(&y),
+        (Term::Port(x), Term::Port(y)) => x.cmp(&y),
+        (Term::Pid(x), Term::Pid(y)) => x.cmp(&y),
+        (Term::Nil, Term::Nil) => Ordering::Equal,
+        (Term::Closure(x), Term::Closure(y)) => {
+            let result = x.cmp(&y).then_with(|| x.env_size().cmp(&y.env_size()));
+            if result == Ordering::Equal {
+                push_pairs(stack, x.env(), y.env());
+            }
+            result
+        }
+        (Term::Tuple(x), Term::Tuple(y)) => {
+            let result = x.len().cmp(&y.len());
+            if result == Ordering::Equal {
+                push_pairs(stack, x.as_slice(), y.as_slice());
+            }
+            result
+        }
+        (Term::Map(x), Term::Map(y)) => compare_maps(&x, &y, stack),
+        (Term::Cons(x), Term::Cons(y)) => {
+            stack.push((x.tail, y.tail));
+            stack.push((x.head, y.head));
+            Ordering::Equal
+        }
+        (lhs, rhs) => {
+            let result = type_order(&lhs).cmp(&type_order(&rhs));
+            if result != Ordering::Equal {
+                return result;
+            }
+            match (bitstring(&lhs), bitstring(&rhs)) {
+                (Some(x), Some(y)) => compare_bitstrings(x, y),
+                _ => compare_numbers(lhs, rhs, exact),
+            }
+        }
+    }
+}
+
+/// Pushes the element pairs of `lhs` and `rhs`, which must be of equal length, such that the
+/// first pair is compared first
+fn push_pairs(stack: &mut Vec<(OpaqueTerm, OpaqueTerm)>, lhs: &[OpaqueTerm], rhs: &[OpaqueTerm]) {
+    stack.extend(lhs.iter().zip(rhs.iter()).rev().map(|(x, y)| (*x, *y)));
+}
+
+/// Maps are ordered first by size, then by their keys in map key order, and finally by their
+/// values, in the order of their keys
+fn compare_maps(lhs: &Map, rhs: &Map, stack: &mut Vec<(OpaqueTerm, OpaqueTerm)>) -> Ordering {
+    let result = lhs.size().cmp(&rhs.size());
+    if result != Ordering::Equal {
+        return result;
+    }
+
+    // Map keys are stored sorted in map key order, and are always compared in that order,
+    // regardless of the current mode
+    for (x, y) in lhs.keys().iter().zip(rhs.keys().iter()) {
+        match cmp_terms_exact((*x).into(), (*y).into()) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+
+    // The values are compared in key order, in the current mode, once the keys are known to be
+    // equal
+    push_pairs(stack, lhs.values(), rhs.values());
+    Ordering::Equal
+}
+
+fn compare_numbers(lhs: Term, rhs: Term, exact: bool) -> Ordering {
+    match (lhs, rhs) {
+        (Term::Int(x), Term::Int(y)) => x.cmp(&y),
+        (Term::Int(x), Term::BigInt(y)) => match y.to_i64() {
+            Some(y) => x.cmp(&y),
+            None if y.sign() == Sign::Minus => Ordering::Greater,
+            None => Ordering::Less,
+        },
+        (Term::BigInt(x), Term::Int(y)) => {
+            compare_numbers(Term::Int(y), Term::BigInt(x), exact).reverse()
+        }
+        (Term::BigInt(x), Term::BigInt(y)) => (&**x).cmp(&**y),
+        (Term::Float(x), Term::Float(y)) => x.partial_cmp(&y).unwrap(),
+        // In map key order, all integers are ordered before all floats
+        (Term::Float(_), _) if exact => Ordering::Greater,
+        (_, Term::Float(_)) if exact => Ordering::Less,
+        (Term::Float(x), Term::Int(y)) => x.partial_cmp(&y).unwrap(),
+        (Term::Float(x), Term::BigInt(y)) => x.partial_cmp(&**y).unwrap(),
+        (lhs, Term::Float(y)) => compare_numbers(Term::Float(y), lhs, exact).reverse(),
+        _ => unreachable!(),
+    }
+}
+
+fn bitstring<'a>(term: &'a Term) -> Option<&'a dyn Bitstring> {
+    match term {
+        Term::HeapBinary(bin) => Some(&**bin),
+        Term::RcBinary(bin) => Some(&**bin),
+        Term::RefBinary(slice) => Some(&**slice),
+        Term::ConstantBinary(bin) => Some(*bin),
+        _ => None,
+    }
+}
+
+/// Bitstrings are compared bit-wise, and if one is a prefix of the other, the shorter one is
+/// ordered first
+fn compare_bitstrings(lhs: &dyn Bitstring, rhs: &dyn Bitstring) -> Ordering {
+    let lhs_bits = lhs.bit_size();
+    let rhs_bits = rhs.bit_size();
+    let common = lhs_bits.min(rhs_bits);
+    let full_bytes = common / 8;
+    let trailing_bits = (common % 8) as u32;
+
+    let mut lhs_bytes = lhs.bytes();
+    let mut rhs_bytes = rhs.bytes();
+    for _ in 0..full_bytes {
+        let x = lhs_bytes.next().unwrap();
+        let y = rhs_bytes.next().unwrap();
+        match x.cmp(&y) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    if trailing_bits > 0 {
+        let shift = 8 - trailing_bits;
+        let x = lhs_bytes.next().unwrap() >> shift;
+        let y = rhs_bytes.next().unwrap() >> shift;
+        match x.cmp(&y) {
+            Ordering::Equal => (),
+            other => return other,
+        }
+    }
+    lhs_bits.cmp(&rhs_bits)
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::etf;
+
+    #[test]
+    fn term_order_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+        let decode = |bytes: &[u8]| etf::decode(bytes, &heap).unwrap().0;
+
+        // number < atom < nil < list < bitstring
+        let list = decode(&[131, 107, 0, 1, 1]);
+        let binary = decode(&[131, 109, 0, 0, 0, 1, 1]);
+        let ordered = [Term::Int(1), Term::Atom(atoms::Ok), Term::Nil, list, binary];
+        for window in ordered.windows(2) {
+            assert_eq!(
+                cmp_terms(window[0].clone(), window[1].clone()),
+                Ordering::Less
+            );
+            assert_eq!(
+                cmp_terms(window[1].clone(), window[0].clone()),
+                Ordering::Greater
+            );
+        }
+
+        // Booleans are ordered as atoms
+        assert_eq!(
+            cmp_terms(Term::Bool(false), Term::Atom(atoms::Ok)),
+            Ordering::Less
+        );
+
+        // Integers and floats are equal by value, except in map key order
+        assert_eq!(
+            cmp_terms(Term::Int(1), Term::Float(1.0.into())),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp_terms(Term::Int(2), Term::Float(1.5.into())),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp_terms_exact(Term::Int(1), Term::Float(1.0.into())),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp_terms_exact(Term::Int(2), Term::Float(1.5.into())),
+            Ordering::Less
+        );
+
+        // {1, 2.0} == {1.0, 2}
+        let a = decode(&[131, 104, 2, 97, 1, 70, 64, 0, 0, 0, 0, 0, 0, 0]);
+        let b = decode(&[131, 104, 2, 70, 63, 240, 0, 0, 0, 0, 0, 0, 97, 2]);
+        assert_eq!(cmp_terms(a.clone(), b.clone()), Ordering::Equal);
+        assert_ne!(cmp_terms_exact(a, b), Ordering::Equal);
+
+        // #{1 => a} < #{1.0 => a}, since keys are always compared in map key order
+        let a = decode(&[131, 116, 0, 0, 0, 1, 97, 1, 119, 1, b'a']);
+        let b = decode(&[
+            131, 116, 0, 0, 0, 1, 70, 63, 240, 0, 0, 0, 0, 0, 0, 119, 1, b'a',
+        ]);
+        assert_eq!(cmp_terms(a, b), Ordering::Less);
+
+        // <<1>> < <<1:1>>, and <<1>> < <<1, 0:1>>
+        let a = decode(&[131, 77, 0, 0, 0, 1, 1, 128]);
+        let b = decode(&[131, 109, 0, 0, 0, 1, 1]);
+        let c = decode(&[131, 77, 0, 0, 0, 2, 1, 1, 0]);
+        assert_eq!(cmp_terms(a.clone(), b.clone()), Ordering::Greater);
+        assert_eq!(cmp_terms(b, c), Ordering::Less);
+    }
+}
@@ -56,6 +56,23 @@ impl Default for DriverFlags {
     }
 }
 
+bitflags::bitflags! {
+    /// These flags are set by a driver instance on its port, see `Port::set_control_flags`, and control how
+    /// data returned from `Driver::control` is handed back to the caller of `erlang:port_control/3`.
+    pub struct PortControlFlags: u32 {
+        /// Data is returned as a list of bytes
+        const DEFAULT = 0;
+        /// Data is returned as a binary
+        const BINARY = 1 << 0;
+    }
+}
+impl Default for PortControlFlags {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// An opaque pointer to a platform/operating-system specific event object.
 ///
 /// On Unix systems, the object corresponds to that expected by the functions select/poll,
@@ -197,22 +214,16 @@ pub trait Driver: Any + Send + Sync {
     ///
     /// If the driver wants to return data, it is to return it in rbuf. When control is called, *rbuf points to a default buffer
     /// of rlen bytes, which can be used to return data. Data is returned differently depending on the port control flags
-    /// (those that are set with erl_driver:set_port_control_flags).
+    /// (those that are set with `Port::set_control_flags`).
     ///
-    /// If the flag is set to PORT_CONTROL_FLAG_BINARY, a binary is returned. Small binaries can be returned by writing the raw data
-    /// into the default buffer. A binary can also be returned by setting *rbuf to point to a binary allocated with
-    /// erl_driver:driver_alloc_binary. This binary is freed automatically after control has returned.
-    /// The driver can retain the binary for read only access with erl_driver:driver_binary_inc_refc to be freed later with
-    /// erl_driver:driver_free_binary. It is never allowed to change the binary after control has returned.
-    /// If *rbuf is set to NULL, an empty list is returned.
-    ///
-    /// If the flag is set to 0, data is returned as a list of integers.
-    /// Either use the default buffer or set *rbuf to point to a larger buffer allocated with erl_driver:driver_alloc.
-    /// The buffer is freed automatically after control has returned.
+    /// If the flag is set to `PortControlFlags::BINARY`, a binary is returned, otherwise data is returned as a list of integers.
+    /// Either use the default buffer or set *rbuf to point to a larger buffer allocated with `drivers::alloc`.
+    /// The buffer is freed automatically after control has returned. If *rbuf is set to NULL, an empty list is returned.
     ///
     /// Using binaries is faster if more than a few bytes are returned.
     ///
-    /// The return value is the number of bytes returned in *rbuf.
+    /// The return value is the number of bytes returned in *rbuf. If it is larger than the buffer in *rbuf, `erlang:port_control/3`
+    /// raises badarg, so returning `usize::MAX` can be used to signal an error.
     fn control(&self, command: u32, buf: &[u8], rbuf: *mut *mut u8, rlen: usize) -> usize;
 
     /// Called any time after the driver's timer reaches 0. The timer is activated with erl_driver:driver_set_timer.
@@ -250,7 +261,7 @@ pub trait Driver: Any + Send + Sync {
     ///
     /// `rbuf` points to a return buffer, `rlen` bytes long. The return data is to be a valid Erlang term in the external (binary) format.
     /// This is converted to an Erlang term and returned by erlang:port_call/3 to the caller.
-    /// If more space than `rlen` bytes is needed to return data, `rbuf` can be set to memory allocated with erl_driver:driver_alloc.
+    /// If more space than `rlen` bytes is needed to return data, `rbuf` can be set to memory allocated with `drivers::alloc`.
     /// This memory is freed automatically after call has returned.
     ///
     /// The `Ok` return value is the number of bytes returned in `rbuf`.
@@ -292,6 +303,51 @@ pub trait Driver: Any + Send + Sync {
     fn stop_select(&self, event: DriverEvent, reserved: *mut ());
 }
 
+/// Allocates a buffer of `size` bytes which a driver can hand over to the runtime, e.g. by returning it from
+/// `Driver::control` or `Driver::call`, in which case the runtime frees it once the data has been consumed.
+///
+/// Returns a null pointer if the allocation fails. A buffer which is not handed over must be freed with [`free`].
+pub fn alloc(size: usize) -> *mut u8 {
+    use alloc::alloc::Layout;
+
+    // The size is stored in a header preceding the buffer, so that it can be freed (and bounds-checked) by the runtime
+    let Some(total) = size.checked_add(BUFFER_HEADER) else { return core::ptr::null_mut(); };
+    let Ok(layout) = Layout::from_size_align(total, BUFFER_HEADER) else { return core::ptr::null_mut(); };
+    unsafe {
+        let ptr = alloc::alloc::alloc(layout);
+        if ptr.is_null() {
+            return ptr;
+        }
+        ptr.cast::<usize>().write(size);
+        ptr.add(BUFFER_HEADER)
+    }
+}
+
+/// Frees a buffer allocated with [`alloc`]
+///
+/// # SAFETY
+///
+/// `ptr` must have been returned by [`alloc`], and must not have been freed already.
+pub unsafe fn free(ptr: *mut u8) {
+    use alloc::alloc::Layout;
+
+    let base = ptr.sub(BUFFER_HEADER);
+    let size = buffer_size(ptr);
+    let layout = Layout::from_size_align_unchecked(size + BUFFER_HEADER, BUFFER_HEADER);
+    alloc::alloc::dealloc(base, layout);
+}
+
+/// Returns the size of a buffer allocated with [`alloc`]
+///
+/// # SAFETY
+///
+/// `ptr` must have been returned by [`alloc`], and must not have been freed already.
+pub(crate) unsafe fn buffer_size(ptr: *const u8) -> usize {
+    ptr.sub(BUFFER_HEADER).cast::<usize>().read()
+}
+
+const BUFFER_HEADER: usize = core::mem::size_of::<usize>();
+
 pub type DriverInitFn = unsafe extern "C" fn() -> Result<Box<dyn LoadableDriver>, DriverError>;
 
 #[cfg(feature = "std")]
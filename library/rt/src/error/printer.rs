@@ -5,6 +5,7 @@ use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 use crate::backtrace::Symbol;
 use crate::error::ExceptionClass;
 use crate::process::ProcessLock;
+use crate::services::opaque_ids;
 use crate::term::*;
 
 pub fn print(process: &mut ProcessLock) -> io::Result<()> {
@@ -112,7 +113,10 @@ pub fn print(process: &mut ProcessLock) -> io::Result<()> {
 
     writer.set_color(&bold)?;
 
-    write!(writer, "\nProcess ({}) ", process.pid())?;
+    match opaque_ids::opaque_pid(&process.pid()) {
+        Some(id) => write!(writer, "\nProcess ({}) ", id)?,
+        None => write!(writer, "\nProcess ({}) ", process.pid())?,
+    }
 
     let kind_suffix = match process.exception_info.class().unwrap() {
         ExceptionClass::Error => "raised an error.",
@@ -123,7 +127,10 @@ pub fn print(process: &mut ProcessLock) -> io::Result<()> {
     let value: Term = process.exception_info.value.into();
     writeln!(writer, "{}", kind_suffix)?;
     writer.set_color(&yellow)?;
-    writeln!(writer, "  {}\n", &value)?;
+    let options = format::FormatOptions::print()
+        .with_column(2)
+        .with_opaque_ids(true);
+    writeln!(writer, "  {}\n", format::format_term(value, options))?;
 
     writer.reset()?;
 
@@ -0,0 +1,980 @@
+use alloc::alloc::{AllocError, Layout};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::str;
+
+use firefly_alloc::fragment::HeapFragment;
+use firefly_alloc::heap::{EmptyHeap, Heap};
+use firefly_binary::{BinaryFlags, Encoding};
+use firefly_number::Sign;
+
+use smallvec::SmallVec;
+
+use crate::function::{find_symbol, ModuleFunctionArity};
+use crate::gc::Gc;
+use crate::process::ProcessId;
+use crate::services::distribution::{self, Node};
+use crate::services::registry;
+use crate::term::atom::MAX_ATOM_LENGTH;
+use crate::term::*;
+
+use super::*;
+
+/// The error produced when a binary cannot be decoded as a term in the external term format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before a complete term could be decoded
+    UnexpectedEof,
+    /// The input does not begin with the external term format version byte
+    InvalidVersion,
+    /// An unknown tag was encountered, or a known tag was found where it is not permitted
+    InvalidTag(u8),
+    /// The input is malformed, e.g. an atom which is not valid UTF-8, a map with duplicate keys,
+    /// or a port belonging to the local node which no longer exists
+    Invalid,
+    /// A pid, port or reference is malformed, i.e. its node is not a valid node name, or its
+    /// creation or id is out of range for the format it was encoded in
+    InvalidIdentifier,
+    /// The input contains a local fun, which cannot be decoded as there is no fun table
+    UnsupportedFun,
+    /// The input contains an exported function which is not present in the symbol table
+    UndefinedFunction,
+    /// The input exceeds an implementation limit, e.g. a map with too many keys, or one of the
+    /// limits given in [`DecodeOptions`]
+    SystemLimit,
+    /// The input would create a new atom or resolve a function, but the `safe` option was given
+    Unsafe,
+    /// There is insufficient space on the target heap to hold the decoded term
+    AllocError,
+}
+impl From<AllocError> for DecodeError {
+    #[inline]
+    fn from(_err: AllocError) -> Self {
+        Self::AllocError
+    }
+}
+impl From<AtomError> for DecodeError {
+    #[inline]
+    fn from(err: AtomError) -> Self {
+        match err {
+            AtomError::InvalidLength(_) | AtomError::TableFull(_) => Self::SystemLimit,
+            _ => Self::Invalid,
+        }
+    }
+}
+
+/// The options which control decoding, see `binary_to_term/2`
+#[derive(Debug, Copy, Clone)]
+pub struct DecodeOptions {
+    /// Corresponds to the `safe` option of `binary_to_term/2`
+    ///
+    /// When set, decoding fails rather than creating atoms which do not already exist, or
+    /// resolving exported functions, so that untrusted input cannot be used to exhaust the
+    /// atom table or obtain references to arbitrary functions.
+    pub safe: bool,
+    /// The maximum depth to which terms may be nested
+    pub max_depth: usize,
+    /// The maximum number of bytes which may be allocated for the decoded term, including
+    /// any reference-counted binaries allocated outside of the heap
+    pub max_size: usize,
+}
+impl DecodeOptions {
+    /// The default limit on the nesting depth of decoded terms
+    pub const MAX_DEPTH: usize = 2048;
+    /// The default limit on the total allocation made for a decoded term
+    pub const MAX_SIZE: usize = 1 << 30;
+}
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            safe: false,
+            max_depth: Self::MAX_DEPTH,
+            max_size: Self::MAX_SIZE,
+        }
+    }
+}
+
+/// Decodes the term encoded at the start of `input` onto `heap`.
+///
+/// Returns the decoded term, along with the number of bytes of `input` that were consumed.
+pub fn decode<H: ?Sized + Heap>(input: &[u8], heap: &H) -> Result<(Term, usize), DecodeError> {
+    let decoder = Decoder::new(input)?;
+    let term = decoder.decode(heap)?;
+    Ok((term, decoder.used()))
+}
+
+/// A decoder for a single term in the external term format.
+///
+/// Constructing a decoder validates the input, and calculates the layout required to hold the
+/// decoded term, so that callers can ensure sufficient space is available before decoding.
+///
+/// Compressed terms are decompressed when the decoder is constructed. The size of the
+/// decompressed term is known up front, and is subject to the `max_size` limit, so the
+/// decompressed data is written directly to a buffer of exactly that size.
+pub struct Decoder<'a> {
+    /// The encoded term, including the version byte, if any
+    data: Cow<'a, [u8]>,
+    /// The offset in `data` at which the encoded term starts, i.e. after the version byte
+    start: usize,
+    /// The offset in `data` at which the encoded term ends
+    end: usize,
+    layout: Layout,
+    /// The number of bytes of the original input occupied by the term
+    used: usize,
+    /// The atoms referenced by `ATOM_CACHE_REF`, see [`Decoder::with_atom_cache`]
+    atom_cache: &'a [Atom],
+}
+impl<'a> Decoder<'a> {
+    /// Validates the term encoded at the start of `input`, returning a decoder for it
+    #[inline]
+    pub fn new(input: &'a [u8]) -> Result<Self, DecodeError> {
+        Self::with_options(input, DecodeOptions::default())
+    }
+
+    /// Validates the term encoded at the start of `input` according to `options`, returning a
+    /// decoder for it
+    pub fn with_options(input: &'a [u8], options: DecodeOptions) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(input);
+        if reader.u8()? != VERSION {
+            return Err(DecodeError::InvalidVersion);
+        }
+        let (data, used) = match input.get(1).copied() {
+            Some(COMPRESSED) => {
+                reader.u8()?;
+                let size = reader.u32()? as usize;
+                if size > options.max_size {
+                    return Err(DecodeError::SystemLimit);
+                }
+                let (data, consumed) = zlib::inflate(&input[reader.pos..], size)?;
+                (Cow::Owned(data), reader.pos + consumed)
+            }
+            _ => (Cow::Borrowed(input), 0),
+        };
+
+        let (end, layout) = Self::scan(&data, 1, &[], options)?;
+        // A compressed term must occupy the entirety of the decompressed data
+        let used = match data {
+            Cow::Borrowed(_) => end,
+            Cow::Owned(ref data) if end == data.len() => used,
+            Cow::Owned(_) => return Err(DecodeError::Invalid),
+        };
+        Ok(Self {
+            data,
+            start: 1,
+            end,
+            layout,
+            used,
+            atom_cache: &[],
+        })
+    }
+
+    /// Validates a term which follows a distribution header, returning a decoder for it
+    ///
+    /// Such terms are not prefixed with the version byte, and may contain `ATOM_CACHE_REF`s, each
+    /// of which is the index of an atom in `atom_cache`, i.e. the atoms of the distribution header
+    /// in the order they appear in it. Resolving these is a lookup in `atom_cache` rather than in
+    /// the atom table, which is what makes the atom cache worth having for small messages.
+    pub fn with_atom_cache(
+        input: &'a [u8],
+        atom_cache: &'a [Atom],
+        options: DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let (end, layout) = Self::scan(input, 0, atom_cache, options)?;
+        Ok(Self {
+            data: Cow::Borrowed(input),
+            start: 0,
+            end,
+            layout,
+            used: end,
+            atom_cache,
+        })
+    }
+
+    /// Scans the term starting at `start` in `data`, returning the offset at which it ends, and
+    /// the layout required to hold it
+    fn scan(
+        data: &[u8],
+        start: usize,
+        atom_cache: &[Atom],
+        options: DecodeOptions,
+    ) -> Result<(usize, Layout), DecodeError> {
+        let mut scanner = Scanner {
+            reader: Reader::with_atom_cache(data, atom_cache),
+            layout: LayoutBuilder::new(),
+            options,
+            depth: 0,
+            off_heap: 0,
+        };
+        scanner.reader.pos = start;
+        scanner.scan()?;
+        let layout = scanner.layout.finish();
+        if layout.size().saturating_add(scanner.off_heap) > options.max_size {
+            return Err(DecodeError::SystemLimit);
+        }
+        Ok((scanner.reader.pos, layout))
+    }
+
+    /// Returns the layout of the heap space required to hold the decoded term
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Returns the number of bytes of the input occupied by the encoded term
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Decodes the term onto `heap`, which must have at least `self.layout().size()` bytes available
+    pub fn decode<H: ?Sized + Heap>(&self, heap: &H) -> Result<Term, DecodeError> {
+        if heap.heap_available() < self.layout.size() {
+            return Err(DecodeError::AllocError);
+        }
+        let mut builder = Builder {
+            reader: Reader::with_atom_cache(&self.data[..self.end], self.atom_cache),
+            heap,
+            local_node: None,
+            last_node: None,
+        };
+        // Skip the version byte, if any, which was validated when scanning
+        builder.reader.pos = self.start;
+        builder.build()
+    }
+
+    /// Decodes the term into a new heap fragment
+    pub fn decode_fragment(&self) -> Result<TermFragment, DecodeError> {
+        if self.layout.size() == 0 {
+            let term = self.decode(&EmptyHeap)?;
+            return Ok(TermFragment {
+                term: term.into(),
+                fragment: None,
+            });
+        }
+
+        // The fragment is owned by the result up front, so that it is freed if decoding fails
+        let mut result = TermFragment {
+            term: OpaqueTerm::NIL,
+            fragment: Some(HeapFragment::new(self.layout, None)?),
+        };
+        let heap = unsafe { result.fragment.unwrap().as_ref() };
+        result.term = self.decode(heap)?.into();
+        Ok(result)
+    }
+}
+
+/// The largest number of bytes a valid atom name encoded as Latin-1 occupies when converted to
+/// UTF-8, as each character outside of ASCII takes two bytes
+const MAX_LATIN1_ATOM_UTF8: usize = MAX_ATOM_LENGTH * 2;
+
+/// The name of an atom as encoded in the input
+#[derive(Copy, Clone)]
+enum AtomName<'a> {
+    Utf8(&'a str),
+    Latin1(&'a [u8]),
+    /// An `ATOM_CACHE_REF`, which refers to an atom which has already been resolved
+    Cached(Atom),
+}
+impl<'a> AtomName<'a> {
+    /// Calls `f` with this name as a string, converting it from Latin-1 if necessary
+    ///
+    /// Names which are valid atoms are converted on the stack, only longer ones, which are about
+    /// to be rejected anyway, are converted on the heap.
+    fn with_str<R, F: FnOnce(&str) -> R>(self, f: F) -> R {
+        match self {
+            Self::Utf8(name) => f(name),
+            Self::Cached(atom) => f(atom.as_str()),
+            Self::Latin1(name) => match str::from_utf8(name) {
+                Ok(name) if name.is_ascii() => f(name),
+                _ if name.len() <= MAX_ATOM_LENGTH => {
+                    let mut buf = [0; MAX_LATIN1_ATOM_UTF8];
+                    let mut len = 0;
+                    for c in name.iter().map(|b| *b as char) {
+                        len += c.encode_utf8(&mut buf[len..]).len();
+                    }
+                    // SAFETY: `buf` was filled by encoding characters as UTF-8
+                    f(unsafe { str::from_utf8_unchecked(&buf[..len]) })
+                }
+                _ => {
+                    let name = name.iter().map(|b| *b as char).collect::<String>();
+                    f(name.as_str())
+                }
+            },
+        }
+    }
+
+    /// Returns true if this name corresponds to an atom which already exists
+    fn exists(self) -> bool {
+        match self {
+            Self::Cached(_) => true,
+            name => name.with_str(|name| Atom::try_from_str_existing(name).is_ok()),
+        }
+    }
+
+    /// Returns true if this name is a valid node name, e.g. `name@host`
+    fn is_node_name(self) -> bool {
+        self.with_str(distribution::is_node_name)
+    }
+
+    /// Resolves this name to an atom, creating it if it does not exist yet
+    ///
+    /// Nearly every atom received already exists, so this looks it up with the atom table only
+    /// locked for reading, and only falls back to inserting it, which locks the table for writing,
+    /// when that fails. Whether creating atoms is permitted was decided when scanning.
+    fn to_atom(self) -> Result<Atom, DecodeError> {
+        match self {
+            Self::Cached(atom) => Ok(atom),
+            name => name.with_str(|name| match Atom::try_from_str_existing(name) {
+                Ok(atom) => Ok(atom),
+                Err(AtomError::NonExistent) => Ok(Atom::try_from(name)?),
+                Err(err) => Err(err.into()),
+            }),
+        }
+    }
+}
+
+/// The largest pid number which can be encoded using `PID_EXT`
+const MAX_PID_EXT_NUMBER: u64 = (1 << 15) - 1;
+/// The largest pid serial which can be encoded using `PID_EXT`
+const MAX_PID_EXT_SERIAL: u32 = (1 << 13) - 1;
+/// The largest port number which can be encoded using `PORT_EXT`
+const MAX_PORT_EXT_NUMBER: u64 = (1 << 28) - 1;
+/// The largest value of the first word of a reference encoded using `REFERENCE_EXT`
+const MAX_REFERENCE_EXT_NUMBER: u32 = (1 << 18) - 1;
+
+/// The components of an encoded pid or port
+struct RawId<'a> {
+    node: AtomName<'a>,
+    id: u64,
+    serial: u32,
+    creation: u32,
+}
+
+/// The components of an encoded reference
+struct RawReference<'a> {
+    node: AtomName<'a>,
+    creation: u32,
+    words: [u32; MAX_REFERENCE_WORDS],
+    len: usize,
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// The atoms referenced by `ATOM_CACHE_REF`, which is invalid when this is empty
+    atom_cache: &'a [Atom],
+}
+impl<'a> Reader<'a> {
+    #[inline]
+    fn new(input: &'a [u8]) -> Self {
+        Self::with_atom_cache(input, &[])
+    }
+
+    #[inline]
+    fn with_atom_cache(input: &'a [u8], atom_cache: &'a [Atom]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            atom_cache,
+        }
+    }
+
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.input.len() - self.pos
+    }
+
+    /// Ensures that the input contains enough data for `n` more terms.
+    ///
+    /// Every term occupies at least one byte, so this is used to reject lengths which could
+    /// not possibly be valid before we use them to calculate sizes.
+    fn ensure_terms(&self, n: usize) -> Result<(), DecodeError> {
+        if self.remaining() < n {
+            Err(DecodeError::UnexpectedEof)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.remaining() < len {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let bytes = &self.input[self.pos..(self.pos + len)];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    #[inline]
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn atom_name(&mut self) -> Result<AtomName<'a>, DecodeError> {
+        let tag = self.u8()?;
+        self.atom_name_with_tag(tag)
+    }
+
+    fn atom_name_with_tag(&mut self, tag: u8) -> Result<AtomName<'a>, DecodeError> {
+        let (len, utf8) = match tag {
+            ATOM_EXT => (self.u16()? as usize, false),
+            SMALL_ATOM_EXT => (self.u8()? as usize, false),
+            ATOM_UTF8_EXT => (self.u16()? as usize, true),
+            SMALL_ATOM_UTF8_EXT => (self.u8()? as usize, true),
+            ATOM_CACHE_REF if !self.atom_cache.is_empty() => {
+                let index = self.u8()? as usize;
+                let atom = self.atom_cache.get(index).ok_or(DecodeError::Invalid)?;
+                return Ok(AtomName::Cached(*atom));
+            }
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        };
+        let bytes = self.bytes(len)?;
+        if utf8 {
+            str::from_utf8(bytes)
+                .map(AtomName::Utf8)
+                .map_err(|_| DecodeError::Invalid)
+        } else {
+            Ok(AtomName::Latin1(bytes))
+        }
+    }
+
+    /// Reads the sign and little-endian digits of a big integer
+    fn big(&mut self, tag: u8) -> Result<(bool, &'a [u8]), DecodeError> {
+        let len = match tag {
+            SMALL_BIG_EXT => self.u8()? as usize,
+            _ => self.u32()? as usize,
+        };
+        let negative = match self.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::Invalid),
+        };
+        Ok((negative, self.bytes(len)?))
+    }
+
+    fn float(&mut self, tag: u8) -> Result<f64, DecodeError> {
+        let f = match tag {
+            NEW_FLOAT_EXT => f64::from_bits(self.u64()?),
+            _ => {
+                let bytes = self.bytes(FLOAT_EXT_SIZE)?;
+                let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                str::from_utf8(&bytes[..len])
+                    .ok()
+                    .and_then(|s| s.trim().parse::<f64>().ok())
+                    .ok_or(DecodeError::Invalid)?
+            }
+        };
+        if f.is_finite() {
+            Ok(f)
+        } else {
+            Err(DecodeError::Invalid)
+        }
+    }
+
+    /// Reads the node name of an identifier, which must be a valid node name
+    fn node(&mut self) -> Result<AtomName<'a>, DecodeError> {
+        let node = self.atom_name()?;
+        if node.is_node_name() {
+            Ok(node)
+        } else {
+            Err(DecodeError::InvalidIdentifier)
+        }
+    }
+
+    /// Reads the creation of an identifier in one of the older formats, in which creations were
+    /// only two bits, despite being encoded as a byte
+    fn tiny_creation(&mut self) -> Result<u32, DecodeError> {
+        match self.u8()? {
+            creation @ 0..=3 => Ok(creation as u32),
+            _ => Err(DecodeError::InvalidIdentifier),
+        }
+    }
+
+    fn pid(&mut self, tag: u8) -> Result<RawId<'a>, DecodeError> {
+        let node = self.node()?;
+        let id = self.u32()? as u64;
+        let serial = self.u32()?;
+        let creation = match tag {
+            PID_EXT => self.tiny_creation()?,
+            _ => self.u32()?,
+        };
+        if tag == PID_EXT && (id > MAX_PID_EXT_NUMBER || serial > MAX_PID_EXT_SERIAL) {
+            return Err(DecodeError::InvalidIdentifier);
+        }
+        Ok(RawId {
+            node,
+            id,
+            serial,
+            creation,
+        })
+    }
+
+    fn port(&mut self, tag: u8) -> Result<RawId<'a>, DecodeError> {
+        let node = self.node()?;
+        let id = match tag {
+            V4_PORT_EXT => self.u64()?,
+            _ => self.u32()? as u64,
+        };
+        let creation = match tag {
+            PORT_EXT => self.tiny_creation()?,
+            _ => self.u32()?,
+        };
+        if tag == PORT_EXT && id > MAX_PORT_EXT_NUMBER {
+            return Err(DecodeError::InvalidIdentifier);
+        }
+        Ok(RawId {
+            node,
+            id,
+            serial: 0,
+            creation,
+        })
+    }
+
+    fn reference(&mut self, tag: u8) -> Result<RawReference<'a>, DecodeError> {
+        let len = match tag {
+            REFERENCE_EXT => 1,
+            _ => self.u16()? as usize,
+        };
+        if len == 0 || len > MAX_REFERENCE_WORDS {
+            return Err(DecodeError::InvalidIdentifier);
+        }
+        let node = self.node()?;
+        let creation = match tag {
+            NEWER_REFERENCE_EXT => self.u32()?,
+            // The creation follows the id in the oldest format
+            NEW_REFERENCE_EXT => self.tiny_creation()?,
+            _ => 0,
+        };
+        let mut words = [0; MAX_REFERENCE_WORDS];
+        for word in words.iter_mut().take(len) {
+            *word = self.u32()?;
+        }
+        let creation = match tag {
+            REFERENCE_EXT => self.tiny_creation()?,
+            _ => creation,
+        };
+        if tag == REFERENCE_EXT && words[0] > MAX_REFERENCE_EXT_NUMBER {
+            return Err(DecodeError::InvalidIdentifier);
+        }
+        Ok(RawReference {
+            node,
+            creation,
+            words,
+            len,
+        })
+    }
+
+    fn export(&mut self) -> Result<(AtomName<'a>, AtomName<'a>, u8), DecodeError> {
+        let module = self.atom_name()?;
+        let function = self.atom_name()?;
+        match self.u8()? {
+            SMALL_INTEGER_EXT => Ok((module, function, self.u8()?)),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+/// Returns the value of the big integer with the given sign and little-endian digits, if it
+/// can be represented as a small integer.
+///
+/// Big integers are always normalized when decoded, just as they are when produced by arithmetic.
+fn small_big(negative: bool, digits: &[u8]) -> Option<i64> {
+    let significant = digits.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    if significant > 8 {
+        return None;
+    }
+    let mut bytes = [0; 8];
+    bytes[..significant].copy_from_slice(&digits[..significant]);
+    let magnitude = u64::from_le_bytes(bytes);
+    let value = if negative {
+        if magnitude > (i64::MAX as u64) + 1 {
+            return None;
+        }
+        (magnitude as i64).wrapping_neg()
+    } else {
+        i64::try_from(magnitude).ok()?
+    };
+    Some(value).filter(|i| OpaqueTerm::is_small_integer(*i))
+}
+
+/// The first decoding pass, which validates the input and calculates the required heap layout
+struct Scanner<'a> {
+    reader: Reader<'a>,
+    layout: LayoutBuilder,
+    options: DecodeOptions,
+    /// The nesting depth of the term currently being scanned
+    depth: usize,
+    /// The number of bytes required for binaries allocated outside of the heap
+    off_heap: usize,
+}
+impl<'a> Scanner<'a> {
+    fn scan(&mut self) -> Result<(), DecodeError> {
+        self.depth += 1;
+        if self.depth > self.options.max_depth {
+            return Err(DecodeError::SystemLimit);
+        }
+        self.scan_term()?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Fails if decoding `name` would create a new atom when the `safe` option is set
+    fn check_atom(&self, name: AtomName<'_>) -> Result<(), DecodeError> {
+        if self.options.safe && !name.exists() {
+            Err(DecodeError::Unsafe)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn scan_term(&mut self) -> Result<(), DecodeError> {
+        let tag = self.reader.u8()?;
+        match tag {
+            SMALL_INTEGER_EXT => {
+                self.reader.u8()?;
+            }
+            INTEGER_EXT => {
+                self.reader.u32()?;
+            }
+            SMALL_BIG_EXT | LARGE_BIG_EXT => {
+                let (negative, digits) = self.reader.big(tag)?;
+                if small_big(negative, digits).is_none() {
+                    self.layout.build_bigint();
+                }
+            }
+            NEW_FLOAT_EXT | FLOAT_EXT => {
+                self.reader.float(tag)?;
+            }
+            ATOM_EXT | SMALL_ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_UTF8_EXT | ATOM_CACHE_REF => {
+                let name = self.reader.atom_name_with_tag(tag)?;
+                self.check_atom(name)?;
+            }
+            SMALL_TUPLE_EXT | LARGE_TUPLE_EXT => {
+                let arity = match tag {
+                    SMALL_TUPLE_EXT => self.reader.u8()? as usize,
+                    _ => self.reader.u32()? as usize,
+                };
+                self.reader.ensure_terms(arity)?;
+                self.layout.build_tuple(arity);
+                for _ in 0..arity {
+                    self.scan()?;
+                }
+            }
+            NIL_EXT => (),
+            STRING_EXT => {
+                let len = self.reader.u16()? as usize;
+                self.reader.bytes(len)?;
+                self.layout.build_list(len);
+            }
+            LIST_EXT => {
+                let len = self.reader.u32()? as usize;
+                self.reader.ensure_terms(len + 1)?;
+                for _ in 0..len {
+                    self.scan()?;
+                }
+                // The tail
+                self.scan()?;
+                self.layout.build_list(len);
+            }
+            BINARY_EXT => {
+                let len = self.reader.u32()? as usize;
+                self.reader.bytes(len)?;
+                self.scan_binary(len);
+            }
+            BIT_BINARY_EXT => {
+                let len = self.reader.u32()? as usize;
+                let bits = self.reader.u8()?;
+                let valid = match len {
+                    0 => bits == 0,
+                    _ => bits > 0 && bits <= 8,
+                };
+                if !valid {
+                    return Err(DecodeError::Invalid);
+                }
+                self.reader.bytes(len)?;
+                self.scan_binary(len);
+            }
+            MAP_EXT => {
+                let size = self.reader.u32()? as usize;
+                if size > SMALL_MAP_LIMIT {
+                    return Err(DecodeError::SystemLimit);
+                }
+                for _ in 0..(size * 2) {
+                    self.scan()?;
+                }
+                self.layout.build_map(size);
+            }
+            PID_EXT | NEW_PID_EXT => {
+                let pid = self.reader.pid(tag)?;
+                self.check_atom(pid.node)?;
+                self.layout.build_pid();
+            }
+            PORT_EXT | NEW_PORT_EXT | V4_PORT_EXT => {
+                let port = self.reader.port(tag)?;
+                self.check_atom(port.node)?;
+            }
+            REFERENCE_EXT | NEW_REFERENCE_EXT | NEWER_REFERENCE_EXT => {
+                let reference = self.reader.reference(tag)?;
+                self.check_atom(reference.node)?;
+                self.layout.build_reference();
+            }
+            EXPORT_EXT => {
+                self.reader.export()?;
+                if self.options.safe {
+                    return Err(DecodeError::Unsafe);
+                }
+                self.layout.build_closure(0);
+            }
+            NEW_FUN_EXT | FUN_EXT => return Err(DecodeError::UnsupportedFun),
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        }
+        Ok(())
+    }
+
+    fn scan_binary(&mut self, len: usize) {
+        self.layout.build_binary(len);
+        if len > BinaryData::MAX_HEAP_BYTES {
+            self.off_heap = self.off_heap.saturating_add(len);
+        }
+    }
+}
+
+/// The second decoding pass, which constructs the term from previously validated input
+struct Builder<'a, 'h, H: ?Sized> {
+    reader: Reader<'a>,
+    heap: &'h H,
+    /// The current node, fetched on first use, as most terms do not require it
+    local_node: Option<Arc<Node>>,
+    /// The node most recently resolved by `resolve_node`, as identifiers from the same node tend
+    /// to appear together, e.g. in a list of pids
+    last_node: Option<(Atom, u32, Option<Arc<Node>>)>,
+}
+impl<'a, 'h, H: ?Sized + Heap> Builder<'a, 'h, H> {
+    fn build(&mut self) -> Result<Term, DecodeError> {
+        let tag = self.reader.u8()?;
+        match tag {
+            SMALL_INTEGER_EXT => Ok(Term::Int(self.reader.u8()? as i64)),
+            INTEGER_EXT => Ok(Term::Int(self.reader.u32()? as i32 as i64)),
+            SMALL_BIG_EXT | LARGE_BIG_EXT => {
+                let (negative, digits) = self.reader.big(tag)?;
+                match small_big(negative, digits) {
+                    Some(i) => Ok(Term::Int(i)),
+                    None => {
+                        let sign = if negative { Sign::Minus } else { Sign::Plus };
+                        let i = firefly_number::BigInt::from_bytes_le(sign, digits);
+                        Ok(Term::BigInt(Gc::new_in(BigInt::new(i), self.heap)?))
+                    }
+                }
+            }
+            NEW_FLOAT_EXT | FLOAT_EXT => Ok(Term::Float(self.reader.float(tag)?.into())),
+            ATOM_EXT | SMALL_ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_UTF8_EXT | ATOM_CACHE_REF => {
+                let atom = self.reader.atom_name_with_tag(tag)?.to_atom()?;
+                if atom.is_boolean() {
+                    Ok(Term::Bool(atom.as_boolean()))
+                } else {
+                    Ok(Term::Atom(atom))
+                }
+            }
+            SMALL_TUPLE_EXT | LARGE_TUPLE_EXT => {
+                let arity = match tag {
+                    SMALL_TUPLE_EXT => self.reader.u8()? as usize,
+                    _ => self.reader.u32()? as usize,
+                };
+                let mut tuple = Tuple::new_in(arity, self.heap)?;
+                for i in 0..arity {
+                    tuple.as_mut_slice()[i] = self.build()?.into();
+                }
+                Ok(Term::Tuple(tuple))
+            }
+            NIL_EXT => Ok(Term::Nil),
+            STRING_EXT => {
+                let len = self.reader.u16()? as usize;
+                let bytes = self.reader.bytes(len)?;
+                Ok(Cons::from_bytes(bytes, self.heap)?
+                    .map(Term::Cons)
+                    .unwrap_or(Term::Nil))
+            }
+            LIST_EXT => {
+                let len = self.reader.u32()? as usize;
+                let mut elements = Vec::with_capacity(len);
+                for _ in 0..len {
+                    elements.push(OpaqueTerm::from(self.build()?));
+                }
+                let tail: OpaqueTerm = self.build()?.into();
+                // Lists are constructed back to front
+                let mut builder = ListBuilder::new_improper(tail, self.heap);
+                for element in elements.iter().rev().copied() {
+                    unsafe {
+                        builder.push_unsafe(element)?;
+                    }
+                }
+                Ok(builder.finish().map(Term::Cons).unwrap_or(tail.into()))
+            }
+            BINARY_EXT => {
+                let len = self.reader.u32()? as usize;
+                let bytes = self.reader.bytes(len)?;
+                self.build_bitstring(bytes, 0)
+            }
+            BIT_BINARY_EXT => {
+                let len = self.reader.u32()? as usize;
+                let bits = self.reader.u8()?;
+                let bytes = self.reader.bytes(len)?;
+                self.build_bitstring(bytes, bits % 8)
+            }
+            MAP_EXT => {
+                let size = self.reader.u32()? as usize;
+                let mut pairs = SmallVec::<[(OpaqueTerm, OpaqueTerm); 8]>::with_capacity(size);
+                for _ in 0..size {
+                    let key = self.build()?.into();
+                    let value = self.build()?.into();
+                    pairs.push((key, value));
+                }
+                let map =
+                    SmallMap::from_iter(pairs.into_iter(), self.heap).map_err(|err| match err {
+                        MapError::AllocError(_) => DecodeError::AllocError,
+                        _ => DecodeError::SystemLimit,
+                    })?;
+                // Keys are sorted when the map is constructed, so duplicates will be adjacent
+                let has_duplicates = map.keys().windows(2).any(|keys| {
+                    let a: Term = keys[0].into();
+                    a.exact_eq(&keys[1].into())
+                });
+                if has_duplicates {
+                    return Err(DecodeError::Invalid);
+                }
+                Ok(Term::Map(map))
+            }
+            PID_EXT | NEW_PID_EXT => {
+                let raw = self.reader.pid(tag)?;
+                let pid = match self.resolve_node(raw.node, raw.creation)? {
+                    None => {
+                        let id = ProcessId::new(raw.id as u32, raw.serial)
+                            .map_err(|_| DecodeError::Invalid)?;
+                        Pid::new_local(id)
+                    }
+                    Some(node) => Pid::new_external(node, raw.id as usize, raw.serial as usize)
+                        .map_err(|_| DecodeError::Invalid)?,
+                };
+                Ok(Term::Pid(Gc::new_in(pid, self.heap)?))
+            }
+            PORT_EXT | NEW_PORT_EXT | V4_PORT_EXT => {
+                let raw = self.reader.port(tag)?;
+                let id = PortId::from_raw(raw.id);
+                match self.resolve_node(raw.node, raw.creation)? {
+                    None => registry::get_by_port_id(id)
+                        .map(Term::Port)
+                        .ok_or(DecodeError::Invalid),
+                    Some(node) => Ok(Term::Port(Port::new_external(node, id))),
+                }
+            }
+            REFERENCE_EXT | NEW_REFERENCE_EXT | NEWER_REFERENCE_EXT => {
+                let raw = self.reader.reference(tag)?;
+                let words = raw.words;
+                let id = ReferenceId::from_raw([words[0], words[1], words[2]]);
+                let reference = match self.resolve_node(raw.node, raw.creation)? {
+                    // Pid references carry the pid they belong to in the trailing words
+                    None if raw.len == MAX_REFERENCE_WORDS && id.is_pid() => {
+                        let pid =
+                            ProcessId::new(words[3], words[4]).map_err(|_| DecodeError::Invalid)?;
+                        Reference::new_pid(id, Pid::new_local(pid))
+                    }
+                    None => Reference::new(id),
+                    Some(node) => Reference::new_external(node, id),
+                };
+                Ok(Term::Reference(Gc::new_in(reference, self.heap)?))
+            }
+            EXPORT_EXT => {
+                let (module, function, arity) = self.reader.export()?;
+                let module = module.to_atom()?;
+                let function = function.to_atom()?;
+                let mfa = ModuleFunctionArity::new(module, function, arity as usize);
+                let callee = find_symbol(&mfa).ok_or(DecodeError::UndefinedFunction)?;
+                let fun =
+                    Closure::new_in(module, function, arity, callee as *const (), &[], self.heap)?;
+                Ok(Term::Closure(fun))
+            }
+            NEW_FUN_EXT | FUN_EXT => Err(DecodeError::UnsupportedFun),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+
+    fn build_bitstring(&mut self, bytes: &[u8], trailing_bits: u8) -> Result<Term, DecodeError> {
+        let len = bytes.len();
+        let flags = if trailing_bits == 0 {
+            BinaryFlags::new(len, Encoding::detect(bytes))
+        } else {
+            BinaryFlags::new(len, Encoding::Raw).with_trailing_bits(trailing_bits as usize)
+        };
+        // Any bits beyond the end of the bitstring are not significant, so make sure they are zeroed
+        let mask = 0xffu8 << ((8 - trailing_bits) % 8);
+        if len <= BinaryData::MAX_HEAP_BYTES {
+            let mut bin = BinaryData::with_capacity_small(len, self.heap)?;
+            bin.copy_from_slice(bytes);
+            if trailing_bits > 0 {
+                bin[len - 1] &= mask;
+            }
+            unsafe {
+                bin.set_flags(flags);
+            }
+            Ok(Term::HeapBinary(bin))
+        } else {
+            let mut bin = BinaryData::with_capacity_large(len);
+            {
+                let data = Arc::get_mut(&mut bin).unwrap();
+                data.copy_from_slice(bytes);
+                if trailing_bits > 0 {
+                    data[len - 1] &= mask;
+                }
+                unsafe {
+                    data.set_flags(flags);
+                }
+            }
+            Ok(Term::RcBinary(bin))
+        }
+    }
+
+    /// Returns the node identified by `name` and `creation`, or `None` if it is the local node
+    ///
+    /// Nodes we have not seen before are added to the node table, but are not connected to until
+    /// something is sent to them.
+    fn resolve_node(
+        &mut self,
+        name: AtomName<'_>,
+        creation: u32,
+    ) -> Result<Option<Arc<Node>>, DecodeError> {
+        let name = name.to_atom()?;
+        if let Some((last_name, last_creation, node)) = self.last_node.as_ref() {
+            if *last_name == name && *last_creation == creation {
+                return Ok(node.clone());
+            }
+        }
+        let node = distribution::get_or_insert_node(name, creation);
+        let local = self
+            .local_node
+            .get_or_insert_with(distribution::current_node);
+        let node = if Arc::ptr_eq(&node, local) {
+            None
+        } else {
+            Some(node)
+        };
+        self.last_node = Some((name, creation, node.clone()));
+        Ok(node)
+    }
+}
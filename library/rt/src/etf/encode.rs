@@ -0,0 +1,524 @@
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use firefly_binary::Bitstring;
+use firefly_number::Sign;
+
+use crate::services::distribution::{self, Node};
+use crate::term::*;
+
+use super::zlib::{Deflater, CHUNK_SIZE};
+use super::*;
+
+/// The error produced when a term cannot be encoded in the external term format
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The term, or a term contained within it, has no external representation
+    ///
+    /// This is only the case for internal runtime values, e.g. catch handlers or code pointers.
+    Unencodable,
+    /// The term is too large to be represented, e.g. a binary larger than 4GB
+    SystemLimit,
+}
+
+/// Options which control the encoding produced by [`encode_with_options`]
+#[derive(Debug, Copy, Clone)]
+pub struct EncodeOptions {
+    /// Corresponds to the `{minor_version, Version}` option of `term_to_binary/2`
+    ///
+    /// * `0` - floats are encoded in their textual form, and atoms as Latin-1 when possible
+    /// * `1` - floats are encoded in their 64-bit IEEE 754 form
+    /// * `2` - atoms are always encoded as UTF-8, this is the default
+    pub minor_version: u8,
+    /// Corresponds to the `{compressed, Level}` option of `term_to_binary/2`
+    ///
+    /// When non-zero, the encoded term is compressed with zlib at the given level, from `1`
+    /// (fastest) to `9` (smallest). As with ERTS, if compression does not reduce the size of
+    /// a small term, it is returned uncompressed.
+    pub compressed: u8,
+}
+impl EncodeOptions {
+    /// The compression level used for the `compressed` option of `term_to_binary/2`
+    pub const DEFAULT_COMPRESSION: u8 = 6;
+}
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            minor_version: 2,
+            compressed: 0,
+        }
+    }
+}
+
+/// Encodes `term` in the external term format, using the default options
+pub fn encode(term: Term) -> Result<Vec<u8>, EncodeError> {
+    encode_with_options(term, EncodeOptions::default())
+}
+
+/// Encodes `term` in the external term format, using the provided options
+pub fn encode_with_options(term: Term, options: EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = Encoder {
+        buffer: Vec::new(),
+        options,
+        local_node: None,
+        deflater: None,
+        patching: 0,
+        chunks: None,
+        atoms: None,
+    };
+    if options.compressed == 0 {
+        encoder.buffer.push(VERSION);
+        encoder.encode(term)?;
+        return Ok(encoder.buffer);
+    }
+
+    encoder.encode(term)?;
+    let buffer = core::mem::take(&mut encoder.buffer);
+    let mut deflater = match encoder.deflater.take() {
+        Some(deflater) => deflater,
+        None => {
+            // The term was small enough to be buffered entirely, so only compress it if doing
+            // so actually makes it smaller
+            let mut deflater = Deflater::new(options.compressed);
+            deflater.write(&buffer);
+            let compressed = deflater.finish()?;
+            if compressed.len() > buffer.len() {
+                let mut encoded = Vec::with_capacity(buffer.len() + 1);
+                encoded.push(VERSION);
+                encoded.extend_from_slice(&buffer);
+                return Ok(encoded);
+            }
+            return Ok(compressed);
+        }
+    };
+    deflater.write(&buffer);
+    deflater.finish()
+}
+
+/// A part of a term encoded by [`encode_iovec`]
+pub enum IoVecChunk {
+    /// Encoded bytes
+    Bytes(Vec<u8>),
+    /// The contents of a binary from the encoded term, referenced rather than copied
+    Binary(Term),
+}
+
+/// Encodes `term` in the external term format as a sequence of chunks, in which large reference-
+/// counted binaries are referenced rather than copied into the output
+///
+/// Compressed terms are always produced as a single chunk of bytes.
+pub fn encode_iovec(term: Term, options: EncodeOptions) -> Result<Vec<IoVecChunk>, EncodeError> {
+    if options.compressed > 0 {
+        let encoded = encode_with_options(term, options)?;
+        return Ok(alloc::vec![IoVecChunk::Bytes(encoded)]);
+    }
+    let mut encoder = Encoder {
+        buffer: Vec::new(),
+        options,
+        local_node: None,
+        deflater: None,
+        patching: 0,
+        chunks: Some(Vec::new()),
+        atoms: None,
+    };
+    encoder.buffer.push(VERSION);
+    encoder.encode(term)?;
+    let mut chunks = encoder.chunks.take().unwrap();
+    if !encoder.buffer.is_empty() {
+        chunks.push(IoVecChunk::Bytes(encoder.buffer));
+    }
+    Ok(chunks)
+}
+
+/// Encodes `term` in the external term format, using the default options, and returns the range
+/// of the output in which each atom was encoded, in order
+///
+/// This is used to encode a term once for many distribution connections, each of which may replace
+/// the atoms with references to its own atom cache, see `distribution::EncodedMessage`.
+pub fn encode_with_atoms(
+    term: Term,
+) -> Result<(Vec<u8>, Vec<(Range<usize>, Atom)>), EncodeError> {
+    let mut encoder = Encoder {
+        buffer: Vec::new(),
+        options: EncodeOptions::default(),
+        local_node: None,
+        deflater: None,
+        patching: 0,
+        chunks: None,
+        atoms: Some(Vec::new()),
+    };
+    encoder.buffer.push(VERSION);
+    encoder.encode(term)?;
+    Ok((encoder.buffer, encoder.atoms.unwrap()))
+}
+
+struct Encoder {
+    buffer: Vec<u8>,
+    options: EncodeOptions,
+    /// The current node, fetched on first use, as most terms do not require it
+    local_node: Option<Arc<Node>>,
+    /// When compressing, the encoded term is streamed through this in chunks, rather than
+    /// being buffered in its entirety
+    deflater: Option<Deflater>,
+    /// The number of terms currently being encoded whose size is patched in afterwards,
+    /// the buffer cannot be flushed to the deflater while this is non-zero
+    patching: usize,
+    /// When encoding to an iovec, the chunks which precede the current buffer
+    chunks: Option<Vec<IoVecChunk>>,
+    /// When requested, the range of the buffer in which each atom was encoded
+    atoms: Option<Vec<(Range<usize>, Atom)>>,
+}
+impl Encoder {
+    fn encode(&mut self, term: Term) -> Result<(), EncodeError> {
+        if self.options.compressed > 0 && self.patching == 0 && self.buffer.len() >= CHUNK_SIZE {
+            let level = self.options.compressed;
+            let deflater = self.deflater.get_or_insert_with(|| Deflater::new(level));
+            deflater.write(&self.buffer);
+            self.buffer.clear();
+        }
+
+        match term {
+            Term::None | Term::Catch(_) | Term::Code(_) => Err(EncodeError::Unencodable),
+            Term::Nil => {
+                self.buffer.push(NIL_EXT);
+                Ok(())
+            }
+            Term::Bool(b) => {
+                self.encode_atom(b.into());
+                Ok(())
+            }
+            Term::Atom(a) => {
+                self.encode_atom(a);
+                Ok(())
+            }
+            Term::Int(i) => {
+                self.encode_integer(i);
+                Ok(())
+            }
+            Term::BigInt(i) => self.encode_bigint(i.inner()),
+            Term::Float(f) => {
+                self.encode_float(f.inner());
+                Ok(())
+            }
+            Term::Cons(cons) => self.encode_list(&cons),
+            Term::Tuple(tuple) => self.encode_tuple(tuple.as_slice()),
+            Term::Map(map) => {
+                self.buffer.push(MAP_EXT);
+                self.put_u32(map.size() as u32);
+                for (k, v) in map.keys().iter().zip(map.values().iter()) {
+                    self.encode((*k).into())?;
+                    self.encode((*v).into())?;
+                }
+                Ok(())
+            }
+            Term::Closure(fun) => self.encode_closure(&fun),
+            Term::Pid(pid) => {
+                self.encode_pid(&pid);
+                Ok(())
+            }
+            Term::Port(port) => {
+                self.encode_port(&port);
+                Ok(())
+            }
+            Term::Reference(reference) => {
+                self.encode_reference(&reference);
+                Ok(())
+            }
+            Term::HeapBinary(bin) => self.encode_bitstring(&bin),
+            Term::RcBinary(ref bin) if self.can_reference(bin) => self.reference_binary(term),
+            Term::RefBinary(ref slice) if self.can_reference(slice) => self.reference_binary(term),
+            Term::ConstantBinary(bin) if self.can_reference(bin) => self.reference_binary(term),
+            Term::RcBinary(bin) => self.encode_bitstring(&bin),
+            Term::RefBinary(slice) => self.encode_bitstring(&slice),
+            Term::ConstantBinary(bin) => self.encode_bitstring(bin),
+        }
+    }
+
+    /// Returns true if `bitstring` should be referenced rather than copied when encoding to an iovec
+    ///
+    /// Only binaries too large to be heap binaries are referenced, as smaller ones are cheaper to
+    /// copy than to reference. Binaries within terms whose size is patched in afterwards are always
+    /// copied, as the buffer cannot be split until the size is known.
+    fn can_reference(&self, bitstring: &dyn Bitstring) -> bool {
+        self.chunks.is_some()
+            && self.patching == 0
+            && bitstring.is_binary()
+            && bitstring.is_aligned()
+            && bitstring.byte_size() > BinaryData::MAX_HEAP_BYTES
+    }
+
+    /// Writes the header of the binary `term`, and then references its contents as a chunk
+    fn reference_binary(&mut self, term: Term) -> Result<(), EncodeError> {
+        let bin = term.as_binary().unwrap();
+        let len = u32::try_from(bin.byte_size()).map_err(|_| EncodeError::SystemLimit)?;
+        self.buffer.push(BINARY_EXT);
+        self.put_u32(len);
+        let chunks = self.chunks.as_mut().unwrap();
+        chunks.push(IoVecChunk::Bytes(core::mem::take(&mut self.buffer)));
+        chunks.push(IoVecChunk::Binary(term));
+        Ok(())
+    }
+
+    fn encode_atom(&mut self, atom: Atom) {
+        let start = self.buffer.len();
+        self.encode_atom_text(atom);
+        if let Some(atoms) = self.atoms.as_mut() {
+            atoms.push((start..self.buffer.len(), atom));
+        }
+    }
+
+    fn encode_atom_text(&mut self, atom: Atom) {
+        let name = atom.as_str();
+        // Prior to minor version 2, atoms which are representable as Latin-1 are encoded as such
+        if self.options.minor_version < 2 && name.chars().all(|c| (c as u32) < 256) {
+            let len = name.chars().count();
+            if len < 256 {
+                self.buffer.push(SMALL_ATOM_EXT);
+                self.buffer.push(len as u8);
+            } else {
+                self.buffer.push(ATOM_EXT);
+                self.put_u16(len as u16);
+            }
+            self.buffer.extend(name.chars().map(|c| c as u8));
+            return;
+        }
+
+        let bytes = name.as_bytes();
+        if bytes.len() < 256 {
+            self.buffer.push(SMALL_ATOM_UTF8_EXT);
+            self.buffer.push(bytes.len() as u8);
+        } else {
+            self.buffer.push(ATOM_UTF8_EXT);
+            self.put_u16(bytes.len() as u16);
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn encode_integer(&mut self, i: i64) {
+        if let Ok(byte) = u8::try_from(i) {
+            self.buffer.push(SMALL_INTEGER_EXT);
+            self.buffer.push(byte);
+        } else if let Ok(word) = i32::try_from(i) {
+            self.buffer.push(INTEGER_EXT);
+            self.buffer.extend_from_slice(&word.to_be_bytes());
+        } else {
+            let (sign, magnitude) = (i < 0, i.unsigned_abs());
+            let digits = magnitude.to_le_bytes();
+            let len = 8 - (magnitude.leading_zeros() as usize / 8);
+            self.buffer.push(SMALL_BIG_EXT);
+            self.buffer.push(len as u8);
+            self.buffer.push(sign as u8);
+            self.buffer.extend_from_slice(&digits[..len]);
+        }
+    }
+
+    fn encode_bigint(&mut self, i: &firefly_number::BigInt) -> Result<(), EncodeError> {
+        let (sign, digits) = i.to_bytes_le();
+        if digits.len() < 256 {
+            self.buffer.push(SMALL_BIG_EXT);
+            self.buffer.push(digits.len() as u8);
+        } else {
+            let len = u32::try_from(digits.len()).map_err(|_| EncodeError::SystemLimit)?;
+            self.buffer.push(LARGE_BIG_EXT);
+            self.put_u32(len);
+        }
+        self.buffer.push((sign == Sign::Minus) as u8);
+        self.buffer.extend_from_slice(&digits);
+        Ok(())
+    }
+
+    fn encode_float(&mut self, f: f64) {
+        if self.options.minor_version > 0 {
+            self.buffer.push(NEW_FLOAT_EXT);
+            self.buffer.extend_from_slice(&f.to_bits().to_be_bytes());
+            return;
+        }
+
+        // This matches the `%.20e` format used by ERTS, which differs from Rust in that the
+        // exponent always has a sign and at least two digits
+        let formatted = format!("{:.20e}", f);
+        let (mantissa, exponent) = formatted.split_once('e').unwrap();
+        let exponent: i32 = exponent.parse().unwrap();
+        let sign = if exponent < 0 { '-' } else { '+' };
+        let formatted = format!("{}e{}{:02}", mantissa, sign, exponent.abs());
+        let start = self.buffer.len();
+        self.buffer.push(FLOAT_EXT);
+        self.buffer.extend_from_slice(formatted.as_bytes());
+        self.buffer.resize(start + 1 + FLOAT_EXT_SIZE, 0);
+    }
+
+    fn encode_tuple(&mut self, elements: &[OpaqueTerm]) -> Result<(), EncodeError> {
+        let arity = elements.len();
+        if arity < 256 {
+            self.buffer.push(SMALL_TUPLE_EXT);
+            self.buffer.push(arity as u8);
+        } else {
+            let arity = u32::try_from(arity).map_err(|_| EncodeError::SystemLimit)?;
+            self.buffer.push(LARGE_TUPLE_EXT);
+            self.put_u32(arity);
+        }
+        for element in elements.iter().copied() {
+            self.encode(element.into())?;
+        }
+        Ok(())
+    }
+
+    fn encode_list(&mut self, cons: &Cons) -> Result<(), EncodeError> {
+        // Proper lists of bytes are encoded compactly as strings, if short enough
+        let mut len = 0;
+        let mut is_string = true;
+        for element in cons.iter_raw() {
+            match element {
+                Ok(element) if is_string && len < (u16::MAX as usize) => match element.into() {
+                    Term::Int(i) if (0..256).contains(&i) => (),
+                    _ => is_string = false,
+                },
+                Ok(_) => is_string = false,
+                Err(_) => {
+                    is_string = false;
+                    break;
+                }
+            }
+            len += 1;
+        }
+
+        if is_string {
+            self.buffer.push(STRING_EXT);
+            self.put_u16(len as u16);
+            for element in cons.iter_raw() {
+                let Term::Int(i) = element.unwrap().into() else { unreachable!() };
+                self.buffer.push(i as u8);
+            }
+            return Ok(());
+        }
+
+        let len = u32::try_from(len).map_err(|_| EncodeError::SystemLimit)?;
+        self.buffer.push(LIST_EXT);
+        self.put_u32(len);
+        for element in cons.iter_raw() {
+            match element {
+                Ok(element) => self.encode(element.into())?,
+                Err(tail) => return self.encode(tail.into()),
+            }
+        }
+        self.buffer.push(NIL_EXT);
+        Ok(())
+    }
+
+    fn encode_bitstring(&mut self, bitstring: &dyn Bitstring) -> Result<(), EncodeError> {
+        let len = u32::try_from(bitstring.byte_size()).map_err(|_| EncodeError::SystemLimit)?;
+        let trailing_bits = (bitstring.bit_size() % 8) as u8;
+        if trailing_bits == 0 {
+            self.buffer.push(BINARY_EXT);
+            self.put_u32(len);
+        } else {
+            self.buffer.push(BIT_BINARY_EXT);
+            self.put_u32(len);
+            self.buffer.push(trailing_bits);
+        }
+        self.buffer.extend(bitstring.bytes());
+        Ok(())
+    }
+
+    fn encode_closure(&mut self, fun: &Closure) -> Result<(), EncodeError> {
+        // Function captures have no environment, and are encoded as `fun M:F/A`
+        if fun.is_thin() {
+            self.buffer.push(EXPORT_EXT);
+            self.encode_atom(fun.module);
+            self.encode_atom(fun.name);
+            self.encode_integer(fun.arity as i64);
+            return Ok(());
+        }
+
+        // There is no fun table from which to derive the index/uniq of a closure, so those are
+        // left zeroed, making the resulting fun usable only for identification purposes. The
+        // arity excludes the implicit closure argument.
+        let start = self.buffer.len();
+        self.patching += 1;
+        self.buffer.push(NEW_FUN_EXT);
+        self.put_u32(0);
+        self.buffer.push(fun.arity - 1);
+        self.buffer.extend_from_slice(&[0; 16]);
+        self.put_u32(0);
+        self.put_u32(fun.env_size() as u32);
+        self.encode_atom(fun.module);
+        self.encode_integer(0);
+        self.encode_integer(0);
+        let creator = Pid::new_local(unsafe { crate::process::ProcessId::from_raw(0) });
+        self.encode_pid(&creator);
+        for value in fun.env().iter().copied() {
+            self.encode(value.into())?;
+        }
+        self.patching -= 1;
+        // The size includes the size field itself, but not the tag
+        let size = self.buffer.len() - start - 1;
+        let size = u32::try_from(size).map_err(|_| EncodeError::SystemLimit)?;
+        self.buffer[(start + 1)..(start + 5)].copy_from_slice(&size.to_be_bytes());
+        Ok(())
+    }
+
+    fn encode_pid(&mut self, pid: &Pid) {
+        let node = pid.node().unwrap_or_else(|| self.local_node());
+        let id = pid.id();
+        self.buffer.push(NEW_PID_EXT);
+        self.encode_atom(node.name());
+        self.put_u32(id.number());
+        self.put_u32(id.serial());
+        self.put_u32(node.creation());
+    }
+
+    fn encode_port(&mut self, port: &Port) {
+        let node = port.node().unwrap_or_else(|| self.local_node());
+        let id = port.id().into_raw();
+        match u32::try_from(id) {
+            Ok(id) => {
+                self.buffer.push(NEW_PORT_EXT);
+                self.encode_atom(node.name());
+                self.put_u32(id);
+            }
+            Err(_) => {
+                self.buffer.push(V4_PORT_EXT);
+                self.encode_atom(node.name());
+                self.buffer.extend_from_slice(&id.to_be_bytes());
+            }
+        }
+        self.put_u32(node.creation());
+    }
+
+    fn encode_reference(&mut self, reference: &Reference) {
+        let node = reference.node().unwrap_or_else(|| self.local_node());
+        let words = reference.id().into_raw();
+        // Pid references carry the pid they belong to in two additional words
+        let pid = reference.pid();
+        let len = words.len() + if pid.is_some() { 2 } else { 0 };
+        self.buffer.push(NEWER_REFERENCE_EXT);
+        self.put_u16(len as u16);
+        self.encode_atom(node.name());
+        self.put_u32(node.creation());
+        for word in words {
+            self.put_u32(word);
+        }
+        if let Some(pid) = pid {
+            self.put_u32(pid.id().number());
+            self.put_u32(pid.id().serial());
+        }
+    }
+
+    fn local_node(&mut self) -> Arc<Node> {
+        self.local_node
+            .get_or_insert_with(distribution::current_node)
+            .clone()
+    }
+
+    #[inline]
+    fn put_u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[inline]
+    fn put_u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+}
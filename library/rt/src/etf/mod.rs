@@ -0,0 +1,409 @@
+//! This module implements the [External Term Format](https://www.erlang.org/doc/apps/erts/erl_ext_dist.html),
+//! the serialization format used by `term_to_binary/1` and `binary_to_term/1`, as well as
+//! by the distribution protocol.
+//!
+//! Terms are encoded to a `Vec<u8>` via [`encode`], and decoded onto a heap via [`decode`]. Decoding
+//! is done in two passes: the first validates the input and calculates the amount of heap space
+//! required to hold the result, the second constructs the term. The [`Decoder`] type exposes these
+//! two steps separately, for situations in which the heap must be prepared in between, e.g. when
+//! decoding onto a process heap which may need to be garbage collected first.
+//!
+//! Compressed terms are supported in both directions, using zlib. When encoding, the output is
+//! streamed through the compressor in chunks, see [`EncodeOptions::compressed`].
+//!
+//! NOTE: Firefly has no equivalent of the fun table used by ERTS to identify local funs, so while
+//! closures with a captured environment can be encoded, they cannot be decoded. Exported function
+//! captures (e.g. `fun erlang:display/1`) are supported in both directions, but can only be decoded
+//! if the function is present in the symbol table.
+mod decode;
+mod encode;
+mod zlib;
+
+pub use self::decode::{decode, DecodeError, DecodeOptions, Decoder};
+pub use self::encode::{
+    encode, encode_iovec, encode_with_atoms, encode_with_options, EncodeError, EncodeOptions,
+    IoVecChunk,
+};
+
+/// The version byte which prefixes every term encoded in the external term format
+pub const VERSION: u8 = 131;
+
+const NEW_FLOAT_EXT: u8 = 70;
+const BIT_BINARY_EXT: u8 = 77;
+const COMPRESSED: u8 = 80;
+/// Only valid in terms following a distribution header, see `distribution::EncodedMessage`
+pub(crate) const ATOM_CACHE_REF: u8 = 82;
+const NEW_PID_EXT: u8 = 88;
+const NEW_PORT_EXT: u8 = 89;
+const NEWER_REFERENCE_EXT: u8 = 90;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const REFERENCE_EXT: u8 = 101;
+const PORT_EXT: u8 = 102;
+const PID_EXT: u8 = 103;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const NEW_FUN_EXT: u8 = 112;
+const EXPORT_EXT: u8 = 113;
+const NEW_REFERENCE_EXT: u8 = 114;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const FUN_EXT: u8 = 117;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+const V4_PORT_EXT: u8 = 120;
+
+/// The size of the textual representation of floats used by `FLOAT_EXT`
+const FLOAT_EXT_SIZE: usize = 31;
+
+/// The maximum number of words in the id of an encoded reference
+const MAX_REFERENCE_WORDS: usize = 5;
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::gc::Gc;
+    use crate::term::*;
+
+    fn roundtrip(term: Term) -> Term {
+        let heap = FixedSizeHeap::<4096>::default();
+        let encoded = encode(term).unwrap();
+        let (decoded, used) = decode(&encoded, &heap).unwrap();
+        assert_eq!(used, encoded.len());
+        // Re-encoding the decoded term must produce identical output
+        assert_eq!(encode(decoded.clone()).unwrap(), encoded);
+        decoded
+    }
+
+    #[test]
+    fn etf_immediates_test() {
+        assert_eq!(encode(Term::Int(1)).unwrap(), vec![131, 97, 1]);
+        assert_eq!(encode(Term::Nil).unwrap(), vec![131, 106]);
+        assert_eq!(
+            encode(Term::Atom(atoms::Ok)).unwrap(),
+            vec![131, 119, 2, b'o', b'k']
+        );
+
+        assert_eq!(roundtrip(Term::Int(-1)), Term::Int(-1));
+        assert_eq!(roundtrip(Term::Int(256)), Term::Int(256));
+        assert_eq!(
+            roundtrip(Term::Int(i64::from(i32::MAX) + 1)),
+            Term::Int(i64::from(i32::MAX) + 1)
+        );
+        assert_eq!(roundtrip(Term::Bool(true)), Term::Bool(true));
+        assert_eq!(
+            roundtrip(Term::Atom(atoms::Undefined)),
+            Term::Atom(atoms::Undefined)
+        );
+        assert_eq!(roundtrip(Term::Float(1.5.into())), Term::Float(1.5.into()));
+
+        // Legacy Latin-1 atoms and textual floats are still accepted
+        let heap = FixedSizeHeap::<64>::default();
+        let (atom, _) = decode(&[131, 100, 0, 2, b'o', b'k'], &heap).unwrap();
+        assert_eq!(atom, Term::Atom(atoms::Ok));
+        let options = EncodeOptions {
+            minor_version: 0,
+            ..Default::default()
+        };
+        let encoded = encode_with_options(Term::Float((-0.25).into()), options).unwrap();
+        assert_eq!(encoded[1], FLOAT_EXT);
+        assert_eq!(encoded.len(), 2 + FLOAT_EXT_SIZE);
+        let (float, _) = decode(&encoded, &heap).unwrap();
+        assert_eq!(float, Term::Float((-0.25).into()));
+    }
+
+    #[test]
+    fn etf_big_integers_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let big = Gc::new_in(BigInt::new(u64::MAX), &heap).unwrap();
+        let encoded = encode(Term::BigInt(big)).unwrap();
+        assert_eq!(&encoded[..4], &[131, SMALL_BIG_EXT, 8, 0]);
+        let Term::BigInt(decoded) = roundtrip(Term::BigInt(big)) else { panic!("expected bigint") };
+        assert_eq!(decoded.inner(), big.inner());
+
+        // Big integers which fit in a small integer are normalized
+        let (small, _) = decode(
+            &[131, SMALL_BIG_EXT, 9, 1, 5, 0, 0, 0, 0, 0, 0, 0, 0],
+            &heap,
+        )
+        .unwrap();
+        assert_eq!(small, Term::Int(-5));
+    }
+
+    #[test]
+    fn etf_containers_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        let inner = Tuple::from_slice(&[atoms::Ok.into(), Term::Int(1).into()], &heap).unwrap();
+        let string = Cons::charlist_from_str("abc", &heap).unwrap().unwrap();
+        let mut builder = ListBuilder::new_improper(Term::Int(3).into(), &heap);
+        builder.push(Term::Tuple(inner)).unwrap();
+        builder.push(Term::Cons(string)).unwrap();
+        let improper = builder.finish().unwrap();
+        let map = SmallMap::from_iter(
+            [
+                (atoms::Error.into(), Term::Cons(improper).into()),
+                (Term::Int(1).into(), OpaqueTerm::NIL),
+            ]
+            .into_iter(),
+            &heap,
+        )
+        .unwrap();
+        let bin = BinaryData::from_small_bytes(b"hello", &heap).unwrap();
+        let outer = Tuple::from_slice(
+            &[Term::Map(map).into(), Term::HeapBinary(bin).into()],
+            &heap,
+        )
+        .unwrap();
+
+        let encoded = encode(Term::Tuple(outer)).unwrap();
+        let decoded = roundtrip(Term::Tuple(outer));
+        assert_eq!(decoded, Term::Tuple(outer));
+
+        // Strings are encoded compactly
+        assert_eq!(
+            encode(Term::Cons(string)).unwrap(),
+            vec![131, STRING_EXT, 0, 3, b'a', b'b', b'c']
+        );
+
+        // Truncated input is rejected
+        for len in 1..encoded.len() {
+            assert!(decode(&encoded[..len], &heap).is_err());
+        }
+    }
+
+    #[test]
+    fn etf_bitstrings_test() {
+        let heap = FixedSizeHeap::<512>::default();
+
+        let mut builder = BinaryBuilder::new();
+        builder
+            .push_integer(
+                Term::Int(5).into(),
+                11,
+                false,
+                firefly_binary::Endianness::Big,
+            )
+            .unwrap();
+        let bits = builder.finish(&heap).unwrap();
+        let encoded = encode(bits.clone()).unwrap();
+        assert_eq!(
+            encoded,
+            vec![131, BIT_BINARY_EXT, 0, 0, 0, 2, 3, 0, 0b1010_0000]
+        );
+        assert_eq!(roundtrip(bits.clone()), bits);
+
+        let large = Term::RcBinary(BinaryData::from_bytes(&[7u8; 100]));
+        assert!(matches!(roundtrip(large.clone()), Term::RcBinary(_)));
+        assert_eq!(roundtrip(large.clone()), large);
+
+        // Maps with duplicate keys are invalid
+        let duplicated = [131, MAP_EXT, 0, 0, 0, 2, 97, 1, 106, 97, 1, 106];
+        assert_eq!(decode(&duplicated, &heap), Err(DecodeError::Invalid));
+    }
+    #[test]
+    fn etf_decode_options_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let safe = DecodeOptions {
+            safe: true,
+            ..Default::default()
+        };
+
+        // Existing atoms are permitted in safe mode, new ones are not
+        let decoder = Decoder::with_options(&[131, SMALL_ATOM_UTF8_EXT, 2, b'o', b'k'], safe);
+        assert_eq!(decoder.unwrap().decode(&heap), Ok(Term::Atom(atoms::Ok)));
+        let name = b"etf_decode_options_test_atom";
+        let mut input = vec![131, SMALL_ATOM_UTF8_EXT, name.len() as u8];
+        input.extend_from_slice(name);
+        assert_eq!(
+            Decoder::with_options(&input, safe).err(),
+            Some(DecodeError::Unsafe)
+        );
+        assert!(Atom::try_from_str_existing("etf_decode_options_test_atom").is_err());
+
+        // Exported functions are never resolved in safe mode
+        let mut input = vec![131, EXPORT_EXT, SMALL_ATOM_UTF8_EXT, 6];
+        input.extend_from_slice(b"erlang");
+        input.extend_from_slice(&[SMALL_ATOM_UTF8_EXT, 7]);
+        input.extend_from_slice(b"display");
+        input.extend_from_slice(&[SMALL_INTEGER_EXT, 1]);
+        assert_eq!(
+            Decoder::with_options(&input, safe).err(),
+            Some(DecodeError::Unsafe)
+        );
+
+        // Nesting depth and allocation size are limited
+        let nested = [131, SMALL_TUPLE_EXT, 1, SMALL_TUPLE_EXT, 1, NIL_EXT];
+        let shallow = DecodeOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(Decoder::with_options(&nested, shallow).is_ok());
+        let shallow = DecodeOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            Decoder::with_options(&nested, shallow).err(),
+            Some(DecodeError::SystemLimit)
+        );
+        let encoded = encode(Term::RcBinary(BinaryData::from_bytes(&[0u8; 100]))).unwrap();
+        let small = DecodeOptions {
+            max_size: 64,
+            ..Default::default()
+        };
+        assert_eq!(
+            Decoder::with_options(&encoded, small).err(),
+            Some(DecodeError::SystemLimit)
+        );
+
+        // Trailing data is not consumed
+        let decoder = Decoder::new(&[131, NIL_EXT, 0, 0]).unwrap();
+        assert_eq!(decoder.used(), 2);
+    }
+
+    #[test]
+    fn etf_atom_cache_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let cache = [atoms::Ok, atoms::True];
+        let expected = Tuple::from_slice(&[atoms::Ok.into(), true.into()], &heap).unwrap();
+
+        // Terms following a distribution header have no version byte, and may refer to the cache
+        let input = [SMALL_TUPLE_EXT, 2, ATOM_CACHE_REF, 0, ATOM_CACHE_REF, 1];
+        let decoder = Decoder::with_atom_cache(&input, &cache, DecodeOptions::default()).unwrap();
+        assert_eq!(decoder.used(), input.len());
+        assert_eq!(decoder.decode(&heap), Ok(Term::Tuple(expected)));
+
+        // Cached atoms already exist, so they are permitted in safe mode
+        let safe = DecodeOptions {
+            safe: true,
+            ..Default::default()
+        };
+        assert!(Decoder::with_atom_cache(&input, &cache, safe).is_ok());
+
+        // References must be to an entry in the cache, and are only valid after a header
+        let input = [ATOM_CACHE_REF, 2];
+        assert_eq!(
+            Decoder::with_atom_cache(&input, &cache, safe).err(),
+            Some(DecodeError::Invalid)
+        );
+        assert_eq!(
+            Decoder::new(&[131, ATOM_CACHE_REF, 0]).err(),
+            Some(DecodeError::InvalidTag(ATOM_CACHE_REF))
+        );
+
+        // Latin-1 names outside of ASCII are converted to UTF-8
+        let (decoded, _) = decode(&[131, SMALL_ATOM_EXT, 2, 0xe5, b'a'], &heap).unwrap();
+        assert_eq!(decoded, Term::Atom(Atom::try_from_str_existing("åa").unwrap()));
+    }
+    #[test]
+    fn etf_invalid_identifiers_test() {
+        fn pid(node: &[u8], id: u32, serial: u32, creation: u8) -> vec::Vec<u8> {
+            let mut input = vec![131, PID_EXT, SMALL_ATOM_UTF8_EXT, node.len() as u8];
+            input.extend_from_slice(node);
+            input.extend_from_slice(&id.to_be_bytes());
+            input.extend_from_slice(&serial.to_be_bytes());
+            input.push(creation);
+            input
+        }
+
+        let invalid = Some(DecodeError::InvalidIdentifier);
+        assert!(Decoder::new(&pid(b"a@host", 1, 0, 1)).is_ok());
+        // The node must be a valid node name
+        assert_eq!(Decoder::new(&pid(b"nohost", 1, 0, 1)).err(), invalid);
+        assert_eq!(Decoder::new(&pid(b"a b@host", 1, 0, 1)).err(), invalid);
+        // The old formats only support small ids and creations
+        assert_eq!(Decoder::new(&pid(b"a@host", 1 << 15, 0, 1)).err(), invalid);
+        assert_eq!(Decoder::new(&pid(b"a@host", 1, 1 << 13, 1)).err(), invalid);
+        assert_eq!(Decoder::new(&pid(b"a@host", 1, 0, 4)).err(), invalid);
+
+        let mut port = vec![131, PORT_EXT, SMALL_ATOM_UTF8_EXT, 6];
+        port.extend_from_slice(b"a@host");
+        port.extend_from_slice(&(1u32 << 28).to_be_bytes());
+        port.push(1);
+        assert_eq!(Decoder::new(&port).err(), invalid);
+
+        let mut reference = vec![131, NEW_REFERENCE_EXT, 0, 4, SMALL_ATOM_UTF8_EXT, 6];
+        reference.extend_from_slice(b"a@host");
+        reference.push(4);
+        assert_eq!(Decoder::new(&reference).err(), invalid);
+    }
+
+    #[test]
+    fn etf_compressed_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let options = EncodeOptions {
+            compressed: EncodeOptions::DEFAULT_COMPRESSION,
+            ..Default::default()
+        };
+
+        // Small terms are not compressed if doing so would not make them smaller
+        let encoded = encode_with_options(Term::Int(1), options).unwrap();
+        assert_eq!(encoded, vec![131, SMALL_INTEGER_EXT, 1]);
+
+        // Large terms are streamed through the compressor in chunks
+        let parts = [
+            Term::RcBinary(BinaryData::from_bytes(&[1u8; 20_000])),
+            Term::RcBinary(BinaryData::from_bytes(&[2u8; 20_000])),
+        ];
+        let tuple =
+            Tuple::from_slice(&[parts[0].clone().into(), parts[1].clone().into()], &heap).unwrap();
+        let uncompressed = encode(Term::Tuple(tuple)).unwrap();
+        let compressed = encode_with_options(Term::Tuple(tuple), options).unwrap();
+        assert_eq!(&compressed[..2], &[131, COMPRESSED]);
+        assert_eq!(
+            &compressed[2..6],
+            &((uncompressed.len() - 1) as u32).to_be_bytes()
+        );
+        assert!(compressed.len() < uncompressed.len() / 10);
+
+        let (decoded, used) = decode(&compressed, &heap).unwrap();
+        assert_eq!(used, compressed.len());
+        assert_eq!(decoded, Term::Tuple(tuple));
+
+        // The uncompressed size must be correct, and is subject to the allocation limit
+        let mut invalid = compressed.clone();
+        invalid[5] ^= 1;
+        assert!(decode(&invalid, &heap).is_err());
+        let small = DecodeOptions {
+            max_size: 1024,
+            ..Default::default()
+        };
+        assert_eq!(
+            Decoder::with_options(&compressed, small).err(),
+            Some(DecodeError::SystemLimit)
+        );
+        assert!(decode(&compressed[..compressed.len() - 1], &heap).is_err());
+    }
+
+    #[test]
+    fn etf_iovec_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        let large = Term::RcBinary(BinaryData::from_bytes(&[7; 100]));
+        let small = Term::from_str_in("small", &heap).unwrap();
+        let tuple = Tuple::from_slice(&[small.into(), large.clone().into()], &heap).unwrap();
+        let chunks = encode_iovec(Term::Tuple(tuple), EncodeOptions::default()).unwrap();
+
+        // The large binary is referenced, and everything else is encoded around it
+        assert_eq!(chunks.len(), 2);
+        let IoVecChunk::Bytes(ref header) = chunks[0] else { panic!("expected bytes") };
+        let IoVecChunk::Binary(ref referenced) = chunks[1] else { panic!("expected binary") };
+        assert_eq!(referenced, &large);
+        let mut encoded = header.clone();
+        encoded.extend_from_slice(&[7; 100]);
+        assert_eq!(encoded, encode(Term::Tuple(tuple)).unwrap());
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use miniz_oxide::deflate::core::CompressorOxide;
+use miniz_oxide::deflate::stream::deflate;
+use miniz_oxide::inflate::stream::{inflate as inflate_stream, InflateState};
+use miniz_oxide::{DataFormat, MZFlush, MZStatus};
+
+use super::{DecodeError, EncodeError, COMPRESSED, VERSION};
+
+/// The amount of encoded data buffered before it is fed to the compressor
+pub(super) const CHUNK_SIZE: usize = 16 * 1024;
+
+/// The size of the header of a compressed term, i.e. the version byte, tag, and uncompressed size
+const HEADER_SIZE: usize = 6;
+
+/// Incrementally compresses an encoded term, producing a complete compressed term on completion
+pub(super) struct Deflater {
+    compressor: CompressorOxide,
+    output: Vec<u8>,
+    uncompressed: usize,
+}
+impl Deflater {
+    pub fn new(level: u8) -> Self {
+        let mut compressor = CompressorOxide::default();
+        compressor.set_format_and_level(DataFormat::Zlib, level);
+        // The uncompressed size is written once it is known
+        let mut output = Vec::with_capacity(CHUNK_SIZE);
+        output.extend_from_slice(&[VERSION, COMPRESSED, 0, 0, 0, 0]);
+        Self {
+            compressor,
+            output,
+            uncompressed: 0,
+        }
+    }
+
+    /// Compresses `input`, which is the next chunk of the encoded term
+    pub fn write(&mut self, mut input: &[u8]) {
+        self.uncompressed += input.len();
+        let mut chunk = [0; 4096];
+        while !input.is_empty() {
+            let result = deflate(&mut self.compressor, input, &mut chunk, MZFlush::None);
+            assert!(result.status.is_ok(), "zlib compression failed");
+            input = &input[result.bytes_consumed..];
+            self.output
+                .extend_from_slice(&chunk[..result.bytes_written]);
+        }
+    }
+
+    /// Flushes any remaining data through the compressor, and returns the compressed term
+    pub fn finish(mut self) -> Result<Vec<u8>, EncodeError> {
+        let uncompressed =
+            u32::try_from(self.uncompressed).map_err(|_| EncodeError::SystemLimit)?;
+        let mut chunk = [0; 4096];
+        loop {
+            let result = deflate(&mut self.compressor, &[], &mut chunk, MZFlush::Finish);
+            self.output
+                .extend_from_slice(&chunk[..result.bytes_written]);
+            match result.status {
+                Ok(MZStatus::StreamEnd) => break,
+                Ok(_) => continue,
+                Err(_) => panic!("zlib compression failed"),
+            }
+        }
+        self.output[2..HEADER_SIZE].copy_from_slice(&uncompressed.to_be_bytes());
+        Ok(self.output)
+    }
+}
+
+/// Decompresses the body of a compressed term, which must expand to exactly `size` bytes
+///
+/// The result is prefixed with the version byte, so that it can be decoded like any other term.
+/// Returns the decompressed term, and the number of bytes of `input` that were consumed.
+pub(super) fn inflate(input: &[u8], size: usize) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut output = vec![0; size + 1];
+    output[0] = VERSION;
+    let mut state = InflateState::new_boxed(DataFormat::Zlib);
+    let result = inflate_stream(&mut state, input, &mut output[1..], MZFlush::Finish);
+    match result.status {
+        Ok(MZStatus::StreamEnd) if result.bytes_written == size => {
+            Ok((output, result.bytes_consumed))
+        }
+        // The input was truncated
+        Err(_) if result.bytes_consumed == input.len() && result.bytes_written < size => {
+            Err(DecodeError::UnexpectedEof)
+        }
+        _ => Err(DecodeError::Invalid),
+    }
+}
@@ -78,12 +78,18 @@ const BIFS: &'static [&'static str] = &[
     "erlang:bit_size/1",
     "erlang:bitstring_to_list/1",
     "erlang:byte_size/1",
+    "erlang:cancel_timer/1",
+    "erlang:cancel_timer/2",
     "erlang:ceil/1",
     "erlang:date/0",
     "erlang:demonitor/1",
     "erlang:demonitor/2",
     "erlang:disconnect_node/1",
     "erlang:display/1",
+    "erlang:dist_ctrl_get_data/1",
+    "erlang:dist_ctrl_get_data_notification/1",
+    "erlang:dist_ctrl_input_handler/2",
+    "erlang:dist_ctrl_put_data/2",
     "erlang:element/2",
     "erlang:erase/0",
     "erlang:erase/1",
@@ -111,6 +117,7 @@ const BIFS: &'static [&'static str] = &[
     "erlang:halt/1",
     "erlang:halt/2",
     "erlang:hd/1",
+    "erlang:hibernate/3",
     "erlang:integer_to_binary/1",
     "erlang:integer_to_binary/2",
     "erlang:integer_to_list/1",
@@ -154,24 +161,33 @@ const BIFS: &'static [&'static str] = &[
     "erlang:make_ref/0",
     "erlang:map_get/2",
     "erlang:map_size/1",
+    "erlang:match_spec_test/3",
     "erlang:max/2",
+    "erlang:memory/0",
+    "erlang:memory/1",
     "erlang:min/2",
     "erlang:monitor/2",
     "erlang:monitor/3",
     "erlang:monitor_node/2",
     "erlang:monitor_node/3",
+    "erlang:monotonic_time/0",
     "erlang:node/0",
     "erlang:node/1",
     "erlang:nodes/0",
     "erlang:nodes/1",
     "erlang:now/0",
     "erlang:open_port/2",
+    "erlang:phash2/1",
+    "erlang:phash2/2",
     "erlang:pid_to_list/1",
+    "erlang:port_call/3",
     "erlang:port_close/1",
     "erlang:port_command/2",
     "erlang:port_command/3",
     "erlang:port_connect/2",
     "erlang:port_control/3",
+    "erlang:port_info/1",
+    "erlang:port_info/2",
     "erlang:port_to_list/1",
     "erlang:process_flag/2",
     "erlang:process_flag/3",
@@ -181,11 +197,17 @@ const BIFS: &'static [&'static str] = &[
     "erlang:put/2",
     "erlang:raise/2",
     "erlang:raise/3",
+    "erlang:read_timer/1",
+    "erlang:read_timer/2",
+    "erlang:recv_marker_use/1",
     "erlang:ref_to_list/1",
     "erlang:register/2",
     "erlang:registered/0",
     "erlang:round/1",
+    "erlang:send_after/3",
+    "erlang:send_after/4",
     "erlang:setelement/3",
+    "erlang:setnode/2",
     "erlang:self/0",
     "erlang:size/1",
     "erlang:spawn/1",
@@ -212,7 +234,11 @@ const BIFS: &'static [&'static str] = &[
     "erlang:spawn_request/5",
     "erlang:spawn_request_abandon/1",
     "erlang:split_binary/2",
+    "erlang:start_timer/3",
+    "erlang:start_timer/4",
     "erlang:statistics/1",
+    "erlang:system_info/1",
+    "erlang:system_time/0",
     "erlang:term_to_binary/1",
     "erlang:term_to_binary/2",
     "erlang:term_to_iovec/1",
@@ -220,6 +246,9 @@ const BIFS: &'static [&'static str] = &[
     "erlang:throw/1",
     "erlang:time/0",
     "erlang:tl/1",
+    "erlang:trace/3",
+    "erlang:trace_info/2",
+    "erlang:trace_pattern/3",
     "erlang:trunc/1",
     "erlang:tuple_size/1",
     "erlang:tuple_to_list/1",
@@ -0,0 +1,122 @@
+use smallvec::SmallVec;
+
+use crate::term::{OpaqueTerm, Term};
+
+/// The maximum number of arguments any Erlang function may be called with
+pub const MAX_ARGS: usize = u8::MAX as usize;
+
+/// A vector of arguments materialized from an argument list, e.g. the `Args` in `apply(M, F, Args)`
+///
+/// Most functions have a small number of arguments, so this avoids allocating in the common case.
+pub type Arguments = SmallVec<[OpaqueTerm; 8]>;
+
+/// The error produced when an argument list cannot be converted to `Arguments`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgumentListError {
+    /// The term given was not a proper list
+    Improper,
+    /// The list contained more elements than allowed
+    TooManyArguments,
+}
+
+/// Materializes the proper list `list` as a vector of argument terms.
+///
+/// At most `max_args` arguments are permitted, which must be no larger than `MAX_ARGS`. The
+/// list is only traversed as far as needed to determine that it is too long, so this is safe
+/// to use with arbitrarily long lists received from untrusted sources.
+///
+/// NOTE: The terms produced by this function are still owned by whatever heap `list` was
+/// allocated on, so care must be taken if a garbage collection might occur while they are live.
+pub fn list_to_args(list: OpaqueTerm, max_args: usize) -> Result<Arguments, ArgumentListError> {
+    debug_assert!(max_args <= MAX_ARGS);
+
+    let mut args = Arguments::new();
+    match list.into() {
+        Term::Nil => Ok(args),
+        Term::Cons(cons) => {
+            for result in cons.iter_raw() {
+                let Ok(arg) = result else {
+                    return Err(ArgumentListError::Improper);
+                };
+                if args.len() == max_args {
+                    return Err(ArgumentListError::TooManyArguments);
+                }
+                args.push(arg);
+            }
+            Ok(args)
+        }
+        _ => Err(ArgumentListError::Improper),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::term::{atoms, ListBuilder};
+
+    #[test]
+    fn list_to_args_proper_list_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let mut builder = ListBuilder::new(&heap);
+        builder.push(Term::Int(3)).unwrap();
+        builder.push(Term::Int(2)).unwrap();
+        builder.push(Term::Int(1)).unwrap();
+        let list: OpaqueTerm = Term::Cons(builder.finish().unwrap()).into();
+
+        let args = list_to_args(list, MAX_ARGS).unwrap();
+        let expected: [OpaqueTerm; 3] = [
+            Term::Int(1).into(),
+            Term::Int(2).into(),
+            Term::Int(3).into(),
+        ];
+        assert_eq!(args.as_slice(), &expected);
+
+        assert_eq!(list_to_args(OpaqueTerm::NIL, 0), Ok(Arguments::new()));
+        assert_eq!(list_to_args(list, 3).map(|args| args.len()), Ok(3));
+        assert_eq!(
+            list_to_args(list, 2),
+            Err(ArgumentListError::TooManyArguments)
+        );
+    }
+
+    #[test]
+    fn list_to_args_malformed_list_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        let not_lists = [
+            Term::Int(1),
+            Term::Atom(atoms::Undefined),
+            Term::Float(1.0.into()),
+        ];
+        for term in not_lists.iter().cloned() {
+            assert_eq!(
+                list_to_args(term.into(), MAX_ARGS),
+                Err(ArgumentListError::Improper)
+            );
+        }
+
+        // Construct improper lists of every length up to 8, with a variety of improper tails,
+        // and ensure they are rejected regardless of where the limit falls
+        for len in 1..=8 {
+            for tail in not_lists.iter().cloned() {
+                let mut builder = ListBuilder::new_improper(tail.into(), &heap);
+                for i in 0..len {
+                    builder.push(Term::Int(i)).unwrap();
+                }
+                let list: OpaqueTerm = Term::Cons(builder.finish().unwrap()).into();
+
+                assert_eq!(
+                    list_to_args(list, MAX_ARGS),
+                    Err(ArgumentListError::Improper)
+                );
+                // The list is only traversed as far as needed to detect it is too long
+                assert_eq!(
+                    list_to_args(list, len as usize - 1),
+                    Err(ArgumentListError::TooManyArguments)
+                );
+            }
+        }
+    }
+}
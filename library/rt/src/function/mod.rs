@@ -1,8 +1,10 @@
 mod apply;
+mod args;
 mod mfa;
 mod result;
 
 pub use self::apply::*;
+pub use self::args::{list_to_args, ArgumentListError, Arguments, MAX_ARGS};
 pub use self::mfa::ModuleFunctionArity;
 pub use self::result::ErlangResult;
 
@@ -356,6 +356,94 @@ impl Reap for Range<*mut u8> {
     }
 }
 
+/// Returns the total size in bytes of the reference-counted binaries referenced from `range`
+///
+/// The range must hold only whole terms, so this must not be used on the source heap of a
+/// collection in progress. Dead terms in the range are counted too, as the references they hold
+/// keep their binaries alive until the heap is next collected.
+pub(crate) fn rc_binary_size(range: Range<*mut u8>) -> usize {
+    use firefly_binary::Bitstring;
+
+    let rc_size = |term: OpaqueTerm| -> usize {
+        if !term.is_rc() {
+            return 0;
+        }
+        match term.into() {
+            Term::RcBinary(bin) => bin.byte_size(),
+            _ => 0,
+        }
+    };
+
+    let mut iter = HeapRange::new(range.start.cast(), range.end.cast());
+    let mut size = 0;
+    while let Some(ptr) = iter.next() {
+        let term = unsafe { *ptr };
+
+        if term.is_rc() {
+            size += rc_size(term);
+            continue;
+        }
+
+        // Skip over any holes left behind by terms which were resized in place
+        if term.is_gcbox() || term.is_tuple() {
+            if let Some(next) = iter.peek() {
+                let term2 = unsafe { *next };
+                if term2.is_hole() {
+                    iter.skip_bytes(unsafe { term2.hole_size() });
+                }
+            }
+            continue;
+        }
+
+        if !term.is_header() {
+            continue;
+        }
+
+        // See `Reap` for how each type of term is laid out
+        let header = unsafe { term.as_header() };
+        match header.tag() {
+            Tag::Tuple => {
+                let tuple = unsafe { &*<Tuple as Boxable>::from_raw_parts(ptr.cast(), header) };
+                size += tuple.as_slice().iter().copied().map(rc_size).sum::<usize>();
+                iter.skip_bytes(mem::size_of_val(tuple));
+            }
+            Tag::Map => {
+                let map = unsafe { &*<SmallMap as Boxable>::from_raw_parts(ptr.cast(), header) };
+                size += map.keys().iter().copied().map(rc_size).sum::<usize>();
+                size += map.values().iter().copied().map(rc_size).sum::<usize>();
+                iter.skip_bytes(mem::size_of_val(map));
+            }
+            Tag::Closure => {
+                let closure =
+                    unsafe { &*<Closure as Boxable>::from_raw_parts(ptr.cast(), header) };
+                size += closure.env().iter().copied().map(rc_size).sum::<usize>();
+                iter.skip_bytes(mem::size_of_val(closure));
+            }
+            Tag::Slice => {
+                let slice = unsafe { &*<BitSlice as Boxable>::from_raw_parts(ptr.cast(), header) };
+                size += rc_size(slice.owner);
+                iter.skip_bytes(mem::size_of::<BitSlice>());
+            }
+            Tag::Match => {
+                let matcher =
+                    unsafe { &*<MatchContext as Boxable>::from_raw_parts(ptr.cast(), header) };
+                size += rc_size(matcher.owner);
+                iter.skip_bytes(mem::size_of::<MatchContext>());
+            }
+            Tag::BigInt => iter.skip_bytes(mem::size_of::<BigInt>()),
+            Tag::Pid => iter.skip_bytes(mem::size_of::<Pid>()),
+            Tag::Port => unimplemented!(),
+            Tag::Reference => iter.skip_bytes(mem::size_of::<Reference>()),
+            Tag::Binary => {
+                let bin =
+                    unsafe { &*<BinaryData as Boxable>::from_raw_parts(ptr.cast(), header) };
+                iter.skip_bytes(mem::size_of_val(bin));
+            }
+        }
+    }
+    size
+}
+
 pub struct HeapIter(HeapRange);
 impl HeapIter {
     pub fn new(range: Range<*mut u8>) -> Self {
@@ -449,6 +537,29 @@ mod tests {
         assert_eq!(weak.upgrade(), None);
     }
 
+    #[test]
+    fn rc_binary_size_test() {
+        let heap = FixedSizeHeap::<256>::default();
+
+        let rc = BinaryData::from_str("foobar");
+        let small = BinaryData::from_small_str("not counted", &heap).unwrap();
+
+        // Each reference is counted, whether it is in a container or a cons cell
+        Tuple::from_slice(
+            &[Term::HeapBinary(small).into(), Term::RcBinary(rc.clone()).into()],
+            &heap,
+        )
+        .unwrap();
+        let mut list = ListBuilder::new(&heap);
+        list.push(Term::RcBinary(rc)).unwrap();
+        list.push(Term::Int(1)).unwrap();
+        list.finish().unwrap();
+
+        assert_eq!(rc_binary_size(heap.used_range()), 12);
+
+        heap.reap();
+    }
+
     #[test]
     fn heap_iter_test() {
         let heap = FixedSizeHeap::<1024>::default();
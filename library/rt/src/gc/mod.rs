@@ -5,7 +5,7 @@ mod roots;
 mod sweep;
 
 use self::collector::HeapIter;
-pub(crate) use self::collector::Reap;
+pub(crate) use self::collector::{rc_binary_size, Reap};
 pub use self::collector::SimpleCollector;
 pub use self::full::{FullCollection, ReferenceCollection};
 pub use self::minor::MinorCollection;
@@ -26,7 +26,6 @@ use core::ptr::{self, NonNull, Pointee};
 
 use log::trace;
 
-use crate::error::ExceptionFlags;
 use crate::function::ErlangResult;
 use crate::process::{ProcessHeap, ProcessLock, StatusFlags};
 use crate::term::*;
@@ -40,11 +39,6 @@ pub enum GcError {
     /// The system is out of memory, and there is not much you can do
     /// but panic, however this choice is left up to the caller
     AllocError,
-    /// Occurs when a process is configured with a maximum heap size,
-    /// and a projected heap growth is found to exceed the limit. In
-    /// this situation the only meaningful thing to do is to kill the
-    /// process
-    MaxHeapSizeExceeded,
     /// Indicates that an allocation could not be filled without first
     /// performing a full sweep collection
     FullsweepRequired,
@@ -58,7 +52,6 @@ impl fmt::Display for GcError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::AllocError => f.write_str("unable to allocate memory for garbage collection"),
-            Self::MaxHeapSizeExceeded => f.write_str("maximum heap size exceeded"),
             Self::FullsweepRequired => f.write_str("a full garbage collection sweep is required"),
         }
     }
@@ -138,18 +131,7 @@ pub trait CollectionType {
 /// Garbage collection intrinsic for use by natively-implemented functions
 #[inline(never)]
 pub fn garbage_collect(process: &mut ProcessLock, roots: RootSet) -> Result<(), ()> {
-    use crate::services::error_logger;
     use core::sync::atomic::Ordering;
-    use smallvec::SmallVec;
-
-    const MAX_HEAP_ERROR_FORMAT: &'static str = "      Process:            ~p~n\
-                                                        Context:            maximum heap size reached~n\
-                                                        Max Heap Size:      ~p~n\
-                                                        Total Heap Size:    ~p~n\
-                                                        Kill:               ~p~n\
-                                                        Error Logger:       ~p~n\
-                                                        Message Queue Len:  ~p~n\
-                                                        GC Info:            ~p~n";
 
     process.set_status_flags(StatusFlags::GC, Ordering::Relaxed);
     match process.garbage_collect(roots) {
@@ -167,59 +149,6 @@ pub fn garbage_collect(process: &mut ProcessLock, roots: RootSet) -> Result<(),
             // Unable to allocate from the system allocator, so try to shut down gracefully
             system_limit_exceeded("out of memory");
         }
-        Err(GcError::MaxHeapSizeExceeded) => {
-            // GC would require growing the process heap beyond the configured limit,
-            // so send an untrappable kill signal to the process
-            let max_heap_size = process.max_heap_size();
-            trace!(
-                "garbage collection could not complete, max heap size of {:?} was reached",
-                max_heap_size.size
-            );
-            if max_heap_size.error_logger {
-                // Allocate a heap fragment to hold the format string arguments
-                let max_heap_size_in_bytes = match max_heap_size.size {
-                    None => Term::Int(0),
-                    Some(size) => Term::Int(size.get().try_into().unwrap()),
-                };
-                let process_heap_size_in_bytes =
-                    Term::Int(process.heap.heap_size().try_into().unwrap());
-                let signals_len = {
-                    let signals = process.signals().lock();
-                    Term::Int(signals.len().try_into().unwrap())
-                };
-                let mut pid = process.pid();
-                let mut format_args = SmallVec::<[OpaqueTerm; 8]>::default();
-                format_args.push(unsafe { Gc::from_raw(&mut pid) }.into());
-                format_args.push(max_heap_size_in_bytes.into());
-                format_args.push(process_heap_size_in_bytes.into());
-                format_args.push(max_heap_size.kill.into());
-                format_args.push(true.into());
-                format_args.push(signals_len.into());
-                format_args.push(OpaqueTerm::NIL);
-
-                error_logger::send_error_term_to_logger(
-                    MAX_HEAP_ERROR_FORMAT,
-                    format_args,
-                    process.group_leader().cloned(),
-                )
-                .ok();
-            }
-            if max_heap_size.kill {
-                process.exception_info.flags = ExceptionFlags::EXIT;
-                process.exception_info.reason = atoms::Killed.into();
-                process.exception_info.value = atoms::Killed.into();
-                process.exception_info.trace = None;
-                process.stack.nocatch();
-                process.set_status_flags(
-                    StatusFlags::EXITING | StatusFlags::ACTIVE,
-                    Ordering::Release,
-                );
-                process.remove_status_flags(StatusFlags::GC, Ordering::Release);
-                Err(())
-            } else {
-                Ok(())
-            }
-        }
         Err(GcError::FullsweepRequired) => unreachable!(),
     }
 }
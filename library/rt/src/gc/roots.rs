@@ -34,7 +34,7 @@ impl fmt::Debug for Root {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct RootSet {
     roots: Vec<Root>,
 }
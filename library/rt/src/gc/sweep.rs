@@ -244,7 +244,8 @@ impl Sweep for Gc<Map> {
             });
         }
 
-        if !collector.should_sweep(ptr) {
+        // Literal maps are shared by every process, and are never moved
+        if self.is_literal() || !collector.should_sweep(ptr) {
             return Ok(Move::Skipped);
         }
 
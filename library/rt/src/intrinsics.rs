@@ -5,7 +5,7 @@
 #[cfg(feature = "async")]
 use core::ptr::NonNull;
 
-use crate::cmp::ExactEq;
+use crate::cmp::{cmp_terms, ExactEq};
 use crate::term::{OpaqueTerm, Term, TermType};
 
 #[inline]
@@ -83,41 +83,37 @@ pub extern "C" fn size(value: OpaqueTerm) -> usize {
 #[inline]
 #[export_name = "__firefly_builtin_gte"]
 pub extern "C" fn gte(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    lhs >= rhs
+    cmp_terms(lhs.into(), rhs.into()).is_ge()
 }
 
 #[inline]
 #[export_name = "__firefly_builtin_gt"]
 pub extern "C" fn gt(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    lhs > rhs
+    cmp_terms(lhs.into(), rhs.into()).is_gt()
 }
 
 #[inline]
 #[export_name = "__firefly_builtin_lt"]
 pub extern "C" fn lt(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    lhs < rhs
+    cmp_terms(lhs.into(), rhs.into()).is_lt()
 }
 
 #[inline]
 #[export_name = "__firefly_builtin_lte"]
 pub extern "C" fn lte(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    lhs <= rhs
+    cmp_terms(lhs.into(), rhs.into()).is_le()
 }
 
 #[inline]
 #[export_name = "__firefly_builtin_eq"]
 pub extern "C" fn eq(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    let lhs: Term = lhs.into();
-    let rhs: Term = rhs.into();
-    lhs == rhs
+    lhs == rhs || cmp_terms(lhs.into(), rhs.into()).is_eq()
 }
 
 #[inline]
 #[export_name = "__firefly_builtin_ne"]
 pub extern "C" fn ne(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
-    let lhs: Term = lhs.into();
-    let rhs: Term = rhs.into();
-    lhs != rhs
+    !eq(lhs, rhs)
 }
 
 #[inline]
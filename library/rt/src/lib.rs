@@ -81,11 +81,15 @@ pub mod bifs;
 pub mod cmp;
 pub mod drivers;
 pub mod error;
+pub mod etf;
 pub mod function;
 pub mod fundamental;
 pub mod gc;
 pub mod intrinsics;
+pub mod match_spec;
 pub mod process;
 pub mod scheduler;
 pub mod services;
 pub mod term;
+#[cfg(feature = "proptest")]
+pub mod testing;
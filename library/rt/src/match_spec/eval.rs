@@ -0,0 +1,551 @@
+use alloc::alloc::{AllocError, Layout};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+use core::ptr::{self, NonNull};
+
+use firefly_alloc::fragment::HeapFragment;
+use firefly_binary::{Bitstring, Selection};
+use firefly_number::{Float, Int, Number};
+use smallvec::SmallVec;
+
+use crate::cmp::cmp_terms;
+use crate::gc::Gc;
+use crate::services::distribution;
+use crate::term::*;
+
+use super::{Action, Expr, Guard, MatchSpec, Pattern};
+
+/// The side effects of the actions called by the body of a trace match spec
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TraceActions {
+    /// The argument of the last call to `message/1`, if any
+    pub message: Option<OpaqueTerm>,
+    pub return_trace: bool,
+    pub exception_trace: bool,
+}
+
+/// The outcome of running a match spec against a term which matched one of its clauses
+#[derive(Debug, Copy, Clone)]
+pub struct Matched {
+    /// The value of the last expression in the clause body, or `'EXIT'` if the body raised
+    pub result: OpaqueTerm,
+    pub actions: TraceActions,
+}
+
+/// Runs compiled match specs, and owns any terms constructed while doing so
+///
+/// Terms produced by an evaluator may reference both the terms the match spec was run against,
+/// and the literals of the match spec itself, so they must be copied before either of those, or
+/// the evaluator, go away.
+pub struct Evaluator {
+    /// The pid returned by `self()`, if running on behalf of a process
+    self_pid: Option<Pid>,
+    fragments: Vec<NonNull<HeapFragment>>,
+    /// Terms allocated in `fragments` which own resources that must be released on drop
+    owned: Vec<OpaqueTerm>,
+}
+impl Evaluator {
+    pub fn new(self_pid: Option<Pid>) -> Self {
+        Self {
+            self_pid,
+            fragments: Vec::new(),
+            owned: Vec::new(),
+        }
+    }
+
+    /// Runs `spec` against `term`, returning the outcome of the first clause which matches
+    pub fn run(&mut self, spec: &MatchSpec, term: OpaqueTerm) -> Option<Matched> {
+        let mut bindings = Vec::new();
+        for clause in spec.clauses.iter() {
+            bindings.clear();
+            bindings.resize(clause.arity, OpaqueTerm::NONE);
+            if !matches(&clause.head, term, &mut bindings) {
+                continue;
+            }
+
+            let mut frame = Frame {
+                term,
+                bindings: &bindings,
+                actions: TraceActions::default(),
+            };
+            // A guard which raises an exception fails just like one which evaluates to false
+            let passed = clause
+                .guards
+                .iter()
+                .all(|guard| self.eval(&mut frame, guard) == Ok(OpaqueTerm::TRUE));
+            if !passed {
+                continue;
+            }
+
+            let mut result = OpaqueTerm::TRUE;
+            for expr in clause.body.iter() {
+                match self.eval(&mut frame, expr) {
+                    Ok(value) => result = value,
+                    Err(_) => {
+                        result = atoms::UppercaseExit.into();
+                        break;
+                    }
+                }
+            }
+            return Some(Matched {
+                result,
+                actions: frame.actions,
+            });
+        }
+        None
+    }
+
+    /// Constructs a proper list of `elements` owned by this evaluator
+    pub fn list(&mut self, elements: &[OpaqueTerm]) -> Result<OpaqueTerm, AllocError> {
+        self.improper_list(elements, OpaqueTerm::NIL)
+    }
+
+    /// Constructs a tuple of `elements` owned by this evaluator
+    pub fn tuple(&mut self, elements: &[OpaqueTerm]) -> Result<OpaqueTerm, AllocError> {
+        let mut layout = LayoutBuilder::new();
+        layout.build_tuple(elements.len());
+        let fragment = self.alloc(layout.finish())?;
+        let tuple = Tuple::from_slice(elements, unsafe { fragment.as_ref() })?;
+        Ok(tuple.into())
+    }
+
+    fn improper_list(
+        &mut self,
+        elements: &[OpaqueTerm],
+        tail: OpaqueTerm,
+    ) -> Result<OpaqueTerm, AllocError> {
+        if elements.is_empty() {
+            return Ok(tail);
+        }
+        let mut layout = LayoutBuilder::new();
+        layout.build_list(elements.len());
+        let fragment = self.alloc(layout.finish())?;
+        let fragment = unsafe { fragment.as_ref() };
+        let mut list = tail;
+        for head in elements.iter().rev().copied() {
+            let cons = Cons::new_in(Cons { head, tail: list }, fragment)?;
+            list = cons.into();
+        }
+        Ok(list)
+    }
+
+    fn alloc(&mut self, layout: Layout) -> Result<NonNull<HeapFragment>, AllocError> {
+        let fragment = HeapFragment::new(layout, None)?;
+        self.fragments.push(fragment);
+        Ok(fragment)
+    }
+
+    fn eval_all(
+        &mut self,
+        frame: &mut Frame<'_>,
+        exprs: &[Expr],
+    ) -> Result<SmallVec<[OpaqueTerm; 4]>, ()> {
+        exprs.iter().map(|expr| self.eval(frame, expr)).collect()
+    }
+
+    fn eval(&mut self, frame: &mut Frame<'_>, expr: &Expr) -> Result<OpaqueTerm, ()> {
+        match expr {
+            Expr::Const(term) => Ok(*term),
+            Expr::Var(slot) => Ok(frame.bindings[*slot]),
+            Expr::WholeMatch => Ok(frame.term),
+            Expr::AllBindings => self.list(frame.bindings).map_err(|_| ()),
+            Expr::Tuple(elements) => {
+                let elements = self.eval_all(frame, elements)?;
+                self.tuple(&elements).map_err(|_| ())
+            }
+            Expr::Cons(head, tail) => {
+                let head = self.eval(frame, head)?;
+                let tail = self.eval(frame, tail)?;
+                self.improper_list(&[head], tail).map_err(|_| ())
+            }
+            Expr::Map(entries) => {
+                let mut kvs = Vec::with_capacity(entries.len());
+                for (key, value) in entries.iter() {
+                    kvs.push((self.eval(frame, key)?, self.eval(frame, value)?));
+                }
+                let mut layout = LayoutBuilder::new();
+                layout.build_map(kvs.len());
+                let fragment = self.alloc(layout.finish()).map_err(|_| ())?;
+                let map = Map::from_iter(kvs.into_iter(), unsafe { fragment.as_ref() })
+                    .map_err(|_| ())?;
+                Ok(map.into())
+            }
+            Expr::AndAlso(exprs) => {
+                for expr in exprs.iter() {
+                    let value = self.eval(frame, expr)?;
+                    if value == OpaqueTerm::FALSE {
+                        return Ok(value);
+                    }
+                    if value != OpaqueTerm::TRUE {
+                        return Err(());
+                    }
+                }
+                Ok(OpaqueTerm::TRUE)
+            }
+            Expr::OrElse(exprs) => {
+                for expr in exprs.iter() {
+                    let value = self.eval(frame, expr)?;
+                    if value == OpaqueTerm::TRUE {
+                        return Ok(value);
+                    }
+                    if value != OpaqueTerm::FALSE {
+                        return Err(());
+                    }
+                }
+                Ok(OpaqueTerm::FALSE)
+            }
+            Expr::Call(guard, args) => {
+                let args = self.eval_all(frame, args)?;
+                self.call(*guard, &args)
+            }
+            Expr::Action(action, args) => {
+                let args = self.eval_all(frame, args)?;
+                match action {
+                    Action::Message => frame.actions.message = Some(args[0]),
+                    Action::ReturnTrace => frame.actions.return_trace = true,
+                    Action::ExceptionTrace => frame.actions.exception_trace = true,
+                    Action::Display => (),
+                    Action::Caller => return Ok(atoms::Undefined.into()),
+                }
+                Ok(OpaqueTerm::TRUE)
+            }
+        }
+    }
+
+    fn call(&mut self, guard: Guard, args: &[OpaqueTerm]) -> Result<OpaqueTerm, ()> {
+        let arg = |index: usize| -> Term { args[index].into() };
+        let result = match guard {
+            Guard::IsAtom => matches!(arg(0), Term::Atom(_) | Term::Bool(_)),
+            Guard::IsBinary => arg(0).as_binary().is_some(),
+            Guard::IsBitstring => arg(0).is_bitstring(),
+            Guard::IsBoolean => matches!(arg(0), Term::Bool(_)),
+            Guard::IsFloat => matches!(arg(0), Term::Float(_)),
+            Guard::IsFunction => matches!(arg(0), Term::Closure(_)),
+            Guard::IsFunction2 => match (arg(0), arg(1)) {
+                (Term::Closure(fun), Term::Int(arity)) if arity >= 0 => fun.arity as i64 == arity,
+                (_, Term::Int(arity)) if arity >= 0 => false,
+                _ => return Err(()),
+            },
+            Guard::IsInteger => matches!(arg(0), Term::Int(_) | Term::BigInt(_)),
+            Guard::IsList => matches!(arg(0), Term::Nil | Term::Cons(_)),
+            Guard::IsMap => matches!(arg(0), Term::Map(_)),
+            Guard::IsMapKey => match arg(1) {
+                Term::Map(map) => map.contains_key(args[0]),
+                _ => return Err(()),
+            },
+            Guard::IsNumber => matches!(arg(0), Term::Int(_) | Term::BigInt(_) | Term::Float(_)),
+            Guard::IsPid => matches!(arg(0), Term::Pid(_)),
+            Guard::IsPort => matches!(arg(0), Term::Port(_)),
+            Guard::IsRecord => match (arg(0), arg(1), arg(2)) {
+                (Term::Tuple(tuple), Term::Atom(_), Term::Int(size)) => {
+                    tuple.len() as i64 == size && tuple.len() > 0 && tuple[0] == args[1]
+                }
+                (_, Term::Atom(_), Term::Int(_)) => false,
+                _ => return Err(()),
+            },
+            Guard::IsReference => matches!(arg(0), Term::Reference(_)),
+            Guard::IsTuple => matches!(arg(0), Term::Tuple(_)),
+            Guard::Abs => {
+                return match to_number(args[0])? {
+                    Number::Integer(i) => self.int(i.abs()),
+                    Number::Float(f) => Ok(Term::Float(f.abs()).into()),
+                }
+            }
+            Guard::BinaryPart2 => {
+                let Term::Tuple(start_length) = arg(1) else { return Err(()); };
+                if start_length.len() != 2 {
+                    return Err(());
+                }
+                return self.binary_part(args[0], start_length[0], start_length[1]);
+            }
+            Guard::BinaryPart3 => return self.binary_part(args[0], args[1], args[2]),
+            Guard::BitSize => {
+                let bits = arg(0);
+                let bits = bits.as_bitstring().ok_or(())?;
+                return self.int(Int::from(bits.bit_size()));
+            }
+            Guard::ByteSize => {
+                let bits = arg(0);
+                let bits = bits.as_bitstring().ok_or(())?;
+                return self.int(Int::from(bits.byte_size()));
+            }
+            Guard::Element => {
+                let (Term::Int(index), Term::Tuple(tuple)) = (arg(0), arg(1)) else { return Err(()); };
+                let index = usize::try_from(index).map_err(|_| ())?;
+                return index.checked_sub(1).and_then(|i| tuple.get(i)).ok_or(());
+            }
+            Guard::Float => {
+                return match to_number(args[0])? {
+                    Number::Integer(i) => Float::new(i.to_float())
+                        .map(|f| Term::Float(f).into())
+                        .map_err(|_| ()),
+                    Number::Float(_) => Ok(args[0]),
+                }
+            }
+            Guard::Hd => {
+                let Term::Cons(cons) = arg(0) else { return Err(()); };
+                return Ok(cons.head);
+            }
+            Guard::Tl => {
+                let Term::Cons(cons) = arg(0) else { return Err(()); };
+                return Ok(cons.tail);
+            }
+            Guard::Length => {
+                return match arg(0) {
+                    Term::Nil => Ok(Term::Int(0).into()),
+                    Term::Cons(cons) => {
+                        let len = cons.length().map_err(|_| ())?;
+                        self.int(Int::from(len))
+                    }
+                    _ => Err(()),
+                }
+            }
+            Guard::MapGet => {
+                let Term::Map(map) = arg(1) else { return Err(()); };
+                return map.get(args[0]).ok_or(());
+            }
+            Guard::MapSize => {
+                let Term::Map(map) = arg(0) else { return Err(()); };
+                return self.int(Int::from(map.size()));
+            }
+            Guard::Max => {
+                return match cmp_terms(arg(0), arg(1)) {
+                    Ordering::Less => Ok(args[1]),
+                    _ => Ok(args[0]),
+                }
+            }
+            Guard::Min => {
+                return match cmp_terms(arg(0), arg(1)) {
+                    Ordering::Greater => Ok(args[1]),
+                    _ => Ok(args[0]),
+                }
+            }
+            Guard::Node0 => return Ok(distribution::current_node().name().into()),
+            Guard::Node1 => {
+                let node = match arg(0) {
+                    Term::Pid(pid) => pid.node(),
+                    Term::Port(port) => port.node(),
+                    _ => return Err(()),
+                };
+                let node = node.unwrap_or_else(distribution::current_node);
+                return Ok(node.name().into());
+            }
+            Guard::Round => return self.float_to_int(args[0], f64::round),
+            Guard::Trunc => return self.float_to_int(args[0], f64::trunc),
+            Guard::SelfPid => {
+                let pid = self.self_pid.clone().ok_or(())?;
+                let mut layout = LayoutBuilder::new();
+                layout.build_pid();
+                let fragment = self.alloc(layout.finish()).map_err(|_| ())?;
+                let pid: OpaqueTerm = Gc::new_in(pid, unsafe { fragment.as_ref() })
+                    .map_err(|_| ())?
+                    .into();
+                self.owned.push(pid);
+                return Ok(pid);
+            }
+            Guard::Size => {
+                let term = arg(0);
+                let size = match term {
+                    Term::Tuple(ref tuple) => tuple.len(),
+                    _ => term.as_binary().ok_or(())?.byte_size(),
+                };
+                return self.int(Int::from(size));
+            }
+            Guard::TupleSize => {
+                let Term::Tuple(tuple) = arg(0) else { return Err(()); };
+                return self.int(Int::from(tuple.len()));
+            }
+            Guard::Not => !to_bool(args[0])?,
+            Guard::And => to_bool(args[0])? & to_bool(args[1])?,
+            Guard::Or => to_bool(args[0])? | to_bool(args[1])?,
+            Guard::Xor => to_bool(args[0])? ^ to_bool(args[1])?,
+            Guard::Pos => {
+                to_number(args[0])?;
+                return Ok(args[0]);
+            }
+            Guard::Neg => return self.number((-arg(0)).map_err(|_| ())?),
+            Guard::Add => return self.number((arg(0) + arg(1)).map_err(|_| ())?),
+            Guard::Sub => return self.number((arg(0) - arg(1)).map_err(|_| ())?),
+            Guard::Mul => return self.number((arg(0) * arg(1)).map_err(|_| ())?),
+            Guard::Div => {
+                let lhs = to_number(args[0])?;
+                let rhs = to_number(args[1])?;
+                let rhs = to_f64(&rhs);
+                if rhs == 0.0 {
+                    return Err(());
+                }
+                let result = Float::new(to_f64(&lhs) / rhs).map_err(|_| ())?;
+                return Ok(Term::Float(result).into());
+            }
+            Guard::IntDiv => {
+                let lhs = to_int(args[0])?;
+                let rhs = to_int(args[1])?;
+                return self.int((lhs / rhs).map_err(|_| ())?);
+            }
+            Guard::Rem => return self.int((arg(0) % arg(1)).map_err(|_| ())?.map_err(|_| ())?),
+            Guard::Band => return self.int((arg(0) & arg(1)).map_err(|_| ())?),
+            Guard::Bor => return self.int((arg(0) | arg(1)).map_err(|_| ())?),
+            Guard::Bxor => return self.int((arg(0) ^ arg(1)).map_err(|_| ())?),
+            Guard::Bnot => return self.int(!to_int(args[0])?),
+            Guard::Bsl => return self.int((arg(0) << arg(1)).map_err(|_| ())?),
+            Guard::Bsr => return self.int((arg(0) >> arg(1)).map_err(|_| ())?),
+            Guard::Gt => cmp_terms(arg(0), arg(1)) == Ordering::Greater,
+            Guard::Gte => cmp_terms(arg(0), arg(1)) != Ordering::Less,
+            Guard::Lt => cmp_terms(arg(0), arg(1)) == Ordering::Less,
+            Guard::Lte => cmp_terms(arg(0), arg(1)) != Ordering::Greater,
+            Guard::Eq => cmp_terms(arg(0), arg(1)) == Ordering::Equal,
+            Guard::Neq => cmp_terms(arg(0), arg(1)) != Ordering::Equal,
+            Guard::ExactEq => exact_eq(args[0], args[1]),
+            Guard::ExactNeq => !exact_eq(args[0], args[1]),
+        };
+        Ok(result.into())
+    }
+
+    fn binary_part(
+        &mut self,
+        binary: OpaqueTerm,
+        start: OpaqueTerm,
+        length: OpaqueTerm,
+    ) -> Result<OpaqueTerm, ()> {
+        let (Term::Int(start), Term::Int(length)) = (start.into(), length.into()) else { return Err(()); };
+        let start = usize::try_from(start).map_err(|_| ())?;
+        let length = isize::try_from(length).map_err(|_| ())?;
+        let term: Term = binary.into();
+        let bin = term.as_binary().ok_or(())?;
+        let selection = bin.select_binary_part(start, length).map_err(|_| ())?;
+        let selection = unsafe { mem::transmute::<_, Selection<'static>>(selection) };
+        let fragment = self.alloc(Layout::new::<BitSlice>()).map_err(|_| ())?;
+        let slice = BitSlice::from_selection(binary, selection);
+        let slice: OpaqueTerm = Gc::new_in(slice, unsafe { fragment.as_ref() })
+            .map_err(|_| ())?
+            .into();
+        self.owned.push(slice);
+        Ok(slice)
+    }
+
+    fn float_to_int(&mut self, term: OpaqueTerm, op: fn(f64) -> f64) -> Result<OpaqueTerm, ()> {
+        match to_number(term)? {
+            Number::Integer(_) => Ok(term),
+            Number::Float(f) => {
+                let f = op(f.inner());
+                // Floats this large are integral, but beyond the range we can convert directly
+                if f.abs() >= i128::MAX as f64 {
+                    return Err(());
+                }
+                self.int(Int::from(f as i128))
+            }
+        }
+    }
+
+    fn number(&mut self, number: Number) -> Result<OpaqueTerm, ()> {
+        match number {
+            Number::Integer(i) => self.int(i),
+            Number::Float(f) => Ok(Term::Float(f).into()),
+        }
+    }
+
+    fn int(&mut self, i: Int) -> Result<OpaqueTerm, ()> {
+        let big = match i {
+            Int::Small(i) if OpaqueTerm::is_small_integer(i) => return Ok(Term::Int(i).into()),
+            Int::Small(i) => BigInt::new(i),
+            Int::Big(i) => BigInt::new(i),
+        };
+        let mut layout = LayoutBuilder::new();
+        layout.build_bigint();
+        let fragment = self.alloc(layout.finish()).map_err(|_| ())?;
+        let big: OpaqueTerm = Gc::new_in(big, unsafe { fragment.as_ref() })
+            .map_err(|_| ())?
+            .into();
+        self.owned.push(big);
+        Ok(big)
+    }
+}
+impl Drop for Evaluator {
+    fn drop(&mut self) {
+        // Most terms in the fragments only borrow from terms owned elsewhere, so rather than reaping
+        // the fragments, we release the resources held by the few terms which do own something
+        for term in self.owned.drain(..) {
+            match term.into() {
+                Term::BigInt(big) => unsafe { ptr::drop_in_place(big.as_non_null_ptr().as_ptr()) },
+                Term::Pid(pid) => unsafe { ptr::drop_in_place(pid.as_non_null_ptr().as_ptr()) },
+                Term::RefBinary(slice) => slice.owner.maybe_decrement_refcount(),
+                _ => unreachable!(),
+            }
+        }
+        for fragment in self.fragments.drain(..) {
+            unsafe {
+                ptr::drop_in_place(fragment.as_ptr());
+            }
+        }
+    }
+}
+
+/// The state of the clause being evaluated
+struct Frame<'a> {
+    term: OpaqueTerm,
+    bindings: &'a [OpaqueTerm],
+    actions: TraceActions,
+}
+
+fn matches(pattern: &Pattern, term: OpaqueTerm, bindings: &mut [OpaqueTerm]) -> bool {
+    match pattern {
+        Pattern::Any => true,
+        Pattern::Var(slot) => {
+            let bound = bindings[*slot];
+            if bound.is_none() {
+                bindings[*slot] = term;
+                true
+            } else {
+                exact_eq(bound, term)
+            }
+        }
+        Pattern::Literal(literal) => exact_eq(*literal, term),
+        Pattern::Tuple(elements) => match term.into() {
+            Term::Tuple(tuple) if tuple.len() == elements.len() => elements
+                .iter()
+                .zip(tuple.as_slice())
+                .all(|(pattern, element)| matches(pattern, *element, bindings)),
+            _ => false,
+        },
+        Pattern::Cons(head, tail) => match term.into() {
+            Term::Cons(cons) => {
+                matches(head, cons.head, bindings) && matches(tail, cons.tail, bindings)
+            }
+            _ => false,
+        },
+        Pattern::Map(entries) => match term.into() {
+            Term::Map(map) => entries.iter().all(|(key, pattern)| match map.get(*key) {
+                Some(value) => matches(pattern, value, bindings),
+                None => false,
+            }),
+            _ => false,
+        },
+    }
+}
+
+fn exact_eq(lhs: OpaqueTerm, rhs: OpaqueTerm) -> bool {
+    lhs == rhs || Term::from(lhs).exact_eq(&Term::from(rhs))
+}
+
+fn to_bool(term: OpaqueTerm) -> Result<bool, ()> {
+    match term.into() {
+        Term::Bool(b) => Ok(b),
+        _ => Err(()),
+    }
+}
+
+fn to_number(term: OpaqueTerm) -> Result<Number, ()> {
+    Term::from(term).try_into().map_err(|_| ())
+}
+
+fn to_int(term: OpaqueTerm) -> Result<Int, ()> {
+    Term::from(term).try_into().map_err(|_| ())
+}
+
+fn to_f64(number: &Number) -> f64 {
+    match number {
+        Number::Integer(i) => i.to_float(),
+        Number::Float(f) => f.inner(),
+    }
+}
@@ -0,0 +1,653 @@
+//! This module implements match specifications, the small pattern matching language used by ETS
+//! (e.g. `ets:select/2`) and the tracing facilities to select and transform terms.
+//!
+//! A match spec is a list of clauses of the form `{Head, Guards, Body}`, where `Head` is a pattern
+//! in which the atoms `'$1'`, `'$2'`, etc. are variables and `'_'` matches anything, `Guards` is a
+//! list of guard expressions which must all evaluate to `true`, and `Body` is a list of
+//! expressions, the last of which is the result of the clause. Expressions use a term encoding of
+//! Erlang syntax, e.g. `{'+', '$1', 1}` is a call, `{{'$1', '$2'}}` constructs a tuple, and
+//! `{const, T}` quotes `T`.
+//!
+//! Match specs are compiled once via [`MatchSpec::compile`], which validates the spec and
+//! resolves variables and functions, and are then run against any number of terms using an
+//! [`Evaluator`]. Terms constructed while running a match spec are allocated in heap fragments
+//! owned by the evaluator, so results must be copied elsewhere before it is dropped.
+mod eval;
+
+pub use self::eval::{Evaluator, Matched, TraceActions};
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::alloc::AllocError;
+use core::fmt;
+
+use crate::term::*;
+
+/// The kinds of match specs, which differ in which actions are allowed in clause bodies
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MatchSpecKind {
+    /// A match spec used to select objects from a table, e.g. via `ets:select/2`
+    Table,
+    /// A match spec used to select and act on traced function calls, whose heads match the
+    /// argument list of the call
+    Trace,
+}
+
+/// The errors which can occur when compiling a match spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchSpecError {
+    /// The match spec is not a proper list
+    NotAList,
+    /// A clause is not a tuple of `{Head, Guards, Body}`, with `Guards` and `Body` being lists
+    InvalidClause,
+    /// The head of a trace match spec clause is not a list
+    InvalidTraceHead,
+    /// A variable is used in a guard or body without being bound in the head
+    UnboundVariable(u32),
+    /// `'_'` was used outside of the head
+    WildcardInExpression,
+    /// A map key in the head contains a variable or wildcard
+    InvalidMapKey,
+    /// A tuple in an expression is neither a call, nor `{{...}}`, nor `{const, _}`
+    InvalidExpression,
+    /// An unknown function was called
+    UnknownFunction(Atom, usize),
+    /// An action was called in a guard, or in the body of a table match spec
+    ActionNotAllowed(Atom),
+    /// The spec could not be copied
+    Alloc,
+}
+impl From<AllocError> for MatchSpecError {
+    fn from(_: AllocError) -> Self {
+        Self::Alloc
+    }
+}
+impl fmt::Display for MatchSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAList => f.write_str("match_spec is not a list"),
+            Self::InvalidClause => {
+                f.write_str("match_spec clause is not of the form {Head, Guards, Body}")
+            }
+            Self::InvalidTraceHead => f.write_str("match head of trace match_spec is not a list"),
+            Self::UnboundVariable(n) => write!(f, "variable '${}' is unbound", n),
+            Self::WildcardInExpression => f.write_str("'_' is only allowed in the match head"),
+            Self::InvalidMapKey => f.write_str("map keys in the match head must be literals"),
+            Self::InvalidExpression => f.write_str("invalid expression in match_spec"),
+            Self::UnknownFunction(name, arity) => {
+                write!(
+                    f,
+                    "function {}/{} is not allowed in match_spec",
+                    name, arity
+                )
+            }
+            Self::ActionNotAllowed(name) => {
+                write!(f, "special form '{}' is not allowed in this context", name)
+            }
+            Self::Alloc => f.write_str("unable to allocate match_spec"),
+        }
+    }
+}
+
+/// A compiled match spec
+pub struct MatchSpec {
+    kind: MatchSpecKind,
+    clauses: Vec<Clause>,
+    /// A copy of the source term, which owns any literals referenced by the compiled clauses
+    source: TermFragment,
+}
+// The compiled form is immutable, and only refers to the private copy of the source term
+unsafe impl Send for MatchSpec {}
+unsafe impl Sync for MatchSpec {}
+impl fmt::Debug for MatchSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchSpec")
+            .field("kind", &self.kind)
+            .field("source", &self.source.term)
+            .finish()
+    }
+}
+
+struct Clause {
+    head: Pattern,
+    guards: Vec<Expr>,
+    body: Vec<Expr>,
+    /// The number of distinct variables bound by the head
+    arity: usize,
+}
+
+/// A compiled match head pattern
+///
+/// Variables are numbered by slot, the position of the variable in the ordered set of variables
+/// used in the head, so that `'$$'` is the bindings in slot order.
+enum Pattern {
+    /// Matches anything
+    Any,
+    /// Binds its slot on first occurrence, and otherwise matches the bound value
+    Var(usize),
+    Literal(OpaqueTerm),
+    Tuple(Vec<Pattern>),
+    Cons(Box<Pattern>, Box<Pattern>),
+    /// Matches maps containing at least the given keys, whose values match the given patterns
+    Map(Vec<(OpaqueTerm, Pattern)>),
+}
+
+/// A compiled guard or body expression
+enum Expr {
+    Const(OpaqueTerm),
+    Var(usize),
+    /// `'$_'`, the term being matched
+    WholeMatch,
+    /// `'$$'`, a list of all bindings in slot order
+    AllBindings,
+    Tuple(Vec<Expr>),
+    Cons(Box<Expr>, Box<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    AndAlso(Vec<Expr>),
+    OrElse(Vec<Expr>),
+    Call(Guard, Vec<Expr>),
+    Action(Action, Vec<Expr>),
+}
+
+/// The functions which may be called from match spec guards and bodies
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Guard {
+    IsAtom,
+    IsBinary,
+    IsBitstring,
+    IsBoolean,
+    IsFloat,
+    IsFunction,
+    IsFunction2,
+    IsInteger,
+    IsList,
+    IsMap,
+    IsMapKey,
+    IsNumber,
+    IsPid,
+    IsPort,
+    IsRecord,
+    IsReference,
+    IsTuple,
+    Abs,
+    BinaryPart2,
+    BinaryPart3,
+    BitSize,
+    ByteSize,
+    Element,
+    Float,
+    Hd,
+    Length,
+    MapGet,
+    MapSize,
+    Max,
+    Min,
+    Node0,
+    Node1,
+    Round,
+    SelfPid,
+    Size,
+    Tl,
+    Trunc,
+    TupleSize,
+    Not,
+    And,
+    Or,
+    Xor,
+    Pos,
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Rem,
+    Band,
+    Bor,
+    Bxor,
+    Bnot,
+    Bsl,
+    Bsr,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Eq,
+    Neq,
+    ExactEq,
+    ExactNeq,
+}
+impl Guard {
+    fn get(name: &str, arity: usize) -> Option<Self> {
+        let guard = match (name, arity) {
+            ("is_atom", 1) => Self::IsAtom,
+            ("is_binary", 1) => Self::IsBinary,
+            ("is_bitstring", 1) => Self::IsBitstring,
+            ("is_boolean", 1) => Self::IsBoolean,
+            ("is_float", 1) => Self::IsFloat,
+            ("is_function", 1) => Self::IsFunction,
+            ("is_function", 2) => Self::IsFunction2,
+            ("is_integer", 1) => Self::IsInteger,
+            ("is_list", 1) => Self::IsList,
+            ("is_map", 1) => Self::IsMap,
+            ("is_map_key", 2) => Self::IsMapKey,
+            ("is_number", 1) => Self::IsNumber,
+            ("is_pid", 1) => Self::IsPid,
+            ("is_port", 1) => Self::IsPort,
+            ("is_record", 3) => Self::IsRecord,
+            ("is_reference", 1) => Self::IsReference,
+            ("is_tuple", 1) => Self::IsTuple,
+            ("abs", 1) => Self::Abs,
+            ("binary_part", 2) => Self::BinaryPart2,
+            ("binary_part", 3) => Self::BinaryPart3,
+            ("bit_size", 1) => Self::BitSize,
+            ("byte_size", 1) => Self::ByteSize,
+            ("element", 2) => Self::Element,
+            ("float", 1) => Self::Float,
+            ("hd", 1) => Self::Hd,
+            ("length", 1) => Self::Length,
+            ("map_get", 2) => Self::MapGet,
+            ("map_size", 1) => Self::MapSize,
+            ("max", 2) => Self::Max,
+            ("min", 2) => Self::Min,
+            ("node", 0) => Self::Node0,
+            ("node", 1) => Self::Node1,
+            ("round", 1) => Self::Round,
+            ("self", 0) => Self::SelfPid,
+            ("size", 1) => Self::Size,
+            ("tl", 1) => Self::Tl,
+            ("trunc", 1) => Self::Trunc,
+            ("tuple_size", 1) => Self::TupleSize,
+            ("not", 1) => Self::Not,
+            ("and", 2) => Self::And,
+            ("or", 2) => Self::Or,
+            ("xor", 2) => Self::Xor,
+            ("+", 1) => Self::Pos,
+            ("-", 1) => Self::Neg,
+            ("+", 2) => Self::Add,
+            ("-", 2) => Self::Sub,
+            ("*", 2) => Self::Mul,
+            ("/", 2) => Self::Div,
+            ("div", 2) => Self::IntDiv,
+            ("rem", 2) => Self::Rem,
+            ("band", 2) => Self::Band,
+            ("bor", 2) => Self::Bor,
+            ("bxor", 2) => Self::Bxor,
+            ("bnot", 1) => Self::Bnot,
+            ("bsl", 2) => Self::Bsl,
+            ("bsr", 2) => Self::Bsr,
+            (">", 2) => Self::Gt,
+            (">=", 2) => Self::Gte,
+            ("<", 2) => Self::Lt,
+            ("=<", 2) => Self::Lte,
+            ("==", 2) => Self::Eq,
+            ("/=", 2) => Self::Neq,
+            ("=:=", 2) => Self::ExactEq,
+            ("=/=", 2) => Self::ExactNeq,
+            _ => return None,
+        };
+        Some(guard)
+    }
+}
+
+/// The actions which may be called from the body of a trace match spec
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Action {
+    /// `message(Term)` sets the trace message, or suppresses it if `Term` is `false`
+    Message,
+    /// `return_trace()` requests a trace message when the traced function returns
+    ReturnTrace,
+    /// `exception_trace()` is like `return_trace()`, but also traces exceptions
+    ExceptionTrace,
+    /// `display(Term)` is only meant for debugging, and is a no-op
+    Display,
+    /// `caller()` returns the calling function, which is unknown when testing match specs
+    Caller,
+}
+impl Action {
+    fn get(name: &str, arity: usize) -> Option<Self> {
+        match (name, arity) {
+            ("message", 1) => Some(Self::Message),
+            ("return_trace", 0) => Some(Self::ReturnTrace),
+            ("exception_trace", 0) => Some(Self::ExceptionTrace),
+            ("display", 1) => Some(Self::Display),
+            ("caller", 0) => Some(Self::Caller),
+            _ => None,
+        }
+    }
+}
+
+impl MatchSpec {
+    /// Compiles `spec` as a match spec of the given kind
+    pub fn compile(spec: Term, kind: MatchSpecKind) -> Result<Self, MatchSpecError> {
+        let source = TermFragment::clone_from(&spec)?;
+        let clauses = match source.term.into() {
+            Term::Nil => Vec::new(),
+            Term::Cons(cons) => {
+                let mut clauses = Vec::new();
+                for clause in cons.iter_raw() {
+                    let clause = clause.map_err(|_| MatchSpecError::NotAList)?;
+                    clauses.push(compile_clause(clause, kind)?);
+                }
+                clauses
+            }
+            _ => return Err(MatchSpecError::NotAList),
+        };
+        Ok(Self {
+            kind,
+            clauses,
+            source,
+        })
+    }
+
+    #[inline]
+    pub fn kind(&self) -> MatchSpecKind {
+        self.kind
+    }
+
+    /// Returns the match spec this was compiled from
+    ///
+    /// The term is owned by this match spec, and must be copied to outlive it.
+    #[inline]
+    pub fn source(&self) -> OpaqueTerm {
+        self.source.term
+    }
+}
+
+fn compile_clause(clause: OpaqueTerm, kind: MatchSpecKind) -> Result<Clause, MatchSpecError> {
+    let Term::Tuple(clause) = clause.into() else { return Err(MatchSpecError::InvalidClause); };
+    if clause.len() != 3 {
+        return Err(MatchSpecError::InvalidClause);
+    }
+    let head = clause[0];
+    if kind == MatchSpecKind::Trace
+        && !(head.is_list() || is_wildcard(head) || variable(head).is_some())
+    {
+        return Err(MatchSpecError::InvalidTraceHead);
+    }
+
+    let mut vars = BTreeSet::new();
+    collect_variables(head, &mut vars);
+    let vars = vars.into_iter().collect::<Vec<_>>();
+    let head = compile_pattern(head, &vars)?;
+
+    let mut compiler = ExprCompiler {
+        vars: &vars,
+        kind,
+        in_guard: true,
+    };
+    let guards = compiler.compile_list(clause[1])?;
+    compiler.in_guard = false;
+    let body = compiler.compile_list(clause[2])?;
+
+    Ok(Clause {
+        head,
+        guards,
+        body,
+        arity: vars.len(),
+    })
+}
+
+/// Returns the number of the variable `term`, if it is an atom of the form `'$N'`
+fn variable(term: OpaqueTerm) -> Option<u32> {
+    if !term.is_atom() {
+        return None;
+    }
+    let digits = term.as_atom().as_str().strip_prefix('$')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn is_wildcard(term: OpaqueTerm) -> bool {
+    term.is_atom() && term.as_atom() == "_"
+}
+
+fn collect_variables(term: OpaqueTerm, vars: &mut BTreeSet<u32>) {
+    if let Some(n) = variable(term) {
+        vars.insert(n);
+        return;
+    }
+    match term.into() {
+        Term::Tuple(tuple) => tuple
+            .as_slice()
+            .iter()
+            .for_each(|element| collect_variables(*element, vars)),
+        Term::Cons(cons) => {
+            collect_variables(cons.head, vars);
+            collect_variables(cons.tail, vars);
+        }
+        Term::Map(map) => map
+            .values()
+            .iter()
+            .for_each(|value| collect_variables(*value, vars)),
+        _ => (),
+    }
+}
+
+/// Returns true if `term` contains no variables or wildcards
+fn is_ground(term: OpaqueTerm) -> bool {
+    if variable(term).is_some() || is_wildcard(term) {
+        return false;
+    }
+    match term.into() {
+        Term::Tuple(tuple) => tuple.as_slice().iter().copied().all(is_ground),
+        Term::Cons(cons) => is_ground(cons.head) && is_ground(cons.tail),
+        Term::Map(map) => {
+            map.keys().iter().copied().all(is_ground) && map.values().iter().copied().all(is_ground)
+        }
+        _ => true,
+    }
+}
+
+fn compile_pattern(term: OpaqueTerm, vars: &[u32]) -> Result<Pattern, MatchSpecError> {
+    if is_wildcard(term) {
+        return Ok(Pattern::Any);
+    }
+    if let Some(n) = variable(term) {
+        return Ok(Pattern::Var(vars.binary_search(&n).unwrap()));
+    }
+    if is_ground(term) {
+        return Ok(Pattern::Literal(term));
+    }
+    match term.into() {
+        Term::Tuple(tuple) => tuple
+            .as_slice()
+            .iter()
+            .map(|element| compile_pattern(*element, vars))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Pattern::Tuple),
+        Term::Cons(cons) => Ok(Pattern::Cons(
+            Box::new(compile_pattern(cons.head, vars)?),
+            Box::new(compile_pattern(cons.tail, vars)?),
+        )),
+        Term::Map(map) => {
+            let mut entries = Vec::with_capacity(map.size());
+            for (key, value) in map.keys().iter().zip(map.values()) {
+                if !is_ground(*key) {
+                    return Err(MatchSpecError::InvalidMapKey);
+                }
+                entries.push((*key, compile_pattern(*value, vars)?));
+            }
+            Ok(Pattern::Map(entries))
+        }
+        _ => Ok(Pattern::Literal(term)),
+    }
+}
+
+struct ExprCompiler<'a> {
+    /// The variables bound by the head, in slot order
+    vars: &'a [u32],
+    kind: MatchSpecKind,
+    in_guard: bool,
+}
+impl<'a> ExprCompiler<'a> {
+    fn compile_list(&self, list: OpaqueTerm) -> Result<Vec<Expr>, MatchSpecError> {
+        match list.into() {
+            Term::Nil => Ok(Vec::new()),
+            Term::Cons(cons) => {
+                let mut exprs = Vec::new();
+                for expr in cons.iter_raw() {
+                    let expr = expr.map_err(|_| MatchSpecError::InvalidClause)?;
+                    exprs.push(self.compile(expr)?);
+                }
+                Ok(exprs)
+            }
+            _ => Err(MatchSpecError::InvalidClause),
+        }
+    }
+
+    fn compile(&self, term: OpaqueTerm) -> Result<Expr, MatchSpecError> {
+        if term.is_atom() {
+            let atom = term.as_atom();
+            return match atom.as_str() {
+                "_" => Err(MatchSpecError::WildcardInExpression),
+                "$_" => Ok(Expr::WholeMatch),
+                "$$" => Ok(Expr::AllBindings),
+                _ => match variable(term) {
+                    Some(n) => match self.vars.binary_search(&n) {
+                        Ok(slot) => Ok(Expr::Var(slot)),
+                        Err(_) => Err(MatchSpecError::UnboundVariable(n)),
+                    },
+                    None => Ok(Expr::Const(term)),
+                },
+            };
+        }
+        match term.into() {
+            Term::Tuple(tuple) => self.compile_tuple(tuple.as_slice()),
+            Term::Cons(cons) => Ok(Expr::Cons(
+                Box::new(self.compile(cons.head)?),
+                Box::new(self.compile(cons.tail)?),
+            )),
+            Term::Map(map) => {
+                let mut entries = Vec::with_capacity(map.size());
+                for (key, value) in map.keys().iter().zip(map.values()) {
+                    entries.push((self.compile(*key)?, self.compile(*value)?));
+                }
+                Ok(Expr::Map(entries))
+            }
+            _ => Ok(Expr::Const(term)),
+        }
+    }
+
+    fn compile_tuple(&self, elements: &[OpaqueTerm]) -> Result<Expr, MatchSpecError> {
+        let Some((first, args)) = elements.split_first() else { return Err(MatchSpecError::InvalidExpression); };
+        match (*first).into() {
+            // {{...}} constructs a tuple
+            Term::Tuple(tuple) if args.is_empty() => tuple
+                .as_slice()
+                .iter()
+                .map(|element| self.compile(*element))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Expr::Tuple),
+            Term::Atom(name) => {
+                let arity = args.len();
+                match (name.as_str(), arity) {
+                    ("const", 1) => return Ok(Expr::Const(args[0])),
+                    ("andalso", n) if n > 0 => return Ok(Expr::AndAlso(self.compile_args(args)?)),
+                    ("orelse", n) if n > 0 => return Ok(Expr::OrElse(self.compile_args(args)?)),
+                    _ => (),
+                }
+                if let Some(guard) = Guard::get(name.as_str(), arity) {
+                    return Ok(Expr::Call(guard, self.compile_args(args)?));
+                }
+                match Action::get(name.as_str(), arity) {
+                    Some(_) if self.in_guard || self.kind == MatchSpecKind::Table => {
+                        Err(MatchSpecError::ActionNotAllowed(name))
+                    }
+                    Some(action) => Ok(Expr::Action(action, self.compile_args(args)?)),
+                    None => Err(MatchSpecError::UnknownFunction(name, arity)),
+                }
+            }
+            _ => Err(MatchSpecError::InvalidExpression),
+        }
+    }
+
+    fn compile_args(&self, args: &[OpaqueTerm]) -> Result<Vec<Expr>, MatchSpecError> {
+        args.iter().map(|arg| self.compile(*arg)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+
+    fn atom(name: &str) -> OpaqueTerm {
+        Atom::str_to_term(name)
+    }
+
+    fn tuple(heap: &FixedSizeHeap<4096>, elements: &[OpaqueTerm]) -> OpaqueTerm {
+        Tuple::from_slice(elements, heap).unwrap().into()
+    }
+
+    fn list(heap: &FixedSizeHeap<4096>, elements: &[OpaqueTerm]) -> OpaqueTerm {
+        Cons::from_slice(elements, heap)
+            .unwrap()
+            .map(|cons| cons.into())
+            .unwrap_or(OpaqueTerm::NIL)
+    }
+
+    #[test]
+    fn match_spec_compile_errors() {
+        let heap = FixedSizeHeap::<4096>::default();
+
+        let spec = tuple(&heap, &[atom("_"), OpaqueTerm::NIL, OpaqueTerm::NIL]);
+        assert_eq!(
+            MatchSpec::compile(spec.into(), MatchSpecKind::Table).unwrap_err(),
+            MatchSpecError::NotAList
+        );
+
+        // [{{'$1'}, [], ['$2']}]
+        let head = tuple(&heap, &[atom("$1")]);
+        let body = list(&heap, &[atom("$2")]);
+        let clause = tuple(&heap, &[head, OpaqueTerm::NIL, body]);
+        let spec = list(&heap, &[clause]);
+        assert_eq!(
+            MatchSpec::compile(spec.into(), MatchSpecKind::Table).unwrap_err(),
+            MatchSpecError::UnboundVariable(2)
+        );
+
+        // [{'_', [], [{return_trace}]}]
+        let body = list(&heap, &[tuple(&heap, &[atom("return_trace")])]);
+        let clause = tuple(&heap, &[atom("_"), OpaqueTerm::NIL, body]);
+        let spec = list(&heap, &[clause]);
+        assert_eq!(
+            MatchSpec::compile(spec.into(), MatchSpecKind::Table).unwrap_err(),
+            MatchSpecError::ActionNotAllowed(Atom::try_from("return_trace").unwrap())
+        );
+        assert!(MatchSpec::compile(spec.into(), MatchSpecKind::Trace).is_ok());
+    }
+
+    #[test]
+    fn match_spec_run() {
+        let heap = FixedSizeHeap::<4096>::default();
+
+        // [{{'$1', '$2', '_'}, [{'>', {abs, '$2'}, 1}], [{{'$2', '$1'}}]}]
+        let head = tuple(&heap, &[atom("$1"), atom("$2"), atom("_")]);
+        let abs = tuple(&heap, &[atom("abs"), atom("$2")]);
+        let guard = tuple(&heap, &[atom(">"), abs, Term::Int(1).into()]);
+        let result = tuple(&heap, &[tuple(&heap, &[atom("$2"), atom("$1")])]);
+        let clause = tuple(
+            &heap,
+            &[head, list(&heap, &[guard]), list(&heap, &[result])],
+        );
+        let spec = MatchSpec::compile(list(&heap, &[clause]).into(), MatchSpecKind::Table).unwrap();
+
+        let mut evaluator = Evaluator::new(None);
+        let object = tuple(&heap, &[atom("a"), Term::Int(2).into(), atom("b")]);
+        let matched = evaluator.run(&spec, object).unwrap();
+        let expected = tuple(&heap, &[Term::Int(2).into(), atom("a")]);
+        assert_eq!(Term::from(matched.result), Term::from(expected));
+
+        let object = tuple(&heap, &[atom("a"), Term::Int(1).into(), atom("b")]);
+        assert!(evaluator.run(&spec, object).is_none());
+
+        // A guard which raises fails the clause
+        let object = tuple(&heap, &[atom("a"), atom("b"), atom("c")]);
+        assert!(evaluator.run(&spec, object).is_none());
+    }
+}
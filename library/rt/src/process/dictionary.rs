@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+use core::hash::BuildHasherDefault;
+
+use rustc_hash::FxHasher;
+use smallvec::SmallVec;
+
+use crate::cmp::ExactEq;
+use crate::gc::RootSet;
+use crate::term::hash::phash2;
+use crate::term::{OpaqueTerm, Term};
+
+type HashMap<K, V> = hashbrown::HashMap<K, V, BuildHasherDefault<FxHasher>>;
+
+/// The process dictionary, i.e. the key/value store accessed via `erlang:put/2` and friends
+///
+/// Keys and values live on the process heap, and are roots for garbage collection, see
+/// [`ProcessDictionary::roots`]. Entries are bucketed by the portable hash of their key, which is
+/// stable across collections, and keys are compared using exact equality, as in ERTS.
+#[derive(Default)]
+pub struct ProcessDictionary {
+    buckets: HashMap<u32, SmallVec<[(OpaqueTerm, OpaqueTerm); 1]>>,
+    len: usize,
+}
+impl ProcessDictionary {
+    /// Returns the number of entries in the dictionary
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value associated with `key`, if present
+    pub fn get(&self, key: OpaqueTerm) -> Option<OpaqueTerm> {
+        let key: Term = key.into();
+        self.buckets
+            .get(&phash2(key.clone()))?
+            .iter()
+            .find(|(k, _)| key.exact_eq(&(*k).into()))
+            .map(|(_, v)| *v)
+    }
+
+    /// Associates `value` with `key`, returning the value previously associated with it, if any
+    pub fn put(&mut self, key: OpaqueTerm, value: OpaqueTerm) -> Option<OpaqueTerm> {
+        let term: Term = key.into();
+        let bucket = self.buckets.entry(phash2(term.clone())).or_default();
+        match bucket.iter_mut().find(|(k, _)| term.exact_eq(&(*k).into())) {
+            Some((_, v)) => Some(core::mem::replace(v, value)),
+            None => {
+                bucket.push((key, value));
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// Removes `key` from the dictionary, returning the value associated with it, if any
+    pub fn erase(&mut self, key: OpaqueTerm) -> Option<OpaqueTerm> {
+        let key: Term = key.into();
+        let hash = phash2(key.clone());
+        let bucket = self.buckets.get_mut(&hash)?;
+        let index = bucket
+            .iter()
+            .position(|(k, _)| key.exact_eq(&(*k).into()))?;
+        let (_, value) = bucket.swap_remove(index);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Removes all entries from the dictionary, returning them
+    pub fn clear(&mut self) -> Vec<(OpaqueTerm, OpaqueTerm)> {
+        let entries = self.iter().collect();
+        self.buckets.clear();
+        self.len = 0;
+        entries
+    }
+
+    /// Returns an iterator over all of the entries in the dictionary, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (OpaqueTerm, OpaqueTerm)> + '_ {
+        self.buckets
+            .values()
+            .flat_map(|bucket| bucket.iter().copied())
+    }
+
+    /// Returns all of the keys associated with a value exactly equal to `value`
+    pub fn keys_for(&self, value: OpaqueTerm) -> Vec<OpaqueTerm> {
+        let value: Term = value.into();
+        self.iter()
+            .filter(|(_, v)| value.exact_eq(&(*v).into()))
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    /// Adds every key and value in the dictionary to `roots`
+    ///
+    /// # Safety
+    ///
+    /// The dictionary must not be modified until the collection using `roots` is complete.
+    pub unsafe fn roots(&mut self, roots: &mut RootSet) {
+        for bucket in self.buckets.values_mut() {
+            for (key, value) in bucket.iter_mut() {
+                *roots += key as *mut OpaqueTerm;
+                *roots += value as *mut OpaqueTerm;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use crate::term::{atoms, Tuple};
+
+    use super::*;
+
+    #[test]
+    fn process_dictionary_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+        let mut dict = ProcessDictionary::default();
+
+        let one: OpaqueTerm = Term::Int(1).into();
+        let tuple = Tuple::from_slice(&[atoms::Ok.into(), one], &heap).unwrap();
+        let same = Tuple::from_slice(&[atoms::Ok.into(), one], &heap).unwrap();
+
+        assert_eq!(dict.put(tuple.into(), atoms::True.into()), None);
+        assert_eq!(dict.put(one, atoms::True.into()), None);
+        assert_eq!(dict.len(), 2);
+        // Keys are compared by value, not identity
+        assert_eq!(dict.get(same.into()), Some(atoms::True.into()));
+        assert_eq!(
+            dict.put(same.into(), atoms::False.into()),
+            Some(atoms::True.into())
+        );
+        assert_eq!(dict.len(), 2);
+
+        assert_eq!(dict.keys_for(atoms::True.into()), [one]);
+        assert_eq!(dict.erase(one), Some(atoms::True.into()));
+        assert_eq!(dict.erase(one), None);
+        assert_eq!(dict.get(one), None);
+
+        let entries = dict.clear();
+        assert_eq!(entries.len(), 1);
+        assert!(dict.is_empty());
+        assert_eq!(dict.get(tuple.into()), None);
+    }
+}
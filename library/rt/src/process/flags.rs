@@ -1,7 +1,8 @@
 use core::mem;
 use core::num::NonZeroUsize;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::term::{atoms, OpaqueTerm, Term};
+use crate::term::{atoms, Atom, OpaqueTerm, Term};
 
 /// Represents what priority queue a given process resides in
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -40,7 +41,13 @@ impl TryFrom<Term> for Priority {
     }
 }
 
+/// The system-wide default maximum heap size, in bytes, or zero if unlimited
+static SYSTEM_MAX_HEAP_SIZE: AtomicUsize = AtomicUsize::new(0);
+
 /// This represents the current `max_heap_size` configuration of a process
+///
+/// The size is in bytes, while in Erlang, i.e. in spawn options and `process_flag/2`, it is given
+/// in words, as is the total heap size reported by `process_info/2` which it is compared against.
 #[derive(Debug, Copy, Clone)]
 pub struct MaxHeapSize {
     pub size: Option<NonZeroUsize>,
@@ -67,11 +74,29 @@ impl MaxHeapSize {
         self.size = NonZeroUsize::new(size);
         self
     }
+
+    /// Returns the size limit in words, or zero if unlimited
+    pub fn size_in_words(&self) -> usize {
+        self.size.map(|sz| sz.get()).unwrap_or(0) / mem::size_of::<OpaqueTerm>()
+    }
+
+    /// Converts a size given in words from Erlang to bytes, where zero means unlimited
+    fn size_from_words(words: i64) -> Result<Option<NonZeroUsize>, ()> {
+        let words: usize = words.try_into().map_err(|_| ())?;
+        let bytes = words.saturating_mul(mem::size_of::<OpaqueTerm>());
+        Ok(NonZeroUsize::new(bytes.min(Self::MAX_SIZE)))
+    }
+
+    /// Sets the maximum heap size used for processes which are spawned without one, or zero to
+    /// remove the limit
+    pub fn set_system_default(size: usize) {
+        SYSTEM_MAX_HEAP_SIZE.store(size.min(Self::MAX_SIZE), Ordering::Relaxed);
+    }
 }
 impl Default for MaxHeapSize {
     fn default() -> Self {
         Self {
-            size: None,
+            size: NonZeroUsize::new(SYSTEM_MAX_HEAP_SIZE.load(Ordering::Relaxed)),
             kill: true,
             error_logger: true,
         }
@@ -83,7 +108,7 @@ impl TryFrom<Term> for MaxHeapSize {
     fn try_from(term: Term) -> Result<Self, Self::Error> {
         match term {
             Term::Int(i) if i >= 0 => Ok(Self {
-                size: NonZeroUsize::new(i.try_into().map_err(|_| ())?),
+                size: Self::size_from_words(i)?,
                 kill: true,
                 error_logger: true,
             }),
@@ -92,7 +117,7 @@ impl TryFrom<Term> for MaxHeapSize {
                 if let Some(v) = opts.get(atoms::Size) {
                     match v.into() {
                         Term::Int(i) if i >= 0 => {
-                            max_heap_size.size = NonZeroUsize::new(i.try_into().map_err(|_| ())?);
+                            max_heap_size.size = Self::size_from_words(i)?;
                         }
                         _ => return Err(()),
                     }
@@ -136,8 +161,8 @@ impl firefly_system::sync::Atom for MaxHeapSize {
     #[inline]
     fn unpack(raw: Self::Repr) -> Self {
         let size = NonZeroUsize::new(raw & Self::MAX_SIZE);
-        let kill = raw & Self::KILL_BIT == Self::KILL_BIT;
-        let error_logger = raw & Self::LOGGER_BIT == Self::LOGGER_BIT;
+        let kill = raw & (1 << Self::KILL_BIT) != 0;
+        let error_logger = raw & (1 << Self::LOGGER_BIT) != 0;
         Self {
             size,
             kill,
@@ -296,6 +321,66 @@ impl StatusFlags {
         )
     }
 }
+/// A summary of what a process is doing, as reported by `process_info(Pid, status)`
+///
+/// This is derived from a single read of the process [`StatusFlags`], so it can be taken from any
+/// thread without locking the process, but may be out of date by the time it is used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcessStatus {
+    /// The process is exiting, or has exited
+    Exiting,
+    /// The process is garbage collecting
+    GarbageCollecting,
+    /// The process is executing, or handling signals, on a scheduler
+    Running,
+    /// The process is waiting to be picked up by a scheduler
+    Runnable,
+    /// The process is waiting for a message or timeout
+    Waiting,
+}
+impl ProcessStatus {
+    /// Returns true unless the process is exiting
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        *self != Self::Exiting
+    }
+
+    /// Returns the atom used for this status by `process_info/2`
+    pub fn as_atom(&self) -> Atom {
+        match self {
+            Self::Exiting => atoms::Exiting,
+            Self::GarbageCollecting => atoms::GarbageCollecting,
+            Self::Running => atoms::Running,
+            Self::Runnable => atoms::Runnable,
+            Self::Waiting => atoms::Waiting,
+        }
+    }
+}
+impl From<StatusFlags> for ProcessStatus {
+    fn from(status: StatusFlags) -> Self {
+        if status.intersects(StatusFlags::EXITING | StatusFlags::FREE) {
+            Self::Exiting
+        } else if status.contains(StatusFlags::GC) {
+            Self::GarbageCollecting
+        } else if status.intersects(StatusFlags::RUNNING | StatusFlags::RUNNING_SYS) {
+            Self::Running
+        } else if status.contains(StatusFlags::SUSPENDED) {
+            // A suspended process only becomes runnable again when it receives a message
+            Self::Waiting
+        } else if status.intersects(StatusFlags::ACTIVE | StatusFlags::ACTIVE_SYS) {
+            Self::Runnable
+        } else {
+            Self::Waiting
+        }
+    }
+}
+impl Into<OpaqueTerm> for ProcessStatus {
+    #[inline]
+    fn into(self) -> OpaqueTerm {
+        self.as_atom().into()
+    }
+}
+
 impl core::ops::BitOr<Priority> for StatusFlags {
     type Output = StatusFlags;
 
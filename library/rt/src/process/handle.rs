@@ -0,0 +1,228 @@
+//! Handles to processes for use by code embedding the runtime
+//!
+//! These wrap the lower-level process APIs so that embedders can address, message and monitor
+//! processes using ordinary Rust values, without constructing signals or managing heap fragments
+//! themselves.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+use intrusive_collections::UnsafeRef;
+
+use crate::services::registry::{self, Registrant, WeakAddress};
+use crate::term::{atoms, Atom, CopyMode, Pid, Reference, Term, TermFragment};
+
+use super::monitor::{make_down_message, LocalMonitorInfo, Monitor, MonitorEntry};
+use super::signals::{Message, Signal};
+use super::{Process, ProcessLock, StatusFlags};
+
+/// The error returned when a message could not be sent using a [`ProcessHandle`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendError {
+    /// The process has exited, so the message was dropped
+    Exited,
+    /// The message could not be copied, as there was not enough memory available
+    Alloc,
+}
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited => f.write_str("the process has exited"),
+            Self::Alloc => f.write_str("unable to allocate memory for the message"),
+        }
+    }
+}
+
+/// A strong reference to a local process
+///
+/// Holding a handle keeps the process structure alive, but not the process itself, so a handle
+/// may refer to a process which has since exited, see [`ProcessHandle::is_alive`].
+#[derive(Clone)]
+pub struct ProcessHandle(Arc<Process>);
+impl ProcessHandle {
+    /// Returns a handle to the process identified by `pid`, if it is a live local process
+    pub fn from_pid(pid: &Pid) -> Option<Self> {
+        if !pid.is_local() {
+            return None;
+        }
+        registry::get_by_pid(pid).map(Self)
+    }
+
+    /// Returns a handle to the process registered as `name`, if there is one
+    pub fn whereis(name: Atom) -> Option<Self> {
+        match registry::get_by_name(name)? {
+            Registrant::Process(process) => Some(Self(process)),
+            Registrant::Port(_) => None,
+        }
+    }
+
+    /// Returns the pid of this process
+    #[inline]
+    pub fn pid(&self) -> Pid {
+        self.0.pid()
+    }
+
+    /// Returns the name this process is registered under, if any
+    #[inline]
+    pub fn registered_name(&self) -> Option<Atom> {
+        self.0.registered_name()
+    }
+
+    /// Returns true if this process has not started exiting
+    pub fn is_alive(&self) -> bool {
+        !self
+            .0
+            .status(Ordering::Acquire)
+            .intersects(StatusFlags::EXITING | StatusFlags::FREE)
+    }
+
+    /// Sends a copy of `message` to this process on behalf of the runtime system
+    pub fn send(&self, message: &Term) -> Result<(), SendError> {
+        self.send_as(WeakAddress::System, message)
+    }
+
+    /// Sends a copy of `message` to this process on behalf of `sender`
+    pub fn send_from(&self, sender: &ProcessLock, message: &Term) -> Result<(), SendError> {
+        self.send_as(sender.addr(), message)
+    }
+
+    /// Sends a copy of each of `messages` to this process on behalf of the runtime system, in
+    /// order, returning the number of messages sent
+    ///
+    /// All of the messages are copied before any of them is sent, and the queue of the process is
+    /// then locked once for the whole batch, rather than once per message as with `send`. Nothing
+    /// is sent if any of the messages could not be copied.
+    pub fn send_batch<I>(&self, messages: I) -> Result<usize, SendError>
+    where
+        I: IntoIterator<Item = Term>,
+    {
+        let fragments = messages
+            .into_iter()
+            .map(|message| TermFragment::copy_from(&message, CopyMode::Flat))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| SendError::Alloc)?;
+        Arc::clone(&self.0)
+            .send_fragments(WeakAddress::System, fragments)
+            .map_err(|_| SendError::Exited)
+    }
+
+    fn send_as(&self, sender: WeakAddress, message: &Term) -> Result<(), SendError> {
+        let fragment =
+            TermFragment::copy_from(message, CopyMode::Flat).map_err(|_| SendError::Alloc)?;
+        Arc::clone(&self.0)
+            .send_fragment(sender, fragment)
+            .map_err(|_| SendError::Exited)
+    }
+
+    /// Makes `watcher` monitor this process, returning the reference of the monitor
+    ///
+    /// As with `erlang:monitor/2`, `watcher` receives `{'DOWN', Ref, process, Pid, Reason}` when
+    /// this process exits, immediately with reason `noproc` if it has exited already.
+    pub fn monitor(&self, watcher: &mut ProcessLock) -> Reference {
+        let reference_id = crate::scheduler::get(watcher.scheduler_id()).next_reference_id();
+        let reference = Reference::new(reference_id);
+        let monitor = MonitorEntry::new(Monitor::LocalProcess {
+            origin: watcher.id(),
+            target: self.0.id(),
+            info: LocalMonitorInfo {
+                reference: reference_id,
+                name_or_tag: TermFragment::new(Term::None).unwrap(),
+            },
+        });
+        watcher.monitored.insert(monitor.clone());
+
+        // A process monitoring itself gets a monitor which is never triggered
+        if self.0.id() == watcher.id() {
+            return reference;
+        }
+        let target = self.0.addr();
+        if Arc::clone(&self.0).send_signal(Signal::monitor(monitor)).is_err() {
+            watcher.monitored.find_mut(&reference_id).remove();
+            let message = make_down_message(
+                None,
+                reference.clone(),
+                atoms::Process,
+                None,
+                target.clone(),
+                atoms::Noproc.into(),
+            );
+            watcher.send_fragment(target, message).ok();
+        }
+        reference
+    }
+}
+impl fmt::Debug for ProcessHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ProcessHandle").field(&self.pid()).finish()
+    }
+}
+impl PartialEq for ProcessHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl Eq for ProcessHandle {}
+impl From<Arc<Process>> for ProcessHandle {
+    #[inline]
+    fn from(process: Arc<Process>) -> Self {
+        Self(process)
+    }
+}
+impl AsRef<Process> for ProcessHandle {
+    #[inline]
+    fn as_ref(&self) -> &Process {
+        &self.0
+    }
+}
+
+/// The mailbox of a process, borrowed from the lock held on it
+///
+/// Messages received through a mailbox are moved to the heap of the process, so the terms
+/// returned remain valid until the next garbage collection of the process, like any other term
+/// on its heap.
+pub struct Mailbox<'a, 'p> {
+    process: &'a mut ProcessLock<'p>,
+}
+impl<'a, 'p> Mailbox<'a, 'p> {
+    #[inline]
+    pub fn new(process: &'a mut ProcessLock<'p>) -> Self {
+        Self { process }
+    }
+
+    /// Returns the number of signals waiting in the queue, most of which are usually messages
+    pub fn len(&self) -> usize {
+        self.process.signals().lock().len()
+    }
+
+    /// Returns true if there are no signals waiting in the queue
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the oldest message from the queue and returns it, if there is one
+    pub fn try_receive(&mut self) -> Option<Term> {
+        self.try_receive_matching(|_| true)
+    }
+
+    /// Removes the oldest message for which `predicate` returns true and returns it, if any,
+    /// leaving all other messages in the queue in order, like a selective receive
+    pub fn try_receive_matching<F>(&mut self, predicate: F) -> Option<Term>
+    where
+        F: Fn(&Term) -> bool,
+    {
+        let mut signals = self.process.signals().lock();
+        let mut message: Message =
+            signals.remove_first_message(|msg| predicate(&msg.message.term.into()))?;
+        drop(signals);
+        if let Some(fragment_ptr) = message.message.fragment.take() {
+            unsafe {
+                self.process
+                    .heap_fragments
+                    .push_back(UnsafeRef::from_raw(fragment_ptr.as_ptr().cast_const()));
+            }
+        }
+        Some(message.message.term.into())
+    }
+}
@@ -1,8 +1,9 @@
-use alloc::alloc::{AllocError, Allocator, Global, Layout};
+use alloc::alloc::{AllocError, Allocator, Layout};
 use core::cell::UnsafeCell;
 use core::mem;
 use core::ptr::{self, NonNull};
 
+use firefly_alloc::allocators::LargeCarriers;
 use firefly_alloc::heap::{Heap, HeapMut};
 
 use crate::term::OpaqueTerm;
@@ -18,7 +19,8 @@ impl ProcessHeap {
 
     pub fn new(size: usize) -> Self {
         let layout = Layout::from_size_align(size, mem::align_of::<OpaqueTerm>()).unwrap();
-        let nonnull = Global.allocate(layout).unwrap();
+        // Large heaps are placed in their own carriers, so that they may use huge pages
+        let nonnull = LargeCarriers.allocate(layout).unwrap();
         let top = nonnull.as_non_null_ptr().as_ptr();
         Self {
             range: nonnull.as_ptr(),
@@ -87,7 +89,7 @@ impl Drop for ProcessHeap {
     fn drop(&mut self) {
         let size = ptr::metadata(self.range) as usize;
         let layout = Layout::from_size_align(size, mem::align_of::<OpaqueTerm>()).unwrap();
-        unsafe { Global.deallocate(NonNull::new_unchecked(self.range.cast()), layout) }
+        unsafe { LargeCarriers.deallocate(NonNull::new_unchecked(self.range.cast()), layout) }
     }
 }
 unsafe impl Allocator for ProcessHeap {
@@ -11,9 +11,11 @@ pub enum ProcessIdError {
 /// Local pids in the BEAM are designed to fit in an immediate, which limits their range to a value
 /// that can be expressed in a single 32-bit word. However, because external pids are always 64 bits,
 /// (both number and serial are given a full 32-bits), we choose to use 64-bits in both cases, storing
-/// the serial in the high 32-bits, and the number in the low 32 bits. We do still impose a restriction
-/// on the maximum value of local pids, for both number and serial, that is less than 32-bits, allowing
-/// us to detect overflow when calculating the next possibly-available id.
+/// the number in the high 32-bits, and the serial in the low 32 bits.
+///
+/// Local pids are assigned by the process table, see `crate::services::registry::ProcessTable`, in
+/// which the number is the index of the slot of the process, and the serial is the generation of that
+/// slot. Zero is never a valid process identifier.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
 pub struct ProcessId(u64);
@@ -88,28 +90,6 @@ impl ProcessId {
         Self::from_raw((number << 32) | serial)
     }
 
-    /// Generates the next globally-unique process number.
-    ///
-    /// # SAFETY
-    ///
-    /// The maximum number of processes that can be spawned before we exhaust available
-    /// process identifiers is 2^64 - sufficient to last a very very long time. However, we
-    /// do protect against rollover, by guaranteeing that the first pid starts at 1, and
-    /// asserting if we try to assign a pid of 0
-    pub fn next() -> Self {
-        use core::sync::atomic::{AtomicU64, Ordering};
-
-        static COUNTER: AtomicU64 = AtomicU64::new(1);
-
-        let next = COUNTER.fetch_add(1, Ordering::SeqCst);
-
-        assert_ne!(
-            next, 0,
-            "system limit: exhausted all available process identifiers"
-        );
-        unsafe { Self::from_raw(next.rotate_left(32)) }
-    }
-
     /// Given a the raw process id value (as a usize), reifies it into a `ProcessId`
     #[inline]
     pub unsafe fn from_raw(pid: u64) -> Self {
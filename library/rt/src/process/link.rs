@@ -6,8 +6,9 @@ use core::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
 
 use rustc_hash::FxHasher;
 
-use crate::services::registry::WeakAddress;
-use crate::term::Pid;
+use crate::gc::Gc;
+use crate::services::registry::{self, WeakAddress};
+use crate::term::{atoms, LayoutBuilder, OpaqueTerm, Pid, Term, TermFragment, Tuple};
 
 use super::ProcessId;
 
@@ -125,6 +126,11 @@ impl LinkTree {
         Some(link)
     }
 
+    /// Returns an iterator over the addresses of the processes/ports linked to the tree owner
+    pub fn iter(&self) -> impl Iterator<Item = &WeakAddress> + '_ {
+        self.0.keys()
+    }
+
     /// Takes the internal `HashMap` of this link tree, replacing it with an empty one.
     #[inline]
     pub fn take(&mut self) -> HashMap<WeakAddress, Arc<LinkEntry>> {
@@ -240,3 +246,54 @@ pub enum Link {
     /// The remote part of the link is stored in the `links` list of the dist structure
     FromExternalProcess { origin: Pid, target: ProcessId },
 }
+
+/// Builds the `{'EXIT', From, Reason}` message an exit signal from `sender` is converted to when
+/// received by a process which is trapping exits
+pub fn make_exit_message(sender: WeakAddress, reason: Term) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    layout += reason.layout();
+    if let WeakAddress::Process(_) = sender {
+        layout.build_pid();
+    }
+    layout.build_tuple(3);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let reason = unsafe { reason.unsafe_clone_to_heap(fragment) };
+    let from: OpaqueTerm = match sender {
+        WeakAddress::Process(pid) => Gc::new_in(pid, fragment).unwrap().into(),
+        WeakAddress::Port(id) => match registry::get_by_port_id(id) {
+            Some(port) => Term::Port(port).into(),
+            None => atoms::Undefined.into(),
+        },
+        WeakAddress::Name(name) => name.into(),
+        WeakAddress::System => atoms::System.into(),
+    };
+    let message = Tuple::from_slice(
+        &[atoms::UppercaseExit.into(), from, reason.into()],
+        fragment,
+    )
+    .unwrap();
+
+    TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exit_message_test() {
+        let from = Pid::new_local(unsafe { ProcessId::new_unchecked(1, 0) });
+        let reason = TermFragment::new(atoms::Killed.into()).unwrap();
+        let message = make_exit_message(WeakAddress::Process(from.clone()), reason.term.into());
+        let Term::Tuple(message) = message.term.into() else { panic!("expected tuple") };
+        assert_eq!(message.len(), 3);
+        assert_eq!(message[0], atoms::UppercaseExit.into());
+        assert_eq!(Term::from(message[1]), Term::Pid(Gc::new(from)));
+        assert_eq!(message[2], atoms::Killed.into());
+    }
+}
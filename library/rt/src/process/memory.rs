@@ -0,0 +1,72 @@
+//! Accounting of the memory used by each process, which can be read without the process lock
+//!
+//! Each process publishes its memory usage at the end of every garbage collection, so the figures
+//! are only as recent as the last collection of the process, but finding the largest consumers
+//! of memory on the node never waits on, or interrupts, a running process.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{self, AtomicUsize};
+
+use crate::services::registry;
+
+use super::Process;
+
+/// The kinds of memory accounted for per process
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryCategory {
+    /// The heap of a process, including its mature generation and heap fragments, and its stack
+    Heap,
+    /// The reference-counted binaries referenced from the heap of a process
+    ///
+    /// A binary referenced by multiple processes is accounted for by each of them, and by each
+    /// reference to it, including those from garbage which has not been collected yet.
+    Binary,
+}
+
+/// The memory usage of a process in bytes, as of its last garbage collection
+#[derive(Default)]
+pub struct MemoryUsage {
+    heap: AtomicUsize,
+    binary: AtomicUsize,
+}
+impl MemoryUsage {
+    pub(super) fn new(heap: usize, binary: usize) -> Self {
+        Self {
+            heap: AtomicUsize::new(heap),
+            binary: AtomicUsize::new(binary),
+        }
+    }
+
+    /// Returns the size of the heap and stack of the process
+    #[inline]
+    pub fn heap(&self) -> usize {
+        self.heap.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the total size of the reference-counted binaries referenced by the process
+    #[inline]
+    pub fn binary(&self) -> usize {
+        self.binary.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes used in `category`
+    pub fn get(&self, category: MemoryCategory) -> usize {
+        match category {
+            MemoryCategory::Heap => self.heap(),
+            MemoryCategory::Binary => self.binary(),
+        }
+    }
+
+    pub(super) fn publish(&self, heap: usize, binary: usize) {
+        self.heap.store(heap, atomic::Ordering::Relaxed);
+        self.binary.store(binary, atomic::Ordering::Relaxed);
+    }
+}
+
+/// Returns the `n` processes using the most memory in `category`, largest first, along with the
+/// number of bytes used by each
+///
+/// Only the published usage of each process is read, so this takes no process locks.
+pub fn top(category: MemoryCategory, n: usize) -> Vec<(Arc<Process>, usize)> {
+    registry::top_processes(n, |process| process.memory().get(category))
+}
@@ -1,18 +1,23 @@
+mod dictionary;
 mod flags;
 mod generator;
+mod handle;
 mod heap;
 mod id;
 pub mod link;
+mod memory;
 pub mod monitor;
 pub mod signals;
 mod spawn;
 mod stack;
 mod system_tasks;
+pub mod trace;
 
 use alloc::alloc::{AllocError, Allocator, Layout};
 use alloc::boxed::Box;
 use alloc::fmt;
 use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use core::assert_matches::assert_matches;
 use core::cell::UnsafeCell;
 use core::cmp;
@@ -21,10 +26,11 @@ use core::mem;
 use core::num::{NonZeroU64, NonZeroUsize};
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
 
 use firefly_alloc::fragment::HeapFragmentList;
 use firefly_alloc::heap::Heap;
+use firefly_system::sync::lcnt::LockClass;
 use firefly_system::sync::{Atomic, Mutex, MutexGuard};
 
 use crossbeam::deque::Injector;
@@ -36,17 +42,21 @@ use log::trace;
 
 use crate::error::{ErlangException, ErrorCode, ExceptionClass, ExceptionFlags, ExceptionInfo};
 use crate::function::ModuleFunctionArity;
-use crate::gc::{GcError, RootSet, SemispaceProcessHeap};
+use crate::gc::{self, GcError, RootSet, SemispaceProcessHeap};
 use crate::scheduler::SchedulerId;
-use crate::services::registry::WeakAddress;
+use crate::services::persistent_term;
+use crate::services::registry::{self, WeakAddress};
 use crate::term::{
-    atoms, Atom, LayoutBuilder, OpaqueTerm, Pid, ReferenceId, Term, TermFragment, Tuple,
+    atoms, Atom, CopyMode, LayoutBuilder, OpaqueTerm, Pid, ReferenceId, Term, TermFragment, Tuple,
 };
 
-pub use self::flags::{MaxHeapSize, Priority, ProcessFlags, StatusFlags};
+pub use self::dictionary::ProcessDictionary;
+pub use self::flags::{MaxHeapSize, Priority, ProcessFlags, ProcessStatus, StatusFlags};
 pub use self::generator::{Continuation, ContinuationResult, Generator, GeneratorState};
+pub use self::handle::{Mailbox, ProcessHandle, SendError};
 pub use self::heap::ProcessHeap;
 pub use self::id::{ProcessId, ProcessIdError};
+pub use self::memory::{top as memory_top, MemoryCategory, MemoryUsage};
 pub use self::spawn::*;
 pub use self::stack::{ProcessStack, Register, StackFrame, ARG0_REG, CP_REG, RETURN_REG};
 pub use self::system_tasks::{SystemTask, SystemTaskType};
@@ -61,6 +71,9 @@ pub type ProcessList = LinkedList<ProcessAdapter>;
 
 intrusive_adapter!(pub ProcessAdapter = Arc<Process>: Process { link: LinkedListAtomicLink });
 
+/// Shared by the main locks of all processes, see `firefly_system::sync::lcnt`
+static PROC_MAIN_LOCK: LockClass = LockClass::new("proc_main");
+
 /// This represents the state of the process timer at any given time.
 ///
 /// The process timer is used when a process is suspended while waiting
@@ -130,6 +143,8 @@ pub struct SchedulerData {
     pub ip: usize,
     /// The reduction counter for this process
     pub reductions: usize,
+    /// The number of reductions executed by this process in previous scheduling cycles
+    pub total_reductions: u64,
     /// The number of bytes needed when the next garbage collection is performed
     ///
     /// If zero, no requirement is imposed on the collector. If non-zero, the collector
@@ -139,8 +154,6 @@ pub struct SchedulerData {
     pub gc_threshold: f64,
     /// The number of minor collections that have occurred since the last full sweep
     pub gc_count: usize,
-    /// The number of schedules remaining for a low priority process
-    pub schedule_count: u8,
     /// A unique number counter for this process
     pub uniq: NonZeroU64,
     /// The set of internal process flags which are controlled by the scheduler
@@ -153,6 +166,9 @@ pub struct SchedulerData {
     pub awaiting: Option<Generator>,
     /// Stores the target of the trap instruction
     pub trap: Option<firefly_bytecode::FunId>,
+    /// The function to which execution time is currently being attributed for `call_time`
+    /// tracing, and the monotonic time in nanoseconds at which it started accruing
+    pub call_time: Option<(firefly_bytecode::FunId, u64)>,
     /// This field represents metadata about the current exception and how it should be handled.
     ///
     /// For exceptions which have already been allocated, the `current_exception` field holds
@@ -193,8 +209,15 @@ pub struct SchedulerData {
     group_leader: Option<Pid>,
     /// The heap fragment list for this process
     pub heap_fragments: HeapFragmentList,
+    /// The process dictionary, whose keys and values are on the process heap
+    pub dictionary: ProcessDictionary,
     /// The system task queues, one for each priority: low, normal, high, max
     pub system_tasks: [SystemTaskList; 4],
+    /// The total size of the reference-counted binaries referenced from the mature generation
+    ///
+    /// This is maintained by each collection, so that only the newly tenured part of the mature
+    /// generation is scanned to publish the memory usage of the process.
+    mature_binary_size: usize,
 }
 impl SchedulerData {
     pub fn set_exception_info(&mut self, exception: Box<ErlangException>) {
@@ -233,10 +256,15 @@ pub struct ProcessLock<'a> {
 }
 impl<'a> ProcessLock<'a> {
     fn new(process: &'a Process) -> Self {
-        let guard = process.scheduler_data.lock();
+        let guard = PROC_MAIN_LOCK.lock(&process.scheduler_data);
         Self { process, guard }
     }
 
+    fn try_new(process: &'a Process) -> Option<Self> {
+        let guard = process.scheduler_data.try_lock()?;
+        Some(Self { process, guard })
+    }
+
     /// Get a new strong `Arc` reference to the locked process
     pub fn strong(&self) -> Arc<Process> {
         unsafe {
@@ -357,6 +385,10 @@ pub struct Process {
     /// dictionary under the '$initial_call' key. It is not used once the process has been
     /// scheduled the first time.
     pub initial_call: ModuleFunctionArity,
+    /// Metadata about how this process was spawned, e.g. its ancestors
+    ///
+    /// This is set on creation and never changes, so is safe to be read concurrently
+    spawn_info: SpawnInfo,
     /// Stores the initial arguments of this process
     ///
     /// None means there were no initial arguments, or they have been discarded
@@ -386,6 +418,28 @@ pub struct Process {
     pub min_heap_size: Option<NonZeroUsize>,
     pub min_bin_vheap_size: Option<NonZeroUsize>,
     pub max_heap_size: Atomic<MaxHeapSize>,
+    /// The `persistent_term` epoch as of the last full sweep of this process
+    ///
+    /// This may only be modified by the holder of the main lock, see `services::persistent_term`.
+    persistent_term_epoch: AtomicU64,
+    /// The memory usage of this process as of its last garbage collection
+    ///
+    /// This may only be modified by the holder of the main lock, but is always safe to read.
+    memory: MemoryUsage,
+    /// The total number of reductions executed by this process as of the end of its last time
+    /// slice
+    ///
+    /// This may only be modified by the holder of the main lock, but is always safe to read.
+    reductions: AtomicU64,
+    /// The trace flags and tracer of this process, see `erlang:trace/3`
+    ///
+    /// These may be modified by any process at any time.
+    trace: trace::TraceState,
+    /// The number of times this low priority process has been skipped over by the run queue
+    ///
+    /// This is only used by the run queue, which may come across the process while it is running,
+    /// so it is kept out of the main lock.
+    pub schedule_count: AtomicU8,
     /// The mailbox/signal queue for this process
     ///
     /// The signal queue is a thread-safe structure which internally maintains multiple queues for
@@ -413,6 +467,13 @@ impl fmt::Debug for Process {
     }
 }
 
+impl Drop for Process {
+    fn drop(&mut self) {
+        // Frees the pid of a process which was never registered, this is a no-op otherwise
+        registry::release_process_id(self.id);
+    }
+}
+
 /// Processes can be scheduled across threads
 unsafe impl Send for Process {}
 
@@ -434,8 +495,11 @@ impl Process {
         initial_arguments: &[OpaqueTerm],
         injector: Arc<Injector<Arc<Process>>>,
         opts: SpawnOpts,
+        spawn_info: SpawnInfo,
     ) -> Arc<Self> {
-        let id = ProcessId::next();
+        let Some(id) = registry::reserve_process_id() else {
+            panic!("system limit: exceeded the maximum number of simultaneously alive processes");
+        };
 
         // Make sure the heap is at least large enough to hold `initial_arguments`
         let min_heap_size = cmp::max(
@@ -493,20 +557,36 @@ impl Process {
             Some(Term::Tuple(args))
         };
 
+        let memory = {
+            use firefly_alloc::heap::GenerationalHeap;
+
+            let immature = heap.immature();
+            MemoryUsage::new(
+                immature.heap_size() + stack.capacity() * mem::size_of::<OpaqueTerm>(),
+                gc::rc_binary_size(immature.used_range()),
+            )
+        };
+
+        let mut status = StatusFlags::default() | StatusFlags::ACTIVE | opts.priority;
+        if opts.message_queue_data == MessageQueueData::OnHeap {
+            status.remove(StatusFlags::OFF_HEAP_MSGQ);
+        }
+
         Arc::new(Self {
             link: LinkedListAtomicLink::new(),
             scheduler_data: Mutex::new(SchedulerData {
                 ip: 0,
                 reductions: 0,
+                total_reductions: 0,
                 gc_needed: 0,
                 gc_threshold: 0.75,
                 gc_count: 0,
-                schedule_count: 0,
                 uniq: unsafe { NonZeroU64::new_unchecked(1) },
                 timer_ref: ReferenceId::zero(),
                 injector,
                 awaiting: None,
                 trap: None,
+                call_time: None,
                 flags: ProcessFlags::empty(),
                 exception_info: ExceptionInfo::default(),
                 continue_exit: ContinueExitPhase::Timers,
@@ -517,27 +597,35 @@ impl Process {
                 links: Default::default(),
                 group_leader,
                 heap_fragments: HeapFragmentList::default(),
+                dictionary: ProcessDictionary::default(),
                 system_tasks: [
                     SystemTaskList::default(),
                     SystemTaskList::default(),
                     SystemTaskList::default(),
                     SystemTaskList::default(),
                 ],
+                mature_binary_size: 0,
             }),
             scheduler_id: Atomic::new(scheduler_id),
             parent,
             id,
             registered_name: Atomic::new(atoms::Undefined),
             initial_call,
+            spawn_info,
             initial_arguments: UnsafeCell::new(initial_arguments),
             timer: Atomic::new(Default::default()),
-            status: Atomic::new(StatusFlags::default() | StatusFlags::ACTIVE | opts.priority),
+            status: Atomic::new(status),
             error_handler: Atomic::new(atoms::Undefined),
             fullsweep_after: AtomicUsize::new(opts.fullsweep_after.unwrap_or(usize::MAX)),
             min_heap_size: opts.min_heap_size,
             min_bin_vheap_size: opts.min_bin_vheap_size,
             max_heap_size: Atomic::new(opts.max_heap_size),
-            signals: SignalQueue::default(),
+            persistent_term_epoch: AtomicU64::new(persistent_term::epoch()),
+            memory,
+            reductions: AtomicU64::new(0),
+            trace: trace::TraceState::for_new_process(),
+            schedule_count: AtomicU8::new(0),
+            signals: SignalQueue::new(opts.message_queue_data),
         })
     }
 
@@ -547,6 +635,44 @@ impl Process {
         ProcessLock::new(self)
     }
 
+    /// Calls `f` with this process locked, if it is waiting in a run queue rather than executing
+    ///
+    /// This lets another process read state which is normally only read by the process itself,
+    /// e.g. its heap, without waiting for it to be scheduled. The process is claimed the same way
+    /// a scheduler claims it to handle its signals, so no scheduler will execute it until `f`
+    /// returns. Since it was already queued when claimed, it never needs to be requeued.
+    ///
+    /// Returns `None` without waiting if the process is executing, exiting, not runnable, or
+    /// locked by another thread.
+    pub fn try_inspect<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut ProcessLock) -> T,
+    {
+        let mut process = ProcessLock::try_new(self)?;
+
+        let mut status = self.status(Ordering::Acquire);
+        loop {
+            let is_active = status.intersects(StatusFlags::ACTIVE | StatusFlags::ACTIVE_SYS);
+            let is_suspended = status & (StatusFlags::SUSPENDED | StatusFlags::ACTIVE_SYS)
+                == StatusFlags::SUSPENDED;
+            let is_running = status.intersects(StatusFlags::RUNNING | StatusFlags::RUNNING_SYS);
+            let is_exiting = status.intersects(StatusFlags::EXITING | StatusFlags::FREE);
+            if !is_active || is_suspended || is_running || is_exiting {
+                return None;
+            }
+            match self.cmpxchg_status_flags(status, status | StatusFlags::RUNNING_SYS) {
+                Ok(_) => break,
+                Err(current) => {
+                    status = current;
+                }
+            }
+        }
+
+        let result = f(&mut process);
+        self.remove_status_flags(StatusFlags::RUNNING_SYS, Ordering::Release);
+        Some(result)
+    }
+
     /// Sets the initial instruction pointer for a new process
     pub fn set_instruction_pointer(&mut self, ip: usize) {
         self.scheduler_data.get_mut().ip = ip;
@@ -556,6 +682,12 @@ impl Process {
         self.parent.clone()
     }
 
+    /// Returns the metadata recorded when this process was spawned
+    #[inline]
+    pub fn spawn_info(&self) -> &SpawnInfo {
+        &self.spawn_info
+    }
+
     /// Returns true if this process is the init process (i.e. the first spawned process)
     pub fn is_init(&self) -> bool {
         unsafe { self.id == ProcessId::from_raw(1 << 32) }
@@ -591,6 +723,12 @@ impl Process {
         &self.signals
     }
 
+    /// Get a reference to the trace settings of this process
+    #[inline]
+    pub fn trace(&self) -> &trace::TraceState {
+        &self.trace
+    }
+
     /// Returns the registered name of this process, if one exists
     pub fn registered_name(&self) -> Option<Atom> {
         let name = self.registered_name.load(Ordering::Acquire);
@@ -646,6 +784,31 @@ impl Process {
         self.max_heap_size.load(Ordering::Relaxed)
     }
 
+    /// Returns where messages waiting in the queue of this process are stored
+    #[inline]
+    pub fn message_queue_data(&self) -> MessageQueueData {
+        self.signals.message_queue_data()
+    }
+
+    /// Returns the `persistent_term` epoch as of the last full sweep of this process
+    #[inline]
+    pub fn persistent_term_epoch(&self) -> u64 {
+        self.persistent_term_epoch.load(Ordering::Acquire)
+    }
+
+    /// Returns the memory usage of this process as of its last garbage collection
+    #[inline]
+    pub fn memory(&self) -> &MemoryUsage {
+        &self.memory
+    }
+
+    /// Returns the total number of reductions executed by this process as of the end of its last
+    /// time slice
+    #[inline]
+    pub fn reductions(&self) -> u64 {
+        self.reductions.load(Ordering::Relaxed)
+    }
+
     /// Reads the current process status flags with the given memory ordering
     ///
     /// Any read which needs a happens-before relationship with another write should use `Acquire`,
@@ -656,6 +819,15 @@ impl Process {
         self.status.load(ordering)
     }
 
+    /// Returns a snapshot of what this process is doing, see [`ProcessStatus`]
+    ///
+    /// This is a single atomic read, so it is cheap enough to call from other schedulers, e.g. for
+    /// monitoring, without sending this process a signal or taking its lock.
+    #[inline]
+    pub fn status_snapshot(&self) -> ProcessStatus {
+        self.status.load(Ordering::Acquire).into()
+    }
+
     /// Enables all of the status flags in `flags`, using the given memory ordering for the write.
     ///
     /// Returns the previous status flags
@@ -709,7 +881,7 @@ impl Process {
     pub fn set_timeout(self: Arc<Self>, current: ProcessTimer) -> Result<(), ProcessTimer> {
         // We must acquire the main lock to proceed
         let process = self.clone();
-        let mut scheduler_data = self.scheduler_data.lock();
+        let mut scheduler_data = PROC_MAIN_LOCK.lock(&self.scheduler_data);
         self.timer.compare_exchange(
             current,
             ProcessTimer::TimedOut,
@@ -738,7 +910,7 @@ impl Process {
 
     /// Send `message` from `sender` to this process
     pub fn send(self: Arc<Self>, sender: WeakAddress, message: Term) -> Result<(), ()> {
-        let fragment = TermFragment::new(message).unwrap();
+        let fragment = TermFragment::copy_from(&message, CopyMode::Flat).unwrap();
         self.send_fragment(sender, fragment)
     }
 
@@ -757,6 +929,38 @@ impl Process {
         )
     }
 
+    /// Sends each message allocated in `fragments` from `sender` to this process, in order,
+    /// returning the number of messages sent
+    ///
+    /// Unlike sending each message individually, the queue of this process is locked once for the
+    /// whole batch, and the process is woken up at most once.
+    pub fn send_fragments<I>(
+        self: Arc<Self>,
+        sender: WeakAddress,
+        fragments: I,
+    ) -> Result<usize, ()>
+    where
+        I: IntoIterator<Item = TermFragment>,
+    {
+        if self.status(Ordering::Relaxed).contains(StatusFlags::EXITING) {
+            return Err(());
+        }
+        let entries = fragments
+            .into_iter()
+            .map(|message| {
+                SignalEntry::new(Signal::Message(Message {
+                    sender: sender.clone(),
+                    message,
+                }))
+            })
+            .collect::<Vec<_>>();
+        let sent = self.signals.push_messages(&sender, entries);
+        if sent > 0 {
+            self.wake_for_message();
+        }
+        Ok(sent)
+    }
+
     /// Sends a raw signal entry to this process
     #[inline]
     pub fn send_signal(self: Arc<Self>, entry: Box<SignalEntry>) -> Result<(), ()> {
@@ -803,20 +1007,27 @@ impl Process {
             self.signals.push(entry);
         }
 
+        self.wake_for_message();
+        Ok(())
+    }
+
+    /// Marks this process active after a message has been pushed to its queue, scheduling it if
+    /// it was suspended waiting for one
+    fn wake_for_message(self: Arc<Self>) {
         // Acquire the status again since we may have context-switched on a lock when pushing
-        status = self.status(Ordering::Relaxed);
+        let mut status = self.status(Ordering::Relaxed);
 
         // If the process is currently active, we're done
         if status.intersects(StatusFlags::RUNNING | StatusFlags::ACTIVE) {
             trace!(target: "process", "recipient is currently active, send considered successful");
-            return Ok(());
+            return;
         }
 
         // Finally, mark the process active/non-suspended if it wasn't already
         trace!(target: "process", "recipient is currently inactive, attempting to reschedule it");
         loop {
             if status.contains(StatusFlags::EXITING | StatusFlags::ACTIVE) {
-                return Ok(());
+                return;
             }
             let mut new_status = status | StatusFlags::ACTIVE;
             new_status.remove(StatusFlags::SUSPENDED);
@@ -841,8 +1052,6 @@ impl Process {
                 }
             }
         }
-
-        Ok(())
     }
 
     fn do_send_signal(
@@ -950,6 +1159,15 @@ impl<'a> ProcessLock<'a> {
         self.as_ref().pid()
     }
 
+    /// Publishes the number of reductions executed by this process so far, see
+    /// `Process::reductions`
+    ///
+    /// This is called by the scheduler at the end of each time slice.
+    pub fn publish_reductions(&self) {
+        let total = self.total_reductions + self.reductions as u64;
+        self.as_ref().reductions.store(total, Ordering::Relaxed);
+    }
+
     #[inline]
     pub fn next_unique(&mut self) -> NonZeroU64 {
         let id = self.guard.uniq;
@@ -962,6 +1180,11 @@ impl<'a> ProcessLock<'a> {
         self.as_ref().initial_call
     }
 
+    #[inline]
+    pub fn spawn_info(&self) -> &SpawnInfo {
+        self.as_ref().spawn_info()
+    }
+
     #[inline]
     pub fn initial_arguments(&self) -> Option<Term> {
         let initial_args = unsafe { &*self.as_ref().initial_arguments.get() };
@@ -983,6 +1206,34 @@ impl<'a> ProcessLock<'a> {
         Process::MAX_REDUCTIONS.saturating_sub(self.guard.reductions)
     }
 
+    /// Consumes `reductions` from the current time slice, without going beyond its end
+    ///
+    /// Native functions which do an amount of work proportional to their input should call this
+    /// as they go, so that the cost of that work is accounted to the process.
+    #[inline]
+    pub fn bump_reductions(&mut self, reductions: usize) {
+        self.guard.reductions = cmp::min(
+            Process::MAX_REDUCTIONS,
+            self.guard.reductions.saturating_add(reductions),
+        );
+    }
+
+    /// Returns true if the current time slice has been used up
+    ///
+    /// A long-running native function which sees this should save its progress and trap back to
+    /// itself, rather than continue, so that the scheduler can preempt the process in between.
+    #[inline]
+    pub fn should_yield(&self) -> bool {
+        self.guard.reductions >= Process::MAX_REDUCTIONS
+    }
+
+    /// Consumes the rest of the current time slice, so that the process is scheduled out as soon
+    /// as the current native function returns or traps
+    #[inline]
+    pub fn yield_now(&mut self) {
+        self.guard.reductions = Process::MAX_REDUCTIONS;
+    }
+
     #[inline]
     pub fn signals(&self) -> &SignalQueue {
         self.as_ref().signals()
@@ -1008,11 +1259,36 @@ impl<'a> ProcessLock<'a> {
         self.as_ref().max_heap_size()
     }
 
+    #[inline]
+    pub fn message_queue_data(&self) -> MessageQueueData {
+        self.as_ref().message_queue_data()
+    }
+
+    /// Changes where messages waiting in the queue of this process are stored, returning the
+    /// previous setting, see `SignalQueueLock::set_message_queue_data`
+    pub fn set_message_queue_data(&self, mode: MessageQueueData) -> MessageQueueData {
+        let prev = self.signals().lock().set_message_queue_data(mode);
+        match mode {
+            MessageQueueData::OffHeap => {
+                self.set_status_flags(StatusFlags::OFF_HEAP_MSGQ, Ordering::Relaxed);
+            }
+            MessageQueueData::OnHeap => {
+                self.remove_status_flags(StatusFlags::OFF_HEAP_MSGQ, Ordering::Relaxed);
+            }
+        }
+        prev
+    }
+
     #[inline]
     pub fn status(&self, ordering: Ordering) -> StatusFlags {
         self.as_ref().status(ordering)
     }
 
+    #[inline]
+    pub fn status_snapshot(&self) -> ProcessStatus {
+        self.as_ref().status_snapshot()
+    }
+
     #[inline]
     pub fn set_status_flags(&self, flags: StatusFlags, ordering: Ordering) -> StatusFlags {
         self.as_ref().set_status_flags(flags, ordering)
@@ -1039,7 +1315,7 @@ impl<'a> ProcessLock<'a> {
 
     /// Send `message` from `sender` to this process
     pub fn send(&mut self, sender: WeakAddress, message: Term) -> Result<(), ()> {
-        let fragment = TermFragment::new(message).unwrap();
+        let fragment = TermFragment::copy_from(&message, CopyMode::Flat).unwrap();
         self.send_fragment(sender, fragment)
     }
 
@@ -1280,6 +1556,9 @@ impl<'a> ProcessLock<'a> {
             true
         } else if self.guard.flags.contains(ProcessFlags::FORCE_GC) {
             true
+        } else if self.as_ref().persistent_term_epoch() < persistent_term::epoch() {
+            // This process may still refer to a retired persistent term
+            true
         } else {
             self.guard.heap.should_collect(self.guard.gc_threshold)
         }
@@ -1295,10 +1574,63 @@ impl<'a> ProcessLock<'a> {
     /// of reductions that the collection approximately cost. Otherwise, `Err` is returned
     /// with the cause.
     #[inline(never)]
-    pub fn garbage_collect(&mut self, mut roots: RootSet) -> Result<usize, GcError> {
+    pub fn garbage_collect(&mut self, roots: RootSet) -> Result<usize, GcError> {
+        let reductions = self.collect(roots, false)?;
+        self.enforce_max_heap_size();
+        Ok(reductions)
+    }
+
+    /// Performs a full sweep on this process which shrinks its heap to the size of its live data
+    ///
+    /// This is used when hibernating, so the caller is expected to have already discarded the
+    /// call stack of the process, leaving only the values it must retain.
+    #[inline(never)]
+    pub fn garbage_collect_hibernate(&mut self) -> Result<usize, GcError> {
+        self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
+        let reductions = self.collect(RootSet::default(), true)?;
+        self.enforce_max_heap_size();
+        Ok(reductions)
+    }
+
+    fn collect(&mut self, mut roots: RootSet, hibernate: bool) -> Result<usize, GcError> {
+        use firefly_alloc::heap::GenerationalHeap;
+
         let needed = self.guard.gc_needed;
         log::trace!(target: "process", "starting garbage collection ({} bytes needed)", needed);
 
+        let gc_count = self.guard.gc_count;
+        let fullsweep_after = self.as_ref().fullsweep_after.load(Ordering::Relaxed);
+        if gc_count >= fullsweep_after {
+            self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
+        }
+
+        // A full sweep is needed to copy any retired persistent terms this process refers to
+        // into its heap, at which point the storage for them can be freed
+        let epoch = persistent_term::epoch();
+        let lagging = self.as_ref().persistent_term_epoch() < epoch;
+        if lagging {
+            self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
+        }
+
+        // If the mature generation can't hold the mature objects in the young generation, a
+        // minor collection isn't possible either
+        let mature_range = self.guard.heap.immature().mature_range();
+        let mature_size = unsafe { mature_range.start.sub_ptr(mature_range.end) };
+        let mature_available = self.guard.heap.mature().heap_available();
+        if !self.guard.heap.mature().is_empty() && mature_size > mature_available {
+            log::trace!(target: "gc", "insufficient space on target mature heap (only {} available), full sweep required", mature_available);
+            self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
+        }
+
+        let fullsweep = self.guard.flags.contains(ProcessFlags::NEED_FULLSWEEP);
+        let (start, end) = if fullsweep {
+            (atoms::GcMajorStart, atoms::GcMajorEnd)
+        } else {
+            (atoms::GcMinorStart, atoms::GcMinorEnd)
+        };
+        let used_before = self.heap_used_words();
+        self.trace_gc(start, Some((needed / mem::size_of::<OpaqueTerm>()) as i64));
+
         for root in self.guard.stack.stack.iter().take(self.guard.stack.sp) {
             roots += (root as *const OpaqueTerm).cast_mut();
         }
@@ -1307,20 +1639,177 @@ impl<'a> ProcessLock<'a> {
             roots += (tuple as *const Term).cast_mut();
         }
 
-        let gc_count = self.guard.gc_count;
-        let fullsweep_after = self.as_ref().fullsweep_after.load(Ordering::Relaxed);
-        if gc_count >= fullsweep_after {
-            self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
+        unsafe {
+            self.guard.dictionary.roots(&mut roots);
+        }
+
+        // Messages on the process heap are roots too, so the queue stays locked until we're done
+        let process = self.process;
+        let mut signals = process.signals.lock();
+        unsafe {
+            signals.root_messages(&mut roots, &mut self.guard.heap_fragments);
         }
 
-        if self.guard.flags.contains(ProcessFlags::NEED_FULLSWEEP) {
-            self.gc_full(needed, roots)
+        let reductions = if fullsweep {
+            let reductions = self.gc_full(needed, roots, hibernate)?;
+            self.as_ref()
+                .persistent_term_epoch
+                .store(epoch, Ordering::Release);
+            if lagging {
+                persistent_term::reclaim();
+            }
+            reductions
         } else {
-            self.gc_minor(needed, roots)
+            self.gc_minor(needed, roots)?
+        };
+        // The queue must be unlocked before tracing, as the process may be its own tracer
+        drop(signals);
+        self.publish_memory_usage();
+
+        let reclaimed = used_before as i64 - self.heap_used_words() as i64;
+        self.trace_gc(end, Some(reclaimed));
+        Ok(reductions)
+    }
+
+    /// Returns the number of words used by both generations of the heap of this process
+    fn heap_used_words(&self) -> usize {
+        use firefly_alloc::heap::GenerationalHeap;
+
+        let used = self.guard.heap.immature().heap_used() + self.guard.heap.mature().heap_used();
+        used / mem::size_of::<OpaqueTerm>()
+    }
+
+    /// Sends the garbage collection trace event `event` to the tracer of this process, if any
+    ///
+    /// The event carries the sizes of the heap of this process in words, along with `wordsize`,
+    /// which is the space needed for a `*_start` event, or the space reclaimed for an `*_end`
+    /// event.
+    fn trace_gc(&self, event: Atom, wordsize: Option<i64>) {
+        use firefly_alloc::heap::GenerationalHeap;
+
+        let event_flag = trace::TraceFlags::GARBAGE_COLLECTION;
+        if self.as_ref().trace().tracer_for(event_flag).is_none() {
+            return;
         }
+
+        let words = |bytes: usize| (bytes / mem::size_of::<OpaqueTerm>()) as i64;
+        let immature = self.guard.heap.immature();
+        let mature = self.guard.heap.mature();
+        let fragments = self
+            .guard
+            .heap_fragments
+            .iter()
+            .map(|fragment| fragment.heap_size())
+            .sum::<usize>();
+        let info = [
+            (atoms::HeapSize, words(immature.heap_used())),
+            (atoms::HeapBlockSize, words(immature.heap_size())),
+            (atoms::OldHeapSize, words(mature.heap_used())),
+            (atoms::OldHeapBlockSize, words(mature.heap_size())),
+            (atoms::MbufSize, words(fragments)),
+            (atoms::StackSize, self.guard.stack.sp as i64),
+            (atoms::Wordsize, wordsize.unwrap_or_default()),
+        ];
+        let len = if wordsize.is_some() { info.len() } else { info.len() - 1 };
+        let info = trace::make_info_fragment(&info[..len]);
+        trace::trace_event(self.as_ref(), event_flag, event, &[info.term.into()]);
+    }
+
+    /// Returns the total size in bytes of the heap of this process, i.e. both generations, heap
+    /// fragments and the stack, which is what `max_heap_size` limits
+    pub fn total_heap_size(&self) -> usize {
+        use firefly_alloc::heap::GenerationalHeap;
+
+        let word_size = mem::size_of::<OpaqueTerm>();
+        let fragments = self
+            .guard
+            .heap_fragments
+            .iter()
+            .map(|fragment| fragment.heap_size())
+            .sum::<usize>();
+        self.guard.heap.immature().heap_size()
+            + self.guard.heap.mature().heap_size()
+            + fragments
+            + self.guard.stack.capacity() * word_size
+    }
+
+    /// Publishes the memory usage of this process following a collection, see `Process::memory`
+    fn publish_memory_usage(&mut self) {
+        use firefly_alloc::heap::GenerationalHeap;
+
+        let heap = self.total_heap_size();
+        let immature = self.guard.heap.immature();
+        let binary = self.guard.mature_binary_size + gc::rc_binary_size(immature.used_range());
+        self.as_ref().memory.publish(heap, binary);
     }
 
-    fn gc_full(&mut self, needed: usize, roots: RootSet) -> Result<usize, GcError> {
+    /// Checks the heap of this process against its `max_heap_size` following a collection
+    ///
+    /// If the heap is larger than the limit, a `gc_max_heap_size` trace event is sent if garbage
+    /// collection of this process is traced, an error report is sent to the logger, and the process
+    /// is killed, if configured to do so. The process is killed by sending itself an exit signal
+    /// with reason `kill`, which cannot be trapped, and its remaining reductions are consumed so
+    /// that it is scheduled out and handles the signal right away.
+    fn enforce_max_heap_size(&mut self) {
+        use smallvec::SmallVec;
+
+        use crate::services::error_logger;
+
+        const MAX_HEAP_ERROR_FORMAT: &'static str = "      Process:            ~p~n\
+            Context:            maximum heap size reached~n\
+            Max Heap Size:      ~p~n\
+            Total Heap Size:    ~p~n\
+            Kill:               ~p~n\
+            Error Logger:       ~p~n\
+            Message Queue Len:  ~p~n\
+            GC Info:            ~p~n";
+
+        let max_heap_size = self.max_heap_size();
+        let Some(max_size) = max_heap_size.size else { return; };
+        let heap_size = self.total_heap_size();
+        if heap_size <= max_size.get() {
+            return;
+        }
+        log::trace!(target: "gc", "heap size of {} bytes exceeds the max heap size", heap_size);
+        self.trace_gc(atoms::GcMaxHeapSize, None);
+
+        if max_heap_size.error_logger {
+            let signals_len = self.signals().lock().len();
+            let mut pid = self.pid();
+            let heap_words = heap_size / mem::size_of::<OpaqueTerm>();
+            let mut format_args = SmallVec::<[OpaqueTerm; 8]>::default();
+            format_args.push(unsafe { gc::Gc::from_raw(&mut pid) }.into());
+            format_args.push(Term::Int(max_heap_size.size_in_words() as i64).into());
+            format_args.push(Term::Int(heap_words as i64).into());
+            format_args.push(max_heap_size.kill.into());
+            format_args.push(true.into());
+            format_args.push(Term::Int(signals_len.try_into().unwrap()).into());
+            format_args.push(OpaqueTerm::NIL);
+            error_logger::send_error_term_to_logger(
+                MAX_HEAP_ERROR_FORMAT,
+                format_args,
+                self.group_leader().cloned(),
+            )
+            .ok();
+        }
+
+        if max_heap_size.kill {
+            let signal = SignalEntry::new(Signal::Exit(signals::Exit {
+                sender: Some(self.addr()),
+                reason: TermFragment::new(atoms::Kill.into()).unwrap(),
+                normal_kills: false,
+            }));
+            self.send_signal(signal).ok();
+            self.guard.reductions = Process::MAX_REDUCTIONS;
+        }
+    }
+
+    fn gc_full(
+        &mut self,
+        needed: usize,
+        roots: RootSet,
+        hibernate: bool,
+    ) -> Result<usize, GcError> {
         use crate::gc::*;
         use firefly_alloc::heap::GenerationalHeap;
 
@@ -1346,26 +1835,22 @@ impl<'a> ProcessLock<'a> {
             baseline_size
         };
 
-        // Verify that our projected heap size is not going to blow the max heap size, if set
-        let max_heap_size = self.max_heap_size();
-        if let Some(max_size) = max_heap_size.size {
-            if max_size.get() < new_heap_size {
-                log::trace!(target: "gc", "max heap size of {} bytes was exceeded ({})", max_size, new_heap_size);
-                return Err(GcError::MaxHeapSizeExceeded);
-            }
-        }
-
         // Unset the heap growth flag and fullswep flags
         self.guard
             .flags
             .remove(ProcessFlags::HEAP_GROW | ProcessFlags::NEED_FULLSWEEP);
 
+        // The roots are needed again to shrink the heap once the live data size is known
+        let shrink_roots = if hibernate { Some(roots.clone()) } else { None };
+
         // Allocate target heap (new immature generation)
         let mut target = ProcessHeap::new(new_heap_size);
 
         let mut collector =
             SimpleCollector::new(FullCollection::new(&mut self.guard.heap, &mut target));
-        let moved = collector.garbage_collect(roots)?;
+        let mut moved = collector.garbage_collect(roots)?;
+        // The mature generation was freed by the collection
+        self.guard.mature_binary_size = 0;
 
         // Calculate reclamation for tracing
         let size_after = self.guard.heap.immature().heap_used();
@@ -1377,6 +1862,14 @@ impl<'a> ProcessLock<'a> {
             log::trace!(target: "gc", "garbage collection resulted in heap growth of {} bytes", size_after - size_before);
         }
 
+        // A hibernating process is not expected to allocate until it is woken up, so its heap is
+        // shrunk to fit the live data, rather than sized for future allocations
+        if let Some(roots) = shrink_roots {
+            moved += self.gc_shrink_to_fit(min_heap_size, roots)?;
+            self.guard.gc_count = 0;
+            return Ok(estimate_cost(moved, 0));
+        }
+
         // Check if the needed space consumes more than 75% of the new heap,
         // and if so, schedule some heap growth to try and get ahead of allocations
         // failing due to lack of space
@@ -1410,6 +1903,32 @@ impl<'a> ProcessLock<'a> {
         Ok(estimate_cost(moved, 0))
     }
 
+    /// Copies the live data of this process into a new heap of exactly the size it occupies, or
+    /// `min_heap_size`, whichever is larger
+    ///
+    /// This must only be called immediately following a full sweep, so that the heap contains
+    /// nothing but live data in a single generation.
+    fn gc_shrink_to_fit(&mut self, min_heap_size: usize, roots: RootSet) -> Result<usize, GcError> {
+        use crate::gc::*;
+        use firefly_alloc::heap::GenerationalHeap;
+
+        let heap_used = self.guard.heap.immature().heap_used();
+        let heap_size = self.guard.heap.immature().heap_size();
+        let new_heap_size = cmp::max(
+            cmp::max(min_heap_size, heap_used),
+            mem::size_of::<OpaqueTerm>(),
+        );
+        if new_heap_size >= heap_size {
+            return Ok(0);
+        }
+
+        log::trace!(target: "gc", "shrinking heap from {} to {} bytes", heap_size, new_heap_size);
+        let mut target = ProcessHeap::new(new_heap_size);
+        let mut collector =
+            SimpleCollector::new(FullCollection::new(&mut self.guard.heap, &mut target));
+        collector.garbage_collect(roots)
+    }
+
     fn gc_minor(&mut self, needed: usize, roots: RootSet) -> Result<usize, GcError> {
         use crate::gc::*;
         use firefly_alloc::heap::GenerationalHeap;
@@ -1424,48 +1943,17 @@ impl<'a> ProcessLock<'a> {
         let mature_size = unsafe { mature_range.start.sub_ptr(mature_range.end) };
         log::trace!(target: "gc", "source mature heap size is {} bytes", mature_size);
 
-        // Verify that our projected heap size does not exceed the max heap size, if set
-        let max_heap_size = self.max_heap_size();
-        let has_mature = !self.guard.heap.mature().is_empty();
-        if let Some(max_size) = max_heap_size.size {
-            let mut heap_size = size_before;
-            if !has_mature && mature_size > 0 {
-                heap_size += ProcessHeap::next_size(size_before);
-            } else if has_mature {
-                heap_size += self.guard.heap.mature().heap_used();
-            }
-
-            // Add potential new young heap size, conservatively estimating
-            // the worst case scenario where we free no memory and need to reclaim
-            // `needed` bytes. We grow the projected size until there is at least
-            // enough memory for the current heap + `needed`
-            let baseline_size = size_before + needed;
-            heap_size += ProcessHeap::next_size(baseline_size);
-
-            if heap_size > max_size.get() {
-                log::trace!(target: "gc", "estimated target heap size of {} exceeds the max heap size of {}", heap_size, max_size);
-                return Err(GcError::MaxHeapSizeExceeded);
-            }
-        }
-
         // Allocate an old generation if we don't have one
-        if !has_mature && mature_size > 0 {
+        //
+        // The caller has already checked that an existing old generation is large enough to hold
+        // the mature objects in the young generation, switching to a full sweep otherwise
+        if self.guard.heap.mature().is_empty() && mature_size > 0 {
             let size = ProcessHeap::next_size(size_before);
             log::trace!(target: "gc", "allocating a fresh mature generation heap of {} bytes", size);
             let heap = ProcessHeap::new(size);
             let _ = self.guard.heap.swap_mature(heap);
         }
 
-        // If the old heap isn't present, or isn't large enough to hold
-        // the mature objects in the young generation, then a full sweep is
-        // required
-        let mature_available = self.guard.heap.mature().heap_available();
-        if has_mature && mature_size > mature_available {
-            log::trace!(target: "gc", "insufficient space on target mature heap (only {} available), full sweep required", mature_available);
-            // Switch to a full collection
-            return self.gc_full(needed, roots);
-        }
-
         let prev_old_top = self.guard.heap.mature().heap_top();
         let baseline_size = cmp::max(min_heap_size, size_before + needed);
         // While we expect that we will free memory during collection,
@@ -1483,6 +1971,10 @@ impl<'a> ProcessLock<'a> {
             SimpleCollector::new(MinorCollection::new(&mut source, &mut self.guard.heap));
         let moved = collector.garbage_collect(roots)?;
 
+        // Account for any binaries referenced from newly tenured terms
+        let tenured = prev_old_top..self.guard.heap.mature().heap_top();
+        self.guard.mature_binary_size += gc::rc_binary_size(tenured);
+
         // Calculate memory usage after collection
         let new_mature_size = unsafe { self.guard.heap.mature().heap_top().sub_ptr(prev_old_top) };
         let heap_used = self.guard.heap.immature().heap_used();
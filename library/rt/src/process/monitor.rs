@@ -8,9 +8,10 @@ use intrusive_collections::{
 
 use firefly_system::sync::Atomic;
 
-use crate::services::distribution::NodeConnection;
-use crate::services::registry::WeakAddress;
-use crate::term::{atoms, Atom, OpaqueTerm, Pid, Reference, ReferenceId, Term, TermFragment};
+use crate::gc::Gc;
+use crate::services::distribution::{self, NodeConnection};
+use crate::services::registry::{self, WeakAddress};
+use crate::term::*;
 
 use super::ProcessId;
 
@@ -40,9 +41,9 @@ impl TryFrom<Atom> for UnaliasMode {
 
     fn try_from(value: Atom) -> Result<Self, Self::Error> {
         match value {
-            v if v == atoms::Explicit => Ok(Self::Explicit),
+            v if v == atoms::ExplicitUnalias => Ok(Self::Explicit),
             v if v == atoms::Demonitor => Ok(Self::Demonitor),
-            v if v == atoms::ReplyDemonitor => Ok(Self::Explicit),
+            v if v == atoms::ReplyDemonitor => Ok(Self::ReplyDemonitor),
             _ => Err(()),
         }
     }
@@ -78,6 +79,9 @@ bitflags::bitflags! {
         const UNALIAS_REPLY = 1 << 9;
         /// If set, the `name_or_tag` field of the monitor info contains a tag, not a name
         const TAG = 1 << 10;
+        /// If set along with `TAG`, the `name_or_tag` field of the monitor info contains a
+        /// `{Tag, Name}` tuple, see [`make_named_tag`]
+        const NAMED = 1 << 11;
 
         /// The default alias options, when aliasing is enabled
         const ALIAS_DEFAULT = Self::ALIAS.bits | Self::UNALIAS_EXPLICIT.bits;
@@ -250,41 +254,26 @@ impl MonitorEntry {
 
     /// Returns the name associated with this monitor, if applicable, and set
     pub fn name(&self) -> Option<Atom> {
-        if self.flags().contains(MonitorFlags::TAG) {
-            None
+        let flags = self.flags();
+        let name = if flags.contains(MonitorFlags::NAMED) {
+            let Term::Tuple(pair) = self.name_or_tag().into() else { unreachable!() };
+            pair[1]
+        } else if flags.contains(MonitorFlags::TAG) {
+            return None;
         } else {
-            match &self.monitor {
-                Monitor::LocalProcess { ref info, .. }
-                | Monitor::LocalPort { ref info, .. }
-                | Monitor::TimeOffset { ref info, .. } => match info.name_or_tag.term.into() {
-                    Term::None => None,
-                    Term::Atom(name) => Some(name),
-                    _ => unreachable!(),
-                },
-                Monitor::ToExternalProcess { ref info, .. } => match info.name_or_tag.term.into() {
-                    Term::None => None,
-                    Term::Atom(name) => Some(name),
-                    _ => unreachable!(),
-                },
-                Monitor::FromExternalProcess { ref info, .. } => match info.name_or_tag.term.into()
-                {
-                    Term::None => None,
-                    Term::Atom(name) => Some(name),
-                    _ => unreachable!(),
-                },
-                Monitor::Alias { .. }
-                | Monitor::Resource { .. }
-                | Monitor::Node { .. }
-                | Monitor::Nodes { .. }
-                | Monitor::Suspend { .. } => None,
-            }
+            self.name_or_tag()
+        };
+        match name.into() {
+            Term::None => None,
+            Term::Atom(name) => Some(name),
+            _ => unreachable!(),
         }
     }
 
     pub fn node_name(&self) -> Atom {
         match &self.monitor {
             Monitor::Alias { .. } | Monitor::LocalProcess { .. } | Monitor::LocalPort { .. } => {
-                atoms::NoNodeAtNoHost
+                distribution::current_node().name()
             }
             Monitor::ToExternalProcess { ref info, .. } => match info.dist.upgrade() {
                 None => atoms::NoNodeAtNoHost,
@@ -295,7 +284,7 @@ impl MonitorEntry {
                 Some(dist) => dist.name,
             },
             Monitor::Node { target, .. } => *target,
-            _ => atoms::NoNodeAtNoHost,
+            _ => distribution::current_node().name(),
         }
     }
 
@@ -306,11 +295,19 @@ impl MonitorEntry {
         }
 
         let term = match &self.monitor {
-            Monitor::LocalProcess { ref info, .. }
-            | Monitor::LocalPort { ref info, .. }
-            | Monitor::TimeOffset { ref info, .. } => info.name_or_tag.term,
-            Monitor::ToExternalProcess { ref info, .. } => info.name_or_tag.term,
-            Monitor::FromExternalProcess { ref info, .. } => info.name_or_tag.term,
+            Monitor::LocalProcess { .. }
+            | Monitor::LocalPort { .. }
+            | Monitor::TimeOffset { .. }
+            | Monitor::ToExternalProcess { .. }
+            | Monitor::FromExternalProcess { .. } => {
+                let term = self.name_or_tag();
+                if self.flags().contains(MonitorFlags::NAMED) {
+                    let Term::Tuple(pair) = term.into() else { unreachable!() };
+                    pair[0]
+                } else {
+                    term
+                }
+            }
             Monitor::Resource { .. } => return None,
             Monitor::Node { ref info, .. } | Monitor::Nodes { ref info, .. } => info.tag.term,
             Monitor::Suspend { ref info, .. } => info.tag.term,
@@ -324,6 +321,39 @@ impl MonitorEntry {
         }
     }
 
+    /// Returns the contents of the `name_or_tag` field of the monitor info, if it has one
+    fn name_or_tag(&self) -> OpaqueTerm {
+        match &self.monitor {
+            Monitor::LocalProcess { ref info, .. }
+            | Monitor::LocalPort { ref info, .. }
+            | Monitor::TimeOffset { ref info, .. } => info.name_or_tag.term,
+            Monitor::ToExternalProcess { ref info, .. } => info.name_or_tag.term,
+            Monitor::FromExternalProcess { ref info, .. } => info.name_or_tag.term,
+            Monitor::Alias { .. }
+            | Monitor::Resource { .. }
+            | Monitor::Node { .. }
+            | Monitor::Nodes { .. }
+            | Monitor::Suspend { .. } => OpaqueTerm::NONE,
+        }
+    }
+
+    /// Returns the alias which outlives this monitor once it is removed, if any
+    ///
+    /// Aliases created with a monitor are deactivated along with it, unless they were created with
+    /// `{alias, explicit_unalias}`, in which case only `unalias/1` deactivates them.
+    pub fn to_alias(&self, pid: Pid) -> Option<Arc<Self>> {
+        let flags = self.flags();
+        if flags.alias() != Some(UnaliasMode::Explicit) {
+            return None;
+        }
+        let alias = Self::new(Monitor::Alias {
+            origin: pid.id(),
+            reference: Reference::new_pid(self.key(), pid),
+        });
+        alias.set_flags(flags & MonitorFlags::ALIAS_MASK);
+        Some(alias)
+    }
+
     #[doc(hidden)]
     pub fn key(&self) -> ReferenceId {
         match &self.monitor {
@@ -498,3 +528,153 @@ pub struct SuspendMonitorInfo {
     /// If set, uses a custom tag for this monitor
     pub tag: TermFragment,
 }
+
+/// Constructs the contents of the `name_or_tag` field for a monitor by `name` with a custom `tag`
+///
+/// The monitor must have both the `TAG` and `NAMED` flags set.
+pub fn make_named_tag(tag: OpaqueTerm, name: Atom) -> TermFragment {
+    let tag: Term = tag.into();
+    let mut layout = LayoutBuilder::new();
+    layout += tag.layout();
+    layout.build_tuple(2);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
+    let pair = Tuple::from_slice(&[tag.into(), name.into()], fragment).unwrap();
+
+    TermFragment {
+        term: pair.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Constructs the message sent to the origin of a monitor when it is triggered
+///
+/// This is `{Tag, Ref, Type, Object, Info}`, where `Tag` is `'DOWN'` unless a custom tag was given,
+/// and `Object` is `{Name, Node}` if the monitor was created by `name`, otherwise it is `target`.
+pub fn make_down_message(
+    tag: Option<OpaqueTerm>,
+    reference: Reference,
+    ty: Atom,
+    name: Option<(Atom, Atom)>,
+    target: WeakAddress,
+    reason: Term,
+) -> TermFragment {
+    let tag: Term = tag.unwrap_or_else(|| atoms::DOWN.into()).into();
+    let mut layout = LayoutBuilder::new();
+    layout += tag.layout();
+    layout += reason.layout();
+    match (name, &target) {
+        (Some(_), _) => {
+            layout.build_tuple(2);
+        }
+        (None, WeakAddress::Process(_)) => {
+            layout.build_pid();
+        }
+        (None, WeakAddress::Port(_)) => {
+            layout.build_port();
+        }
+        (None, _) => (),
+    }
+    layout.build_reference().build_tuple(5);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
+    let reason = unsafe { reason.unsafe_clone_to_heap(fragment) };
+    let object: OpaqueTerm = match (name, target) {
+        (Some((name, node)), _) => Tuple::from_slice(&[name.into(), node.into()], fragment)
+            .unwrap()
+            .into(),
+        (None, WeakAddress::Process(pid)) => Gc::new_in(pid, fragment).unwrap().into(),
+        (None, WeakAddress::Port(id)) => match registry::get_by_port_id(id) {
+            Some(port) => Term::Port(port).into(),
+            None => atoms::Undefined.into(),
+        },
+        (None, WeakAddress::Name(name)) => name.into(),
+        (None, WeakAddress::System) => atoms::System.into(),
+    };
+    let reference = Gc::new_in(reference, fragment).unwrap();
+    let message = Tuple::from_slice(
+        &[
+            tag.into(),
+            reference.into(),
+            ty.into(),
+            object,
+            reason.into(),
+        ],
+        fragment,
+    )
+    .unwrap();
+
+    TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scheduler::SchedulerId;
+
+    use super::*;
+
+    #[test]
+    fn monitor_down_message_test() {
+        let id = unsafe { ReferenceId::new(SchedulerId::from_raw(1), 1) };
+        let pid = Pid::new_local(unsafe { ProcessId::new_unchecked(1, 0) });
+        let target = Pid::new_local(unsafe { ProcessId::new_unchecked(2, 0) });
+        let monitor = MonitorEntry::new(Monitor::LocalProcess {
+            origin: pid.id(),
+            target: target.id(),
+            info: LocalMonitorInfo {
+                reference: id,
+                name_or_tag: TermFragment::new(atoms::Reply.into()).unwrap(),
+            },
+        });
+
+        // Only explicit aliases outlive their monitor
+        monitor.set_flags(MonitorFlags::TAG | UnaliasMode::Demonitor);
+        assert!(monitor.to_alias(pid.clone()).is_none());
+        monitor.set_flags(MonitorFlags::empty() | UnaliasMode::Explicit);
+        let alias = monitor.to_alias(pid.clone()).unwrap();
+        assert_eq!(alias.key(), id);
+        assert_eq!(alias.flags().alias(), Some(UnaliasMode::Explicit));
+
+        let down = make_down_message(
+            monitor.tag(),
+            Reference::new(id),
+            atoms::Process,
+            monitor.name(),
+            monitor.target().unwrap(),
+            atoms::Noproc.into(),
+        );
+        let Term::Tuple(down) = down.term.into() else { panic!("expected tuple") };
+        assert_eq!(down.len(), 5);
+        assert_eq!(down[0], atoms::Reply.into());
+        assert_eq!(down[2], atoms::Process.into());
+        assert_eq!(Term::from(down[3]), Term::Pid(Gc::new(target)));
+        assert_eq!(down[4], atoms::Noproc.into());
+    }
+
+    #[test]
+    fn monitor_named_tag_test() {
+        let id = unsafe { ReferenceId::new(SchedulerId::from_raw(1), 2) };
+        let pid = Pid::new_local(unsafe { ProcessId::new_unchecked(1, 0) });
+        let target = Pid::new_local(unsafe { ProcessId::new_unchecked(2, 0) });
+        let monitor = MonitorEntry::new(Monitor::LocalProcess {
+            origin: pid.id(),
+            target: target.id(),
+            info: LocalMonitorInfo {
+                reference: id,
+                name_or_tag: make_named_tag(atoms::Reply.into(), atoms::Undefined),
+            },
+        });
+        monitor.set_flags(MonitorFlags::TAG | MonitorFlags::NAMED);
+
+        // Both the tag and the name are recovered from the pair
+        assert_eq!(monitor.tag(), Some(atoms::Reply.into()));
+        assert_eq!(monitor.name(), Some(atoms::Undefined));
+    }
+}
@@ -11,19 +11,22 @@ use core::ptr;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use intrusive_collections::intrusive_adapter;
-use intrusive_collections::{LinkedList, LinkedListLink};
+use intrusive_collections::{LinkedList, LinkedListLink, UnsafeRef};
 
 use log::trace;
 
+use firefly_alloc::fragment::HeapFragmentList;
 use firefly_system::mem::CachePadded;
+use firefly_system::sync::lcnt::LockClass;
 use firefly_system::sync::{Atomic, Mutex, MutexGuard};
 
+use crate::gc::RootSet;
 use crate::services::registry::WeakAddress;
-use crate::term::{Atom, Pid, Reference, TermFragment};
+use crate::term::{Atom, OpaqueTerm, Pid, Reference, ReferenceId, TermFragment};
 
 use super::link::LinkEntry;
 use super::monitor::MonitorEntry;
-use super::{Priority, Process, ProcessLock};
+use super::{MessageQueueData, Priority, Process, ProcessLock};
 
 pub type RpcCallback = fn(process: &mut ProcessLock, state: *mut ()) -> TermFragment;
 
@@ -89,6 +92,8 @@ pub enum Signal {
     /// type must unconditionally enter a receive that matches on `Ref` in all clauses, or bad
     /// things will happen.
     Rpc(Rpc),
+    /// The result of a `spawn_request` made by the receiver on a remote node
+    SpawnReply(SpawnReply),
 }
 impl fmt::Debug for Signal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -107,6 +112,7 @@ impl fmt::Debug for Signal {
             Self::ProcessInfo(_) => f.debug_struct("ProcessInfo").finish(),
             Self::Flush(_) => f.debug_struct("Flush").finish(),
             Self::Rpc(_) => f.debug_struct("Rpc").finish(),
+            Self::SpawnReply(_) => f.debug_struct("SpawnReply").finish(),
         }
     }
 }
@@ -133,16 +139,57 @@ impl Signal {
         SignalEntry::new(Self::Unlink(Unlink { sender, id }))
     }
 
+    #[inline]
+    pub fn group_leader(
+        sender: Pid,
+        group_leader: Pid,
+        reference: Option<Reference>,
+    ) -> Box<SignalEntry> {
+        SignalEntry::new(Self::GroupLeader(GroupLeader {
+            sender,
+            group_leader,
+            reference,
+        }))
+    }
+
     #[inline]
     pub fn is_alive(sender: Pid, reference: Reference) -> Box<SignalEntry> {
         SignalEntry::new(Self::IsAlive(IsAlive { sender, reference }))
     }
 
+    #[inline]
+    pub fn process_info(
+        sender: Arc<Process>,
+        items: TermFragment,
+        reference: Reference,
+        need_msgq_len: bool,
+    ) -> Box<SignalEntry> {
+        SignalEntry::new(Self::ProcessInfo(ProcessInfo {
+            sender: Some(sender),
+            items,
+            reference,
+            need_msgq_len,
+        }))
+    }
+
     #[inline]
     pub fn flush(ty: FlushType) -> Box<SignalEntry> {
         SignalEntry::new(Self::Flush(Flush { sender: None, ty }))
     }
 
+    #[inline]
+    pub fn spawn_reply(
+        reference: ReferenceId,
+        result: Result<Pid, Atom>,
+        link: bool,
+    ) -> Box<SignalEntry> {
+        SignalEntry::new(Self::SpawnReply(SpawnReply {
+            reference,
+            result,
+            link,
+        }))
+    }
+
     #[inline]
     pub fn rpc_noreply(
         sender: Pid,
@@ -176,6 +223,7 @@ impl DynSignal for Signal {
             Self::ProcessInfo(sig) => sig.sender(),
             Self::Flush(sig) => sig.sender(),
             Self::Rpc(sig) => sig.sender(),
+            Self::SpawnReply(sig) => sig.sender(),
         }
     }
 }
@@ -333,14 +381,15 @@ impl DynSignal for UnlinkAck {
 /// Represents a request to change the group leader of the receiving process.
 ///
 /// If sent locally, a response message of `{Ref, true | badarg}` is sent to the sender,
-/// where `Ref` is the reference given.
+/// where `Ref` is the reference given. Changes requested by a remote node via the `GROUP_LEADER`
+/// control message have no reference, and are not replied to.
 pub struct GroupLeader {
     /// The process enacting the change
     pub sender: Pid,
     /// The new group leader
     pub group_leader: Pid,
-    /// The reference associated with this request
-    pub reference: Reference,
+    /// The reference associated with this request, if a reply is expected
+    pub reference: Option<Reference>,
 }
 impl DynSignal for GroupLeader {
     fn sender(&self) -> Option<WeakAddress> {
@@ -372,8 +421,8 @@ pub struct ProcessInfo {
     ///
     /// This is `None` if the signal arrived via distribution
     pub sender: Option<Arc<Process>>,
-    /// The atom representing the info type requested
-    pub item: Atom,
+    /// The item, or list of items, requested, as given to `erlang:process_info/2`
+    pub items: TermFragment,
     /// The reference used int he response message
     pub reference: Reference,
     /// If true, the message queue length is needed, so it will be calculated before
@@ -412,6 +461,25 @@ impl DynSignal for Rpc {
     }
 }
 
+/// Represents the outcome of a `spawn_request` on a remote node, i.e. `DIST_SPAWN_REPLY`
+///
+/// If the request is still pending when received, the receiver converts this into a
+/// `{Tag, Ref, ok, Pid}` or `{Tag, Ref, error, Reason}` message, according to the options
+/// given to `spawn_request`.
+pub struct SpawnReply {
+    /// The reference returned by `spawn_request`
+    pub reference: ReferenceId,
+    /// The spawned process on success, or the reason the spawn failed
+    pub result: Result<Pid, Atom>,
+    /// True if the spawned process has linked itself to the receiver
+    pub link: bool,
+}
+impl DynSignal for SpawnReply {
+    fn sender(&self) -> Option<WeakAddress> {
+        self.result.as_ref().ok().map(|pid| pid.clone().into())
+    }
+}
+
 // An intrusive linked list adapter for storing boxed signal entries
 intrusive_adapter!(pub SignalAdapter = Box<SignalEntry>: SignalEntry { link: LinkedListLink });
 
@@ -453,6 +521,9 @@ bitflags::bitflags! {
         /// The message queue stores data off-heap
         const OFF_HEAP = 1;
         /// The message queue stores data on-heap
+        ///
+        /// This remains set after switching to off-heap, until the messages which were moved onto
+        /// the process heap have been received, see `SignalQueueLock::root_messages`.
         const ON_HEAP = 1 << 1;
         /// Process is flushing signals
         const FLUSHING = 1 << 4;
@@ -498,6 +569,11 @@ impl Queue {
 /// non-empty
 const NUM_INQ_BUFFERS: usize = mem::size_of::<usize>() * 8;
 
+/// Shared by the in-transit buffers of all processes, see `firefly_system::sync::lcnt`
+static PROC_SIG_BUFFER_LOCK: LockClass = LockClass::new("proc_sig_buffers");
+/// Shared by the private signal queues of all processes
+static PROC_MSGQ_LOCK: LockClass = LockClass::new("proc_msgq");
+
 struct InTransitQueue {
     buffers: [CachePadded<Mutex<Queue>>; NUM_INQ_BUFFERS],
     nonempty_slots: AtomicUsize,
@@ -537,6 +613,7 @@ struct PrivateSignalQueue {
     received: Queue,
     cursor: *const SignalEntry,
     last_seen: *const SignalEntry,
+    marker: Option<ReceiveMarker>,
 }
 impl PrivateSignalQueue {
     #[inline(always)]
@@ -544,6 +621,20 @@ impl PrivateSignalQueue {
         self.received.is_empty()
     }
 
+    /// Moves the receive marker back to the previous message if it points to `entry`, which must
+    /// be called before `entry` is removed from the message queue
+    fn unmark(&mut self, entry: *const SignalEntry) {
+        match self.marker {
+            Some(ref marker) if marker.last == entry => (),
+            _ => return,
+        }
+        let cursor = unsafe { self.received.messages.cursor_from_ptr(entry) };
+        match cursor.peek_prev().get() {
+            None => self.marker = None,
+            Some(prev) => self.marker.as_mut().unwrap().last = prev as *const _,
+        }
+    }
+
     fn cursor(&self) -> SignalCursor<'_> {
         if unlikely(self.cursor.is_null()) {
             // Return a null cursor
@@ -565,6 +656,18 @@ impl PrivateSignalQueue {
     }
 }
 
+/// The position of the message queue at the time a reference was created
+///
+/// A message can only contain a reference which existed when the message was sent, so a receive
+/// which only matches messages containing the reference need not look at the messages which were
+/// already in the queue when it was created. This makes the common pattern of creating a reference
+/// for a request, and receiving the reply tagged with it, independent of the length of the queue.
+struct ReceiveMarker {
+    reference: ReferenceId,
+    /// The last message in the private queue when the reference was created
+    last: *const SignalEntry,
+}
+
 /// This type represents ownership over the `PrivateSignalQueue` of a process.
 ///
 /// The holder of this lock is allowed to send signals directly to the private queue
@@ -581,6 +684,20 @@ impl<'a> SignalQueueLock<'a> {
         self.queue.received.len + self.signals.in_transit.len()
     }
 
+    /// Returns an iterator over the messages in the private queue, oldest first
+    ///
+    /// Messages still in the in-transit buffers are not included, see `flush_buffers`.
+    pub fn messages(&self) -> impl Iterator<Item = &Message> + '_ {
+        self.queue
+            .received
+            .messages
+            .iter()
+            .map(|entry| match entry.signal {
+                Signal::Message(ref msg) => msg,
+                _ => unreachable!(),
+            })
+    }
+
     /// Returns true if there are signals in the private queue
     pub fn has_pending_signals(&self) -> bool {
         !self.queue.received.signals.is_empty()
@@ -660,7 +777,8 @@ impl<'a> SignalQueueLock<'a> {
                 Ok(())
             } else {
                 trace!(target: "process", "messages found in the private queue, setting cursor to front of queue");
-                Err(())
+                self.queue.cursor = cursor.get().unwrap() as *const _;
+                Ok(())
             }
         }
     }
@@ -717,6 +835,8 @@ impl<'a> SignalQueueLock<'a> {
     /// This function assumes that the cursor is valid (as prepared by `try_receive`), and
     /// will panic if there are no messages in the received queue.
     pub fn remove_message(&mut self) -> Message {
+        let cursor = self.queue.cursor;
+        self.queue.unmark(cursor);
         let sig = self.queue.cursor_mut().remove().unwrap();
         trace!(target: "process", "recv_pop successful");
         match sig.signal {
@@ -725,6 +845,12 @@ impl<'a> SignalQueueLock<'a> {
                 self.queue.cursor = ptr::null();
                 self.queue.last_seen = ptr::null();
                 self.queue.received.len -= 1;
+                // Once an off-heap queue is drained, none of its messages are on the process heap
+                if self.queue.received.messages.is_empty()
+                    && self.signals.flags().contains(SignalQueueFlags::OFF_HEAP)
+                {
+                    self.signals.remove_flags(SignalQueueFlags::ON_HEAP);
+                }
                 msg
             }
             _ => unreachable!(),
@@ -737,6 +863,165 @@ impl<'a> SignalQueueLock<'a> {
         self.queue.last_seen = ptr::null();
     }
 
+    /// Marks the end of the private queue as the point after which messages containing
+    /// `reference` may appear, as it was just created
+    ///
+    /// Only one reference is marked at a time, creating another replaces the marker. The in-transit
+    /// buffers are not flushed, so messages still in transit are not skipped by the marker.
+    pub fn set_receive_marker(&mut self, reference: ReferenceId) {
+        let last = self.queue.received.messages.back().get();
+        self.queue.marker = last.map(|entry| ReceiveMarker {
+            reference,
+            last: entry as *const _,
+        });
+    }
+
+    /// Starts the next receive after the messages which were already in the queue when
+    /// `reference` was created, as long as it is still the marked reference
+    ///
+    /// This must only be called before a receive which matches nothing but messages containing
+    /// `reference`. The marker is consumed either way, so it only applies to a single receive.
+    pub fn use_receive_marker(&mut self, reference: ReferenceId) -> bool {
+        let Some(marker) = self.queue.marker.take() else { return false; };
+        if marker.reference != reference {
+            return false;
+        }
+        let cursor = unsafe { self.queue.received.messages.cursor_from_ptr(marker.last) };
+        match cursor.peek_next().get() {
+            Some(next) => {
+                self.queue.cursor = next as *const _;
+                self.queue.last_seen = ptr::null();
+            }
+            None => {
+                // Everything in the queue has been seen already, `try_receive` will start with
+                // the messages in the in-transit buffers, if there are any
+                self.queue.cursor = marker.last;
+                self.queue.last_seen = marker.last;
+            }
+        }
+        trace!(target: "process", "receive marker skipped to {:p}", self.queue.cursor);
+        true
+    }
+
+    /// Removes the first message in the queue for which `predicate` returns true
+    ///
+    /// This is used to drop messages outside of a receive, e.g. a `'DOWN'` message flushed by
+    /// `demonitor/2`, so the receive marker is reset if a message is removed. Messages in the
+    /// in-transit buffers are fetched first, so that messages already sent are also considered.
+    pub fn remove_first_message<F>(&mut self, predicate: F) -> Option<Message>
+    where
+        F: Fn(&Message) -> bool,
+    {
+        let queue = self.queue.deref_mut();
+        self.signals
+            .try_flush_message_buffers(&mut queue.received)
+            .ok();
+        let mut cursor = queue.received.messages.front();
+        let found = loop {
+            let Some(entry) = cursor.get() else { return None; };
+            let found = match entry.signal {
+                Signal::Message(ref msg) => predicate(msg),
+                _ => false,
+            };
+            if found {
+                break entry as *const SignalEntry;
+            }
+            cursor.move_next();
+        };
+        queue.unmark(found);
+        let sig = unsafe { queue.received.messages.cursor_mut_from_ptr(found) }
+            .remove()
+            .unwrap();
+        queue.cursor = ptr::null();
+        queue.last_seen = ptr::null();
+        queue.received.len -= 1;
+        match sig.signal {
+            Signal::Message(msg) => Some(msg),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Removes the first non-message signal in the queue for which `predicate` returns true
+    ///
+    /// This is used to take back a request the process has not handled yet, e.g. a `ProcessInfo`
+    /// signal which was answered on its behalf. Signals in the in-transit buffers are fetched
+    /// first, so that signals already sent are also considered.
+    pub fn remove_first_signal<F>(&mut self, predicate: F) -> Option<Box<SignalEntry>>
+    where
+        F: Fn(&Signal) -> bool,
+    {
+        let queue = self.queue.deref_mut();
+        self.signals
+            .try_flush_signal_buffers(&mut queue.received)
+            .ok();
+        let mut cursor = queue.received.signals.front_mut();
+        while let Some(entry) = cursor.get() {
+            if predicate(&entry.signal) {
+                queue.received.len -= 1;
+                return cursor.remove();
+            }
+            cursor.move_next();
+        }
+        None
+    }
+
+    /// Changes where messages waiting in this queue are stored, returning the previous setting
+    ///
+    /// Messages already in the queue are moved onto the process heap by the next collection when
+    /// switching to on-heap, but messages already on the process heap stay there when switching
+    /// to off-heap.
+    pub fn set_message_queue_data(&mut self, mode: MessageQueueData) -> MessageQueueData {
+        let prev = self.signals.message_queue_data();
+        match mode {
+            MessageQueueData::OnHeap => {
+                self.signals.set_flags(SignalQueueFlags::ON_HEAP);
+                self.signals.remove_flags(SignalQueueFlags::OFF_HEAP);
+            }
+            MessageQueueData::OffHeap => {
+                self.signals.set_flags(SignalQueueFlags::OFF_HEAP);
+                if self.queue.received.messages.is_empty() {
+                    self.signals.remove_flags(SignalQueueFlags::ON_HEAP);
+                }
+            }
+        }
+        prev
+    }
+
+    /// Adds the messages in the private queue which live on the process heap to `roots`
+    ///
+    /// When the queue is on-heap, the fragment of each message is first handed over to `fragments`,
+    /// the heap fragment list of the process, so the collection moves the message onto the heap.
+    /// Messages which were moved onto the heap while on-heap remain roots until received, even if
+    /// the queue has since been switched to off-heap.
+    ///
+    /// Off-heap queues are skipped entirely, so the cost of collecting a process with an off-heap
+    /// queue does not depend on the number of messages waiting in it.
+    ///
+    /// # SAFETY
+    ///
+    /// This must only be called by the process which owns this signal queue, and the lock must be
+    /// held until the collection using `roots` has completed.
+    pub unsafe fn root_messages(&mut self, roots: &mut RootSet, fragments: &mut HeapFragmentList) {
+        let flags = self.signals.flags();
+        if !flags.contains(SignalQueueFlags::ON_HEAP) {
+            return;
+        }
+        let absorb = !flags.contains(SignalQueueFlags::OFF_HEAP);
+        let mut cursor = self.queue.received.messages.front();
+        while let Some(entry) = cursor.get() {
+            // Entries are only ever modified by the owning process, which holds the queue lock
+            let entry = entry as *const SignalEntry as *mut SignalEntry;
+            let Signal::Message(ref mut msg) = (*entry).signal else { unreachable!() };
+            if absorb {
+                if let Some(fragment) = msg.message.fragment.take() {
+                    fragments.push_back(UnsafeRef::from_raw(fragment.as_ptr().cast_const()));
+                }
+            }
+            *roots += &mut msg.message.term as *mut OpaqueTerm;
+            cursor.move_next();
+        }
+    }
+
     /// Pushes the given message to the front of the message queue
     ///
     /// # SAFETY
@@ -746,6 +1031,8 @@ impl<'a> SignalQueueLock<'a> {
     /// converting a MonitorDown signal to a Message, while ensuring that the
     /// resulting message is handled at the same priority as its origin signal.
     pub unsafe fn push_next_message(&mut self, signal: Box<SignalEntry>) {
+        // This message would be skipped by the marker, but may contain the marked reference
+        self.queue.marker = None;
         self.queue.received.messages.push_front(signal);
         self.queue.received.len += 1;
     }
@@ -779,7 +1066,13 @@ impl<'a> SignalQueueLock<'a> {
     /// signals are present
     pub fn pop(&mut self) -> Option<Box<SignalEntry>> {
         match self.queue.received.signals.pop_front() {
-            None => self.queue.received.messages.pop_front(),
+            None => {
+                let front = self.queue.received.messages.front().get();
+                if let Some(entry) = front.map(|entry| entry as *const SignalEntry) {
+                    self.queue.unmark(entry);
+                }
+                self.queue.received.messages.pop_front()
+            }
             sig @ Some(_) => sig,
         }
     }
@@ -868,21 +1161,31 @@ pub struct SignalQueue {
 }
 impl Default for SignalQueue {
     fn default() -> Self {
+        Self::new(MessageQueueData::default())
+    }
+}
+impl SignalQueue {
+    /// Creates an empty signal queue which stores messages according to `mode`
+    pub fn new(mode: MessageQueueData) -> Self {
+        let flags = match mode {
+            MessageQueueData::OffHeap => SignalQueueFlags::OFF_HEAP,
+            MessageQueueData::OnHeap => SignalQueueFlags::ON_HEAP,
+        };
         Self {
-            flags: Atomic::new(SignalQueueFlags::OFF_HEAP),
+            flags: Atomic::new(flags),
             in_transit: InTransitQueue::default(),
             private: Mutex::new(PrivateSignalQueue {
                 received: Queue::default(),
                 cursor: ptr::null(),
                 last_seen: ptr::null(),
+                marker: None,
             }),
         }
     }
-}
-impl SignalQueue {
+
     /// Acquires the signal queue lock for use by the caller
     pub fn lock<'a>(&'a self) -> SignalQueueLock<'a> {
-        let queue = self.private.lock();
+        let queue = PROC_MSGQ_LOCK.lock(&self.private);
         SignalQueueLock {
             signals: self,
             queue,
@@ -891,7 +1194,16 @@ impl SignalQueue {
 
     /// Returns the current flags set on this queue
     pub fn flags(&self) -> SignalQueueFlags {
-        self.flags.load(Ordering::Release)
+        self.flags.load(Ordering::Acquire)
+    }
+
+    /// Returns where messages waiting in this queue are stored
+    pub fn message_queue_data(&self) -> MessageQueueData {
+        if self.flags().contains(SignalQueueFlags::OFF_HEAP) {
+            MessageQueueData::OffHeap
+        } else {
+            MessageQueueData::OnHeap
+        }
     }
 
     /// Sets one or more flags on this queue, returning the previous flags
@@ -917,7 +1229,7 @@ impl SignalQueue {
         let slot = hash_address_to_index(&sender);
         let mut result = SendResult::SUCCESS;
 
-        let mut buffer = self.in_transit.buffers[slot].lock();
+        let mut buffer = PROC_SIG_BUFFER_LOCK.lock(&*self.in_transit.buffers[slot]);
         if buffer.is_empty() {
             // The buffer is empty so we need to notify the receiver,
             // unless some other slot is non-empty, in which case another
@@ -972,6 +1284,38 @@ impl SignalQueue {
         result
     }
 
+    /// Called from the context of the sending entity to place a batch of messages from `sender`
+    /// in the in-transit queue of the receiver, in order
+    ///
+    /// This is equivalent to calling `push` with each message, except that the in-transit buffer
+    /// for `sender` is only locked once. Returns the number of messages pushed.
+    pub(super) fn push_messages<I>(&self, sender: &WeakAddress, messages: I) -> usize
+    where
+        I: IntoIterator<Item = Box<SignalEntry>>,
+    {
+        let slot = hash_address_to_index(sender);
+
+        let mut buffer = PROC_SIG_BUFFER_LOCK.lock(&*self.in_transit.buffers[slot]);
+        let was_empty = buffer.is_empty();
+        let mut pushed = 0;
+        for message in messages {
+            debug_assert!(message.is_message());
+            buffer.messages.push_back(message);
+            pushed += 1;
+        }
+        if pushed > 0 {
+            if was_empty {
+                self.in_transit
+                    .nonempty_slots
+                    .fetch_or(1 << slot, Ordering::Relaxed);
+            }
+            self.in_transit.len.fetch_add(pushed, Ordering::Relaxed);
+            buffer.len += pushed;
+        }
+
+        pushed
+    }
+
     /// This function is called when attempting to fetch any pending messages and there
     /// aren't any in the received queue. When this occurs, we flush all non-empty buffers
     /// containing messages.
@@ -1038,7 +1382,7 @@ impl SignalQueue {
             let messages;
             // Acquire the buffer lock just long enough to take the contents out
             {
-                let mut buffer = self.in_transit.buffers[slot].lock();
+                let mut buffer = PROC_SIG_BUFFER_LOCK.lock(&*self.in_transit.buffers[slot]);
                 debug_assert!(!buffer.is_empty());
                 len = buffer.len;
                 signals = buffer.signals.take();
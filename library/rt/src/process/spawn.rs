@@ -1,11 +1,14 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp;
 use core::num::NonZeroUsize;
 
+use crate::function::ModuleFunctionArity;
 use crate::gc::Gc;
 use crate::term::*;
 
 use super::monitor::{MonitorFlags, UnaliasMode};
-use super::{MaxHeapSize, Priority, Process};
+use super::{MaxHeapSize, Priority, Process, ProcessId};
 
 #[derive(Debug, Copy, Clone)]
 pub struct MonitorOpts {
@@ -49,6 +52,7 @@ impl TryFrom<Term> for MonitorOpts {
                                 monitor_opts.flags |= mode;
                             } else if key == atoms::Tag {
                                 monitor_opts.tag = pair[1];
+                                monitor_opts.flags |= MonitorFlags::TAG;
                             } else {
                                 return Err(());
                             }
@@ -64,12 +68,25 @@ impl TryFrom<Term> for MonitorOpts {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+/// Controls where the messages waiting in the queue of a process are stored
+///
+/// Off-heap messages stay in the heap fragments they were sent in until they are received, so
+/// they are not traversed when the process is collected. On-heap messages are moved onto the
+/// process heap by the next collection, and are roots of every collection until received.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MessageQueueData {
     #[default]
     OffHeap,
     OnHeap,
 }
+impl Into<OpaqueTerm> for MessageQueueData {
+    fn into(self) -> OpaqueTerm {
+        match self {
+            Self::OffHeap => atoms::OffHeap.into(),
+            Self::OnHeap => atoms::OnHeap.into(),
+        }
+    }
+}
 impl TryFrom<Term> for MessageQueueData {
     type Error = ();
 
@@ -82,6 +99,147 @@ impl TryFrom<Term> for MessageQueueData {
     }
 }
 
+/// Controls which replies are sent in response to a `spawn_request`
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpawnReplyMode {
+    /// A reply is sent regardless of the outcome
+    #[default]
+    Yes,
+    /// No reply is sent regardless of the outcome
+    No,
+    /// A reply is only sent if the spawn operation fails
+    ErrorOnly,
+    /// A reply is only sent if the spawn operation succeeds
+    SuccessOnly,
+}
+impl SpawnReplyMode {
+    /// Returns true if a reply should be sent on success
+    #[inline]
+    pub fn reply_on_success(&self) -> bool {
+        match self {
+            Self::Yes | Self::SuccessOnly => true,
+            Self::No | Self::ErrorOnly => false,
+        }
+    }
+
+    /// Returns true if a reply should be sent on error
+    #[inline]
+    pub fn reply_on_error(&self) -> bool {
+        match self {
+            Self::Yes | Self::ErrorOnly => true,
+            Self::No | Self::SuccessOnly => false,
+        }
+    }
+
+    /// Returns the monitor flags used to represent this mode on a pending spawn request
+    pub fn monitor_flags(&self) -> MonitorFlags {
+        let mut flags = MonitorFlags::empty();
+        if !self.reply_on_success() {
+            flags |= MonitorFlags::SPAWN_NO_REPLY_SUCCESS;
+        }
+        if !self.reply_on_error() {
+            flags |= MonitorFlags::SPAWN_NO_REPLY_ERROR;
+        }
+        flags
+    }
+}
+impl TryFrom<Term> for SpawnReplyMode {
+    type Error = ();
+
+    fn try_from(term: Term) -> Result<Self, Self::Error> {
+        match term {
+            Term::Atom(a) if a == atoms::Yes => Ok(Self::Yes),
+            Term::Atom(a) if a == atoms::No => Ok(Self::No),
+            Term::Atom(a) if a == atoms::ErrorOnly => Ok(Self::ErrorOnly),
+            Term::Atom(a) if a == atoms::SuccessOnly => Ok(Self::SuccessOnly),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Constructs the message sent to the requestor in reply to a `spawn_request`
+///
+/// On success this is `{Tag, Ref, ok, Pid}`, otherwise it is `{Tag, Ref, error, Reason}`.
+pub fn make_spawn_reply(
+    tag: OpaqueTerm,
+    reference: Reference,
+    result: Result<Pid, Atom>,
+) -> TermFragment {
+    let tag: Term = tag.into();
+    let mut layout = LayoutBuilder::new();
+    layout += tag.layout();
+    layout.build_reference().build_tuple(4);
+    if result.is_ok() {
+        layout.build_pid();
+    }
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
+    let reference = Gc::new_in(reference, fragment).unwrap();
+    let (status, value): (Atom, OpaqueTerm) = match result {
+        Ok(pid) => (atoms::Ok, Gc::new_in(pid, fragment).unwrap().into()),
+        Err(reason) => (atoms::Error, reason.into()),
+    };
+    let message = Tuple::from_slice(
+        &[tag.into(), reference.into(), status.into(), value],
+        fragment,
+    )
+    .unwrap();
+
+    TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Metadata about how a process was started, recorded when it is spawned.
+///
+/// This is used for crash reports and introspection (e.g. `process_info/2`), and never changes
+/// once the process is created, so it is safe to read concurrently.
+#[derive(Debug, Default, Clone)]
+pub struct SpawnInfo {
+    /// The function the parent was executing when it spawned this process, if known
+    pub spawned_from: Option<ModuleFunctionArity>,
+    /// The ancestors of this process, starting with its parent, then its parent's parent, etc.
+    ///
+    /// At most `SpawnInfo::MAX_ANCESTORS` are recorded, the oldest are dropped first.
+    pub ancestors: Vec<ProcessId>,
+    /// True if this process was linked to its parent when spawned
+    pub link: bool,
+    /// True if this process was monitored by its parent when spawned
+    pub monitor: bool,
+    /// The priority this process was spawned with
+    pub priority: Priority,
+}
+impl SpawnInfo {
+    /// The maximum number of ancestors recorded for a process
+    ///
+    /// This ensures that long chains of processes which spawn their successor do not grow
+    /// without bound.
+    pub const MAX_ANCESTORS: usize = 16;
+
+    /// Records the metadata for a process spawned by `parent` with the given options
+    pub fn new(
+        parent: &Process,
+        spawned_from: Option<ModuleFunctionArity>,
+        opts: &SpawnOpts,
+    ) -> Self {
+        let inherited = parent.spawn_info().ancestors.as_slice();
+        let mut ancestors = Vec::with_capacity(cmp::min(inherited.len() + 1, Self::MAX_ANCESTORS));
+        ancestors.push(parent.id());
+        ancestors.extend(inherited.iter().copied().take(Self::MAX_ANCESTORS - 1));
+
+        Self {
+            spawned_from,
+            ancestors,
+            link: opts.link,
+            monitor: opts.monitor.is_some(),
+            priority: opts.priority,
+        }
+    }
+}
+
 pub struct Spawned {
     pub process: Arc<Process>,
     pub monitor_ref: Gc<Reference>,
@@ -97,7 +255,12 @@ pub struct SpawnOpts {
     pub max_heap_size: MaxHeapSize,
     pub message_queue_data: MessageQueueData,
     pub priority: Priority,
+    /// The tag used in `spawn_request` reply messages
     pub tag: OpaqueTerm,
+    /// Which `spawn_request` reply messages should be sent
+    pub reply: SpawnReplyMode,
+    /// Set if any options were given which are only valid with `spawn_request`
+    pub request_only: bool,
 }
 impl Default for SpawnOpts {
     fn default() -> Self {
@@ -112,6 +275,8 @@ impl Default for SpawnOpts {
             message_queue_data: Default::default(),
             priority: Default::default(),
             tag: atoms::SpawnReply.into(),
+            reply: Default::default(),
+            request_only: false,
         }
     }
 }
@@ -165,6 +330,14 @@ impl TryFrom<Term> for SpawnOpts {
                                 k if k == atoms::MessageQueueData => {
                                     spawn_opts.message_queue_data = value.try_into()?;
                                 }
+                                k if k == atoms::Reply => {
+                                    spawn_opts.reply = value.try_into()?;
+                                    spawn_opts.request_only = true;
+                                }
+                                k if k == atoms::ReplyTag => {
+                                    spawn_opts.tag = pair[1];
+                                    spawn_opts.request_only = true;
+                                }
                                 _ => return Err(()),
                             }
                         }
@@ -202,6 +202,22 @@ impl ProcessStack {
         });
     }
 
+    /// Discards the entire call stack, including any catch handlers, and releases any memory
+    /// allocated beyond the default stack size
+    ///
+    /// This leaves the stack in the state of a freshly spawned process, i.e. with a single frame
+    /// whose return address is null, so returning from it exits the process.
+    pub fn reset(&mut self) {
+        self.marks.clear();
+        self.marks.shrink_to_fit();
+        self.stack.truncate(MIN_STACK_SIZE);
+        self.stack.shrink_to_fit();
+        self.stack.fill(OpaqueTerm::NONE);
+        self.stack[0] = OpaqueTerm::NIL;
+        self.sp = RESERVED_REGISTERS;
+        self.fp = 0;
+    }
+
     /// Returns true if there is at least one catch handler on the stack
     pub fn catches(&self) -> bool {
         self.marks.iter().any(|mark| mark.is_catch())
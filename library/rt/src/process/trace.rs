@@ -0,0 +1,457 @@
+//! Per-process trace settings, i.e. those set by `erlang:trace/3`
+//!
+//! The trace flags and tracer of a process may be changed by any process at any time, so they are
+//! stored in atomics rather than behind the main process lock. Events are generated by whichever
+//! scheduler observes them, which stamps them using its own clock, see [`TraceTimestamp`].
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use firefly_alloc::fragment::HeapFragment;
+
+use smallvec::SmallVec;
+
+use crate::function::ModuleFunctionArity;
+use crate::gc::Gc;
+use crate::services::registry;
+use crate::term::{
+    atoms, Atom, BigInt, Cons, LayoutBuilder, OpaqueTerm, Pid, Term, TermFragment, Tuple,
+};
+
+use super::{Process, ProcessId};
+
+bitflags::bitflags! {
+    /// The trace flags accepted by `erlang:trace/3`
+    pub struct TraceFlags: u32 {
+        /// Trace messages sent by the process
+        const SEND = 1;
+        /// Trace messages received by the process
+        const RECEIVE = 1 << 1;
+        /// Trace process-related events, i.e. spawn and exit
+        const PROCS = 1 << 2;
+        /// Include an `erlang:now/0`-style timestamp in trace messages
+        const TIMESTAMP = 1 << 3;
+        /// Make `TIMESTAMP` use the CPU time of the scheduler thread rather than the system time
+        const CPU_TIMESTAMP = 1 << 4;
+        /// Include `erlang:monotonic_time/0` in trace messages
+        const MONOTONIC_TIMESTAMP = 1 << 5;
+        /// Include `erlang:monotonic_time/0` and a strictly increasing integer in trace messages
+        const STRICT_MONOTONIC_TIMESTAMP = 1 << 6;
+        /// Trace garbage collections of the process, and it exceeding its `max_heap_size`
+        const GARBAGE_COLLECTION = 1 << 7;
+        /// Trace the process being scheduled in and out
+        const RUNNING = 1 << 8;
+        /// Trace the process being scheduled in and out while it is exiting
+        const EXITING = 1 << 9;
+
+        /// All of the trace event flags, i.e. `all`
+        const EVENTS = Self::SEND.bits
+            | Self::RECEIVE.bits
+            | Self::PROCS.bits
+            | Self::GARBAGE_COLLECTION.bits
+            | Self::RUNNING.bits
+            | Self::EXITING.bits;
+    }
+}
+impl TraceFlags {
+    /// Parses a single atom from the flag list given to `erlang:trace/3`
+    pub fn from_atom(flag: Atom) -> Option<Self> {
+        match flag {
+            a if a == atoms::Send => Some(Self::SEND),
+            a if a == atoms::Receive => Some(Self::RECEIVE),
+            a if a == atoms::Procs => Some(Self::PROCS),
+            a if a == atoms::GarbageCollection => Some(Self::GARBAGE_COLLECTION),
+            a if a == atoms::Running => Some(Self::RUNNING),
+            a if a == atoms::Exiting => Some(Self::EXITING),
+            a if a == atoms::All => Some(Self::EVENTS),
+            a if a == atoms::Timestamp => Some(Self::TIMESTAMP),
+            a if a == atoms::CpuTimestamp => Some(Self::CPU_TIMESTAMP),
+            a if a == atoms::MonotonicTimestamp => Some(Self::MONOTONIC_TIMESTAMP),
+            a if a == atoms::StrictMonotonicTimestamp => Some(Self::STRICT_MONOTONIC_TIMESTAMP),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind of timestamp to attach to trace messages, if any
+    ///
+    /// Like ERTS, `strict_monotonic_timestamp` takes precedence over `monotonic_timestamp`, which
+    /// takes precedence over `timestamp`. `cpu_timestamp` only has an effect with `timestamp`.
+    pub fn timestamp(&self) -> Option<TimestampKind> {
+        if self.contains(Self::STRICT_MONOTONIC_TIMESTAMP) {
+            Some(TimestampKind::StrictMonotonic)
+        } else if self.contains(Self::MONOTONIC_TIMESTAMP) {
+            Some(TimestampKind::Monotonic)
+        } else if self.contains(Self::TIMESTAMP | Self::CPU_TIMESTAMP) {
+            Some(TimestampKind::Cpu)
+        } else if self.contains(Self::TIMESTAMP) {
+            Some(TimestampKind::System)
+        } else {
+            None
+        }
+    }
+}
+
+/// The kinds of timestamp which may be attached to trace messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimestampKind {
+    /// `timestamp`, the system time
+    System,
+    /// `timestamp` with `cpu_timestamp`, the CPU time of the scheduler thread
+    Cpu,
+    /// `monotonic_timestamp`
+    Monotonic,
+    /// `strict_monotonic_timestamp`
+    StrictMonotonic,
+}
+
+/// A timestamp attached to a trace message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceTimestamp {
+    /// A time in microseconds, represented as `{MegaSecs, Secs, MicroSecs}`
+    Micros(u64),
+    /// A monotonic time in nanoseconds
+    Monotonic(i64),
+    /// A monotonic time in nanoseconds, and a unique integer which orders events with the same time
+    StrictMonotonic(i64, i64),
+}
+impl TraceTimestamp {
+    fn layout(&self, layout: &mut LayoutBuilder) {
+        match self {
+            Self::Micros(_) => {
+                layout.build_tuple(3);
+            }
+            Self::Monotonic(time) => {
+                layout.build_for_i64(*time);
+            }
+            Self::StrictMonotonic(time, unique) => {
+                layout
+                    .build_for_i64(*time)
+                    .build_for_i64(*unique)
+                    .build_tuple(2);
+            }
+        }
+    }
+
+    unsafe fn write_to_fragment(&self, fragment: &HeapFragment) -> OpaqueTerm {
+        match *self {
+            Self::Micros(micros) => {
+                let mega = (micros / 1_000_000_000_000) as i64;
+                let secs = ((micros / 1_000_000) % 1_000_000) as i64;
+                let micros = (micros % 1_000_000) as i64;
+                Tuple::from_slice(
+                    &[
+                        OpaqueTerm::try_from(mega).unwrap(),
+                        OpaqueTerm::try_from(secs).unwrap(),
+                        OpaqueTerm::try_from(micros).unwrap(),
+                    ],
+                    fragment,
+                )
+                .unwrap()
+                .into()
+            }
+            Self::Monotonic(time) => int_to_fragment(time, fragment),
+            Self::StrictMonotonic(time, unique) => Tuple::from_slice(
+                &[
+                    int_to_fragment(time, fragment),
+                    int_to_fragment(unique, fragment),
+                ],
+                fragment,
+            )
+            .unwrap()
+            .into(),
+        }
+    }
+}
+
+fn int_to_fragment(i: i64, fragment: &HeapFragment) -> OpaqueTerm {
+    match OpaqueTerm::try_from(i) {
+        Ok(term) => term,
+        Err(_) => Gc::new_in(BigInt::from(i), fragment).unwrap().into(),
+    }
+}
+
+/// The trace flags and tracer of a process
+pub struct TraceState {
+    flags: AtomicU32,
+    /// The raw id of the tracer process, or zero if there is none
+    ///
+    /// Zero is never a valid process id, see `ProcessId`
+    tracer: AtomicU64,
+}
+impl TraceState {
+    pub const fn new() -> Self {
+        Self {
+            flags: AtomicU32::new(0),
+            tracer: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the trace state for a newly spawned process, see `set_new_processes`
+    pub(super) fn for_new_process() -> Self {
+        let state = Self::new();
+        let flags = NEW_PROCESSES.flags();
+        if let Some(tracer) = NEW_PROCESSES.tracer() {
+            state.set(flags, tracer);
+        }
+        state
+    }
+
+    /// Returns the current trace flags
+    #[inline]
+    pub fn flags(&self) -> TraceFlags {
+        TraceFlags::from_bits_truncate(self.flags.load(Ordering::Relaxed))
+    }
+
+    /// Returns the current tracer, if there is one
+    #[inline]
+    pub fn tracer(&self) -> Option<ProcessId> {
+        match self.tracer.load(Ordering::Acquire) {
+            0 => None,
+            raw => Some(unsafe { ProcessId::from_raw(raw) }),
+        }
+    }
+
+    /// Returns the current flags and tracer if `event` is being traced
+    ///
+    /// This is the check made on every traceable event, so it is kept to a single load when
+    /// tracing is disabled.
+    #[inline]
+    pub fn tracer_for(&self, event: TraceFlags) -> Option<(TraceFlags, ProcessId)> {
+        let flags = self.flags();
+        if !flags.intersects(event) {
+            return None;
+        }
+        self.tracer().map(|tracer| (flags, tracer))
+    }
+
+    /// Enables `flags`, sending trace messages to `tracer`
+    ///
+    /// The flags and tracer are not updated as a single atomic operation, so an event which races
+    /// with a change of tracer may be delivered to the previous tracer.
+    pub fn set(&self, flags: TraceFlags, tracer: ProcessId) {
+        self.tracer.store(tracer.raw(), Ordering::Release);
+        self.flags.fetch_or(flags.bits(), Ordering::Release);
+    }
+
+    /// Disables `flags`, removing the tracer if no events remain traced
+    pub fn clear(&self, flags: TraceFlags) {
+        let prev = self.flags.fetch_and(!flags.bits(), Ordering::AcqRel);
+        let remaining = TraceFlags::from_bits_truncate(prev) - flags;
+        if !remaining.intersects(TraceFlags::EVENTS) {
+            self.tracer.store(0, Ordering::Release);
+        }
+    }
+}
+
+/// The trace settings applied to processes spawned from now on, i.e. `erlang:trace(new, ..)`
+static NEW_PROCESSES: TraceState = TraceState::new();
+
+/// Enables `flags` for processes spawned from now on, with `tracer` as their tracer
+pub fn set_new_processes(flags: TraceFlags, tracer: ProcessId) {
+    NEW_PROCESSES.set(flags, tracer);
+}
+
+/// Disables `flags` for processes spawned from now on
+pub fn clear_new_processes(flags: TraceFlags) {
+    NEW_PROCESSES.clear(flags);
+}
+
+/// Constructs the trace message for `event` in `traced`, to be sent to its tracer
+///
+/// The message is `{trace, Pid, Event, Args..}`, or `{trace_ts, Pid, Event, Args.., Timestamp}`
+/// when a `timestamp` is given.
+pub fn make_trace_message(
+    traced: Pid,
+    event: Atom,
+    args: &[Term],
+    timestamp: Option<TraceTimestamp>,
+) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    layout.build_pid();
+    for arg in args {
+        layout += arg.layout();
+    }
+    if let Some(ts) = timestamp.as_ref() {
+        ts.layout(&mut layout);
+    }
+    let arity = 3 + args.len() + timestamp.is_some() as usize;
+    layout.build_tuple(arity);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let tag = if timestamp.is_some() {
+        atoms::TraceTs
+    } else {
+        atoms::Trace
+    };
+    let mut elements = SmallVec::<[OpaqueTerm; 6]>::with_capacity(arity);
+    elements.push(tag.into());
+    elements.push(Gc::new_in(traced, fragment).unwrap().into());
+    elements.push(event.into());
+    for arg in args {
+        elements.push(unsafe { arg.unsafe_clone_to_heap(fragment) }.into());
+    }
+    if let Some(ts) = timestamp.as_ref() {
+        elements.push(unsafe { ts.write_to_fragment(fragment) });
+    }
+    let message = Tuple::from_slice(&elements, fragment).unwrap();
+
+    TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Sends the trace message for `event` in `traced` to its tracer, if it is being traced
+///
+/// This is for events raised by the runtime on behalf of a process, e.g. garbage collection, rather
+/// than by the scheduler loop. Such events only happen while `traced` is executing, so the
+/// timestamp is taken from the scheduler currently responsible for it.
+pub fn trace_event(traced: &Process, event: TraceFlags, tag: Atom, args: &[Term]) {
+    let Some((flags, tracer)) = traced.trace().tracer_for(event) else { return; };
+    let Some(tracer) = registry::get_by_process_id(tracer) else { return; };
+    let timestamp = flags
+        .timestamp()
+        .map(|kind| crate::scheduler::get(traced.scheduler_id()).trace_timestamp(kind));
+    let message = make_trace_message(traced.pid(), tag, args, timestamp);
+    tracer.send_fragment(traced.addr(), message).ok();
+}
+
+/// Constructs the `{Module, Function, Arity}` term used by the `in` and `out` events
+pub fn make_function_fragment(mfa: ModuleFunctionArity) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(3);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let tuple = Tuple::from_slice(
+        &[
+            mfa.module.into(),
+            mfa.function.into(),
+            Term::Int(mfa.arity as i64).into(),
+        ],
+        fragment,
+    )
+    .unwrap();
+
+    TermFragment {
+        term: tuple.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Constructs the `[{Key, Value}]` term used by the garbage collection events
+pub fn make_info_fragment(info: &[(Atom, i64)]) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    for (_, value) in info {
+        layout.build_for_i64(*value).build_tuple(2);
+    }
+    layout.build_list(info.len());
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let items = info
+        .iter()
+        .map(|(key, value)| {
+            Tuple::from_slice(&[(*key).into(), int_to_fragment(*value, fragment)], fragment)
+                .unwrap()
+                .into()
+        })
+        .collect::<SmallVec<[OpaqueTerm; 8]>>();
+    let list = Cons::from_slice(&items, fragment)
+        .unwrap()
+        .map(Term::Cons)
+        .unwrap_or(Term::Nil);
+
+    TermFragment {
+        term: list.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Constructs the `{Module, Function, Args}` term used by the `spawn` and `spawned` events
+pub fn make_mfa_fragment(mfa: ModuleFunctionArity, args: &[OpaqueTerm]) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    for arg in args.iter().copied() {
+        if arg.is_box() {
+            let arg: Term = arg.into();
+            layout += arg.layout();
+        }
+    }
+    layout.build_list(args.len()).build_tuple(3);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let args = args
+        .iter()
+        .copied()
+        .map(|arg| {
+            if arg.is_box() {
+                let arg: Term = arg.into();
+                unsafe { arg.unsafe_clone_to_heap(fragment) }.into()
+            } else {
+                arg
+            }
+        })
+        .collect::<SmallVec<[OpaqueTerm; 4]>>();
+    let args = Cons::from_slice(&args, fragment)
+        .unwrap()
+        .map(Term::Cons)
+        .unwrap_or(Term::Nil);
+    let tuple = Tuple::from_slice(
+        &[mfa.module.into(), mfa.function.into(), args.into()],
+        fragment,
+    )
+    .unwrap();
+
+    TermFragment {
+        term: tuple.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timestamp_precedence_test() {
+        let flags = TraceFlags::TIMESTAMP | TraceFlags::CPU_TIMESTAMP;
+        assert_eq!(flags.timestamp(), Some(TimestampKind::Cpu));
+        let flags = flags | TraceFlags::MONOTONIC_TIMESTAMP;
+        assert_eq!(flags.timestamp(), Some(TimestampKind::Monotonic));
+        let flags = flags | TraceFlags::STRICT_MONOTONIC_TIMESTAMP;
+        assert_eq!(flags.timestamp(), Some(TimestampKind::StrictMonotonic));
+        assert_eq!(TraceFlags::SEND.timestamp(), None);
+        assert_eq!(TraceFlags::CPU_TIMESTAMP.timestamp(), None);
+    }
+
+    #[test]
+    fn clearing_all_events_removes_tracer_test() {
+        let tracer = unsafe { ProcessId::new_unchecked(1, 0) };
+        let state = TraceState::new();
+        state.set(TraceFlags::SEND | TraceFlags::RECEIVE | TraceFlags::TIMESTAMP, tracer);
+        assert_eq!(state.tracer_for(TraceFlags::PROCS), None);
+        assert!(state.tracer_for(TraceFlags::SEND).is_some());
+
+        state.clear(TraceFlags::SEND);
+        assert_eq!(state.tracer(), Some(tracer));
+        state.clear(TraceFlags::RECEIVE);
+        assert_eq!(state.tracer(), None);
+        assert_eq!(state.flags(), TraceFlags::TIMESTAMP);
+    }
+
+    #[test]
+    fn scheduling_events_keep_tracer_test() {
+        let tracer = unsafe { ProcessId::new_unchecked(1, 0) };
+        let state = TraceState::new();
+        state.set(TraceFlags::RUNNING | TraceFlags::GARBAGE_COLLECTION, tracer);
+        assert_eq!(state.tracer_for(TraceFlags::EXITING), None);
+        assert!(state.tracer_for(TraceFlags::RUNNING | TraceFlags::EXITING).is_some());
+
+        state.clear(TraceFlags::RUNNING);
+        assert_eq!(state.tracer(), Some(tracer));
+        state.clear(TraceFlags::GARBAGE_COLLECTION);
+        assert_eq!(state.tracer(), None);
+        assert_eq!(TraceFlags::from_atom(atoms::All), Some(TraceFlags::EVENTS));
+        assert!(TraceFlags::EVENTS.contains(TraceFlags::EXITING));
+    }
+}
@@ -2,14 +2,19 @@ use alloc::sync::Arc;
 use core::mem::MaybeUninit;
 
 use firefly_number::Int;
+use firefly_system::sync::lcnt::LockClass;
 use firefly_system::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use crate::function::ModuleFunctionArity;
 use crate::gc::Gc;
+use crate::process::trace::{TimestampKind, TraceTimestamp};
 use crate::process::{Process, ProcessLock, SpawnOpts};
 use crate::services::timers::{Timer, TimerError};
 use crate::term::{OpaqueTerm, Reference, ReferenceId};
 
+/// The maximum number of schedulers which can be online at the same time
+pub const MAX_SCHEDULERS: usize = 64;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SchedulerId(u16);
 impl SchedulerId {
@@ -122,6 +127,11 @@ pub trait Scheduler: Send + Sync {
 
     /// Reschedules `process` using this scheduler's run queue
     fn reschedule(&self, process: Arc<Process>);
+
+    /// Returns a timestamp of the given kind for a trace message generated on this scheduler
+    ///
+    /// This must only be called from the thread executing this scheduler.
+    fn trace_timestamp(&self, kind: TimestampKind) -> TraceTimestamp;
 }
 
 /// Returns a strong reference to the scheduler corresponding to `id`
@@ -176,23 +186,34 @@ pub fn online() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.online())
 }
 
-/// Returns the number of schedulers online
+/// Returns the number of dirty CPU schedulers online
 pub fn dirty_cpu() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.dirty_cpu())
 }
 
-/// Returns the number of schedulers online
+/// Returns the number of dirty I/O schedulers online
 pub fn dirty_io() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.dirty_io())
 }
 
+/// Records the number of dirty CPU and I/O schedulers which have been started
+///
+/// Dirty schedulers are not registered like normal schedulers, as they only run native jobs, not
+/// processes, so the runtime which starts them reports them here instead.
+pub fn set_dirty(cpu: u32, io: u32) {
+    with_schedulers(|mut schedulers| {
+        schedulers.dirty_cpu = cpu;
+        schedulers.dirty_io = io;
+    })
+}
+
 #[inline]
 fn with_schedulers_readonly<F, T>(callback: F) -> T
 where
     F: FnOnce(RwLockReadGuard<'static, SchedulerSet>) -> T,
 {
     let schedulers = SCHEDULERS.get_or_init(|| RwLock::new(SchedulerSet::new()));
-    callback(schedulers.read())
+    callback(SCHEDULERS_LOCK.read(schedulers))
 }
 
 #[inline]
@@ -201,10 +222,11 @@ where
     F: FnOnce(RwLockWriteGuard<'static, SchedulerSet>) -> T,
 {
     let schedulers = SCHEDULERS.get_or_init(|| RwLock::new(SchedulerSet::new()));
-    callback(schedulers.write())
+    callback(SCHEDULERS_LOCK.write(schedulers))
 }
 
 static SCHEDULERS: OnceLock<RwLock<SchedulerSet>> = OnceLock::new();
+static SCHEDULERS_LOCK: LockClass = LockClass::new("schedulers");
 
 struct SchedulerSet {
     /// The set of schedulers, up to 64 can be run at the same time in this configuration
@@ -216,10 +238,12 @@ struct SchedulerSet {
     online: u64,
     /// A bitset defining which scheduler slots are available for assignment
     assigned: u64,
+    /// The number of dirty CPU schedulers online
+    dirty_cpu: u32,
+    /// The number of dirty I/O schedulers online
+    dirty_io: u32,
 }
 impl SchedulerSet {
-    const MAX_SCHEDULERS: usize = 64;
-
     pub fn new() -> Self {
         let mut slots = MaybeUninit::<Option<Arc<dyn Scheduler>>>::uninit_array::<64>();
         for slot in &mut slots {
@@ -230,6 +254,8 @@ impl SchedulerSet {
             schedulers: unsafe { MaybeUninit::array_assume_init(slots) },
             online: 0,
             assigned: 0,
+            dirty_cpu: 0,
+            dirty_io: 0,
         }
     }
 
@@ -246,7 +272,7 @@ impl SchedulerSet {
         // Mark the slot as assigned
         self.assigned |= 1 << (next_free - 1);
 
-        SchedulerId((Self::MAX_SCHEDULERS - next_free as usize) as u16)
+        SchedulerId((MAX_SCHEDULERS - next_free as usize) as u16)
     }
 
     /// Inserts `scheduler` in the set, marking it as online
@@ -256,7 +282,7 @@ impl SchedulerSet {
     pub fn insert(&mut self, scheduler: Arc<dyn Scheduler>) {
         let id = scheduler.id().as_u16() as usize;
         assert!(
-            id < Self::MAX_SCHEDULERS,
+            id < MAX_SCHEDULERS,
             "invalid scheduler id, make sure you request a scheduler id from scheduler set"
         );
 
@@ -287,7 +313,7 @@ impl SchedulerSet {
     pub unsafe fn release(&mut self, id: SchedulerId) {
         let id = id.as_u16() as usize;
         assert!(
-            id < Self::MAX_SCHEDULERS,
+            id < MAX_SCHEDULERS,
             "invalid scheduler id, make sure you request a scheduler id from scheduler set"
         );
         let bit = 1 << (id as u64);
@@ -311,7 +337,7 @@ impl SchedulerSet {
     pub unsafe fn remove(&mut self, id: SchedulerId) {
         let id = id.as_u16() as usize;
         assert!(
-            id < Self::MAX_SCHEDULERS,
+            id < MAX_SCHEDULERS,
             "invalid scheduler id, make sure you request a scheduler id from scheduler set"
         );
         let bit = 1 << (id as u64);
@@ -338,7 +364,7 @@ impl SchedulerSet {
     pub fn fetch(&self, id: SchedulerId) -> Arc<dyn Scheduler> {
         let id = id.as_u16() as usize;
         assert!(
-            id < Self::MAX_SCHEDULERS,
+            id < MAX_SCHEDULERS,
             "invalid scheduler id, make sure you request a scheduler id from scheduler set"
         );
 
@@ -368,11 +394,11 @@ impl SchedulerSet {
 
     /// Returns the number of dirty CPU schedulers online
     pub fn dirty_cpu(&self) -> u32 {
-        0
+        self.dirty_cpu
     }
 
     /// Returns the number of dirty I/O schedulers online
     pub fn dirty_io(&self) -> u32 {
-        0
+        self.dirty_io
     }
 }
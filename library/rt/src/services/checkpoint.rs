@@ -0,0 +1,164 @@
+//! This module provides a way for embedders to checkpoint the runtime system, e.g. with CRIU, so
+//! that a node can later be restored from the checkpoint rather than started from scratch.
+//!
+//! A checkpoint is taken in three steps, via [`checkpoint`]:
+//!
+//! * Each registered [`CheckpointHook`] is prepared, while processes are still running, e.g. to
+//!   flush buffered output which only a process can send, or to stop threads which report events
+//!   to processes.
+//! * The world is paused (see [`safepoint`](super::safepoint)), and each hook releases resources
+//!   which cannot be checkpointed, e.g. file descriptors which a checkpointing tool can't restore.
+//! * The given closure is called to take the checkpoint, after which each hook, in reverse order,
+//!   restores what it released and re-arms what it stopped, before the world is resumed.
+//!
+//! The last step happens both in the original OS process, once the checkpoint has been taken, and
+//! in any process restored from it, as the closure returns in both, so hooks must not assume that
+//! anything they released is still there to be reacquired.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use firefly_system::sync::{const_mutex, Mutex};
+
+use log::debug;
+
+use super::safepoint;
+
+/// A participant in checkpoints, see the module documentation for when each method is called
+pub trait CheckpointHook: Send + Sync {
+    /// The name of this hook, for logging
+    fn name(&self) -> &'static str;
+
+    /// Called before the world is paused, while processes are still running
+    fn prepare(&self) {}
+
+    /// Called while the world is paused, before the checkpoint is taken
+    fn release(&self) {}
+
+    /// Called while the world is paused, after the checkpoint has been taken
+    fn restore(&self) {}
+}
+
+/// A handle for a registered hook, which can be used to unregister it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HookId(usize);
+
+static HOOKS: Mutex<Vec<(HookId, Arc<dyn CheckpointHook>)>> = const_mutex(Vec::new());
+static NEXT_HOOK_ID: AtomicUsize = AtomicUsize::new(0);
+/// Held for the duration of a checkpoint, so that only one happens at a time
+static CHECKPOINTING: Mutex<()> = const_mutex(());
+
+/// Registers `hook` to take part in all future checkpoints
+///
+/// Hooks are prepared and released in the order they are registered, and restored in reverse.
+pub fn register(hook: Arc<dyn CheckpointHook>) -> HookId {
+    let id = HookId(NEXT_HOOK_ID.fetch_add(1, Ordering::Relaxed));
+    HOOKS.lock().push((id, hook));
+    id
+}
+
+/// Unregisters a hook previously registered with [`register`]
+///
+/// Returns false if the hook was not registered.
+pub fn unregister(id: HookId) -> bool {
+    let mut hooks = HOOKS.lock();
+    let len = hooks.len();
+    hooks.retain(|(hid, _)| *hid != id);
+    hooks.len() != len
+}
+
+/// Quiesces the runtime system, calls `f` to take a checkpoint of the OS process, then restores
+/// the runtime system and resumes it
+///
+/// `f` may, for example, ask CRIU to dump the current process and leave it running, in which case
+/// it returns once in this process, and once more in each process restored from the dump.
+pub fn checkpoint<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    struct Restore<'a>(&'a [Arc<dyn CheckpointHook>]);
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            for hook in self.0.iter().rev() {
+                debug!(target: "checkpoint", "restoring {}", hook.name());
+                hook.restore();
+            }
+        }
+    }
+
+    let _checkpointing = CHECKPOINTING.lock();
+    // Hooks are called without holding the lock, so that they may register/unregister hooks
+    let hooks = HOOKS
+        .lock()
+        .iter()
+        .map(|(_, hook)| hook.clone())
+        .collect::<Vec<_>>();
+
+    for hook in hooks.iter() {
+        debug!(target: "checkpoint", "preparing {}", hook.name());
+        hook.prepare();
+    }
+
+    safepoint::pause(|| {
+        for hook in hooks.iter() {
+            debug!(target: "checkpoint", "releasing {}", hook.name());
+            hook.release();
+        }
+        // Restore even if `f` panics, as the world is about to be resumed either way
+        let _restore = Restore(hooks.as_slice());
+        f()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static CALLS: Mutex<Vec<(&'static str, bool)>> = const_mutex(Vec::new());
+
+    struct Hook(&'static str);
+    impl CheckpointHook for Hook {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn prepare(&self) {
+            CALLS.lock().push((self.0, safepoint::is_paused()));
+        }
+
+        fn release(&self) {
+            CALLS.lock().push((self.0, safepoint::is_paused()));
+        }
+
+        fn restore(&self) {
+            CALLS.lock().push((self.0, safepoint::is_paused()));
+        }
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let a = register(Arc::new(Hook("a")));
+        let b = register(Arc::new(Hook("b")));
+
+        // Hooks are prepared while running, and released and restored in reverse while paused
+        assert_eq!(checkpoint(|| CALLS.lock().len()), 4);
+        assert_eq!(
+            CALLS.lock().as_slice(),
+            &[
+                ("a", false),
+                ("b", false),
+                ("a", true),
+                ("b", true),
+                ("b", true),
+                ("a", true)
+            ]
+        );
+
+        assert!(unregister(a));
+        assert!(!unregister(a));
+        CALLS.lock().clear();
+        checkpoint(|| ());
+        assert_eq!(CALLS.lock().as_slice(), &[("b", false), ("b", true), ("b", true)]);
+        assert!(unregister(b));
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::time::Duration;
+
+use super::DistFlags;
+
+/// Controls whether connections to other nodes are established implicitly
+///
+/// This corresponds to the `dist_auto_connect` kernel parameter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutoConnect {
+    /// Connections are only ever established explicitly, e.g. via `net_kernel:connect_node/1`
+    Never,
+    /// Connections are established on first use, e.g. when sending a message to a remote pid
+    OnDemand,
+}
+impl Default for AutoConnect {
+    #[inline]
+    fn default() -> Self {
+        Self::OnDemand
+    }
+}
+
+/// Controls how connection attempts are retried when a node cannot be reached
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts made to connect to a node, including the first
+    pub max_attempts: u32,
+    /// The delay before the first retry, which doubles on each subsequent retry
+    pub initial_backoff: Duration,
+    /// The upper bound on the delay between retries
+    pub max_backoff: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+impl RetryPolicy {
+    /// Returns the delay to wait before making connection attempt number `attempt`, where the
+    /// first attempt is `0`, or `None` if no more attempts should be made.
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if attempt == 0 {
+            return Some(Duration::ZERO);
+        }
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff);
+        Some(delay.min(self.max_backoff))
+    }
+}
+
+/// The error produced when the distribution configuration contains an invalid value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub key: String,
+    pub value: String,
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid value for kernel parameter {}: '{}'",
+            &self.key, &self.value
+        )
+    }
+}
+
+/// Configuration for the distribution subsystem
+///
+/// This can be constructed directly when embedding the runtime, or derived from the command-line
+/// arguments the system was started with, using the same flags and kernel parameters as ERTS:
+///
+/// * `-hidden`, connect to other nodes as a hidden node
+/// * `-kernel dist_auto_connect never | on_demand`
+/// * `-kernel net_setuptime Seconds`, the maximum time allowed to set up a connection
+/// * `-kernel dist_connect_attempts N`
+/// * `-kernel dist_connect_backoff Milliseconds`, the delay before the first retry
+/// * `-kernel dist_connect_max_backoff Milliseconds`
+/// * `-kernel dist_reject_flags Names`, a comma-separated list of capabilities not to advertise
+/// * `-kernel dist_require_flags Names`, a comma-separated list of capabilities other nodes must
+///   support in order to connect to us, see `DistFlags::from_name` for the names of each flag
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DistributionConfig {
+    pub auto_connect: AutoConnect,
+    /// When true, connections to other nodes are hidden, i.e. they are not published to
+    /// other nodes, and are not included in the results of `erlang:nodes/0`
+    pub hidden: bool,
+    /// The maximum time allowed to establish a connection to another node
+    pub setup_time: Duration,
+    pub retry: RetryPolicy,
+    /// The capabilities advertised to other nodes during the handshake
+    ///
+    /// This may only differ from `DistFlags::DEFAULT` by the `DistFlags::REJECTABLE` flags.
+    pub flags: DistFlags,
+    /// The capabilities other nodes must advertise in order to connect, in addition to the
+    /// mandatory ones
+    pub required_flags: DistFlags,
+}
+impl Default for DistributionConfig {
+    fn default() -> Self {
+        Self {
+            auto_connect: AutoConnect::default(),
+            hidden: false,
+            setup_time: Duration::from_secs(7),
+            retry: RetryPolicy::default(),
+            flags: DistFlags::DEFAULT,
+            required_flags: DistFlags::MANDATORY,
+        }
+    }
+}
+impl DistributionConfig {
+    /// Returns the flags to advertise to other nodes, which are published unless we are hidden
+    pub fn advertised_flags(&self) -> DistFlags {
+        if self.hidden {
+            self.flags
+        } else {
+            self.flags | DistFlags::PUBLISHED
+        }
+    }
+
+    /// Derives the configuration from the given command-line arguments
+    ///
+    /// Arguments which are not relevant to distribution are ignored.
+    pub fn from_args<I, S>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "-hidden" => config.hidden = true,
+                "-kernel" => {
+                    let Some(key) = args.next() else { break; };
+                    let Some(value) = args.next() else { break; };
+                    config.set_parameter(key.as_ref(), value.as_ref())?;
+                }
+                _ => continue,
+            }
+        }
+        Ok(config)
+    }
+
+    /// Sets the kernel parameter `key` to `value`
+    ///
+    /// Parameters which are not relevant to distribution are ignored.
+    pub fn set_parameter(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        match key {
+            "dist_auto_connect" => {
+                self.auto_connect = match value {
+                    "never" => AutoConnect::Never,
+                    "on_demand" => AutoConnect::OnDemand,
+                    _ => return Err(invalid()),
+                };
+            }
+            "net_setuptime" => {
+                let secs = value.parse::<u64>().map_err(|_| invalid())?;
+                self.setup_time = Duration::from_secs(secs);
+            }
+            "dist_connect_attempts" => {
+                self.retry.max_attempts = value.parse::<u32>().map_err(|_| invalid())?;
+            }
+            "dist_connect_backoff" => {
+                let millis = value.parse::<u64>().map_err(|_| invalid())?;
+                self.retry.initial_backoff = Duration::from_millis(millis);
+            }
+            "dist_connect_max_backoff" => {
+                let millis = value.parse::<u64>().map_err(|_| invalid())?;
+                self.retry.max_backoff = Duration::from_millis(millis);
+            }
+            // Flags we do not advertise cannot be required of other nodes
+            "dist_reject_flags" => {
+                let flags = DistFlags::parse_list(value).ok_or_else(invalid)?;
+                if !DistFlags::REJECTABLE.contains(flags) {
+                    return Err(invalid());
+                }
+                self.flags = DistFlags::DEFAULT - flags;
+                self.required_flags &= self.flags;
+            }
+            "dist_require_flags" => {
+                let flags = DistFlags::parse_list(value).ok_or_else(invalid)?;
+                if !self.flags.contains(flags) {
+                    return Err(invalid());
+                }
+                self.required_flags = DistFlags::MANDATORY | flags;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distribution_config_from_args_test() {
+        let args = [
+            "firefly",
+            "-hidden",
+            "-kernel",
+            "dist_auto_connect",
+            "never",
+            "-kernel",
+            "logger_level",
+            "debug",
+            "-kernel",
+            "dist_connect_backoff",
+            "250",
+        ];
+        let config = DistributionConfig::from_args(args).unwrap();
+        assert!(config.hidden);
+        assert_eq!(config.auto_connect, AutoConnect::Never);
+        assert_eq!(config.retry.initial_backoff, Duration::from_millis(250));
+
+        let args = ["-kernel", "dist_auto_connect", "sometimes"];
+        assert!(DistributionConfig::from_args(args).is_err());
+    }
+
+    #[test]
+    fn distribution_config_flags_test() {
+        let config = DistributionConfig::default();
+        assert!(config.advertised_flags().contains(DistFlags::PUBLISHED));
+
+        let args = [
+            "-hidden",
+            "-kernel",
+            "dist_reject_flags",
+            "fragments,alias",
+            "-kernel",
+            "dist_require_flags",
+            "spawn",
+        ];
+        let config = DistributionConfig::from_args(args).unwrap();
+        let advertised = config.advertised_flags();
+        assert!(!advertised.contains(DistFlags::PUBLISHED));
+        assert!(!advertised.intersects(DistFlags::FRAGMENTS | DistFlags::ALIAS));
+        assert!(advertised.contains(DistFlags::MANDATORY | DistFlags::SPAWN));
+        assert_eq!(
+            config.required_flags,
+            DistFlags::MANDATORY | DistFlags::SPAWN
+        );
+
+        // Mandatory flags can't be rejected, and rejected flags can't be required
+        let args = ["-kernel", "dist_reject_flags", "utf8_atoms"];
+        assert!(DistributionConfig::from_args(args).is_err());
+        let args = [
+            "-kernel",
+            "dist_reject_flags",
+            "spawn",
+            "-kernel",
+            "dist_require_flags",
+            "spawn",
+        ];
+        assert!(DistributionConfig::from_args(args).is_err());
+    }
+
+    #[test]
+    fn retry_policy_backoff_test() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+        };
+        assert_eq!(policy.backoff(0), Some(Duration::ZERO));
+        assert_eq!(policy.backoff(1), Some(Duration::from_millis(100)));
+        assert_eq!(policy.backoff(2), Some(Duration::from_millis(200)));
+        assert_eq!(policy.backoff(3), Some(Duration::from_millis(300)));
+        assert_eq!(policy.backoff(4), Some(Duration::from_millis(300)));
+        assert_eq!(policy.backoff(5), None);
+    }
+}
@@ -1,7 +1,11 @@
 use alloc::boxed::Box;
+use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use firefly_system::sync::Atomic;
+use firefly_system::sync::lcnt::LockClass;
+use firefly_system::sync::{Atomic, Mutex};
 use firefly_system::time::MonotonicTime;
 
 use intrusive_collections::intrusive_adapter;
@@ -10,8 +14,10 @@ use intrusive_collections::{LinkedList, LinkedListAtomicLink};
 use crate::process::link::LinkTree;
 use crate::process::monitor::MonitorList;
 use crate::process::ProcessList;
-use crate::services::registry::WeakAddress;
-use crate::term::{atoms, Atom, OpaqueTerm, Port};
+use crate::services::registry::{Registrant, WeakAddress};
+use crate::term::{atoms, Atom, OpaqueTerm, Pid, Port, Reference, ReferenceId, Term};
+
+use super::DistFlags;
 
 /// The connection state of a given node
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -26,6 +32,25 @@ pub enum NodeStatus {
     /// The node is connected, but hidden
     Hidden,
 }
+impl firefly_system::sync::Atom for NodeStatus {
+    type Repr = u8;
+
+    #[inline]
+    fn pack(self) -> Self::Repr {
+        self as u8
+    }
+
+    #[inline]
+    fn unpack(raw: Self::Repr) -> Self {
+        match raw {
+            0 => Self::Disconnected,
+            1 => Self::Pending,
+            2 => Self::Visible,
+            3 => Self::Hidden,
+            _ => unreachable!(),
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ConnectionError {
@@ -35,6 +60,8 @@ pub enum ConnectionError {
     Unauthenticated,
     /// Connect failed because the remote node is not allowing connections from us
     Unauthorized,
+    /// Connect failed because the remote node does not support the capabilities we require
+    MissingFlags(DistFlags),
 }
 
 intrusive_adapter!(pub NodeConnectionAdapter = Arc<NodeConnection>: NodeConnection { link: LinkedListAtomicLink });
@@ -42,6 +69,12 @@ intrusive_adapter!(pub NodeConnectionAdapter = Arc<NodeConnection>: NodeConnecti
 #[allow(unused)]
 pub type NodeConnectionList = LinkedList<NodeConnectionAdapter>;
 
+/// Shared by the controller and input handler of all connections, see
+/// `firefly_system::sync::lcnt`
+static DIST_ENTRY_LOCK: LockClass = LockClass::new("dist_entry");
+/// Shared by the output queues of all connections
+static DIST_OUTPUT_LOCK: LockClass = LockClass::new("dist_output");
+
 /// This structure represents the connection backing a [`Node`],
 /// and corresponds to `dist_entry_` in `erl_node_tables.h`.
 ///
@@ -60,16 +93,22 @@ pub struct NodeConnection {
     id: u32,
     pub name: Atom,
     creation: u32,
-    input_handler: Atomic<OpaqueTerm>,
+    /// The process to which incoming data is attributed, defaults to the controller
+    input_handler: Mutex<Option<Pid>>,
     /// The process or port which owns this connection
     ///
     /// If `None`, this connection is unused.
-    connection_handler_id: Option<WeakAddress>,
-    status: NodeStatus,
+    connection_handler_id: Mutex<Option<WeakAddress>>,
+    /// Encoded data waiting to be fetched by the controller via `dist_ctrl_get_data`
+    output: Mutex<VecDeque<Vec<u8>>>,
+    /// Set when the controller has requested a `dist_data` message when output is available
+    notify_on_output: AtomicBool,
+    status: Atomic<NodeStatus>,
     pending_nodedown: bool,
     // This is a reference to a process
     suspended_nodeup: OpaqueTerm,
-    flags: u64,
+    /// The capabilities negotiated for this connection, see `DistFlags::negotiate`
+    flags: Atomic<DistFlags>,
     opts: u32,
     links: LinkTree,
     monitors: MonitorList,
@@ -93,12 +132,14 @@ impl NodeConnection {
             id: connection_id as u32,
             name: atoms::NoNodeAtNoHost,
             creation: 0,
-            input_handler: Atomic::new(OpaqueTerm::NIL),
-            connection_handler_id: None,
-            status: NodeStatus::Disconnected,
+            input_handler: Mutex::new(None),
+            connection_handler_id: Mutex::new(None),
+            output: Mutex::new(VecDeque::new()),
+            notify_on_output: AtomicBool::new(false),
+            status: Atomic::new(NodeStatus::Disconnected),
             pending_nodedown: false,
             suspended_nodeup: OpaqueTerm::NONE,
-            flags: 0,
+            flags: Atomic::new(DistFlags::empty()),
             opts: 0,
             links: LinkTree::default(),
             monitors: MonitorList::default(),
@@ -106,4 +147,128 @@ impl NodeConnection {
             send: None,
         })
     }
+    /// Creates a distribution handle for this connection, for use by the `dist_ctrl_*` BIFs
+    ///
+    /// The handle is a magic reference which keeps the connection alive as long as it is live.
+    pub fn handle(self: &Arc<Self>, mut id: ReferenceId) -> Reference {
+        id.set_magic();
+        Reference::new_magic(id, self.clone())
+    }
+
+    /// Returns the connection associated with `handle`, if it is a distribution handle
+    pub fn from_handle(handle: &Reference) -> Option<Arc<Self>> {
+        handle.magic()?.downcast::<Self>().ok()
+    }
+
+    /// Returns the connection state of this connection
+    pub fn status(&self) -> NodeStatus {
+        self.status.load(Ordering::Acquire)
+    }
+
+    /// Returns true if this connection is up, but hidden, i.e. either side of it is a hidden node
+    pub fn is_hidden(&self) -> bool {
+        self.status() == NodeStatus::Hidden
+    }
+
+    /// Returns the capabilities negotiated for this connection
+    ///
+    /// This is empty until the connection is up, see [`NodeConnection::set_up`].
+    pub fn flags(&self) -> DistFlags {
+        self.flags.load(Ordering::Acquire)
+    }
+
+    /// Marks this connection as up, once the handshake has negotiated `flags` with the other node
+    ///
+    /// The connection is visible if `flags` are published, otherwise it is hidden, and the other
+    /// node is not included in the results of `erlang:nodes/0`.
+    pub fn set_up(&self, flags: DistFlags) {
+        let status = if flags.contains(DistFlags::PUBLISHED) {
+            NodeStatus::Visible
+        } else {
+            NodeStatus::Hidden
+        };
+        self.flags.store(flags, Ordering::Release);
+        self.status.store(status, Ordering::Release);
+    }
+
+    /// Returns the process or port which controls this connection, if one has been assigned
+    pub fn controller(&self) -> Option<WeakAddress> {
+        DIST_ENTRY_LOCK.lock(&self.connection_handler_id).clone()
+    }
+
+    /// Assigns the process or port which will act as the controller for this connection
+    pub fn set_controller(&self, controller: WeakAddress) {
+        *DIST_ENTRY_LOCK.lock(&self.connection_handler_id) = Some(controller);
+    }
+
+    /// Returns true if `address` is the controller of this connection
+    pub fn is_controller(&self, address: &WeakAddress) -> bool {
+        DIST_ENTRY_LOCK.lock(&self.connection_handler_id).as_ref() == Some(address)
+    }
+
+    /// Returns the process which is permitted to deliver incoming data on this connection
+    ///
+    /// Unless explicitly set via `dist_ctrl_input_handler/2`, this is the controller.
+    pub fn input_handler(&self) -> Option<Pid> {
+        if let Some(pid) = DIST_ENTRY_LOCK.lock(&self.input_handler).clone() {
+            return Some(pid);
+        }
+        match self.controller() {
+            Some(WeakAddress::Process(pid)) => Some(pid),
+            _ => None,
+        }
+    }
+
+    /// Sets the process which is permitted to deliver incoming data on this connection
+    pub fn set_input_handler(&self, pid: Pid) {
+        *DIST_ENTRY_LOCK.lock(&self.input_handler) = Some(pid);
+    }
+
+    /// Enqueues encoded `data` to be sent by the controller of this connection
+    ///
+    /// If the controller is waiting on a data notification, it will be sent one.
+    pub fn enqueue(&self, data: Vec<u8>) {
+        DIST_OUTPUT_LOCK.lock(&self.output).push_back(data);
+        if self.notify_on_output.swap(false, Ordering::AcqRel) {
+            self.notify_controller();
+        }
+    }
+
+    /// Dequeues the next chunk of encoded data to be sent by the controller, if available
+    pub fn dequeue(&self) -> Option<Vec<u8>> {
+        DIST_OUTPUT_LOCK.lock(&self.output).pop_front()
+    }
+
+    /// Returns the total size in bytes of the data waiting to be fetched by the controller
+    pub fn output_size(&self) -> usize {
+        DIST_OUTPUT_LOCK
+            .lock(&self.output)
+            .iter()
+            .map(|data| data.len())
+            .sum()
+    }
+
+    /// Requests that the controller be sent a `dist_data` message when data is available
+    ///
+    /// If data is already available, the notification is sent immediately. Only a single
+    /// notification is sent per request.
+    pub fn request_data_notification(&self) {
+        let output = DIST_OUTPUT_LOCK.lock(&self.output);
+        if output.is_empty() {
+            self.notify_on_output.store(true, Ordering::Release);
+        } else {
+            drop(output);
+            self.notify_controller();
+        }
+    }
+
+    fn notify_controller(&self) {
+        let controller = self.controller().and_then(|address| address.try_resolve());
+        // Port controllers are notified by the port itself, so only processes are handled here
+        if let Some(Registrant::Process(process)) = controller {
+            process
+                .send(WeakAddress::System, Term::Atom(atoms::DistData))
+                .ok();
+        }
+    }
 }
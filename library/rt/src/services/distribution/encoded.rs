@@ -0,0 +1,85 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::etf::{self, EncodeError, ATOM_CACHE_REF};
+use crate::term::{Atom, Term};
+
+/// A message which has been encoded in the external term format once, so that it can be sent to
+/// processes on many nodes without being encoded again for each of them
+///
+/// This is intended for processes which fan out the same message to many remote subscribers, e.g.
+/// pub/sub brokers bridging clusters. Share it via `Arc` and send it with [`send_encoded`].
+///
+/// The encoding records where each atom was encoded, so that connections using an atom cache can
+/// replace them with references to their cache by copying the bytes in between, see
+/// [`EncodedMessage::with_atom_cache`], while all other connections send [`EncodedMessage::bytes`]
+/// as-is.
+///
+/// [`send_encoded`]: super::send_encoded
+pub struct EncodedMessage {
+    bytes: Vec<u8>,
+    atoms: Vec<(Range<usize>, Atom)>,
+}
+impl EncodedMessage {
+    /// Encodes `message`
+    ///
+    /// Local pids, ports and references in `message` are encoded with the current name of this
+    /// node, so a message encoded before distribution is started should not be sent afterwards.
+    pub fn new(message: Term) -> Result<Self, EncodeError> {
+        let (bytes, atoms) = etf::encode_with_atoms(message)?;
+        Ok(Self { bytes, atoms })
+    }
+
+    /// Returns the encoded message, including the version byte
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the atoms in the message, in the order they were encoded, including duplicates
+    ///
+    /// Connections using an atom cache use this to build the distribution header for the message.
+    pub fn atoms(&self) -> impl Iterator<Item = Atom> + '_ {
+        self.atoms.iter().map(|(_, atom)| *atom)
+    }
+
+    /// Returns the encoded message for a connection using an atom cache, in which each atom for
+    /// which `cache_index` returns an index is replaced by a reference to that entry
+    ///
+    /// The index is of the atom in the distribution header sent with the message, and must be
+    /// consistent with it. As terms following a distribution header are not prefixed with the
+    /// version byte, it is omitted. Atoms for which `cache_index` returns `None` are sent in full.
+    pub fn with_atom_cache<F>(&self, mut cache_index: F) -> Vec<u8>
+    where
+        F: FnMut(Atom) -> Option<u8>,
+    {
+        let mut encoded = Vec::with_capacity(self.bytes.len());
+        let mut copied = 1;
+        for (range, atom) in self.atoms.iter() {
+            let Some(index) = cache_index(*atom) else { continue; };
+            encoded.extend_from_slice(&self.bytes[copied..range.start]);
+            encoded.push(ATOM_CACHE_REF);
+            encoded.push(index);
+            copied = range.end;
+        }
+        encoded.extend_from_slice(&self.bytes[copied..]);
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use super::*;
+    use crate::term::atoms;
+
+    #[test]
+    fn encoded_message_atom_cache_test() {
+        let encoded = EncodedMessage::new(Term::Atom(atoms::Ok)).unwrap();
+        assert_eq!(encoded.bytes(), &[131, 119, 2, b'o', b'k']);
+        assert_eq!(encoded.atoms().collect::<Vec<_>>(), vec![atoms::Ok]);
+        assert_eq!(encoded.with_atom_cache(|_| Some(3)), vec![ATOM_CACHE_REF, 3]);
+        assert_eq!(encoded.with_atom_cache(|_| None), vec![119, 2, b'o', b'k']);
+    }
+}
@@ -0,0 +1,202 @@
+bitflags::bitflags! {
+    /// The capability flags exchanged by nodes during the distribution handshake
+    ///
+    /// These correspond to the `DFLAG_*` flags in `dist.h`, and have the same values, as they are
+    /// part of the distribution protocol. Each side of a connection advertises the flags it
+    /// supports, and the connection then uses only those flags supported by both, see
+    /// [`DistFlags::negotiate`].
+    pub struct DistFlags: u64 {
+        /// The node is visible, i.e. it is published to other nodes, see `-hidden`
+        const PUBLISHED = 0x1;
+        const ATOM_CACHE = 0x2;
+        const EXTENDED_REFERENCES = 0x4;
+        const DIST_MONITOR = 0x8;
+        const FUN_TAGS = 0x10;
+        const DIST_MONITOR_NAME = 0x20;
+        const HIDDEN_ATOM_CACHE = 0x40;
+        const NEW_FUN_TAGS = 0x80;
+        const EXTENDED_PIDS_PORTS = 0x100;
+        const EXPORT_PTR_TAG = 0x200;
+        const BIT_BINARIES = 0x400;
+        const NEW_FLOATS = 0x800;
+        const UNICODE_IO = 0x1000;
+        const DIST_HDR_ATOM_CACHE = 0x2000;
+        const SMALL_ATOM_TAGS = 0x4000;
+        const UTF8_ATOMS = 0x10000;
+        const MAP_TAG = 0x20000;
+        const BIG_CREATION = 0x40000;
+        const SEND_SENDER = 0x80000;
+        const BIG_SEQTRACE_LABELS = 0x100000;
+        const EXIT_PAYLOAD = 0x400000;
+        const FRAGMENTS = 0x800000;
+        const HANDSHAKE_23 = 0x1000000;
+        const UNLINK_ID = 0x2000000;
+        const MANDATORY_25_DIGEST = 0x4000000;
+        const SPAWN = 1 << 32;
+        const NAME_ME = 1 << 33;
+        const V4_NC = 1 << 34;
+        const ALIAS = 1 << 35;
+        const ALTACT_SIG = 1 << 37;
+
+        /// The flags every node must support as of OTP 25
+        const MANDATORY_25 = Self::EXTENDED_REFERENCES.bits
+            | Self::FUN_TAGS.bits
+            | Self::EXTENDED_PIDS_PORTS.bits
+            | Self::UTF8_ATOMS.bits
+            | Self::NEW_FUN_TAGS.bits
+            | Self::BIG_CREATION.bits
+            | Self::NEW_FLOATS.bits
+            | Self::MAP_TAG.bits
+            | Self::EXPORT_PTR_TAG.bits
+            | Self::BIT_BINARIES.bits
+            | Self::HANDSHAKE_23.bits;
+        /// The flags every node must support as of OTP 26
+        const MANDATORY_26 = Self::V4_NC.bits | Self::UNLINK_ID.bits;
+        /// The flags we refuse to connect without, these can never be rejected
+        const MANDATORY = Self::MANDATORY_25.bits | Self::MANDATORY_26.bits;
+        /// The flags supported by this runtime, which are advertised unless configured otherwise
+        ///
+        /// Atom caches are not supported, so nodes will send us atoms in full.
+        const DEFAULT = Self::MANDATORY.bits
+            | Self::DIST_MONITOR.bits
+            | Self::DIST_MONITOR_NAME.bits
+            | Self::UNICODE_IO.bits
+            | Self::SMALL_ATOM_TAGS.bits
+            | Self::SEND_SENDER.bits
+            | Self::BIG_SEQTRACE_LABELS.bits
+            | Self::EXIT_PAYLOAD.bits
+            | Self::FRAGMENTS.bits
+            | Self::SPAWN.bits
+            | Self::ALIAS.bits;
+        /// The flags which require signals on a connection to be delivered in order
+        const STRICT_ORDER = Self::DIST_HDR_ATOM_CACHE.bits | Self::FRAGMENTS.bits;
+    }
+}
+impl DistFlags {
+    /// The flags which may be removed from those advertised, see `DistributionConfig`
+    pub const REJECTABLE: Self =
+        Self::from_bits_truncate(Self::DEFAULT.bits & !Self::MANDATORY.bits);
+
+    /// Negotiates the flags to use on a connection, given the flags we `advertise`, the flags we
+    /// `require` of the other node, and the flags the other node advertised to us.
+    ///
+    /// The result is the set of flags supported by both sides, and is only published if both sides
+    /// are, so a connection is hidden if either node is hidden. If the other node does not support
+    /// all of the required and mandatory flags, the missing ones are returned as the error.
+    pub fn negotiate(advertise: Self, require: Self, theirs: Self) -> Result<Self, Self> {
+        let missing = (require | Self::MANDATORY).difference(theirs);
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        Ok(advertise & theirs)
+    }
+
+    /// Returns the flag named `name`, which is the name of the `DFLAG_*` constant without the
+    /// prefix, in lowercase, e.g. `fragments` for `DFLAG_FRAGMENTS`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let flag = match name {
+            "published" => Self::PUBLISHED,
+            "atom_cache" => Self::ATOM_CACHE,
+            "extended_references" => Self::EXTENDED_REFERENCES,
+            "dist_monitor" => Self::DIST_MONITOR,
+            "fun_tags" => Self::FUN_TAGS,
+            "dist_monitor_name" => Self::DIST_MONITOR_NAME,
+            "hidden_atom_cache" => Self::HIDDEN_ATOM_CACHE,
+            "new_fun_tags" => Self::NEW_FUN_TAGS,
+            "extended_pids_ports" => Self::EXTENDED_PIDS_PORTS,
+            "export_ptr_tag" => Self::EXPORT_PTR_TAG,
+            "bit_binaries" => Self::BIT_BINARIES,
+            "new_floats" => Self::NEW_FLOATS,
+            "unicode_io" => Self::UNICODE_IO,
+            "dist_hdr_atom_cache" => Self::DIST_HDR_ATOM_CACHE,
+            "small_atom_tags" => Self::SMALL_ATOM_TAGS,
+            "utf8_atoms" => Self::UTF8_ATOMS,
+            "map_tag" => Self::MAP_TAG,
+            "big_creation" => Self::BIG_CREATION,
+            "send_sender" => Self::SEND_SENDER,
+            "big_seqtrace_labels" => Self::BIG_SEQTRACE_LABELS,
+            "exit_payload" => Self::EXIT_PAYLOAD,
+            "fragments" => Self::FRAGMENTS,
+            "handshake_23" => Self::HANDSHAKE_23,
+            "unlink_id" => Self::UNLINK_ID,
+            "mandatory_25_digest" => Self::MANDATORY_25_DIGEST,
+            "spawn" => Self::SPAWN,
+            "name_me" => Self::NAME_ME,
+            "v4_nc" => Self::V4_NC,
+            "alias" => Self::ALIAS,
+            "altact_sig" => Self::ALTACT_SIG,
+            _ => return None,
+        };
+        Some(flag)
+    }
+
+    /// Parses a comma-separated list of flag names, see [`DistFlags::from_name`]
+    pub fn parse_list(names: &str) -> Option<Self> {
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .try_fold(Self::empty(), |flags, name| {
+                Some(flags | Self::from_name(name)?)
+            })
+    }
+}
+impl firefly_system::sync::Atom for DistFlags {
+    type Repr = u64;
+
+    #[inline]
+    fn pack(self) -> Self::Repr {
+        self.bits()
+    }
+
+    #[inline]
+    fn unpack(raw: Self::Repr) -> Self {
+        unsafe { DistFlags::from_bits_unchecked(raw) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dist_flags_negotiate_test() {
+        let ours = DistFlags::DEFAULT | DistFlags::PUBLISHED;
+
+        // A visible node with fewer optional capabilities
+        let theirs = DistFlags::MANDATORY | DistFlags::PUBLISHED | DistFlags::SPAWN;
+        let flags = DistFlags::negotiate(ours, DistFlags::MANDATORY, theirs).unwrap();
+        assert_eq!(flags, theirs);
+
+        // A hidden node makes the connection hidden
+        let theirs = DistFlags::DEFAULT;
+        let flags = DistFlags::negotiate(ours, DistFlags::MANDATORY, theirs).unwrap();
+        assert!(!flags.contains(DistFlags::PUBLISHED));
+        assert_eq!(flags, DistFlags::DEFAULT);
+
+        // Required flags the other node lacks are reported
+        let theirs = DistFlags::MANDATORY;
+        let require = DistFlags::MANDATORY | DistFlags::SPAWN | DistFlags::ALIAS;
+        assert_eq!(
+            DistFlags::negotiate(ours, require, theirs),
+            Err(DistFlags::SPAWN | DistFlags::ALIAS)
+        );
+
+        // Mandatory flags are always required
+        let theirs = DistFlags::MANDATORY_25;
+        assert_eq!(
+            DistFlags::negotiate(ours, DistFlags::empty(), theirs),
+            Err(DistFlags::MANDATORY_26)
+        );
+    }
+
+    #[test]
+    fn dist_flags_parse_list_test() {
+        assert_eq!(
+            DistFlags::parse_list("spawn, alias,fragments"),
+            Some(DistFlags::SPAWN | DistFlags::ALIAS | DistFlags::FRAGMENTS)
+        );
+        assert_eq!(DistFlags::parse_list(""), Some(DistFlags::empty()));
+        assert_eq!(DistFlags::parse_list("spawn,bogus"), None);
+    }
+}
@@ -1,17 +1,30 @@
+mod config;
 mod connection;
+mod encoded;
+mod flags;
 mod node;
+mod spawn;
 
+pub use self::config::{AutoConnect, ConfigError, DistributionConfig, RetryPolicy};
 pub use self::connection::{ConnectionError, NodeConnection, NodeStatus};
+pub use self::encoded::EncodedMessage;
+pub use self::flags::DistFlags;
 pub use self::node::Node;
+pub use self::spawn::SpawnRequest;
 
 use alloc::sync::Arc;
 use alloc::{vec, vec::Vec};
 use core::ops::Deref;
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use firefly_system::sync::{Atomic, OnceLock};
+use firefly_system::sync::lcnt::LockClass;
+use firefly_system::sync::{Atomic, Mutex, OnceLock};
 
-use crate::term::{atoms, Atom};
+use crate::etf::{DecodeError, DecodeOptions, Decoder};
+use crate::process::signals::Signal;
+use crate::process::Process;
+use crate::services::registry::{self, Registrant, WeakAddress};
+use crate::term::{atoms, Atom, Pid, TermFragment};
 
 static DISTRIBUTION: OnceLock<Arc<dyn DistributionService>> = OnceLock::new();
 
@@ -26,6 +39,9 @@ pub enum DistributionError {
     /// Indicates that an operation failed because either the current node,
     /// or a given node, is not part of a distributed system.
     NotAlive,
+    /// Indicates that an operation failed because a node is not connected, and the
+    /// configuration does not permit connecting to it implicitly
+    NotConnected,
     /// A connection failure occurred
     ConnectionError(ConnectionError),
 }
@@ -46,14 +62,57 @@ pub fn init(dist: Arc<dyn DistributionService>) {
     }
 }
 
-/// Starts the distribution service.
+/// Starts the distribution service, naming the current node `name`, with `creation`
 ///
 /// Starting is separate from initialization, and implies that the current node
 /// is available to connect to remote nodes (or be connected to). This requires
 /// that a valid configuration for the underlying service implementation is present,
 /// so this function returns `Ok` if successful, or `Err` if the service could not start.
-pub fn start(name: Atom) -> Result<(), DistributionError> {
-    with_distribution(move |dist| dist.start(name))
+///
+/// Distribution may be started at any time, not just during boot, in which case the current
+/// node goes from `nonode@nohost` to `name`. See [`next_creation`] for how to obtain `creation`.
+pub fn start(name: Atom, creation: u32) -> Result<(), DistributionError> {
+    if creation == 0 {
+        return Err(DistributionError::InvalidOrMissingConfig);
+    }
+    with_distribution(move |dist| dist.start(name, creation))
+}
+
+/// Returns true if `name` is a valid node name, i.e. of the form `name@host`
+///
+/// The name part may only contain alphanumerics, `_` and `-`, while the host part may contain
+/// anything but `@`, as it may be a hostname or an IP address.
+pub fn is_node_name(name: &str) -> bool {
+    let Some((name, host)) = name.split_once('@') else { return false; };
+    !name.is_empty()
+        && !host.is_empty()
+        && !host.contains('@')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns a creation to start the current node with, which differs from its current creation
+///
+/// The creation distinguishes incarnations of a node with the same name, so that identifiers from
+/// an earlier incarnation are not mistaken for those of the current one. Creations 0 to 3 are
+/// never returned: 0 is the creation of a node which is not alive, and 1 to 3 were used by nodes
+/// which only supported 2-bit creations.
+pub fn next_creation() -> u32 {
+    use core::hash::{Hash, Hasher};
+    use firefly_system::time::{SystemTime, UNIX_EPOCH};
+
+    let current = current_node().creation();
+    let mut hasher = rustc_hash::FxHasher::default();
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        now.as_nanos().hash(&mut hasher);
+    }
+    current.hash(&mut hasher);
+    let mut creation = hasher.finish() as u32;
+    while creation < 4 || creation == current {
+        creation = creation.wrapping_add(4);
+    }
+    creation
 }
 
 /// Stops distribution, disconnecting any connected nodes, and triggering any links/monitors
@@ -84,6 +143,38 @@ pub fn connect(node: Atom) -> Result<Arc<Node>, DistributionError> {
     with_distribution_started(move |dist| dist.connect(node))
 }
 
+/// Returns a reference to `node`, connecting to it first if permitted by the configuration.
+///
+/// This is used when a connection is required implicitly, e.g. sending to a remote process,
+/// as opposed to an explicit request to connect. When auto-connect is disabled, this returns
+/// `Err` unless `node` is already connected.
+pub fn auto_connect(node: Atom) -> Result<Arc<Node>, DistributionError> {
+    with_distribution_started(move |dist| match dist.config().auto_connect {
+        AutoConnect::OnDemand => dist.connect(node),
+        AutoConnect::Never => dist
+            .list()
+            .into_iter()
+            .find(|n| n.name() == node)
+            .ok_or(DistributionError::NotConnected),
+    })
+}
+
+/// Returns the configuration of the distribution service
+pub fn config() -> DistributionConfig {
+    with_distribution(|dist| *dist.config())
+}
+
+/// Negotiates the capabilities of a new connection, given the flags advertised by the other node
+///
+/// Implementations call this during the handshake, and then mark the connection up with the
+/// result, see [`NodeConnection::set_up`]. Fails if the other node lacks any of the flags we
+/// require of it.
+pub fn negotiate_flags(theirs: DistFlags) -> Result<DistFlags, ConnectionError> {
+    let config = config();
+    DistFlags::negotiate(config.advertised_flags(), config.required_flags, theirs)
+        .map_err(ConnectionError::MissingFlags)
+}
+
 /// Returns a reference to the current node
 pub fn current_node() -> Arc<Node> {
     with_distribution(move |dist| dist.current_node())
@@ -99,6 +190,133 @@ pub fn set_cookie(node: Atom, cookie: Atom) -> Result<(), DistributionError> {
     with_distribution_started(move |dist| dist.set_cookie(node, cookie))
 }
 
+/// Returns the entry in the node table for the node identified by `name` and `creation`
+///
+/// If no such node is known, a new, disconnected entry is created. This is used when
+/// materializing pids/ports/references received from other nodes.
+pub fn get_or_insert_node(name: Atom, creation: u32) -> Arc<Node> {
+    with_distribution(move |dist| dist.get_or_insert_node(name, creation))
+}
+
+/// Sends `request` to `node`, asking it to spawn a process on behalf of the requestor.
+///
+/// The outcome of the request is delivered asynchronously to the requesting process.
+///
+/// NOTE: Distribution must be started, and `node` connected, to send spawn requests.
+pub fn spawn_request(node: &Node, request: SpawnRequest) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.spawn_request(node, request))
+}
+
+/// Sends an exit signal with `reason` from the local process `from` to the remote process `to`
+///
+/// NOTE: Distribution must be started to send exit signals to remote processes.
+pub fn send_exit(from: Pid, to: Pid, reason: Atom) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.send_exit(from, to, reason))
+}
+
+/// Sends `message` from the local process `from` to the remote process `to`
+///
+/// NOTE: Distribution must be started to send messages to remote processes.
+pub fn send(from: Pid, to: Pid, message: TermFragment) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.send(from, to, message))
+}
+
+/// Sends the pre-encoded `message` from the local process `from` to the remote process `to`
+///
+/// The same message may be sent to any number of processes, on any number of nodes, without being
+/// encoded again, see [`EncodedMessage`].
+///
+/// NOTE: Distribution must be started to send messages to remote processes.
+pub fn send_encoded(
+    from: Pid,
+    to: Pid,
+    message: Arc<EncodedMessage>,
+) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.send_encoded(from, to, message))
+}
+
+/// Makes `group_leader` the group leader of the remote process `to`
+///
+/// This is sent as a `GROUP_LEADER` control message, to which there is no reply.
+///
+/// NOTE: Distribution must be started to set the group leader of remote processes.
+pub fn send_group_leader(group_leader: Pid, to: Pid) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.send_group_leader(group_leader, to))
+}
+
+/// Handles a `GROUP_LEADER` control message received from a remote node, making `group_leader`
+/// the group leader of the local process `to`.
+///
+/// Implementations call this when dispatching incoming signals. Nothing happens if `to` no
+/// longer exists, as the remote node does not expect a reply.
+pub fn group_leader_received(group_leader: Pid, to: Pid) {
+    if let Some(process) = registry::get_by_pid(&to) {
+        let sender = group_leader.clone();
+        process
+            .send_signal(Signal::group_leader(sender, group_leader, None))
+            .ok();
+    }
+}
+
+/// Handles a `SEND` control message received from a remote node, decoding the message which
+/// follows it in `payload`, and delivering it from `from` to the local process `to`.
+///
+/// Implementations call this when dispatching incoming signals, with the atoms of the message's
+/// distribution header, in the order they appear in it, as `atom_cache`. The message is decoded
+/// straight into the fragment it is delivered in, resolving references to the atom cache without
+/// touching the atom table, see [`Decoder::with_atom_cache`]. Atoms sent in full are looked up,
+/// and only created if they do not exist yet, as is permitted for connected nodes.
+///
+/// Returns `Err` if `payload` is malformed, in which case the connection should be closed. The
+/// message is still validated, but not decoded, if `to` no longer exists.
+pub fn message_received(
+    from: Pid,
+    to: Pid,
+    payload: &[u8],
+    atom_cache: &[Atom],
+) -> Result<(), DecodeError> {
+    dispatch_message(from, registry::get_by_pid(&to), payload, atom_cache)
+}
+
+/// Like [`message_received`], but for a `REG_SEND` control message, in which the recipient is the
+/// process registered as `to`
+pub fn reg_send_received(
+    from: Pid,
+    to: Atom,
+    payload: &[u8],
+    atom_cache: &[Atom],
+) -> Result<(), DecodeError> {
+    let to = match registry::get_by_name(to) {
+        Some(Registrant::Process(process)) => Some(process),
+        _ => None,
+    };
+    dispatch_message(from, to, payload, atom_cache)
+}
+
+fn dispatch_message(
+    from: Pid,
+    to: Option<Arc<Process>>,
+    payload: &[u8],
+    atom_cache: &[Atom],
+) -> Result<(), DecodeError> {
+    let decoder = Decoder::with_atom_cache(payload, atom_cache, DecodeOptions::default())?;
+    if let Some(process) = to {
+        let message = decoder.decode_fragment()?;
+        process
+            .send_fragment(WeakAddress::Process(from), message)
+            .ok();
+    }
+    Ok(())
+}
+
+/// Delivers `data` received by the controller of `connection` to the distribution service
+///
+/// This is used by `erlang:dist_ctrl_put_data/2` to hand off data read from a transport
+/// implemented outside of the runtime, which is then decoded and dispatched by the service.
+pub fn put_data(connection: &Arc<NodeConnection>, data: &[u8]) -> Result<(), DistributionError> {
+    with_distribution_started(move |dist| dist.put_data(connection, data))
+}
+
 /// Returns a `Vec` containing all of the currently connected nodes
 pub fn list() -> Vec<Arc<Node>> {
     with_distribution(|dist| dist.list())
@@ -168,13 +386,17 @@ pub trait DistributionService: Send + Sync {
     /// By default, distribution is stopped/disabled, and must be explicitly enabled either
     /// by command-line options, or by request.
     ///
+    /// The current node takes on `name` and `creation`, which is never 0, once started.
+    ///
     /// Returns `Ok` if successfully started, or `Err` if unable to start for some reason.
-    fn start(&self, name: Atom) -> Result<(), DistributionError>;
+    fn start(&self, name: Atom, creation: u32) -> Result<(), DistributionError>;
     /// Stops distribution, disconnecting any connected nodes, and triggering any links/monitors
     /// which are still active.
     fn stop(&self) -> Result<(), DistributionError>;
     /// Returns true if distribution has been started and is available
     fn is_started(&self) -> bool;
+    /// Returns the configuration this service was started with
+    fn config(&self) -> &DistributionConfig;
     /// Connects to `node` via distribution.
     ///
     /// If the node is already connected, this always returns `Ok`.
@@ -196,16 +418,65 @@ pub trait DistributionService: Send + Sync {
     ///
     /// Returns `Err` if distribution is not started or `node` is not alive
     fn set_cookie(&self, node: Atom, cookie: Atom) -> Result<(), DistributionError>;
+    /// Returns the entry in the node table for `name`/`creation`, creating a new, disconnected
+    /// entry if no such node is known.
+    ///
+    /// The current node is returned if `name` matches it, and `creation` is either its creation,
+    /// or 0, which is used by nodes that do not know the creation of the node an identifier is from.
+    fn get_or_insert_node(&self, name: Atom, creation: u32) -> Arc<Node>;
+    /// Sends a spawn request to `node` on behalf of a local process
+    ///
+    /// Returns `Ok` if the request was sent, in which case the implementation must eventually
+    /// deliver a `SpawnReply` signal to the requestor, unless the connection is lost first, in
+    /// which case the requestor must be sent a `noconnection` monitor down signal.
+    fn spawn_request(&self, node: &Node, request: SpawnRequest) -> Result<(), DistributionError>;
+    /// Sends an exit signal from the local process `from` to the remote process `to`
+    fn send_exit(&self, from: Pid, to: Pid, reason: Atom) -> Result<(), DistributionError>;
+    /// Sends `message` from the local process `from` to the remote process `to`
+    ///
+    /// Messages which cannot be delivered are dropped, as with local sends.
+    fn send(&self, from: Pid, to: Pid, message: TermFragment) -> Result<(), DistributionError>;
+    /// Sends the pre-encoded `message` from the local process `from` to the remote process `to`
+    ///
+    /// Implementations send `message` as-is, or with atoms replaced by references to the atom
+    /// cache of the connection, but must not decode and encode it again.
+    fn send_encoded(
+        &self,
+        from: Pid,
+        to: Pid,
+        message: Arc<EncodedMessage>,
+    ) -> Result<(), DistributionError>;
+    /// Sends a `GROUP_LEADER` control message, making `group_leader` the group leader of the
+    /// remote process `to`.
+    ///
+    /// The receiving node should hand the message off to [`group_leader_received`].
+    fn send_group_leader(&self, group_leader: Pid, to: Pid) -> Result<(), DistributionError>;
+    /// Handles `data` received on `connection` by its controller
+    ///
+    /// Implementations are responsible for decoding the data and dispatching any signals it
+    /// contains. Outgoing data for connections driven by a controller is placed in the connection
+    /// output queue via [`NodeConnection::enqueue`], from which the controller fetches it.
+    fn put_data(
+        &self,
+        connection: &Arc<NodeConnection>,
+        data: &[u8],
+    ) -> Result<(), DistributionError>;
     /// Returns a `Vec` containing all of the currently connected nodes
     fn list(&self) -> Vec<Arc<Node>>;
     /// Returns a `Vec` containing all the nodes currently in `status`.
     fn list_by_status(&self, status: NodeStatus) -> Vec<Arc<Node>>;
 }
 
+/// See `firefly_system::sync::lcnt`
+static NODE_TABLE_LOCK: LockClass = LockClass::new("node_table");
+
 /// A simple distribution service which is not capable of remote connections, it simply
 /// provides an implementation of the service interface for use in non-distributed contexts.
 pub struct NoDistribution {
+    config: DistributionConfig,
     current_node: Arc<Node>,
+    /// Nodes we've seen referenced in terms, but which can never be connected to
+    nodes: Mutex<Vec<Arc<Node>>>,
     default_cookie: Atomic<Atom>,
     started: AtomicBool,
 }
@@ -213,28 +484,33 @@ unsafe impl Sync for NoDistribution {}
 unsafe impl Send for NoDistribution {}
 impl NoDistribution {
     pub fn new() -> Arc<Self> {
+        Self::with_config(DistributionConfig::default())
+    }
+
+    pub fn with_config(config: DistributionConfig) -> Arc<Self> {
         let current_node = Arc::new(Node::default());
         Arc::new(Self {
+            config,
             current_node,
+            nodes: Mutex::new(vec![]),
             default_cookie: Atomic::new(atoms::Nocookie),
             started: AtomicBool::new(false),
         })
     }
 }
 impl DistributionService for NoDistribution {
-    fn start(&self, name: Atom) -> Result<(), DistributionError> {
-        unsafe {
-            self.current_node.set_name(name);
-        }
+    fn start(&self, name: Atom, creation: u32) -> Result<(), DistributionError> {
         match self
             .started
             .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
         {
-            Ok(_) => Ok(()),
-            Err(_) => unsafe {
-                self.current_node.unset_name();
-                Err(DistributionError::AlreadyStarted)
-            },
+            Ok(_) => {
+                unsafe {
+                    self.current_node.set_name(name, creation);
+                }
+                Ok(())
+            }
+            Err(_) => Err(DistributionError::AlreadyStarted),
         }
     }
 
@@ -258,6 +534,11 @@ impl DistributionService for NoDistribution {
         self.started.load(Ordering::Relaxed)
     }
 
+    #[inline]
+    fn config(&self) -> &DistributionConfig {
+        &self.config
+    }
+
     fn connect(&self, _node: Atom) -> Result<Arc<Node>, DistributionError> {
         Err(ConnectionError::Unreachable.into())
     }
@@ -276,6 +557,57 @@ impl DistributionService for NoDistribution {
         }
     }
 
+    fn get_or_insert_node(&self, name: Atom, creation: u32) -> Arc<Node> {
+        let current = &self.current_node;
+        if current.name() == name && (current.creation() == creation || creation == 0) {
+            return current.clone();
+        }
+        let mut nodes = NODE_TABLE_LOCK.lock(&self.nodes);
+        if let Some(node) = nodes
+            .iter()
+            .find(|n| n.name() == name && n.creation() == creation)
+        {
+            return node.clone();
+        }
+        // The current node always has the id 0
+        let node = Arc::new(Node::new(nodes.len() + 1, name, atoms::Nocookie, creation));
+        nodes.push(node.clone());
+        node
+    }
+
+    fn spawn_request(&self, _node: &Node, _request: SpawnRequest) -> Result<(), DistributionError> {
+        Err(ConnectionError::Unreachable.into())
+    }
+
+    fn send_exit(&self, _from: Pid, _to: Pid, _reason: Atom) -> Result<(), DistributionError> {
+        Err(ConnectionError::Unreachable.into())
+    }
+
+    fn send(&self, _from: Pid, _to: Pid, _message: TermFragment) -> Result<(), DistributionError> {
+        Err(ConnectionError::Unreachable.into())
+    }
+
+    fn send_encoded(
+        &self,
+        _from: Pid,
+        _to: Pid,
+        _message: Arc<EncodedMessage>,
+    ) -> Result<(), DistributionError> {
+        Err(ConnectionError::Unreachable.into())
+    }
+
+    fn send_group_leader(&self, _group_leader: Pid, _to: Pid) -> Result<(), DistributionError> {
+        Err(ConnectionError::Unreachable.into())
+    }
+
+    fn put_data(
+        &self,
+        _connection: &Arc<NodeConnection>,
+        _data: &[u8],
+    ) -> Result<(), DistributionError> {
+        Err(DistributionError::NotAlive)
+    }
+
     #[inline]
     fn list(&self) -> Vec<Arc<Node>> {
         vec![self.current_node.clone()]
@@ -1,7 +1,7 @@
 use alloc::sync::Arc;
 use core::fmt;
 use core::hash::{Hash, Hasher};
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicU32, Ordering};
 
 use firefly_system::sync::Atomic;
 
@@ -16,7 +16,8 @@ pub struct Node {
     id: usize,
     name: Atomic<Atom>,
     cookie: Atomic<Atom>,
-    creation: u32,
+    /// Only the creation of the current node changes, when distribution is started or stopped
+    creation: AtomicU32,
     /// This is only ever `None` for the current node we're on
     connection: Option<Arc<NodeConnection>>,
 }
@@ -26,7 +27,7 @@ impl Default for Node {
             id: 0,
             name: Atomic::new(atoms::NoNodeAtNoHost),
             cookie: Atomic::new(atoms::Nocookie),
-            creation: 0,
+            creation: AtomicU32::new(0),
             connection: None,
         }
     }
@@ -38,7 +39,7 @@ impl Node {
             id,
             name: Atomic::new(name),
             cookie: Atomic::new(cookie),
-            creation,
+            creation: AtomicU32::new(creation),
             connection: None,
         }
     }
@@ -63,7 +64,7 @@ impl Node {
         self.cookie.store(cookie, Ordering::Relaxed)
     }
 
-    /// Changes the name of this node.
+    /// Changes the name and creation of this node.
     ///
     /// This is only valid for the current node, calling this function on any other node will panic.
     ///
@@ -71,14 +72,19 @@ impl Node {
     /// node name cannot be changed once distribution is started. You must first explicitly unset the
     /// current name with `unset_name`, then call this function.
     ///
+    /// Local pids, ports and references are stamped with the name and creation of the current node
+    /// when they are encoded, so identifiers created before the name was set are also reachable
+    /// under the new name.
+    ///
     /// # SAFETY
     ///
     /// This function may only be called by implementations of `DistributionService` when handling
     /// requests to set the local node name prior to starting distribution. This must never be called
     /// once distribution is started, as it will cause conflicts with connections to other nodes which
     /// will not be aware of the name change.
-    pub unsafe fn set_name(&self, name: Atom) {
+    pub unsafe fn set_name(&self, name: Atom, creation: u32) {
         assert!(self.connection.is_none());
+        assert_ne!(creation, 0);
         assert!(self
             .name
             .compare_exchange(
@@ -88,9 +94,10 @@ impl Node {
                 Ordering::Relaxed
             )
             .is_ok());
+        self.creation.store(creation, Ordering::Relaxed);
     }
 
-    /// Changes the name of this node to `nonode@nohost`
+    /// Changes the name of this node to `nonode@nohost`, and its creation to 0
     ///
     /// This is only valid for the current node, calling this function on any other node will panic.
     ///
@@ -100,12 +107,18 @@ impl Node {
     /// distribution has been stopped and it is safe to modify the name of the local node.
     pub unsafe fn unset_name(&self) {
         assert!(self.connection.is_none());
-        self.name.store(atoms::NoNodeAtNoHost, Ordering::Relaxed)
+        self.name.store(atoms::NoNodeAtNoHost, Ordering::Relaxed);
+        self.creation.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the connection backing this node, if this is not the current node
+    pub fn connection(&self) -> Option<&Arc<NodeConnection>> {
+        self.connection.as_ref()
     }
 
     /// Returns the creation time of this node
     pub fn creation(&self) -> u32 {
-        self.creation
+        self.creation.load(Ordering::Relaxed)
     }
 }
 impl fmt::Debug for Node {
@@ -0,0 +1,27 @@
+use crate::function::ModuleFunctionArity;
+use crate::term::{Pid, Reference, TermFragment};
+
+/// A request to spawn a process on a remote node on behalf of a local process.
+///
+/// This corresponds to the `DIST_SPAWN_REQUEST` control message, and is produced by
+/// `erlang:spawn_request/{2,3,4,5}` when the target node is not the local node. The
+/// distribution service is responsible for encoding and sending it to the remote node,
+/// and for delivering the `DIST_SPAWN_REPLY` back to the requestor as a
+/// [`Signal::SpawnReply`](crate::process::signals::Signal::SpawnReply).
+pub struct SpawnRequest {
+    /// The reference identifying this request, as returned to the caller of `spawn_request`
+    pub reference: Reference,
+    /// The process which made the request
+    pub from: Pid,
+    /// The group leader of the spawned process
+    pub group_leader: Pid,
+    /// The function the spawned process should begin executing
+    pub mfa: ModuleFunctionArity,
+    /// The arguments to `mfa`, as a proper list
+    pub args: TermFragment,
+    /// The options given to `spawn_request`, as a proper list
+    ///
+    /// The `monitor`, `reply`, and `reply_tag` options are handled by the requesting node,
+    /// all other options are to be applied by the remote node.
+    pub opts: TermFragment,
+}
@@ -2,7 +2,7 @@ use core::sync::atomic::{AtomicU8, Ordering};
 
 use smallvec::SmallVec;
 
-use firefly_system::time::SystemTime;
+use firefly_system::time::{clock, SystemTime};
 
 use crate::gc::Gc;
 use crate::process::signals::{Message, Signal, SignalEntry};
@@ -174,7 +174,7 @@ fn send_to_logger(level: LogLevel, group_leader: Option<Pid>, message: &str) ->
         group_leader,
         "~s~n",
         Args::<Term>::Str(message),
-        SystemTime::now(),
+        clock::system_now(),
         Pid::current(),
     )
 }
@@ -194,7 +194,7 @@ where
         group_leader,
         format,
         Args::Term(args),
-        SystemTime::now(),
+        clock::system_now(),
         Pid::current(),
     )
 }
@@ -0,0 +1,119 @@
+//! This module provides a way for the runtime system to react to memory pressure.
+//!
+//! Memory pressure is reported by some external source, e.g. the host operating system, the
+//! pressure stall information of the cgroup we're running in, or an embedder calling [`notify`]
+//! directly. Each report is forwarded to all registered callbacks, which are expected to free up
+//! memory where possible, e.g. by performing a full sweep collection of every process heap.
+//!
+//! Callbacks are invoked on the thread which reported the pressure, so they must not block for
+//! long periods of time, and should instead hand off any expensive work, e.g. to the schedulers.
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use firefly_system::sync::{const_mutex, Mutex};
+
+use log::warn;
+
+/// The severity of a memory pressure event
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PressureLevel {
+    /// Memory is getting low, and memory which can be freed cheaply should be freed
+    Moderate,
+    /// Memory is nearly exhausted, and any memory which can be freed should be freed, even at
+    /// significant cost
+    Critical,
+}
+impl fmt::Display for PressureLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Moderate => f.write_str("moderate"),
+            Self::Critical => f.write_str("critical"),
+        }
+    }
+}
+
+/// The origin of a memory pressure event
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PressureSource {
+    /// Reported by the host operating system
+    Host,
+    /// Reported via the pressure stall information of the cgroup this process belongs to
+    Cgroup,
+    /// Reported explicitly, e.g. by an embedder or via a BIF
+    Api,
+}
+impl fmt::Display for PressureSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Host => f.write_str("host"),
+            Self::Cgroup => f.write_str("cgroup"),
+            Self::Api => f.write_str("api"),
+        }
+    }
+}
+
+/// A handle for a registered callback, which can be used to unregister it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CallbackId(usize);
+
+pub type PressureCallback = dyn Fn(PressureLevel) + Send + Sync;
+
+static CALLBACKS: Mutex<Vec<(CallbackId, Arc<PressureCallback>)>> = const_mutex(Vec::new());
+static NEXT_CALLBACK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of events received at each pressure level, see [`stats`]
+static EVENTS: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Statistics about the memory pressure events received by the runtime
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PressureStats {
+    pub moderate: u64,
+    pub critical: u64,
+}
+
+/// Registers `callback` to be invoked whenever memory pressure is reported
+pub fn register<F>(callback: F) -> CallbackId
+where
+    F: Fn(PressureLevel) + Send + Sync + 'static,
+{
+    let id = CallbackId(NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed));
+    CALLBACKS.lock().push((id, Arc::new(callback)));
+    id
+}
+
+/// Unregisters a callback previously registered with [`register`]
+///
+/// Returns false if the callback was not registered.
+pub fn unregister(id: CallbackId) -> bool {
+    let mut callbacks = CALLBACKS.lock();
+    let len = callbacks.len();
+    callbacks.retain(|(cid, _)| *cid != id);
+    callbacks.len() != len
+}
+
+/// Reports memory pressure of the given `level`, invoking all registered callbacks
+pub fn notify(level: PressureLevel, source: PressureSource) {
+    EVENTS[level as usize].fetch_add(1, Ordering::Relaxed);
+    warn!(target: "memory_pressure", "{} memory pressure reported by {}", level, source);
+
+    // Callbacks are invoked without holding the lock, so that they may register/unregister
+    // callbacks themselves
+    let callbacks = CALLBACKS
+        .lock()
+        .iter()
+        .map(|(_, cb)| cb.clone())
+        .collect::<Vec<_>>();
+    for callback in callbacks {
+        callback(level);
+    }
+}
+
+/// Returns statistics about the memory pressure events received so far
+pub fn stats() -> PressureStats {
+    PressureStats {
+        moderate: EVENTS[PressureLevel::Moderate as usize].load(Ordering::Relaxed),
+        critical: EVENTS[PressureLevel::Critical as usize].load(Ordering::Relaxed),
+    }
+}
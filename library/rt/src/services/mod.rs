@@ -1,5 +1,11 @@
+pub mod checkpoint;
 pub mod distribution;
 pub mod error_logger;
+pub mod memory_pressure;
+pub mod opaque_ids;
+pub mod persistent_term;
 pub mod registry;
+pub mod safepoint;
 pub mod system;
 pub mod timers;
+pub mod warmup;
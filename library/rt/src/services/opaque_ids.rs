@@ -0,0 +1,217 @@
+//! This module provides stable, opaque identifiers for pids and references in log output, for
+//! nodes whose logs are shipped off-box, where the raw identifiers should not be exposed, but log
+//! lines still need to be correlated with each other, or with the live node.
+//!
+//! When enabled with [`enable`], the runtime's own log output, i.e. log events formatted natively
+//! and crash reports, renders each pid as `<#Id>` and each reference as `#Ref<#Id>`, where `Id` is
+//! a keyed hash of the identifier, as 16 hex digits. An identifier always renders the same way for
+//! as long as the node runs, but without the key, which never leaves the node, the identifier
+//! cannot be recovered from its rendering, nor can renderings be precomputed for likely pids.
+//!
+//! The most recently rendered identifiers are remembered, so that while the node runs, an id found
+//! in the logs can be resolved back to the pid or reference it stands for with [`resolve`].
+use alloc::collections::{BTreeMap, VecDeque};
+use core::fmt::{self, Write};
+#[allow(deprecated)]
+use core::hash::{Hasher, SipHasher};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use firefly_system::sync::{const_mutex, Mutex};
+
+use crate::term::{Pid, Reference, Term};
+
+/// The default number of identifiers which can be resolved, see [`enable`]
+pub const DEFAULT_CAPACITY: usize = 1 << 16;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static KEY0: AtomicU64 = AtomicU64::new(0);
+static KEY1: AtomicU64 = AtomicU64::new(0);
+static RENDERED: Mutex<Option<Rendered>> = const_mutex(None);
+
+/// The identifier an opaque id stands for, see [`resolve`]
+#[derive(Debug, Clone)]
+pub enum Identity {
+    Pid(Pid),
+    Reference(Reference),
+}
+
+/// The identifiers rendered so far, oldest first, of which at most `capacity` are kept
+struct Rendered {
+    ids: BTreeMap<u64, Identity>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+impl Rendered {
+    fn insert(&mut self, id: u64, identity: Identity) {
+        if self.capacity == 0 || self.ids.contains_key(&id) {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            let oldest = self.order.pop_front().unwrap();
+            self.ids.remove(&oldest);
+        }
+        self.ids.insert(id, identity);
+        self.order.push_back(id);
+    }
+}
+
+/// Renders pids and references in log output as opaque ids hashed with `key`
+///
+/// The key should be random, and must be kept secret for the ids to be of any use. Up to
+/// `capacity` of the most recently rendered ids can be resolved with [`resolve`].
+///
+/// Enabling again replaces the key, after which previously rendered ids can no longer be resolved.
+pub fn enable(key: u128, capacity: usize) {
+    *RENDERED.lock() = Some(Rendered {
+        ids: BTreeMap::new(),
+        order: VecDeque::new(),
+        capacity,
+    });
+    KEY0.store(key as u64, Ordering::Relaxed);
+    KEY1.store((key >> 64) as u64, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Renders pids and references in log output as-is again
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+    *RENDERED.lock() = None;
+}
+
+/// Returns true if pids and references are rendered as opaque ids in log output
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Returns the opaque id of `term`, if it is a pid or reference and opaque ids are enabled
+///
+/// The id is remembered, so that it can be resolved later.
+pub fn opaque_id(term: &Term) -> Option<OpaqueId> {
+    match term {
+        Term::Pid(pid) => opaque_pid(pid),
+        Term::Reference(reference) => opaque_reference(reference),
+        _ => None,
+    }
+}
+
+/// Like [`opaque_id`], but for a pid which is not in a term, e.g. the pid of a process
+pub fn opaque_pid(pid: &Pid) -> Option<OpaqueId> {
+    if !is_enabled() {
+        return None;
+    }
+    Some(remember(Kind::Pid, Identity::Pid(pid.clone())))
+}
+
+/// Like [`opaque_id`], but for a reference which is not in a term
+pub fn opaque_reference(reference: &Reference) -> Option<OpaqueId> {
+    if !is_enabled() {
+        return None;
+    }
+    Some(remember(Kind::Reference, Identity::Reference(reference.clone())))
+}
+
+fn remember(kind: Kind, identity: Identity) -> OpaqueId {
+    let id = hash(&identity);
+    if let Some(rendered) = RENDERED.lock().as_mut() {
+        rendered.insert(id, identity);
+    }
+    OpaqueId { kind, id }
+}
+
+/// Returns the pid or reference which was rendered as `id`, if it was rendered recently enough
+///
+/// `id` may be given as it appears in log output, e.g. `<#0123456789abcdef>`, or as just the hex
+/// digits.
+pub fn resolve(id: &str) -> Option<Identity> {
+    let digits = id
+        .strip_prefix("#Ref<#")
+        .or_else(|| id.strip_prefix("<#"))
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(id);
+    let id = u64::from_str_radix(digits, 16).ok()?;
+    RENDERED.lock().as_ref()?.ids.get(&id).cloned()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Pid,
+    Reference,
+}
+
+/// The opaque rendering of a pid or reference, see [`opaque_id`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpaqueId {
+    kind: Kind,
+    id: u64,
+}
+impl fmt::Display for OpaqueId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Pid => write!(f, "<#{:016x}>", self.id),
+            Kind::Reference => write!(f, "#Ref<#{:016x}>", self.id),
+        }
+    }
+}
+
+/// Hashes the usual rendering of `identity`, which includes the node of a remote identifier, so
+/// that identifiers from different nodes are distinct
+#[allow(deprecated)]
+fn hash(identity: &Identity) -> u64 {
+    struct HashWriter(SipHasher);
+    impl Write for HashWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    let key0 = KEY0.load(Ordering::Relaxed);
+    let key1 = KEY1.load(Ordering::Relaxed);
+    let mut hasher = HashWriter(SipHasher::new_with_keys(key0, key1));
+    match identity {
+        Identity::Pid(pid) => write!(&mut hasher, "{}", pid).unwrap(),
+        Identity::Reference(reference) => write!(&mut hasher, "{}", reference).unwrap(),
+    }
+    hasher.0.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::process::ProcessId;
+
+    #[test]
+    fn opaque_id_display_test() {
+        let pid = OpaqueId {
+            kind: Kind::Pid,
+            id: 0xabc,
+        };
+        assert_eq!(pid.to_string(), "<#0000000000000abc>");
+        let reference = OpaqueId {
+            kind: Kind::Reference,
+            id: 0xabc,
+        };
+        assert_eq!(reference.to_string(), "#Ref<#0000000000000abc>");
+    }
+
+    #[test]
+    fn opaque_id_eviction_test() {
+        let mut rendered = Rendered {
+            ids: BTreeMap::new(),
+            order: VecDeque::new(),
+            capacity: 2,
+        };
+        let pid = Pid::new_local(ProcessId::new(1, 0).unwrap());
+        for id in [1, 2, 1, 3] {
+            rendered.insert(id, Identity::Pid(pid.clone()));
+        }
+        // Rendering an id again does not make it any newer
+        assert_eq!(rendered.ids.keys().copied().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(rendered.order, [2, 3]);
+    }
+}
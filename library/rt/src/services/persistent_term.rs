@@ -0,0 +1,274 @@
+//! This module provides the storage behind the `persistent_term` module, a global key-value store
+//! which is optimized for reads at the expense of writes.
+//!
+//! Keys and values are copied out of the process which stores them, preserving any sharing within
+//! them, into storage owned by the runtime, where they remain until replaced or erased. Reading a
+//! persistent term does not copy it, the reader just gets a pointer into that storage, and looking
+//! up a key only requires loading the current snapshot of the table, never taking a lock. Writers
+//! are serialized, and publish a new snapshot of the table on each change.
+//!
+//! Since this storage is not part of any process heap, it is treated by the garbage collector like
+//! any other off-heap term, i.e. the parts of a persistent term which are still reachable from a
+//! process are copied into its heap when it is next collected. As a result, once a value has been
+//! replaced or erased, its storage cannot be freed until every process which may have read it has
+//! done a full sweep. Rather than track which processes read which terms, each update or erase
+//! advances a global epoch, and a process whose last full sweep predates the current epoch will
+//! perform one the next time it is scheduled, see `Process::is_gc_desired`. Retired storage is
+//! freed once every live process has caught up to the epoch at which it was retired, so like in
+//! BEAM, updating or erasing a persistent term is expensive, and should be rare. Storing a value
+//! under a new key, or storing a value equal to the current one, does not require this.
+use alloc::alloc::AllocError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::hash::BuildHasherDefault;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crossbeam::epoch::{self, Atomic, Owned};
+use firefly_alloc::heap::EmptyHeap;
+use firefly_system::sync::{const_mutex, Mutex};
+use rustc_hash::FxHasher;
+
+use crate::cmp::ExactEq;
+use crate::term::hash::phash2;
+use crate::term::{copy_layout, CopyMode, OpaqueTerm, Term, TermFragment};
+
+use super::registry;
+
+type HashMap<K, V> = hashbrown::HashMap<K, V, BuildHasherDefault<FxHasher>>;
+
+/// The current snapshot of the table, replaced by writers on each change
+static TABLE: Atomic<Table> = Atomic::null();
+/// Serializes writers, so that no change is lost when publishing a new snapshot
+static WRITER: Mutex<()> = const_mutex(());
+/// Entries which have been replaced or erased, and the epoch at which that happened
+static RETIRED: Mutex<Vec<(u64, Arc<Entry>)>> = const_mutex(Vec::new());
+/// The number of times an entry has been retired
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// A key and its value, copied out of the process which stored them
+struct Entry {
+    key: TermFragment,
+    value: TermFragment,
+    /// The number of bytes of storage used by the key and value
+    size: usize,
+}
+// Entries are never modified once stored, so they can be read from any thread
+unsafe impl Sync for Entry {}
+
+/// An immutable snapshot of all persistent terms, keyed by the portable hash of each key
+#[derive(Default, Clone)]
+struct Table {
+    buckets: HashMap<u32, Vec<Arc<Entry>>>,
+    count: usize,
+    memory: usize,
+}
+impl Table {
+    fn get(&self, hash: u32, key: &Term) -> Option<&Arc<Entry>> {
+        self.buckets
+            .get(&hash)?
+            .iter()
+            .find(|entry| key.exact_eq(&entry.key.term.into()))
+    }
+
+    /// Inserts `entry`, returning the entry it replaced, if any
+    fn insert(&mut self, hash: u32, entry: Arc<Entry>) -> Option<Arc<Entry>> {
+        let key: Term = entry.key.term.into();
+        self.count += 1;
+        self.memory += entry.size;
+        let bucket = self.buckets.entry(hash).or_default();
+        match bucket.iter().position(|e| key.exact_eq(&e.key.term.into())) {
+            Some(index) => {
+                let replaced = core::mem::replace(&mut bucket[index], entry);
+                self.count -= 1;
+                self.memory -= replaced.size;
+                Some(replaced)
+            }
+            None => {
+                bucket.push(entry);
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, hash: u32, key: &Term) -> Option<Arc<Entry>> {
+        let bucket = self.buckets.get_mut(&hash)?;
+        let index = bucket
+            .iter()
+            .position(|entry| key.exact_eq(&entry.key.term.into()))?;
+        let removed = bucket.swap_remove(index);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.count -= 1;
+        self.memory -= removed.size;
+        Some(removed)
+    }
+}
+
+/// Summary information about the persistent terms currently stored, as returned by
+/// `persistent_term:info/0`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Info {
+    /// The number of persistent terms
+    pub count: usize,
+    /// The number of bytes of storage used by all persistent terms
+    pub memory: usize,
+}
+
+/// Returns the value stored under `key`, if any
+///
+/// The returned term points into storage owned by this module, which remains valid until the
+/// calling process next does a full sweep.
+pub fn get(key: &Term) -> Option<OpaqueTerm> {
+    let hash = phash2(key.clone());
+    let guard = epoch::pin();
+    let table = TABLE.load(Ordering::Acquire, &guard);
+    let table = unsafe { table.as_ref() }?;
+    table.get(hash, key).map(|entry| entry.value.term)
+}
+
+/// Stores `value` under `key`, replacing any previous value
+///
+/// Replacing a value which is not exactly equal to `value` retires the previous value, see the
+/// module documentation.
+pub fn put(key: &Term, value: &Term) -> Result<(), AllocError> {
+    let hash = phash2(key.clone());
+    let _writer = WRITER.lock();
+    let guard = epoch::pin();
+    let current = TABLE.load(Ordering::Acquire, &guard);
+    let mut table = unsafe { current.as_ref() }.cloned().unwrap_or_default();
+    if let Some(entry) = table.get(hash, key) {
+        if value.exact_eq(&entry.value.term.into()) {
+            return Ok(());
+        }
+    }
+
+    let size = copy_layout(key, &EmptyHeap, CopyMode::PreserveSharing).size()
+        + copy_layout(value, &EmptyHeap, CopyMode::PreserveSharing).size();
+    let entry = Arc::new(Entry {
+        key: TermFragment::copy_from(key, CopyMode::PreserveSharing)?,
+        value: TermFragment::copy_from(value, CopyMode::PreserveSharing)?,
+        size,
+    });
+    let replaced = table.insert(hash, entry);
+    publish(table, &guard);
+    if let Some(replaced) = replaced {
+        retire(replaced);
+    }
+    Ok(())
+}
+
+/// Erases the value stored under `key`, returning false if there was none
+///
+/// The erased value is retired, see the module documentation.
+pub fn erase(key: &Term) -> bool {
+    let hash = phash2(key.clone());
+    let _writer = WRITER.lock();
+    let guard = epoch::pin();
+    let current = TABLE.load(Ordering::Acquire, &guard);
+    let Some(mut table) = unsafe { current.as_ref() }.cloned() else { return false; };
+    match table.remove(hash, key) {
+        Some(removed) => {
+            publish(table, &guard);
+            retire(removed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns summary information about the persistent terms currently stored
+pub fn info() -> Info {
+    let guard = epoch::pin();
+    let table = TABLE.load(Ordering::Acquire, &guard);
+    match unsafe { table.as_ref() } {
+        Some(table) => Info {
+            count: table.count,
+            memory: table.memory,
+        },
+        None => Info::default(),
+    }
+}
+
+/// Returns the current epoch, which is advanced each time a value is replaced or erased
+///
+/// A process which has done a full sweep since the epoch was last advanced cannot refer to any
+/// retired value.
+#[inline]
+pub fn epoch() -> u64 {
+    EPOCH.load(Ordering::Acquire)
+}
+
+/// Frees the storage of any retired values which can no longer be referenced by a live process
+///
+/// This is called by processes after catching up to the current epoch, as well as by writers.
+pub fn reclaim() {
+    let mut retired = RETIRED.lock();
+    if retired.is_empty() {
+        return;
+    }
+    let oldest = registry::processes()
+        .iter()
+        .map(|process| process.persistent_term_epoch())
+        .min()
+        .unwrap_or(u64::MAX);
+    retired.retain(|(epoch, _)| *epoch > oldest);
+}
+
+/// Replaces the current snapshot of the table with `table`
+///
+/// The caller must hold the writer lock.
+fn publish(table: Table, guard: &epoch::Guard) {
+    let previous = TABLE.swap(Owned::new(table), Ordering::AcqRel, guard);
+    if !previous.is_null() {
+        // The previous snapshot may still be in use by concurrent readers
+        unsafe {
+            guard.defer_destroy(previous);
+        }
+    }
+}
+
+fn retire(entry: Arc<Entry>) {
+    let epoch = EPOCH.fetch_add(1, Ordering::AcqRel) + 1;
+    RETIRED.lock().push((epoch, entry));
+    reclaim();
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::{FixedSizeHeap, Heap};
+
+    use super::*;
+    use crate::term::{atoms, Tuple};
+
+    #[test]
+    fn persistent_term_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let key: Term = Tuple::from_slice(&[atoms::Ok.into(), Term::Int(1).into()], &heap)
+            .unwrap()
+            .into();
+        let value: Term = Tuple::from_slice(&[Term::Int(2).into()], &heap)
+            .unwrap()
+            .into();
+        assert_eq!(get(&key), None);
+
+        // Storing a new key, or an equal value, does not retire anything
+        let epoch = epoch();
+        put(&key, &value).unwrap();
+        put(&key, &value).unwrap();
+        assert_eq!(super::epoch(), epoch);
+        let stored = get(&key).unwrap();
+        assert!(!heap.contains(unsafe { stored.as_ptr() }.cast_const()));
+        assert_eq!(Term::from(stored), value);
+        assert!(info().count >= 1);
+
+        put(&key, &Term::Int(3)).unwrap();
+        assert_eq!(super::epoch(), epoch + 1);
+        assert_eq!(get(&key), Some(Term::Int(3).into()));
+
+        assert!(erase(&key));
+        assert!(!erase(&key));
+        assert_eq!(get(&key), None);
+        assert_eq!(super::epoch(), epoch + 2);
+    }
+}
@@ -20,18 +20,25 @@ cfg_if::cfg_if! {
     }
 }
 
+mod process_table;
+
 pub use self::imp::*;
+pub use self::process_table::{ProcessTable, ProcessTableGuard};
 
+use alloc::collections::BinaryHeap;
 use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
 use core::hash::{Hash, Hasher};
 use core::ptr;
+use core::sync::atomic;
 
 use firefly_system::sync::OnceLock;
 
 use crate::error::ExceptionFlags;
 use crate::function::ErlangResult;
 use crate::gc::Gc;
-use crate::process::{Process, ProcessId, ProcessLock};
+use crate::process::{Process, ProcessId, ProcessLock, StatusFlags};
 use crate::term::{atoms, Atom, Cons, OpaqueTerm, Pid, Port, PortId, Term};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -322,6 +329,19 @@ impl PartialEq for WeakRegistrant {
 /// gets initialized exactly once.
 static REGISTRY: OnceLock<Registry> = OnceLock::new();
 
+/// Initializes the registry ahead of its first use, with room for `processes` simultaneously
+/// alive processes, and for `names` registered names and `ports` ports before those tables grow
+///
+/// Returns false if the registry was already initialized, in which case this has no effect.
+pub fn init_with_capacity(processes: usize, names: usize, ports: usize) -> bool {
+    let mut initialized = false;
+    REGISTRY.get_or_init(|| {
+        initialized = true;
+        Registry::with_capacity(processes, names, ports)
+    });
+    initialized
+}
+
 /// Get a reference to the local process registered to `name`
 pub fn get_by_name(name: Atom) -> Option<Registrant> {
     with_name_table(|registry, guard| registry.get_by_name(name, guard))
@@ -343,9 +363,22 @@ pub fn get_by_port_id(id: PortId) -> Option<Arc<Port>> {
     with_port_table(|registry, guard| registry.get_by_port_id(id, guard))
 }
 
+/// Assigns a process identifier to a new process
+///
+/// Returns `None` if the maximum number of simultaneously alive processes has been reached.
+pub fn reserve_process_id() -> Option<ProcessId> {
+    with_registry(|registry| registry.reserve_process_id())
+}
+
+/// Releases a process identifier obtained from [`reserve_process_id`], if no process was ever
+/// registered with it
+pub fn release_process_id(id: ProcessId) {
+    with_registry(|registry| registry.release_process_id(id))
+}
+
 /// Inserts a process in the registry
 ///
-/// This function will panic if the registry already contains a registration for the same pid
+/// This function will panic if the pid of the process was not reserved for it
 pub fn register_process(process: Arc<Process>) {
     with_process_table(|registry, guard| registry.register_process(process, guard))
 }
@@ -355,6 +388,104 @@ pub fn unregister_process(pid: ProcessId) -> Option<Arc<Process>> {
     with_process_table(|registry, guard| registry.unregister_process(pid, guard))
 }
 
+/// Returns a snapshot of all processes currently in the registry
+///
+/// Processes spawned while the snapshot is being taken may or may not be included.
+pub fn processes() -> Vec<Arc<Process>> {
+    with_process_table(|registry, guard| {
+        let mut processes = Vec::with_capacity(registry.process_count(guard));
+        processes.extend(registry.processes(guard));
+        processes
+    })
+}
+
+/// Calls `f` with each process currently in the registry, without taking a snapshot first
+///
+/// Like [`processes`], processes spawned while iterating may or may not be visited.
+pub fn for_each_process<F>(mut f: F)
+where
+    F: FnMut(Arc<Process>),
+{
+    with_process_table(|registry, guard| {
+        for process in registry.processes(guard) {
+            f(process);
+        }
+    })
+}
+
+/// Returns the `n` processes for which `key` is largest, largest first, along with their keys
+///
+/// Only `n` processes are retained at a time, however many processes there are, and ties are
+/// broken in favor of the lower pid.
+pub fn top_processes<K, F>(n: usize, mut key: F) -> Vec<(Arc<Process>, K)>
+where
+    K: Ord + Copy,
+    F: FnMut(&Process) -> K,
+{
+    if n == 0 {
+        return Vec::new();
+    }
+
+    // A min-heap of the largest seen so far, so the smallest is the one evicted
+    let mut top = BinaryHeap::<Reverse<Ranked<K>>>::with_capacity(n + 1);
+    for_each_process(|process| {
+        let key = key(&process);
+        if top.len() == n && top.peek().map(|min| key <= min.0.key).unwrap_or(false) {
+            return;
+        }
+        top.push(Reverse(Ranked { key, process }));
+        if top.len() > n {
+            top.pop();
+        }
+    });
+
+    top.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(ranked)| (ranked.process, ranked.key))
+        .collect()
+}
+
+/// A process ordered by some key, then by pid, for [`top_processes`]
+struct Ranked<K> {
+    key: K,
+    process: Arc<Process>,
+}
+impl<K: Ord> PartialEq for Ranked<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<K: Ord> Eq for Ranked<K> {}
+impl<K: Ord> PartialOrd for Ranked<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<K: Ord> Ord for Ranked<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| other.process.id().cmp(&self.process.id()))
+    }
+}
+
+/// Returns the number of processes currently in the registry
+pub fn process_count() -> usize {
+    with_process_table(|registry, guard| registry.process_count(guard))
+}
+
+/// Returns the maximum number of simultaneously alive processes
+pub fn process_limit() -> usize {
+    with_registry(|registry| registry.process_limit())
+}
+
+/// Returns a snapshot of all ports currently in the registry
+///
+/// Ports opened while the snapshot is being taken may or may not be included.
+pub fn ports() -> Vec<Arc<Port>> {
+    with_port_table(|registry, guard| registry.ports(guard).collect())
+}
+
 /// Inserts a port in the registry
 ///
 /// This function will panic if the registry already contains a registration for the same port id
@@ -386,7 +517,7 @@ pub fn unregister_name(name: Registrant) {
 /// Raises `badarg` if:
 ///
 /// * The name is invalid (not an atom, or the atom 'undefined')
-/// * The process/port does not exist
+/// * The process/port does not exist, or is exiting
 /// * The process/port already have a registered name
 /// * The name is already registered to someone else
 #[export_name = "erlang:register/2"]
@@ -407,7 +538,11 @@ pub extern "C-unwind" fn register2(
     match id.into() {
         Term::Pid(pid) => {
             if let Some(p) = get_by_pid(&pid) {
-                if register_name(name, p.into()).is_ok() {
+                // An exiting process is still in the registry until it is freed, but may not be
+                // given a name, as its name is about to be unregistered
+                let status = p.status(atomic::Ordering::Acquire);
+                let exiting = status.intersects(StatusFlags::EXITING | StatusFlags::FREE);
+                if !exiting && register_name(name, p.into()).is_ok() {
                     return ErlangResult::Ok(true.into());
                 }
             }
@@ -444,14 +579,7 @@ pub extern "C-unwind" fn unregister1(process: &mut ProcessLock, name: OpaqueTerm
     }
     let name = name.as_atom();
 
-    let exists = with_name_table(|registry, guard| {
-        if let Some(registrant) = registry.get_by_name(name, guard) {
-            registry.unregister_name(registrant, guard);
-            true
-        } else {
-            false
-        }
-    });
+    let exists = with_name_table(|registry, guard| registry.unregister_by_name(name, guard));
 
     if exists {
         ErlangResult::Ok(true.into())
@@ -501,12 +629,18 @@ pub extern "C-unwind" fn registered(process: &mut ProcessLock) -> ErlangResult {
     use crate::term::LayoutBuilder;
     use firefly_alloc::heap::Heap;
 
-    // Get the number of entries in the name table
-    let len = with_name_table(|registry, guard| registry.registered_names(guard));
+    // Take a snapshot of the names of live registrants, so the list is sized exactly, even if
+    // names are registered while it is being built
+    let names = with_name_table(|registry, guard| {
+        registry
+            .names(guard)
+            .filter(|(_, registrant)| registrant.upgrade().is_some())
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+    });
 
-    // Pad with room for extras in case any new registered names appear while walking the table
     let mut builder = LayoutBuilder::new();
-    builder.build_list(len + 5);
+    builder.build_list(names.len());
     let layout = builder.finish();
 
     if process.heap.heap_available() < layout.size() {
@@ -514,18 +648,47 @@ pub extern "C-unwind" fn registered(process: &mut ProcessLock) -> ErlangResult {
         assert!(gc::garbage_collect(process, Default::default()).is_ok());
     }
 
-    // Build the list on the heap, panicking if for some reason we run out of heap during this
-    with_name_table(|registry, guard| {
-        let mut tail = OpaqueTerm::NIL;
-        for (name, _) in registry.names(guard) {
-            let head: OpaqueTerm = name.into();
+    let mut tail = OpaqueTerm::NIL;
+    for name in names {
+        let head: OpaqueTerm = name.into();
+        let list = Cons::new_in(Cons { head, tail }, process).unwrap();
+        tail = list.into();
+    }
+    ErlangResult::Ok(tail)
+}
+
+/// Produces a list of the pids of all processes on the local node, including those which are exiting
+///
+/// The process table is not locked while the snapshot is taken, so processes spawned or exiting
+/// concurrently may or may not be included.
+#[export_name = "erlang:processes/0"]
+pub extern "C-unwind" fn processes0(process: &mut ProcessLock) -> ErlangResult {
+    use crate::gc;
+    use crate::term::LayoutBuilder;
+    use firefly_alloc::heap::Heap;
 
-            let list = Cons::new_in(Cons { head, tail }, process).unwrap();
-            tail = list.into();
-        }
-        Ok(tail)
-    })
-    .into()
+    let snapshot = processes();
+
+    let mut builder = LayoutBuilder::new();
+    for _ in snapshot.iter() {
+        builder.build_pid();
+    }
+    builder.build_list(snapshot.len());
+    let layout = builder.finish();
+
+    if process.heap.heap_available() < layout.size() {
+        process.gc_needed = layout.size();
+        assert!(gc::garbage_collect(process, Default::default()).is_ok());
+    }
+
+    // Build the list back to front, so that the pids are in the order of the process table
+    let mut tail = OpaqueTerm::NIL;
+    for p in snapshot.iter().rev() {
+        let head = Gc::new_in(p.pid(), process).unwrap().into();
+        let list = Cons::new_in(Cons { head, tail }, process).unwrap();
+        tail = list.into();
+    }
+    ErlangResult::Ok(tail)
 }
 
 /// This function lets you combine multiple operations against the registry by
@@ -7,10 +7,7 @@ use crossbeam_skiplist::SkipMap;
 use crate::process::{Process, ProcessId};
 use crate::term::{Atom, Port, PortId};
 
-use super::{Registrant, RegistrationError, WeakRegistrant};
-
-#[repr(transparent)]
-pub struct ProcessTableGuard<'a>(PhantomData<&'a SkipMap<ProcessId, Arc<Process>>>);
+use super::{ProcessTable, ProcessTableGuard, Registrant, RegistrationError, WeakRegistrant};
 
 #[repr(transparent)]
 pub struct PortTableGuard<'a>(PhantomData<&'a SkipMap<PortId, Arc<Port>>>);
@@ -20,14 +17,25 @@ pub struct NameTableGuard<'a>(PhantomData<&'a SkipMap<Atom, WeakRegistrant>>);
 
 #[derive(Default)]
 pub struct Registry {
-    processes: SkipMap<ProcessId, Arc<Process>>,
+    processes: ProcessTable,
     ports: SkipMap<PortId, Arc<Port>>,
     names: SkipMap<Atom, WeakRegistrant>,
 }
 impl Registry {
+    /// Creates a registry with room for `processes` simultaneously alive processes
+    ///
+    /// The other tables are skip lists, which have no capacity, so `names` and `ports` are unused.
+    pub fn with_capacity(processes: usize, _names: usize, _ports: usize) -> Self {
+        Self {
+            processes: ProcessTable::new(processes),
+            ports: SkipMap::new(),
+            names: SkipMap::new(),
+        }
+    }
+
     #[inline]
     pub fn process_table_guard(&self) -> ProcessTableGuard<'_> {
-        ProcessTableGuard(PhantomData)
+        self.processes.guard()
     }
 
     #[inline]
@@ -47,37 +55,36 @@ impl Registry {
     pub fn get_by_process_id(
         &self,
         pid: ProcessId,
-        _guard: &ProcessTableGuard<'_>,
+        guard: &ProcessTableGuard<'_>,
     ) -> Option<Arc<Process>> {
-        self.processes.get(&pid).map(|p| p.value().clone())
+        self.processes.get(pid, guard)
     }
 
     pub fn get_by_port_id(&self, id: PortId, _guard: &PortTableGuard<'_>) -> Option<Arc<Port>> {
         self.ports.get(&id).map(|p| p.value().clone())
     }
 
-    pub fn register_process(&self, process: Arc<Process>, _guard: &ProcessTableGuard<'_>) {
-        let pid = process.id();
-        if unlikely(self.processes.contains_key(&pid)) {
-            panic!("attempted to register pid that is already in use {}", &pid);
-        }
+    pub fn reserve_process_id(&self) -> Option<ProcessId> {
+        self.processes.reserve()
+    }
 
-        self.processes.get_or_insert(pid, process);
+    pub fn release_process_id(&self, pid: ProcessId) {
+        self.processes.release(pid)
+    }
+
+    pub fn register_process(&self, process: Arc<Process>, guard: &ProcessTableGuard<'_>) {
+        self.processes.insert(process, guard)
     }
 
     pub fn unregister_process(
         &self,
         pid: ProcessId,
-        _guard: &ProcessTableGuard<'_>,
+        guard: &ProcessTableGuard<'_>,
     ) -> Option<Arc<Process>> {
-        if let Some(entry) = self.processes.remove(&pid) {
-            let process = entry.value().clone();
-            let ntg = self.name_table_guard();
-            self.unregister_name(Registrant::Process(process.clone()), &ntg);
-            Some(process)
-        } else {
-            None
-        }
+        let process = self.processes.remove(pid, guard)?;
+        let ntg = self.name_table_guard();
+        self.unregister_name(Registrant::Process(process.clone()), &ntg);
+        Some(process)
     }
 
     pub fn register_port(&self, port: Arc<Port>, _guard: &PortTableGuard<'_>) {
@@ -114,28 +121,38 @@ impl Registry {
         }
 
         let weak = to.downgrade();
-        let entry = self.names.get_or_insert(name, weak.clone());
-        if entry.value() == &weak {
-            Ok(())
-        } else {
-            Err(RegistrationError::AlreadyRegistered)
+        loop {
+            let entry = self.names.get_or_insert(name, weak.clone());
+            if entry.value() == &weak {
+                return Ok(());
+            }
+            // The registrant of the name has since died, so take over the registration
+            if entry.value().upgrade().is_none() {
+                entry.remove();
+                continue;
+            }
+            to.unregister_name().unwrap();
+            return Err(RegistrationError::AlreadyRegistered);
         }
     }
 
     pub fn unregister_name(&self, registrant: Registrant, _guard: &NameTableGuard<'_>) {
         let Some(name) = registrant.registered_name() else { return; };
-        if let Some(entry) = self.names.remove(&name) {
-            // This should never fail because `registrant` and `registered` should be the same process.
-            //
-            // If this does fail, it means that somehow another process/port was allowed to register a name which
-            // ostensibly belongs to `registrant`, or vice/versa. In any case, it indicates a violation of what
-            // we expect, so we want to panic if it happens.
-            assert!(registrant.eq(entry.value()));
+        let Some(entry) = self.names.get(&name) else { return; };
+        // The name may have been unregistered and then registered to someone else concurrently
+        if registrant.eq(entry.value()) && entry.remove() {
             // Remove the name from the registrant itself
-            registrant.unregister_name().unwrap();
+            registrant.unregister_name().ok();
         }
     }
 
+    pub fn unregister_by_name(&self, name: Atom, _guard: &NameTableGuard<'_>) -> bool {
+        let Some(entry) = self.names.remove(&name) else { return false; };
+        let Some(registrant) = entry.value().upgrade() else { return false; };
+        registrant.unregister_name().ok();
+        true
+    }
+
     pub fn names(
         &self,
         _guard: &NameTableGuard<'_>,
@@ -143,6 +160,32 @@ impl Registry {
         self.names.iter().map(|e| (*e.key(), e.value().clone()))
     }
 
+    /// Returns an iterator over all processes in the registry
+    pub fn processes<'g>(
+        &'g self,
+        guard: &'g ProcessTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Process>> + 'g {
+        self.processes.iter(guard)
+    }
+
+    /// Returns an iterator over all ports in the registry
+    pub fn ports<'g>(
+        &'g self,
+        _guard: &'g PortTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Port>> + 'g {
+        self.ports.iter().map(|e| e.value().clone())
+    }
+
+    /// Returns the number of processes in the registry
+    pub fn process_count<'g>(&'g self, _guard: &'g ProcessTableGuard<'_>) -> usize {
+        self.processes.len()
+    }
+
+    /// Returns the maximum number of simultaneously alive processes
+    pub fn process_limit(&self) -> usize {
+        self.processes.capacity()
+    }
+
     /// Returns the number of registered names in the registry
     pub fn registered_names<'g>(&'g self, _guard: &'g NameTableGuard<'_>) -> usize {
         self.names.len()
@@ -0,0 +1,257 @@
+//! The process table, which assigns process identifiers, and maps them to live processes
+//!
+//! Like the process table of ERTS, this is a fixed-size array of slots, in which the number
+//! component of a pid is the index of the slot assigned to the process. The serial component of a
+//! pid is the generation of its slot, which is advanced every time the slot is freed, so a stale
+//! pid never resolves to a process which later reused its slot. Pids of other incarnations of the
+//! local node are distinguished by the node creation, which is not part of a [`ProcessId`].
+//!
+//! Slots are claimed and freed using atomic operations only, and lookups are protected by an
+//! epoch guard rather than a lock, so spawning, exiting, resolving a pid, and taking a snapshot of
+//! all processes never wait on another thread.
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+
+use crossbeam::epoch::{self, Guard};
+
+use crate::process::{Process, ProcessId};
+
+/// Stored in a slot which has been claimed for a process that has not been registered yet
+const RESERVED: *mut Process = 1 as *mut Process;
+
+/// A guard reference for the process table
+///
+/// While a guard is held, processes removed from the table are kept alive, so that a process
+/// found by a lookup can be safely referenced, even if it is concurrently unregistered.
+pub struct ProcessTableGuard<'a> {
+    guard: Guard,
+    _marker: PhantomData<&'a ProcessTable>,
+}
+
+struct Slot {
+    /// The registered process, `RESERVED`, or null if the slot is free
+    ///
+    /// The table holds a strong reference to the registered process, obtained by `Arc::into_raw`.
+    process: AtomicPtr<Process>,
+    /// The serial of the pid assigned to the current, or next, occupant of this slot
+    serial: AtomicU32,
+}
+impl Slot {
+    fn new() -> Self {
+        Self {
+            process: AtomicPtr::new(ptr::null_mut()),
+            serial: AtomicU32::new(0),
+        }
+    }
+
+    /// Advances the serial of this slot and frees it
+    ///
+    /// The slot must be `RESERVED` by the caller.
+    fn free(&self) {
+        let serial = self.serial.load(Ordering::Relaxed);
+        self.serial.store(serial.wrapping_add(1), Ordering::Relaxed);
+        self.process.store(ptr::null_mut(), Ordering::Release);
+    }
+}
+
+/// The table of all local processes, see the module docs for details
+pub struct ProcessTable {
+    /// The slots of the table, the first of which is never used, so no pid is ever zero
+    slots: Box<[Slot]>,
+    /// The index from which the search for a free slot starts
+    next: AtomicUsize,
+    /// The number of registered processes
+    count: AtomicUsize,
+}
+impl Default for ProcessTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+impl ProcessTable {
+    /// The default maximum number of simultaneously alive processes, the same as in ERTS
+    pub const DEFAULT_CAPACITY: usize = 1 << 18;
+
+    /// Creates a table which can hold up to `capacity` simultaneously alive processes
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0 && capacity < u32::MAX as usize,
+            "invalid process table capacity"
+        );
+        Self {
+            slots: (0..=capacity).map(|_| Slot::new()).collect(),
+            next: AtomicUsize::new(1),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Return a guard which can be used to access the table safely
+    #[inline]
+    pub fn guard(&self) -> ProcessTableGuard<'_> {
+        ProcessTableGuard {
+            guard: epoch::pin(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the maximum number of simultaneously alive processes
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    /// Returns the number of registered processes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn slot(&self, id: ProcessId) -> Option<&Slot> {
+        match id.number() as usize {
+            0 => None,
+            index => self.slots.get(index),
+        }
+    }
+
+    /// Claims a free slot, returning the process identifier assigned to it
+    ///
+    /// Returns `None` if every slot is in use.
+    pub fn reserve(&self) -> Option<ProcessId> {
+        let len = self.slots.len();
+        for _ in 0..len {
+            let index = self.next.fetch_add(1, Ordering::Relaxed) % len;
+            if index == 0 {
+                continue;
+            }
+            let slot = &self.slots[index];
+            let claimed = slot.process.compare_exchange(
+                ptr::null_mut(),
+                RESERVED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            );
+            if claimed.is_ok() {
+                let serial = slot.serial.load(Ordering::Relaxed);
+                return Some(unsafe { ProcessId::new_unchecked(index as u32, serial) });
+            }
+        }
+        None
+    }
+
+    /// Frees the slot of `id`, if it was reserved but no process was ever registered with it
+    pub fn release(&self, id: ProcessId) {
+        let Some(slot) = self.slot(id) else { return; };
+        if slot.serial.load(Ordering::Relaxed) == id.serial()
+            && slot.process.load(Ordering::Relaxed) == RESERVED
+        {
+            slot.free();
+        }
+    }
+
+    /// Stores `process` in the slot reserved for its process identifier
+    ///
+    /// This function will panic if that slot is not reserved for the process.
+    pub fn insert(&self, process: Arc<Process>, _guard: &ProcessTableGuard<'_>) {
+        let id = process.id();
+        let slot = self
+            .slot(id)
+            .filter(|slot| slot.serial.load(Ordering::Relaxed) == id.serial());
+        let raw = Arc::into_raw(process) as *mut Process;
+        let inserted = slot.map(|slot| {
+            slot.process
+                .compare_exchange(RESERVED, raw, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+        });
+        if inserted != Some(true) {
+            drop(unsafe { Arc::from_raw(raw) });
+            panic!("attempted to register a pid already in use {}", id);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Fetches the process associated with `id`, if it is registered
+    pub fn get(&self, id: ProcessId, guard: &ProcessTableGuard<'_>) -> Option<Arc<Process>> {
+        let slot = self.slot(id)?;
+        let process = unsafe { upgrade(slot.process.load(Ordering::Acquire), guard) }?;
+        if process.id() == id {
+            Some(process)
+        } else {
+            None
+        }
+    }
+
+    /// Removes the process associated with `id`, freeing its slot for reuse under a new serial
+    pub fn remove(&self, id: ProcessId, guard: &ProcessTableGuard<'_>) -> Option<Arc<Process>> {
+        let slot = self.slot(id)?;
+        let raw = slot.process.load(Ordering::Acquire);
+        if raw.is_null() || raw == RESERVED || unsafe { (*raw).id() } != id {
+            return None;
+        }
+        // Reserving the slot first ensures only one of any concurrent removals succeeds
+        slot.process
+            .compare_exchange(raw, RESERVED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        slot.free();
+        self.count.fetch_sub(1, Ordering::Relaxed);
+
+        // Lookups may still be about to take a reference to the process, so the reference held by
+        // the table is only dropped once every guard held at this point is released
+        let process = unsafe { Arc::from_raw(raw) };
+        let removed = process.clone();
+        guard.guard.defer(move || drop(process));
+        Some(removed)
+    }
+
+    /// Returns an iterator over all registered processes, in slot order
+    ///
+    /// This does not stop processes from being registered or unregistered while iterating, so
+    /// processes registered after the iterator is returned may or may not be seen.
+    pub fn iter<'g>(
+        &'g self,
+        guard: &'g ProcessTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Process>> + 'g {
+        self.slots
+            .iter()
+            .filter_map(move |slot| unsafe { upgrade(slot.process.load(Ordering::Acquire), guard) })
+    }
+}
+
+/// Takes a new strong reference to the process stored in a slot, if any
+///
+/// # Safety
+///
+/// `raw` must have been loaded from a slot while `_guard` was held.
+unsafe fn upgrade(raw: *mut Process, _guard: &ProcessTableGuard<'_>) -> Option<Arc<Process>> {
+    if raw.is_null() || raw == RESERVED {
+        return None;
+    }
+    Arc::increment_strong_count(raw);
+    Some(Arc::from_raw(raw))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slot_reuse_test() {
+        let table = ProcessTable::new(1);
+        let first = table.reserve().unwrap();
+        assert_eq!(first.number(), 1);
+        assert_eq!(table.reserve(), None);
+
+        table.release(first);
+        let second = table.reserve().unwrap();
+        assert_eq!(second.number(), first.number());
+        assert_ne!(second, first);
+
+        // Releasing a stale pid must not free the slot of its new occupant
+        table.release(first);
+        assert_eq!(table.reserve(), None);
+        assert!(table.get(first, &table.guard()).is_none());
+    }
+}
@@ -6,13 +6,10 @@ use rustc_hash::FxHasher;
 use crate::process::{Process, ProcessId};
 use crate::term::{Atom, Port, PortId};
 
-use super::{Registrant, RegistrationError, WeakRegistrant};
+use super::{ProcessTable, ProcessTableGuard, Registrant, RegistrationError, WeakRegistrant};
 
 type HashMap<K, V> = flurry::HashMap<K, V, core::hash::BuildHasherDefault<FxHasher>>;
 
-/// A guard reference for the process table
-pub type ProcessTableGuard<'a> = flurry::Guard<'a>;
-
 /// A guard reference for the port table
 pub type PortTableGuard<'a> = flurry::Guard<'a>;
 
@@ -41,16 +38,26 @@ pub type RegisteredNameIter<'a> = flurry::iter::Iter<'a, Atom, WeakRegistrant>;
 /// ideally when that happens the last remaining reference to that process/port is the one returned from
 /// the registry itself.
 ///
-/// This registry implementation is built on [`flurry::HashMap`] for each table, which provides us with some
-/// nice guarantees when it comes to traversing the table without unnecessarily competing with other threads
-/// trying to register things.
+/// This registry implementation is built on [`flurry::HashMap`] for the port and name tables, which provides
+/// us with some nice guarantees when it comes to traversing the table without unnecessarily competing with
+/// other threads trying to register things. The process table is a [`ProcessTable`], which also assigns pids.
 #[derive(Default)]
 pub struct Registry {
-    processes: HashMap<ProcessId, Arc<Process>>,
+    processes: ProcessTable,
     ports: HashMap<PortId, Arc<Port>>,
     names: HashMap<Atom, WeakRegistrant>,
 }
 impl Registry {
+    /// Creates a registry with room for `processes` simultaneously alive processes, and for
+    /// `names` registered names and `ports` ports before those tables need to grow
+    pub fn with_capacity(processes: usize, names: usize, ports: usize) -> Self {
+        Self {
+            processes: ProcessTable::new(processes),
+            ports: HashMap::with_capacity_and_hasher(ports, Default::default()),
+            names: HashMap::with_capacity_and_hasher(names, Default::default()),
+        }
+    }
+
     /// Return a guard which can be used to access the process table safely
     ///
     /// Holding a guard to any of the tables prevents collection of garbage generated
@@ -67,7 +74,7 @@ impl Registry {
     ///
     /// See the note on `process_table_guard`, for notes on using guards in general.
     #[inline]
-    pub fn port_table_guard(&self) -> ProcessTableGuard<'_> {
+    pub fn port_table_guard(&self) -> PortTableGuard<'_> {
         self.ports.guard()
     }
 
@@ -75,7 +82,7 @@ impl Registry {
     ///
     /// See the note on `process_table_guard`, for notes on using guards in general.
     #[inline]
-    pub fn name_table_guard(&self) -> ProcessTableGuard<'_> {
+    pub fn name_table_guard(&self) -> NameTableGuard<'_> {
         self.names.guard()
     }
 
@@ -94,7 +101,7 @@ impl Registry {
         pid: ProcessId,
         guard: &ProcessTableGuard<'_>,
     ) -> Option<Arc<Process>> {
-        self.processes.get(&pid, guard).map(|p| p.clone())
+        self.processes.get(pid, guard)
     }
 
     /// Fetches the port associated with the given port identifier.
@@ -104,17 +111,27 @@ impl Registry {
         self.ports.get(&id, guard).map(|p| p.clone())
     }
 
+    /// Assigns a process identifier to a new process, reserving its entry in the process table.
+    ///
+    /// Returns `None` if the maximum number of simultaneously alive processes has been reached.
+    pub fn reserve_process_id(&self) -> Option<ProcessId> {
+        self.processes.reserve()
+    }
+
+    /// Releases a process identifier obtained from `reserve_process_id`, if no process was ever
+    /// registered with it.
+    pub fn release_process_id(&self, pid: ProcessId) {
+        self.processes.release(pid)
+    }
+
     /// Registers a process by its process identifier, in the process table.
     ///
     /// A process identifier is only considered valid when it is associated with a process in the process table,
     /// so before a pid is used, or a process scheduled, it must have been registered.
+    ///
+    /// This function will panic if the pid of the process was not reserved for it by `reserve_process_id`.
     pub fn register_process(&self, process: Arc<Process>, guard: &ProcessTableGuard<'_>) {
-        if let Err(err) = self.processes.try_insert(process.id(), process, guard) {
-            panic!(
-                "attempted to register a pid already in use {}",
-                err.current.id()
-            );
-        }
+        self.processes.insert(process, guard)
     }
 
     /// Unregisters a process, given its process identifier, removing it from the process table.
@@ -128,10 +145,10 @@ impl Registry {
         pid: ProcessId,
         guard: &ProcessTableGuard<'_>,
     ) -> Option<Arc<Process>> {
-        let process = self.processes.remove(&pid, guard)?;
+        let process = self.processes.remove(pid, guard)?;
         let ntg = self.names.guard();
         self.unregister_name(Registrant::Process(process.clone()), &ntg);
-        Some(process.clone())
+        Some(process)
     }
 
     /// Registers a port by its port identifier, in the port table.
@@ -174,58 +191,83 @@ impl Registry {
         }
 
         let weak = to.downgrade();
+        loop {
+            if self.names.try_insert(name, weak.clone(), guard).is_ok() {
+                return Ok(());
+            }
 
-        match self.names.try_insert(name, weak, guard) {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                // Check to see if we can change ownership of the existing registration
-                let mut success = false;
-                self.names.compute_if_present(
-                    &name,
-                    |_k, v| {
-                        match v.upgrade() {
-                            None => {
-                                // Name was previously registered, but process has since died;
-                                // change ownership of the registration to `to`.
-                                success = true;
-                                Some(err.not_inserted)
-                            }
-                            Some(_) => {
-                                // Name is registered by a process which is still alive;
-                                // keep existing ownership.
-                                Some(v.clone())
-                            }
+            // Check to see if we can change ownership of the existing registration
+            let mut present = false;
+            let mut success = false;
+            self.names.compute_if_present(
+                &name,
+                |_k, v| {
+                    present = true;
+                    match v.upgrade() {
+                        None => {
+                            // Name was previously registered, but process has since died;
+                            // change ownership of the registration to `to`.
+                            success = true;
+                            Some(weak.clone())
                         }
-                    },
-                    guard,
-                );
+                        Some(_) => {
+                            // Name is registered by a process which is still alive;
+                            // keep existing ownership.
+                            Some(v.clone())
+                        }
+                    }
+                },
+                guard,
+            );
 
-                // If that failed, we have to return an error
-                if success {
-                    Ok(())
-                } else {
-                    to.unregister_name().unwrap();
-                    Err(RegistrationError::AlreadyRegistered)
-                }
+            if success {
+                return Ok(());
+            }
+            // If the name was unregistered in the meantime, try again, otherwise it is taken
+            if present {
+                to.unregister_name().unwrap();
+                return Err(RegistrationError::AlreadyRegistered);
             }
         }
     }
 
     /// Unregisters the name registered to `registrant` in the registered names table.
+    ///
+    /// The name is only removed from the table if it is still registered to `registrant`, as it
+    /// may have been unregistered and then registered to someone else concurrently.
     pub fn unregister_name(&self, registrant: Registrant, guard: &NameTableGuard<'_>) {
         let Some(name) = registrant.registered_name() else { return; };
-        if let Some(weak) = self.names.remove(&name, guard) {
-            // This should never fail because `registrant` and `registered` should be the same process.
-            //
-            // If this does fail, it means that somehow another process/port was allowed to register a name which
-            // ostensibly belongs to `registrant`, or vice/versa. In any case, it indicates a violation of what
-            // we expect, so we want to panic if it happens.
-            assert!(registrant.eq(weak));
+        let mut removed = false;
+        self.names.compute_if_present(
+            &name,
+            |_k, v| {
+                if registrant.eq(v) {
+                    removed = true;
+                    None
+                } else {
+                    Some(v.clone())
+                }
+            },
+            guard,
+        );
+        if removed {
             // Remove the name from the registrant itself
             registrant.unregister_name().ok();
         }
     }
 
+    /// Unregisters `name` from whichever process or port it is registered to.
+    ///
+    /// Returns `false` if the name was not registered, or its registrant has since died.
+    pub fn unregister_by_name(&self, name: Atom, guard: &NameTableGuard<'_>) -> bool {
+        let Some(weak) = self.names.remove(&name, guard) else { return false; };
+        let Some(registrant) = weak.upgrade() else { return false; };
+        // Only whoever removes the entry from the table clears the name of the registrant, so it
+        // can not have been given another name in the meantime
+        registrant.unregister_name().ok();
+        true
+    }
+
     /// Returns an iterator over the registered names in this registry
     ///
     /// This iterator does not lock the registry, but it does prevent collection of garbage generated
@@ -241,6 +283,38 @@ impl Registry {
         self.names.iter(guard).map(|(k, v)| (*k, v.clone()))
     }
 
+    /// Returns an iterator over all processes in the registry
+    ///
+    /// Like [`Self::names`], this does not lock the registry, and there is no guarantee that the
+    /// iterator will see processes which are registered after it is returned.
+    pub fn processes<'g>(
+        &'g self,
+        guard: &'g ProcessTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Process>> + 'g {
+        self.processes.iter(guard)
+    }
+
+    /// Returns an iterator over all ports in the registry
+    ///
+    /// Like [`Self::names`], this does not lock the registry, and there is no guarantee that the
+    /// iterator will see ports which are registered after it is returned.
+    pub fn ports<'g>(
+        &'g self,
+        guard: &'g PortTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Port>> + 'g {
+        self.ports.values(guard).cloned()
+    }
+
+    /// Returns the number of processes in the registry
+    pub fn process_count<'g>(&'g self, _guard: &'g ProcessTableGuard<'_>) -> usize {
+        self.processes.len()
+    }
+
+    /// Returns the maximum number of simultaneously alive processes
+    pub fn process_limit(&self) -> usize {
+        self.processes.capacity()
+    }
+
     /// Returns the number of registered names in the registry
     pub fn registered_names<'g>(&'g self, _guard: &'g NameTableGuard<'_>) -> usize {
         self.names.len()
@@ -0,0 +1,257 @@
+//! This module provides a way for embedders to pause the runtime system at a safe point.
+//!
+//! Taking a consistent snapshot of process state, forking the OS process for checkpointing, or
+//! attaching a debugger all require that no Erlang code runs, and that no process state changes,
+//! for a while. [`pause`] asks every scheduler to stop at its next safe point, i.e. between
+//! processes, waits until all of them have, runs the given closure, and then resumes them.
+//!
+//! Two kinds of threads take part in a pause:
+//!
+//! * Schedulers [`register`] as participants, and call [`Participant::checkpoint`] at their safe
+//!   points. A scheduler which is about to sleep does so via [`Participant::idle`], so that a
+//!   pause does not wait for it to wake up.
+//! * Other threads which touch process state now and then, e.g. I/O threads delivering messages,
+//!   do so within a [`Region`]. A pause waits for any regions in progress to end, and entering a
+//!   region while paused blocks until the pause ends. These threads are not otherwise stopped, so
+//!   dirty schedulers, which may be in a native call for an arbitrarily long time, should only be
+//!   in a region while they actually interact with processes, or they will hold up every pause.
+//!
+//! Only one pause happens at a time. A scheduler may request a pause itself, e.g. from a BIF, in
+//! which case it counts as stopped for the duration, but requesting one from within a region, or
+//! entering a region from within another on a thread which is not a participant, will deadlock.
+use core::cell::Cell;
+use core::intrinsics::unlikely;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use firefly_system::sync::{const_mutex, Condvar, Mutex};
+
+struct State {
+    /// The number of registered participants
+    participants: usize,
+    /// The number of participants currently stopped at a safe point
+    stopped: usize,
+    /// The number of regions in progress
+    regions: usize,
+    /// True from the time a pause is requested until it ends
+    paused: bool,
+}
+
+static STATE: Mutex<State> = const_mutex(State {
+    participants: 0,
+    stopped: 0,
+    regions: 0,
+    paused: false,
+});
+/// Notified whenever `STATE` changes in a way which another thread may be waiting for
+static CHANGED: Condvar = Condvar::new();
+/// Mirrors `State::paused`, so that a checkpoint is a single load when no pause is requested
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Held for the duration of a pause, so that only one happens at a time
+static PAUSING: Mutex<()> = const_mutex(());
+
+#[thread_local]
+static IS_PARTICIPANT: Cell<bool> = Cell::new(false);
+/// True while the current participant is counted in `State::stopped`
+#[thread_local]
+static IS_STOPPED: Cell<bool> = Cell::new(false);
+
+/// The registration of the current thread as a participant in pauses, see [`register`]
+///
+/// The thread is unregistered when this is dropped.
+pub struct Participant {
+    _not_send: PhantomData<*const ()>,
+}
+impl Participant {
+    /// Stops the current thread for as long as a pause is in progress, if one has been requested
+    ///
+    /// This must only be called at a safe point, i.e. while not holding the lock of any process.
+    #[inline]
+    pub fn checkpoint(&self) {
+        if unlikely(REQUESTED.load(Ordering::Acquire)) {
+            stop(|| ());
+        }
+    }
+
+    /// Calls `f`, e.g. to sleep while there is no work, during which this thread counts as stopped
+    ///
+    /// `f` must not touch process state. If a pause is in progress when `f` returns, this waits
+    /// for it to end.
+    pub fn idle<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        stop(f)
+    }
+}
+impl Drop for Participant {
+    fn drop(&mut self) {
+        let mut state = STATE.lock();
+        state.participants -= 1;
+        IS_PARTICIPANT.set(false);
+        CHANGED.notify_all();
+    }
+}
+
+/// Registers the current thread as a scheduler which must be stopped during a pause
+///
+/// If a pause is in progress, this waits for it to end, so that a scheduler which starts while
+/// paused does not run anything until resumed.
+pub fn register() -> Participant {
+    assert!(!IS_PARTICIPANT.get(), "this thread is already a participant");
+    let mut state = STATE.lock();
+    while state.paused {
+        CHANGED.wait(&mut state);
+    }
+    state.participants += 1;
+    IS_PARTICIPANT.set(true);
+    Participant {
+        _not_send: PhantomData,
+    }
+}
+
+/// A region in which a thread other than a scheduler may touch process state, see [`enter_region`]
+///
+/// The region ends when this is dropped.
+pub struct Region {
+    counted: bool,
+    _not_send: PhantomData<*const ()>,
+}
+impl Drop for Region {
+    fn drop(&mut self) {
+        if self.counted {
+            let mut state = STATE.lock();
+            state.regions -= 1;
+            CHANGED.notify_all();
+        }
+    }
+}
+
+/// Enters a region in which the current thread may touch process state
+///
+/// If a pause is in progress, this waits for it to end. Regions should be kept short, as a pause
+/// cannot start until all regions in progress have ended.
+///
+/// A participant is already accounted for by its checkpoints, so on a scheduler thread this does
+/// nothing, which lets code shared with other threads enter a region unconditionally.
+pub fn enter_region() -> Region {
+    if IS_PARTICIPANT.get() {
+        return Region {
+            counted: false,
+            _not_send: PhantomData,
+        };
+    }
+    let mut state = STATE.lock();
+    while state.paused {
+        CHANGED.wait(&mut state);
+    }
+    state.regions += 1;
+    Region {
+        counted: true,
+        _not_send: PhantomData,
+    }
+}
+
+/// Stops every scheduler at its next safe point, then calls `f` and resumes them
+///
+/// If `f` forks the OS process, only the calling thread exists in the child, so the runtime cannot
+/// continue there, and the child should only do things like writing out a checkpoint, or `exec`.
+pub fn pause<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    if IS_PARTICIPANT.get() {
+        // This thread counts as stopped while it pauses, including while waiting for any pause
+        // requested by another thread to end first
+        stop(|| pause_world(f))
+    } else {
+        pause_world(f)
+    }
+}
+
+/// Returns true if a pause has been requested, and has not yet ended
+pub fn is_paused() -> bool {
+    REQUESTED.load(Ordering::Acquire)
+}
+
+fn pause_world<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    struct Resume;
+    impl Drop for Resume {
+        fn drop(&mut self) {
+            let mut state = STATE.lock();
+            state.paused = false;
+            REQUESTED.store(false, Ordering::Release);
+            CHANGED.notify_all();
+        }
+    }
+
+    let _pausing = PAUSING.lock();
+    let mut state = STATE.lock();
+    state.paused = true;
+    REQUESTED.store(true, Ordering::Release);
+    while state.stopped < state.participants || state.regions > 0 {
+        CHANGED.wait(&mut state);
+    }
+    drop(state);
+
+    // Resume even if `f` panics
+    let _resume = Resume;
+    f()
+}
+
+/// Calls `f` with the current participant counted as stopped, then waits for any pause to end
+fn stop<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    // A participant which pauses while idle must not be counted twice
+    if IS_STOPPED.get() {
+        return f();
+    }
+    {
+        let mut state = STATE.lock();
+        state.stopped += 1;
+        CHANGED.notify_all();
+    }
+    IS_STOPPED.set(true);
+    let result = f();
+    IS_STOPPED.set(false);
+    let mut state = STATE.lock();
+    while state.paused {
+        CHANGED.wait(&mut state);
+    }
+    state.stopped -= 1;
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pause_test() {
+        // A pause with no other participants or regions runs immediately
+        assert!(pause(is_paused));
+
+        // A participant may pause, counting itself as stopped once, even while idle
+        let participant = register();
+        participant.checkpoint();
+        assert_eq!(participant.idle(|| pause(|| STATE.lock().stopped)), 1);
+        assert_eq!(pause(|| 2), 2);
+        let region = enter_region();
+        assert_eq!(STATE.lock().regions, 0);
+        drop(region);
+        drop(participant);
+
+        // Regions may be entered again once a pause ends
+        drop(enter_region());
+        pause(|| ());
+        let region = enter_region();
+        drop(region);
+        let state = STATE.lock();
+        assert_eq!((state.participants, state.stopped, state.regions), (0, 0, 0));
+    }
+}
@@ -1,5 +1,7 @@
+mod table;
 mod wheel;
 
+pub use self::table::{cancel, time_left};
 pub use self::wheel::TimerList;
 use self::wheel::{HierarchicalTimerWheel, TimerEntry};
 
@@ -202,6 +204,9 @@ pub trait TimerService {
     /// Creates and starts a new one-shot [`Timer`] which sends `message` to `recipient` after `timeout`.
     ///
     /// The provided `timer_ref` will be used as the reference for the created timer.
+    ///
+    /// Until it fires, the timer can be read or cancelled from any thread via [`time_left`] and
+    /// [`cancel`], cancelling it via `cancel_timer` only frees its entry in this service early.
     fn send_after(
         &mut self,
         timer_ref: ReferenceId,
@@ -219,7 +224,14 @@ pub trait TimerService {
                 message,
             },
         };
-        self.start_timer(timer)
+        // Infinite timeouts are rejected by `start_timer`, and have no deadline to record
+        if !timeout.is_infinite() {
+            table::insert(timer_ref, MonotonicTime::now() + timeout);
+        }
+        self.start_timer(timer).map_err(|err| {
+            table::remove(timer_ref);
+            err
+        })
     }
 
     /// Creates a new one-shot [`Timer`] which sends `message` to `recipient` after `timeout`.
@@ -398,7 +410,7 @@ impl PerSchedulerTimerService {
 
     fn fire(&self, id: *const (), timer: Timer) {
         match timer {
-            Timer::Once { event, .. } => match event {
+            Timer::Once { id: timer_ref, event, .. } => match event {
                 TimerEvent::Timeout(weak) => {
                     if let Some(process) = weak.upgrade() {
                         trace!(target: "timers", "process timeout expired for {}", process.pid());
@@ -415,6 +427,9 @@ impl PerSchedulerTimerService {
                     trace!(target: "timers", "callback timer expired");
                     callback()
                 }
+                TimerEvent::Message { .. } if !table::remove(timer_ref) => {
+                    trace!(target: "timers", "send_after timer expired, but was already cancelled");
+                }
                 TimerEvent::Message {
                     sender,
                     recipient,
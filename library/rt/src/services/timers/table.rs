@@ -0,0 +1,109 @@
+use core::hash::{Hash, Hasher};
+
+use firefly_system::sync::{const_mutex, Mutex};
+use firefly_system::time::{Duration, MonotonicTime};
+
+use rustc_hash::FxHasher;
+
+use crate::term::ReferenceId;
+
+type HashMap<K, V> = hashbrown::HashMap<K, V, core::hash::BuildHasherDefault<FxHasher>>;
+
+/// The number of shards the table is split into, to keep contention between schedulers low
+const SHARDS: usize = 64;
+
+/// The deadlines of the timers started via [`send_after`](super::TimerService::send_after), which
+/// have neither fired nor been cancelled yet
+///
+/// Timers live in the wheel of the scheduler which started them, but a process may be reading or
+/// cancelling one of its timers from any scheduler, as processes migrate between schedulers. So
+/// the table, rather than the wheel, decides whether a timer is still pending: whichever of the
+/// scheduler firing the timer, or a process cancelling it, removes it from the table first wins.
+static TABLE: [Mutex<Option<HashMap<ReferenceId, MonotonicTime>>>; SHARDS] = {
+    const SHARD: Mutex<Option<HashMap<ReferenceId, MonotonicTime>>> = const_mutex(None);
+    [SHARD; SHARDS]
+};
+
+fn shard(id: ReferenceId) -> &'static Mutex<Option<HashMap<ReferenceId, MonotonicTime>>> {
+    let mut hasher = FxHasher::default();
+    id.hash(&mut hasher);
+    &TABLE[(hasher.finish() as usize) % SHARDS]
+}
+
+/// Records the timer `id` as pending until `deadline`
+pub(super) fn insert(id: ReferenceId, deadline: MonotonicTime) {
+    let mut shard = shard(id).lock();
+    shard.get_or_insert_with(HashMap::default).insert(id, deadline);
+}
+
+/// Removes the timer `id` from the table, returning true if it was still pending
+///
+/// This is called when the timer fires, and it must only deliver its message if this returns true.
+pub(super) fn remove(id: ReferenceId) -> bool {
+    let mut shard = shard(id).lock();
+    shard
+        .as_mut()
+        .and_then(|timers| timers.remove(&id))
+        .is_some()
+}
+
+/// Returns the time left until the timer `id` fires, or `None` if it has already fired, has been
+/// cancelled, or never existed
+pub fn time_left(id: ReferenceId) -> Option<Duration> {
+    let shard = shard(id).lock();
+    let deadline = *shard.as_ref()?.get(&id)?;
+    Some(remaining(deadline))
+}
+
+/// Cancels the timer `id`, returning the time that was left until it would have fired, or `None`
+/// if it has already fired, has already been cancelled, or never existed
+///
+/// Once this returns `Some`, the timer is guaranteed never to deliver its message, even if it is
+/// still in the wheel of another scheduler, which discards it when it expires.
+pub fn cancel(id: ReferenceId) -> Option<Duration> {
+    let mut shard = shard(id).lock();
+    let deadline = shard.as_mut()?.remove(&id)?;
+    Some(remaining(deadline))
+}
+
+fn remaining(deadline: MonotonicTime) -> Duration {
+    let now = MonotonicTime::now();
+    if deadline > now {
+        deadline - now
+    } else {
+        Duration::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scheduler::SchedulerId;
+
+    use super::*;
+
+    #[test]
+    fn timer_table_cancel_test() {
+        let timer_ref = unsafe { ReferenceId::new(SchedulerId::from_raw(0), 1) };
+        insert(timer_ref, MonotonicTime::now() + Duration::from_secs(60));
+
+        let left = time_left(timer_ref).unwrap();
+        assert!(left > Duration::from_secs(59) && left <= Duration::from_secs(60));
+
+        // Once cancelled, the timer is gone, and must not fire
+        assert!(cancel(timer_ref).is_some());
+        assert_eq!(time_left(timer_ref), None);
+        assert_eq!(cancel(timer_ref), None);
+        assert!(!remove(timer_ref));
+    }
+
+    #[test]
+    fn timer_table_fire_test() {
+        let timer_ref = unsafe { ReferenceId::new(SchedulerId::from_raw(0), 2) };
+        insert(timer_ref, MonotonicTime::now());
+        assert_eq!(time_left(timer_ref), Some(Duration::ZERO));
+
+        // Once fired, the timer can no longer be cancelled
+        assert!(remove(timer_ref));
+        assert_eq!(cancel(timer_ref), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! This module provides a way to do work at boot which the runtime system would otherwise do on
+//! first use, so that latency-sensitive services don't pay for it while serving their first
+//! requests.
+//!
+//! * Atoms which would be created at runtime, e.g. by `list_to_atom/1` when decoding requests, are
+//!   created up front, rather than by whichever request first needs each of them, while holding
+//!   the atom table lock for writing.
+//! * The process registry is created with its tables sized for the expected number of processes,
+//!   registered names and ports, rather than when the first process is spawned, with its tables
+//!   then grown as they fill up.
+//! * Memory for process heaps is faulted in, either by touching the free part of the super carrier
+//!   (see `+MMscs`), or by having the system allocator hold on to memory which has been touched.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use firefly_alloc::allocators::carriers;
+
+use crate::term::{Atom, AtomError};
+
+use super::registry::{self, ProcessTable};
+
+/// What to do in [`warmup`]
+#[derive(Debug, Clone, Default)]
+pub struct WarmupConfig {
+    /// The names of the atoms to create
+    pub atoms: Vec<String>,
+    /// The maximum number of simultaneously alive processes, or `None` for the default
+    pub processes: Option<usize>,
+    /// The number of registered names to make room for in the registry
+    pub names: usize,
+    /// The number of ports to make room for in the registry
+    pub ports: usize,
+    /// Whether to fault in the free part of the super carrier, if one is in use
+    pub prefault_super_carrier: bool,
+    /// The number of bytes of memory to fault in via the system allocator
+    pub prefault: usize,
+}
+
+/// The work done by [`warmup`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct WarmupStats {
+    /// The number of atoms which did not exist yet
+    pub atoms_created: usize,
+    /// True if the registry was created, false if it had already been used, and so was not resized
+    pub registry_created: bool,
+    /// The number of bytes of memory faulted in
+    pub prefaulted: usize,
+}
+
+/// Warms up the runtime system as described by `config`
+///
+/// This should be called at boot, before any processes are spawned, as the registry can only be
+/// sized before its first use. Returns `Err` if one of the atoms could not be created, e.g. as its
+/// name is too long, in which case any atoms before it have been created, but nothing else is done.
+pub fn warmup(config: &WarmupConfig) -> Result<WarmupStats, AtomError> {
+    let mut stats = WarmupStats::default();
+
+    for name in config.atoms.iter() {
+        if Atom::try_from_str_existing(name).is_err() {
+            Atom::try_from(name.as_str())?;
+            stats.atoms_created += 1;
+        }
+    }
+
+    let processes = config.processes.unwrap_or(ProcessTable::DEFAULT_CAPACITY);
+    stats.registry_created = registry::init_with_capacity(processes, config.names, config.ports);
+
+    if config.prefault_super_carrier {
+        stats.prefaulted += carriers::prefault_super_carrier();
+    }
+    if config.prefault > 0 {
+        stats.prefaulted += firefly_system::alloc::prefault(config.prefault);
+    }
+
+    Ok(stats)
+}
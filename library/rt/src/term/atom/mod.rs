@@ -9,9 +9,11 @@ pub mod atoms {
 mod table;
 
 pub use self::table::{
-    with_atom_table, with_atom_table_readonly, AtomData, AtomTable, GlobalAtomTable,
+    atom_limit, set_atom_limit, with_atom_table, with_atom_table_readonly, AtomData, AtomTable,
+    GlobalAtomTable, DEFAULT_ATOM_LIMIT, MIN_ATOM_LIMIT,
 };
 
+use alloc::string::String;
 use core::convert::AsRef;
 use core::fmt::{self, Debug, Display};
 use core::hash::{Hash, Hasher};
@@ -24,8 +26,8 @@ use static_assertions::assert_eq_size;
 
 use super::OpaqueTerm;
 
-/// The maximum length of an atom (255)
-pub const MAX_ATOM_LENGTH: usize = u16::max_value() as usize;
+/// The maximum length of an atom, in characters (255)
+pub const MAX_ATOM_LENGTH: usize = 255;
 
 /// Produced by operations which create atoms
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -33,6 +35,8 @@ pub enum AtomError {
     InvalidLength(usize),
     NonExistent,
     InvalidString(Utf8Error),
+    /// The atom table has reached the configured atom limit
+    TableFull(usize),
 }
 #[cfg(feature = "std")]
 impl std::error::Error for AtomError {
@@ -59,6 +63,11 @@ impl Display for AtomError {
             ),
             Self::NonExistent => f.write_str("tried to convert to an atom that doesn't exist"),
             Self::InvalidString(err) => write!(f, "invalid utf-8 bytes: {}", &err),
+            Self::TableFull(limit) => write!(
+                f,
+                "cannot create atom, the maximum number of atoms ({}) has been reached",
+                limit
+            ),
         }
     }
 }
@@ -116,11 +125,26 @@ impl firefly_bytecode::Atom for Atom {
     }
 }
 impl Atom {
-    /// Creates a new atom from a slice of bytes interpreted as Latin-1.
+    /// Creates a new atom from a slice of bytes interpreted as Latin-1, but only if the atom
+    /// already exists
     ///
     /// Returns `Err` if the atom does not exist
-    #[inline]
     pub fn try_from_latin1_bytes_existing(name: &[u8]) -> Result<Self, AtomError> {
+        if name.is_ascii() {
+            // SAFETY: ASCII is valid UTF-8
+            Self::try_from_str_existing(unsafe { str::from_utf8_unchecked(name) })
+        } else {
+            let s = name.iter().copied().map(char::from).collect::<String>();
+            Self::try_from_str_existing(s.as_str())
+        }
+    }
+
+    /// Creates a new atom from a slice of bytes interpreted as UTF-8, but only if the atom
+    /// already exists
+    ///
+    /// Returns `Err` if the bytes are not valid UTF-8, or the atom does not exist
+    #[inline]
+    pub fn try_from_utf8_bytes_existing(name: &[u8]) -> Result<Self, AtomError> {
         Self::try_from_str_existing(str::from_utf8(name)?)
     }
 
@@ -237,13 +261,34 @@ impl Atom {
         }
     }
 
+    /// Returns `Err` if `name` is too long to be an atom
+    ///
+    /// The limit is in characters rather than bytes, so a name may be up to 4 times as long in
+    /// bytes when it contains non-ASCII characters.
     pub fn validate(name: &str) -> Result<(), AtomError> {
-        let len = name.len();
+        if name.len() <= MAX_ATOM_LENGTH {
+            return Ok(());
+        }
+        let len = name.chars().count();
         if len > MAX_ATOM_LENGTH {
             return Err(AtomError::InvalidLength(len));
         }
         Ok(())
     }
+
+    /// Creates an atom from a slice of bytes interpreted as Latin-1, i.e. each byte is a
+    /// character
+    ///
+    /// Returns `Err` if the atom name is invalid or the table is full
+    pub fn try_from_latin1_bytes(name: &[u8]) -> Result<Self, AtomError> {
+        if name.is_ascii() {
+            // SAFETY: ASCII is valid UTF-8
+            Self::try_from(unsafe { str::from_utf8_unchecked(name) })
+        } else {
+            let s = name.iter().copied().map(char::from).collect::<String>();
+            Self::try_from(s.as_str())
+        }
+    }
 }
 impl From<NonNull<AtomData>> for Atom {
     #[inline]
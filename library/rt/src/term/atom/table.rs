@@ -5,14 +5,39 @@ use core::mem;
 use core::ptr::{self, NonNull};
 use core::slice;
 use core::str;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use firefly_arena::DroplessArena;
+use firefly_system::sync::lcnt::LockClass;
 use firefly_system::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use super::{Atom, AtomError};
 
 /// The atom table used by the runtime system
 static ATOMS: OnceLock<RwLock<AtomTable>> = OnceLock::new();
+static ATOMS_LOCK: LockClass = LockClass::new("atom_tab");
+
+/// The default maximum number of atoms, matching ERTS
+pub const DEFAULT_ATOM_LIMIT: usize = 1_048_576;
+
+/// The smallest atom limit which may be configured, matching ERTS
+pub const MIN_ATOM_LIMIT: usize = 8192;
+
+static ATOM_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_ATOM_LIMIT);
+
+/// Returns the maximum number of atoms which may exist at any one time
+#[inline]
+pub fn atom_limit() -> usize {
+    ATOM_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum number of atoms which may exist at any one time
+///
+/// The limit is clamped to [`MIN_ATOM_LIMIT`]. It only applies to atoms created at runtime, e.g.
+/// by `list_to_atom/1`, atoms which are part of the program are always permitted.
+pub fn set_atom_limit(limit: usize) {
+    ATOM_LIMIT.store(limit.max(MIN_ATOM_LIMIT), Ordering::Relaxed);
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct TryAtomFromTermError(pub &'static str);
@@ -116,7 +141,7 @@ where
     F: FnOnce(RwLockReadGuard<'static, AtomTable>) -> T,
 {
     let atoms = ATOMS.get_or_init(|| RwLock::new(AtomTable::default()));
-    callback(atoms.read())
+    callback(ATOMS_LOCK.read(atoms))
 }
 
 #[inline]
@@ -125,7 +150,7 @@ where
     F: FnOnce(RwLockWriteGuard<'static, AtomTable>) -> T,
 {
     let atoms = ATOMS.get_or_init(|| RwLock::new(AtomTable::default()));
-    callback(atoms.write())
+    callback(ATOMS_LOCK.write(atoms))
 }
 
 #[derive(Default)]
@@ -288,6 +313,11 @@ impl AtomTable {
     unsafe fn insert(&mut self, name: &str) -> Result<NonNull<AtomData>, AtomError> {
         use core::intrinsics::unlikely;
 
+        let limit = atom_limit();
+        if unlikely(self.ids.len() >= limit) {
+            return Err(AtomError::TableFull(limit));
+        }
+
         if unlikely(name.len() == 0) {
             let data = self.alloc_data(AtomData {
                 ptr: ptr::null_mut(),
@@ -337,3 +367,25 @@ impl AtomTable {
         NonNull::new_unchecked(ptr)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn atom_table_limit_test() {
+        set_atom_limit(MIN_ATOM_LIMIT);
+        let mut table = AtomTable::default();
+        for i in 0..MIN_ATOM_LIMIT {
+            let name = alloc::format!("atom_{}", i);
+            assert!(table.get_data_or_insert(&name).is_ok());
+        }
+        // Existing atoms can still be looked up, but no new atoms can be created
+        assert!(table.get_data_or_insert("atom_0").is_ok());
+        assert_eq!(
+            table.get_data_or_insert("one_too_many"),
+            Err(AtomError::TableFull(MIN_ATOM_LIMIT))
+        );
+        set_atom_limit(DEFAULT_ATOM_LIMIT);
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::alloc::{AllocError, Allocator};
+use alloc::sync::Arc;
+use core::ops::Deref;
+
+use firefly_binary::{BinaryEntrySpecifier, BitVec, Bitstring, Endianness};
+use firefly_number::{f16, ToPrimitive};
+
+use crate::term::{BinaryData, OpaqueTerm, Term};
+
+/// The error produced when a segment cannot be appended to a `BinaryBuilder`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryPushError {
+    /// The size given for the segment is missing or invalid for the segment type
+    InvalidSize,
+    /// The value given is not valid for the segment type, or is too small to
+    /// provide the number of bits requested
+    BadValue,
+}
+
+/// Implements the construction semantics of the bit syntax, i.e. `<<...>>` expressions.
+///
+/// Segments are appended one at a time in the order they appear in the expression, and once
+/// all segments have been pushed, `finish` is used to produce the resulting binary term. Small
+/// binaries are allocated on the given heap, while larger ones are allocated as ref-counted
+/// binaries.
+#[derive(Default)]
+pub struct BinaryBuilder {
+    buffer: BitVec,
+}
+impl BinaryBuilder {
+    /// Creates a new, empty builder
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            buffer: BitVec::new(),
+        }
+    }
+
+    /// Returns the number of bytes needed to hold the data pushed so far
+    #[inline]
+    pub fn byte_size(&self) -> usize {
+        self.buffer.byte_size()
+    }
+
+    /// Returns the number of bits pushed so far
+    #[inline]
+    pub fn bit_size(&self) -> usize {
+        self.buffer.bit_size()
+    }
+
+    /// Appends a segment described by `spec` to the binary being built.
+    ///
+    /// The `size` value is the size of the segment in units of the spec, and is required for
+    /// integer and float segments. For binary segments, `None` indicates that all of `value`
+    /// is to be appended. It is ignored for utf8/utf16/utf32 segments.
+    pub fn push(
+        &mut self,
+        spec: BinaryEntrySpecifier,
+        value: OpaqueTerm,
+        size: Option<usize>,
+    ) -> Result<(), BinaryPushError> {
+        match spec {
+            BinaryEntrySpecifier::Integer {
+                signed,
+                unit,
+                endianness,
+            } => {
+                let size = size.ok_or(BinaryPushError::InvalidSize)?;
+                self.push_integer(value, size * (unit as usize), signed, endianness)
+            }
+            BinaryEntrySpecifier::Float { unit, endianness } => {
+                let size = size.ok_or(BinaryPushError::InvalidSize)?;
+                self.push_float(value, size * (unit as usize), endianness)
+            }
+            BinaryEntrySpecifier::Binary { unit } => self.push_binary(value, size, unit),
+            BinaryEntrySpecifier::Utf8 => self.push_utf8(value),
+            BinaryEntrySpecifier::Utf16 { endianness } => self.push_utf16(value, endianness),
+            BinaryEntrySpecifier::Utf32 { endianness } => self.push_utf32(value, endianness),
+        }
+    }
+
+    /// Appends an integer segment of exactly `num_bits` bits
+    ///
+    /// Values which do not fit in `num_bits` are truncated, keeping the least-significant bits.
+    pub fn push_integer(
+        &mut self,
+        value: OpaqueTerm,
+        num_bits: usize,
+        signed: bool,
+        endianness: Endianness,
+    ) -> Result<(), BinaryPushError> {
+        match value.into() {
+            // Pushing with a size of zero has no effect
+            Term::Int(_) | Term::BigInt(_) if num_bits == 0 => (),
+            Term::Int(i) if signed => self.buffer.push_ap_number(i, num_bits, endianness),
+            Term::Int(i) => self.buffer.push_ap_number(i as u64, num_bits, endianness),
+            Term::BigInt(i) => self
+                .buffer
+                .push_ap_bigint(i.deref(), num_bits, signed, endianness),
+            _ => return Err(BinaryPushError::BadValue),
+        }
+        Ok(())
+    }
+
+    /// Appends a float segment of `num_bits` bits, which must be one of 16, 32, or 64
+    ///
+    /// Integer values are converted to floats before being appended.
+    pub fn push_float(
+        &mut self,
+        value: OpaqueTerm,
+        num_bits: usize,
+        endianness: Endianness,
+    ) -> Result<(), BinaryPushError> {
+        let f = match value.into() {
+            Term::Float(f) => f.inner(),
+            Term::Int(i) => i as f64,
+            Term::BigInt(i) => i.to_f64().ok_or(BinaryPushError::BadValue)?,
+            _ => return Err(BinaryPushError::BadValue),
+        };
+        match num_bits {
+            16 => self.buffer.push_number(f16::from_f64(f), endianness),
+            32 => self.buffer.push_number(f as f32, endianness),
+            64 => self.buffer.push_number(f, endianness),
+            _ => return Err(BinaryPushError::InvalidSize),
+        }
+        Ok(())
+    }
+
+    /// Appends a binary/bitstring segment
+    ///
+    /// When `size` is `None`, all of `value` is appended, and if `unit` is 8, `value` must be
+    /// a binary. Otherwise exactly `size * unit` bits are taken from the front of `value`.
+    pub fn push_binary(
+        &mut self,
+        value: OpaqueTerm,
+        size: Option<usize>,
+        unit: u8,
+    ) -> Result<(), BinaryPushError> {
+        let term: Term = value.into();
+        let bs = term.as_bitstring().ok_or(BinaryPushError::BadValue)?;
+        match size {
+            None if unit == 8 => {
+                if !bs.is_binary() {
+                    return Err(BinaryPushError::BadValue);
+                }
+                self.buffer.extend(bs.bytes());
+            }
+            None if bs.is_binary() => self.buffer.extend(bs.bytes()),
+            None => self.buffer.extend(bs.bits()),
+            Some(size) if unit == 8 => {
+                let selection = bs
+                    .select_bytes(size)
+                    .map_err(|_| BinaryPushError::BadValue)?;
+                self.buffer.extend(selection.bytes());
+            }
+            Some(size) => {
+                let selection = bs
+                    .select_bits(size * (unit as usize))
+                    .map_err(|_| BinaryPushError::BadValue)?;
+                self.buffer.extend(selection.bits());
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the UTF-8 encoding of the codepoint `value`
+    pub fn push_utf8(&mut self, value: OpaqueTerm) -> Result<(), BinaryPushError> {
+        let c: char = value.try_into().map_err(|_| BinaryPushError::BadValue)?;
+        self.buffer.push_utf8(c);
+        Ok(())
+    }
+
+    /// Appends the UTF-16 encoding of the codepoint `value`
+    pub fn push_utf16(
+        &mut self,
+        value: OpaqueTerm,
+        endianness: Endianness,
+    ) -> Result<(), BinaryPushError> {
+        let c: char = value.try_into().map_err(|_| BinaryPushError::BadValue)?;
+        self.buffer.push_utf16(c, endianness);
+        Ok(())
+    }
+
+    /// Appends the UTF-32 encoding of the codepoint `value`
+    pub fn push_utf32(
+        &mut self,
+        value: OpaqueTerm,
+        endianness: Endianness,
+    ) -> Result<(), BinaryPushError> {
+        let c: char = value.try_into().map_err(|_| BinaryPushError::BadValue)?;
+        self.buffer.push_utf32(c, endianness);
+        Ok(())
+    }
+
+    /// Produces a binary term containing all of the segments pushed so far
+    ///
+    /// If the result is small enough to be a heap binary, it is allocated using `alloc`, which
+    /// may fail if there is insufficient space. In that case, the builder is left untouched,
+    /// so this may be called again once space is made available.
+    pub fn finish<A: ?Sized + Allocator>(&self, alloc: &A) -> Result<Term, AllocError> {
+        let byte_size = self.buffer.byte_size();
+        let selection = self.buffer.select();
+        if byte_size <= BinaryData::MAX_HEAP_BYTES {
+            let mut bin = BinaryData::with_capacity_small(byte_size, alloc)?;
+            bin.copy_from_selection(selection);
+            Ok(Term::HeapBinary(bin))
+        } else {
+            let mut bin = BinaryData::with_capacity_large(byte_size);
+            Arc::get_mut(&mut bin)
+                .unwrap()
+                .copy_from_selection(selection);
+            Ok(Term::RcBinary(bin))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+    use firefly_binary::Binary;
+
+    use super::*;
+
+    #[test]
+    fn binary_builder_integer_and_float_segments() {
+        let heap = FixedSizeHeap::<256>::default();
+        let mut builder = BinaryBuilder::new();
+
+        // <<1:16/big, -1:8/signed, 1.0:32/float-little>>
+        builder
+            .push_integer(Term::Int(1).into(), 16, false, Endianness::Big)
+            .unwrap();
+        builder
+            .push_integer(Term::Int(-1).into(), 8, true, Endianness::Big)
+            .unwrap();
+        builder
+            .push_float(Term::Float(1.0.into()).into(), 32, Endianness::Little)
+            .unwrap();
+        assert_eq!(
+            builder.push_float(Term::Int(1).into(), 24, Endianness::Big),
+            Err(BinaryPushError::InvalidSize)
+        );
+
+        let Term::HeapBinary(bin) = builder.finish(&heap).unwrap() else {
+            panic!("expected heap binary")
+        };
+        assert_eq!(bin.as_bytes(), &[0, 1, 0xff, 0, 0, 0x80, 0x3f]);
+    }
+
+    #[test]
+    fn binary_builder_bitstring_segments() {
+        let heap = FixedSizeHeap::<256>::default();
+        let mut builder = BinaryBuilder::new();
+
+        // <<5:3, $é/utf8>>
+        builder
+            .push_integer(Term::Int(5).into(), 3, false, Endianness::Big)
+            .unwrap();
+        builder.push_utf8(Term::Int('é' as i64).into()).unwrap();
+        assert_eq!(builder.bit_size(), 19);
+
+        let result = builder.finish(&heap).unwrap();
+        let bs = result.as_bitstring().unwrap();
+        assert_eq!(bs.bit_size(), 19);
+        assert!(!bs.is_binary());
+    }
+
+    #[test]
+    fn binary_builder_large_binaries_are_refcounted() {
+        let heap = FixedSizeHeap::<256>::default();
+        let mut builder = BinaryBuilder::new();
+
+        let bytes = [0xabu8; 100];
+        let bin = BinaryData::from_bytes(&bytes);
+        let value: OpaqueTerm = Term::RcBinary(bin).into();
+        builder.push_binary(value, None, 8).unwrap();
+        builder.push_binary(value, Some(2), 8).unwrap();
+        assert_eq!(
+            builder.push_binary(value, Some(101), 8),
+            Err(BinaryPushError::BadValue)
+        );
+
+        let Term::RcBinary(result) = builder.finish(&heap).unwrap() else {
+            panic!("expected ref-counted binary")
+        };
+        assert_eq!(result.len(), 102);
+    }
+}
@@ -6,10 +6,11 @@ use core::slice;
 
 use firefly_alloc::clone::WriteCloneIntoRaw;
 use firefly_alloc::heap::Heap;
-use firefly_binary::{Bitstring, Matcher, Selection};
+use firefly_binary::{Bitstring, Endianness, Matcher, Selection};
+use firefly_number::{f16, Int, ToPrimitive};
 
 use crate::gc::Gc;
-use crate::term::{BinaryData, Boxable, Header, LayoutBuilder, OpaqueTerm, Tag, Term};
+use crate::term::{BinaryData, BitSlice, Boxable, Header, LayoutBuilder, OpaqueTerm, Tag, Term};
 
 /// This represents the structure of the result expected by generated code
 /// and produced by binary matching intrinsics, it is equivalent to a multi-value
@@ -143,7 +144,131 @@ impl MatchContext {
     pub fn bits_remaining(&self) -> usize {
         self.matcher.bit_size()
     }
+
+    /// Returns the current position of this context, which can later be returned to with `restore`
+    #[inline]
+    pub fn save(&self) -> MatchPosition {
+        MatchPosition(self.matcher.selection)
+    }
+
+    /// Resets this context to a position previously obtained from `save`
+    ///
+    /// The position must have been obtained from this context, or a clone of it.
+    #[inline]
+    pub fn restore(&mut self, position: MatchPosition) {
+        self.matcher.selection = position.0;
+    }
+
+    /// Advances the current position by `bit_size` bits, returning false if there are not enough
+    /// bits remaining, in which case the position is left unchanged.
+    pub fn skip(&mut self, bit_size: usize) -> bool {
+        self.matcher.match_bits(bit_size).is_some()
+    }
+
+    /// Matches an integer of `bit_size` bits from the current position.
+    ///
+    /// Returns `None` if there are not enough bits remaining, in which case the position is left
+    /// unchanged. Integers which are too large to be represented as a small integer are returned
+    /// as big integers, it is up to the caller to allocate them as needed.
+    pub fn get_integer(
+        &mut self,
+        bit_size: usize,
+        signed: bool,
+        endianness: Endianness,
+    ) -> Option<Int> {
+        if bit_size == 0 {
+            return Some(Int::Small(0));
+        }
+        if bit_size > 64 || (!signed && bit_size == 64) {
+            let i = self.matcher.match_bigint(bit_size, signed, endianness)?;
+            match i.to_i64() {
+                Some(i) => Some(Int::new(i)),
+                None => Some(Int::Big(i)),
+            }
+        } else if signed {
+            let i = self
+                .matcher
+                .match_ap_number::<i64, 8>(bit_size, endianness)?;
+            Some(Int::new(i))
+        } else {
+            let i = self
+                .matcher
+                .match_ap_number::<u64, 8>(bit_size, endianness)?;
+            Some(Int::new(i as i64))
+        }
+    }
+
+    /// Matches a float of `bit_size` bits, which must be one of 16, 32, or 64, from the current
+    /// position.
+    ///
+    /// Returns `None` if there are not enough bits remaining, if the bit size is invalid, or if the
+    /// matched value is not a finite float, in which case the position is left unchanged.
+    pub fn get_float(&mut self, bit_size: usize, endianness: Endianness) -> Option<f64> {
+        let position = self.save();
+        let f: f64 = match bit_size {
+            16 => self.matcher.match_number::<f16, 2>(endianness)?.into(),
+            32 => self.matcher.match_number::<f32, 4>(endianness)?.into(),
+            64 => self.matcher.match_number::<f64, 8>(endianness)?,
+            _ => return None,
+        };
+        if f.is_finite() {
+            Some(f)
+        } else {
+            self.restore(position);
+            None
+        }
+    }
+
+    /// Matches a binary/bitstring from the current position, returning it as a slice of the
+    /// data being matched, i.e. no data is copied.
+    ///
+    /// If `size` is given, exactly `size * unit` bits are matched. Otherwise, all of the remaining
+    /// data is matched, as long as its size in bits is evenly divisible by `unit`.
+    ///
+    /// Returns `None` if the match fails, in which case the position is left unchanged.
+    pub fn get_binary(&mut self, size: Option<usize>, unit: u8) -> Option<BitSlice> {
+        let selection = match size {
+            Some(size) => self.matcher.match_bits(size * unit as usize)?,
+            None if unit == 8 => self.matcher.match_binary()?,
+            None if unit <= 1 || self.bits_remaining() % (unit as usize) == 0 => {
+                self.matcher.match_any()
+            }
+            None => return None,
+        };
+        Some(BitSlice::from_selection(self.owner, selection))
+    }
+
+    /// Matches a single UTF-8 encoded codepoint from the current position
+    ///
+    /// Returns `None` if the match fails, in which case the position is left unchanged.
+    #[inline]
+    pub fn get_utf8(&mut self) -> Option<char> {
+        self.matcher.match_utf8()
+    }
+
+    /// Matches a single UTF-16 encoded codepoint from the current position
+    ///
+    /// Returns `None` if the match fails, in which case the position is left unchanged.
+    #[inline]
+    pub fn get_utf16(&mut self, endianness: Endianness) -> Option<char> {
+        self.matcher.match_utf16(endianness)
+    }
+
+    /// Matches a single UTF-32 encoded codepoint from the current position
+    ///
+    /// Returns `None` if the match fails, in which case the position is left unchanged.
+    #[inline]
+    pub fn get_utf32(&mut self, endianness: Endianness) -> Option<char> {
+        self.matcher.match_utf32(endianness)
+    }
 }
+
+/// Represents a saved position in a `MatchContext`
+///
+/// This is used to backtrack when a pattern fails to match part way through, so that the
+/// next pattern can be tried starting from the same position.
+#[derive(Copy, Clone)]
+pub struct MatchPosition(Selection<'static>);
 impl Boxable for MatchContext {
     type Metadata = ();
 
@@ -297,3 +422,42 @@ fn recreate_on_target_heap<H: ?Sized + Heap>(
         cloned.assume_init()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+
+    #[test]
+    fn match_context_get_and_restore_test() {
+        let heap = FixedSizeHeap::<256>::default();
+        let bytes = [0x01, 0x02, 0x3f, 0x80, 0x00, 0x00, b'a', 0xff];
+        let bin = BinaryData::from_small_bytes(&bytes, &heap).unwrap();
+        let mut context = MatchContext::new(bin.into(), &heap).unwrap();
+
+        assert_eq!(
+            context.get_integer(16, false, Endianness::Big),
+            Some(Int::Small(0x0102))
+        );
+        let position = context.save();
+        assert_eq!(context.get_float(32, Endianness::Big), Some(1.0));
+        assert_eq!(context.get_utf8(), Some('a'));
+        context.restore(position);
+        assert_eq!(context.bits_remaining(), 48);
+
+        assert!(context.skip(32));
+        let slice = context.get_binary(Some(1), 8).unwrap();
+        assert_eq!(slice.owner, context.owner());
+        assert!(slice.as_selection().bytes().eq(b"a".iter().copied()));
+        assert_eq!(
+            context.get_integer(8, true, Endianness::Big),
+            Some(Int::Small(-1))
+        );
+
+        // Nothing remains, so all further matches fail without advancing
+        assert!(!context.skip(1));
+        assert_eq!(context.get_utf8(), None);
+        assert_eq!(context.get_binary(Some(1), 8).map(|_| ()), None);
+    }
+}
@@ -1,7 +1,9 @@
+mod builder;
 mod matching;
 mod slice;
 
-pub use self::matching::{MatchContext, MatchResult};
+pub use self::builder::{BinaryBuilder, BinaryPushError};
+pub use self::matching::{MatchContext, MatchPosition, MatchResult};
 pub use self::slice::BitSlice;
 
 use alloc::alloc::{AllocError, Allocator, Global, Layout};
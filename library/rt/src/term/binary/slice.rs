@@ -44,11 +44,7 @@ impl BitSlice {
         let selection =
             Selection::new(data, 0, bit_offset, None, num_bits).expect("invalid selection");
 
-        Self {
-            header: Header::new(Tag::Slice, 0),
-            owner,
-            selection,
-        }
+        Self::from_selection(owner, selection)
     }
 
     /// Returns the term from which this bit slice is derived
@@ -83,8 +79,15 @@ impl BitSlice {
     }
 
     /// Create a BitSlice from an existing selection and its owning term
-    #[inline]
+    ///
+    /// If `owner` is itself a slice, the new slice will reference the slice's owner instead,
+    /// so that sub-binaries always point directly at the heap, ref-counted, or literal binary
+    /// which holds the underlying data, no matter how many times a binary is sliced.
+    ///
+    /// If the owner is a ref-counted binary, the new slice holds its own strong reference to it.
     pub fn from_selection(owner: OpaqueTerm, selection: Selection<'static>) -> Self {
+        let owner = Self::root_owner(owner);
+        owner.maybe_increment_refcount();
         Self {
             header: Header::new(Tag::Slice, 0),
             owner,
@@ -92,6 +95,20 @@ impl BitSlice {
         }
     }
 
+    /// Resolves the binary term which actually owns the data referenced by `owner`
+    ///
+    /// Slices never reference other slices (see `from_selection`), so at most one level
+    /// of indirection needs to be removed here.
+    fn root_owner(owner: OpaqueTerm) -> OpaqueTerm {
+        match owner.into() {
+            Term::RefBinary(slice) => {
+                debug_assert!(!matches!(slice.owner(), Term::RefBinary(_)));
+                slice.owner
+            }
+            _ => owner,
+        }
+    }
+
     /// Returns the selection represented by this slice
     #[inline]
     pub fn as_selection(&self) -> Selection<'static> {
@@ -199,10 +216,10 @@ impl Boxable for BitSlice {
         }
         let mut builder = LayoutBuilder::new();
         builder += Layout::new::<Self>();
-        // If the referenced data is ref-counted, and is larger than a heap binary, its better to clone
-        // the slice rather than the data. If the data is not ref-counted, or is a candidate for a heap
-        // allocated binary, clone just the data referenced by the slice
-        if !self.owner.is_literal() {
+        // If the referenced data is ref-counted or literal, we clone the slice and share the data.
+        // Otherwise the owner is a heap binary on another process heap, so we must clone just the
+        // data referenced by the slice into a new heap binary
+        if !self.owner.is_literal() && !self.owner.is_rc() {
             let byte_size = self.byte_size();
             if byte_size <= BinaryData::MAX_HEAP_BYTES {
                 builder.build_heap_binary(byte_size);
@@ -224,8 +241,9 @@ impl Boxable for BitSlice {
                 }
             }
 
+            // Cloning the slice takes a new strong reference to the owner, so the
+            // underlying data is shared rather than copied
             if self.is_owner_refcounted() {
-                self.owner.maybe_increment_refcount();
                 let mut cloned = Gc::new_uninit_in(heap).unwrap();
                 unsafe {
                     self.write_clone_into_raw(cloned.as_mut_ptr());
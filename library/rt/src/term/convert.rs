@@ -1,4 +1,5 @@
 use alloc::alloc::AllocError;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 
@@ -221,3 +222,82 @@ impl<const N: usize, T: ToTerm> ToTerm for SmallVec<[T; N]> {
         Ok(list.finish().map(Term::Cons).unwrap_or(Term::Nil))
     }
 }
+
+/// Converts a utf8-encoded binary, or a proper list of unicode codepoints, to a `String`
+///
+/// Cyclic lists are rejected rather than looping forever, so this is safe to use on terms of
+/// unknown origin.
+impl TryFrom<Term> for String {
+    type Error = ();
+
+    fn try_from(term: Term) -> Result<Self, Self::Error> {
+        if let Some(bin) = term.as_binary() {
+            if let Some(s) = bin.as_str() {
+                return Ok(s.to_string());
+            }
+            return String::from_utf8(bin.bytes().collect()).map_err(|_| ());
+        }
+        let list = term.as_list_iter().ok_or(())?.detect_cycles();
+        let mut buffer = String::new();
+        for element in list {
+            buffer.push(element.map_err(|_| ())?.as_char()?);
+        }
+        Ok(buffer)
+    }
+}
+
+/// Converts a binary, or a proper list of integers in the range `0..=255`, to a `Vec<u8>`
+///
+/// Like the conversion to `String`, cyclic lists are rejected.
+impl TryFrom<Term> for Vec<u8> {
+    type Error = ();
+
+    fn try_from(term: Term) -> Result<Self, Self::Error> {
+        if let Some(bin) = term.as_binary() {
+            if bin.is_aligned() {
+                return Ok(unsafe { bin.as_bytes_unchecked() }.to_vec());
+            }
+            return Ok(bin.bytes().collect());
+        }
+        let list = term.as_list_iter().ok_or(())?.detect_cycles();
+        let mut buffer = Vec::new();
+        for element in list {
+            let Term::Int(byte) = element.map_err(|_| ())? else { return Err(()); };
+            buffer.push(byte.try_into().map_err(|_| ())?);
+        }
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+
+    #[test]
+    fn string_conversion_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        let bin = Term::from_str_in("héllo", &heap).unwrap();
+        assert_eq!(String::try_from(bin.clone()), Ok("héllo".to_string()));
+        assert_eq!(Vec::<u8>::try_from(bin), Ok("héllo".as_bytes().to_vec()));
+
+        let charlist = Term::charlist_from_str_in("héllo", &heap).unwrap();
+        assert_eq!(String::try_from(charlist.clone()), Ok("héllo".to_string()));
+        assert_eq!(
+            Vec::<u8>::try_from(charlist),
+            Ok(alloc::vec![104, 233, 108, 108, 111])
+        );
+
+        let bytes = Term::bytelist_from_bytes_in(&[0xff, 0xfe], &heap).unwrap();
+        assert_eq!(Vec::<u8>::try_from(bytes), Ok(alloc::vec![0xff, 0xfe]));
+        let invalid = Term::from_bytes_in(&[0xff, 0xfe], &heap).unwrap();
+        assert_eq!(String::try_from(invalid), Err(()));
+
+        assert_eq!(String::try_from(Term::Nil), Ok(String::new()));
+        assert_eq!(String::try_from(Term::Int(1)), Err(()));
+        let wide = Term::charlist_from_str_in("λ", &heap).unwrap();
+        assert_eq!(Vec::<u8>::try_from(wide), Err(()));
+    }
+}
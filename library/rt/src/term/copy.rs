@@ -0,0 +1,250 @@
+//! Copying terms from one heap to another, e.g. when sending messages or spawning processes.
+//!
+//! In both modes, reference-counted binaries, and sub-binaries of them, are shared with the
+//! source term rather than copied, as are literals, and any part of the term which is already
+//! allocated on the destination heap.
+//!
+//! By default, like message passing in BEAM, a subterm which is referenced more than once in the
+//! source term is copied once for each reference. This is cheaper for the common case of terms
+//! without sharing, but a term built by repeatedly nesting a value, e.g. `T1 = {T0, T0}`,
+//! `T2 = {T1, T1}`, etc., grows exponentially when copied this way. [`CopyMode::PreserveSharing`]
+//! copies each distinct subterm once, preserving the structure of the source term.
+use alloc::alloc::{AllocError, Layout};
+use core::hash::BuildHasherDefault;
+
+use firefly_alloc::heap::{EmptyHeap, Heap};
+use rustc_hash::FxHasher;
+use smallvec::SmallVec;
+
+use super::*;
+
+type HashMap<K, V> = hashbrown::HashMap<K, V, BuildHasherDefault<FxHasher>>;
+type HashSet<K> = hashbrown::HashSet<K, BuildHasherDefault<FxHasher>>;
+
+/// How subterms referenced more than once are handled by [`copy_term`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CopyMode {
+    /// Each reference to a subterm gets its own copy
+    #[default]
+    Flat,
+    /// Each distinct subterm is copied once, and references to it in the copy are shared
+    PreserveSharing,
+}
+
+/// Calculates the layout required to copy `term` to `heap` using `mode`
+pub fn copy_layout<H: ?Sized + Heap>(term: &Term, heap: &H, mode: CopyMode) -> Layout {
+    match mode {
+        CopyMode::Flat => term.layout_excluding_heap(heap),
+        CopyMode::PreserveSharing => {
+            let mut builder = LayoutBuilder::new();
+            let mut seen = HashSet::default();
+            shared_layout(term.clone().into(), heap, &mut seen, &mut builder);
+            builder.finish()
+        }
+    }
+}
+
+/// Copies `term` to `heap` using `mode`, returning the copy
+///
+/// Returns `Err` without copying anything if `heap` does not have enough space available.
+pub fn copy_term<H: ?Sized + Heap>(
+    term: &Term,
+    heap: &H,
+    mode: CopyMode,
+) -> Result<Term, AllocError> {
+    let layout = copy_layout(term, heap, mode);
+    if heap.heap_available() < layout.size() {
+        return Err(AllocError);
+    }
+    Ok(unsafe { unsafe_copy_term(term, heap, mode) })
+}
+
+/// Like [`copy_term`], but without checking that `heap` has enough space available
+///
+/// # Safety
+///
+/// The caller must ensure `heap` has at least `copy_layout(term, heap, mode)` bytes available,
+/// otherwise this function will panic.
+pub unsafe fn unsafe_copy_term<H: ?Sized + Heap>(term: &Term, heap: &H, mode: CopyMode) -> Term {
+    match mode {
+        CopyMode::Flat => term.unsafe_clone_to_heap(heap),
+        CopyMode::PreserveSharing => {
+            let mut copier = SharingCopier {
+                heap,
+                copied: HashMap::default(),
+            };
+            copier.copy(term.clone().into()).into()
+        }
+    }
+}
+
+/// Returns true if `term` must be copied, i.e. it is neither an immediate, nor shared with the
+/// source, nor already on `heap`
+#[inline]
+fn needs_copy<H: ?Sized + Heap>(term: OpaqueTerm, heap: &H) -> bool {
+    if !term.is_box() || term.is_rc() || term.is_literal() {
+        return false;
+    }
+    !heap.contains(unsafe { term.as_ptr() }.cast_const())
+}
+
+fn shared_layout<H: ?Sized + Heap>(
+    mut term: OpaqueTerm,
+    heap: &H,
+    seen: &mut HashSet<usize>,
+    builder: &mut LayoutBuilder,
+) {
+    loop {
+        if !needs_copy(term, heap) || !seen.insert(unsafe { term.as_ptr() } as usize) {
+            return;
+        }
+        match term.into() {
+            // Walk the spine of lists iteratively, as they may be very long
+            Term::Cons(cons) => {
+                builder.build_cons();
+                shared_layout(cons.head, heap, seen, builder);
+                term = cons.tail;
+            }
+            Term::Tuple(tuple) => {
+                builder.build_tuple(tuple.len());
+                for element in tuple.as_slice().iter().copied() {
+                    shared_layout(element, heap, seen, builder);
+                }
+                return;
+            }
+            Term::Reference(_) => {
+                builder.build_reference();
+                return;
+            }
+            // Everything else is copied as a unit, see `SharingCopier::copy`
+            other => {
+                *builder += other.layout_excluding_heap(heap);
+                return;
+            }
+        }
+    }
+}
+
+struct SharingCopier<'a, H: ?Sized> {
+    heap: &'a H,
+    /// Maps the address of each subterm copied so far to its copy
+    copied: HashMap<usize, OpaqueTerm>,
+}
+impl<H: ?Sized + Heap> SharingCopier<'_, H> {
+    unsafe fn copy(&mut self, term: OpaqueTerm) -> OpaqueTerm {
+        if !needs_copy(term, self.heap) {
+            return term;
+        }
+        let addr = term.as_ptr() as usize;
+        if let Some(copy) = self.copied.get(&addr) {
+            return *copy;
+        }
+        let copy = match term.into() {
+            Term::Cons(_) => return self.copy_list(term),
+            Term::Tuple(tuple) => {
+                let elements = tuple
+                    .as_slice()
+                    .iter()
+                    .copied()
+                    .map(|element| self.copy(element))
+                    .collect::<SmallVec<[OpaqueTerm; 8]>>();
+                Tuple::from_slice(&elements, self.heap).unwrap().into()
+            }
+            // Maps, closures and the remaining boxed types are copied as a unit. Only maps and
+            // closures can contain other terms, and these are copied flat.
+            other => other.unsafe_clone_to_heap(self.heap).into(),
+        };
+        self.copied.insert(addr, copy);
+        copy
+    }
+
+    /// Copies the cells of the list starting at `list`, up to the first cell which has already
+    /// been copied, or which does not need to be copied
+    unsafe fn copy_list(&mut self, list: OpaqueTerm) -> OpaqueTerm {
+        let mut cells = SmallVec::<[(usize, OpaqueTerm); 8]>::new();
+        let mut rest = list;
+        while rest.is_nonempty_list() && needs_copy(rest, self.heap) {
+            let addr = rest.as_ptr() as usize;
+            if self.copied.contains_key(&addr) {
+                break;
+            }
+            let cons = &*(rest.as_ptr() as *const Cons);
+            cells.push((addr, self.copy(cons.head)));
+            rest = cons.tail;
+        }
+        let mut tail = self.copy(rest);
+        for (addr, head) in cells.drain(..).rev() {
+            let cell = Cons::new_in(Cons { head, tail }, self.heap).unwrap();
+            tail = cell.into();
+            self.copied.insert(addr, tail);
+        }
+        tail
+    }
+}
+
+impl Term {
+    /// Copies this term to `heap`, see [`copy_term`]
+    #[inline]
+    pub fn copy_to_heap<H: ?Sized + Heap>(
+        &self,
+        heap: &H,
+        mode: CopyMode,
+    ) -> Result<Term, AllocError> {
+        copy_term(self, heap, mode)
+    }
+
+    /// Returns the number of words this term occupies on the heap, counting each subterm which
+    /// is shared within the term only once, like `erts_debug:size/1`
+    ///
+    /// Reference-counted binaries and literals are not stored on the heap, so they only count
+    /// towards the size of a term where it contains a reference to them.
+    pub fn shared_size(&self) -> usize {
+        words(copy_layout(self, &EmptyHeap, CopyMode::PreserveSharing))
+    }
+
+    /// Returns the number of words this term would occupy on the heap if every reference to a
+    /// shared subterm were a distinct copy, like `erts_debug:flat_size/1`
+    ///
+    /// This is the size of the term once copied by a message send, see [`CopyMode::Flat`].
+    pub fn flat_size(&self) -> usize {
+        words(copy_layout(self, &EmptyHeap, CopyMode::Flat))
+    }
+}
+
+#[inline]
+fn words(layout: Layout) -> usize {
+    layout.size().div_ceil(core::mem::size_of::<OpaqueTerm>())
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+
+    #[test]
+    fn copy_term_sharing_test() {
+        let src = FixedSizeHeap::<1024>::default();
+        let mut term: OpaqueTerm = Term::Int(1).into();
+        for _ in 0..4 {
+            term = Tuple::from_slice(&[term, term], &src).unwrap().into();
+        }
+        let term: Term = term.into();
+
+        let flat = copy_layout(&term, &EmptyHeap, CopyMode::Flat);
+        let shared = copy_layout(&term, &EmptyHeap, CopyMode::PreserveSharing);
+        assert!(shared.size() < flat.size());
+        assert_eq!(term.flat_size() * 8, flat.size());
+        assert!(term.shared_size() < term.flat_size());
+
+        let dest = FixedSizeHeap::<1024>::default();
+        let copy = copy_term(&term, &dest, CopyMode::PreserveSharing).unwrap();
+        assert_eq!(copy, term);
+        let Term::Tuple(tuple) = copy else { panic!("expected tuple"); };
+        assert_eq!(tuple.as_slice()[0], tuple.as_slice()[1]);
+        assert!(dest.contains(unsafe { tuple.as_slice()[0].as_ptr() }.cast_const()));
+
+        let dest = FixedSizeHeap::<1024>::default();
+        assert_eq!(copy_term(&term, &dest, CopyMode::Flat).unwrap(), term);
+    }
+}
@@ -0,0 +1,738 @@
+//! This module implements formatting of terms in the style of `io_lib`.
+//!
+//! Terms can be formatted in two styles, corresponding to the `~w` and `~p` control sequences of
+//! `io_lib:format/2`:
+//!
+//! * [`FormatOptions::write`] formats terms on a single line, exactly as they would be written in
+//! source code, without attempting to detect strings
+//! * [`FormatOptions::print`] formats printable lists and binaries as strings, and breaks terms
+//! which do not fit within the line length across multiple lines
+//!
+//! In either style, terms may be truncated at a given depth, as with `~W`/`~P`, in which case the
+//! elided parts of the term are replaced with `...`.
+//!
+//! This is the formatter behind the `Display` implementation of [`Term`], so it is also what is
+//! used by `erlang:display/1`, crash reports, and native logger output.
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::str;
+
+use firefly_binary::Bitstring;
+use firefly_number::FloatFormat;
+
+use crate::services::opaque_ids;
+
+use super::{Cons, Map, Term, Tuple};
+
+/// The default line length used when printing terms, as used by `io_lib:format/2`
+pub const DEFAULT_LINE_LENGTH: usize = 80;
+
+/// Controls how terms are formatted, see [`write_term`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The depth at which terms are truncated, if at all, see `io_lib:write/2`
+    pub depth: Option<usize>,
+    /// The line length beyond which terms are broken across multiple lines, if at all
+    pub line_length: Option<usize>,
+    /// The column at which the term begins, which determines how much of the first line is used
+    pub column: usize,
+    /// Whether printable lists and binaries are formatted as strings
+    pub strings: bool,
+    /// Whether characters outside of Latin-1 are considered printable, as with the `t` modifier
+    pub unicode: bool,
+    /// Whether pids and references are rendered as opaque ids, if enabled, see
+    /// [`opaque_ids`](crate::services::opaque_ids)
+    pub opaque_ids: bool,
+}
+impl FormatOptions {
+    /// Options equivalent to the `~w` control sequence
+    pub const fn write() -> Self {
+        Self {
+            depth: None,
+            line_length: None,
+            column: 0,
+            strings: false,
+            unicode: false,
+            opaque_ids: false,
+        }
+    }
+
+    /// Options equivalent to the `~p` control sequence
+    pub const fn print() -> Self {
+        Self {
+            depth: None,
+            line_length: Some(DEFAULT_LINE_LENGTH),
+            column: 0,
+            strings: true,
+            unicode: false,
+            opaque_ids: false,
+        }
+    }
+
+    /// Options used by `erlang:display/1`, which formats strings like `~tp`, but never breaks lines
+    pub const fn display() -> Self {
+        Self {
+            depth: None,
+            line_length: None,
+            column: 0,
+            strings: true,
+            unicode: true,
+            opaque_ids: false,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_line_length(mut self, line_length: usize) -> Self {
+        self.line_length = Some(line_length);
+        self
+    }
+
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
+
+    pub fn with_unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    pub fn with_opaque_ids(mut self, opaque_ids: bool) -> Self {
+        self.opaque_ids = opaque_ids;
+        self
+    }
+}
+impl Default for FormatOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::print()
+    }
+}
+
+/// A term paired with the options used to format it, for use with `format!` and friends
+pub struct Formatted {
+    term: Term,
+    options: FormatOptions,
+}
+impl fmt::Display for Formatted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_term(f, self.term.clone(), &self.options)
+    }
+}
+
+/// Returns a value which formats `term` according to `options` when displayed
+#[inline]
+pub fn format_term(term: Term, options: FormatOptions) -> Formatted {
+    Formatted { term, options }
+}
+
+/// Writes `term` to `w`, formatted according to `options`
+pub fn write_term<W: Write + ?Sized>(
+    w: &mut W,
+    term: Term,
+    options: &FormatOptions,
+) -> fmt::Result {
+    let printer = Printer { options };
+    let doc = printer.term(term, options.depth);
+    printer.render(w, &doc, options.column, 0).map(|_| ())
+}
+
+pub(crate) fn display_tuple(tuple: &Tuple, f: &mut fmt::Formatter) -> fmt::Result {
+    let options = FormatOptions::display();
+    let printer = Printer { options: &options };
+    printer.write_flat(f, &printer.tuple(tuple, None))
+}
+
+pub(crate) fn display_list(cons: &Cons, f: &mut fmt::Formatter) -> fmt::Result {
+    let options = FormatOptions::display();
+    let printer = Printer { options: &options };
+    printer.write_flat(f, &printer.list(cons, None))
+}
+
+pub(crate) fn display_map(map: &Map, f: &mut fmt::Formatter) -> fmt::Result {
+    let options = FormatOptions::display();
+    let printer = Printer { options: &options };
+    printer.write_flat(f, &printer.map(map, None))
+}
+
+/// An error produced by [`format`] when the format string is invalid, or does not agree with
+/// the arguments given
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatError;
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("format string does not match arguments")
+    }
+}
+
+/// Formats `args` according to `format`, like `io_lib:format/2`
+///
+/// Only the control sequences needed by the runtime itself are supported, i.e. `~p`, `~P`, `~w`,
+/// `~W`, `~s`, `~a`, `~c`, `~b`, `~B`, `~i`, `~n` and `~~`, with an optional `t` modifier.
+/// Field widths and precisions are not supported.
+///
+/// As this is used to format log events, pids and references are rendered as opaque ids when
+/// those are enabled.
+pub fn format(format: &str, args: &[Term]) -> Result<String, FormatError> {
+    let mut out = String::with_capacity(format.len());
+    let mut args = args.iter().cloned();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            out.push(c);
+            continue;
+        }
+        let mut control = chars.next().ok_or(FormatError)?;
+        let mut unicode = false;
+        while control == 't' || control == 'l' {
+            unicode |= control == 't';
+            control = chars.next().ok_or(FormatError)?;
+        }
+        match control {
+            '~' => out.push('~'),
+            'n' => out.push('\n'),
+            'p' | 'P' | 'w' | 'W' => {
+                let term = args.next().ok_or(FormatError)?;
+                let mut options = if control == 'p' || control == 'P' {
+                    FormatOptions::print()
+                } else {
+                    FormatOptions::write()
+                };
+                if control == 'P' || control == 'W' {
+                    let Some(Term::Int(depth)) = args.next() else { return Err(FormatError); };
+                    // A negative depth means no limit
+                    if let Ok(depth) = usize::try_from(depth) {
+                        options = options.with_depth(depth);
+                    }
+                }
+                let column = out.len() - out.rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let options = options
+                    .with_unicode(unicode)
+                    .with_column(column)
+                    .with_opaque_ids(true);
+                write_term(&mut out, term, &options).map_err(|_| FormatError)?;
+            }
+            's' => match args.next().ok_or(FormatError)? {
+                Term::Nil => (),
+                Term::Atom(atom) => out.push_str(atom.as_str()),
+                Term::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+                Term::Cons(cons) => out.push_str(&cons.to_string().ok_or(FormatError)?),
+                term => {
+                    let bin = term.as_binary().ok_or(FormatError)?;
+                    match bin.as_str() {
+                        Some(s) if unicode => out.push_str(s),
+                        _ => out.extend(bin.bytes().map(char::from)),
+                    }
+                }
+            },
+            'a' => match args.next().ok_or(FormatError)? {
+                Term::Atom(atom) => out.push_str(atom.as_str()),
+                Term::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+                _ => return Err(FormatError),
+            },
+            'c' => {
+                let Some(Term::Int(c)) = args.next() else { return Err(FormatError); };
+                let c = u32::try_from(c).ok().and_then(char::from_u32);
+                out.push(c.ok_or(FormatError)?);
+            }
+            'b' | 'B' => match args.next().ok_or(FormatError)? {
+                Term::Int(i) => write!(&mut out, "{}", i).unwrap(),
+                Term::BigInt(i) => write!(&mut out, "{}", &*i).unwrap(),
+                _ => return Err(FormatError),
+            },
+            'i' => {
+                args.next().ok_or(FormatError)?;
+            }
+            _ => return Err(FormatError),
+        }
+    }
+    if args.next().is_some() {
+        return Err(FormatError);
+    }
+    Ok(out)
+}
+
+/// An intermediate representation of a formatted term, which records the width of each part of
+/// the term when formatted on a single line, so that line breaks can be placed in a single pass
+enum Doc {
+    Text(String),
+    /// A list, tuple, map or binary, with an optional improper tail
+    Seq {
+        open: &'static str,
+        items: Vec<Doc>,
+        tail: Option<Box<Doc>>,
+        close: &'static str,
+        width: usize,
+    },
+    /// A key/value pair in a map
+    Assoc {
+        key: Box<Doc>,
+        value: Box<Doc>,
+        width: usize,
+    },
+}
+impl Doc {
+    fn text<S: Into<String>>(s: S) -> Self {
+        Self::Text(s.into())
+    }
+
+    fn seq(open: &'static str, items: Vec<Doc>, tail: Option<Doc>, close: &'static str) -> Self {
+        let separators = items.len().saturating_sub(1);
+        let width = open.len()
+            + items.iter().map(Doc::width).sum::<usize>()
+            + separators
+            + tail.as_ref().map(|tail| tail.width() + 1).unwrap_or(0)
+            + close.len();
+        Self::Seq {
+            open,
+            items,
+            tail: tail.map(Box::new),
+            close,
+            width,
+        }
+    }
+
+    fn assoc(key: Doc, value: Doc) -> Self {
+        let width = key.width() + 4 + value.width();
+        Self::Assoc {
+            key: Box::new(key),
+            value: Box::new(value),
+            width,
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Self::Text(s) => s.chars().count(),
+            Self::Seq { width, .. } | Self::Assoc { width, .. } => *width,
+        }
+    }
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+}
+impl Printer<'_> {
+    fn term(&self, term: Term, depth: Option<usize>) -> Doc {
+        if depth == Some(0) {
+            return Doc::text("...");
+        }
+        if self.options.opaque_ids {
+            if let Some(id) = opaque_ids::opaque_id(&term) {
+                return Doc::text(id.to_string());
+            }
+        }
+        match term {
+            Term::None => Doc::text("NONE"),
+            Term::Catch(_) => Doc::text("CATCH"),
+            Term::Code(_) => Doc::text("CP"),
+            Term::Nil => Doc::text("[]"),
+            Term::Bool(b) => Doc::text(if b { "true" } else { "false" }),
+            Term::Atom(atom) => Doc::text(atom.to_string()),
+            Term::Int(i) => Doc::text(i.to_string()),
+            Term::BigInt(i) => Doc::text(format!("{}", &*i)),
+            Term::Float(f) => Doc::text(f.format(FloatFormat::Short).unwrap()),
+            Term::Cons(cons) => self.list(&cons, depth),
+            Term::Tuple(tuple) => self.tuple(&tuple, depth),
+            Term::Map(map) => self.map(&map, depth),
+            Term::Closure(fun) => Doc::text(format!("{}", &*fun)),
+            Term::Pid(pid) => Doc::text(format!("{}", &*pid)),
+            Term::Port(port) => Doc::text(format!("{}", &*port)),
+            Term::Reference(reference) => Doc::text(format!("{}", &*reference)),
+            Term::HeapBinary(bin) => self.bitstring(&*bin, depth),
+            Term::RcBinary(bin) => self.bitstring(&*bin, depth),
+            Term::RefBinary(bin) => self.bitstring(&*bin, depth),
+            Term::ConstantBinary(bin) => self.bitstring(bin, depth),
+        }
+    }
+
+    fn list(&self, cons: &Cons, depth: Option<usize>) -> Doc {
+        // Like `io_lib_pretty`, strings are never truncated
+        if self.options.strings {
+            if let Some(s) = self.printable_list(cons) {
+                return Doc::Text(s);
+            }
+        }
+        if depth == Some(1) {
+            return Doc::text("[...]");
+        }
+
+        // Each successive element is formatted with one less level of depth, see `io_lib:write/2`
+        let mut depth = decrement(depth);
+        let mut items = Vec::new();
+        let mut tail = None;
+        for (i, element) in cons.iter().enumerate() {
+            if i > 0 && depth == Some(1) {
+                tail = Some(Doc::text("..."));
+                break;
+            }
+            match element {
+                Ok(element) => {
+                    if i > 0 {
+                        depth = decrement(depth);
+                    }
+                    items.push(self.term(element, depth));
+                }
+                Err(improper) => {
+                    tail = Some(self.term(improper.tail, decrement(depth)));
+                }
+            }
+        }
+        Doc::seq("[", items, tail, "]")
+    }
+
+    fn tuple(&self, tuple: &Tuple, depth: Option<usize>) -> Doc {
+        if tuple.len() == 0 {
+            return Doc::text("{}");
+        }
+        if depth == Some(1) {
+            return Doc::text("{...}");
+        }
+
+        let mut depth = decrement(depth);
+        let mut items = Vec::with_capacity(tuple.len());
+        for (i, element) in tuple.as_slice().iter().enumerate() {
+            if i > 0 {
+                if depth == Some(1) {
+                    items.push(Doc::text("..."));
+                    break;
+                }
+                depth = decrement(depth);
+            }
+            items.push(self.term((*element).into(), depth));
+        }
+        Doc::seq("{", items, None, "}")
+    }
+
+    fn map(&self, map: &Map, depth: Option<usize>) -> Doc {
+        if map.size() == 0 {
+            return Doc::text("#{}");
+        }
+        if depth == Some(1) {
+            return Doc::text("#{...}");
+        }
+
+        // Unlike lists and tuples, all keys and values are formatted at the same depth, but the
+        // number of associations shown is still limited by the depth
+        let element_depth = decrement(depth);
+        let mut remaining = element_depth;
+        let mut items = Vec::with_capacity(map.size());
+        for (i, (key, value)) in map.iter().enumerate() {
+            if i > 0 {
+                if remaining == Some(1) {
+                    items.push(Doc::text("..."));
+                    break;
+                }
+                remaining = decrement(remaining);
+            }
+            let key = self.term(key, element_depth);
+            let value = self.term(value, element_depth);
+            items.push(Doc::assoc(key, value));
+        }
+        Doc::seq("#{", items, None, "}")
+    }
+
+    fn bitstring(&self, bits: &dyn Bitstring, depth: Option<usize>) -> Doc {
+        let bit_size = bits.bit_size();
+        if bit_size == 0 {
+            return Doc::text("<<>>");
+        }
+        if depth == Some(1) {
+            return Doc::text("<<...>>");
+        }
+        if self.options.strings && bits.is_binary() {
+            if let Some(s) = self.printable_binary(bits, depth) {
+                return Doc::Text(s);
+            }
+        }
+
+        let mut s = String::from("<<");
+        let mut depth = depth;
+        let mut bytes = bits.bytes();
+        let full_bytes = bit_size / 8;
+        let trailing_bits = bit_size % 8;
+        for i in 0..full_bytes {
+            if i > 0 {
+                s.push(',');
+            }
+            if depth == Some(1) {
+                s.push_str("...>>");
+                return Doc::Text(s);
+            }
+            write!(&mut s, "{}", bytes.next().unwrap()).unwrap();
+            depth = decrement(depth);
+        }
+        if trailing_bits > 0 {
+            if full_bytes > 0 {
+                s.push(',');
+            }
+            if depth == Some(1) {
+                s.push_str("...");
+            } else {
+                // The trailing bits are stored in the most significant bits of the last byte
+                let value = bytes.next().unwrap() >> (8 - trailing_bits);
+                write!(&mut s, "{}:{}", value, trailing_bits).unwrap();
+            }
+        }
+        s.push_str(">>");
+        Doc::Text(s)
+    }
+
+    /// Returns the string form of `cons` if all of its elements are printable characters
+    fn printable_list(&self, cons: &Cons) -> Option<String> {
+        let mut s = String::from("\"");
+        for element in cons.iter() {
+            let Ok(Term::Int(c)) = element else { return None; };
+            let c = u32::try_from(c).ok().and_then(char::from_u32)?;
+            if !self.is_printable(c) {
+                return None;
+            }
+            push_escaped(&mut s, c);
+        }
+        s.push('"');
+        Some(s)
+    }
+
+    /// Returns the string form of `bin` if all of its characters are printable
+    ///
+    /// When printing Unicode, binaries which are valid UTF-8 are decoded, and marked with `/utf8`
+    /// if they contain any non-ASCII characters. Otherwise, each byte is treated as a Latin-1
+    /// character.
+    fn printable_binary(&self, bin: &dyn Bitstring, depth: Option<usize>) -> Option<String> {
+        let bytes = bin.bytes().collect::<Vec<_>>();
+        let (chars, suffix): (Vec<char>, &str) = match str::from_utf8(&bytes) {
+            Ok(s) if self.options.unicode && !s.is_ascii() => (s.chars().collect(), "/utf8"),
+            _ => (bytes.iter().copied().map(char::from).collect(), ""),
+        };
+        if !chars.iter().all(|c| self.is_printable(*c)) {
+            return None;
+        }
+
+        // Printable binaries are truncated to `depth - 1` characters, similar to `io_lib_pretty`
+        let limit = depth.map(|d| d - 1).unwrap_or(usize::MAX);
+        let mut s = String::from("<<\"");
+        for c in chars.iter().take(limit) {
+            push_escaped(&mut s, *c);
+        }
+        s.push('"');
+        s.push_str(suffix);
+        if chars.len() > limit {
+            s.push_str("...");
+        }
+        s.push_str(">>");
+        Some(s)
+    }
+
+    /// See `io_lib:printable_latin1_list/1` and `io_lib:printable_unicode_list/1`
+    fn is_printable(&self, c: char) -> bool {
+        match c {
+            ' '..='~' | '\n' | '\r' | '\t' | '\u{B}' | '\u{8}' | '\u{C}' | '\u{1B}' => true,
+            '\u{A0}'..='\u{FF}' => true,
+            '\u{100}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}' => self.options.unicode,
+            _ => false,
+        }
+    }
+
+    /// Writes `doc` to `w`, starting at `column`, and breaking it across lines where it would not
+    /// otherwise fit, leaving room for `trailing` characters after it
+    ///
+    /// Returns the column at which the output ends.
+    fn render<W: Write + ?Sized>(
+        &self,
+        w: &mut W,
+        doc: &Doc,
+        column: usize,
+        trailing: usize,
+    ) -> Result<usize, fmt::Error> {
+        let fits = match self.options.line_length {
+            None => true,
+            Some(line_length) => column + doc.width() + trailing <= line_length,
+        };
+        if fits {
+            self.write_flat(w, doc)?;
+            return Ok(column + doc.width());
+        }
+
+        match doc {
+            Doc::Text(_) => {
+                self.write_flat(w, doc)?;
+                Ok(column + doc.width())
+            }
+            Doc::Seq {
+                open,
+                items,
+                tail,
+                close,
+                ..
+            } => {
+                // Elements are placed one per line, aligned with the first element
+                w.write_str(open)?;
+                let indent = column + open.len();
+                let mut column = indent;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        w.write_str(",\n")?;
+                        write_indent(w, indent)?;
+                    }
+                    let is_last = i + 1 == items.len() && tail.is_none();
+                    let item_trailing = if is_last { close.len() + trailing } else { 1 };
+                    column = self.render(w, item, indent, item_trailing)?;
+                }
+                if let Some(tail) = tail {
+                    w.write_char('|')?;
+                    column = self.render(w, tail, column + 1, close.len() + trailing)?;
+                }
+                w.write_str(close)?;
+                Ok(column + close.len())
+            }
+            Doc::Assoc { key, value, .. } => {
+                let column = self.render(w, key, column, 4)?;
+                w.write_str(" => ")?;
+                self.render(w, value, column + 4, trailing)
+            }
+        }
+    }
+
+    fn write_flat<W: Write + ?Sized>(&self, w: &mut W, doc: &Doc) -> fmt::Result {
+        match doc {
+            Doc::Text(s) => w.write_str(s),
+            Doc::Seq {
+                open,
+                items,
+                tail,
+                close,
+                ..
+            } => {
+                w.write_str(open)?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        w.write_char(',')?;
+                    }
+                    self.write_flat(w, item)?;
+                }
+                if let Some(tail) = tail {
+                    w.write_char('|')?;
+                    self.write_flat(w, tail)?;
+                }
+                w.write_str(close)
+            }
+            Doc::Assoc { key, value, .. } => {
+                self.write_flat(w, key)?;
+                w.write_str(" => ")?;
+                self.write_flat(w, value)
+            }
+        }
+    }
+}
+
+#[inline]
+fn decrement(depth: Option<usize>) -> Option<usize> {
+    depth.map(|d| d.saturating_sub(1))
+}
+
+fn write_indent<W: Write + ?Sized>(w: &mut W, indent: usize) -> fmt::Result {
+    for _ in 0..indent {
+        w.write_char(' ')?;
+    }
+    Ok(())
+}
+
+/// Appends `c` to `s` as it would appear in a string literal, see `io_lib:write_string/1`
+fn push_escaped(s: &mut String, c: char) {
+    match c {
+        '"' => s.push_str("\\\""),
+        '\\' => s.push_str("\\\\"),
+        '\n' => s.push_str("\\n"),
+        '\r' => s.push_str("\\r"),
+        '\t' => s.push_str("\\t"),
+        '\u{B}' => s.push_str("\\v"),
+        '\u{8}' => s.push_str("\\b"),
+        '\u{C}' => s.push_str("\\f"),
+        '\u{1B}' => s.push_str("\\e"),
+        c => s.push(c),
+    }
+}
+
+#[cfg(test)]
+mod golden;
+
+#[cfg(test)]
+mod test {
+    use alloc::string::ToString;
+
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::etf;
+
+    #[test]
+    fn format_term_test() {
+        let heap = FixedSizeHeap::<4096>::default();
+        let decode = |bytes: &[u8]| etf::decode(bytes, &heap).unwrap().0;
+
+        // "hi" is printed as a string by ~p, but not by ~w
+        let string = decode(&[131, 107, 0, 2, b'h', b'i']);
+        let w = format_term(string.clone(), FormatOptions::write()).to_string();
+        let p = format_term(string, FormatOptions::print()).to_string();
+        assert_eq!(w, "[104,105]");
+        assert_eq!(p, "\"hi\"");
+
+        // {ok, [1,2,3,4], 'Quoted'} truncated at depth 3
+        let tuple = decode(&[
+            131, 104, 3, 119, 2, b'o', b'k', 107, 0, 4, 1, 2, 3, 4, 119, 6, b'Q', b'u', b'o', b't',
+            b'e', b'd',
+        ]);
+        let full = format_term(tuple.clone(), FormatOptions::write()).to_string();
+        assert_eq!(full, "{ok,[1,2,3,4],'Quoted'}");
+        let truncated = format_term(tuple, FormatOptions::write().with_depth(3)).to_string();
+        assert_eq!(truncated, "{ok,[...],...}");
+
+        // [1,2,3,4] truncated at depth 3, and a binary truncated at depth 3
+        let list = decode(&[131, 107, 0, 4, 1, 2, 3, 4]);
+        let truncated = format_term(list, FormatOptions::write().with_depth(3)).to_string();
+        assert_eq!(truncated, "[1,2|...]");
+        let bin = decode(&[131, 109, 0, 0, 0, 3, 0, 1, 2]);
+        let truncated = format_term(bin, FormatOptions::print().with_depth(3)).to_string();
+        assert_eq!(truncated, "<<0,1,...>>");
+
+        // Printable binaries and floats
+        let bin = decode(&[131, 109, 0, 0, 0, 3, b'a', b'"', b'c']);
+        assert_eq!(
+            format_term(bin, FormatOptions::print()).to_string(),
+            "<<\"a\\\"c\">>"
+        );
+        assert_eq!(
+            format_term(Term::Float(1.0.into()), FormatOptions::write()).to_string(),
+            "1.0"
+        );
+        assert_eq!(
+            format_term(Term::Float(1.0e100.into()), FormatOptions::write()).to_string(),
+            "1.0e100"
+        );
+
+        // Terms which do not fit on one line are broken, with elements aligned
+        let list = decode(&[
+            131, 108, 0, 0, 0, 2, 107, 0, 4, 1, 2, 3, 4, 107, 0, 2, 5, 6, 106,
+        ]);
+        let wrapped = format_term(list, FormatOptions::write().with_line_length(12)).to_string();
+        assert_eq!(wrapped, "[[1,2,3,4],\n [5,6]]");
+    }
+
+    #[test]
+    fn format_test() {
+        let args = [Term::Atom(crate::term::atoms::Ok), Term::Int(42)];
+        assert_eq!(
+            format("result: ~p~n~w~~", &args).unwrap(),
+            "result: ok\n42~"
+        );
+        assert_eq!(format("~p", &[]), Err(FormatError));
+        assert_eq!(format("~p", &args), Err(FormatError));
+    }
+}
@@ -0,0 +1,246 @@
+//! Compares the formatting of terms against golden output generated by OTP.
+//!
+//! The golden output lives in `testdata/format.golden`, and is generated by
+//! `testdata/format_golden.escript`, which describes its format. Each case in that file must have
+//! a counterpart of the same name in [`cases`], which builds the equivalent term, and vice versa.
+//!
+//! When adding a new kind of term, or changing how terms are formatted, add a case to both, and
+//! regenerate the golden file with OTP rather than editing it by hand.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use firefly_alloc::heap::{FixedSizeHeap, Heap};
+
+use crate::etf;
+use crate::gc::Gc;
+use crate::term::*;
+
+const GOLDEN: &str = include_str!("../../../testdata/format.golden");
+
+/// A case from the golden file
+struct Golden<'a> {
+    name: &'a str,
+    /// The expected output for each style, in the order listed
+    outputs: Vec<(&'a str, String)>,
+    /// The styles in which formatting is known to differ from OTP
+    divergent: Vec<&'a str>,
+}
+
+fn parse(golden: &str) -> Vec<Golden<'_>> {
+    let mut cases: Vec<Golden<'_>> = Vec::new();
+    for line in golden.lines() {
+        if let Some(continuation) = line.strip_prefix("  ") {
+            let case = cases.last_mut().expect("continuation outside of a case");
+            let (_, output) = case
+                .outputs
+                .last_mut()
+                .expect("continuation without output");
+            output.push('\n');
+            output.push_str(continuation);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("== ") {
+            cases.push(Golden {
+                name,
+                outputs: vec![],
+                divergent: vec![],
+            });
+            continue;
+        }
+        let case = cases.last_mut().expect("output outside of a case");
+        let (style, value) = line.split_once(": ").expect("invalid golden line");
+        match style {
+            "divergent" => case.divergent.extend(value.split(' ')),
+            "w" | "p" | "tp" => case.outputs.push((style, value.to_string())),
+            other => panic!("unknown style `{}` in case `{}`", other, case.name),
+        }
+    }
+    cases
+}
+
+fn render(term: Term, style: &str) -> String {
+    match style {
+        "w" => format_term(term, FormatOptions::write()).to_string(),
+        "p" => format_term(term, FormatOptions::print()).to_string(),
+        "tp" => term.to_string(),
+        _ => unreachable!(),
+    }
+}
+
+/// Stands in for the callee of closures, which is never called
+extern "C" fn callee() {}
+
+/// Builds the term corresponding to each case in the golden file
+fn cases<H: ?Sized + Heap>(heap: &H) -> Vec<(&'static str, Term)> {
+    let atom = |name: &str| -> OpaqueTerm { Atom::str_to_term(name) };
+    let int = |i: i64| -> OpaqueTerm {
+        match Term::try_from(i) {
+            Ok(term) => term.into(),
+            Err(_) => Gc::new_in(BigInt::from(i), heap).unwrap().into(),
+        }
+    };
+    let list = |elements: &[OpaqueTerm], tail: OpaqueTerm| -> OpaqueTerm {
+        elements.iter().rev().fold(tail, |tail, head| {
+            Cons::new_in(Cons { head: *head, tail }, heap)
+                .unwrap()
+                .into()
+        })
+    };
+    let tuple = |elements: &[OpaqueTerm]| -> OpaqueTerm {
+        Tuple::from_slice(elements, heap).unwrap().into()
+    };
+    let charlist = |s: &str| -> OpaqueTerm { Term::charlist_from_str_in(s, heap).unwrap().into() };
+    let binary = |bytes: &[u8]| -> OpaqueTerm { Term::from_bytes_in(bytes, heap).unwrap().into() };
+    let float = |f: f64| -> OpaqueTerm { f.into() };
+    let nil = OpaqueTerm::NIL;
+
+    let map = Map::from_iter(
+        [(atom("a"), int(1)), (atom("b"), list(&[atom("x")], nil))].into_iter(),
+        heap,
+    )
+    .unwrap();
+    let empty_map = Map::new_in(heap).unwrap();
+    // <<1,2:3>>, the trailing bits are stored in the most significant bits of the last byte
+    let (bitstring, _) = etf::decode(&[131, 77, 0, 0, 0, 2, 3, 1, 0b0100_0000], heap).unwrap();
+    let pid = Gc::new_in(Pid::new(80, 0).unwrap(), heap).unwrap();
+    let reference = Gc::new_in(Reference::new(ReferenceId::from_raw([1, 2, 3])), heap).unwrap();
+    let fun = Closure::new_in(
+        Atom::str_to_term("lists").as_atom(),
+        Atom::str_to_term("map").as_atom(),
+        2,
+        callee as *const (),
+        &[],
+        heap,
+    )
+    .unwrap();
+
+    let cases: Vec<(&'static str, OpaqueTerm)> = vec![
+        (
+            "integers",
+            list(
+                &[int(0), int(42), int(-7), int(i64::MAX), int(i64::MIN)],
+                nil,
+            ),
+        ),
+        (
+            "floats",
+            list(
+                &[
+                    float(1.0),
+                    float(-0.5),
+                    float(0.1),
+                    float(1.0e100),
+                    float(1.0e-10),
+                    float(123456789.0),
+                ],
+                nil,
+            ),
+        ),
+        (
+            "atoms",
+            list(
+                &[
+                    atom("ok"),
+                    atom("Quoted"),
+                    atom("hello world"),
+                    atom(""),
+                    atom("true"),
+                    atom("don't"),
+                ],
+                nil,
+            ),
+        ),
+        ("latin1_atom", atom("héllo")),
+        ("unicode_atom", atom("привет")),
+        ("string", charlist("hello")),
+        ("latin1_string", charlist("héllo wörld")),
+        ("unicode_string", charlist("привет")),
+        ("escaped_string", charlist("a\"b\\c\n")),
+        ("improper_list", list(&[int(1), int(2)], int(3))),
+        (
+            "nested",
+            tuple(&[
+                atom("ok"),
+                list(
+                    &[tuple(&[atom("a"), int(1)]), tuple(&[atom("b"), nil])],
+                    nil,
+                ),
+                tuple(&[]),
+            ]),
+        ),
+        (
+            "empty",
+            tuple(&[nil, tuple(&[]), empty_map.into(), binary(b"")]),
+        ),
+        (
+            "binaries",
+            list(&[binary(b""), binary(&[1, 2, 3]), binary(b"abc")], nil),
+        ),
+        ("utf8_binary", binary("héllo".as_bytes())),
+        ("bitstring", bitstring.into()),
+        ("map", map.into()),
+        ("pid", tuple(&[atom("self"), pid.into()])),
+        ("reference", reference.into()),
+        ("export_fun", fun.into()),
+        (
+            "wrapped",
+            list(
+                &[
+                    tuple(&[atom("alpha"), charlist("first element is here")]),
+                    tuple(&[atom("beta"), charlist("second element is here")]),
+                    tuple(&[atom("gamma"), charlist("third")]),
+                ],
+                nil,
+            ),
+        ),
+    ];
+    cases
+        .into_iter()
+        .map(|(name, term)| (name, term.into()))
+        .collect()
+}
+
+#[test]
+fn format_golden_test() {
+    let heap = FixedSizeHeap::<8192>::default();
+    let golden = parse(GOLDEN);
+    let mut terms = cases(&heap);
+
+    let mut failures = Vec::new();
+    for case in golden.iter() {
+        let Some(index) = terms.iter().position(|(name, _)| *name == case.name) else {
+            failures.push(format!("{}: no term is defined for this case", case.name));
+            continue;
+        };
+        let (_, term) = terms.swap_remove(index);
+        for (style, expected) in case.outputs.iter() {
+            let actual = render(term.clone(), style);
+            if case.divergent.contains(style) {
+                if actual == *expected {
+                    failures.push(format!(
+                        "{} ({}): now matches OTP, remove it from the divergent styles",
+                        case.name, style
+                    ));
+                }
+            } else if actual != *expected {
+                failures.push(format!(
+                    "{} ({}):\n  expected: {}\n    actual: {}",
+                    case.name, style, expected, actual
+                ));
+            }
+        }
+    }
+    for (name, _) in terms.iter() {
+        failures.push(format!("{}: no golden output for this case", name));
+    }
+
+    assert!(
+        failures.is_empty(),
+        "formatting differs from golden output:\n{}",
+        failures.join("\n")
+    );
+}
@@ -2,8 +2,9 @@ use alloc::alloc::AllocError;
 use core::ptr::{self, NonNull};
 
 use firefly_alloc::fragment::HeapFragment;
+use firefly_alloc::heap::EmptyHeap;
 
-use super::{OpaqueTerm, Term, Value};
+use super::{copy_layout, unsafe_copy_term, CopyMode, OpaqueTerm, Term, Value};
 
 /// A term fragment is used for situations in which a single term needs a lifetime
 /// separate from that of any process or port, such as those associated with internal
@@ -53,6 +54,25 @@ impl TermFragment {
             })
         }
     }
+
+    /// Copies `source` into a new `TermFragment` using `mode`, see [`copy_term`](super::copy_term)
+    ///
+    /// This is how terms are copied out of a process heap, e.g. messages and spawn arguments.
+    pub fn copy_from(source: &Term, mode: CopyMode) -> Result<Self, AllocError> {
+        if source.is_immediate() || source.is_refcounted() {
+            return Ok(Self {
+                term: source.clone().into(),
+                fragment: None,
+            });
+        }
+        let layout = copy_layout(source, &EmptyHeap, mode);
+        let fragment = HeapFragment::new(layout, None)?;
+        let term = unsafe { unsafe_copy_term(source, fragment.as_ref(), mode) };
+        Ok(Self {
+            term: term.into(),
+            fragment: Some(fragment),
+        })
+    }
 }
 impl Drop for TermFragment {
     fn drop(&mut self) {
@@ -0,0 +1,400 @@
+//! This module implements the portable term hash used by `erlang:phash2/1,2`.
+//!
+//! The algorithm is the one used by ERTS (`make_hash2`), which is based on Bob Jenkins'
+//! `lookup2` mixing function, so that identical terms produce identical hashes on both BEAM and
+//! Firefly. This is relied upon by applications for things like sharding and consistent hashing,
+//! so any change here is a breaking change.
+//!
+//! There are a few cases in which the result can not match BEAM exactly, because the
+//! information hashed by ERTS has no equivalent here:
+//!
+//! * Local funs are hashed by their module, function name, and arity, rather than the fun table
+//! index and uniq value
+//! * Pids, ports and references are only meaningful within a single node, so their hashes are
+//! consistent within the runtime, but not with BEAM
+use alloc::vec::Vec;
+
+use firefly_binary::Bitstring;
+use firefly_number::Sign;
+
+use super::*;
+
+const HCONST: u32 = 0x9e3779b9;
+const HCONST_2: u32 = 0x3c6ef372;
+const HCONST_3: u32 = 0xdaa66d2b;
+const HCONST_4: u32 = 0x78dde6e4;
+const HCONST_5: u32 = 0x1715609d;
+const HCONST_6: u32 = 0xb54cda56;
+const HCONST_7: u32 = 0x5384540f;
+const HCONST_9: u32 = 0x8ff34781;
+const HCONST_10: u32 = 0x2e2ac13a;
+const HCONST_11: u32 = 0xcc623af3;
+const HCONST_12: u32 = 0x6a99b4ac;
+const HCONST_13: u32 = 0x08d12e65;
+const HCONST_14: u32 = 0xa708a81e;
+const HCONST_15: u32 = 0x454021d7;
+const HCONST_16: u32 = 0xe3779b90;
+const HCONST_19: u32 = 0xbe1e08bb;
+
+/// The hash of `[]` when it is the first term hashed, as used by ERTS
+const NIL_HASH: u32 = 3468870702;
+/// The tagged representation of `[]` in ERTS, which is hashed when `[]` is not the first term
+const NIL_DEF: u32 = 0x3b;
+
+/// Computes the full 32-bit portable hash of `term`
+///
+/// `erlang:phash2/1` returns the low 27 bits of this value, and `erlang:phash2/2` returns this
+/// value modulo the requested range.
+pub fn phash2(term: Term) -> u32 {
+    let mut hasher = Hash2::default();
+    hasher.hash(term);
+    hasher.hash
+}
+
+/// Computes the hash of the name of `atom`, as used by the ERTS atom table
+///
+/// This is the `hashpjw` algorithm, applied to the name with any two-byte UTF-8 sequences which
+/// represent Latin-1 characters folded back into a single byte. The state is 64 bits wide, as
+/// it is in ERTS on 64-bit platforms, before being truncated by the caller.
+pub fn atom_hash(atom: Atom) -> u32 {
+    let name = atom.as_str().as_bytes();
+    let mut h: u64 = 0;
+    let mut i = 0;
+    while i < name.len() {
+        let mut v = name[i];
+        i += 1;
+        if i < name.len() && (v & 0xfe) == 0xc2 && (name[i] & 0xc0) == 0x80 {
+            v = (v << 6) | (name[i] & 0x3f);
+            i += 1;
+        }
+        h = (h << 4) + v as u64;
+        let g = h & 0xf0000000;
+        if g != 0 {
+            h ^= g >> 24;
+            h ^= g;
+        }
+    }
+    h as u32
+}
+
+#[inline(always)]
+fn mix(a: &mut u32, b: &mut u32, c: &mut u32) {
+    macro_rules! step {
+        ($x:ident, $y:ident, $z:ident, $shift:expr) => {
+            *$x = $x.wrapping_sub(*$y).wrapping_sub(*$z) ^ $shift;
+        };
+    }
+    step!(a, b, c, *c >> 13);
+    step!(b, c, a, *a << 8);
+    step!(c, a, b, *b >> 13);
+    step!(a, b, c, *c >> 12);
+    step!(b, c, a, *a << 16);
+    step!(c, a, b, *b >> 5);
+    step!(a, b, c, *c >> 3);
+    step!(b, c, a, *a << 10);
+    step!(c, a, b, *b >> 15);
+}
+
+/// Hashes `bytes` with the `lookup2` block hash, starting from `initval`
+fn block_hash(bytes: &[u8], initval: u32) -> u32 {
+    let word = |k: &[u8]| u32::from_le_bytes([k[0], k[1], k[2], k[3]]);
+
+    let mut a = HCONST;
+    let mut b = HCONST;
+    let mut c = initval;
+    let mut chunks = bytes.chunks_exact(12);
+    for k in &mut chunks {
+        a = a.wrapping_add(word(&k[0..4]));
+        b = b.wrapping_add(word(&k[4..8]));
+        c = c.wrapping_add(word(&k[8..12]));
+        mix(&mut a, &mut b, &mut c);
+    }
+
+    // The remaining bytes are added to a, b and c in order, except that the first byte of c is
+    // reserved for the length
+    c = c.wrapping_add(bytes.len() as u32);
+    for (i, byte) in chunks.remainder().iter().copied().enumerate() {
+        let byte = byte as u32;
+        match i {
+            0..=3 => a = a.wrapping_add(byte << (i * 8)),
+            4..=7 => b = b.wrapping_add(byte << ((i - 4) * 8)),
+            _ => c = c.wrapping_add(byte << ((i - 7) * 8)),
+        }
+    }
+    mix(&mut a, &mut b, &mut c);
+    c
+}
+
+/// An entry on the stack of pending work in [`Hash2`]
+enum Pending {
+    Term(OpaqueTerm),
+    /// Marks the end of a key/value pair in a map
+    MapPair,
+    /// Marks the end of a map, carrying the state to restore once the pairs have been hashed
+    MapTail {
+        hash: u32,
+        xor_pairs: u32,
+    },
+}
+
+#[derive(Default)]
+struct Hash2 {
+    hash: u32,
+    xor_pairs: u32,
+    stack: Vec<Pending>,
+}
+impl Hash2 {
+    #[inline]
+    fn hash_u32_2(&mut self, x: u32, y: u32, constant: u32) {
+        let mut a = constant.wrapping_add(x);
+        let mut b = constant.wrapping_add(y);
+        mix(&mut a, &mut b, &mut self.hash);
+    }
+
+    #[inline]
+    fn hash_u32(&mut self, x: u32, constant: u32) {
+        self.hash_u32_2(x, 0, constant)
+    }
+
+    fn hash_atom(&mut self, atom: Atom) {
+        if self.hash == 0 {
+            self.hash = atom_hash(atom);
+        } else {
+            self.hash_u32(atom_hash(atom), HCONST_3);
+        }
+    }
+
+    fn hash_integer(&mut self, i: i64) {
+        // Integers which fit in 28 bits are hashed directly, everything else is hashed as the
+        // digits of a bignum, as that is how they are represented on 32-bit platforms.
+        if (-(1 << 27)..(1 << 27)).contains(&i) {
+            let y = i as i32;
+            if y < 0 {
+                // Negative numbers are mixed twice in ERTS, which we must replicate
+                self.hash_u32(y.wrapping_neg() as u32, HCONST);
+            }
+            self.hash_u32(y as u32, HCONST);
+        } else {
+            let magnitude = i.unsigned_abs();
+            let constant = if i < 0 { HCONST_10 } else { HCONST_11 };
+            self.hash_u32_2(magnitude as u32, (magnitude >> 32) as u32, constant);
+        }
+    }
+
+    fn hash_bigint(&mut self, i: &firefly_number::BigInt) {
+        let (sign, digits) = i.to_bytes_le();
+        let constant = if sign == Sign::Minus {
+            HCONST_10
+        } else {
+            HCONST_11
+        };
+        for digit in digits.chunks(8) {
+            let mut bytes = [0; 8];
+            bytes[..digit.len()].copy_from_slice(digit);
+            let digit = u64::from_le_bytes(bytes);
+            self.hash_u32_2(digit as u32, (digit >> 32) as u32, constant);
+        }
+    }
+
+    fn hash_float(&mut self, f: f64) {
+        // Positive and negative zero hash the same
+        let f = if f == 0.0 { 0.0f64 } else { f };
+        let bits = f.to_bits();
+        self.hash_u32_2((bits >> 32) as u32, bits as u32, HCONST_12);
+    }
+
+    fn hash_bitstring(&mut self, bitstring: &dyn Bitstring) {
+        let constant = HCONST_13.wrapping_add(self.hash);
+        let trailing_bits = bitstring.bit_size() % 8;
+        let mut bytes = bitstring.bytes().collect::<Vec<_>>();
+        if bytes.is_empty() {
+            self.hash = constant;
+            return;
+        }
+        let partial = if trailing_bits > 0 { bytes.pop() } else { None };
+        self.hash = block_hash(&bytes, constant);
+        if let Some(partial) = partial {
+            let bits = (partial >> (8 - trailing_bits)) as u32;
+            self.hash_u32_2(trailing_bits as u32, bits, HCONST_15);
+        }
+    }
+
+    /// Hashes the leading run of bytes in a list four at a time, then schedules whatever remains
+    fn hash_list(&mut self, mut cons: Gc<Cons>) {
+        let mut count = 0;
+        let mut packed = 0u32;
+        loop {
+            let Term::Int(byte @ 0..=255) = cons.head.into() else {
+                // The head is not a byte, so it is hashed next, followed by the tail
+                self.flush_bytes(packed, count);
+                self.stack.push(Pending::Term(cons.tail));
+                self.stack.push(Pending::Term(cons.head));
+                return;
+            };
+            packed = (packed << 8) + byte as u32;
+            if count == 3 {
+                self.hash_u32(packed, HCONST_4);
+                count = 0;
+                packed = 0;
+            } else {
+                count += 1;
+            }
+            match cons.tail.into() {
+                Term::Cons(next) => cons = next,
+                _ => {
+                    // The list ended in a run of bytes, so only the tail remains
+                    self.flush_bytes(packed, count);
+                    self.stack.push(Pending::Term(cons.tail));
+                    return;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn flush_bytes(&mut self, packed: u32, count: usize) {
+        if count > 0 {
+            self.hash_u32(packed, HCONST_4);
+        }
+    }
+
+    fn hash(&mut self, term: Term) {
+        self.stack.push(Pending::Term(term.into()));
+        while let Some(pending) = self.stack.pop() {
+            let term = match pending {
+                Pending::Term(term) => term,
+                Pending::MapPair => {
+                    self.xor_pairs ^= self.hash;
+                    self.hash = 0;
+                    continue;
+                }
+                Pending::MapTail { hash, xor_pairs } => {
+                    self.hash = hash;
+                    self.hash_u32(self.xor_pairs, HCONST_19);
+                    self.xor_pairs = xor_pairs;
+                    continue;
+                }
+            };
+
+            match term.into() {
+                Term::None | Term::Catch(_) | Term::Code(_) => {
+                    panic!("invalid term given to phash2")
+                }
+                Term::Nil => {
+                    if self.hash == 0 {
+                        self.hash = NIL_HASH;
+                    } else {
+                        self.hash_u32(NIL_DEF, HCONST_2);
+                    }
+                }
+                Term::Bool(b) => self.hash_atom(b.into()),
+                Term::Atom(a) => self.hash_atom(a),
+                Term::Int(i) => self.hash_integer(i),
+                Term::BigInt(i) => self.hash_bigint(i.inner()),
+                Term::Float(f) => self.hash_float(f.inner()),
+                Term::Cons(cons) => self.hash_list(cons),
+                Term::Tuple(tuple) => {
+                    self.hash_u32(tuple.len() as u32, HCONST_9);
+                    self.stack
+                        .extend(tuple.as_slice().iter().rev().copied().map(Pending::Term));
+                }
+                Term::Map(map) => {
+                    self.hash_u32(map.size() as u32, HCONST_16);
+                    if map.size() == 0 {
+                        continue;
+                    }
+                    // Each key/value pair is hashed independently and the results combined
+                    // with xor, so that the result is independent of iteration order
+                    self.stack.push(Pending::MapTail {
+                        hash: self.hash,
+                        xor_pairs: self.xor_pairs,
+                    });
+                    self.hash = 0;
+                    self.xor_pairs = 0;
+                    for (k, v) in map.keys().iter().zip(map.values().iter()) {
+                        self.stack.push(Pending::MapPair);
+                        self.stack.push(Pending::Term(*v));
+                        self.stack.push(Pending::Term(*k));
+                    }
+                }
+                Term::Closure(fun) => {
+                    let module = atom_hash(fun.module);
+                    if fun.is_thin() {
+                        self.hash_u32_2(fun.arity as u32, module, HCONST);
+                        self.hash_u32(atom_hash(fun.name), HCONST_14);
+                    } else {
+                        let env = fun.env();
+                        self.hash_u32_2(env.len() as u32, module, HCONST);
+                        self.hash_u32_2(atom_hash(fun.name), fun.arity as u32, HCONST);
+                        self.stack
+                            .extend(env.iter().rev().copied().map(Pending::Term));
+                    }
+                }
+                Term::Pid(pid) => self.hash_u32(pid.id().number(), HCONST_5),
+                Term::Port(port) => {
+                    let id = port.id().into_raw();
+                    self.hash_u32_2(id as u32, (id >> 32) as u32, HCONST_6);
+                }
+                Term::Reference(reference) => {
+                    self.hash_u32(reference.id().into_raw()[0], HCONST_7);
+                }
+                Term::HeapBinary(bin) => self.hash_bitstring(&bin),
+                Term::RcBinary(bin) => self.hash_bitstring(&bin),
+                Term::RefBinary(slice) => self.hash_bitstring(&slice),
+                Term::ConstantBinary(bin) => self.hash_bitstring(bin),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::etf;
+
+    fn decode(bytes: &[u8], heap: &FixedSizeHeap<1024>) -> Term {
+        etf::decode(bytes, heap).unwrap().0
+    }
+
+    #[test]
+    fn phash2_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        assert_eq!(phash2(Term::Nil), NIL_HASH);
+        assert_eq!(
+            phash2(Term::Float(0.0.into())),
+            phash2(Term::Float((-0.0).into()))
+        );
+        assert_ne!(phash2(Term::Int(1)), phash2(Term::Int(-1)));
+        // Integers are hashed by value, regardless of representation
+        let big = decode(&[131, 110, 8, 0, 0, 0, 0, 0, 0, 0, 0, 1], &heap);
+        assert_eq!(phash2(big), phash2(Term::Int(1 << 56)));
+
+        // "abc" and <<"abc">>
+        let string = decode(&[131, 107, 0, 3, b'a', b'b', b'c'], &heap);
+        let binary = decode(&[131, 109, 0, 0, 0, 3, b'a', b'b', b'c'], &heap);
+        assert_ne!(phash2(string), phash2(binary));
+
+        // {a, b} and {b, a}
+        let ab = decode(&[131, 104, 2, 119, 1, b'a', 119, 1, b'b'], &heap);
+        let ba = decode(&[131, 104, 2, 119, 1, b'b', 119, 1, b'a'], &heap);
+        assert_ne!(phash2(ab), phash2(ba));
+
+        // #{a => 1, b => 2}, with the pairs in either order
+        let map1 = decode(
+            &[
+                131, 116, 0, 0, 0, 2, 119, 1, b'a', 97, 1, 119, 1, b'b', 97, 2,
+            ],
+            &heap,
+        );
+        let map2 = decode(
+            &[
+                131, 116, 0, 0, 0, 2, 119, 1, b'b', 97, 2, 119, 1, b'a', 97, 1,
+            ],
+            &heap,
+        );
+        assert_eq!(phash2(map1), phash2(map2));
+    }
+}
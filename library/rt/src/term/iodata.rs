@@ -0,0 +1,191 @@
+//! Traversal of iodata, i.e. binaries, or possibly deep lists of bytes and binaries, which may have
+//! a binary as their tail.
+//!
+//! Converting iodata to a contiguous buffer is done in two passes: the first validates the term and
+//! calculates the size of the result, and the second writes the data into a buffer allocated up
+//! front with that size. Both passes are iterative, so deeply nested lists cannot overflow the
+//! stack, and neither allocates intermediate buffers.
+use alloc::alloc::AllocError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use firefly_alloc::heap::Heap;
+use firefly_binary::{BinaryFlags, Bitstring, Encoding};
+use smallvec::SmallVec;
+
+use super::{BinaryData, Term};
+
+/// A byte or binary found in iodata
+enum Chunk<'a> {
+    Byte(u8),
+    Binary(&'a dyn Bitstring),
+}
+
+/// Calls `f` with each byte and binary of `iodata`, in order
+///
+/// Returns `Err` if `iodata` is not valid iodata, in which case `f` may have been called with some
+/// of its contents.
+fn visit<F>(iodata: &Term, mut f: F) -> Result<(), ()>
+where
+    F: FnMut(Chunk<'_>),
+{
+    if let Some(bin) = iodata.as_binary() {
+        f(Chunk::Binary(bin));
+        return Ok(());
+    }
+
+    // The tails of the lists we have descended from
+    let mut tails = SmallVec::<[Term; 8]>::new();
+    let mut list = iodata.clone();
+    loop {
+        match list {
+            Term::Nil => match tails.pop() {
+                Some(tail) => list = tail,
+                None => return Ok(()),
+            },
+            Term::Cons(cons) => {
+                let tail = cons.tail();
+                match cons.head() {
+                    Term::Int(i) if (0..256).contains(&i) => f(Chunk::Byte(i as u8)),
+                    Term::Nil => (),
+                    head @ Term::Cons(_) => {
+                        tails.push(tail);
+                        list = head;
+                        continue;
+                    }
+                    head => f(Chunk::Binary(head.as_binary().ok_or(())?)),
+                }
+                list = tail;
+            }
+            // Only a binary is permitted as the tail of an improper list
+            tail => {
+                f(Chunk::Binary(tail.as_binary().ok_or(())?));
+                list = Term::Nil;
+            }
+        }
+    }
+}
+
+/// Returns the number of bytes in `iodata`, or `Err` if it is not valid iodata
+pub fn iodata_size(iodata: &Term) -> Result<usize, ()> {
+    let mut size = 0usize;
+    visit(iodata, |chunk| match chunk {
+        Chunk::Byte(_) => size += 1,
+        Chunk::Binary(bin) => size += bin.byte_size(),
+    })?;
+    Ok(size)
+}
+
+/// Writes the contents of `iodata` into `buf`, which must be exactly `iodata_size(iodata)` bytes
+///
+/// Returns `Err` if `iodata` is not valid iodata, or `buf` is the wrong size.
+pub fn write_iodata(iodata: &Term, buf: &mut [u8]) -> Result<(), ()> {
+    let mut pos = 0;
+    let mut overflow = false;
+    visit(iodata, |chunk| {
+        let len = match chunk {
+            Chunk::Byte(_) => 1,
+            Chunk::Binary(bin) => bin.byte_size(),
+        };
+        let Some(dst) = buf.get_mut(pos..(pos + len)) else { overflow = true; return; };
+        match chunk {
+            Chunk::Byte(byte) => dst[0] = byte,
+            Chunk::Binary(bin) if bin.is_aligned() => {
+                dst.copy_from_slice(unsafe { bin.as_bytes_unchecked() })
+            }
+            Chunk::Binary(bin) => {
+                for (dst, byte) in dst.iter_mut().zip(bin.bytes()) {
+                    *dst = byte;
+                }
+            }
+        }
+        pos += len;
+    })?;
+    if overflow || pos != buf.len() {
+        return Err(());
+    }
+    Ok(())
+}
+
+/// Flattens `iodata` into a vector of bytes
+pub fn iodata_to_vec(iodata: &Term) -> Result<Vec<u8>, ()> {
+    let size = iodata_size(iodata)?;
+    let mut bytes = alloc::vec![0; size];
+    write_iodata(iodata, bytes.as_mut_slice())?;
+    Ok(bytes)
+}
+
+/// Flattens `iodata` into a new binary of `size` bytes, as calculated by `iodata_size`
+///
+/// Binaries small enough to be heap binaries are allocated on `heap`, so callers must ensure it
+/// has enough space for a binary of `size` bytes, see `LayoutBuilder::build_binary`.
+pub fn iodata_to_binary<H: ?Sized + Heap>(
+    iodata: &Term,
+    size: usize,
+    heap: &H,
+) -> Result<Term, AllocError> {
+    if size > BinaryData::MAX_HEAP_BYTES {
+        let mut bin = BinaryData::with_capacity_large(size);
+        let data = Arc::get_mut(&mut bin).unwrap();
+        write_iodata(iodata, &mut data[..]).unwrap();
+        set_encoding(data);
+        Ok(Term::RcBinary(bin))
+    } else {
+        let mut bin = BinaryData::with_capacity_small(size, heap)?;
+        write_iodata(iodata, &mut bin[..]).unwrap();
+        set_encoding(&mut bin);
+        Ok(Term::HeapBinary(bin))
+    }
+}
+
+fn set_encoding(bin: &mut BinaryData) {
+    let encoding = Encoding::detect(&bin[..]);
+    unsafe {
+        bin.set_flags(BinaryFlags::new(bin.len(), encoding));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use crate::term::Cons;
+
+    use super::*;
+
+    #[test]
+    fn iodata_flatten_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+
+        let hello = Term::from_str_in("hello", &heap).unwrap();
+        let world = Term::from_str_in("world", &heap).unwrap();
+        // [$<, [hello, [], $\s] | world]
+        let inner = Cons::new_in(Cons::cons(Term::Int(32), Term::Nil), &heap).unwrap();
+        let inner = Cons::new_in(Cons::cons(Term::Nil, Term::Cons(inner)), &heap).unwrap();
+        let inner = Cons::new_in(Cons::cons(hello, Term::Cons(inner)), &heap).unwrap();
+        let outer = Cons::new_in(Cons::cons(Term::Cons(inner), world.clone()), &heap).unwrap();
+        let outer = Cons::new_in(Cons::cons(Term::Int(60), Term::Cons(outer)), &heap).unwrap();
+        let iodata = Term::Cons(outer);
+
+        assert_eq!(iodata_size(&iodata), Ok(12));
+        assert_eq!(iodata_to_vec(&iodata).as_deref(), Ok(&b"<hello world"[..]));
+        let bin = iodata_to_binary(&iodata, 12, &heap).unwrap();
+        assert_eq!(Vec::<u8>::try_from(bin), Ok(b"<hello world".to_vec()));
+
+        assert_eq!(iodata_to_vec(&world).as_deref(), Ok(&b"world"[..]));
+        assert_eq!(iodata_size(&Term::Nil), Ok(0));
+
+        // An integer tail, or a byte out of range, are invalid
+        let bad_tail = Cons::new_in(Cons::cons(Term::Int(1), Term::Int(2)), &heap).unwrap();
+        assert_eq!(iodata_size(&Term::Cons(bad_tail)), Err(()));
+        let bad_byte = Cons::new_in(Cons::cons(Term::Int(256), Term::Nil), &heap).unwrap();
+        assert_eq!(iodata_size(&Term::Cons(bad_byte)), Err(()));
+        let nested = Cons::new_in(Cons::cons(Term::Cons(bad_tail), Term::Nil), &heap).unwrap();
+        assert_eq!(iodata_size(&Term::Cons(nested)), Err(()));
+        assert_eq!(iodata_size(&Term::Int(1)), Err(()));
+        // Nested lists may also have binary tails
+        let good_tail = Cons::new_in(Cons::cons(Term::Int(1), world), &heap).unwrap();
+        let nested = Cons::new_in(Cons::cons(Term::Cons(good_tail), Term::Nil), &heap).unwrap();
+        assert_eq!(iodata_size(&Term::Cons(nested)), Ok(6));
+    }
+}
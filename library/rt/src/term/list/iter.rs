@@ -146,3 +146,150 @@ impl Iterator for RawIter<'_> {
         }
     }
 }
+
+/// An iterator over the elements of a list term, see [`Term::as_list_iter`]
+///
+/// Each element is yielded as `Ok`, and if the list is improper, its tail is yielded last as
+/// `Err`, after which the iterator is exhausted.
+///
+/// Cyclic lists can only be constructed by unsafe code, but when handling terms of unknown origin,
+/// [`ListIter::detect_cycles`] can be used to guarantee that iteration terminates. When a cycle is
+/// detected, the cell at which it was detected is yielded as the tail of an improper list, since a
+/// cyclic list never ends in `[]`.
+pub struct ListIter<'a> {
+    /// The remainder of the list, i.e. `[]`, a cons cell, or the tail of an improper list
+    rest: OpaqueTerm,
+    cycles: Option<CycleDetector>,
+    _marker: PhantomData<&'a Cons>,
+}
+impl ListIter<'_> {
+    pub(crate) fn new(list: OpaqueTerm) -> Self {
+        Self {
+            rest: list,
+            cycles: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Enables detection of cyclic lists
+    pub fn detect_cycles(mut self) -> Self {
+        self.cycles = Some(CycleDetector::default());
+        self
+    }
+}
+
+impl core::iter::FusedIterator for ListIter<'_> {}
+
+impl Iterator for ListIter<'_> {
+    type Item = Result<Term, ImproperList>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest;
+        if rest.is_nil() {
+            return None;
+        }
+        // We're done after this, regardless of the outcome
+        self.rest = OpaqueTerm::NIL;
+        if !rest.is_nonempty_list() {
+            return Some(Err(ImproperList { tail: rest.into() }));
+        }
+        if let Some(cycles) = self.cycles.as_mut() {
+            if cycles.is_cycle(rest) {
+                return Some(Err(ImproperList { tail: rest.into() }));
+            }
+        }
+        let cons = unsafe { &*(rest.as_ptr() as *const Cons) };
+        self.rest = cons.tail;
+        Some(Ok(cons.head()))
+    }
+}
+
+/// Detects cycles in a sequence of cons cells using Brent's algorithm, which requires constant
+/// space, and visits each cell at most a small constant number of times
+struct CycleDetector {
+    tortoise: OpaqueTerm,
+    power: usize,
+    steps: usize,
+}
+impl Default for CycleDetector {
+    fn default() -> Self {
+        Self {
+            tortoise: OpaqueTerm::NONE,
+            power: 1,
+            steps: 1,
+        }
+    }
+}
+impl CycleDetector {
+    /// Advances to `cell`, returning true if it has been visited before
+    fn is_cycle(&mut self, cell: OpaqueTerm) -> bool {
+        if cell == self.tortoise {
+            return true;
+        }
+        if self.steps == self.power {
+            self.tortoise = cell;
+            self.power *= 2;
+            self.steps = 0;
+        }
+        self.steps += 1;
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+    use crate::gc::Gc;
+    use crate::term::atoms;
+
+    #[test]
+    fn list_iter_test() {
+        let heap = FixedSizeHeap::<1024>::default();
+        assert_eq!(Term::Nil.as_list_iter().unwrap().count(), 0);
+        assert!(Term::Int(1).as_list_iter().is_none());
+
+        let list = Cons::from_slice(&[Term::Int(1).into(), Term::Int(2).into()], &heap)
+            .unwrap()
+            .unwrap();
+        let items = Term::Cons(list).as_list_iter().unwrap().collect::<Vec<_>>();
+        assert_eq!(items, [Ok(Term::Int(1)), Ok(Term::Int(2))]);
+
+        // [1 | ok]
+        let improper =
+            Cons::new_in(Cons::cons(Term::Int(1), Term::Atom(atoms::Ok)), &heap).unwrap();
+        let items = Term::Cons(improper)
+            .as_list_iter()
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                Ok(Term::Int(1)),
+                Err(ImproperList {
+                    tail: Term::Atom(atoms::Ok)
+                })
+            ]
+        );
+
+        // A list whose last cell points back to its second cell
+        let cells = (0..5)
+            .map(|i| Cons::new_in(Cons::cons(Term::Int(i), Term::Nil), &heap).unwrap())
+            .collect::<Vec<Gc<Cons>>>();
+        for (i, cell) in cells.iter().enumerate() {
+            let next = cells.get(i + 1).unwrap_or(&cells[1]);
+            unsafe {
+                (*(Gc::as_ptr(cell) as *mut Cons)).tail = (*next).into();
+            }
+        }
+        let mut iter = Term::Cons(cells[0]).as_list_iter().unwrap().detect_cycles();
+        let items = iter.by_ref().take(100).collect::<Vec<_>>();
+        assert!(items.len() < 100);
+        assert!(items.last().unwrap().is_err());
+        assert!(items[..(items.len() - 1)].iter().all(|item| item.is_ok()));
+        assert_eq!(iter.next(), None);
+    }
+}
@@ -5,6 +5,7 @@ pub mod iter;
 
 pub use self::builder::ListBuilder;
 pub use self::charlist::CharlistToBinaryError;
+pub use self::iter::ListIter;
 
 use alloc::alloc::{AllocError, Allocator};
 use core::fmt;
@@ -250,37 +251,8 @@ impl fmt::Debug for Cons {
     }
 }
 impl fmt::Display for Cons {
+    #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use core::fmt::Write;
-
-        // See https://github.com/erlang/otp/blob/b8e11b6abe73b5f6306e8833511fcffdb9d252b5/erts/emulator/beam/erl_printf_term.c#L423-443
-        if self.is_printable_string() {
-            f.write_char('\"')?;
-
-            for result in self.iter() {
-                // `is_printable_string` guarantees all Ok
-                let element = result.unwrap();
-                match element.try_into().unwrap() {
-                    '\n' => f.write_str("\\\n")?,
-                    '\"' => f.write_str("\\\"")?,
-                    c => f.write_char(c)?,
-                }
-            }
-
-            f.write_char('\"')
-        } else {
-            f.write_char('[')?;
-
-            for (i, value) in self.iter().enumerate() {
-                match value {
-                    Ok(value) if i > 0 => write!(f, ", {}", value)?,
-                    Ok(value) => write!(f, "{}", value)?,
-                    Err(improper) if i > 0 => write!(f, " | {}", improper)?,
-                    Err(improper) => write!(f, "{}", improper)?,
-                }
-            }
-
-            f.write_char(']')
-        }
+        crate::term::format::display_list(self, f)
     }
 }
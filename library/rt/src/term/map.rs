@@ -116,7 +116,7 @@ impl SmallMap {
             boxed.bitmap = 0;
             boxed.header = Header::new(
                 Tag::Map,
-                MapFlags(MapFlags::FLATMAP | (capacity << 2)).pack(),
+                MapFlags::flatmap(capacity).pack(),
             );
             boxed
         }
@@ -140,7 +140,7 @@ impl SmallMap {
         boxed.bitmap = 0;
         boxed.header = Header::new(
             Tag::Map,
-            MapFlags(MapFlags::FLATMAP | (capacity << 2)).pack(),
+            MapFlags::flatmap(capacity).pack(),
         );
         Ok(boxed)
     }
@@ -233,7 +233,7 @@ impl SmallMap {
         pairs: &[(OpaqueTerm, OpaqueTerm)],
         alloc: &A,
     ) -> Result<Gc<Self>, MapError> {
-        debug_assert!(pairs.is_sorted());
+        debug_assert!(pairs.is_sorted_by(|(a, _), (b, _)| Some(compare_keys(*a, *b))));
         let capacity = pairs.len();
         if capacity > SMALL_MAP_LIMIT {
             return Err(MapError::SizeLimit);
@@ -268,6 +268,26 @@ impl SmallMap {
         self.size() == 0
     }
 
+    /// Returns true if this map lives in a literal area, e.g. as a map literal of a loaded module
+    ///
+    /// A literal map, like everything it references, is never moved or freed, so the garbage
+    /// collector leaves it where it is, and it must never be modified in place.
+    #[inline]
+    pub fn is_literal(&self) -> bool {
+        self.metadata().is_literal()
+    }
+
+    /// Marks this map as a literal, see [`is_literal`](Self::is_literal)
+    ///
+    /// # Safety
+    ///
+    /// This map, and everything it references, must live for the rest of the program, and must
+    /// not be modified again.
+    pub unsafe fn mark_literal(&mut self) {
+        let flags = MapFlags(self.metadata().pack() | MapFlags::LITERAL);
+        self.header.set_arity(flags.pack());
+    }
+
     /// Produces an iterator which traverses each key/value pair in this map
     #[inline]
     pub fn iter(&self) -> SmallMapIter<'_> {
@@ -333,6 +353,7 @@ impl SmallMap {
     }
 
     fn do_put_mut(&mut self, key: OpaqueTerm, value: OpaqueTerm) {
+        debug_assert!(!self.is_literal(), "attempted to modify a literal map");
         use core::cmp::Ordering;
 
         let key = key.into();
@@ -658,6 +679,7 @@ impl SmallMap {
     }
 
     fn do_take_mut(&mut self, key: OpaqueTerm) -> Option<OpaqueTerm> {
+        debug_assert!(!self.is_literal(), "attempted to modify a literal map");
         let size = self.size();
         let capacity = self.capacity();
         if size == 0 {
@@ -687,11 +709,21 @@ impl SmallMap {
 
     /// Merges two maps, producing a new map containing the union of all keys, preferring the values
     /// from `map2` when a conflicting key is present in both maps.
+    ///
+    /// No new map is allocated when the result would be identical to one of the inputs, e.g. when
+    /// `map2` only overrides keys of `self` with the values they already have, or when `map2`
+    /// contains every key of `self`. When the keys of one map all sort before those of the other,
+    /// e.g. when adding fields to a record-like map, the maps are concatenated without comparing
+    /// each pair of keys.
+    ///
+    /// Returns `Err(MapError::SizeLimit)` if the union has more than `SMALL_MAP_LIMIT` keys.
     pub fn merge<A: ?Sized + Allocator>(
         self: Gc<Self>,
         map2: &Gc<Self>,
         alloc: &A,
-    ) -> Result<Gc<Self>, AllocError> {
+    ) -> Result<Gc<Self>, MapError> {
+        use core::cmp::Ordering;
+
         // In the unlikely case that the two maps are the same map, just clone one of them
         if unlikely(Gc::as_ptr(&self) == Gc::as_ptr(map2)) {
             return Ok(self);
@@ -708,23 +740,35 @@ impl SmallMap {
             return Ok(self.clone());
         }
 
-        // Allocate a temporary buffer on the stack while we determine the size of the final
-        // map and its key ordering. The alternative is to
-        let new_capacity = n1 + n2;
-        let mut buffer = [OpaqueTerm::NONE; SMALL_MAP_LIMIT * 2];
-        let new_kv = &mut buffer[..(new_capacity * 2)];
-
         let keys1 = self.keys();
         let keys2 = map2.keys();
         let values1 = self.values();
         let values2 = map2.values();
+
+        // If the key ranges are disjoint, the result is one map followed by the other
+        if compare_keys(keys1[n1 - 1], keys2[0]) == Ordering::Less {
+            return Self::concat(&self, map2, alloc);
+        }
+        if compare_keys(keys2[n2 - 1], keys1[0]) == Ordering::Less {
+            return Self::concat(map2, &self, alloc);
+        }
+
+        // Allocate a temporary buffer on the stack while we determine the size of the final
+        // map and its key ordering, as we don't know how many keys the maps have in common. It
+        // must hold the keys and values of both maps, as the union may exceed the size limit
+        let new_capacity = n1 + n2;
+        let mut buffer = [OpaqueTerm::NONE; SMALL_MAP_LIMIT * 4];
+        let new_kv = &mut buffer[..(new_capacity * 2)];
+
         let mut i = 0;
         let mut i1 = 0;
         let mut i2 = 0;
+        // Whether the result differs from `self`, i.e. `map2` adds a key or changes a value
+        let mut changed = false;
+        // Whether the result differs from `map2`, i.e. `self` has a key `map2` doesn't
+        let mut extended = false;
         let (new_keys, new_values) = unsafe { new_kv.split_at_mut_unchecked(new_capacity) };
         while i1 < n1 && i2 < n2 {
-            use core::cmp::Ordering;
-
             let k1 = keys1[i1];
             let k2 = keys2[i2];
             match compare_keys(k1, k2) {
@@ -732,6 +776,7 @@ impl SmallMap {
                     // Use right-hand side map's value, but advance both maps
                     new_keys[i] = k2;
                     new_values[i] = values2[i2];
+                    changed |= values1[i1] != values2[i2];
                     i += 1;
                     i1 += 1;
                     i2 += 1;
@@ -739,12 +784,14 @@ impl SmallMap {
                 Ordering::Less => {
                     new_keys[i] = k1;
                     new_values[i] = values1[i1];
+                    extended = true;
                     i += 1;
                     i1 += 1;
                 }
                 Ordering::Greater => {
                     new_keys[i] = k2;
                     new_values[i] = values2[i2];
+                    changed = true;
                     i += 1;
                     i2 += 1;
                 }
@@ -762,6 +809,7 @@ impl SmallMap {
             let nv = &mut new_values[i..(i + remaining)];
             nv.copy_from_slice(rest);
 
+            extended = true;
             i += remaining;
         }
         if i2 < n2 {
@@ -774,9 +822,21 @@ impl SmallMap {
             let nv = &mut new_values[i..(i + remaining)];
             nv.copy_from_slice(rest);
 
+            changed = true;
             i += remaining;
         }
 
+        // Share one of the inputs if the result is identical to it
+        if !changed {
+            return Ok(self);
+        }
+        if !extended {
+            return Ok(map2.clone());
+        }
+        if i > SMALL_MAP_LIMIT {
+            return Err(MapError::SizeLimit);
+        }
+
         // Allocate the correctly-sized map on the process heap and memcpy the
         // contents from our temporary map allocated on the stack
         let mut new_map = Self::with_capacity_in(i, alloc)?;
@@ -785,6 +845,29 @@ impl SmallMap {
         new_map.values_mut().copy_from_slice(&new_values[..i]);
         Ok(new_map)
     }
+
+    /// Creates a new map containing the keys of `first` followed by those of `second`, which must
+    /// all be greater than those of `first`
+    fn concat<A: ?Sized + Allocator>(
+        first: &Self,
+        second: &Self,
+        alloc: &A,
+    ) -> Result<Gc<Self>, MapError> {
+        let n1 = first.size();
+        let n = n1 + second.size();
+        if n > SMALL_MAP_LIMIT {
+            return Err(MapError::SizeLimit);
+        }
+
+        let mut new_map = Self::with_capacity_in(n, alloc)?;
+        new_map.bitmap = u64::MAX << (64 - n);
+        let (keys, values) = new_map.kv.split_at_mut(n);
+        keys[..n1].copy_from_slice(first.keys());
+        keys[n1..].copy_from_slice(second.keys());
+        values[..n1].copy_from_slice(first.values());
+        values[n1..].copy_from_slice(second.values());
+        Ok(new_map)
+    }
 }
 impl fmt::Debug for SmallMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -799,15 +882,9 @@ impl fmt::Debug for SmallMap {
     }
 }
 impl fmt::Display for SmallMap {
+    #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("#{")?;
-        for (i, (key, value)) in self.iter().enumerate() {
-            if i > 0 {
-                f.write_str(", ")?;
-            }
-            write!(f, "{} => {}", key, value)?;
-        }
-        f.write_str("}")
+        super::format::display_map(self, f)
     }
 }
 impl Eq for SmallMap {}
@@ -932,23 +1009,12 @@ fn find_key(map: &SmallMap, key: OpaqueTerm) -> Result<usize, usize> {
 }
 
 #[inline]
+/// Keys are kept sorted in map key order, in which integers and floats are never equal
 fn compare_keys(k1: OpaqueTerm, k2: OpaqueTerm) -> core::cmp::Ordering {
-    use core::cmp::Ordering;
-
-    match k1.cmp(&k2) {
-        Ordering::Equal => {
-            // We have to perform an exact comparison to "confirm" equality
-            // of terms here, as term comparison for ordering does not use
-            // strict equality as an optimization.
-            if k1.exact_eq(&k2) {
-                Ordering::Equal
-            } else {
-                // This is arbitrary, but suitable for our purposes
-                Ordering::Less
-            }
-        }
-        ord => ord,
+    if k1 == k2 {
+        return core::cmp::Ordering::Equal;
     }
+    crate::cmp::cmp_terms_exact(k1.into(), k2.into())
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -962,6 +1028,15 @@ impl MapFlags {
     const HEAD_ARRAY_NODE: usize = 0b10;
     const HEAD_BMAP_NODE: usize = 0b11;
 
+    /// Set on flatmaps which live in a literal area, rather than on a heap
+    const LITERAL: usize = 0b100;
+    /// The capacity of a flatmap is stored above its tag and literal flag
+    const CAPACITY_SHIFT: usize = 3;
+
+    fn flatmap(capacity: usize) -> Self {
+        Self(Self::FLATMAP | (capacity << Self::CAPACITY_SHIFT))
+    }
+
     pub fn is_flatmap(&self) -> bool {
         self.0 & Self::TAG_MASK == Self::FLATMAP
     }
@@ -981,7 +1056,12 @@ impl MapFlags {
 
     pub fn capacity(&self) -> usize {
         assert!(self.is_flatmap());
-        (self.0 & Self::VAL_MASK) >> 2
+        (self.0 & Self::VAL_MASK) >> Self::CAPACITY_SHIFT
+    }
+
+    /// Returns true if this is a literal flatmap, see [`SmallMap::is_literal`]
+    pub fn is_literal(&self) -> bool {
+        self.is_flatmap() && self.0 & Self::LITERAL == Self::LITERAL
     }
 
     pub fn bitmap(&self) -> usize {
@@ -1416,6 +1496,87 @@ mod tests {
         assert_eq!(merged.values(), expected);
     }
 
+    #[test]
+    fn smallmap_merge_fast_path_test() {
+        let heap = FixedSizeHeap::<512>::default();
+
+        let mut map = SmallMap::with_capacity_in(2, &heap).unwrap();
+        map.put_mut(Term::Int(1), Term::Bool(true));
+        map.put_mut(Term::Int(2), Term::Bool(false));
+
+        // Overriding keys with the values they already have shares the left-hand side map
+        let mut same = SmallMap::with_capacity_in(1, &heap).unwrap();
+        same.put_mut(Term::Int(2), Term::Bool(false));
+        let merged = map.merge(&same, &heap).unwrap();
+        assert_eq!(Gc::as_ptr(&map), Gc::as_ptr(&merged));
+
+        // A right-hand side map containing every key of the left-hand side map is shared
+        let mut superset = SmallMap::with_capacity_in(3, &heap).unwrap();
+        superset.put_mut(Term::Int(1), Term::Bool(false));
+        superset.put_mut(Term::Int(2), Term::Bool(true));
+        superset.put_mut(Term::Int(3), Term::Bool(true));
+        let merged = map.merge(&superset, &heap).unwrap();
+        assert_eq!(Gc::as_ptr(&superset), Gc::as_ptr(&merged));
+
+        // Maps with disjoint key ranges are concatenated, in either order
+        let mut higher = SmallMap::with_capacity_in(2, &heap).unwrap();
+        higher.put_mut(Term::Int(5), Term::Bool(true));
+        higher.put_mut(Term::Int(6), Term::Bool(true));
+        let expected: &[OpaqueTerm] = &[
+            Term::Int(1).into(),
+            Term::Int(2).into(),
+            Term::Int(5).into(),
+            Term::Int(6).into(),
+        ];
+        let merged = map.merge(&higher, &heap).unwrap();
+        assert_eq!(merged.keys(), expected);
+        assert_eq!(merged.get(Term::Int(2)), Some(Term::Bool(false).into()));
+        let merged = higher.merge(&map, &heap).unwrap();
+        assert_eq!(merged.keys(), expected);
+        assert_eq!(merged.get(Term::Int(6)), Some(Term::Bool(true).into()));
+    }
+
+    #[test]
+    fn smallmap_merge_size_limit_test() {
+        let heap = FixedSizeHeap::<4096>::default();
+
+        let mut map = SmallMap::with_capacity_in(SMALL_MAP_LIMIT, &heap).unwrap();
+        for i in 0..(SMALL_MAP_LIMIT as i64) {
+            map.put_mut(Term::Int(i * 2), Term::Nil);
+        }
+        let mut odd = SmallMap::with_capacity_in(1, &heap).unwrap();
+        odd.put_mut(Term::Int(1), Term::Nil);
+        let mut after = SmallMap::with_capacity_in(1, &heap).unwrap();
+        after.put_mut(Term::Int(SMALL_MAP_LIMIT as i64 * 2), Term::Nil);
+
+        assert_eq!(map.clone().merge(&odd, &heap).unwrap_err(), MapError::SizeLimit);
+        assert_eq!(map.merge(&after, &heap).unwrap_err(), MapError::SizeLimit);
+    }
+
+    #[test]
+    fn smallmap_literal_test() {
+        let heap = FixedSizeHeap::<256>::default();
+
+        let mut map = SmallMap::with_capacity_in(2, &heap).unwrap();
+        map.put_mut(Term::Int(1), Term::Bool(true));
+        map.put_mut(Term::Int(2), Term::Bool(false));
+        assert!(!map.is_literal());
+
+        unsafe {
+            map.mark_literal();
+        }
+        assert!(map.is_literal());
+        assert_eq!(map.capacity(), 2);
+        assert_eq!(map.size(), 2);
+        assert_eq!(map.get(Term::Int(2)), Some(Term::Bool(false).into()));
+
+        // Maps derived from a literal map are not literals themselves
+        let updated = map.clone().put(Term::Int(3), Term::Nil, &heap).unwrap();
+        assert!(!updated.is_literal());
+        let cloned = SmallMap::clone_from(&map, &heap).unwrap();
+        assert!(!cloned.is_literal());
+    }
+
     #[test]
     fn smallmap_compare_keys_test() {
         let heap = FixedSizeHeap::<256>::default();
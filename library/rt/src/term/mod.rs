@@ -2,9 +2,13 @@ pub mod atom;
 mod binary;
 mod closure;
 mod convert;
+mod copy;
+pub mod format;
 mod fragment;
+pub mod hash;
 mod header;
 mod index;
+mod iodata;
 mod integer;
 mod layout;
 mod list;
@@ -20,16 +24,18 @@ pub use self::atom::{atoms, Atom, AtomData, AtomError};
 pub use self::binary::*;
 pub use self::closure::{Closure, ClosureFlags};
 pub use self::convert::ToTerm;
+pub use self::copy::{copy_layout, copy_term, unsafe_copy_term, CopyMode};
 pub use self::fragment::TermFragment;
 pub use self::header::{Boxable, Header, Metadata, Tag};
 pub use self::index::{NonPrimitiveIndex, OneBasedIndex, TupleIndex, ZeroBasedIndex};
+pub use self::iodata::{iodata_size, iodata_to_binary, iodata_to_vec, write_iodata};
 pub use self::integer::BigInt;
 pub use self::layout::LayoutBuilder;
-pub use self::list::{Cons, ImproperList, ListBuilder};
-pub use self::map::{Map, MapError, SmallMap};
+pub use self::list::{Cons, ImproperList, ListBuilder, ListIter};
+pub use self::map::{Map, MapError, SmallMap, SMALL_MAP_LIMIT};
 pub use self::opaque::{OpaqueTerm, TermType};
 pub use self::pid::Pid;
-pub use self::port::{Port, PortId};
+pub use self::port::{Port, PortCounters, PortId, PortStat, PortStats};
 pub use self::reference::{Reference, ReferenceId};
 pub use self::tuple::Tuple;
 pub use self::value::Value;
@@ -219,6 +225,52 @@ impl Term {
         }
     }
 
+    /// Returns an iterator over the elements of this term, if it is a list
+    ///
+    /// See [`ListIter`] for details on how improper and cyclic lists are handled.
+    pub fn as_list_iter(&self) -> Option<ListIter<'_>> {
+        match self {
+            Self::Nil => Some(ListIter::new(OpaqueTerm::NIL)),
+            Self::Cons(cons) => Some(ListIter::new((*cons).into())),
+            _ => None,
+        }
+    }
+
+    /// Constructs a utf8-encoded binary term from the given string
+    ///
+    /// Small binaries are allocated on `heap`, larger ones are reference-counted.
+    pub fn from_str_in<H: ?Sized + Heap>(s: &str, heap: &H) -> Result<Self, AllocError> {
+        if s.len() > BinaryData::MAX_HEAP_BYTES {
+            Ok(Self::RcBinary(BinaryData::from_str(s)))
+        } else {
+            BinaryData::from_small_str(s, heap).map(Self::HeapBinary)
+        }
+    }
+
+    /// Constructs a binary term from the given bytes
+    ///
+    /// Small binaries are allocated on `heap`, larger ones are reference-counted.
+    pub fn from_bytes_in<H: ?Sized + Heap>(bytes: &[u8], heap: &H) -> Result<Self, AllocError> {
+        if bytes.len() > BinaryData::MAX_HEAP_BYTES {
+            Ok(Self::RcBinary(BinaryData::from_bytes(bytes)))
+        } else {
+            BinaryData::from_small_bytes(bytes, heap).map(Self::HeapBinary)
+        }
+    }
+
+    /// Constructs a charlist term, i.e. a list of codepoints, from the given string
+    pub fn charlist_from_str_in<H: ?Sized + Heap>(s: &str, heap: &H) -> Result<Self, AllocError> {
+        Cons::charlist_from_str(s, heap).map(|list| list.map(Self::Cons).unwrap_or(Self::Nil))
+    }
+
+    /// Constructs a list term of the given bytes
+    pub fn bytelist_from_bytes_in<H: ?Sized + Heap>(
+        bytes: &[u8],
+        heap: &H,
+    ) -> Result<Self, AllocError> {
+        Cons::from_bytes(bytes, heap).map(|list| list.map(Self::Cons).unwrap_or(Self::Nil))
+    }
+
     pub fn is_bitstring(&self) -> bool {
         match self {
             Self::HeapBinary(_)
@@ -263,7 +315,6 @@ impl Term {
             | Self::Int(_)
             | Self::Float(_)
             | Self::Port(_)
-            | Self::Reference(_)
             | Self::RcBinary(_)
             | Self::ConstantBinary(_) => Layout::new::<()>(),
             Self::BigInt(boxed) => boxed.deref().layout_excluding_heap(heap),
@@ -272,6 +323,7 @@ impl Term {
             Self::Map(boxed) => boxed.deref().layout_excluding_heap(heap),
             Self::Closure(boxed) => boxed.deref().layout_excluding_heap(heap),
             Self::Pid(boxed) => boxed.deref().layout_excluding_heap(heap),
+            Self::Reference(boxed) => boxed.deref().layout_excluding_heap(heap),
             Self::HeapBinary(boxed) => boxed.deref().layout_excluding_heap(heap),
             Self::RefBinary(boxed) => boxed.layout_excluding_heap(heap),
         }
@@ -616,28 +668,7 @@ impl AsRef<Term> for Term {
 }
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::None => write!(f, "NONE"),
-            Self::Catch(_) => write!(f, "CATCH"),
-            Self::Code(_) => write!(f, "CP"),
-            Self::Nil => f.write_str("[]"),
-            Self::Bool(term) => write!(f, "{}", term),
-            Self::Atom(term) => write!(f, "{}", term),
-            Self::Int(term) => write!(f, "{}", term),
-            Self::BigInt(term) => write!(f, "{}", term),
-            Self::Float(term) => write!(f, "{}", term),
-            Self::Cons(term) => write!(f, "{}", term),
-            Self::Tuple(term) => write!(f, "{}", term),
-            Self::Map(boxed) => write!(f, "{}", boxed),
-            Self::Closure(boxed) => write!(f, "{}", boxed),
-            Self::Pid(boxed) => write!(f, "{}", boxed),
-            Self::Port(boxed) => write!(f, "{}", boxed),
-            Self::Reference(boxed) => write!(f, "{}", boxed),
-            Self::HeapBinary(boxed) => write!(f, "{}", boxed),
-            Self::RcBinary(boxed) => write!(f, "{}", boxed),
-            Self::RefBinary(boxed) => write!(f, "{}", boxed),
-            Self::ConstantBinary(bytes) => write!(f, "{}", bytes),
-        }
+        self::format::write_term(f, self.clone(), &self::format::FormatOptions::display())
     }
 }
 impl Eq for Term {}
@@ -138,6 +138,7 @@
 ///!
 use alloc::sync::Arc;
 use core::fmt;
+use core::intrinsics::unlikely;
 use core::mem::{self, MaybeUninit};
 use core::ptr::NonNull;
 
@@ -1107,6 +1108,60 @@ impl OpaqueTerm {
         f64::from_bits(self.0)
     }
 
+    /// Applies `op` to `self` and `rhs` as floats, when at least one of them is a float and the
+    /// other is a float or an immediate integer
+    ///
+    /// Since floats are immediates, this avoids decoding the operands into `Number` and lets
+    /// float-heavy code run without touching the heap. Returns `None` if the operands are not
+    /// suitable, or if the result is not a valid float (e.g. due to overflow or division by zero),
+    /// in which case the caller should fall back to the general arithmetic path.
+    #[inline]
+    pub fn float_arith<F>(self, rhs: Self, op: F) -> Option<Self>
+    where
+        F: FnOnce(f64, f64) -> f64,
+    {
+        let (l, r) = match (self.is_float(), rhs.is_float()) {
+            (true, true) => (self.as_float(), rhs.as_float()),
+            (true, false) if rhs.is_integer() => {
+                (self.as_float(), unsafe { rhs.as_integer() } as f64)
+            }
+            (false, true) if self.is_integer() => {
+                (unsafe { self.as_integer() } as f64, rhs.as_float())
+            }
+            _ => return None,
+        };
+        let result = op(l, r);
+        if result.is_finite() {
+            Some(Self(result.to_bits()))
+        } else {
+            None
+        }
+    }
+
+    /// Applies `op` to `self` and `rhs` as 128-bit integers, when both are immediate integers
+    ///
+    /// Immediate integers are at most 52 bits wide, so the sum, difference or product of any two
+    /// of them is exact in 128 bits, and integer-heavy code only has to check whether the result
+    /// is still an immediate. Returns `None` if the operands are not suitable, or if the result is
+    /// too large for an immediate, in which case the caller should fall back to the general
+    /// arithmetic path, which allocates a big integer.
+    #[inline]
+    pub fn int_arith<F>(self, rhs: Self, op: F) -> Option<Self>
+    where
+        F: FnOnce(i128, i128) -> i128,
+    {
+        if !(self.is_integer() && rhs.is_integer()) {
+            return None;
+        }
+        let (l, r) = unsafe { (self.as_integer(), rhs.as_integer()) };
+        let result = op(l as i128, r as i128);
+        let small = result as i64;
+        if unlikely(small as i128 != result || !Self::is_small_integer(small)) {
+            return None;
+        }
+        Self::try_from(small).ok()
+    }
+
     /// Returns true if the given i64 value is in the range allowed for immediates
     pub fn is_small_integer(value: i64) -> bool {
         let value = value as u64;
@@ -1922,6 +1977,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn opaque_term_float_arith() {
+        let half: OpaqueTerm = 0.5f64.into();
+        let two: OpaqueTerm = 2i64.try_into().unwrap();
+        let zero: OpaqueTerm = 0.0f64.into();
+
+        let sum = half.float_arith(two, |l, r| l + r).unwrap();
+        assert!(sum.is_float());
+        assert_eq!(sum.as_float(), 2.5);
+        assert_eq!(two.float_arith(half, |l, r| l * r).unwrap().as_float(), 1.0);
+        // Integer-only arithmetic must not produce a float
+        assert_eq!(two.float_arith(two, |l, r| l + r), None);
+        // Non-numeric operands and invalid results fall back to the slow path
+        assert_eq!(half.float_arith(OpaqueTerm::NIL, |l, r| l + r), None);
+        assert_eq!(half.float_arith(zero, |l, r| l / r), None);
+        let max: OpaqueTerm = f64::MAX.into();
+        assert_eq!(max.float_arith(max, |l, r| l * r), None);
+    }
+
+    #[test]
+    fn opaque_term_int_arith() {
+        let max: OpaqueTerm = MAX_SMALL.try_into().unwrap();
+        let min: OpaqueTerm = MIN_SMALL.try_into().unwrap();
+        let two: OpaqueTerm = 2i64.try_into().unwrap();
+        let neg1: OpaqueTerm = (-1i64).try_into().unwrap();
+        let half: OpaqueTerm = 0.5f64.into();
+
+        let sum = two.int_arith(neg1, |l, r| l + r).unwrap();
+        assert!(sum.is_integer());
+        assert_eq!(unsafe { sum.as_integer() }, 1);
+        let product = max.int_arith(neg1, |l, r| l * r).unwrap();
+        assert_eq!(unsafe { product.as_integer() }, -MAX_SMALL);
+        let difference = min.int_arith(neg1, |l, r| l - r).unwrap();
+        assert_eq!(unsafe { difference.as_integer() }, MIN_SMALL + 1);
+        // Results which are too large for an immediate fall back to the slow path
+        assert_eq!(min.int_arith(neg1, |l, r| l * r), None);
+        assert_eq!(max.int_arith(two, |l, r| l + r), None);
+        assert_eq!(min.int_arith(two, |l, r| l - r), None);
+        assert_eq!(max.int_arith(max, |l, r| l * r), None);
+        // Floats and non-numeric operands are not handled
+        assert_eq!(two.int_arith(half, |l, r| l + r), None);
+        assert_eq!(two.int_arith(OpaqueTerm::NIL, |l, r| l + r), None);
+    }
+
     #[test]
     fn opaque_term_integer() {
         let max: OpaqueTerm = MAX_SMALL.try_into().unwrap();
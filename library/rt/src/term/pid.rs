@@ -104,21 +104,6 @@ impl Pid {
         })
     }
 
-    /// Allocates a new local pid, using the global counter.
-    ///
-    /// NOTE: The pid returned by this function is not guaranteed to be unique. Once the pid
-    /// space has been exhausted at least once, pids may be reused, and it is up to the caller
-    /// to ensure that a pid is only used by a single live process on the local node at any given
-    /// time.
-    #[inline]
-    pub fn next() -> Self {
-        Self {
-            header: Header::new(Tag::Pid, 0),
-            id: ProcessId::next(),
-            node: None,
-        }
-    }
-
     /// Returns the raw process identifier
     #[inline]
     pub fn id(&self) -> ProcessId {
@@ -1,14 +1,16 @@
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::fmt;
 use core::hash::{Hash, Hasher};
 use core::ops::Deref;
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
-use firefly_system::sync::Atomic;
+use firefly_system::sync::{Atomic, Mutex};
 
-use crate::drivers::{Driver, DriverError, LoadableDriver};
+use crate::drivers::{self, Driver, DriverError, LoadableDriver, PortControlFlags};
+use crate::process::ProcessId;
 use crate::services::distribution::Node;
 
 use super::{atoms, Atom, Header, Pid, Tag};
@@ -19,10 +21,13 @@ pub struct Port {
     header: Header,
     id: PortId,
     node: Option<Arc<Node>>,
-    #[allow(unused)]
     owner: Pid,
     registered_name: Atomic<Atom>,
-    #[allow(unused)]
+    control_flags: AtomicU32,
+    /// The number of bytes written to/read from the port, as opposed to its `stats`
+    input: AtomicU64,
+    output: AtomicU64,
+    stats: Arc<PortStats>,
     info: Option<PortInfo>,
 }
 impl Port {
@@ -41,6 +46,10 @@ impl Port {
                 node: None,
                 owner,
                 registered_name: Atomic::new(atoms::Undefined),
+                control_flags: AtomicU32::new(PortControlFlags::DEFAULT.bits()),
+                input: AtomicU64::new(0),
+                output: AtomicU64::new(0),
+                stats: Arc::new(PortStats::new()),
                 info: Some(PortInfo {
                     name: command.to_string(),
                     driver,
@@ -50,6 +59,25 @@ impl Port {
         }
     }
 
+    /// Creates a handle for the port identified by `id` on another node
+    ///
+    /// External ports have no driver or local owner, as they cannot be interacted with directly.
+    pub fn new_external(node: Arc<Node>, id: PortId) -> Arc<Self> {
+        let owner = Pid::new_local(unsafe { ProcessId::from_raw(0) });
+        Arc::new(Self {
+            header: Header::new(Tag::Port, 0),
+            id,
+            node: Some(node),
+            owner,
+            registered_name: Atomic::new(atoms::Undefined),
+            control_flags: AtomicU32::new(PortControlFlags::DEFAULT.bits()),
+            input: AtomicU64::new(0),
+            output: AtomicU64::new(0),
+            stats: Arc::new(PortStats::new()),
+            info: None,
+        })
+    }
+
     #[cfg(test)]
     pub(crate) fn new_with_id(
         id: PortId,
@@ -67,6 +95,10 @@ impl Port {
                 node: None,
                 owner,
                 registered_name: Atomic::new(atoms::Undefined),
+                control_flags: AtomicU32::new(PortControlFlags::DEFAULT.bits()),
+                input: AtomicU64::new(0),
+                output: AtomicU64::new(0),
+                stats: Arc::new(PortStats::new()),
                 info: Some(PortInfo {
                     name: command.to_string(),
                     driver,
@@ -86,6 +118,41 @@ impl Port {
         self.node.clone()
     }
 
+    /// Returns the process which this port is connected to
+    #[inline]
+    pub fn owner(&self) -> Pid {
+        self.owner.clone()
+    }
+
+    /// Returns the name of the command this port was opened with, if local
+    pub fn name(&self) -> Option<&str> {
+        self.info.as_ref().map(|info| info.name.as_str())
+    }
+
+    /// Returns the number of bytes the driver has delivered to the owner of this port
+    pub fn input(&self) -> u64 {
+        self.input.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes which have been written to this port
+    pub fn output(&self) -> u64 {
+        self.output.load(Ordering::Relaxed)
+    }
+
+    /// Called by drivers to record `size` bytes being delivered to the owner of this port
+    pub fn record_input(&self, size: usize) {
+        self.input.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the I/O counters of this port
+    ///
+    /// Drivers which perform I/O on behalf of the port are expected to share these with their
+    /// sockets, so that the counters reflect traffic on the wire rather than port commands.
+    #[inline]
+    pub fn stats(&self) -> &Arc<PortStats> {
+        &self.stats
+    }
+
     #[inline]
     pub fn is_local(&self) -> bool {
         self.node.is_none()
@@ -144,6 +211,84 @@ impl Port {
             Ok(())
         }
     }
+
+    /// Returns the flags which control how the result of [`Port::control`] is returned to Erlang
+    pub fn control_flags(&self) -> PortControlFlags {
+        PortControlFlags::from_bits_truncate(self.control_flags.load(Ordering::Relaxed))
+    }
+
+    /// Sets the flags which control how the result of [`Port::control`] is returned to Erlang
+    ///
+    /// This is typically called by the driver when the port is started.
+    pub fn set_control_flags(&self, flags: PortControlFlags) {
+        self.control_flags.store(flags.bits(), Ordering::Relaxed);
+    }
+
+    /// Passes `data` to the `output` callback of the driver, as done by `erlang:port_command/2`
+    pub fn command(&self, data: &[u8]) -> Result<(), DriverError> {
+        let info = self.info.as_ref().ok_or(DriverError::Badarg)?;
+        self.output.fetch_add(data.len() as u64, Ordering::Relaxed);
+        info.driver.output(data);
+        Ok(())
+    }
+
+    /// Synchronously calls the `control` callback of the driver, as done by `erlang:port_control/3`
+    ///
+    /// Returns the bytes produced by the driver, or `Err` if this port has no driver, or the
+    /// driver returned an invalid result.
+    pub fn control(&self, command: u32, data: &[u8]) -> Result<Vec<u8>, DriverError> {
+        let info = self.info.as_ref().ok_or(DriverError::Badarg)?;
+        let mut buffer = [0; Self::CONTROL_BUFFER_SIZE];
+        let mut rbuf = buffer.as_mut_ptr();
+        let len = info.driver.control(command, data, &mut rbuf, buffer.len());
+        unsafe { take_driver_buffer(&mut buffer, rbuf, len) }
+    }
+
+    /// Synchronously calls the `call` callback of the driver, as done by `erlang:port_call/3`
+    ///
+    /// Both `data` and the result are in the external term format.
+    pub fn call(&self, command: u32, data: &[u8]) -> Result<Vec<u8>, DriverError> {
+        let info = self.info.as_ref().ok_or(DriverError::Badarg)?;
+        let mut buffer = [0; Self::CONTROL_BUFFER_SIZE];
+        let mut rbuf = buffer.as_mut_ptr();
+        let mut flags = 0;
+        let len = info
+            .driver
+            .call(command, data, &mut rbuf, buffer.len(), &mut flags)?;
+        unsafe { take_driver_buffer(&mut buffer, rbuf, len) }
+    }
+
+    /// The size of the default buffer given to the driver for the results of `control` and `call`
+    const CONTROL_BUFFER_SIZE: usize = 64;
+}
+
+/// Copies `len` bytes out of the buffer returned by a driver in `rbuf`, freeing it if the driver
+/// replaced the default `buffer` with one of its own.
+///
+/// # SAFETY
+///
+/// `rbuf` must be null, point to `buffer`, or have been allocated with `drivers::alloc`.
+unsafe fn take_driver_buffer(
+    buffer: &mut [u8],
+    rbuf: *mut u8,
+    len: usize,
+) -> Result<Vec<u8>, DriverError> {
+    if rbuf.is_null() {
+        return Ok(Vec::new());
+    }
+    if rbuf == buffer.as_mut_ptr() {
+        return buffer
+            .get(..len)
+            .map(|bytes| bytes.to_vec())
+            .ok_or(DriverError::Badarg);
+    }
+    let result = if len <= drivers::buffer_size(rbuf) {
+        Ok(core::slice::from_raw_parts(rbuf, len).to_vec())
+    } else {
+        Err(DriverError::Badarg)
+    };
+    drivers::free(rbuf);
+    result
 }
 impl Eq for Port {}
 impl crate::cmp::ExactEq for Port {}
@@ -194,7 +339,6 @@ impl fmt::Display for Port {
 
 pub struct PortInfo {
     name: String,
-    #[allow(unused)]
     driver: Box<dyn Driver>,
 }
 impl fmt::Debug for PortInfo {
@@ -205,6 +349,189 @@ impl fmt::Debug for PortInfo {
     }
 }
 
+/// The statistics reported by `inet:getstat/2`, see [`PortStats`]
+///
+/// In addition to the statistics supported by OTP, this includes error counts, and the largest
+/// size reached by the send queue.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PortStat {
+    RecvAvg,
+    RecvCnt,
+    RecvDvi,
+    RecvMax,
+    RecvOct,
+    RecvErr,
+    SendAvg,
+    SendCnt,
+    SendMax,
+    SendOct,
+    SendPend,
+    SendPendMax,
+    SendErr,
+}
+impl PortStat {
+    /// All statistics, in the order they are returned by `inet:getstat/1`
+    pub const ALL: [Self; 13] = [
+        Self::RecvAvg,
+        Self::RecvCnt,
+        Self::RecvDvi,
+        Self::RecvMax,
+        Self::RecvOct,
+        Self::RecvErr,
+        Self::SendAvg,
+        Self::SendCnt,
+        Self::SendMax,
+        Self::SendOct,
+        Self::SendPend,
+        Self::SendPendMax,
+        Self::SendErr,
+    ];
+
+    pub fn atom(self) -> Atom {
+        match self {
+            Self::RecvAvg => atoms::RecvAvg,
+            Self::RecvCnt => atoms::RecvCnt,
+            Self::RecvDvi => atoms::RecvDvi,
+            Self::RecvMax => atoms::RecvMax,
+            Self::RecvOct => atoms::RecvOct,
+            Self::RecvErr => atoms::RecvErr,
+            Self::SendAvg => atoms::SendAvg,
+            Self::SendCnt => atoms::SendCnt,
+            Self::SendMax => atoms::SendMax,
+            Self::SendOct => atoms::SendOct,
+            Self::SendPend => atoms::SendPend,
+            Self::SendPendMax => atoms::SendPendMax,
+            Self::SendErr => atoms::SendErr,
+        }
+    }
+}
+impl TryFrom<Atom> for PortStat {
+    type Error = ();
+
+    fn try_from(atom: Atom) -> Result<Self, Self::Error> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|stat| stat.atom() == atom)
+            .ok_or(())
+    }
+}
+
+/// Counters for the data passing through a port or socket
+///
+/// Averages and deviations are maintained incrementally, in the same way as the inet driver of OTP,
+/// so they are rounded down to whole bytes.
+pub struct PortStats(Mutex<PortCounters>);
+impl PortStats {
+    pub fn new() -> Self {
+        Self(Mutex::new(PortCounters::default()))
+    }
+
+    /// Records a packet of `size` bytes being received
+    pub fn record_recv(&self, size: usize) {
+        let size = size as u64;
+        let mut counters = self.0.lock();
+        let counters = &mut *counters;
+        counters.recv_cnt += 1;
+        counters.recv_oct = counters.recv_oct.wrapping_add(size);
+        counters.recv_max = counters.recv_max.max(size);
+        update_average(
+            size,
+            counters.recv_cnt,
+            &mut counters.recv_avg,
+            Some(&mut counters.recv_dvi),
+        );
+    }
+
+    /// Records a packet of `size` bytes being sent
+    pub fn record_send(&self, size: usize) {
+        let size = size as u64;
+        let mut counters = self.0.lock();
+        let counters = &mut *counters;
+        counters.send_cnt += 1;
+        counters.send_oct = counters.send_oct.wrapping_add(size);
+        counters.send_max = counters.send_max.max(size);
+        update_average(size, counters.send_cnt, &mut counters.send_avg, None);
+    }
+
+    pub fn record_recv_error(&self) {
+        self.0.lock().recv_err += 1;
+    }
+
+    pub fn record_send_error(&self) {
+        self.0.lock().send_err += 1;
+    }
+
+    /// Sets the number of bytes waiting to be sent
+    pub fn set_send_pend(&self, size: usize) {
+        let mut counters = self.0.lock();
+        counters.send_pend = size as u64;
+        counters.send_pend_max = counters.send_pend_max.max(size as u64);
+    }
+
+    /// Returns a snapshot of the current counters
+    pub fn get(&self) -> PortCounters {
+        *self.0.lock()
+    }
+}
+impl Default for PortStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for PortStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.get(), f)
+    }
+}
+
+/// A snapshot of [`PortStats`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PortCounters {
+    pub recv_cnt: u64,
+    pub recv_oct: u64,
+    pub recv_max: u64,
+    pub recv_avg: u64,
+    pub recv_dvi: u64,
+    pub recv_err: u64,
+    pub send_cnt: u64,
+    pub send_oct: u64,
+    pub send_max: u64,
+    pub send_avg: u64,
+    pub send_pend: u64,
+    pub send_pend_max: u64,
+    pub send_err: u64,
+}
+impl PortCounters {
+    pub fn get(&self, stat: PortStat) -> u64 {
+        match stat {
+            PortStat::RecvAvg => self.recv_avg,
+            PortStat::RecvCnt => self.recv_cnt,
+            PortStat::RecvDvi => self.recv_dvi,
+            PortStat::RecvMax => self.recv_max,
+            PortStat::RecvOct => self.recv_oct,
+            PortStat::RecvErr => self.recv_err,
+            PortStat::SendAvg => self.send_avg,
+            PortStat::SendCnt => self.send_cnt,
+            PortStat::SendMax => self.send_max,
+            PortStat::SendOct => self.send_oct,
+            PortStat::SendPend => self.send_pend,
+            PortStat::SendPendMax => self.send_pend_max,
+            PortStat::SendErr => self.send_err,
+        }
+    }
+}
+
+/// Folds `size` into the running average `avg` of `count` packets, and its average deviation `dvi`
+fn update_average(size: u64, count: u64, avg: &mut u64, dvi: Option<&mut u64>) {
+    let prev = *avg as i64;
+    let diff = size as i64 - prev;
+    *avg = (prev + diff / count as i64) as u64;
+    if let Some(dvi) = dvi {
+        *dvi = (*dvi as i64 + (diff.abs() - *dvi as i64) / count as i64) as u64;
+    }
+}
+
 /// Uniquely identifies an instance of a [`Port`] system-wide.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PortId(u64);
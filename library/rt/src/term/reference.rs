@@ -50,7 +50,6 @@ pub enum ReferenceType {
     /// If a value can't meet the above criteria, it can't be stored as magic directly, and you
     /// will likely need some intermediate type to use as the magic data.
     Magic(Arc<dyn Any + Send + Sync>),
-    #[allow(unused)]
     External(Arc<Node>),
 }
 
@@ -133,6 +132,15 @@ impl Reference {
         }
     }
 
+    /// Creates a new reference from the given reference id, belonging to `node`
+    pub fn new_external(node: Arc<Node>, id: ReferenceId) -> Self {
+        Self {
+            header: Header::new(Tag::Reference, 0),
+            id,
+            data: ReferenceType::External(node),
+        }
+    }
+
     /// Return the underlying reference identifier for this ref
     #[inline]
     pub fn id(&self) -> ReferenceId {
@@ -283,6 +291,18 @@ impl ReferenceId {
         self.0 == [0; REF_NUMBERS]
     }
 
+    /// Returns the raw words of this reference id, as used in the external term format
+    #[inline]
+    pub const fn into_raw(&self) -> [u32; REF_NUMBERS] {
+        self.0
+    }
+
+    /// Creates a reference id from its raw words, as used in the external term format
+    #[inline]
+    pub const fn from_raw(raw: [u32; REF_NUMBERS]) -> Self {
+        Self(raw)
+    }
+
     /// Create a `ReferenceId` from a given scheduler id and unique identifier
     ///
     /// # SAFETY
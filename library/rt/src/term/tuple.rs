@@ -259,16 +259,9 @@ impl Debug for Tuple {
     }
 }
 impl fmt::Display for Tuple {
+    #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("{")?;
-        for (i, element) in self.iter().enumerate() {
-            if i > 0 {
-                write!(f, ", {}", element)?;
-            } else {
-                write!(f, "{}", element)?;
-            }
-        }
-        f.write_str("}")
+        super::format::display_tuple(self, f)
     }
 }
 impl PartialOrd for Tuple {
@@ -0,0 +1,7 @@
+//! Support for testing code built on top of the runtime, e.g. NIFs and libraries.
+//!
+//! This module is only available when the `proptest` feature is enabled. It provides
+//! [proptest](https://docs.rs/proptest) strategies for generating terms and processes, see
+//! [`strategy`], and a reference implementation of the term order to test against, see [`order`].
+pub mod order;
+pub mod strategy;
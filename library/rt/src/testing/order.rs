@@ -0,0 +1,340 @@
+//! A reference implementation of the term order, for differential testing.
+//!
+//! [`compare`] implements the standard order of terms, as described in the Erlang reference
+//! manual, directly on [`TermSpec`]s, without sharing any code with the runtime's implementation.
+//! It is used as an oracle by the property tests in this module, which check that
+//! [`cmp_terms`](crate::cmp::cmp_terms), exact equality, hashing and encoding of built terms all
+//! agree with it, and can be used the same way by tests of anything else which depends on the
+//! term order.
+//!
+//! Numbers which compare equal in the standard order, e.g. `1` and `1.0`, are distinct in the
+//! exact order used for map keys, [`compare_exact`], in which integers are ordered first.
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use proptest::sample::Index;
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+
+use super::strategy::{self, term::leaf, Config, TermSpec};
+
+/// Compares `a` and `b` using the standard term order, i.e. like `erlang:'<'/2` and friends
+pub fn compare(a: &TermSpec, b: &TermSpec) -> Ordering {
+    cmp(a, b, false)
+}
+
+/// Compares `a` and `b` using the exact term order, i.e. like map keys
+pub fn compare_exact(a: &TermSpec, b: &TermSpec) -> Ordering {
+    cmp(a, b, true)
+}
+
+/// number < atom < reference < fun < port < pid < tuple < map < nil < list < bitstring
+fn rank(spec: &TermSpec) -> u8 {
+    match spec {
+        TermSpec::Int(_) | TermSpec::Float(_) => 0,
+        TermSpec::Bool(_) | TermSpec::Atom(_) => 1,
+        TermSpec::Pid { .. } => 5,
+        TermSpec::Tuple(_) => 6,
+        TermSpec::Map(_) => 7,
+        TermSpec::Nil => 8,
+        TermSpec::List { .. } => 9,
+        TermSpec::Binary { .. } => 10,
+    }
+}
+
+fn cmp(a: &TermSpec, b: &TermSpec, exact: bool) -> Ordering {
+    match (a, b) {
+        (TermSpec::Int(x), TermSpec::Int(y)) => x.cmp(y),
+        (TermSpec::Float(x), TermSpec::Float(y)) => x.partial_cmp(y).unwrap(),
+        (TermSpec::Int(x), TermSpec::Float(y)) => match cmp_int_float(*x, *y) {
+            Ordering::Equal if exact => Ordering::Less,
+            other => other,
+        },
+        (TermSpec::Float(x), TermSpec::Int(y)) => match cmp_int_float(*y, *x).reverse() {
+            Ordering::Equal if exact => Ordering::Greater,
+            other => other,
+        },
+        (TermSpec::Bool(_) | TermSpec::Atom(_), TermSpec::Bool(_) | TermSpec::Atom(_)) => {
+            atom_name(a).cmp(atom_name(b))
+        }
+        (
+            TermSpec::Pid {
+                number: xn,
+                serial: xs,
+            },
+            TermSpec::Pid {
+                number: yn,
+                serial: ys,
+            },
+        ) => (xn, xs).cmp(&(yn, ys)),
+        (TermSpec::Tuple(xs), TermSpec::Tuple(ys)) => {
+            xs.len().cmp(&ys.len()).then_with(|| cmp_seq(xs, ys, exact))
+        }
+        (TermSpec::Map(xs), TermSpec::Map(ys)) => cmp_maps(xs, ys, exact),
+        (TermSpec::Nil, TermSpec::Nil) => Ordering::Equal,
+        (
+            TermSpec::List {
+                elements: xs,
+                tail: xt,
+            },
+            TermSpec::List {
+                elements: ys,
+                tail: yt,
+            },
+        ) => cmp_lists(xs, xt.as_deref(), ys, yt.as_deref(), exact),
+        (TermSpec::Binary { bytes: x, .. }, TermSpec::Binary { bytes: y, .. }) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn atom_name(spec: &TermSpec) -> &str {
+    match spec {
+        TermSpec::Bool(true) => "true",
+        TermSpec::Bool(false) => "false",
+        TermSpec::Atom(atom) => atom.as_str(),
+        _ => unreachable!(),
+    }
+}
+
+/// Compares an integer and a float by their exact values, without converting either of them
+fn cmp_int_float(i: i64, f: f64) -> Ordering {
+    // 2^63, which is the smallest float greater than every i64
+    const LIMIT: f64 = 9223372036854775808.0;
+    if f >= LIMIT {
+        return Ordering::Less;
+    }
+    if f < -LIMIT {
+        return Ordering::Greater;
+    }
+    // `f` truncated is now exactly representable as an i64
+    match i.cmp(&(f.trunc() as i64)) {
+        Ordering::Equal => 0.0f64.partial_cmp(&f.fract()).unwrap(),
+        other => other,
+    }
+}
+
+fn cmp_seq(xs: &[TermSpec], ys: &[TermSpec], exact: bool) -> Ordering {
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        match cmp(x, y, exact) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    xs.len().cmp(&ys.len())
+}
+
+/// Lists are compared element by element, and then by comparing their tails as terms
+fn cmp_lists(
+    xs: &[TermSpec],
+    xt: Option<&TermSpec>,
+    ys: &[TermSpec],
+    yt: Option<&TermSpec>,
+    exact: bool,
+) -> Ordering {
+    let list = rank(&TermSpec::List {
+        elements: Vec::new(),
+        tail: None,
+    });
+    match (xs.split_first(), ys.split_first()) {
+        (Some((x, xs)), Some((y, ys))) => match cmp(x, y, exact) {
+            Ordering::Equal => cmp_lists(xs, xt, ys, yt, exact),
+            other => other,
+        },
+        (None, None) => cmp(
+            xt.unwrap_or(&TermSpec::Nil),
+            yt.unwrap_or(&TermSpec::Nil),
+            exact,
+        ),
+        // The tail of a list is never itself a list, so it is ordered by its type
+        (None, Some(_)) => rank(xt.unwrap_or(&TermSpec::Nil)).cmp(&list),
+        (Some(_), None) => list.cmp(&rank(yt.unwrap_or(&TermSpec::Nil))),
+    }
+}
+
+/// Maps are compared by size, then by their keys in order, and then by their values in key order
+fn cmp_maps(xs: &[(TermSpec, TermSpec)], ys: &[(TermSpec, TermSpec)], exact: bool) -> Ordering {
+    let xs = map_entries(xs);
+    let ys = map_entries(ys);
+    xs.len()
+        .cmp(&ys.len())
+        .then_with(|| {
+            for ((xk, _), (yk, _)) in xs.iter().zip(ys.iter()) {
+                match compare_exact(xk, yk) {
+                    Ordering::Equal => continue,
+                    other => return other,
+                }
+            }
+            Ordering::Equal
+        })
+        .then_with(|| {
+            for ((_, xv), (_, yv)) in xs.iter().zip(ys.iter()) {
+                match cmp(xv, yv, exact) {
+                    Ordering::Equal => continue,
+                    other => return other,
+                }
+            }
+            Ordering::Equal
+        })
+}
+
+/// Returns the entries of a map in key order, keeping only the first of any duplicate keys, as
+/// [`TermSpec::to_term_in`] does
+fn map_entries(entries: &[(TermSpec, TermSpec)]) -> Vec<&(TermSpec, TermSpec)> {
+    let mut unique: Vec<&(TermSpec, TermSpec)> = Vec::with_capacity(entries.len());
+    for entry in entries.iter() {
+        if !unique
+            .iter()
+            .any(|(k, _)| compare_exact(k, &entry.0) == Ordering::Equal)
+        {
+            unique.push(entry);
+        }
+    }
+    unique.sort_by(|(a, _), (b, _)| compare_exact(a, b));
+    unique
+}
+
+/// Generates pairs of terms which are likely to only differ deep within their structure
+///
+/// Independently generated terms almost always differ in type or size, so most pairs are
+/// instead generated by replacing a single leaf of a generated term, or are the same term twice.
+pub fn related_pair(config: Config) -> BoxedStrategy<(TermSpec, TermSpec)> {
+    let term = strategy::term(config);
+    let independent = (term.clone(), term.clone());
+    let same = term.clone().prop_map(|spec| (spec.clone(), spec));
+    let mutated = (term, leaf(config), proptest::arbitrary::any::<Index>()).prop_map(
+        |(spec, replacement, index)| {
+            let leaves = count_leaves(&spec);
+            let mut n = index.index(leaves);
+            let mutated = replace_leaf(&spec, &mut n, &replacement);
+            (spec, mutated)
+        },
+    );
+    proptest::prop_oneof![
+        1 => independent,
+        1 => same,
+        4 => mutated,
+    ]
+    .boxed()
+}
+
+fn count_leaves(spec: &TermSpec) -> usize {
+    match spec {
+        TermSpec::List { elements, tail } => {
+            elements.iter().map(count_leaves).sum::<usize>()
+                + tail.as_deref().map(count_leaves).unwrap_or(0)
+        }
+        TermSpec::Tuple(elements) => elements.iter().map(count_leaves).sum::<usize>().max(1),
+        TermSpec::Map(entries) => entries
+            .iter()
+            .map(|(k, v)| count_leaves(k) + count_leaves(v))
+            .sum::<usize>()
+            .max(1),
+        _ => 1,
+    }
+}
+
+/// Returns a copy of `spec` in which the `n`th leaf, in depth-first order, is replaced
+///
+/// Empty tuples and maps count as a single leaf.
+fn replace_leaf(spec: &TermSpec, n: &mut usize, replacement: &TermSpec) -> TermSpec {
+    let replace_all = |specs: &[TermSpec], n: &mut usize| -> Vec<TermSpec> {
+        specs
+            .iter()
+            .map(|spec| replace_leaf(spec, n, replacement))
+            .collect()
+    };
+    match spec {
+        TermSpec::List { elements, tail } => TermSpec::List {
+            elements: replace_all(elements, n),
+            // The tail of an improper list must not become a list
+            tail: tail.as_ref().map(|tail| {
+                let tail = replace_leaf(tail, n, replacement);
+                match tail {
+                    TermSpec::Nil | TermSpec::List { .. } => TermSpec::Int(0),
+                    tail => tail,
+                }
+                .into()
+            }),
+        },
+        TermSpec::Tuple(elements) if !elements.is_empty() => {
+            TermSpec::Tuple(replace_all(elements, n))
+        }
+        TermSpec::Map(entries) if !entries.is_empty() => TermSpec::Map(
+            entries
+                .iter()
+                .map(|(k, v)| {
+                    let k = replace_leaf(k, n, replacement);
+                    (k, replace_leaf(v, n, replacement))
+                })
+                .collect(),
+        ),
+        leaf => {
+            let replaced = if *n == 0 {
+                replacement.clone()
+            } else {
+                leaf.clone()
+            };
+            *n = n.wrapping_sub(1);
+            replaced
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_alloc::heap::{FixedSizeHeap, Heap};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::cmp::{cmp_terms, cmp_terms_exact, ExactEq};
+    use crate::etf;
+    use crate::term::hash::phash2;
+
+    type TestHeap = FixedSizeHeap<32768>;
+
+    fn config() -> Config {
+        Config::default().with_max_len(4)
+    }
+
+    /// Returns true if `a` and `b` can be built twice on a [`TestHeap`]
+    fn fits(a: &TermSpec, b: &TermSpec) -> bool {
+        (a.layout().size() + b.layout().size()) * 2 <= TestHeap::default().heap_available()
+    }
+
+    proptest! {
+        #[test]
+        fn term_order_matches_oracle((a, b) in related_pair(config())) {
+            prop_assume!(fits(&a, &b));
+            let heap = TestHeap::default();
+            let x = a.to_term_in(&heap).unwrap();
+            let y = b.to_term_in(&heap).unwrap();
+            prop_assert_eq!(cmp_terms(x.clone(), y.clone()), compare(&a, &b));
+            prop_assert_eq!(cmp_terms_exact(x, y), compare_exact(&a, &b));
+        }
+
+        #[test]
+        fn exact_equality_matches_oracle((a, b) in related_pair(config())) {
+            prop_assume!(fits(&a, &b));
+            let heap = TestHeap::default();
+            let x = a.to_term_in(&heap).unwrap();
+            let y = b.to_term_in(&heap).unwrap();
+            let equal = compare_exact(&a, &b) == Ordering::Equal;
+            prop_assert_eq!(x.exact_eq(&y), equal);
+            if equal {
+                prop_assert_eq!(phash2(x), phash2(y));
+            }
+        }
+
+        #[test]
+        fn etf_round_trip_preserves_order((a, b) in related_pair(config())) {
+            prop_assume!(fits(&a, &b));
+            let heap = TestHeap::default();
+            let x = a.to_term_in(&heap).unwrap();
+            let y = b.to_term_in(&heap).unwrap();
+            let (x2, _) = etf::decode(&etf::encode(x.clone()).unwrap(), &heap).unwrap();
+            let (y2, _) = etf::decode(&etf::encode(y.clone()).unwrap(), &heap).unwrap();
+            prop_assert!(x2.exact_eq(&x));
+            prop_assert!(y2.exact_eq(&y));
+            prop_assert_eq!(cmp_terms_exact(x2, y2), compare_exact(&a, &b));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Strategies for property testing with generated terms and processes.
+//!
+//! Terms are generated as [`TermSpec`]s, a description of a term which is independent of any
+//! heap. This allows generated values to be shrunk and reused freely, and leaves the choice of
+//! where the term is actually allocated to the test, which builds it with
+//! [`TermSpec::to_term_in`], e.g. on a process heap, or on a fixed-size heap sized using
+//! [`TermSpec::layout`].
+//!
+//! ```ignore
+//! use firefly_alloc::heap::FixedSizeHeap;
+//! use firefly_rt::testing::strategy::{self, Config};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn phash2_is_stable(spec in strategy::term(Config::default())) {
+//!         let heap = FixedSizeHeap::<4096>::default();
+//!         let term = spec.to_term_in(&heap).unwrap();
+//!         prop_assert_eq!(phash2(term.clone()), phash2(term));
+//!     }
+//! }
+//! ```
+//!
+//! The size and shape of generated terms is controlled by [`Config`].
+pub mod process;
+pub mod term;
+
+pub use self::process::process;
+pub use self::term::TermSpec;
+
+use proptest::strategy::{BoxedStrategy, Strategy};
+
+/// Controls the shape of the terms generated by [`term`] and friends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// The maximum nesting depth of containers, i.e. lists, tuples and maps
+    pub depth: u32,
+    /// The maximum number of elements in a container
+    pub max_len: usize,
+    /// Where generated binaries are allocated
+    pub binaries: BinaryPlacement,
+    /// Whether generated lists may be improper
+    pub improper_lists: bool,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            max_len: 3,
+            binaries: BinaryPlacement::Any,
+            improper_lists: true,
+        }
+    }
+}
+impl Config {
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn with_binaries(mut self, binaries: BinaryPlacement) -> Self {
+        self.binaries = binaries;
+        self
+    }
+
+    pub fn with_improper_lists(mut self, improper_lists: bool) -> Self {
+        self.improper_lists = improper_lists;
+        self
+    }
+}
+
+/// Where generated binaries are allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryPlacement {
+    /// Binaries are allocated on the heap the term is built on, and so are at most
+    /// [`BinaryData::MAX_HEAP_BYTES`](crate::term::BinaryData::MAX_HEAP_BYTES) in size
+    Heap,
+    /// Binaries are reference-counted, and shared by all terms built from the same spec
+    RefCounted,
+    /// Either of the above
+    Any,
+}
+
+/// Generates arbitrary terms, with containers nested up to `config.depth` deep
+pub fn term(config: Config) -> BoxedStrategy<TermSpec> {
+    let desired_size = (config.max_len * (config.depth as usize + 1)) as u32;
+    term::leaf(config)
+        .prop_recursive(
+            config.depth,
+            desired_size,
+            config.max_len as u32,
+            move |element| term::container(element, config),
+        )
+        .boxed()
+}
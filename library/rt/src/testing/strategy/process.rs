@@ -0,0 +1,31 @@
+//! Strategies for generating processes
+use alloc::sync::Arc;
+
+use crossbeam::deque::Injector;
+use proptest::strategy::{BoxedStrategy, LazyJust, Strategy};
+
+use crate::function::ModuleFunctionArity;
+use crate::process::{Process, SpawnInfo, SpawnOpts};
+use crate::scheduler::SchedulerId;
+use crate::term::atoms;
+
+/// Generates fresh processes which are not associated with any scheduler
+///
+/// A new process is created for each test case, so that state left behind by one case, e.g.
+/// messages or registrations, cannot interfere with the next. The processes are never run, but
+/// they can be locked to obtain a heap to build terms on, or to send and receive signals.
+pub fn process() -> BoxedStrategy<Arc<Process>> {
+    LazyJust::new(|| {
+        Process::new(
+            SchedulerId::default(),
+            None,
+            None,
+            ModuleFunctionArity::new(atoms::Erlang, atoms::Apply, 2),
+            &[],
+            Arc::new(Injector::new()),
+            SpawnOpts::default(),
+            SpawnInfo::default(),
+        )
+    })
+    .boxed()
+}
@@ -0,0 +1,304 @@
+//! Strategies for generating terms
+use alloc::alloc::{AllocError, Layout};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::num::FpCategory;
+
+use firefly_alloc::heap::Heap;
+use firefly_number::Int;
+use proptest::arbitrary::any;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prop_oneof;
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+
+use crate::gc::Gc;
+use crate::term::*;
+
+use super::{BinaryPlacement, Config};
+
+/// A description of a term, independent of any heap
+///
+/// Use [`TermSpec::to_term_in`] to build the described term.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermSpec {
+    Nil,
+    Bool(bool),
+    Atom(Atom),
+    /// An integer, which is boxed when built if it is out of range for an immediate
+    Int(i64),
+    Float(f64),
+    Binary {
+        bytes: Vec<u8>,
+        refcounted: bool,
+    },
+    Pid {
+        number: usize,
+        serial: usize,
+    },
+    /// A list of at least one element, with an optional improper tail
+    List {
+        elements: Vec<TermSpec>,
+        tail: Option<Box<TermSpec>>,
+    },
+    Tuple(Vec<TermSpec>),
+    /// A map, if any keys are equal, only the first one is kept
+    Map(Vec<(TermSpec, TermSpec)>),
+}
+impl TermSpec {
+    /// Returns the amount of heap space needed to build this term
+    pub fn layout(&self) -> Layout {
+        let mut builder = LayoutBuilder::new();
+        self.build_layout(&mut builder);
+        builder.finish()
+    }
+
+    fn build_layout(&self, builder: &mut LayoutBuilder) {
+        match self {
+            Self::Nil | Self::Bool(_) | Self::Atom(_) | Self::Float(_) => {
+                builder.build_immediate();
+            }
+            Self::Int(i) => {
+                builder.build_for_i64(*i);
+            }
+            Self::Binary {
+                refcounted: true, ..
+            } => (),
+            Self::Binary { bytes, .. } => {
+                builder.build_heap_binary(bytes.len());
+            }
+            Self::Pid { .. } => {
+                builder.build_pid();
+            }
+            Self::List { elements, tail } => {
+                builder.build_list(elements.len());
+                elements
+                    .iter()
+                    .for_each(|element| element.build_layout(builder));
+                if let Some(tail) = tail {
+                    tail.build_layout(builder);
+                }
+            }
+            Self::Tuple(elements) => {
+                builder.build_tuple(elements.len());
+                elements
+                    .iter()
+                    .for_each(|element| element.build_layout(builder));
+            }
+            Self::Map(entries) => {
+                builder.build_map(entries.len());
+                for (key, value) in entries.iter() {
+                    key.build_layout(builder);
+                    value.build_layout(builder);
+                }
+            }
+        }
+    }
+
+    /// Builds the described term on `heap`
+    ///
+    /// Returns `Err` if `heap` runs out of space, see [`TermSpec::layout`].
+    pub fn to_term_in<H: ?Sized + Heap>(&self, heap: &H) -> Result<Term, AllocError> {
+        let term = match self {
+            Self::Nil => Term::Nil,
+            Self::Bool(b) => Term::Bool(*b),
+            Self::Atom(a) => Term::Atom(*a),
+            Self::Int(i) => match Term::try_from(*i) {
+                Ok(term) => term,
+                Err(_) => Term::BigInt(Gc::new_in(BigInt::from(*i), heap)?),
+            },
+            Self::Float(f) => Term::Float((*f).into()),
+            Self::Binary {
+                bytes,
+                refcounted: true,
+            } => Term::RcBinary(BinaryData::from_bytes(bytes)),
+            Self::Binary { bytes, .. } => {
+                Term::HeapBinary(BinaryData::from_small_bytes(bytes, heap)?)
+            }
+            Self::Pid { number, serial } => {
+                let pid = Pid::new(*number, *serial).unwrap();
+                Term::Pid(Gc::new_in(pid, heap)?)
+            }
+            Self::List { elements, tail } => {
+                let mut list = match tail {
+                    Some(tail) => tail.to_term_in(heap)?.into(),
+                    None => OpaqueTerm::NIL,
+                };
+                for element in elements.iter().rev() {
+                    let head = element.to_term_in(heap)?.into();
+                    list = Cons::new_in(Cons { head, tail: list }, heap)?.into();
+                }
+                list.into()
+            }
+            Self::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_term_in(heap).map(OpaqueTerm::from))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Term::Tuple(Tuple::from_slice(&elements, heap)?)
+            }
+            Self::Map(entries) => {
+                let mut kvs: Vec<(OpaqueTerm, OpaqueTerm)> = Vec::with_capacity(entries.len());
+                for (key, value) in entries.iter() {
+                    let key: Term = key.to_term_in(heap)?;
+                    if kvs.iter().any(|(k, _)| key.exact_eq(&(*k).into())) {
+                        continue;
+                    }
+                    kvs.push((key.into(), value.to_term_in(heap)?.into()));
+                }
+                let map = Map::from_iter(kvs.into_iter(), heap).map_err(|_| AllocError)?;
+                Term::Map(map)
+            }
+        };
+        Ok(term)
+    }
+}
+
+/// Generates terms which do not contain other terms
+pub fn leaf(config: Config) -> BoxedStrategy<TermSpec> {
+    prop_oneof![
+        Just(TermSpec::Nil),
+        is_boolean(),
+        atom(),
+        integer(),
+        float(),
+        binary(config.binaries),
+        pid(),
+    ]
+    .boxed()
+}
+
+/// Generates containers of terms generated by `element`
+pub fn container(element: BoxedStrategy<TermSpec>, config: Config) -> BoxedStrategy<TermSpec> {
+    prop_oneof![
+        list(element.clone(), config),
+        tuple(element.clone(), config),
+        map(element, config),
+    ]
+    .boxed()
+}
+
+pub fn is_boolean() -> BoxedStrategy<TermSpec> {
+    any::<bool>().prop_map(TermSpec::Bool).boxed()
+}
+
+/// Generates atoms from a bounded set of names, so as not to exhaust the atom table
+pub fn atom() -> BoxedStrategy<TermSpec> {
+    "[a-z][a-z0-9_]{0,15}"
+        .prop_map(|name| TermSpec::Atom(Atom::try_from(name.as_str()).unwrap()))
+        .boxed()
+}
+
+pub fn integer() -> BoxedStrategy<TermSpec> {
+    prop_oneof![small_integer(), big_integer()].boxed()
+}
+
+/// Generates integers which are represented as immediates
+pub fn small_integer() -> BoxedStrategy<TermSpec> {
+    (Int::MIN_SMALL..=Int::MAX_SMALL)
+        .prop_map(TermSpec::Int)
+        .boxed()
+}
+
+/// Generates integers which are too large to be represented as immediates
+pub fn big_integer() -> BoxedStrategy<TermSpec> {
+    prop_oneof![i64::MIN..Int::MIN_SMALL, (Int::MAX_SMALL + 1)..=i64::MAX]
+        .prop_map(TermSpec::Int)
+        .boxed()
+}
+
+pub fn float() -> BoxedStrategy<TermSpec> {
+    any::<f64>()
+        .prop_filter("Erlang floats are finite", |f| f.is_finite())
+        .prop_filter("Negative and positive 0.0 are the same for Erlang", |f| {
+            !(f.classify() == FpCategory::Zero && f.is_sign_negative())
+        })
+        .prop_map(TermSpec::Float)
+        .boxed()
+}
+
+pub fn binary(placement: BinaryPlacement) -> BoxedStrategy<TermSpec> {
+    let heap =
+        vec(any::<u8>(), 0..=BinaryData::MAX_HEAP_BYTES).prop_map(|bytes| TermSpec::Binary {
+            bytes,
+            refcounted: false,
+        });
+    let refcounted =
+        vec(any::<u8>(), 0..=(BinaryData::MAX_HEAP_BYTES * 4)).prop_map(|bytes| TermSpec::Binary {
+            bytes,
+            refcounted: true,
+        });
+    match placement {
+        BinaryPlacement::Heap => heap.boxed(),
+        BinaryPlacement::RefCounted => refcounted.boxed(),
+        BinaryPlacement::Any => prop_oneof![heap, refcounted].boxed(),
+    }
+}
+
+/// Generates local pids, which need not refer to a live process
+pub fn pid() -> BoxedStrategy<TermSpec> {
+    (0..(1usize << 15), 0..(1usize << 13))
+        .prop_map(|(number, serial)| TermSpec::Pid { number, serial })
+        .boxed()
+}
+
+/// Generates lists of terms generated by `element`, which may be improper if
+/// `config.improper_lists` is set
+pub fn list(element: BoxedStrategy<TermSpec>, config: Config) -> BoxedStrategy<TermSpec> {
+    let tail = if config.improper_lists {
+        option::of(
+            leaf(config).prop_filter("The tail of an improper list is not a list", |t| {
+                *t != TermSpec::Nil
+            }),
+        )
+        .boxed()
+    } else {
+        Just(None).boxed()
+    };
+    (vec(element, 0..=config.max_len), tail)
+        .prop_map(|(elements, tail)| {
+            if elements.is_empty() {
+                TermSpec::Nil
+            } else {
+                TermSpec::List {
+                    elements,
+                    tail: tail.map(Box::new),
+                }
+            }
+        })
+        .boxed()
+}
+
+pub fn tuple(element: BoxedStrategy<TermSpec>, config: Config) -> BoxedStrategy<TermSpec> {
+    vec(element, 0..=config.max_len)
+        .prop_map(TermSpec::Tuple)
+        .boxed()
+}
+
+/// Generates maps with keys generated by [`leaf`], and values generated by `element`
+pub fn map(element: BoxedStrategy<TermSpec>, config: Config) -> BoxedStrategy<TermSpec> {
+    let max_len = config.max_len.min(SMALL_MAP_LIMIT);
+    vec((leaf(config), element), 0..=max_len)
+        .prop_map(TermSpec::Map)
+        .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use firefly_alloc::heap::FixedSizeHeap;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn term_spec_fits_layout_test(spec in super::super::term(Config::default())) {
+            let heap = FixedSizeHeap::<16384>::default();
+            prop_assume!(spec.layout().size() <= heap.heap_available());
+            let term = spec.to_term_in(&heap);
+            prop_assert!(term.is_ok());
+            prop_assert!(heap.heap_used() <= spec.layout().size());
+        }
+    }
+}
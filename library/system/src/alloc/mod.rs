@@ -1,6 +1,54 @@
 use core::alloc::{AllocError, Layout};
 use core::ptr::{self, NonNull};
 
+/// Statistics reported by the malloc implementation backing the system allocator
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// The number of bytes currently allocated
+    pub allocated: usize,
+    /// The number of bytes of physical memory held by the allocator, including free memory which
+    /// has not been returned to the operating system
+    pub resident: usize,
+}
+
+/// The kind of pages backing a memory mapping
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PageKind {
+    /// Regular pages of the system page size
+    Normal,
+    /// Regular pages, which the kernel has been advised to back with transparent huge pages
+    ///
+    /// Whether or not the mapping is actually backed by huge pages is up to the kernel.
+    TransparentHuge,
+    /// Huge pages explicitly reserved by the system administrator, e.g. `vm.nr_hugepages`
+    Huge,
+}
+
+#[cfg(unix)]
+pub use crate::arch::alloc::{allocator_name, prefault, release_free_memory, stats};
+
+/// Returns the name of the malloc implementation in use
+#[cfg(not(unix))]
+pub fn allocator_name() -> &'static str {
+    "system"
+}
+
+/// Returns statistics about the memory managed by the allocator, if supported
+#[cfg(not(unix))]
+pub fn stats() -> Option<AllocatorStats> {
+    None
+}
+
+/// Returns free memory held by the allocator to the operating system, where supported
+#[cfg(not(unix))]
+pub fn release_free_memory() {}
+
+/// Faults in memory held by the allocator ahead of its use, which is not supported here
+#[cfg(not(unix))]
+pub fn prefault(_size: usize) -> usize {
+    0
+}
+
 /// Fallback for realloc that allocates a new region, copies old data
 /// into the new region, and frees the old region.
 #[inline]
@@ -0,0 +1,329 @@
+//! Detection of resource limits imposed on the current process by Linux control groups.
+//!
+//! Container runtimes (Docker, Kubernetes, systemd, etc.) typically limit the resources available
+//! to a process using cgroups, rather than by limiting what the process can observe via the usual
+//! system interfaces. For example, a container with a CPU quota of 2 on a 64-core machine will
+//! still see 64 processors via `sysconf`, and if it sizes its thread pools accordingly, will be
+//! heavily throttled. This module reads the effective limits for the current process from either
+//! cgroup v1 or v2, so that the runtime can size itself appropriately.
+//!
+//! On platforms other than Linux, no limits are ever detected.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::sync::OnceLock;
+
+static LIMITS: OnceLock<CgroupLimits> = OnceLock::new();
+
+/// The resource limits imposed on the current process by its control groups
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CgroupLimits {
+    /// The CPU bandwidth available to this process, as a fraction of the number of processors,
+    /// i.e. a quota of `1.5` permits the use of one and a half processors worth of CPU time
+    pub cpu_quota: Option<f64>,
+    /// The maximum amount of memory, in bytes, that this process may use
+    pub memory_limit: Option<u64>,
+}
+impl CgroupLimits {
+    /// Returns the number of processors this process can make full use of given its CPU quota,
+    /// rounded up, if a quota is set
+    pub fn cpus(&self) -> Option<usize> {
+        self.cpu_quota.map(|quota| (quota.ceil() as usize).max(1))
+    }
+}
+
+/// Returns the resource limits imposed on the current process by its control groups
+///
+/// The limits are detected the first time this is called, and are cached thereafter.
+pub fn limits() -> CgroupLimits {
+    *LIMITS.get_or_init(detect)
+}
+
+/// Returns the directory of the cgroup v2 group this process belongs to, if any
+///
+/// This can be used to access other interfaces of the group, e.g. `memory.pressure`.
+#[cfg(target_os = "linux")]
+pub fn unified_dir() -> Option<PathBuf> {
+    let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    let memberships = parse_cgroups(&cgroups);
+    let membership = memberships.iter().find(|m| m.hierarchy == 0)?;
+    let mounts = parse_mountinfo(&mountinfo);
+    mounts
+        .iter()
+        .find(|m| m.fstype == "cgroup2")?
+        .resolve(&membership.path)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn unified_dir() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> CgroupLimits {
+    let Ok(cgroups) = fs::read_to_string("/proc/self/cgroup") else { return CgroupLimits::default(); };
+    let Ok(mountinfo) = fs::read_to_string("/proc/self/mountinfo") else { return CgroupLimits::default(); };
+    let mounts = parse_mountinfo(&mountinfo);
+    let memberships = parse_cgroups(&cgroups);
+
+    let mut limits = CgroupLimits::default();
+    // If the unified (v2) hierarchy is in use, it governs all controllers
+    let unified = memberships.iter().find(|m| m.hierarchy == 0);
+    let v2_mount = mounts.iter().find(|m| m.fstype == "cgroup2");
+    if let (Some(membership), Some(mount)) = (unified, v2_mount) {
+        if let Some(dir) = mount.resolve(&membership.path) {
+            for dir in ancestors(&dir, &mount.mountpoint) {
+                if let Some(quota) = read(&dir.join("cpu.max")).and_then(|s| parse_cpu_max(&s)) {
+                    limits.cpu_quota = min(limits.cpu_quota, quota);
+                }
+                if let Some(limit) = read(&dir.join("memory.max")).and_then(|s| parse_limit(&s)) {
+                    limits.memory_limit = min(limits.memory_limit, limit);
+                }
+            }
+        }
+        if limits != CgroupLimits::default() {
+            return limits;
+        }
+    }
+
+    // Otherwise, fall back to the v1 hierarchies for the cpu and memory controllers
+    for membership in memberships.iter().filter(|m| m.hierarchy != 0) {
+        let has = |controller: &str| membership.controllers.iter().any(|c| c == controller);
+        let mount = mounts
+            .iter()
+            .find(|m| m.fstype == "cgroup" && m.options.iter().any(|o| has(o)));
+        let Some(mount) = mount else { continue; };
+        let Some(dir) = mount.resolve(&membership.path) else { continue; };
+        for dir in ancestors(&dir, &mount.mountpoint) {
+            if has("cpu") {
+                let quota = read(&dir.join("cpu.cfs_quota_us"));
+                let period = read(&dir.join("cpu.cfs_period_us"));
+                if let Some(quota) = quota.zip(period).and_then(|(q, p)| parse_cfs_quota(&q, &p)) {
+                    limits.cpu_quota = min(limits.cpu_quota, quota);
+                }
+            }
+            if has("memory") {
+                let limit = read(&dir.join("memory.limit_in_bytes")).and_then(|s| parse_limit(&s));
+                if let Some(limit) = limit {
+                    limits.memory_limit = min(limits.memory_limit, limit);
+                }
+            }
+        }
+    }
+
+    limits
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect() -> CgroupLimits {
+    CgroupLimits::default()
+}
+
+fn min<T: PartialOrd>(current: Option<T>, value: T) -> Option<T> {
+    match current {
+        Some(current) if current <= value => Some(current),
+        _ => Some(value),
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+/// Returns `dir` and each of its parents up to and including `root`
+///
+/// Limits are inherited by child groups, but are not reflected in the child's own configuration,
+/// so the effective limit is the smallest one set anywhere along the path.
+fn ancestors<'a>(dir: &'a Path, root: &'a Path) -> impl Iterator<Item = &'a Path> + 'a {
+    dir.ancestors().take_while(move |dir| dir.starts_with(root))
+}
+
+/// A cgroup hierarchy the current process belongs to, from `/proc/self/cgroup`
+#[derive(Debug, PartialEq)]
+struct Membership {
+    /// The hierarchy id, which is always 0 for the unified (v2) hierarchy
+    hierarchy: u32,
+    controllers: Vec<String>,
+    path: String,
+}
+
+/// A mounted cgroup filesystem, from `/proc/self/mountinfo`
+#[derive(Debug, PartialEq)]
+struct Mount {
+    /// The path within the hierarchy which is mounted, e.g. `/` or the container's own group
+    root: String,
+    mountpoint: PathBuf,
+    fstype: String,
+    options: Vec<String>,
+}
+impl Mount {
+    /// Resolves the path of a group in this hierarchy to a directory in the mounted filesystem,
+    /// if the group is visible via this mount
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let relative = if self.root == "/" {
+            path
+        } else if path == self.root {
+            ""
+        } else {
+            // Make sure we don't match a sibling group which shares a prefix with the root
+            let rest = path.strip_prefix(self.root.as_str())?;
+            if !rest.starts_with('/') {
+                return None;
+            }
+            rest
+        };
+        Some(self.mountpoint.join(relative.trim_start_matches('/')))
+    }
+}
+
+/// Parses `/proc/self/cgroup`, where each line is of the form `hierarchy:controllers:path`
+fn parse_cgroups(contents: &str) -> Vec<Membership> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ':');
+            let hierarchy = parts.next()?.parse().ok()?;
+            let controllers = parts
+                .next()?
+                .split(',')
+                .filter(|c| !c.is_empty())
+                .map(|c| c.to_string())
+                .collect();
+            let path = parts.next()?.to_string();
+            Some(Membership {
+                hierarchy,
+                controllers,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Parses the cgroup mounts from `/proc/self/mountinfo`
+///
+/// Each line is of the form `id parent dev root mountpoint options [optional...] - fstype source
+/// superoptions`, and for v1 hierarchies the controllers are listed in the superoptions.
+fn parse_mountinfo(contents: &str) -> Vec<Mount> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (mount, fs) = line.split_once(" - ")?;
+            let mut mount = mount.split(' ');
+            let root = mount.nth(3)?.to_string();
+            let mountpoint = PathBuf::from(mount.next()?);
+            let mut fs = fs.split(' ');
+            let fstype = fs.next()?.to_string();
+            if fstype != "cgroup" && fstype != "cgroup2" {
+                return None;
+            }
+            let options = fs
+                .nth(1)
+                .unwrap_or("")
+                .split(',')
+                .map(|o| o.to_string())
+                .collect();
+            Some(Mount {
+                root,
+                mountpoint,
+                fstype,
+                options,
+            })
+        })
+        .collect()
+}
+
+/// Parses the v2 `cpu.max` file, which contains `$MAX $PERIOD`, where `$MAX` may be `max`
+fn parse_cpu_max(contents: &str) -> Option<f64> {
+    let mut parts = contents.split_whitespace();
+    let quota = parts.next()?.parse::<u64>().ok()?;
+    let period = parts.next()?.parse::<u64>().ok()?;
+    if quota == 0 || period == 0 {
+        return None;
+    }
+    Some(quota as f64 / period as f64)
+}
+
+/// Parses the v1 `cpu.cfs_quota_us` and `cpu.cfs_period_us` files, where a quota of `-1` means
+/// no limit is set
+fn parse_cfs_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota = quota.trim().parse::<i64>().ok()?;
+    let period = period.trim().parse::<i64>().ok()?;
+    if quota <= 0 || period <= 0 {
+        return None;
+    }
+    Some(quota as f64 / period as f64)
+}
+
+/// Parses a memory limit, which in v2 is `max` when no limit is set, and in v1 is an absurdly
+/// large value (the largest page-aligned `i64`) when no limit is set
+fn parse_limit(contents: &str) -> Option<u64> {
+    const V1_UNLIMITED: u64 = i64::MAX as u64 & !0xfff;
+
+    let limit = contents.trim().parse::<u64>().ok()?;
+    if limit >= V1_UNLIMITED {
+        None
+    } else {
+        Some(limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cgroup_parse_test() {
+        let cgroups = parse_cgroups("12:cpu,cpuacct:/docker/abc\n11:memory:/docker/abc\n0::/\n");
+        assert_eq!(cgroups.len(), 3);
+        assert_eq!(cgroups[0].hierarchy, 12);
+        assert_eq!(cgroups[0].controllers, ["cpu", "cpuacct"]);
+        assert_eq!(cgroups[0].path, "/docker/abc");
+        assert_eq!(cgroups[2].hierarchy, 0);
+        assert!(cgroups[2].controllers.is_empty());
+
+        let mounts = parse_mountinfo(
+            "30 23 0:26 / /sys/fs/cgroup rw,nosuid shared:4 - cgroup2 cgroup2 rw,nsdelegate\n\
+             35 25 0:31 /docker/abc /sys/fs/cgroup/cpu,cpuacct ro,nosuid - cgroup cgroup rw,cpu,cpuacct\n\
+             22 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n",
+        );
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0].fstype, "cgroup2");
+        assert_eq!(mounts[0].mountpoint, Path::new("/sys/fs/cgroup"));
+        assert_eq!(mounts[1].root, "/docker/abc");
+        assert!(mounts[1].options.iter().any(|o| o == "cpu"));
+
+        // The container's own group is mounted at the root of the hierarchy
+        assert_eq!(
+            mounts[1].resolve("/docker/abc"),
+            Some(PathBuf::from("/sys/fs/cgroup/cpu,cpuacct"))
+        );
+        assert_eq!(
+            mounts[1].resolve("/docker/abc/child"),
+            Some(PathBuf::from("/sys/fs/cgroup/cpu,cpuacct/child"))
+        );
+        assert_eq!(mounts[1].resolve("/docker/abcd"), None);
+        assert_eq!(
+            mounts[0].resolve("/system.slice/foo.service"),
+            Some(PathBuf::from("/sys/fs/cgroup/system.slice/foo.service"))
+        );
+
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max("150000 100000\n"), Some(1.5));
+        assert_eq!(parse_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cfs_quota("200000\n", "100000\n"), Some(2.0));
+        assert_eq!(parse_limit("max\n"), None);
+        assert_eq!(parse_limit("9223372036854771712\n"), None);
+        assert_eq!(parse_limit("536870912\n"), Some(536870912));
+
+        let limits = CgroupLimits {
+            cpu_quota: Some(1.5),
+            memory_limit: None,
+        };
+        assert_eq!(limits.cpus(), Some(2));
+    }
+}
@@ -42,6 +42,8 @@ cfg_if::cfg_if! {
 }
 
 pub mod alloc;
+pub mod cgroup;
 pub mod mem;
+pub mod numa;
 pub mod sync;
 pub mod time;
@@ -10,6 +10,12 @@ pub fn page_size() -> usize {
     crate::arch::page_size()
 }
 
+/// Returns the size of a huge page in bytes
+#[inline]
+pub fn huge_page_size() -> usize {
+    crate::arch::huge_page_size()
+}
+
 #[macro_export]
 macro_rules! offset_of {
     ($strukt:path, $field:ident) => {{
@@ -0,0 +1,174 @@
+//! Detection of the NUMA topology of the host, and placement of threads and their memory on
+//! specific NUMA nodes.
+//!
+//! On multi-socket machines, memory attached to a remote node is significantly slower to access
+//! than local memory, so the runtime groups its schedulers by node, binds each scheduler thread to
+//! the processors of its node, and prefers to allocate the memory touched by that thread from the
+//! same node. Since carriers are populated on first touch, this keeps the heaps of the processes
+//! running on a scheduler local to it.
+//!
+//! On platforms other than Linux, the topology always consists of a single node containing all
+//! processors, and binding is a no-op.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::fs;
+use std::io;
+use std::vec::Vec;
+
+use crate::sync::OnceLock;
+
+static TOPOLOGY: OnceLock<Vec<NumaNode>> = OnceLock::new();
+
+/// A NUMA node, and the logical processors attached to it that this process may run on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// Returns the NUMA nodes this process may run on, ordered by id
+///
+/// Nodes with no processors available to this process, e.g. due to its affinity mask, are
+/// omitted. The topology is detected the first time this is called, and cached thereafter.
+pub fn topology() -> &'static [NumaNode] {
+    TOPOLOGY.get_or_init(detect).as_slice()
+}
+
+/// Returns true if this process may run on more than one NUMA node
+pub fn is_numa() -> bool {
+    topology().len() > 1
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> Vec<NumaNode> {
+    let allowed = allowed_cpus();
+    let mut nodes = Vec::new();
+    if let Ok(entries) = fs::read_dir("/sys/devices/system/node") {
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let id = name.to_str().and_then(|n| n.strip_prefix("node"));
+            let Some(Ok(id)) = id.map(str::parse::<usize>) else { continue; };
+            let Ok(cpulist) = fs::read_to_string(entry.path().join("cpulist")) else { continue; };
+            let cpus = parse_cpulist(&cpulist)
+                .into_iter()
+                .filter(|cpu| allowed.as_ref().map(|a| a.contains(cpu)).unwrap_or(true))
+                .collect::<Vec<_>>();
+            if !cpus.is_empty() {
+                nodes.push(NumaNode { id, cpus });
+            }
+        }
+    }
+    if nodes.is_empty() {
+        return single_node();
+    }
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect() -> Vec<NumaNode> {
+    single_node()
+}
+
+fn single_node() -> Vec<NumaNode> {
+    #[cfg(unix)]
+    let cpus = crate::arch::num_cpus();
+    #[cfg(not(unix))]
+    let cpus = 1;
+    std::vec![NumaNode {
+        id: 0,
+        cpus: (0..cpus).collect(),
+    }]
+}
+
+/// Returns the processors in the affinity mask of this process, if available
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Option<Vec<usize>> {
+    use core::mem;
+
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    if unsafe { libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) } != 0 {
+        return None;
+    }
+    Some(
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+            .collect(),
+    )
+}
+
+/// Binds the calling thread to the processors of `node`, and sets its memory policy to prefer
+/// allocating from `node`
+///
+/// The memory policy applies to pages first touched by this thread after this call, whichever
+/// allocator they were obtained from.
+#[cfg(target_os = "linux")]
+pub fn bind_current_thread(node: &NumaNode) -> io::Result<()> {
+    use core::mem;
+
+    /// See `set_mempolicy(2)`
+    const MPOL_PREFERRED: libc::c_int = 1;
+
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for cpu in node.cpus.iter().copied() {
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    if unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let word_bits = mem::size_of::<libc::c_ulong>() * 8;
+    let mut nodemask = std::vec![0 as libc::c_ulong; node.id / word_bits + 1];
+    nodemask[node.id / word_bits] |= 1 << (node.id % word_bits);
+    // The kernel considers one less than `maxnode` bits of the mask
+    let maxnode = (nodemask.len() * word_bits) as libc::c_ulong;
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_set_mempolicy,
+            MPOL_PREFERRED,
+            nodemask.as_ptr(),
+            maxnode + 1,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bind_current_thread(_node: &NumaNode) -> io::Result<()> {
+    Ok(())
+}
+
+/// Parses a list of processors in the format used by sysfs, e.g. `0-3,8,10-11`
+fn parse_cpulist(contents: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for range in contents.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => {
+                if let Ok(cpu) = range.parse::<usize>() {
+                    cpus.push(cpu);
+                }
+            }
+        }
+    }
+    cpus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numa_parse_cpulist_test() {
+        assert_eq!(parse_cpulist("0-3,8,10-11\n"), [0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpulist("5\n"), [5]);
+        assert!(parse_cpulist("\n").is_empty());
+    }
+}
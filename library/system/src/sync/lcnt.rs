@@ -0,0 +1,185 @@
+//! Lock contention counting, the equivalent of `lcnt` in ERTS.
+//!
+//! Each of the runtime's internal locks is associated with a statically allocated [`LockClass`],
+//! through which it is acquired. When the `lcnt` feature is enabled, every acquisition is counted,
+//! and when the lock could not be acquired immediately, the acquisition is counted as a collision,
+//! and the time spent waiting for the lock is recorded. Locks which are instantiated many times,
+//! e.g. per-process locks, share one class, so the statistics are aggregated over all instances.
+//! Lock-free structures, such as the process registry, are not covered.
+//!
+//! When the feature is disabled, acquiring a lock through its class is equivalent to acquiring it
+//! directly, and no statistics are recorded.
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+
+use std::vec::Vec;
+
+use super::{const_mutex, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// All lock classes which have been acquired at least once since the last call to [`clear`]
+static CLASSES: Mutex<Vec<&'static LockClass>> = const_mutex(Vec::new());
+
+/// A statically allocated set of counters shared by all instances of a particular lock
+pub struct LockClass {
+    name: &'static str,
+    registered: AtomicBool,
+    tries: AtomicU64,
+    collisions: AtomicU64,
+    /// The total time spent waiting on the lock, in nanoseconds
+    wait_time: AtomicU64,
+}
+
+/// A snapshot of the statistics for a [`LockClass`], see [`collect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockStats {
+    pub name: &'static str,
+    /// The number of times the lock was acquired
+    pub tries: u64,
+    /// The number of times the lock was already held when acquisition was attempted
+    pub collisions: u64,
+    /// The total time spent waiting to acquire the lock
+    pub wait_time: Duration,
+}
+
+/// Returns true if the runtime was built with lock counting enabled
+#[inline(always)]
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "lcnt")
+}
+
+/// Returns the statistics of all lock classes acquired since counting was last cleared, ordered
+/// by name
+pub fn collect() -> Vec<LockStats> {
+    let mut stats = CLASSES
+        .lock()
+        .iter()
+        .map(|class| class.stats())
+        .collect::<Vec<_>>();
+    stats.sort_by_key(|stats| stats.name);
+    stats
+}
+
+/// Resets the statistics of all lock classes
+pub fn clear() {
+    let mut classes = CLASSES.lock();
+    for class in classes.drain(..) {
+        class.tries.store(0, Ordering::Relaxed);
+        class.collisions.store(0, Ordering::Relaxed);
+        class.wait_time.store(0, Ordering::Relaxed);
+        class.registered.store(false, Ordering::Release);
+    }
+}
+
+impl LockClass {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            registered: AtomicBool::new(false),
+            tries: AtomicU64::new(0),
+            collisions: AtomicU64::new(0),
+            wait_time: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn stats(&self) -> LockStats {
+        LockStats {
+            name: self.name,
+            tries: self.tries.load(Ordering::Relaxed),
+            collisions: self.collisions.load(Ordering::Relaxed),
+            wait_time: Duration::from_nanos(self.wait_time.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Acquires `mutex`, which must be an instance of this class
+    #[inline]
+    pub fn lock<'a, T: ?Sized>(&'static self, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        if !is_enabled() {
+            return mutex.lock();
+        }
+        if let Some(guard) = mutex.try_lock() {
+            self.record(None);
+            return guard;
+        }
+        let start = crate::time::Instant::now();
+        let guard = mutex.lock();
+        self.record(Some(start.elapsed()));
+        guard
+    }
+
+    /// Acquires `lock` for reading, `lock` must be an instance of this class
+    #[inline]
+    pub fn read<'a, T: ?Sized>(&'static self, lock: &'a RwLock<T>) -> RwLockReadGuard<'a, T> {
+        if !is_enabled() {
+            return lock.read();
+        }
+        if let Some(guard) = lock.try_read() {
+            self.record(None);
+            return guard;
+        }
+        let start = crate::time::Instant::now();
+        let guard = lock.read();
+        self.record(Some(start.elapsed()));
+        guard
+    }
+
+    /// Acquires `lock` for writing, `lock` must be an instance of this class
+    #[inline]
+    pub fn write<'a, T: ?Sized>(&'static self, lock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+        if !is_enabled() {
+            return lock.write();
+        }
+        if let Some(guard) = lock.try_write() {
+            self.record(None);
+            return guard;
+        }
+        let start = crate::time::Instant::now();
+        let guard = lock.write();
+        self.record(Some(start.elapsed()));
+        guard
+    }
+
+    /// Records an acquisition, and the time spent waiting if it collided
+    #[cold]
+    fn record(&'static self, waited: Option<Duration>) {
+        if !self.registered.swap(true, Ordering::AcqRel) {
+            CLASSES.lock().push(self);
+        }
+        self.tries.fetch_add(1, Ordering::Relaxed);
+        if let Some(waited) = waited {
+            self.collisions.fetch_add(1, Ordering::Relaxed);
+            self.wait_time
+                .fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "lcnt"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lcnt_collision_test() {
+        static CLASS: LockClass = LockClass::new("lcnt_collision_test");
+        let mutex = Mutex::new(0);
+
+        *CLASS.lock(&mutex) += 1;
+        let guard = mutex.lock();
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| *CLASS.lock(&mutex) += 1);
+            std::thread::sleep(Duration::from_millis(10));
+            drop(guard);
+            waiter.join().unwrap();
+        });
+
+        let stats = CLASS.stats();
+        assert_eq!(stats.tries, 2);
+        assert_eq!(stats.collisions, 1);
+        assert!(stats.wait_time > Duration::ZERO);
+        assert!(collect().iter().any(|s| s.name == "lcnt_collision_test"));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod lcnt;
 mod once;
 
 // FairMutex is useful for the kinds of things we'd use spinlocks for.
@@ -1,3 +1,5 @@
+pub mod clock;
+
 pub use core::time::Duration;
 
 pub use crate::arch::time::{Instant, SystemTime, SystemTimeError, UNIX_EPOCH};
@@ -18,8 +20,10 @@ use core::ops::{Add, AddAssign, Sub, SubAssign};
 pub struct MonotonicTime(Instant);
 impl MonotonicTime {
     /// Get the current monotonic time
+    ///
+    /// This is read from the virtual clock, see [`clock`].
     pub fn now() -> Self {
-        Self(Instant::now())
+        Self(clock::monotonic_now())
     }
 
     /// Get the monotonic time at which `timeout` will expire
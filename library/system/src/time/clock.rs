@@ -0,0 +1,121 @@
+//! A virtual clock, which can be frozen and advanced programmatically.
+//!
+//! All of the runtime's notions of the current time, i.e. [`MonotonicTime::now`], [`system_now`],
+//! and the timer wheel, which is driven by [`MonotonicTime`], are derived from this clock. By
+//! default it simply follows the system clocks, but tests, or an embedder, can [`freeze`] it, at
+//! which point time stands still until it is moved forward explicitly with [`advance`]. This
+//! allows logic which depends on timeouts to be tested instantly and deterministically: rather
+//! than sleeping, a test advances the clock past the timeout, and any timers which expire as a
+//! result fire the next time the timer wheel ticks.
+//!
+//! Time only ever moves forward. When the clock is unfrozen it follows the system clocks again,
+//! but any time it was advanced by is kept.
+//!
+//! [`MonotonicTime::now`]: super::MonotonicTime::now
+//! [`MonotonicTime`]: super::MonotonicTime
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use super::{Duration, Instant, SystemTime};
+use crate::sync::{const_mutex, Mutex};
+
+/// Set when the clock is frozen, so that the common case can avoid taking a lock
+static FROZEN: AtomicBool = AtomicBool::new(false);
+/// The total time the clock has been advanced by, in nanoseconds
+static OFFSET: AtomicU64 = AtomicU64::new(0);
+/// The real monotonic and system time at which the clock was frozen
+static FROZEN_AT: Mutex<Option<(Instant, SystemTime)>> = const_mutex(None);
+
+/// Returns true if the clock is currently frozen
+#[inline]
+pub fn is_frozen() -> bool {
+    FROZEN.load(Ordering::Acquire)
+}
+
+/// Stops the clock, so that time only passes when [`advance`] is called
+///
+/// This has no effect if the clock is already frozen.
+pub fn freeze() {
+    let mut frozen_at = FROZEN_AT.lock();
+    if frozen_at.is_none() {
+        *frozen_at = Some((Instant::now(), SystemTime::now()));
+        FROZEN.store(true, Ordering::Release);
+    }
+}
+
+/// Restarts the clock, so that it follows the system clocks again
+///
+/// Since time cannot go backwards, the time which really passed while the clock was frozen is
+/// skipped over when the clock is restarted.
+pub fn unfreeze() {
+    let mut frozen_at = FROZEN_AT.lock();
+    *frozen_at = None;
+    FROZEN.store(false, Ordering::Release);
+}
+
+/// Moves the clock forward by `duration`
+///
+/// This can be used whether or not the clock is frozen.
+pub fn advance(duration: Duration) {
+    let nanos = duration.as_nanos().try_into().unwrap_or(u64::MAX);
+    OFFSET.fetch_add(nanos, Ordering::AcqRel);
+}
+
+/// Returns the total time the clock has been advanced by
+pub fn offset() -> Duration {
+    Duration::from_nanos(OFFSET.load(Ordering::Acquire))
+}
+
+/// Returns the current monotonic time according to this clock
+#[inline]
+pub fn monotonic_now() -> Instant {
+    match frozen_at() {
+        Some((instant, _)) => instant + offset(),
+        None => Instant::now() + offset(),
+    }
+}
+
+/// Returns the current system time according to this clock
+#[inline]
+pub fn system_now() -> SystemTime {
+    match frozen_at() {
+        Some((_, time)) => time + offset(),
+        None => SystemTime::now() + offset(),
+    }
+}
+
+#[inline]
+fn frozen_at() -> Option<(Instant, SystemTime)> {
+    if is_frozen() {
+        *FROZEN_AT.lock()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MonotonicTime;
+
+    #[test]
+    fn virtual_clock_test() {
+        freeze();
+        assert!(is_frozen());
+        let start = MonotonicTime::now();
+        let system_start = system_now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(MonotonicTime::now(), start);
+        assert_eq!(system_now(), system_start);
+
+        advance(Duration::from_secs(60));
+        assert_eq!(MonotonicTime::now() - start, Duration::from_secs(60));
+        assert_eq!(
+            system_now().duration_since(system_start).unwrap(),
+            Duration::from_secs(60)
+        );
+
+        unfreeze();
+        assert!(!is_frozen());
+        assert!(MonotonicTime::now() - start >= Duration::from_secs(60));
+    }
+}
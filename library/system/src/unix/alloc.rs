@@ -1,14 +1,57 @@
 use core::alloc::{AllocError, Layout};
 use core::ptr::{self, NonNull};
 
-use crate::alloc::realloc_fallback;
+use crate::alloc::{realloc_fallback, AllocatorStats};
 use crate::MIN_ALIGN;
 
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("the `jemalloc` and `mimalloc` features are mutually exclusive");
+
+/// The malloc implementation backing all allocations made via this module
+mod backend {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "jemalloc")] {
+            pub use tikv_jemalloc_sys::{calloc, free, malloc, posix_memalign, realloc};
+
+            pub const NAME: &'static str = "jemalloc";
+        } else if #[cfg(feature = "mimalloc")] {
+            pub use libmimalloc_sys::{
+                mi_calloc as calloc, mi_free as free, mi_malloc as malloc, mi_realloc as realloc,
+            };
+
+            pub const NAME: &'static str = "mimalloc";
+
+            pub unsafe fn posix_memalign(
+                ptr: *mut *mut libc::c_void,
+                align: usize,
+                size: usize,
+            ) -> libc::c_int {
+                let result = libmimalloc_sys::mi_malloc_aligned(size, align);
+                if result.is_null() {
+                    return libc::ENOMEM;
+                }
+                *ptr = result;
+                0
+            }
+        } else {
+            pub use libc::{calloc, free, malloc, posix_memalign, realloc};
+
+            pub const NAME: &'static str = "system";
+        }
+    }
+}
+
+/// Returns the name of the malloc implementation in use
+#[inline]
+pub fn allocator_name() -> &'static str {
+    backend::NAME
+}
+
 #[inline]
 pub fn allocate(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
     let layout_size = layout.size();
     if layout.align() <= MIN_ALIGN && layout.align() <= layout_size {
-        NonNull::new(unsafe { libc::malloc(layout_size) as *mut u8 })
+        NonNull::new(unsafe { backend::malloc(layout_size) as *mut u8 })
             .ok_or(AllocError)
             .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout_size))
     } else {
@@ -26,7 +69,7 @@ pub fn allocate(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
 pub fn allocate_zeroed(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
     let layout_size = layout.size();
     if layout.align() <= MIN_ALIGN && layout.align() <= layout_size {
-        NonNull::new(unsafe { libc::calloc(layout_size, 1) as *mut u8 })
+        NonNull::new(unsafe { backend::calloc(layout_size, 1) as *mut u8 })
             .ok_or(AllocError)
             .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout_size))
     } else {
@@ -40,7 +83,7 @@ pub fn allocate_zeroed(layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
 
 #[inline]
 pub unsafe fn deallocate(ptr: NonNull<u8>, _layout: Layout) {
-    libc::free(ptr.as_ptr() as *mut libc::c_void)
+    backend::free(ptr.as_ptr() as *mut libc::c_void)
 }
 
 #[inline]
@@ -87,7 +130,7 @@ unsafe fn reallocate(
     let new_size = new_layout.size();
 
     if new_layout.align() <= MIN_ALIGN && new_layout.align() <= new_size {
-        NonNull::new(libc::realloc(ptr.as_ptr() as *mut libc::c_void, new_size) as *mut u8)
+        NonNull::new(backend::realloc(ptr.as_ptr() as *mut libc::c_void, new_size) as *mut u8)
             .ok_or(AllocError)
             .map(|ptr| NonNull::slice_from_raw_parts(ptr, new_size))
     } else {
@@ -99,7 +142,7 @@ unsafe fn reallocate(
 unsafe fn aligned_alloc(layout: &Layout) -> Result<NonNull<[u8]>, AllocError> {
     let mut ptr = ptr::null_mut();
     let layout_size = layout.size();
-    let result = libc::posix_memalign(&mut ptr, layout.align(), layout_size);
+    let result = backend::posix_memalign(&mut ptr, layout.align(), layout_size);
     if result != 0 {
         return Err(AllocError);
     }
@@ -108,3 +151,163 @@ unsafe fn aligned_alloc(layout: &Layout) -> Result<NonNull<[u8]>, AllocError> {
         layout_size,
     ))
 }
+
+/// Returns statistics about the memory managed by the allocator, if supported
+#[cfg(feature = "jemalloc")]
+pub fn stats() -> Option<AllocatorStats> {
+    use core::mem;
+    use tikv_jemalloc_sys::mallctl;
+
+    unsafe fn read(name: &[u8]) -> Option<usize> {
+        let mut value = 0usize;
+        let mut len = mem::size_of::<usize>();
+        let result = mallctl(
+            name.as_ptr().cast(),
+            (&mut value as *mut usize).cast(),
+            &mut len,
+            ptr::null_mut(),
+            0,
+        );
+        (result == 0).then_some(value)
+    }
+
+    unsafe {
+        // Statistics are cached by jemalloc until the epoch is advanced
+        let mut epoch = 1u64;
+        let mut len = mem::size_of::<u64>();
+        let epoch_ptr = (&mut epoch as *mut u64).cast();
+        mallctl(
+            b"epoch\0".as_ptr().cast(),
+            epoch_ptr,
+            &mut len,
+            epoch_ptr,
+            mem::size_of::<u64>(),
+        );
+
+        Some(AllocatorStats {
+            allocated: read(b"stats.allocated\0")?,
+            resident: read(b"stats.resident\0")?,
+        })
+    }
+}
+
+/// Returns statistics about the memory managed by the allocator, if supported
+#[cfg(feature = "mimalloc")]
+pub fn stats() -> Option<AllocatorStats> {
+    let mut elapsed = 0;
+    let mut user_time = 0;
+    let mut system_time = 0;
+    let mut current_rss = 0;
+    let mut peak_rss = 0;
+    let mut current_commit = 0;
+    let mut peak_commit = 0;
+    let mut page_faults = 0;
+    unsafe {
+        libmimalloc_sys::mi_process_info(
+            &mut elapsed,
+            &mut user_time,
+            &mut system_time,
+            &mut current_rss,
+            &mut peak_rss,
+            &mut current_commit,
+            &mut peak_commit,
+            &mut page_faults,
+        );
+    }
+    Some(AllocatorStats {
+        allocated: current_commit,
+        resident: current_rss,
+    })
+}
+
+/// Returns statistics about the memory managed by the allocator, if supported
+#[cfg(all(
+    not(any(feature = "jemalloc", feature = "mimalloc")),
+    target_os = "linux",
+    target_env = "gnu"
+))]
+pub fn stats() -> Option<AllocatorStats> {
+    let info = unsafe { libc::mallinfo2() };
+    Some(AllocatorStats {
+        allocated: info.uordblks + info.hblkhd,
+        resident: info.arena + info.hblkhd,
+    })
+}
+
+/// Returns statistics about the memory managed by the allocator, if supported
+#[cfg(not(any(
+    feature = "jemalloc",
+    feature = "mimalloc",
+    all(target_os = "linux", target_env = "gnu")
+)))]
+pub fn stats() -> Option<AllocatorStats> {
+    None
+}
+
+/// Returns free memory held by the allocator to the operating system, where supported
+pub fn release_free_memory() {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "jemalloc")] {
+            // Purges all arenas, see `MALLCTL_ARENAS_ALL`
+            unsafe {
+                tikv_jemalloc_sys::mallctl(
+                    b"arena.4096.purge\0".as_ptr().cast(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    0,
+                );
+            }
+        } else if #[cfg(feature = "mimalloc")] {
+            unsafe {
+                libmimalloc_sys::mi_collect(true);
+            }
+        } else if #[cfg(all(target_os = "linux", target_env = "gnu"))] {
+            unsafe {
+                libc::malloc_trim(0);
+            }
+        }
+    }
+}
+
+/// The size of the blocks allocated by `prefault`, which is small enough for them to be carved
+/// out of the allocator's arenas, as memory mapped for a single large allocation is unmapped again
+/// as soon as it is freed
+const PREFAULT_BLOCK_SIZE: usize = 32 * 1024;
+
+/// Allocates about `size` bytes, touches every page of it, and frees it again, returning the
+/// number of bytes touched
+///
+/// This moves the cost of page faults on fresh memory from the first allocations made by the
+/// runtime to startup. It is best-effort: how much of the memory the allocator holds on to, and
+/// for how long, is up to the allocator, e.g. jemalloc gradually returns unused pages to the
+/// operating system, so it is most effective just before the memory is needed.
+pub fn prefault(size: usize) -> usize {
+    let page_size = crate::mem::page_size();
+    let blocks = (size + PREFAULT_BLOCK_SIZE - 1) / PREFAULT_BLOCK_SIZE;
+    // The blocks are chained through their first word, so as to not allocate anything else
+    let mut head: *mut u8 = ptr::null_mut();
+    let mut faulted = 0;
+    for _ in 0..blocks {
+        let block = unsafe { backend::malloc(PREFAULT_BLOCK_SIZE) as *mut u8 };
+        if block.is_null() {
+            break;
+        }
+        unsafe {
+            for offset in (0..PREFAULT_BLOCK_SIZE).step_by(page_size) {
+                ptr::write_volatile(block.add(offset), 0);
+            }
+            block.cast::<*mut u8>().write(head);
+        }
+        head = block;
+        faulted += PREFAULT_BLOCK_SIZE;
+    }
+    while !head.is_null() {
+        unsafe {
+            let next = head.cast::<*mut u8>().read();
+            backend::free(head.cast());
+            head = next;
+        }
+    }
+    faulted
+}
@@ -1,17 +1,10 @@
 use core::alloc::{AllocError, Layout};
-#[cfg(all(
-    not(target_os = "linux"),
-    not(target_os = "emscripten"),
-    not(target_os = "android"),
-    not(target_os = "freebsd"),
-    not(target_os = "netbsd"),
-))]
 use core::cmp;
 use core::intrinsics::unlikely;
 use core::ptr::{self, NonNull};
 
 use super::sysconf;
-use crate::alloc::round_up_to_multiple_of;
+use crate::alloc::{round_up_to_multiple_of, PageKind};
 
 mod constants {
     pub use libc::{PROT_NONE, PROT_READ, PROT_WRITE};
@@ -103,6 +96,65 @@ pub unsafe fn map(layout: Layout) -> Result<(NonNull<u8>, usize), AllocError> {
     Ok((NonNull::new_unchecked(aligned_ptr), commit_size))
 }
 
+/// Requests a new memory mapping from the OS, preferring to have it backed by huge pages.
+///
+/// If `explicit` is true, the mapping is first attempted using explicitly reserved huge pages,
+/// which only succeeds if the system administrator has reserved some. Otherwise, or if that
+/// fails, a regular mapping aligned to the huge page size is created, and the kernel is advised
+/// to back it with transparent huge pages.
+///
+/// The size of the mapping is always rounded up to a multiple of the huge page size, and that size
+/// must be used when unmapping it. The size is returned along with the kind of pages backing it.
+#[cfg(target_os = "linux")]
+pub unsafe fn map_huge(
+    layout: Layout,
+    explicit: bool,
+) -> Result<(NonNull<u8>, usize, PageKind), AllocError> {
+    let huge_page_size = sysconf::huge_page_size();
+    let size = round_up_to_multiple_of(layout.size(), huge_page_size);
+
+    if explicit && layout.align() <= huge_page_size {
+        let res = libc::mmap(
+            ptr::null_mut(),
+            size,
+            MMAP_PROT,
+            MMAP_FLAGS | libc::MAP_HUGETLB,
+            -1 as libc::c_int,
+            0,
+        );
+        if res != MAP_FAILED {
+            return Ok((NonNull::new_unchecked(res as *mut u8), size, PageKind::Huge));
+        }
+    }
+
+    // Transparent huge pages are only used for regions aligned to the huge page size
+    let align = cmp::max(layout.align(), huge_page_size);
+    let layout = Layout::from_size_align(size, align).map_err(|_| AllocError)?;
+    let (ptr, _) = map(layout)?;
+    let kind = if libc::madvise(ptr.as_ptr() as *mut _, size, libc::MADV_HUGEPAGE) == 0 {
+        PageKind::TransparentHuge
+    } else {
+        PageKind::Normal
+    };
+    Ok((ptr, size, kind))
+}
+
+/// Requests a new memory mapping from the OS, preferring to have it backed by huge pages.
+///
+/// Huge pages are not supported on this platform, so this always creates a regular mapping, but
+/// its size is still rounded up to a multiple of the huge page size, and that size must be used
+/// when unmapping it.
+#[cfg(not(target_os = "linux"))]
+pub unsafe fn map_huge(
+    layout: Layout,
+    _explicit: bool,
+) -> Result<(NonNull<u8>, usize, PageKind), AllocError> {
+    let size = round_up_to_multiple_of(layout.size(), sysconf::huge_page_size());
+    let layout = Layout::from_size_align(size, layout.align()).map_err(|_| AllocError)?;
+    let (ptr, _) = map(layout)?;
+    Ok((ptr, size, PageKind::Normal))
+}
+
 #[inline]
 pub unsafe fn map_stack(pages: usize) -> Result<NonNull<u8>, AllocError> {
     // Stacks must be at least 1 page + 1 guard page
@@ -12,6 +12,43 @@ pub fn num_cpus() -> usize {
     SYSTEM_INFO.get_or_init(SystemInfo::get).num_cpus
 }
 
+/// Returns the size of a huge page, which is the granularity at which large memory mappings are
+/// made so that they can be backed by huge pages
+pub fn huge_page_size() -> usize {
+    static HUGE_PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+
+    *HUGE_PAGE_SIZE.get_or_init(get_huge_page_size)
+}
+
+/// Returns the number of processors this process can make full use of
+///
+/// This differs from [`num_cpus`] when the process is subject to a CPU quota, as is typical when
+/// running in a container, in which case the quota is the effective limit.
+pub fn available_cpus() -> usize {
+    let cpus = num_cpus();
+    crate::cgroup::limits()
+        .cpus()
+        .map(|quota| quota.min(cpus))
+        .unwrap_or(cpus)
+}
+
+/// The huge page size on the most common platforms, i.e. PMD-sized pages on x86_64/aarch64
+const DEFAULT_HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+#[cfg(target_os = "linux")]
+fn get_huge_page_size() -> usize {
+    std::fs::read_to_string("/sys/kernel/mm/transparent_hugepage/hpage_pmd_size")
+        .ok()
+        .and_then(|size| size.trim().parse::<usize>().ok())
+        .filter(|size| size.is_power_of_two() && *size >= page_size())
+        .unwrap_or(DEFAULT_HUGE_PAGE_SIZE)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_huge_page_size() -> usize {
+    DEFAULT_HUGE_PAGE_SIZE
+}
+
 #[derive(Copy, Clone)]
 struct SystemInfo {
     page_size: usize,
@@ -0,0 +1,102 @@
+//! Group leaders, i.e. `group_leader/0,2`
+//!
+//! Every process has a group leader, to which io requests made on its behalf are sent. Spawned
+//! processes inherit the group leader of their parent, including those spawned on behalf of a
+//! remote node, whose group leader is then a process on that node, so their output is routed
+//! back to it, e.g. to a remote shell.
+//!
+//! Changing the group leader of another local process is done by sending it a `GroupLeader`
+//! signal, and waiting for its reply in `erts_internal:group_leader/2`. The group leader of a
+//! remote process is changed via the `GROUP_LEADER` control message, which has no reply.
+use std::ops::Deref;
+
+use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::signals::Signal;
+use firefly_rt::process::{ProcessLock, ARG0_REG};
+use firefly_rt::services::{distribution, registry};
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Returns the group leader of the calling process
+///
+/// A process without a group leader, i.e. the init process, is its own group leader.
+#[export_name = "erlang:group_leader/0"]
+pub extern "C-unwind" fn group_leader0(process: &mut ProcessLock) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_pid();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let group_leader = process
+        .group_leader()
+        .cloned()
+        .unwrap_or_else(|| process.pid());
+    ErlangResult::Ok(Gc::new_in(group_leader, process).unwrap().into())
+}
+
+/// Makes `group_leader` the group leader of `pid`
+#[export_name = "erlang:group_leader/2"]
+pub extern "C-unwind" fn group_leader2(
+    process: &mut ProcessLock,
+    group_leader: OpaqueTerm,
+    pid: OpaqueTerm,
+) -> ErlangResult {
+    static GROUP_LEADER_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::GroupLeader,
+        arity: 2,
+    };
+
+    let Term::Pid(leader) = group_leader.into() else { badarg!(process, group_leader); };
+    let Term::Pid(target) = pid.into() else { badarg!(process, pid); };
+
+    if !target.is_local() {
+        // Like sends to remote processes, the change is silently dropped if it cannot be delivered
+        distribution::send_group_leader(leader.deref().clone(), target.deref().clone()).ok();
+        return ErlangResult::Ok(true.into());
+    }
+
+    if target.id() == process.id() {
+        process.set_group_leader(leader.deref().clone());
+        return ErlangResult::Ok(true.into());
+    }
+
+    process.stack.store(ARG0_REG, group_leader);
+    process.stack.store(ARG0_REG + 1, pid);
+    ErlangResult::Trap(&GROUP_LEADER_TRAP_EXPORT)
+}
+
+/// Asks the local process `pid` to make `group_leader` its group leader
+///
+/// Returns `ok` if the request was sent, in which case `{Ref, true | badarg}` is sent back once
+/// it has been handled, otherwise `badarg` is returned, as `pid` does not exist.
+#[export_name = "erts_internal:group_leader/3"]
+pub extern "C-unwind" fn group_leader3(
+    process: &mut ProcessLock,
+    group_leader: OpaqueTerm,
+    pid: OpaqueTerm,
+    reference: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(leader) = group_leader.into() else { badarg!(process, group_leader); };
+    let Term::Pid(target) = pid.into() else { badarg!(process, pid); };
+    let Term::Reference(reference) = reference.into() else { badarg!(process, reference); };
+
+    let signal = Signal::group_leader(
+        process.pid(),
+        leader.deref().clone(),
+        Some(reference.deref().clone()),
+    );
+    let sent = match registry::get_by_pid(&target) {
+        None => false,
+        Some(target) => target.send_signal(signal).is_ok(),
+    };
+    if sent {
+        ErlangResult::Ok(atoms::Ok.into())
+    } else {
+        ErlangResult::Ok(atoms::Badarg.into())
+    }
+}
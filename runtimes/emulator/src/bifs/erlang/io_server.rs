@@ -0,0 +1,56 @@
+//! The native half of the io server which is the group leader of init, and so of every process
+//! which does not have another group leader
+//!
+//! The io protocol itself is handled by `erts_internal:file_io_server/0`, which passes the
+//! characters of each output request here to be written, to standard output, or to the file it
+//! is redirected to by `-stdio_file`, see `sys::stdio_file`.
+use std::io::{self, Write};
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::nifs::replay::io_error_reason;
+use crate::nifs::unicode::characters_to_string;
+use crate::sys::stdio_file;
+
+/// Writes `chars`, which is character data in `encoding`, i.e. `unicode` or `latin1`, to standard
+/// output, or appends it to the stdio file, as UTF-8
+///
+/// Returns `ok`, or `{error, Reason}` if `chars` is not valid character data, or the write fails.
+#[export_name = "erts_internal:file_io_put_chars/2"]
+pub extern "C-unwind" fn file_io_put_chars2(
+    process: &mut ProcessLock,
+    encoding: OpaqueTerm,
+    chars: OpaqueTerm,
+) -> ErlangResult {
+    match encoding.into() {
+        Term::Atom(enc) if enc == atoms::Unicode || enc == atoms::Latin1 => (),
+        _ => badarg!(process, encoding),
+    }
+    let Some(text) = characters_to_string(chars, encoding) else { return error_tuple(process, atoms::Badarg); };
+    let result = if stdio_file::is_enabled() {
+        stdio_file::write(text.as_bytes())
+    } else {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+    };
+    match result {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(err) => error_tuple(process, io_error_reason(&err)),
+    }
+}
+
+fn error_tuple(process: &mut ProcessLock, reason: Atom) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let tuple = Tuple::from_slice(&[atoms::Error.into(), reason.into()], process).unwrap();
+    ErlangResult::Ok(tuple.into())
+}
@@ -1,26 +1,48 @@
 mod debugging;
+mod group_leader;
+mod io_server;
+mod monitor;
 mod operators;
+mod process_info;
 mod signals;
+mod spawn_request;
+mod system_info;
+mod time;
+mod timers;
+mod trace;
 
 pub use self::debugging::*;
+pub use self::group_leader::*;
+pub use self::io_server::*;
+pub use self::monitor::*;
 pub use self::operators::*;
+pub use self::process_info::*;
 pub use self::signals::*;
+pub use self::spawn_request::*;
+pub use self::system_info::*;
+pub use self::time::*;
+pub use self::timers::*;
+pub use self::trace::*;
 
-use std::cmp;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 use firefly_alloc::heap::Heap;
 use firefly_rt::error::ExceptionFlags;
-use firefly_rt::function::{self, ErlangResult, ModuleFunctionArity};
+use firefly_rt::function::{
+    self, list_to_args, ArgumentListError, ErlangResult, ModuleFunctionArity, MAX_ARGS,
+};
 use firefly_rt::gc::{garbage_collect, Gc, RootSet};
-use firefly_rt::process::{Priority, Process, ProcessFlags, ProcessLock, StatusFlags};
+use firefly_rt::process::{
+    MessageQueueData, Priority, Process, ProcessFlags, ProcessLock, SpawnOpts, StatusFlags,
+    ARG0_REG,
+};
 use firefly_rt::scheduler::Scheduler;
+use firefly_rt::services::distribution;
 use firefly_rt::term::*;
 
 use log::warn;
 
-use smallvec::SmallVec;
-
 use crate::badarg;
 use crate::emulator::current_scheduler;
 
@@ -55,6 +77,7 @@ pub extern "C-unwind" fn is_builtin3(
 #[export_name = "erlang:make_ref/0"]
 pub extern "C-unwind" fn make_ref0(process: &mut ProcessLock) -> ErlangResult {
     let ref_id = current_scheduler().next_reference_id();
+    process.signals().lock().set_receive_marker(ref_id);
     loop {
         match Gc::new_uninit_in(process) {
             Ok(mut empty) => unsafe {
@@ -70,16 +93,46 @@ pub extern "C-unwind" fn make_ref0(process: &mut ProcessLock) -> ErlangResult {
 
 #[export_name = "erlang:list_to_atom/1"]
 pub extern "C-unwind" fn list_to_atom(process: &mut ProcessLock, term: OpaqueTerm) -> ErlangResult {
-    match term.into() {
-        Term::Nil => return ErlangResult::Ok(atoms::Empty.into()),
-        Term::Cons(cons) => {
-            if let Some(s) = cons.as_ref().to_string() {
-                return ErlangResult::Ok(Atom::str_to_term(&s));
-            }
+    let result = match term.into() {
+        Term::Nil => Ok(atoms::Empty),
+        Term::Cons(cons) => match cons.as_ref().to_string() {
+            Some(s) => Atom::try_from(s.as_str()),
+            None => badarg!(process, term),
+        },
+        _ => badarg!(process, term),
+    };
+    match result {
+        Ok(atom) => ErlangResult::Ok(atom.into()),
+        Err(AtomError::InvalidLength(_) | AtomError::TableFull(_)) => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::SystemLimit.into();
+            process.exception_info.value = atoms::SystemLimit.into();
+            process.exception_info.args = Some(term);
+            process.exception_info.trace = None;
+            ErlangResult::Err
         }
-        _ => (),
+        Err(_) => badarg!(process, term),
+    }
+}
+
+/// Like `list_to_atom/1`, but never creates a new atom, so it is safe to use with untrusted input
+#[export_name = "erlang:list_to_existing_atom/1"]
+pub extern "C-unwind" fn list_to_existing_atom(
+    process: &mut ProcessLock,
+    term: OpaqueTerm,
+) -> ErlangResult {
+    let result = match term.into() {
+        Term::Nil => Ok(atoms::Empty),
+        Term::Cons(cons) => match cons.as_ref().to_string() {
+            Some(s) => Atom::try_from_str_existing(s.as_str()),
+            None => badarg!(process, term),
+        },
+        _ => badarg!(process, term),
+    };
+    match result {
+        Ok(atom) => ErlangResult::Ok(atom.into()),
+        Err(_) => badarg!(process, term),
     }
-    badarg!(process, term)
 }
 
 #[export_name = "erlang:binary_to_list/1"]
@@ -196,25 +249,100 @@ pub extern "C-unwind" fn spawn_monitor4(
     ErlangResult::Err
 }
 
+#[export_name = "erlang:spawn/1"]
+pub extern "C-unwind" fn spawn1(process: &mut ProcessLock, fun: OpaqueTerm) -> ErlangResult {
+    spawn_fun(process, fun, SpawnOpts::default())
+}
+
+#[export_name = "erlang:spawn/3"]
+pub extern "C-unwind" fn spawn3(
+    process: &mut ProcessLock,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+) -> ErlangResult {
+    spawn_mfa(process, module, function, args, SpawnOpts::default())
+}
+
+/// Spawns `fun`, linked to the calling process
+///
+/// The link is established before the spawned process can be scheduled, see `spawn_fun`.
+#[export_name = "erlang:spawn_link/1"]
+pub extern "C-unwind" fn spawn_link1(process: &mut ProcessLock, fun: OpaqueTerm) -> ErlangResult {
+    let mut spawn_opts = SpawnOpts::default();
+    spawn_opts.link = true;
+    spawn_fun(process, fun, spawn_opts)
+}
+
+/// Spawns `apply(module, function, args)`, linked to the calling process
+#[export_name = "erlang:spawn_link/3"]
+pub extern "C-unwind" fn spawn_link3(
+    process: &mut ProcessLock,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+) -> ErlangResult {
+    let mut spawn_opts = SpawnOpts::default();
+    spawn_opts.link = true;
+    spawn_mfa(process, module, function, args, spawn_opts)
+}
+
+/// Spawns `fun`, monitored by the calling process, returning `{Pid, MonitorRef}`
+///
+/// The monitor is established before the spawned process can be scheduled, see `spawn_fun`.
+#[export_name = "erlang:spawn_monitor/1"]
+pub extern "C-unwind" fn spawn_monitor1(
+    process: &mut ProcessLock,
+    fun: OpaqueTerm,
+) -> ErlangResult {
+    let mut spawn_opts = SpawnOpts::default();
+    spawn_opts.monitor = Some(Default::default());
+    spawn_fun(process, fun, spawn_opts)
+}
+
+/// Spawns `apply(module, function, args)`, monitored by the calling process, returning
+/// `{Pid, MonitorRef}`
+#[export_name = "erlang:spawn_monitor/3"]
+pub extern "C-unwind" fn spawn_monitor3(
+    process: &mut ProcessLock,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+) -> ErlangResult {
+    let mut spawn_opts = SpawnOpts::default();
+    spawn_opts.monitor = Some(Default::default());
+    spawn_mfa(process, module, function, args, spawn_opts)
+}
+
 #[export_name = "erlang:spawn_opt/2"]
 pub extern "C-unwind" fn spawn_opt2(
     process: &mut ProcessLock,
-    mut fun_term: OpaqueTerm,
+    fun_term: OpaqueTerm,
     opts: OpaqueTerm,
 ) -> ErlangResult {
-    use firefly_rt::process::SpawnOpts;
-
     let opts: Term = opts.into();
-    let mut spawn_opts: SpawnOpts = match opts.try_into() {
-        Ok(opts) => opts,
-        Err(_) => {
+    let spawn_opts: Result<SpawnOpts, _> = opts.try_into();
+    match spawn_opts {
+        Ok(spawn_opts) if !spawn_opts.request_only => spawn_fun(process, fun_term, spawn_opts),
+        _ => {
             process.exception_info.flags = ExceptionFlags::ERROR;
             process.exception_info.reason = atoms::Badarg.into();
             process.exception_info.value = fun_term;
             process.exception_info.trace = None;
-            return ErlangResult::Err;
+            ErlangResult::Err
         }
-    };
+    }
+}
+
+/// The spawn path shared by all of the local `spawn` variants which take a fun
+///
+/// Any link or monitor requested in `spawn_opts` is set up by the scheduler before the spawned
+/// process is registered or scheduled, so it cannot exit without the caller observing it.
+fn spawn_fun(
+    process: &mut ProcessLock,
+    mut fun_term: OpaqueTerm,
+    mut spawn_opts: SpawnOpts,
+) -> ErlangResult {
     let monitor = spawn_opts.monitor.is_some();
 
     let mut layout = LayoutBuilder::new();
@@ -237,18 +365,8 @@ pub extern "C-unwind" fn spawn_opt2(
 
     match fun_term.into() {
         Term::Closure(fun) => {
-            match current_scheduler().spawn(process, fun.mfa(), &[fun_term], spawn_opts) {
-                (spawned, Some(spawn_ref)) => {
-                    let pid = Gc::new_in(spawned.pid(), process).unwrap();
-                    let tuple =
-                        Tuple::from_slice(&[pid.into(), spawn_ref.into()], process).unwrap();
-                    ErlangResult::Ok(tuple.into())
-                }
-                (spawned, None) => {
-                    let pid = Gc::new_in(spawned.pid(), process).unwrap();
-                    ErlangResult::Ok(pid.into())
-                }
-            }
+            let spawned = current_scheduler().spawn(process, fun.mfa(), &[fun_term], spawn_opts);
+            spawn_result(process, spawned)
         }
         _ => {
             process.exception_info.flags = ExceptionFlags::ERROR;
@@ -260,19 +378,74 @@ pub extern "C-unwind" fn spawn_opt2(
     }
 }
 
+/// Returns the pid of a spawned process, or `{Pid, MonitorRef}` if it was spawned with a monitor
+///
+/// The caller must have ensured there is enough space on the heap for the result.
+fn spawn_result(
+    process: &mut ProcessLock,
+    spawned: (Arc<Process>, Option<Gc<Reference>>),
+) -> ErlangResult {
+    match spawned {
+        (spawned, Some(spawn_ref)) => {
+            let pid = Gc::new_in(spawned.pid(), process).unwrap();
+            let tuple = Tuple::from_slice(&[pid.into(), spawn_ref.into()], process).unwrap();
+            ErlangResult::Ok(tuple.into())
+        }
+        (spawned, None) => {
+            let pid = Gc::new_in(spawned.pid(), process).unwrap();
+            ErlangResult::Ok(pid.into())
+        }
+    }
+}
+
+/// Spawns `fun` on `node` with the given options
+///
+/// When `node` is the local node, this is equivalent to `spawn_opt/2`.
 #[export_name = "erlang:spawn_opt/3"]
 pub extern "C-unwind" fn spawn_opt3(
     process: &mut ProcessLock,
     node: OpaqueTerm,
-    _fun: OpaqueTerm,
-    _opts: OpaqueTerm,
+    fun: OpaqueTerm,
+    opts: OpaqueTerm,
 ) -> ErlangResult {
-    warn!(target: "process", "spawn_opt/4 is unavailable, distribution is unimplemented");
-    process.exception_info.flags = ExceptionFlags::ERROR;
-    process.exception_info.reason = atoms::Notsup.into();
-    process.exception_info.value = node;
-    process.exception_info.trace = None;
-    ErlangResult::Err
+    if !node.is_atom() {
+        badarg!(process, node);
+    }
+    if node.as_atom() == distribution::current_node().name() {
+        return spawn_opt2(process, fun, opts);
+    }
+    match fun.into() {
+        Term::Closure(closure) if closure.arity as usize == (!closure.is_thin() as usize) => (),
+        _ => badarg!(process, fun),
+    }
+    if !is_valid_spawn_opts(opts) {
+        badarg!(process, opts);
+    }
+
+    // Funs are spawned remotely as `erlang:apply(Fun, [])`
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(2);
+    let needed = layout.finish().size();
+    let mut fun = fun;
+    let mut opts = opts;
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut fun as *mut OpaqueTerm;
+        roots += &mut opts as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+    let args = Cons::from_slice(&[fun, OpaqueTerm::NIL], process)
+        .unwrap()
+        .unwrap();
+    remote_spawn_opt(
+        process,
+        node,
+        atoms::Erlang.into(),
+        atoms::Apply.into(),
+        args.into(),
+        opts,
+    )
 }
 
 #[export_name = "erlang:spawn_opt/4"]
@@ -280,11 +453,34 @@ pub extern "C-unwind" fn spawn_opt4(
     process: &mut ProcessLock,
     module: OpaqueTerm,
     function: OpaqueTerm,
-    mut args: OpaqueTerm,
+    args: OpaqueTerm,
     opts: OpaqueTerm,
 ) -> ErlangResult {
-    use firefly_rt::process::SpawnOpts;
+    let opts_term: Term = opts.into();
+    let spawn_opts: Result<SpawnOpts, _> = opts_term.try_into();
+    match spawn_opts {
+        Ok(spawn_opts) if !spawn_opts.request_only => {
+            spawn_mfa(process, module, function, args, spawn_opts)
+        }
+        _ => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::Badarg.into();
+            process.exception_info.value = opts;
+            process.exception_info.trace = None;
+            ErlangResult::Err
+        }
+    }
+}
 
+/// The spawn path shared by all of the local `spawn` variants which take a module, function and
+/// argument list, see `spawn_fun`
+fn spawn_mfa(
+    process: &mut ProcessLock,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    mut args: OpaqueTerm,
+    mut spawn_opts: SpawnOpts,
+) -> ErlangResult {
     if !module.is_atom() {
         process.exception_info.flags = ExceptionFlags::ERROR;
         process.exception_info.reason = atoms::Badarg.into();
@@ -298,17 +494,6 @@ pub extern "C-unwind" fn spawn_opt4(
         return ErlangResult::Err;
     }
 
-    let opts_term: Term = opts.into();
-    let mut spawn_opts: SpawnOpts = match opts_term.try_into() {
-        Ok(opts) => opts,
-        Err(_) => {
-            process.exception_info.flags = ExceptionFlags::ERROR;
-            process.exception_info.reason = atoms::Badarg.into();
-            process.exception_info.value = opts;
-            process.exception_info.trace = None;
-            return ErlangResult::Err;
-        }
-    };
     let monitor = spawn_opts.monitor.is_some();
 
     let mut layout = LayoutBuilder::new();
@@ -329,66 +514,93 @@ pub extern "C-unwind" fn spawn_opt4(
         assert!(garbage_collect(process, roots).is_ok());
     }
 
-    let mut arity = 0;
-    let mut argv = SmallVec::<[OpaqueTerm; 4]>::default();
-    match args.into() {
-        Term::Nil => (),
-        Term::Cons(cons) => {
-            for result in cons.iter_raw() {
-                if result.is_err() {
-                    process.exception_info.flags = ExceptionFlags::ERROR;
-                    process.exception_info.reason = atoms::Badarg.into();
-                    process.exception_info.value = args;
-                    process.exception_info.trace = None;
-                    return ErlangResult::Err;
-                }
-                arity += 1;
-                argv.push(unsafe { result.unwrap_unchecked() });
-            }
-        }
-        _ => {
+    let argv = match list_to_args(args, MAX_ARGS) {
+        Ok(argv) => argv,
+        Err(err) => {
             process.exception_info.flags = ExceptionFlags::ERROR;
-            process.exception_info.reason = atoms::Badarg.into();
+            process.exception_info.reason = match err {
+                ArgumentListError::Improper => atoms::Badarg.into(),
+                ArgumentListError::TooManyArguments => atoms::SystemLimit.into(),
+            };
             process.exception_info.value = args;
             process.exception_info.trace = None;
             return ErlangResult::Err;
         }
-    }
+    };
 
     let mfa = ModuleFunctionArity {
         module: module.as_atom(),
         function: function.as_atom(),
-        arity,
+        arity: argv.len() as u8,
     };
 
-    match current_scheduler().spawn(process, mfa, argv.as_slice(), spawn_opts) {
-        (spawned, Some(spawn_ref)) => {
-            let pid = Gc::new_in(spawned.pid(), process).unwrap();
-            let tuple = Tuple::from_slice(&[pid.into(), spawn_ref.into()], process).unwrap();
-            ErlangResult::Ok(tuple.into())
-        }
-        (spawned, None) => {
-            let pid = Gc::new_in(spawned.pid(), process).unwrap();
-            ErlangResult::Ok(pid.into())
-        }
-    }
+    let spawned = current_scheduler().spawn(process, mfa, argv.as_slice(), spawn_opts);
+    spawn_result(process, spawned)
 }
 
+/// Spawns `apply(module, function, args)` on `node` with the given options
+///
+/// When `node` is the local node, this is equivalent to `spawn_opt/4`.
 #[export_name = "erlang:spawn_opt/5"]
 pub extern "C-unwind" fn spawn_opt5(
     process: &mut ProcessLock,
     node: OpaqueTerm,
-    _module: OpaqueTerm,
-    _function: OpaqueTerm,
-    _args: OpaqueTerm,
-    _opts: OpaqueTerm,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+    opts: OpaqueTerm,
 ) -> ErlangResult {
-    warn!(target: "process", "spawn_opt/5 is unavailable, distribution is unimplemented");
-    process.exception_info.flags = ExceptionFlags::ERROR;
-    process.exception_info.reason = atoms::Notsup.into();
-    process.exception_info.value = node;
-    process.exception_info.trace = None;
-    ErlangResult::Err
+    if !node.is_atom() {
+        badarg!(process, node);
+    }
+    if node.as_atom() == distribution::current_node().name() {
+        return spawn_opt4(process, module, function, args, opts);
+    }
+    if !module.is_atom() {
+        badarg!(process, module);
+    }
+    if !function.is_atom() {
+        badarg!(process, function);
+    }
+    if list_to_args(args, MAX_ARGS).is_err() {
+        badarg!(process, args);
+    }
+    if !is_valid_spawn_opts(opts) {
+        badarg!(process, opts);
+    }
+    remote_spawn_opt(process, node, module, function, args, opts)
+}
+
+/// Returns true if `opts` is a valid option list for the `spawn_opt` family, which, unlike
+/// `spawn_request`, does not accept the reply options
+fn is_valid_spawn_opts(opts: OpaqueTerm) -> bool {
+    let opts: Term = opts.into();
+    let opts: Result<SpawnOpts, _> = opts.try_into();
+    opts.map(|opts| !opts.request_only).unwrap_or(false)
+}
+
+/// Remote spawns are made with `spawn_request`, and then wait for its reply, which is handled by
+/// `erts_internal:spawn_opt/5`
+fn remote_spawn_opt(
+    process: &mut ProcessLock,
+    node: OpaqueTerm,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+    opts: OpaqueTerm,
+) -> ErlangResult {
+    static SPAWN_OPT_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::SpawnOpt,
+        arity: 5,
+    };
+
+    process.stack.store(ARG0_REG, node);
+    process.stack.store(ARG0_REG + 1, module);
+    process.stack.store(ARG0_REG + 2, function);
+    process.stack.store(ARG0_REG + 3, args);
+    process.stack.store(ARG0_REG + 4, opts);
+    ErlangResult::Trap(&SPAWN_OPT_TRAP_EXPORT)
 }
 
 #[export_name = "erlang:process_flag/2"]
@@ -453,7 +665,7 @@ pub extern "C-unwind" fn process_flag2(
                     if prev.size.is_none() {
                         return ErlangResult::Ok(Term::Int(0).into());
                     }
-                    let prev_size: Term = prev.size.map(|sz| sz.get()).unwrap().try_into().unwrap();
+                    let prev_size: Term = prev.size_in_words().try_into().unwrap();
                     // If the system defaults are being used, use the integer representation
                     if prev.kill && prev.error_logger {
                         return ErlangResult::Ok(prev_size.into());
@@ -475,11 +687,11 @@ pub extern "C-unwind" fn process_flag2(
             }
         }
         "message_queue_data" => {
-            // This flag has no effect in our implementation, we simply return the current value
-            if !value.is_atom() {
-                badarg!(process, value);
+            let mode: Term = value.into();
+            match MessageQueueData::try_from(mode) {
+                Ok(mode) => ErlangResult::Ok(process.set_message_queue_data(mode).into()),
+                Err(_) => badarg!(process, value),
             }
-            ErlangResult::Ok(atoms::OffHeap.into())
         }
         "priority" => {
             let prio: Term = value.into();
@@ -602,10 +814,78 @@ pub extern "C-unwind" fn yield0(process: &mut ProcessLock) -> ErlangResult {
     // This is slightly different than the behavior of the Yield instruction, which
     // yields before it conceptually returns, but there is no practical difference in
     // behavior.
-    process.reductions = Process::MAX_REDUCTIONS;
+    process.yield_now();
     ErlangResult::Ok(true.into())
 }
 
+/// Starts the next receive after the messages which were already queued when `Ref` was created
+///
+/// The compiler inserts calls to this before a receive in which every clause only matches
+/// messages containing a reference created earlier in the same function, by `make_ref/0` or
+/// `monitor/2,3`. Nothing is skipped if another reference has been created since.
+#[export_name = "erlang:recv_marker_use/1"]
+pub extern "C-unwind" fn recv_marker_use1(
+    process: &mut ProcessLock,
+    reference: OpaqueTerm,
+) -> ErlangResult {
+    if let Term::Reference(reference) = reference.into() {
+        process
+            .signals()
+            .lock()
+            .use_receive_marker(reference.id());
+    }
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+/// Puts the calling process into a wait state where its memory footprint is as small as possible
+///
+/// The call stack is discarded, and the heap is shrunk to the size of the live data by a full
+/// sweep. The process is then suspended until a message is in its queue, at which point it
+/// resumes by calling `apply(Module, Function, Args)`, via `erts_internal:hibernate_resume/3`,
+/// which is the only frame on the stack, so returning from it exits the process.
+#[export_name = "erlang:hibernate/3"]
+pub extern "C-unwind" fn hibernate3(
+    process: &mut ProcessLock,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+) -> ErlangResult {
+    static HIBERNATE_RESUME_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::HibernateResume,
+        arity: 3,
+    };
+
+    if !module.is_atom() {
+        badarg!(process, module);
+    }
+    if !function.is_atom() {
+        badarg!(process, function);
+    }
+    if list_to_args(args, MAX_ARGS).is_err() {
+        badarg!(process, args);
+    }
+
+    process.stack.reset();
+    unsafe {
+        process.stack.alloca(3);
+    }
+    process.stack.store(ARG0_REG, module);
+    process.stack.store(ARG0_REG + 1, function);
+    process.stack.store(ARG0_REG + 2, args);
+
+    // The arguments are the only roots left on the stack, so everything else not referenced by
+    // the process dictionary or message queue is garbage. If the heap can't be shrunk, e.g. as
+    // memory could not be allocated, we still hibernate, just less effectively.
+    if let Err(err) = process.garbage_collect_hibernate() {
+        warn!(target: "process", "unable to shrink the heap of hibernating process: {:?}", err);
+    }
+
+    // The process is suspended when it reaches the trap, unless a message has arrived by then
+    process.flags |= ProcessFlags::HIBERNATE;
+    ErlangResult::Trap(&HIBERNATE_RESUME_TRAP_EXPORT)
+}
+
 #[export_name = "erlang:bump_reductions/1"]
 pub extern "C-unwind" fn bump_reductions(
     process: &mut ProcessLock,
@@ -614,11 +894,7 @@ pub extern "C-unwind" fn bump_reductions(
     match reds.into() {
         Term::Int(i) if i >= 1 => {
             if let Ok(i) = usize::try_from(i) {
-                // Clamp large values to the maximum number of reductions
-                process.reductions = cmp::min(
-                    Process::MAX_REDUCTIONS,
-                    process.reductions.saturating_add(i),
-                );
+                process.bump_reductions(i);
                 return ErlangResult::Ok(true.into());
             }
         }
@@ -0,0 +1,338 @@
+//! Process monitors, i.e. `monitor/2,3` and `demonitor/1,2`
+//!
+//! The origin of a monitor keeps it in its monitor tree, keyed by reference, while the target keeps
+//! it in its monitor list, to which it is added when the target handles the `Monitor` signal. When
+//! the target exits, each monitor in its list is sent back to the origin in a `MonitorDown` signal,
+//! which the origin converts to a `'DOWN'` message, as long as it still has the monitor.
+//!
+//! Monitors of targets which do not exist are triggered immediately with `noproc`. Distribution
+//! does not support remote monitors yet, so monitors of remote processes are triggered immediately
+//! with `noconnection`.
+use std::ops::Deref;
+use std::sync::Arc;
+
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc, RootSet};
+use firefly_rt::process::monitor::{
+    make_down_message, make_named_tag, LocalMonitorInfo, Monitor, MonitorEntry, MonitorFlags,
+    UnaliasMode,
+};
+use firefly_rt::process::signals::{self, Signal, SignalEntry};
+use firefly_rt::process::{MonitorOpts, Process, ProcessLock};
+use firefly_rt::services::distribution;
+use firefly_rt::services::registry::{self, Registrant, WeakAddress};
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+
+#[export_name = "erlang:monitor/2"]
+pub extern "C-unwind" fn monitor2(
+    process: &mut ProcessLock,
+    ty: OpaqueTerm,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    monitor(process, ty, item, OpaqueTerm::NIL)
+}
+
+/// Like `monitor/2`, but accepts the `{alias, Mode}` and `{tag, Tag}` options
+#[export_name = "erlang:monitor/3"]
+pub extern "C-unwind" fn monitor3(
+    process: &mut ProcessLock,
+    ty: OpaqueTerm,
+    item: OpaqueTerm,
+    opts: OpaqueTerm,
+) -> ErlangResult {
+    monitor(process, ty, item, opts)
+}
+
+#[export_name = "erlang:demonitor/1"]
+pub extern "C-unwind" fn demonitor1(
+    process: &mut ProcessLock,
+    reference: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(monitor_ref) = reference.into() else { badarg!(process, reference); };
+    demonitor(process, monitor_ref.id());
+    ErlangResult::Ok(true.into())
+}
+
+/// Removes the monitor identified by `reference`, with the `flush` and `info` options
+///
+/// With `flush`, any `'DOWN'` message already delivered for the monitor is removed from the
+/// message queue. With `info`, the result is `false` if the monitor was not found, i.e. it has
+/// already been triggered, otherwise the result is always `true`.
+#[export_name = "erlang:demonitor/2"]
+pub extern "C-unwind" fn demonitor2(
+    process: &mut ProcessLock,
+    reference: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(monitor_ref) = reference.into() else { badarg!(process, reference); };
+    let mut flush = false;
+    let mut info = false;
+    match options.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for option in cons.iter() {
+                match option {
+                    Ok(Term::Atom(option)) if option == atoms::Flush => flush = true,
+                    Ok(Term::Atom(option)) if option == atoms::Info => info = true,
+                    _ => badarg!(process, options),
+                }
+            }
+        }
+        _ => badarg!(process, options),
+    }
+
+    let id = monitor_ref.id();
+    let removed = demonitor(process, id);
+    if flush {
+        flush_down_message(process, id);
+    }
+    ErlangResult::Ok((removed || !info).into())
+}
+
+/// The target of a monitor, as given to `monitor/2,3`
+enum Target {
+    Pid(Pid),
+    /// A registered name, and the node it is registered on
+    Name(Atom, Atom),
+}
+
+fn monitor(
+    process: &mut ProcessLock,
+    ty: OpaqueTerm,
+    mut item: OpaqueTerm,
+    mut opts: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Atom(ty_atom) = ty.into() else { badarg!(process, ty); };
+    if ty_atom == atoms::Port {
+        process.exception_info.flags = ExceptionFlags::ERROR;
+        process.exception_info.reason = atoms::Notsup.into();
+        process.exception_info.value = ty;
+        process.exception_info.trace = None;
+        return ErlangResult::Err;
+    }
+    if ty_atom != atoms::Process {
+        badarg!(process, ty);
+    }
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut item as *mut OpaqueTerm;
+        roots += &mut opts as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let opts_term: Term = opts.into();
+    let Ok(monitor_opts) = MonitorOpts::try_from(opts_term) else { badarg!(process, opts); };
+    let current = distribution::current_node().name();
+    let target = match item.into() {
+        Term::Pid(pid) => Target::Pid(pid.deref().clone()),
+        Term::Atom(name) => Target::Name(name, current),
+        Term::Tuple(tuple) if tuple.len() == 2 => match (tuple[0].into(), tuple[1].into()) {
+            (Term::Atom(name), Term::Atom(node)) => Target::Name(name, node),
+            _ => badarg!(process, item),
+        },
+        _ => badarg!(process, item),
+    };
+
+    let reference_id = current_scheduler().next_reference_id();
+    process.signals().lock().set_receive_marker(reference_id);
+    let reference = match monitor_opts.alias {
+        None => Reference::new(reference_id),
+        Some(_) => Reference::new_pid(reference_id, process.pid()),
+    };
+    let flags = monitor_opts.flags;
+    let tag = if flags.contains(MonitorFlags::TAG) {
+        Some(monitor_opts.tag)
+    } else {
+        None
+    };
+
+    match target {
+        Target::Pid(pid) if !pid.is_local() => {
+            let target = WeakAddress::Process(pid);
+            trigger(
+                process,
+                &reference,
+                flags,
+                tag,
+                None,
+                target,
+                atoms::Noconnection,
+            );
+        }
+        Target::Name(name, node) if node != current => {
+            let target = WeakAddress::Name(name);
+            let name = Some((name, node));
+            trigger(
+                process,
+                &reference,
+                flags,
+                tag,
+                name,
+                target,
+                atoms::Noconnection,
+            );
+        }
+        Target::Pid(pid) => match registry::get_by_pid(&pid) {
+            Some(target) => monitor_process(process, target, &reference, flags, tag, None),
+            None => {
+                let target = WeakAddress::Process(pid);
+                trigger(process, &reference, flags, tag, None, target, atoms::Noproc);
+            }
+        },
+        Target::Name(name, node) => match registry::get_by_name(name) {
+            Some(Registrant::Process(target)) => {
+                monitor_process(process, target, &reference, flags, tag, Some((name, node)))
+            }
+            _ => {
+                let target = WeakAddress::Name(name);
+                let name = Some((name, node));
+                trigger(process, &reference, flags, tag, name, target, atoms::Noproc);
+            }
+        },
+    }
+
+    ErlangResult::Ok(Gc::new_in(reference, process).unwrap().into())
+}
+
+/// Creates a monitor of the local process `target` identified by `reference`
+///
+/// A process monitoring itself gets a monitor which is never triggered.
+fn monitor_process(
+    process: &mut ProcessLock,
+    target: Arc<Process>,
+    reference: &Reference,
+    mut flags: MonitorFlags,
+    tag: Option<OpaqueTerm>,
+    name: Option<(Atom, Atom)>,
+) {
+    let name_or_tag = match (tag, name) {
+        (Some(tag), Some((name, _))) => {
+            flags |= MonitorFlags::NAMED;
+            make_named_tag(tag, name)
+        }
+        (Some(tag), None) => TermFragment::copy_from(&tag.into(), CopyMode::Flat).unwrap(),
+        (None, Some((name, _))) => TermFragment::new(Term::Atom(name)).unwrap(),
+        (None, None) => TermFragment::new(Term::None).unwrap(),
+    };
+    let monitor = MonitorEntry::new(Monitor::LocalProcess {
+        origin: process.id(),
+        target: target.id(),
+        info: LocalMonitorInfo {
+            reference: reference.id(),
+            name_or_tag,
+        },
+    });
+    monitor.set_flags(flags);
+    process.monitored.insert(monitor.clone());
+
+    if target.id() == process.id() {
+        return;
+    }
+    let target_addr = target.addr();
+    if target.send_signal(Signal::monitor(monitor)).is_err() {
+        // The target exited before it could be told about the monitor
+        process.monitored.find_mut(&reference.id()).remove();
+        trigger(
+            process,
+            reference,
+            flags,
+            tag,
+            name,
+            target_addr,
+            atoms::Noproc,
+        );
+    }
+}
+
+/// Delivers the `'DOWN'` message for a monitor which could not be created
+///
+/// Any alias requested with the monitor remains active if it can only be removed explicitly.
+fn trigger(
+    process: &mut ProcessLock,
+    reference: &Reference,
+    flags: MonitorFlags,
+    tag: Option<OpaqueTerm>,
+    name: Option<(Atom, Atom)>,
+    target: WeakAddress,
+    reason: Atom,
+) {
+    if flags.alias() == Some(UnaliasMode::Explicit) {
+        let alias = MonitorEntry::new(Monitor::Alias {
+            origin: process.id(),
+            reference: reference.clone(),
+        });
+        alias.set_flags(flags & MonitorFlags::ALIAS_MASK);
+        process.monitored.insert(alias);
+    }
+    let message = make_down_message(
+        tag,
+        reference.clone(),
+        atoms::Process,
+        name,
+        target.clone(),
+        reason.into(),
+    );
+    process.send_fragment(target, message).ok();
+}
+
+/// Removes the monitor identified by `id` from this process, returning false if there is no such
+/// monitor
+///
+/// The target is told that the monitor is gone, unless it has already exited.
+fn demonitor(process: &mut ProcessLock, id: ReferenceId) -> bool {
+    let mut cursor = process.monitored.find_mut(&id);
+    let Some(monitor) = cursor.get() else { return false; };
+    // Aliases and pending spawn requests share the tree, but are not monitors
+    let is_monitor = match monitor.monitor {
+        Monitor::LocalProcess { .. } | Monitor::ToExternalProcess { .. } => {
+            !monitor.flags().contains(MonitorFlags::SPAWN_PENDING)
+        }
+        _ => false,
+    };
+    if !is_monitor {
+        return false;
+    }
+    let monitor = cursor.remove().unwrap();
+    if let Some(alias) = monitor.to_alias(process.pid()) {
+        process.monitored.insert(alias);
+    }
+
+    let target = match monitor.monitor {
+        Monitor::LocalProcess { target, .. } if target != process.id() => {
+            registry::get_by_process_id(target)
+        }
+        _ => None,
+    };
+    if let Some(target) = target {
+        let sender = process.addr();
+        target
+            .send_signal(SignalEntry::new(Signal::Demonitor(signals::Demonitor {
+                sender,
+                monitor,
+            })))
+            .ok();
+    }
+    true
+}
+
+/// Removes the `{_, Ref, _, _, _}` message delivered for the monitor identified by `id`, if any
+fn flush_down_message(process: &mut ProcessLock, id: ReferenceId) {
+    let proc = process.strong();
+    let mut signals = proc.signals().lock();
+    signals.remove_first_message(|message| match message.message.term.into() {
+        Term::Tuple(tuple) if tuple.len() == 5 => match tuple[1].into() {
+            Term::Reference(reference) => reference.id() == id,
+            _ => false,
+        },
+        _ => false,
+    });
+}
@@ -54,6 +54,12 @@ pub extern "C-unwind" fn plus2(
     lhs: OpaqueTerm,
     rhs: OpaqueTerm,
 ) -> ErlangResult {
+    if let Some(result) = lhs.int_arith(rhs, |l, r| l + r) {
+        return ErlangResult::Ok(result);
+    }
+    if let Some(result) = lhs.float_arith(rhs, |l, r| l + r) {
+        return ErlangResult::Ok(result);
+    }
     let l: Term = lhs.into();
     let r: Term = rhs.into();
     handle_arith_result!(process, lhs, l + r)
@@ -71,6 +77,12 @@ pub extern "C-unwind" fn minus2(
     lhs: OpaqueTerm,
     rhs: OpaqueTerm,
 ) -> ErlangResult {
+    if let Some(result) = lhs.int_arith(rhs, |l, r| l - r) {
+        return ErlangResult::Ok(result);
+    }
+    if let Some(result) = lhs.float_arith(rhs, |l, r| l - r) {
+        return ErlangResult::Ok(result);
+    }
     let l: Term = lhs.into();
     let r: Term = rhs.into();
     handle_arith_result!(process, lhs, l - r)
@@ -82,6 +94,12 @@ pub extern "C-unwind" fn mul2(
     lhs: OpaqueTerm,
     rhs: OpaqueTerm,
 ) -> ErlangResult {
+    if let Some(result) = lhs.int_arith(rhs, |l, r| l * r) {
+        return ErlangResult::Ok(result);
+    }
+    if let Some(result) = lhs.float_arith(rhs, |l, r| l * r) {
+        return ErlangResult::Ok(result);
+    }
     let l: Term = lhs.into();
     let r: Term = rhs.into();
     handle_arith_result!(process, lhs, l * r)
@@ -93,6 +111,9 @@ pub extern "C-unwind" fn divide2(
     lhs: OpaqueTerm,
     rhs: OpaqueTerm,
 ) -> ErlangResult {
+    if let Some(result) = lhs.float_arith(rhs, |l, r| l / r) {
+        return ErlangResult::Ok(result);
+    }
     let l: Term = lhs.into();
     let r: Term = rhs.into();
     match l / r {
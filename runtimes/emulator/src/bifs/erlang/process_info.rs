@@ -0,0 +1,820 @@
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+use firefly_alloc::heap::Heap;
+use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
+use firefly_rt::gc::{garbage_collect, Gc, RootSet};
+use firefly_rt::process::monitor::Monitor;
+use firefly_rt::process::signals::{Signal, SignalQueueLock};
+use firefly_rt::process::{ProcessFlags, ProcessLock, ProcessStatus, StatusFlags, ARG0_REG};
+use firefly_rt::services::registry::{self, WeakAddress};
+use firefly_rt::term::*;
+
+use smallvec::SmallVec;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+
+/// The items which may be requested via `process_info/2`
+#[derive(Copy, Clone)]
+enum ProcessInfoItem {
+    Status,
+    MessageQueueLen,
+    Messages,
+    Links,
+    Monitors,
+    MonitoredBy,
+    HeapSize,
+    TotalHeapSize,
+    StackSize,
+    Reductions,
+    RegisteredName,
+    CurrentFunction,
+    InitialCall,
+    TrapExit,
+    Priority,
+    GroupLeader,
+    GarbageCollection,
+    Parent,
+    SpawnedFrom,
+    /// `dictionary`
+    DictionaryAll,
+    /// `{dictionary, Key}`
+    Dictionary(OpaqueTerm),
+}
+impl ProcessInfoItem {
+    /// Parses either a single item, or a proper list of items, returning the items and whether
+    /// or not they were given as a list.
+    fn parse(term: OpaqueTerm) -> Option<(SmallVec<[Self; 4]>, bool)> {
+        let mut items = SmallVec::new();
+        match term.into() {
+            Term::Nil => Some((items, true)),
+            Term::Cons(cons) => {
+                for result in cons.iter_raw() {
+                    items.push(Self::try_from(result.ok()?).ok()?);
+                }
+                Some((items, true))
+            }
+            _ => {
+                items.push(Self::try_from(term).ok()?);
+                Some((items, false))
+            }
+        }
+    }
+
+    fn key(&self) -> Atom {
+        match self {
+            Self::Status => atoms::Status,
+            Self::MessageQueueLen => atoms::MessageQueueLen,
+            Self::Messages => atoms::Messages,
+            Self::Links => atoms::Links,
+            Self::Monitors => atoms::Monitors,
+            Self::MonitoredBy => atoms::MonitoredBy,
+            Self::HeapSize => atoms::HeapSize,
+            Self::TotalHeapSize => atoms::TotalHeapSize,
+            Self::StackSize => atoms::StackSize,
+            Self::Reductions => atoms::Reductions,
+            Self::RegisteredName => atoms::RegisteredName,
+            Self::CurrentFunction => atoms::CurrentFunction,
+            Self::InitialCall => atoms::InitialCall,
+            Self::TrapExit => atoms::TrapExit,
+            Self::Priority => atoms::Priority,
+            Self::GroupLeader => atoms::GroupLeader,
+            Self::GarbageCollection => atoms::GarbageCollection,
+            Self::Parent => atoms::Parent,
+            Self::SpawnedFrom => atoms::SpawnedFrom,
+            Self::DictionaryAll | Self::Dictionary(_) => atoms::Dictionary,
+        }
+    }
+
+    /// Returns true if the message queue must be flushed to answer for this item
+    fn needs_message_queue(&self) -> bool {
+        matches!(self, Self::MessageQueueLen | Self::Messages)
+    }
+}
+impl TryFrom<OpaqueTerm> for ProcessInfoItem {
+    type Error = ();
+
+    fn try_from(term: OpaqueTerm) -> Result<Self, Self::Error> {
+        match term.into() {
+            Term::Atom(a) if a == atoms::Status => Ok(Self::Status),
+            Term::Atom(a) if a == atoms::MessageQueueLen => Ok(Self::MessageQueueLen),
+            Term::Atom(a) if a == atoms::Messages => Ok(Self::Messages),
+            Term::Atom(a) if a == atoms::Links => Ok(Self::Links),
+            Term::Atom(a) if a == atoms::Monitors => Ok(Self::Monitors),
+            Term::Atom(a) if a == atoms::MonitoredBy => Ok(Self::MonitoredBy),
+            Term::Atom(a) if a == atoms::HeapSize => Ok(Self::HeapSize),
+            Term::Atom(a) if a == atoms::TotalHeapSize => Ok(Self::TotalHeapSize),
+            Term::Atom(a) if a == atoms::StackSize => Ok(Self::StackSize),
+            Term::Atom(a) if a == atoms::Reductions => Ok(Self::Reductions),
+            Term::Atom(a) if a == atoms::RegisteredName => Ok(Self::RegisteredName),
+            Term::Atom(a) if a == atoms::CurrentFunction => Ok(Self::CurrentFunction),
+            Term::Atom(a) if a == atoms::InitialCall => Ok(Self::InitialCall),
+            Term::Atom(a) if a == atoms::TrapExit => Ok(Self::TrapExit),
+            Term::Atom(a) if a == atoms::Priority => Ok(Self::Priority),
+            Term::Atom(a) if a == atoms::GroupLeader => Ok(Self::GroupLeader),
+            Term::Atom(a) if a == atoms::GarbageCollection => Ok(Self::GarbageCollection),
+            Term::Atom(a) if a == atoms::Parent => Ok(Self::Parent),
+            Term::Atom(a) if a == atoms::SpawnedFrom => Ok(Self::SpawnedFrom),
+            Term::Atom(a) if a == atoms::Dictionary => Ok(Self::DictionaryAll),
+            Term::Tuple(tuple) if tuple.len() == 2 && tuple[0] == atoms::Dictionary => {
+                Ok(Self::Dictionary(tuple[1]))
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+/// The items returned by `process_info/1`, `registered_name` is prepended if the process has one
+const DEFAULT_ITEMS: [Atom; 14] = [
+    atoms::CurrentFunction,
+    atoms::InitialCall,
+    atoms::Status,
+    atoms::MessageQueueLen,
+    atoms::Links,
+    atoms::Dictionary,
+    atoms::TrapExit,
+    atoms::Priority,
+    atoms::GroupLeader,
+    atoms::TotalHeapSize,
+    atoms::HeapSize,
+    atoms::StackSize,
+    atoms::Reductions,
+    atoms::GarbageCollection,
+];
+
+/// The well-known dictionary keys set by `proc_lib`, see `Inspector::lookup`
+fn proc_lib_keys() -> [Atom; 2] {
+    [atoms::DollarAncestors, atoms::DollarInitialCall]
+}
+
+/// The process being inspected, along with its locked signal queue
+///
+/// This is either the calling process inspecting itself, or a process handling the `ProcessInfo`
+/// signal sent by the caller. Either way, the state being read is owned by the current thread, so
+/// a process running on another scheduler is never read while it is executing.
+///
+/// When a process inspects itself, the results are built on its own heap, in which case the terms
+/// in its dictionary need not be copied. Otherwise, they are built in a heap fragment sent back to
+/// the caller, and everything is copied.
+struct Inspector<'a, 'p, 's> {
+    process: &'a ProcessLock<'p>,
+    signals: &'a SignalQueueLock<'s>,
+    is_self: bool,
+}
+impl<'a, 'p, 's> Inspector<'a, 'p, 's> {
+    fn status(&self) -> Atom {
+        if self.is_self {
+            return atoms::Running;
+        }
+        // The process is handling our signal rather than executing, so it is never `running` here,
+        // nor is it runnable just because it was scheduled to handle signals
+        let status = self.process.status(Ordering::Acquire)
+            - (StatusFlags::RUNNING_SYS | StatusFlags::ACTIVE_SYS);
+        ProcessStatus::from(status).as_atom()
+    }
+
+    fn links(&self) -> impl Iterator<Item = &Pid> + '_ {
+        self.process.links.iter().filter_map(|addr| match addr {
+            WeakAddress::Process(pid) => Some(pid),
+            _ => None,
+        })
+    }
+
+    /// Returns the processes monitored by the inspected process, with the name and node of each
+    /// monitor by name
+    fn monitors(&self) -> impl Iterator<Item = (Pid, Option<(Atom, Atom)>)> + '_ {
+        self.process.monitored.iter().filter_map(|monitor| {
+            match &monitor.monitor {
+                Monitor::LocalProcess { .. } | Monitor::ToExternalProcess { .. } => (),
+                _ => return None,
+            }
+            let Some(WeakAddress::Process(pid)) = monitor.target() else { return None; };
+            let name = monitor.name().map(|name| (name, monitor.node_name()));
+            Some((pid, name))
+        })
+    }
+
+    fn monitored_by(&self) -> impl Iterator<Item = Pid> + '_ {
+        self.process
+            .monitored_by
+            .iter()
+            .filter_map(|monitor| match monitor.origin() {
+                Some(WeakAddress::Process(pid)) => Some(pid),
+                _ => None,
+            })
+    }
+
+    fn current_function(&self) -> ModuleFunctionArity {
+        current_scheduler()
+            .mfa_by_ip(self.process.ip)
+            .unwrap_or(self.process.as_ref().initial_call)
+    }
+
+    fn heap_size(&self) -> usize {
+        self.process.heap.heap_size() / mem::size_of::<OpaqueTerm>()
+    }
+
+    fn total_heap_size(&self) -> usize {
+        let fragments = self
+            .process
+            .heap_fragments
+            .iter()
+            .map(|fragment| fragment.heap_size())
+            .sum::<usize>();
+        let mature = self.process.heap.mature().heap_size();
+        self.heap_size() + (mature + fragments) / mem::size_of::<OpaqueTerm>()
+    }
+
+    /// Adds the space needed to copy `term` to the layout, unless it is already on the heap the
+    /// results are built on
+    fn layout_term(&self, term: OpaqueTerm, layout: &mut LayoutBuilder) {
+        if !self.is_self {
+            let term: Term = term.into();
+            layout.extend(&term);
+        }
+    }
+
+    /// Copies `term` to `heap`, unless it is already on the heap the results are built on
+    ///
+    /// NOTE: This assumes that the heap has enough space, as calculated by `layout_term`
+    fn copy_term<H: ?Sized + Heap>(&self, term: OpaqueTerm, heap: &H) -> OpaqueTerm {
+        if self.is_self {
+            term
+        } else {
+            let term: Term = term.into();
+            unsafe { term.unsafe_clone_to_heap(heap).into() }
+        }
+    }
+
+    fn layout(&self, item: ProcessInfoItem, layout: &mut LayoutBuilder) {
+        layout.build_tuple(2);
+        match item {
+            ProcessInfoItem::Messages => {
+                let mut len = 0;
+                for message in self.signals.messages() {
+                    let term: Term = message.message.term.into();
+                    layout.extend(&term);
+                    len += 1;
+                }
+                layout.build_list(len);
+            }
+            ProcessInfoItem::Links => {
+                let len = self.links().count();
+                layout.build_list(len);
+                for _ in 0..len {
+                    layout.build_pid();
+                }
+            }
+            ProcessInfoItem::Monitors => {
+                let mut len = 0;
+                for (_, name) in self.monitors() {
+                    layout.build_tuple(2);
+                    if name.is_some() {
+                        layout.build_tuple(2);
+                    } else {
+                        layout.build_pid();
+                    }
+                    len += 1;
+                }
+                layout.build_list(len);
+            }
+            ProcessInfoItem::MonitoredBy => {
+                let len = self.monitored_by().count();
+                layout.build_list(len);
+                for _ in 0..len {
+                    layout.build_pid();
+                }
+            }
+            ProcessInfoItem::CurrentFunction | ProcessInfoItem::InitialCall => {
+                layout.build_tuple(3);
+            }
+            ProcessInfoItem::GarbageCollection => {
+                layout.build_list(3);
+                for _ in 0..3 {
+                    layout.build_tuple(2);
+                }
+            }
+            ProcessInfoItem::GroupLeader => {
+                layout.build_pid();
+            }
+            ProcessInfoItem::Parent if self.process.parent().is_some() => {
+                layout.build_pid();
+            }
+            ProcessInfoItem::SpawnedFrom if self.process.spawn_info().spawned_from.is_some() => {
+                layout.build_tuple(3);
+            }
+            ProcessInfoItem::DictionaryAll => {
+                let len = self.process.dictionary.len();
+                for (key, value) in self.process.dictionary.iter() {
+                    layout.build_tuple(2);
+                    self.layout_term(key, layout);
+                    self.layout_term(value, layout);
+                }
+                for key in proc_lib_keys() {
+                    layout.build_tuple(2);
+                    self.layout_proc_lib_value(key, layout);
+                }
+                layout.build_list(len + proc_lib_keys().len());
+            }
+            ProcessInfoItem::Dictionary(key) => {
+                layout.build_tuple(2);
+                self.layout_term(key, layout);
+                match self.process.dictionary.get(key) {
+                    Some(value) => self.layout_term(value, layout),
+                    None => {
+                        if let Term::Atom(key) = key.into() {
+                            self.layout_proc_lib_value(key, layout);
+                        }
+                    }
+                }
+            }
+            ProcessInfoItem::Status
+            | ProcessInfoItem::MessageQueueLen
+            | ProcessInfoItem::HeapSize
+            | ProcessInfoItem::TotalHeapSize
+            | ProcessInfoItem::StackSize
+            | ProcessInfoItem::Reductions
+            | ProcessInfoItem::RegisteredName
+            | ProcessInfoItem::TrapExit
+            | ProcessInfoItem::Priority
+            | ProcessInfoItem::Parent
+            | ProcessInfoItem::SpawnedFrom => (),
+        }
+    }
+
+    fn layout_proc_lib_value(&self, key: Atom, layout: &mut LayoutBuilder) {
+        if key == atoms::DollarAncestors {
+            let len = self.process.spawn_info().ancestors.len();
+            layout.build_list(len);
+            for _ in 0..len {
+                layout.build_pid();
+            }
+        } else if key == atoms::DollarInitialCall {
+            layout.build_tuple(3);
+        }
+    }
+
+    /// Returns the value of `key` in the dictionary of the inspected process, if known
+    ///
+    /// The well-known keys set by `proc_lib` are derived from the spawn metadata when they are not
+    /// in the dictionary, as tooling relies on them.
+    fn lookup<H: ?Sized + Heap>(&self, key: OpaqueTerm, heap: &H) -> Option<OpaqueTerm> {
+        if let Some(value) = self.process.dictionary.get(key) {
+            return Some(self.copy_term(value, heap));
+        }
+        if key == atoms::DollarAncestors {
+            let mut builder = ListBuilder::new(heap);
+            for id in self.process.spawn_info().ancestors.iter().rev() {
+                let pid = Gc::new_in(Pid::new_local(*id), heap).unwrap();
+                builder.push(Term::Pid(pid)).unwrap();
+            }
+            Some(
+                builder
+                    .finish()
+                    .map(|list| list.into())
+                    .unwrap_or(OpaqueTerm::NIL),
+            )
+        } else if key == atoms::DollarInitialCall {
+            Some(mfa_to_term(&self.process.as_ref().initial_call, heap))
+        } else {
+            None
+        }
+    }
+
+    /// Constructs the `{Item, Value}` tuple for `item`
+    ///
+    /// NOTE: This assumes that the heap has enough space, as calculated by `layout`
+    fn build<H: ?Sized + Heap>(&self, item: ProcessInfoItem, heap: &H) -> OpaqueTerm {
+        let value = match item {
+            ProcessInfoItem::Status => self.status().into(),
+            ProcessInfoItem::MessageQueueLen => {
+                Term::Int(self.signals.messages().count() as i64).into()
+            }
+            ProcessInfoItem::Messages => {
+                let messages = self
+                    .signals
+                    .messages()
+                    .map(|message| {
+                        let term: Term = message.message.term.into();
+                        unsafe { term.unsafe_clone_to_heap(heap) }
+                    })
+                    .collect::<Vec<_>>();
+                build_list(messages.into_iter(), heap)
+            }
+            ProcessInfoItem::Links => {
+                let pids = self
+                    .links()
+                    .map(|pid| Term::Pid(Gc::new_in(pid.clone(), heap).unwrap()))
+                    .collect::<SmallVec<[Term; 8]>>();
+                build_list(pids.into_iter(), heap)
+            }
+            ProcessInfoItem::Monitors => {
+                let monitors = self
+                    .monitors()
+                    .map(|(pid, name)| {
+                        let target = match name {
+                            Some((name, node)) => {
+                                Tuple::from_slice(&[name.into(), node.into()], heap)
+                                    .unwrap()
+                                    .into()
+                            }
+                            None => Gc::new_in(pid, heap).unwrap().into(),
+                        };
+                        let monitor =
+                            Tuple::from_slice(&[atoms::Process.into(), target], heap).unwrap();
+                        Term::Tuple(monitor)
+                    })
+                    .collect::<SmallVec<[Term; 8]>>();
+                build_list(monitors.into_iter(), heap)
+            }
+            ProcessInfoItem::MonitoredBy => {
+                let pids = self
+                    .monitored_by()
+                    .map(|pid| Term::Pid(Gc::new_in(pid, heap).unwrap()))
+                    .collect::<SmallVec<[Term; 8]>>();
+                build_list(pids.into_iter(), heap)
+            }
+            ProcessInfoItem::HeapSize => Term::Int(self.heap_size() as i64).into(),
+            ProcessInfoItem::TotalHeapSize => Term::Int(self.total_heap_size() as i64).into(),
+            ProcessInfoItem::StackSize => Term::Int(self.process.stack.size() as i64).into(),
+            ProcessInfoItem::Reductions => {
+                let reductions = self.process.total_reductions + self.process.reductions as u64;
+                Term::Int(reductions as i64).into()
+            }
+            ProcessInfoItem::RegisteredName => match self.process.registered_name() {
+                Some(name) => name.into(),
+                None => OpaqueTerm::NIL,
+            },
+            ProcessInfoItem::CurrentFunction => mfa_to_term(&self.current_function(), heap),
+            ProcessInfoItem::InitialCall => mfa_to_term(&self.process.as_ref().initial_call, heap),
+            ProcessInfoItem::TrapExit => {
+                self.process.flags.contains(ProcessFlags::TRAP_EXIT).into()
+            }
+            ProcessInfoItem::Priority => self.process.status(Ordering::Relaxed).priority().into(),
+            ProcessInfoItem::GarbageCollection => {
+                let process = self.process.as_ref();
+                let min_heap_size = process.min_heap_size.map(|sz| sz.get()).unwrap_or(0);
+                // Processes which never fully sweep report the default of ERTS
+                let fullsweep_after = match process.fullsweep_after.load(Ordering::Relaxed) {
+                    usize::MAX => u16::MAX as usize,
+                    n => n,
+                };
+                let options = [
+                    (atoms::MinHeapSize, min_heap_size),
+                    (atoms::FullsweepAfter, fullsweep_after),
+                    (atoms::MinorGcs, self.process.gc_count),
+                ]
+                .map(|(key, value)| {
+                    let value = Term::Int(value as i64).into();
+                    Term::Tuple(Tuple::from_slice(&[key.into(), value], heap).unwrap())
+                });
+                build_list(options.into_iter(), heap)
+            }
+            ProcessInfoItem::GroupLeader => {
+                // A process without a group leader, i.e. the init process, is its own group leader
+                let group_leader = self
+                    .process
+                    .group_leader()
+                    .cloned()
+                    .unwrap_or_else(|| self.process.pid());
+                Gc::new_in(group_leader, heap).unwrap().into()
+            }
+            ProcessInfoItem::Parent => match self.process.parent() {
+                None => atoms::Undefined.into(),
+                Some(pid) => Gc::new_in(pid, heap).unwrap().into(),
+            },
+            ProcessInfoItem::SpawnedFrom => match self.process.spawn_info().spawned_from.as_ref() {
+                None => atoms::Undefined.into(),
+                Some(mfa) => mfa_to_term(mfa, heap),
+            },
+            ProcessInfoItem::DictionaryAll => {
+                let mut entries = SmallVec::<[Term; 8]>::new();
+                for (key, value) in self.process.dictionary.iter() {
+                    let key = self.copy_term(key, heap);
+                    let value = self.copy_term(value, heap);
+                    entries.push(Term::Tuple(Tuple::from_slice(&[key, value], heap).unwrap()));
+                }
+                for key in proc_lib_keys() {
+                    let key: OpaqueTerm = key.into();
+                    if self.process.dictionary.get(key).is_none() {
+                        let value = self.lookup(key, heap).unwrap();
+                        let entry = Tuple::from_slice(&[key, value], heap).unwrap();
+                        entries.push(Term::Tuple(entry));
+                    }
+                }
+                build_list(entries.into_iter(), heap)
+            }
+            ProcessInfoItem::Dictionary(key) => {
+                let value = self
+                    .lookup(key, heap)
+                    .unwrap_or_else(|| atoms::Undefined.into());
+                let key = self.copy_term(key, heap);
+                let item = Tuple::from_slice(&[atoms::Dictionary.into(), key], heap).unwrap();
+                return Tuple::from_slice(&[item.into(), value], heap)
+                    .unwrap()
+                    .into();
+            }
+        };
+        Tuple::from_slice(&[item.key().into(), value], heap)
+            .unwrap()
+            .into()
+    }
+
+    fn layout_all(&self, items: &[ProcessInfoItem], is_list: bool) -> LayoutBuilder {
+        let mut layout = LayoutBuilder::new();
+        if is_list {
+            layout.build_list(items.len());
+        }
+        for item in items.iter().copied() {
+            self.layout(item, &mut layout);
+        }
+        layout
+    }
+
+    /// Constructs the result of `process_info/2` for `items`
+    ///
+    /// NOTE: This assumes that the heap has enough space, as calculated by `layout_all`
+    fn build_all<H: ?Sized + Heap>(
+        &self,
+        items: &[ProcessInfoItem],
+        is_list: bool,
+        heap: &H,
+    ) -> OpaqueTerm {
+        if !is_list {
+            // A process without a registered name reports `[]` rather than a tuple in this case
+            if let ProcessInfoItem::RegisteredName = items[0] {
+                if self.process.registered_name().is_none() {
+                    return OpaqueTerm::NIL;
+                }
+            }
+            return self.build(items[0], heap);
+        }
+
+        let results = items
+            .iter()
+            .map(|item| self.build(*item, heap).into())
+            .collect::<SmallVec<[Term; 4]>>();
+        build_list(results.into_iter(), heap)
+    }
+}
+
+/// Constructs a proper list from `elements`, in order
+fn build_list<H: ?Sized + Heap, I>(elements: I, heap: &H) -> OpaqueTerm
+where
+    I: DoubleEndedIterator<Item = Term>,
+{
+    // Lists are constructed back to front
+    let mut builder = ListBuilder::new(heap);
+    for element in elements.rev() {
+        builder.push(element).unwrap();
+    }
+    builder
+        .finish()
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL)
+}
+
+fn mfa_to_term<H: ?Sized + Heap>(mfa: &ModuleFunctionArity, heap: &H) -> OpaqueTerm {
+    Tuple::from_slice(
+        &[
+            mfa.module.into(),
+            mfa.function.into(),
+            Term::Int(mfa.arity as i64).into(),
+        ],
+        heap,
+    )
+    .unwrap()
+    .into()
+}
+
+/// Constructs the `{Ref, Result}` reply to a `ProcessInfo` signal requesting `items`
+///
+/// This is called by the inspected process while handling the signal, with its signal queue
+/// locked. If the process is exiting, `target` is `None` and the result is `undefined`.
+pub(crate) fn make_process_info_reply(
+    target: Option<(&ProcessLock, &SignalQueueLock)>,
+    items: OpaqueTerm,
+    reference: Reference,
+) -> TermFragment {
+    // The items were validated by the sender
+    let (items, is_list) = ProcessInfoItem::parse(items).unwrap();
+    let inspector = target.map(|(process, signals)| Inspector {
+        process,
+        signals,
+        is_self: false,
+    });
+
+    let mut layout = match inspector.as_ref() {
+        Some(inspector) => inspector.layout_all(&items, is_list),
+        None => LayoutBuilder::new(),
+    };
+    layout.build_reference().build_tuple(2);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let result = match inspector.as_ref() {
+        Some(inspector) => inspector.build_all(&items, is_list, fragment),
+        None => atoms::Undefined.into(),
+    };
+    let reference = Gc::new_in(reference, fragment).unwrap();
+    let reply = Tuple::from_slice(&[reference.into(), result], fragment).unwrap();
+    TermFragment {
+        term: reply.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
+
+/// Answers `process_info/2` for the calling process
+fn process_info_self(process: &mut ProcessLock, mut item_term: OpaqueTerm) -> ErlangResult {
+    let proc = process.strong();
+    let (mut items, is_list) = ProcessInfoItem::parse(item_term).unwrap();
+    let needed = {
+        let mut signals = proc.signals().lock();
+        if items.iter().any(|item| item.needs_message_queue()) {
+            signals.flush_buffers();
+        }
+        let inspector = Inspector {
+            process: &*process,
+            signals: &signals,
+            is_self: true,
+        };
+        inspector.layout_all(&items, is_list).finish().size()
+    };
+    // The signal queue must not be locked during a collection
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut item_term as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+        // Dictionary keys may have been moved by the collection
+        items = ProcessInfoItem::parse(item_term).unwrap().0;
+    }
+
+    let signals = proc.signals().lock();
+    let inspector = Inspector {
+        process: &*process,
+        signals: &signals,
+        is_self: true,
+    };
+    ErlangResult::Ok(inspector.build_all(&items, is_list, &*process))
+}
+
+static PROCESS_INFO_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+    module: atoms::ErtsInternal,
+    function: atoms::ProcessInfo,
+    arity: 2,
+};
+
+#[export_name = "erlang:process_info/1"]
+pub extern "C-unwind" fn process_info1(
+    process: &mut ProcessLock,
+    mut pid_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    if !pid.is_local() {
+        badarg!(process, pid_term);
+    }
+    let registered_name = if process.id() == pid.id() {
+        process.registered_name()
+    } else {
+        match registry::get_by_pid(&pid) {
+            None => return ErlangResult::Ok(atoms::Undefined.into()),
+            Some(other) => other.registered_name(),
+        }
+    };
+
+    let mut items = SmallVec::<[Atom; 15]>::new();
+    if registered_name.is_some() {
+        items.push(atoms::RegisteredName);
+    }
+    items.extend_from_slice(&DEFAULT_ITEMS);
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(items.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut pid_term as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+    let item_term = build_list(items.into_iter().map(Term::Atom), &*process);
+
+    process_info2(process, pid_term, item_term)
+}
+
+#[export_name = "erlang:process_info/2"]
+pub extern "C-unwind" fn process_info2(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    item_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    if !pid.is_local() {
+        badarg!(process, pid_term);
+    }
+    if ProcessInfoItem::parse(item_term).is_none() {
+        badarg!(process, item_term);
+    }
+
+    if process.id() == pid.id() {
+        return process_info_self(process, item_term);
+    }
+
+    let Some(other) = registry::get_by_pid(&pid) else {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    };
+    if other
+        .status(Ordering::Acquire)
+        .contains(StatusFlags::EXITING)
+    {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    }
+
+    // The state of another process is only read by the process itself, so the request is sent to
+    // it as a signal, and the reply awaited in `erts_internal:process_info/2`
+    process.stack.store(ARG0_REG, pid_term);
+    process.stack.store(ARG0_REG + 1, item_term);
+    ErlangResult::Trap(&PROCESS_INFO_TRAP_EXPORT)
+}
+
+/// Sends a `ProcessInfo` signal requesting `items` to `pid`, which replies with `{Ref, Result}`
+///
+/// Returns `undefined` if the process does not exist, in which case no reply is sent.
+#[export_name = "erts_internal:process_info/3"]
+pub extern "C-unwind" fn process_info3(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    item_term: OpaqueTerm,
+    ref_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    let Term::Reference(req_ref) = ref_term.into() else { badarg!(process, ref_term); };
+    let Some((items, _)) = ProcessInfoItem::parse(item_term) else { badarg!(process, item_term); };
+
+    let Some(other) = registry::get_by_pid(&pid) else {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    };
+    let need_msgq_len = items.iter().any(|item| item.needs_message_queue());
+    let items: Term = item_term.into();
+    let items = items.clone_to_fragment().unwrap();
+    let sig = Signal::process_info(
+        process.strong(),
+        items,
+        req_ref.deref().clone(),
+        need_msgq_len,
+    );
+    if other.send_signal(sig).is_err() {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    }
+
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+/// Answers the `ProcessInfo` signal sent to `pid` by `erts_internal:process_info/3` on its behalf,
+/// if it has not handled the signal yet and is waiting to be scheduled rather than executing
+///
+/// This is the fallback used when the target takes too long to reach a point at which it handles
+/// signals, e.g. because it is queued behind other busy processes. The reply is sent to the caller
+/// exactly as the target would have sent it.
+///
+/// Returns `ok` if the reply was sent, `pending` if the target has already taken the request, so
+/// its reply is on the way, or `busy` if the target could not be inspected right now.
+#[export_name = "erts_internal:process_info_fallback/2"]
+pub extern "C-unwind" fn process_info_fallback2(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    ref_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    let Term::Reference(req_ref) = ref_term.into() else { badarg!(process, ref_term); };
+
+    // A process which is gone has answered every request it received before exiting
+    let Some(other) = registry::get_by_pid(&pid) else {
+        return ErlangResult::Ok(atoms::Pending.into());
+    };
+    let reply = other.try_inspect(|target| {
+        let mut signals = other.signals().lock();
+        let entry = signals.remove_first_signal(|sig| match sig {
+            Signal::ProcessInfo(sig) => sig.reference == *req_ref,
+            _ => false,
+        })?;
+        let Signal::ProcessInfo(sig) = entry.signal else { unreachable!() };
+        if sig.need_msgq_len {
+            signals.flush_buffers();
+        }
+        let target = Some((&*target, &signals));
+        Some(make_process_info_reply(target, sig.items.term, sig.reference))
+    });
+
+    match reply {
+        None => ErlangResult::Ok(atoms::Busy.into()),
+        Some(None) => ErlangResult::Ok(atoms::Pending.into()),
+        Some(Some(reply)) => {
+            process.send_fragment(other.pid().into(), reply).ok();
+            ErlangResult::Ok(atoms::Ok.into())
+        }
+    }
+}
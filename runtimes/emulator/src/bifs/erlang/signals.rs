@@ -1,3 +1,4 @@
+use std::alloc::Layout;
 use std::mem;
 use std::ops::Deref;
 use std::sync::atomic::Ordering;
@@ -7,9 +8,10 @@ use firefly_alloc::heap::Heap;
 use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
 use firefly_rt::gc::{garbage_collect, Gc, RootSet};
 use firefly_rt::process::monitor::{Monitor, MonitorEntry, MonitorFlags, UnaliasMode};
-use firefly_rt::process::signals::Signal;
+use firefly_rt::process::signals::{self, Signal, SignalEntry};
 use firefly_rt::process::{Process, ProcessFlags, ProcessLock, StatusFlags, SystemTask, ARG0_REG};
 use firefly_rt::scheduler::Scheduler;
+use firefly_rt::services::distribution;
 use firefly_rt::services::registry::{self, Registrant, WeakAddress};
 use firefly_rt::term::*;
 
@@ -136,6 +138,66 @@ static HANDLE_SIGNALS_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
     arity: 2,
 };
 
+/// Sends an exit signal with `reason` to `pid`
+///
+/// The compiler lowers direct calls to `exit/2` to the `Exit2` instruction, so this is only used
+/// for dynamic calls, e.g. via `apply/3`.
+#[export_name = "erlang:exit/2"]
+pub extern "C-unwind" fn exit2(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    reason: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    if send_exit_signal(process, &pid, reason) {
+        // The signal may terminate the caller, in which case it must do so before returning
+        process.stack.store(ARG0_REG, pid_term);
+        process.stack.store(ARG0_REG + 1, true.into());
+        return ErlangResult::Trap(&HANDLE_SIGNALS_TRAP_EXPORT);
+    }
+    ErlangResult::Ok(true.into())
+}
+
+/// Sends an exit signal with `reason` from `process` to `pid`, as done by `exit/2`
+///
+/// How the signal is handled is decided by the receiver when it handles the signal:
+///
+/// * If it is trapping exits, the signal is converted to an `{'EXIT', From, Reason}` message,
+///   unless `reason` is `kill`
+/// * Otherwise, if `reason` is `kill`, the receiver exits with reason `killed`
+/// * Otherwise, if `reason` is `normal`, the signal is ignored, unless it was sent by the receiver
+///   to itself
+/// * Otherwise, the receiver exits with `reason`
+///
+/// Only atom reasons can be sent to processes on other nodes for now, others are dropped.
+///
+/// Returns true if `pid` is the calling process, in which case the caller must handle its signals
+/// before continuing, since the signal may terminate it.
+pub(crate) fn send_exit_signal(process: &mut ProcessLock, pid: &Pid, reason: OpaqueTerm) -> bool {
+    if !pid.is_local() {
+        if let Term::Atom(reason) = reason.into() {
+            distribution::send_exit(process.pid(), pid.clone(), reason).ok();
+        }
+        return false;
+    }
+
+    let is_self = process.id() == pid.id();
+    let signal = SignalEntry::new(Signal::Exit(signals::Exit {
+        sender: Some(process.addr()),
+        reason: TermFragment::new(reason.into()).unwrap(),
+        // Whether the exit is trapped is decided when the signal is handled, as the trap_exit flag
+        // may change before then, so a normal exit sent to self must kill if not
+        normal_kills: is_self,
+    }));
+    if is_self {
+        process.send_signal(signal).ok();
+    } else if let Some(receiver) = registry::get_by_pid(pid) {
+        // The receiver may already be exiting, in which case the signal is moot
+        receiver.send_signal(signal).ok();
+    }
+    is_self
+}
+
 #[export_name = "erlang:is_process_alive/1"]
 pub extern "C-unwind" fn is_process_alive(
     process: &mut ProcessLock,
@@ -158,7 +220,10 @@ pub extern "C-unwind" fn is_process_alive(
                 None => result = false,
                 Some(other) => {
                     let status = other.status(Ordering::Acquire);
-                    if status.intersects(
+                    if status.contains(StatusFlags::FREE) {
+                        // The process finished exiting after we looked it up
+                        result = false;
+                    } else if status.intersects(
                         StatusFlags::EXITING
                             | StatusFlags::HAS_PENDING_SIGNALS
                             | StatusFlags::HAS_IN_TRANSIT_SIGNALS,
@@ -172,9 +237,9 @@ pub extern "C-unwind" fn is_process_alive(
                         // have earlier sent it an exit signal that has not been processed yet).
                         process.stack.store(ARG0_REG, pid_term);
                         return ErlangResult::Trap(&IS_PROCESS_ALIVE_TRAP_EXPORT);
+                    } else {
+                        result = true;
                     }
-
-                    result = true;
                 }
             }
 
@@ -207,19 +272,23 @@ pub extern "C-unwind" fn is_process_alive2(
         None => failed = true,
         Some(other) => {
             let sig = Signal::is_alive(process.pid(), req_ref.deref().clone());
-            failed = other.send_signal(sig).is_err();
+            // The signal must not be left in the in-transit queue, as a process which has already
+            // handled its signals for the last time would never reply to it; this fails instead
+            failed = other.send_signal_after_flush(sig).is_err();
         }
     }
 
+    // If the signal could not be sent, no reply will arrive, so the caller must not wait for one
     if failed {
         let status = process.status(Ordering::Acquire);
         if status.intersects(StatusFlags::HAS_PENDING_SIGNALS | StatusFlags::HAS_IN_TRANSIT_SIGNALS)
         {
             // Ensure that the signal order of signals from inspected process to us is preserved
             process.stack.store(ARG0_REG, pid_term);
-            process.stack.store(ARG0_REG + 1, atoms::Ok.into());
+            process.stack.store(ARG0_REG + 1, false.into());
             return ErlangResult::Trap(&HANDLE_SIGNALS_TRAP_EXPORT);
         }
+        return ErlangResult::Ok(false.into());
     }
 
     ErlangResult::Ok(atoms::Ok.into())
@@ -462,38 +531,46 @@ fn schedule_sys_task(
             Ordering::Release,
         );
     } else {
-        let mut status = target.status(Ordering::Acquire);
-        loop {
-            if status.contains(StatusFlags::EXITING | StatusFlags::FREE) {
-                return Err(system_task);
-            }
-            match target.status.compare_exchange(
-                status,
-                status | StatusFlags::ACTIVE_SYS | StatusFlags::SYS_TASKS,
-                Ordering::Release,
-                Ordering::Acquire,
-            ) {
-                Ok(prev) => {
-                    // The process is currently suspended, we need to reschedule it
-                    let tgt = target.clone();
-                    let mut guard = target.lock();
-                    guard.system_tasks[system_task.priority as usize].push_back(system_task);
-                    // If currently suspended, wake up the process to handle the task
-                    if prev.contains(StatusFlags::SUSPENDED) {
-                        guard.injector.push(tgt);
-                    }
-                    break;
-                }
-                Err(current) => {
-                    status = current;
-                }
-            }
-        }
+        enqueue_sys_task(process.pid(), target, system_task)?;
     }
 
     Ok(())
 }
 
+/// Enqueues `system_task` on `target`, which must not be the calling process
+///
+/// The task is delivered as a signal from `sender`, and `target` schedules it for itself when it
+/// handles the signal, see `schedule_sig_sys_task`, so the queue of another process is never
+/// touched while it may be running. Returns the task if `target` is exiting.
+pub(crate) fn enqueue_sys_task(
+    sender: Pid,
+    target: Arc<Process>,
+    system_task: Box<SystemTask>,
+) -> Result<(), Box<SystemTask>> {
+    if target
+        .status(Ordering::Acquire)
+        .intersects(StatusFlags::EXITING | StatusFlags::FREE)
+    {
+        return Err(system_task);
+    }
+    let priority = system_task.priority;
+    let st = Box::into_raw(system_task);
+    let signal = Signal::rpc_noreply(sender, schedule_sig_sys_task, st.cast(), priority);
+    target
+        .send_signal(signal)
+        .map_err(|_| unsafe { Box::from_raw(st) })
+}
+
+/// Schedules a full sweep garbage collection of `target` on behalf of the runtime system
+///
+/// No reply is sent when the collection completes. Returns false if the process is exiting.
+pub(crate) fn schedule_system_gc(target: Arc<Process>) -> bool {
+    use firefly_rt::process::SystemTaskType;
+
+    let Ok(system_task) = SystemTask::new(SystemTaskType::GcMajor, Layout::new::<OpaqueTerm>()) else { return false; };
+    enqueue_sys_task(target.pid(), target, system_task).is_ok()
+}
+
 pub(crate) fn notify_sys_task_executed(
     process: &mut ProcessLock,
     task: Box<SystemTask>,
@@ -526,8 +603,8 @@ fn send_sys_task_executed_reply(
     let fragment = unsafe { fragment_ptr.as_ref() };
 
     let tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
-    let request_id = unsafe { tag.unsafe_clone_to_heap(fragment) };
-    let result = unsafe { tag.unsafe_clone_to_heap(fragment) };
+    let request_id = unsafe { request_id.unsafe_clone_to_heap(fragment) };
+    let result = unsafe { result.unsafe_clone_to_heap(fragment) };
     let tuple =
         Tuple::from_slice(&[tag.into(), request_id.into(), result.into()], fragment).unwrap();
 
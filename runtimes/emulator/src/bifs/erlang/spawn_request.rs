@@ -0,0 +1,334 @@
+use std::sync::Arc;
+
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::{
+    list_to_args, ArgumentListError, ErlangResult, ModuleFunctionArity, MAX_ARGS,
+};
+use firefly_rt::gc::{garbage_collect, Gc, RootSet};
+use firefly_rt::process::monitor::{Monitor, MonitorEntry, MonitorFlags, RemoteMonitorInfo};
+use firefly_rt::process::{make_spawn_reply, ProcessLock, SpawnOpts};
+use firefly_rt::scheduler::Scheduler;
+use firefly_rt::services::distribution::{self, SpawnRequest};
+use firefly_rt::services::registry::WeakAddress;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+
+/// The function to be executed by a process spawned via `spawn_request`
+#[derive(Copy, Clone)]
+enum SpawnTarget {
+    /// A fun of arity zero
+    Fun(OpaqueTerm),
+    /// `apply(Module, Function, Args)`
+    Apply(OpaqueTerm, OpaqueTerm, OpaqueTerm),
+}
+
+#[export_name = "erlang:spawn_request/1"]
+pub extern "C-unwind" fn spawn_request1(
+    process: &mut ProcessLock,
+    fun: OpaqueTerm,
+) -> ErlangResult {
+    spawn_request(process, None, SpawnTarget::Fun(fun), OpaqueTerm::NIL)
+}
+
+#[export_name = "erlang:spawn_request/2"]
+pub extern "C-unwind" fn spawn_request2(
+    process: &mut ProcessLock,
+    fun_or_node: OpaqueTerm,
+    opts_or_fun: OpaqueTerm,
+) -> ErlangResult {
+    // spawn_request(Node, Fun) | spawn_request(Fun, Options)
+    if fun_or_node.is_atom() {
+        let target = SpawnTarget::Fun(opts_or_fun);
+        spawn_request(process, Some(fun_or_node), target, OpaqueTerm::NIL)
+    } else {
+        spawn_request(process, None, SpawnTarget::Fun(fun_or_node), opts_or_fun)
+    }
+}
+
+#[export_name = "erlang:spawn_request/3"]
+pub extern "C-unwind" fn spawn_request3(
+    process: &mut ProcessLock,
+    a: OpaqueTerm,
+    b: OpaqueTerm,
+    c: OpaqueTerm,
+) -> ErlangResult {
+    // spawn_request(Node, Fun, Options) | spawn_request(Module, Function, Args)
+    if b.is_atom() {
+        spawn_request(process, None, SpawnTarget::Apply(a, b, c), OpaqueTerm::NIL)
+    } else {
+        spawn_request(process, Some(a), SpawnTarget::Fun(b), c)
+    }
+}
+
+#[export_name = "erlang:spawn_request/4"]
+pub extern "C-unwind" fn spawn_request4(
+    process: &mut ProcessLock,
+    a: OpaqueTerm,
+    b: OpaqueTerm,
+    c: OpaqueTerm,
+    d: OpaqueTerm,
+) -> ErlangResult {
+    // spawn_request(Node, Module, Function, Args) | spawn_request(Module, Function, Args, Options)
+    if c.is_atom() {
+        spawn_request(
+            process,
+            Some(a),
+            SpawnTarget::Apply(b, c, d),
+            OpaqueTerm::NIL,
+        )
+    } else {
+        spawn_request(process, None, SpawnTarget::Apply(a, b, c), d)
+    }
+}
+
+#[export_name = "erlang:spawn_request/5"]
+pub extern "C-unwind" fn spawn_request5(
+    process: &mut ProcessLock,
+    node: OpaqueTerm,
+    module: OpaqueTerm,
+    function: OpaqueTerm,
+    args: OpaqueTerm,
+    opts: OpaqueTerm,
+) -> ErlangResult {
+    let target = SpawnTarget::Apply(module, function, args);
+    spawn_request(process, Some(node), target, opts)
+}
+
+#[export_name = "erlang:spawn_request_abandon/1"]
+pub extern "C-unwind" fn spawn_request_abandon1(
+    process: &mut ProcessLock,
+    ref_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(reference) = ref_term.into() else {
+        badarg!(process, ref_term);
+    };
+
+    // Local spawn requests complete immediately, so only remote requests can still be pending
+    let cursor = process.monitored.find(&reference.id());
+    let abandoned = match cursor.get() {
+        Some(monitor) => {
+            let flags = monitor.flags();
+            let pending = flags & (MonitorFlags::SPAWN_PENDING | MonitorFlags::SPAWN_ABANDONED);
+            if pending == MonitorFlags::SPAWN_PENDING {
+                monitor.set_flags(MonitorFlags::SPAWN_ABANDONED);
+                true
+            } else {
+                false
+            }
+        }
+        None => false,
+    };
+
+    ErlangResult::Ok(abandoned.into())
+}
+
+fn spawn_request(
+    process: &mut ProcessLock,
+    node: Option<OpaqueTerm>,
+    mut target: SpawnTarget,
+    mut opts: OpaqueTerm,
+) -> ErlangResult {
+    if let Some(node) = node {
+        if !node.is_atom() {
+            badarg!(process, node);
+        }
+    }
+    match target {
+        SpawnTarget::Fun(fun) => match fun.into() {
+            Term::Closure(closure) if closure.arity as usize == (!closure.is_thin() as usize) => (),
+            _ => badarg!(process, fun),
+        },
+        SpawnTarget::Apply(module, function, _) => {
+            if !module.is_atom() {
+                badarg!(process, module);
+            }
+            if !function.is_atom() {
+                badarg!(process, function);
+            }
+        }
+    }
+
+    let opts_term: Term = opts.into();
+    let mut spawn_opts: SpawnOpts = match opts_term.try_into() {
+        Ok(spawn_opts) => spawn_opts,
+        Err(_) => badarg!(process, opts),
+    };
+    spawn_opts.spawn_async = true;
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        roots += &mut opts as *mut OpaqueTerm;
+        roots += &mut spawn_opts.tag as *mut OpaqueTerm;
+        if let Some(monitor_opts) = spawn_opts.monitor.as_mut() {
+            roots += &mut monitor_opts.tag as *mut OpaqueTerm;
+        }
+        match target {
+            SpawnTarget::Fun(ref mut fun) => {
+                roots += fun as *mut OpaqueTerm;
+            }
+            SpawnTarget::Apply(_, _, ref mut args) => {
+                roots += args as *mut OpaqueTerm;
+            }
+        }
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let node = node
+        .map(|node| node.as_atom())
+        .filter(|node| *node != distribution::current_node().name());
+    match node {
+        None => spawn_request_local(process, target, spawn_opts),
+        Some(node) => spawn_request_remote(process, node, target, opts, spawn_opts),
+    }
+}
+
+fn spawn_request_local(
+    process: &mut ProcessLock,
+    target: SpawnTarget,
+    spawn_opts: SpawnOpts,
+) -> ErlangResult {
+    let (mfa, argv) = match target {
+        SpawnTarget::Fun(fun) => {
+            let Term::Closure(closure) = fun.into() else {
+                unreachable!()
+            };
+            (closure.mfa(), smallvec::smallvec![fun])
+        }
+        SpawnTarget::Apply(module, function, args) => {
+            let argv = match list_to_args(args, MAX_ARGS) {
+                Ok(argv) => argv,
+                Err(err) => {
+                    process.exception_info.flags = ExceptionFlags::ERROR;
+                    process.exception_info.reason = match err {
+                        ArgumentListError::Improper => atoms::Badarg.into(),
+                        ArgumentListError::TooManyArguments => atoms::SystemLimit.into(),
+                    };
+                    process.exception_info.value = args;
+                    process.exception_info.trace = None;
+                    return ErlangResult::Err;
+                }
+            };
+            let mfa = ModuleFunctionArity {
+                module: module.as_atom(),
+                function: function.as_atom(),
+                arity: argv.len() as u8,
+            };
+            (mfa, argv)
+        }
+    };
+
+    let (_spawned, spawn_ref) =
+        current_scheduler().spawn(process, mfa, argv.as_slice(), spawn_opts);
+    ErlangResult::Ok(spawn_ref.unwrap().into())
+}
+
+fn spawn_request_remote(
+    process: &mut ProcessLock,
+    node: Atom,
+    target: SpawnTarget,
+    opts: OpaqueTerm,
+    spawn_opts: SpawnOpts,
+) -> ErlangResult {
+    let reference_id = current_scheduler().next_reference_id();
+    let alias = spawn_opts.monitor.and_then(|mo| mo.alias);
+    let reference = match alias {
+        None => Reference::new(reference_id),
+        Some(_) => Reference::new_pid(reference_id, process.pid()),
+    };
+
+    // Funs are spawned remotely as `erlang:apply(Fun, [])`
+    let (mfa, args) = match target {
+        SpawnTarget::Fun(fun) => {
+            let fun: Term = fun.into();
+            let mut layout = LayoutBuilder::new();
+            layout += fun.layout();
+            layout.build_list(2);
+            let fragment_ptr = layout.into_fragment().unwrap();
+            let fragment = unsafe { fragment_ptr.as_ref() };
+            let fun = unsafe { fun.unsafe_clone_to_heap(fragment) };
+            let mut builder = ListBuilder::new(fragment);
+            builder.push(Term::Nil).unwrap();
+            builder.push(fun).unwrap();
+            let args = builder.finish().unwrap();
+            let mfa = ModuleFunctionArity {
+                module: atoms::Erlang,
+                function: atoms::Apply,
+                arity: 2,
+            };
+            let args = TermFragment {
+                term: args.into(),
+                fragment: Some(fragment_ptr),
+            };
+            (mfa, args)
+        }
+        SpawnTarget::Apply(module, function, args) => {
+            let arity = match list_to_args(args, MAX_ARGS) {
+                Ok(argv) => argv.len(),
+                Err(_) => badarg!(process, args),
+            };
+            let mfa = ModuleFunctionArity {
+                module: module.as_atom(),
+                function: function.as_atom(),
+                arity: arity as u8,
+            };
+            let args = TermFragment::copy_from(&args.into(), CopyMode::Flat).unwrap();
+            (mfa, args)
+        }
+    };
+
+    // The pending request is tracked by a monitor in the requestor, which is converted to a real
+    // monitor (if requested) when the reply arrives, or triggers an error reply if the connection
+    // is lost first. The spawned process is not known yet, so the target is a placeholder on the
+    // remote node.
+    let sent = distribution::auto_connect(node).and_then(|remote| {
+        let mut flags = MonitorFlags::SPAWN_PENDING | MonitorFlags::TAG;
+        flags |= spawn_opts.reply.monitor_flags();
+        if spawn_opts.link {
+            flags |= MonitorFlags::SPAWN_LINK;
+        }
+        if let Some(monitor_opts) = spawn_opts.monitor.as_ref() {
+            flags |= MonitorFlags::SPAWN_MONITOR | monitor_opts.flags;
+        }
+        let dist = remote.connection().map(Arc::downgrade).unwrap_or_default();
+        let monitor = MonitorEntry::new(Monitor::ToExternalProcess {
+            origin: process.id(),
+            target: Pid::new_external(remote.clone(), 0, 0).unwrap(),
+            info: RemoteMonitorInfo {
+                reference: reference_id,
+                name_or_tag: TermFragment::new(spawn_opts.tag.into()).unwrap(),
+                dist,
+            },
+        });
+        monitor.set_flags(flags);
+        process.monitored.insert(monitor);
+
+        let request = SpawnRequest {
+            reference: reference.clone(),
+            from: process.pid(),
+            group_leader: process
+                .group_leader()
+                .cloned()
+                .unwrap_or_else(|| process.pid()),
+            mfa,
+            args,
+            opts: TermFragment::new(opts.into()).unwrap(),
+        };
+        distribution::spawn_request(&remote, request).map_err(|err| {
+            process.monitored.find_mut(&reference_id).remove();
+            err
+        })
+    });
+
+    if sent.is_err() && spawn_opts.reply.reply_on_error() {
+        let message = make_spawn_reply(spawn_opts.tag, reference.clone(), Err(atoms::Noconnection));
+        process.send_fragment(WeakAddress::System, message).ok();
+    }
+
+    ErlangResult::Ok(Gc::new_in(reference, process).unwrap().into())
+}
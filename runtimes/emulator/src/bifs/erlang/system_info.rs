@@ -0,0 +1,244 @@
+use firefly_alloc::allocators::carriers::{self, HugePages};
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::scheduler;
+use firefly_rt::services::registry;
+use firefly_rt::term::atom::{atom_limit, with_atom_table_readonly};
+use firefly_rt::term::{atoms, Atom, LayoutBuilder, ListBuilder, OpaqueTerm, Term, Tuple};
+
+use smallvec::SmallVec;
+
+use crate::badarg;
+
+#[export_name = "erlang:system_info/1"]
+pub extern "C-unwind" fn system_info1(process: &mut ProcessLock, item: OpaqueTerm) -> ErlangResult {
+    let Term::Atom(item_atom) = item.into() else { badarg!(process, item); };
+    let limits = firefly_system::cgroup::limits();
+    match item_atom.as_str() {
+        "logical_processors" | "logical_processors_online" => {
+            ErlangResult::Ok(Term::try_from(num_cpus()).unwrap().into())
+        }
+        "logical_processors_available" => {
+            ErlangResult::Ok(Term::try_from(crate::available_cpus()).unwrap().into())
+        }
+        "atom_count" => {
+            let count = with_atom_table_readonly(|atoms| atoms.len());
+            ErlangResult::Ok(Term::try_from(count).unwrap().into())
+        }
+        "atom_limit" => ErlangResult::Ok(Term::try_from(atom_limit()).unwrap().into()),
+        "process_count" => {
+            ErlangResult::Ok(Term::try_from(registry::process_count()).unwrap().into())
+        }
+        "process_limit" => {
+            ErlangResult::Ok(Term::try_from(registry::process_limit()).unwrap().into())
+        }
+        "schedulers" | "schedulers_online" => {
+            ErlangResult::Ok(Term::try_from(crate::num_schedulers()).unwrap().into())
+        }
+        "dirty_cpu_schedulers" | "dirty_cpu_schedulers_online" => {
+            ErlangResult::Ok(Term::try_from(scheduler::dirty_cpu() as usize).unwrap().into())
+        }
+        "dirty_io_schedulers" => {
+            ErlangResult::Ok(Term::try_from(scheduler::dirty_io() as usize).unwrap().into())
+        }
+        // The CPU bandwidth available to the node, as a number of processors, if limited
+        "cpu_quota" => match limits.cpu_quota {
+            Some(quota) => ErlangResult::Ok(Term::from(quota).into()),
+            None => ErlangResult::Ok(atoms::Unknown.into()),
+        },
+        // The amount of memory available to the node in bytes, if limited
+        "memory_limit" => {
+            let limit = limits
+                .memory_limit
+                .and_then(|l| Term::try_from(l as i64).ok());
+            match limit {
+                Some(limit) => ErlangResult::Ok(limit.into()),
+                None => ErlangResult::Ok(atoms::Unknown.into()),
+            }
+        }
+        // The current use of large carriers, and whether they are backed by huge pages
+        "huge_pages" => huge_pages_info(process),
+        // The NUMA nodes of the system as `[{Node, Cpus, Schedulers}]`, where `Schedulers` are
+        // the ids of the schedulers bound to that node, if any
+        "numa_topology" => numa_topology(process),
+        _ => badarg!(process, item),
+    }
+}
+
+fn huge_pages_info(process: &mut ProcessLock) -> ErlangResult {
+    let stats = carriers::stats();
+    let mode = match stats.huge_pages {
+        HugePages::Off => atoms::Off,
+        HugePages::Transparent => atoms::Transparent,
+        HugePages::Explicit => atoms::Explicit,
+    };
+    let bytes = |n: usize| -> OpaqueTerm { Term::try_from(n).unwrap().into() };
+    let items = [
+        (atoms::HugePages, mode.into()),
+        (atoms::Carriers, bytes(stats.carriers)),
+        (atoms::Huge, bytes(stats.huge)),
+        (atoms::TransparentHuge, bytes(stats.transparent_huge)),
+        (atoms::Normal, bytes(stats.normal)),
+        (atoms::SuperCarrierSize, bytes(stats.super_carrier_size)),
+        (atoms::SuperCarrierUsed, bytes(stats.super_carrier_used)),
+    ];
+    build_proplist(process, &items)
+}
+
+fn numa_topology(process: &mut ProcessLock) -> ErlangResult {
+    let nodes = firefly_system::numa::topology();
+    let schedulers = nodes
+        .iter()
+        .map(|node| {
+            crate::sys::numa::groups()
+                .iter()
+                .find(|group| group.node.map(|n| n.id) == Some(node.id))
+                .map(|group| group.schedulers.iter().map(|i| i + 1).collect::<Vec<_>>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut layout = LayoutBuilder::new();
+    for (node, schedulers) in nodes.iter().zip(schedulers.iter()) {
+        layout.build_tuple(3);
+        layout.build_list(node.cpus.len());
+        layout.build_list(schedulers.len());
+    }
+    layout.build_list(nodes.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let mut tuples = Vec::with_capacity(nodes.len());
+    for (node, schedulers) in nodes.iter().zip(schedulers.iter()) {
+        let cpus = int_list(process, &node.cpus);
+        let schedulers = int_list(process, schedulers);
+        let id = Term::try_from(node.id).unwrap().into();
+        tuples.push(Tuple::from_slice(&[id, cpus, schedulers], process).unwrap());
+    }
+    let mut builder = ListBuilder::new(process);
+    for tuple in tuples.iter().rev() {
+        builder.push(Term::Tuple(*tuple)).unwrap();
+    }
+    ErlangResult::Ok(
+        builder
+            .finish()
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL),
+    )
+}
+
+/// Builds a list of the small integers in `items`, space for which must have been reserved
+fn int_list(process: &mut ProcessLock, items: &[usize]) -> OpaqueTerm {
+    let mut builder = ListBuilder::new(process);
+    for item in items.iter().rev() {
+        builder.push(Term::Int(*item as i64)).unwrap();
+    }
+    builder
+        .finish()
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL)
+}
+
+/// Memory is accounted for using the statistics of the malloc implementation backing the runtime,
+/// which cannot distinguish between types of memory, so only the `total` is known, all of which is
+/// reported as `system` memory.
+#[export_name = "erlang:memory/0"]
+pub extern "C-unwind" fn memory0(process: &mut ProcessLock) -> ErlangResult {
+    let Some(stats) = firefly_system::alloc::stats() else { return memory_notsup(process, OpaqueTerm::NIL); };
+    let allocated = Term::try_from(stats.allocated).unwrap().into();
+    build_proplist(
+        process,
+        &[(atoms::Total, allocated), (atoms::System, allocated)],
+    )
+}
+
+#[export_name = "erlang:memory/1"]
+pub extern "C-unwind" fn memory1(process: &mut ProcessLock, ty: OpaqueTerm) -> ErlangResult {
+    let mut types = SmallVec::<[Atom; 2]>::new();
+    match ty.into() {
+        Term::Atom(a) => types.push(a),
+        Term::Cons(cons) => {
+            for element in cons.iter() {
+                let Ok(Term::Atom(a)) = element else { badarg!(process, ty); };
+                types.push(a);
+            }
+        }
+        _ => badarg!(process, ty),
+    }
+    for a in types.iter() {
+        match a.as_str() {
+            "total" | "system" => continue,
+            // These are valid memory types, but we can't measure them
+            "processes" | "processes_used" | "atom" | "atom_used" | "binary" | "code" | "ets" => {
+                return memory_notsup(process, ty);
+            }
+            _ => badarg!(process, ty),
+        }
+    }
+    let Some(stats) = firefly_system::alloc::stats() else { return memory_notsup(process, ty); };
+
+    if ty.is_atom() {
+        return ErlangResult::Ok(Term::try_from(stats.allocated).unwrap().into());
+    }
+
+    let allocated = Term::try_from(stats.allocated).unwrap().into();
+    let items = types
+        .iter()
+        .map(|ty| (*ty, allocated))
+        .collect::<SmallVec<[_; 2]>>();
+    build_proplist(process, &items)
+}
+
+/// Builds a list of `{Key, Value}` tuples from `items`, in order
+fn build_proplist(process: &mut ProcessLock, items: &[(Atom, OpaqueTerm)]) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    for _ in items.iter() {
+        layout.build_tuple(2);
+    }
+    layout.build_list(items.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let tuples = items
+        .iter()
+        .map(|(key, value)| Tuple::from_slice(&[(*key).into(), *value], process).unwrap())
+        .collect::<SmallVec<[_; 8]>>();
+    // Lists are constructed back to front
+    let mut builder = ListBuilder::new(process);
+    for item in tuples.iter().rev() {
+        builder.push(Term::Tuple(*item)).unwrap();
+    }
+    ErlangResult::Ok(
+        builder
+            .finish()
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL),
+    )
+}
+
+fn memory_notsup(process: &mut ProcessLock, ty: OpaqueTerm) -> ErlangResult {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.reason = atoms::Notsup.into();
+    process.exception_info.value = atoms::Notsup.into();
+    process.exception_info.args = Some(ty);
+    process.exception_info.trace = None;
+    ErlangResult::Err
+}
+
+#[cfg(unix)]
+fn num_cpus() -> usize {
+    firefly_system::arch::num_cpus()
+}
+
+#[cfg(not(unix))]
+fn num_cpus() -> usize {
+    1
+}
@@ -0,0 +1,38 @@
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::{BigInt, OpaqueTerm};
+use firefly_system::time::{clock, MonotonicTime, UNIX_EPOCH};
+
+/// Returns the current monotonic time in native time units, i.e. nanoseconds
+///
+/// Like all time sources in the runtime, this follows the virtual clock, see `clock`.
+#[export_name = "erlang:monotonic_time/0"]
+pub extern "C-unwind" fn monotonic_time0(process: &mut ProcessLock) -> ErlangResult {
+    let nanos = MonotonicTime::now().elapsed().as_nanos();
+    native_time(process, nanos as i64)
+}
+
+/// Returns the current system time in native time units, i.e. nanoseconds since the Unix epoch
+#[export_name = "erlang:system_time/0"]
+pub extern "C-unwind" fn system_time0(process: &mut ProcessLock) -> ErlangResult {
+    let nanos = match clock::system_now().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i64,
+        Err(err) => -(err.duration().as_nanos() as i64),
+    };
+    native_time(process, nanos)
+}
+
+/// Native time values are generally too large to be immediates, so they may need to be boxed
+fn native_time(process: &mut ProcessLock, nanos: i64) -> ErlangResult {
+    if let Ok(term) = OpaqueTerm::try_from(nanos) {
+        return ErlangResult::Ok(term);
+    }
+    match Gc::new_in(BigInt::from(nanos), process) {
+        Ok(boxed) => ErlangResult::Ok(boxed.into()),
+        Err(_) => {
+            assert!(garbage_collect(process, Default::default()).is_ok());
+            ErlangResult::Ok(Gc::new_in(BigInt::from(nanos), process).unwrap().into())
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! The timer BIFs, i.e. `send_after`, `start_timer`, `read_timer` and `cancel_timer`
+//!
+//! Each timer is started in the timer wheel of the scheduler the calling process is running on,
+//! and fires via the normal send path. As the process which started a timer, or any other, may be
+//! running on a different scheduler by the time it reads or cancels the timer, timers are read and
+//! cancelled via `firefly_rt::services::timers::{time_left, cancel}`, which work from any thread.
+use std::mem;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc, RootSet};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::scheduler::Scheduler;
+use firefly_rt::services::registry::{Registrant, WeakAddress};
+use firefly_rt::services::timers::{self, Timer, TimerError, TimerEvent};
+use firefly_rt::term::*;
+use firefly_system::time::{Duration, MonotonicTime, Timeout};
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+
+/// Sends `msg` to `dest` after `time` milliseconds, returning a reference to the timer
+#[export_name = "erlang:send_after/3"]
+pub extern "C-unwind" fn send_after3(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    msg: OpaqueTerm,
+) -> ErlangResult {
+    send_after4(process, time, dest, msg, OpaqueTerm::NIL)
+}
+
+/// Like `send_after/3`, but if `{abs, true}` is given in `options`, `time` is the absolute time
+/// in milliseconds of the Erlang monotonic clock at which the message is sent
+#[export_name = "erlang:send_after/4"]
+pub extern "C-unwind" fn send_after4(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    msg: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    start(process, time, dest, msg, options, false)
+}
+
+/// Like `send_after/3`, but the message sent is `{timeout, TimerRef, Msg}`
+#[export_name = "erlang:start_timer/3"]
+pub extern "C-unwind" fn start_timer3(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    msg: OpaqueTerm,
+) -> ErlangResult {
+    start_timer4(process, time, dest, msg, OpaqueTerm::NIL)
+}
+
+/// Like `send_after/4`, but the message sent is `{timeout, TimerRef, Msg}`
+#[export_name = "erlang:start_timer/4"]
+pub extern "C-unwind" fn start_timer4(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    msg: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    start(process, time, dest, msg, options, true)
+}
+
+/// Returns the milliseconds left until the timer `timer_ref` fires, or `false` if it has already
+/// fired, been cancelled, or never existed
+#[export_name = "erlang:read_timer/1"]
+pub extern "C-unwind" fn read_timer1(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+) -> ErlangResult {
+    read_timer2(process, timer_ref, OpaqueTerm::NIL)
+}
+
+/// Like `read_timer/1`, but if `{async, true}` is given in `options`, returns `ok`, and sends the
+/// result as the message `{read_timer, TimerRef, Result}` instead
+#[export_name = "erlang:read_timer/2"]
+pub extern "C-unwind" fn read_timer2(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(reference) = timer_ref.into() else { badarg!(process, timer_ref); };
+    let Ok(opts) = TimerOpts::parse(options, &[atoms::Async]) else { badarg!(process, options); };
+
+    let result = time_left_to_term(timers::time_left(reference.id()));
+    if opts.is_async {
+        reply(process, atoms::ReadTimer, &reference, result);
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+    ErlangResult::Ok(result)
+}
+
+/// Cancels the timer `timer_ref`, returning the milliseconds that were left until it would have
+/// fired, or `false` if it has already fired, been cancelled, or never existed
+///
+/// Once this returns, the message of the timer is guaranteed not to be delivered, but if it had
+/// already fired, the message may still be in the message queue of its receiver.
+#[export_name = "erlang:cancel_timer/1"]
+pub extern "C-unwind" fn cancel_timer1(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+) -> ErlangResult {
+    cancel_timer2(process, timer_ref, OpaqueTerm::NIL)
+}
+
+/// Like `cancel_timer/1`, but accepts the options `{async, Bool}` and `{info, Bool}`
+///
+/// With `{async, true}`, `ok` is returned, and the result is sent as the message
+/// `{cancel_timer, TimerRef, Result}` instead. With `{info, false}`, no result is produced at all,
+/// i.e. `ok` is returned, and no message is sent.
+#[export_name = "erlang:cancel_timer/2"]
+pub extern "C-unwind" fn cancel_timer2(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(reference) = timer_ref.into() else { badarg!(process, timer_ref); };
+    let Ok(opts) = TimerOpts::parse(options, &[atoms::Async, atoms::Info]) else {
+        badarg!(process, options);
+    };
+
+    let id = reference.id();
+    let result = time_left_to_term(timers::cancel(id));
+    // If the timer lives on this scheduler, free its entry in the wheel now rather than when it
+    // would have expired
+    current_scheduler().cancel_timer(id).ok();
+
+    if !opts.info {
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+    if opts.is_async {
+        reply(process, atoms::CancelTimer, &reference, result);
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+    ErlangResult::Ok(result)
+}
+
+/// The options accepted by the timer BIFs, each given as `{Name, Bool}` in a proper list
+struct TimerOpts {
+    abs: bool,
+    is_async: bool,
+    info: bool,
+}
+impl TimerOpts {
+    /// Parses `options`, failing if it isn't a proper list of options among `allowed`
+    fn parse(options: OpaqueTerm, allowed: &[Atom]) -> Result<Self, ()> {
+        let mut opts = Self {
+            abs: false,
+            is_async: false,
+            info: true,
+        };
+        match options.into() {
+            Term::Nil => Ok(opts),
+            Term::Cons(list) => {
+                for item in list.iter_raw() {
+                    let Ok(item) = item else { return Err(()); };
+                    let Term::Tuple(tuple) = item.into() else { return Err(()); };
+                    if tuple.len() != 2 {
+                        return Err(());
+                    }
+                    let (name, value) = match (tuple[0].into(), tuple[1].into()) {
+                        (Term::Atom(name), Term::Bool(value)) => (name, value),
+                        _ => return Err(()),
+                    };
+                    if !allowed.contains(&name) {
+                        return Err(());
+                    }
+                    if name == atoms::Abs {
+                        opts.abs = value;
+                    } else if name == atoms::Async {
+                        opts.is_async = value;
+                    } else {
+                        opts.info = value;
+                    }
+                }
+                Ok(opts)
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+fn start(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    msg: OpaqueTerm,
+    options: OpaqueTerm,
+    wrap: bool,
+) -> ErlangResult {
+    let Ok(opts) = TimerOpts::parse(options, &[atoms::Abs]) else { badarg!(process, options); };
+    let Term::Int(time_ms) = time.into() else { badarg!(process, time); };
+    let timeout = if opts.abs {
+        let now_ms = MonotonicTime::now().elapsed().as_millis() as i64;
+        Timeout::from_millis(time_ms.saturating_sub(now_ms).max(0) as u64)
+    } else if time_ms >= 0 {
+        Timeout::from_millis(time_ms as u64)
+    } else {
+        badarg!(process, time);
+    };
+    // Timers may only be sent to local processes, or to names, which are resolved when they fire
+    let recipient = match dest.into() {
+        Term::Pid(pid) if pid.is_local() => WeakAddress::Process((*pid).clone()),
+        Term::Atom(name) => WeakAddress::Name(name),
+        _ => badarg!(process, dest),
+    };
+
+    let timer_ref = current_scheduler().next_reference_id();
+    let message = if wrap {
+        timers::timeout_triple(timer_ref, msg.into()).unwrap()
+    } else {
+        TermFragment::new(msg.into()).unwrap()
+    };
+
+    match current_scheduler().send_after(timer_ref, process, message, recipient, timeout) {
+        Ok(_) => (),
+        // A timer which has already expired fires immediately
+        Err(TimerError::Expired(Timer::Once {
+            event:
+                TimerEvent::Message {
+                    sender,
+                    recipient,
+                    message,
+                },
+            ..
+        })) => {
+            if let Some(Registrant::Process(to)) = recipient.try_resolve() {
+                to.send_fragment(sender, message).ok();
+            }
+        }
+        Err(err) => panic!("unexpected error starting timer: {:?}", err),
+    }
+
+    if process.heap_available() < mem::size_of::<Reference>() {
+        process.gc_needed = mem::size_of::<Reference>();
+        assert!(garbage_collect(process, RootSet::default()).is_ok());
+    }
+    ErlangResult::Ok(Gc::new_in(Reference::new(timer_ref), process).unwrap().into())
+}
+
+/// Converts the time left on a timer to milliseconds, rounding up, or `false` if there is no timer
+fn time_left_to_term(time_left: Option<Duration>) -> OpaqueTerm {
+    match time_left {
+        None => false.into(),
+        Some(left) => {
+            let millis = (left.as_nanos() + 999_999) / 1_000_000;
+            Term::Int(millis as i64).into()
+        }
+    }
+}
+
+/// Sends the result of an asynchronous timer operation as `{Tag, TimerRef, Result}`
+fn reply(process: &mut ProcessLock, tag: Atom, reference: &Reference, result: OpaqueTerm) {
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference().build_tuple(3);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+    let reference = Gc::new_in(reference.clone(), fragment).unwrap();
+    let message = Tuple::from_slice(&[tag.into(), reference.into(), result], fragment).unwrap();
+    let message = TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    };
+    process.send_fragment(WeakAddress::System, message).ok();
+}
@@ -0,0 +1,270 @@
+//! Process tracing, i.e. `erlang:trace/3`, and call count/time tracing, i.e.
+//! `erlang:trace_pattern/3`
+//!
+//! Only local processes may be traced or act as tracers. The trace settings live on the traced
+//! processes themselves, see `firefly_rt::process::trace`, so changing them never waits on the
+//! processes involved. Call tracing is set on the dispatch entries of functions instead, see
+//! `crate::emulator::call_trace`.
+use firefly_bytecode::ModuleFunctionArity;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::trace::{self, TraceFlags};
+use firefly_rt::process::{ProcessId, ProcessLock};
+use firefly_rt::services::registry;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::call_trace::{self, CallTraceAction, MfaPattern};
+use crate::emulator::current_scheduler;
+
+/// Enables (if `how` is true) or disables the trace flags in `flag_list` for the processes given
+/// by `pid_spec`, returning the number of processes affected
+///
+/// `pid_spec` is either a local pid, or one of `all`/`processes`, `existing`/`existing_processes`
+/// or `new`/`new_processes`. `flag_list` may contain `{tracer, Pid}`, which otherwise defaults to
+/// the calling process.
+#[export_name = "erlang:trace/3"]
+pub extern "C-unwind" fn trace3(
+    process: &mut ProcessLock,
+    pid_spec: OpaqueTerm,
+    how: OpaqueTerm,
+    flag_list: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Bool(enable) = how.into() else { badarg!(process, how); };
+    let Some((flags, tracer)) = parse_flags(flag_list) else { badarg!(process, flag_list); };
+    let tracer = match tracer {
+        None => process.id(),
+        Some(tracer) if registry::get_by_process_id(tracer).is_some() => tracer,
+        Some(_) => badarg!(process, flag_list),
+    };
+
+    let apply = |state: &trace::TraceState| {
+        if enable {
+            state.set(flags, tracer);
+        } else {
+            state.clear(flags);
+        }
+    };
+
+    let (existing, new) = match pid_spec.into() {
+        Term::Pid(pid) if pid.is_local() => {
+            let Some(target) = registry::get_by_pid(&pid) else { badarg!(process, pid_spec); };
+            apply(target.trace());
+            return ErlangResult::Ok(Term::Int(1).into());
+        }
+        Term::Atom(a) if a == atoms::All || a == atoms::Processes => (true, true),
+        Term::Atom(a) if a == atoms::Existing || a == atoms::ExistingProcesses => (true, false),
+        Term::Atom(a) if a == atoms::New || a == atoms::NewProcesses => (false, true),
+        _ => badarg!(process, pid_spec),
+    };
+
+    if new {
+        if enable {
+            trace::set_new_processes(flags, tracer);
+        } else {
+            trace::clear_new_processes(flags);
+        }
+    }
+
+    let mut count = 0;
+    if existing {
+        for target in registry::processes() {
+            apply(target.trace());
+            count += 1;
+        }
+    }
+    ErlangResult::Ok(Term::Int(count).into())
+}
+
+/// Parses the flag list of `erlang:trace/3`, returning the flags and the tracer, if given
+fn parse_flags(flag_list: OpaqueTerm) -> Option<(TraceFlags, Option<ProcessId>)> {
+    let mut flags = TraceFlags::empty();
+    let mut tracer = None;
+    match flag_list.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for result in cons.iter() {
+                match result.ok()? {
+                    Term::Atom(a) => {
+                        flags |= TraceFlags::from_atom(a)?;
+                    }
+                    Term::Tuple(tuple) if tuple.len() == 2 && tuple[0] == atoms::Tracer => {
+                        match tuple[1].into() {
+                            Term::Pid(pid) if pid.is_local() => {
+                                tracer = Some(pid.id());
+                            }
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some((flags, tracer))
+}
+
+/// Enables, disables, pauses or restarts call count/time tracing of the functions matching
+/// `mfa`, returning the number of functions matched
+///
+/// Only the `call_count` and `call_time` flags are supported, so `match_spec` must be one of
+/// `true`, `false`, `pause` or `restart`. The `local` and `global` flags are accepted, but have no
+/// effect, as all calls are traced.
+#[export_name = "erlang:trace_pattern/3"]
+pub extern "C-unwind" fn trace_pattern3(
+    process: &mut ProcessLock,
+    mfa: OpaqueTerm,
+    match_spec: OpaqueTerm,
+    flag_list: OpaqueTerm,
+) -> ErlangResult {
+    let Some(pattern) = parse_mfa_pattern(mfa) else { badarg!(process, mfa); };
+    let action = match match_spec.into() {
+        Term::Bool(true) => CallTraceAction::Start,
+        Term::Bool(false) => CallTraceAction::Stop,
+        Term::Atom(a) if a == atoms::Pause => CallTraceAction::Pause,
+        Term::Atom(a) if a == atoms::Restart => CallTraceAction::Restart,
+        _ => badarg!(process, match_spec),
+    };
+    let Some(flags) = parse_call_trace_flags(flag_list) else { badarg!(process, flag_list); };
+
+    let matched = current_scheduler().set_call_trace_pattern(&pattern, flags, action);
+    ErlangResult::Ok(Term::Int(matched as i64).into())
+}
+
+/// Returns the call count/time trace information of the function `{Module, Function, Arity}`
+///
+/// `item` is either `call_count`, which returns `{call_count, Count}`, or `call_time`, which
+/// returns `{call_time, [{Pid, Count, Secs, MicroSecs}]}`. In both cases the value is `false` if
+/// the function is not traced with that flag.
+#[export_name = "erlang:trace_info/2"]
+pub extern "C-unwind" fn trace_info2(
+    process: &mut ProcessLock,
+    target: OpaqueTerm,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    let Some(mfa) = parse_mfa(target) else { badarg!(process, target); };
+    let flag = match item.into() {
+        Term::Atom(a) if a == atoms::CallCount => call_trace::CALL_COUNT,
+        Term::Atom(a) if a == atoms::CallTime => call_trace::CALL_TIME,
+        _ => badarg!(process, item),
+    };
+
+    let info = current_scheduler()
+        .call_trace_info(&mfa)
+        .filter(|(flags, _)| flags & flag != 0);
+    let Some((_, stats)) = info else {
+        let result = Tuple::from_slice(&[item, false.into()], process).unwrap();
+        return ErlangResult::Ok(result.into());
+    };
+
+    if flag == call_trace::CALL_COUNT {
+        let count = stats.iter().map(|(_, s)| s.count).sum::<u64>();
+        let result = Tuple::from_slice(&[item, Term::Int(count as i64).into()], process).unwrap();
+        return ErlangResult::Ok(result.into());
+    }
+
+    let mut layout = LayoutBuilder::new();
+    for _ in stats.iter() {
+        layout.build_pid().build_tuple(4);
+    }
+    layout.build_list(stats.len()).build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let mut entries = Vec::with_capacity(stats.len());
+    for (id, stats) in stats.iter() {
+        let pid = Gc::new_in(Pid::new_local(*id), process).unwrap();
+        let micros = stats.time / 1_000;
+        let entry = Tuple::from_slice(
+            &[
+                pid.into(),
+                Term::Int(stats.count as i64).into(),
+                Term::Int((micros / 1_000_000) as i64).into(),
+                Term::Int((micros % 1_000_000) as i64).into(),
+            ],
+            process,
+        )
+        .unwrap();
+        entries.push(entry);
+    }
+    let mut builder = ListBuilder::new(process);
+    for entry in entries.iter().rev() {
+        builder.push(Term::Tuple(*entry)).unwrap();
+    }
+    let list = builder
+        .finish()
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL);
+    let result = Tuple::from_slice(&[atoms::CallTime.into(), list], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
+
+/// Parses `{Module, Function, Arity}`, in which trailing elements may be the wildcard `'_'`
+fn parse_mfa_pattern(mfa: OpaqueTerm) -> Option<MfaPattern> {
+    let Term::Tuple(tuple) = mfa.into() else { return None; };
+    if tuple.len() != 3 {
+        return None;
+    }
+    let wildcard = |term: OpaqueTerm| term == atoms::Underscore;
+    let module = match tuple[0].into() {
+        _ if wildcard(tuple[0]) => None,
+        Term::Atom(module) => Some(module),
+        _ => return None,
+    };
+    let function = match tuple[1].into() {
+        _ if wildcard(tuple[1]) => None,
+        Term::Atom(_) if module.is_none() => return None,
+        Term::Atom(function) => Some(function),
+        _ => return None,
+    };
+    let arity = match tuple[2].into() {
+        _ if wildcard(tuple[2]) => None,
+        Term::Int(_) if function.is_none() => return None,
+        Term::Int(arity) => Some(u8::try_from(arity).ok()?),
+        _ => return None,
+    };
+    Some(MfaPattern {
+        module,
+        function,
+        arity,
+    })
+}
+
+/// Parses `{Module, Function, Arity}` without wildcards
+fn parse_mfa(mfa: OpaqueTerm) -> Option<ModuleFunctionArity<Atom>> {
+    match parse_mfa_pattern(mfa)? {
+        MfaPattern {
+            module: Some(module),
+            function: Some(function),
+            arity: Some(arity),
+        } => Some(ModuleFunctionArity {
+            module,
+            function,
+            arity,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the flag list of `erlang:trace_pattern/3`, which must enable call count or time tracing
+fn parse_call_trace_flags(flag_list: OpaqueTerm) -> Option<u8> {
+    let Term::Cons(cons) = flag_list.into() else { return None; };
+    let mut flags = 0;
+    for result in cons.iter() {
+        match result.ok()? {
+            Term::Atom(a) if a == atoms::CallCount => flags |= call_trace::CALL_COUNT,
+            Term::Atom(a) if a == atoms::CallTime => flags |= call_trace::CALL_TIME,
+            Term::Atom(a) if a == atoms::Local || a == atoms::Global => (),
+            _ => return None,
+        }
+    }
+    if flags == 0 {
+        None
+    } else {
+        Some(flags)
+    }
+}
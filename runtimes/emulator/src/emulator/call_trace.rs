@@ -0,0 +1,273 @@
+//! Call count and call time tracing, i.e. `erlang:trace_pattern/3` with `call_count`/`call_time`
+//!
+//! The counters are attached to the dispatch entry of each traced bytecode function, i.e. its
+//! [`FunId`], and are kept per process. A call is counted when the `FuncInfo` instruction of a
+//! traced function executes, so both calls and tail calls are counted.
+//!
+//! Execution time is attributed to the time-traced function a process is currently executing,
+//! which is switched on every call and return, as well as paused while the process is scheduled
+//! out. Like ERTS, this means the time of a function excludes the time spent in the bytecode
+//! functions it calls. Natively-implemented functions are never traced, their time is attributed
+//! to the caller.
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use firefly_bytecode::{FunId, ModuleFunctionArity};
+use firefly_rt::process::{ProcessId, ProcessLock};
+use firefly_rt::term::Atom;
+use firefly_system::sync::Mutex;
+use firefly_system::time::MonotonicTime;
+
+use super::Emulator;
+
+type HashMap<K, V> =
+    std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
+
+/// Count the calls made to a function, i.e. `call_count`
+pub const CALL_COUNT: u8 = 1;
+/// Accumulate the execution time of a function, i.e. `call_time`
+pub const CALL_TIME: u8 = 1 << 1;
+
+/// The call trace entries, indexed by [`FunId`]
+///
+/// This is allocated the first time a pattern is set, as the table is sized to the loaded code.
+static TABLE: OnceLock<Box<[CallTraceEntry]>> = OnceLock::new();
+
+/// The number of entries which are currently counting calls or time
+///
+/// When zero, the only cost of this feature on the dispatch path is a load of this counter.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns true if any function is currently having its calls counted or timed
+#[inline(always)]
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed) != 0
+}
+
+/// What to do with the counters of the functions matched by `erlang:trace_pattern/3`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CallTraceAction {
+    /// `true`, start tracing with the counters reset
+    Start,
+    /// `false`, stop tracing and discard the counters
+    Stop,
+    /// `pause`, stop tracing but keep the counters
+    Pause,
+    /// `restart`, reset the counters and resume tracing if paused
+    Restart,
+}
+
+/// A pattern of `{Module, Function, Arity}` in which each element may be the wildcard `'_'`
+#[derive(Debug, Copy, Clone)]
+pub struct MfaPattern {
+    pub module: Option<Atom>,
+    pub function: Option<Atom>,
+    pub arity: Option<u8>,
+}
+impl MfaPattern {
+    fn matches(&self, mfa: &ModuleFunctionArity<Atom>) -> bool {
+        self.module.map(|m| m == mfa.module).unwrap_or(true)
+            && self.function.map(|f| f == mfa.function).unwrap_or(true)
+            && self.arity.map(|a| a == mfa.arity).unwrap_or(true)
+    }
+}
+
+/// The counters of a single process for a single function
+///
+/// Calls are counted while either `call_count` or `call_time` is enabled, the time is only
+/// accumulated while `call_time` is enabled.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CallStats {
+    pub count: u64,
+    /// The accumulated execution time, in nanoseconds
+    pub time: u64,
+}
+
+/// The call trace state of a single function
+struct CallTraceEntry {
+    /// The flags set for this function, whether or not tracing is paused
+    flags: AtomicU8,
+    /// The flags currently in effect, i.e. `flags` unless tracing is paused
+    active: AtomicU8,
+    /// The counters of each process which has called this function
+    ///
+    /// This lock also serializes changes to the flags of this entry.
+    stats: Mutex<HashMap<ProcessId, CallStats>>,
+}
+impl CallTraceEntry {
+    fn new() -> Self {
+        Self {
+            flags: AtomicU8::new(0),
+            active: AtomicU8::new(0),
+            stats: Mutex::new(HashMap::default()),
+        }
+    }
+
+    #[inline]
+    fn active(&self) -> u8 {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, id: ProcessId, count: u64, time: u64) {
+        let mut stats = self.stats.lock();
+        let stats = stats.entry(id).or_default();
+        stats.count += count;
+        stats.time += time;
+    }
+
+    /// Applies `action` to `flags` on this entry
+    fn update(&self, flags: u8, action: CallTraceAction) {
+        let mut stats = self.stats.lock();
+        let prev_flags = self.flags.load(Ordering::Relaxed);
+        let prev_active = self.active();
+        let (new_flags, new_active) = match action {
+            CallTraceAction::Start => {
+                stats.clear();
+                (prev_flags | flags, prev_active | flags)
+            }
+            CallTraceAction::Stop => {
+                stats.clear();
+                (prev_flags & !flags, prev_active & !flags)
+            }
+            CallTraceAction::Pause => (prev_flags, prev_active & !flags),
+            CallTraceAction::Restart => {
+                if prev_flags & flags != 0 {
+                    stats.clear();
+                }
+                (prev_flags, prev_active | (prev_flags & flags))
+            }
+        };
+        self.flags.store(new_flags, Ordering::Relaxed);
+        self.active.store(new_active, Ordering::Relaxed);
+        match (prev_active != 0, new_active != 0) {
+            (false, true) => {
+                ACTIVE.fetch_add(1, Ordering::Relaxed);
+            }
+            (true, false) => {
+                ACTIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[inline]
+fn entry(id: FunId) -> Option<&'static CallTraceEntry> {
+    TABLE.get().and_then(|table| table.get(id as usize))
+}
+
+#[inline]
+fn now() -> u64 {
+    MonotonicTime::now().elapsed().as_nanos() as u64
+}
+
+impl Emulator {
+    /// Applies `action` to the call trace `flags` of every bytecode function matching `pattern`,
+    /// returning the number of functions matched
+    pub(crate) fn set_call_trace_pattern(
+        &self,
+        pattern: &MfaPattern,
+        flags: u8,
+        action: CallTraceAction,
+    ) -> usize {
+        let table = TABLE.get_or_init(|| {
+            (0..self.code.functions.len())
+                .map(|_| CallTraceEntry::new())
+                .collect()
+        });
+        let mut matched = 0;
+        for function in self.code.functions.iter() {
+            if function.offset().is_none() {
+                continue;
+            }
+            let Some(mfa) = function.mfa() else { continue; };
+            if pattern.matches(mfa) {
+                table[function.id() as usize].update(flags, action);
+                matched += 1;
+            }
+        }
+        matched
+    }
+
+    /// Returns the call trace flags set on the bytecode function `mfa`, and a snapshot of its
+    /// counters, or `None` if the function is not traced
+    pub(crate) fn call_trace_info(
+        &self,
+        mfa: &ModuleFunctionArity<Atom>,
+    ) -> Option<(u8, Vec<(ProcessId, CallStats)>)> {
+        let function = self.code.function_by_mfa(mfa)?;
+        let entry = entry(function.id())?;
+        let stats = entry.stats.lock();
+        let flags = entry.flags.load(Ordering::Relaxed);
+        if flags == 0 {
+            return None;
+        }
+        Some((flags, stats.iter().map(|(id, s)| (*id, *s)).collect()))
+    }
+
+    /// Called on entry to the bytecode function `id` while call tracing is active
+    pub(super) fn call_trace_enter(&self, process: &mut ProcessLock, id: FunId) {
+        let Some(entry) = entry(id) else { return; };
+        let active = entry.active();
+        if active != 0 {
+            entry.record(process.id(), 1, 0);
+        }
+        let timed = if active & CALL_TIME != 0 {
+            Some(id)
+        } else {
+            None
+        };
+        switch_call_time(process, timed);
+    }
+
+    /// Called when a process returns to the bytecode function `id` while call tracing is active
+    pub(super) fn call_trace_return(&self, process: &mut ProcessLock, id: FunId) {
+        let timed = entry(id)
+            .filter(|entry| entry.active() & CALL_TIME != 0)
+            .map(|_| id);
+        switch_call_time(process, timed);
+    }
+
+    /// Resumes accumulating the execution time of a process as it is scheduled in
+    pub(super) fn call_time_resume(&self, process: &mut ProcessLock) {
+        if let Some((_, since)) = process.call_time.as_mut() {
+            *since = now();
+        }
+    }
+
+    /// Attributes the execution time of a process as it is scheduled out
+    pub(super) fn call_time_pause(&self, process: &mut ProcessLock) {
+        if let Some((id, since)) = process.call_time {
+            if is_active() {
+                let now = now();
+                record_time(process.id(), id, now.saturating_sub(since));
+                process.call_time = Some((id, now));
+            } else {
+                process.call_time = None;
+            }
+        }
+    }
+}
+
+/// Attributes the time elapsed in the function currently being timed, and starts timing `next`
+fn switch_call_time(process: &mut ProcessLock, next: Option<FunId>) {
+    match (process.call_time, next) {
+        (None, None) => (),
+        (Some((prev, _)), Some(next)) if prev == next => (),
+        (prev, next) => {
+            let now = now();
+            if let Some((prev, since)) = prev {
+                record_time(process.id(), prev, now.saturating_sub(since));
+            }
+            process.call_time = next.map(|id| (id, now));
+        }
+    }
+}
+
+fn record_time(process: ProcessId, id: FunId, time: u64) {
+    if let Some(entry) = entry(id) {
+        if entry.active() & CALL_TIME != 0 {
+            entry.record(process, 0, time);
+        }
+    }
+}
@@ -1,4 +1,7 @@
+pub(crate) mod call_trace;
+pub(crate) mod profile;
 mod scheduler;
+mod trace;
 
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::ptr;
@@ -13,11 +16,11 @@ use firefly_rt::function::ModuleFunctionArity;
 use firefly_rt::process::Process;
 use firefly_rt::scheduler::SchedulerId;
 use firefly_rt::services::{registry, timers};
-use firefly_rt::term::{atom, Atom, Cons, LayoutBuilder, ReferenceId, Term};
+use firefly_rt::term::{atom, Atom, Cons, LayoutBuilder, Pid, ReferenceId, Term};
 
 use tokio::runtime::Handle;
 
-use crate::queue::{LocalProcessQueue, RunQueue};
+use crate::queue::{LocalProcessQueue, Peers, RunQueue};
 
 pub(crate) use self::scheduler::Action;
 
@@ -55,7 +58,9 @@ pub struct Emulator {
     ///
     /// This queue is safe to access from multiple threads, and is designed to support
     /// work stealing to/from other schedulers. It holds a reference to the global task
-    /// queue in which newly spawned processes are placed.
+    /// queue in which newly spawned processes are placed, which is shared by all schedulers on
+    /// the same NUMA node, as well as those of other nodes, and the local queues of all other
+    /// schedulers, which are stolen from when idle.
     runq: RunQueue<LocalProcessQueue>,
     injector: Arc<Injector<Arc<Process>>>,
     /// A handle to the async runtime
@@ -86,6 +91,10 @@ pub struct Emulator {
     /// received, it will look up the scheduler id in the timer reference and relay the
     /// cancellation to the scheduler on which the timer was registered.
     timers: RefCell<timers::PerSchedulerTimerService>,
+    /// The source of timestamps for trace messages generated on this scheduler
+    trace_clock: trace::TraceClock,
+    /// The state of the sampling profiler on this scheduler
+    profile: profile::ProfileState,
 }
 unsafe impl Send for Emulator {}
 unsafe impl Sync for Emulator {}
@@ -95,9 +104,11 @@ impl Emulator {
         id: SchedulerId,
         code: Arc<ByteCode<Atom, atom::GlobalAtomTable>>,
         injector: Arc<Injector<Arc<Process>>>,
+        remote_injectors: Vec<Arc<Injector<Arc<Process>>>>,
+        peers: Vec<Arc<Peers<Arc<Process>>>>,
         handle: Handle,
     ) -> Arc<Self> {
-        let runq = RunQueue::new(injector.clone());
+        let runq = RunQueue::with_remote(injector.clone(), remote_injectors, peers);
         Arc::new(Self {
             id,
             code,
@@ -109,6 +120,8 @@ impl Emulator {
             thread_id: std::thread::current().id(),
             reductions: AtomicU64::new(0),
             timers: RefCell::new(timers::PerSchedulerTimerService::new()),
+            trace_clock: trace::TraceClock::new(),
+            profile: profile::ProfileState::new(),
         })
     }
 
@@ -159,15 +172,20 @@ impl Emulator {
             .map(Term::Cons)
             .unwrap_or(Term::Nil);
 
+        // The io server is the group leader of init, and so of every process which inherits its
+        // group leader from init, so that io requests sent to the group leader are answered
+        let group_leader = self.spawn_io_server();
+
         // Initialize fresh process state
         let mut init_p = Process::new(
             self.id,
             None,
-            None,
+            group_leader,
             init,
             &[initial_args.into()],
             self.injector.clone(),
             Default::default(),
+            Default::default(),
         );
         {
             let proc = Arc::get_mut(&mut init_p).unwrap();
@@ -182,4 +200,50 @@ impl Emulator {
 
         Ok(())
     }
+
+    /// Spawns the io server which writes output to standard output, or to the stdio file when
+    /// standard io is redirected, see `sys::stdio_file`
+    ///
+    /// Returns `None` if the io server is not part of the loaded bytecode.
+    unsafe fn spawn_io_server(&self) -> Option<Pid> {
+        use crate::queue::TaskQueue;
+
+        let server = "erts_internal:file_io_server/0"
+            .parse::<ModuleFunctionArity>()
+            .unwrap();
+        let server_mfa = server.into();
+        let offset = self
+            .code
+            .function_by_mfa(&server_mfa)
+            .and_then(|f| f.offset());
+        let Some(offset) = offset else {
+            if crate::sys::stdio_file::is_enabled() {
+                eprintln!(
+                    "{} is not available, standard io is not redirected",
+                    &server
+                );
+            }
+            return None;
+        };
+
+        let mut server_p = Process::new(
+            self.id,
+            None,
+            None,
+            server,
+            &[],
+            self.injector.clone(),
+            Default::default(),
+            Default::default(),
+        );
+        {
+            let proc = Arc::get_mut(&mut server_p).unwrap();
+            proc.set_instruction_pointer(offset);
+        }
+        let pid = server_p.pid();
+        registry::register_process(server_p.clone());
+        self.runq.push(server_p);
+
+        Some(pid)
+    }
 }
@@ -0,0 +1,179 @@
+//! A sampling CPU profiler, exposed to Erlang as the `firefly_profile` module
+//!
+//! While the profiler is running, a sampler thread advances a global tick once per interval. Each
+//! scheduler notices the tick on the next function entry of the process it is executing, and
+//! records the stack of that process. Samples are thus taken by the thread which owns the stack,
+//! so no scheduler is ever interrupted or stalled, and the cost to a scheduler while the profiler
+//! is stopped is a load of a flag per call, like call tracing.
+//!
+//! Samples are aggregated per distinct stack of [`FunId`]s, which are only resolved to MFAs when
+//! the profile is dumped as folded stacks, i.e. the input format of `flamegraph.pl`, as produced
+//! by eflame. Time spent in natively-implemented functions is attributed to their caller, and idle
+//! schedulers take no samples.
+use std::cell::Cell;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use firefly_bytecode::FunId;
+use firefly_rt::process::ProcessLock;
+
+use smallvec::SmallVec;
+
+use super::scheduler::TRAP_IP;
+use super::Emulator;
+
+type HashMap<K, V> =
+    std::collections::HashMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
+
+/// A sampled stack, outermost function first
+type Stack = SmallVec<[FunId; 16]>;
+
+/// The sampling interval used when none is given
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// True while the profiler is running
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Advanced by the sampler thread once per interval
+///
+/// This is never reset, so a scheduler which last sampled during a previous run of the profiler
+/// does not mistake the first tick of the next run for one it has already seen.
+static TICK: AtomicU64 = AtomicU64::new(1);
+
+/// The sampler thread of the running profiler
+static SAMPLER: Mutex<Option<Sampler>> = Mutex::new(None);
+
+/// The number of samples taken of each stack
+static SAMPLES: Mutex<Option<HashMap<Stack, u64>>> = Mutex::new(None);
+
+/// Returns true if the profiler is running
+#[inline(always)]
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Errors which occur when starting or stopping the profiler
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+    AlreadyStarted,
+    NotStarted,
+}
+
+struct Sampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The state of the profiler kept by each scheduler
+pub(super) struct ProfileState {
+    /// The last tick at which this scheduler took a sample
+    last_tick: Cell<u64>,
+}
+impl ProfileState {
+    pub(super) const fn new() -> Self {
+        Self {
+            last_tick: Cell::new(0),
+        }
+    }
+}
+
+/// Starts the profiler, discarding the samples of any previous run
+pub fn start(interval: Duration) -> Result<(), ProfileError> {
+    let mut sampler = SAMPLER.lock().unwrap();
+    if sampler.is_some() {
+        return Err(ProfileError::AlreadyStarted);
+    }
+    *SAMPLES.lock().unwrap() = Some(HashMap::default());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let stop = stop.clone();
+        thread::Builder::new()
+            .name("firefly_profile".to_string())
+            .spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(interval);
+                    TICK.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .unwrap()
+    };
+    *sampler = Some(Sampler { stop, handle });
+    ACTIVE.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stops the profiler, keeping the samples taken so far until the next start
+pub fn stop() -> Result<(), ProfileError> {
+    let Some(sampler) = SAMPLER.lock().unwrap().take() else {
+        return Err(ProfileError::NotStarted);
+    };
+    ACTIVE.store(false, Ordering::Release);
+    sampler.stop.store(true, Ordering::Relaxed);
+    sampler.handle.join().ok();
+    Ok(())
+}
+
+impl Emulator {
+    /// Called on entry to the bytecode function `id` while the profiler is running
+    ///
+    /// Takes a sample of the stack of `process` if the sampler has ticked since the last sample
+    /// taken by this scheduler.
+    pub(super) fn profile_sample(&self, process: &ProcessLock, id: FunId) {
+        let tick = TICK.load(Ordering::Relaxed);
+        if tick == self.profile.last_tick.get() {
+            return;
+        }
+        self.profile.last_tick.set(tick);
+
+        let mut stack = process
+            .stack
+            .trace(None)
+            .map(|frame| frame.ret)
+            .filter(|ip| *ip > TRAP_IP)
+            .map(|ip| self.code.function_by_ip(ip).id())
+            .collect::<Stack>();
+        stack.reverse();
+        stack.push(id);
+
+        let mut samples = SAMPLES.lock().unwrap();
+        if let Some(samples) = samples.as_mut() {
+            *samples.entry(stack).or_insert(0) += 1;
+        }
+    }
+
+    /// Returns the samples taken as folded stacks, one per line, of the form
+    /// `module:function/arity;...;module:function/arity count`, sorted by stack
+    pub(crate) fn profile_folded_stacks(&self) -> String {
+        let samples = SAMPLES.lock().unwrap();
+        let mut lines = samples
+            .iter()
+            .flat_map(|samples| samples.iter())
+            .map(|(stack, count)| {
+                let mut line = String::new();
+                for (i, id) in stack.iter().enumerate() {
+                    if i > 0 {
+                        line.push(';');
+                    }
+                    match self.code.function_by_id(*id).mfa() {
+                        Some(mfa) => write!(&mut line, "{}", mfa).unwrap(),
+                        None => line.push('?'),
+                    }
+                }
+                write!(&mut line, " {}", count).unwrap();
+                line
+            })
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+
+        let mut folded = String::new();
+        for line in lines.iter() {
+            folded.push_str(line);
+            folded.push('\n');
+        }
+        folded
+    }
+}
@@ -15,26 +15,36 @@ use firefly_number::{Int, Number};
 use firefly_rt::backtrace::{Trace, TraceFrame};
 use firefly_rt::cmp::ExactEq;
 use firefly_rt::error::{ErrorCode, ExceptionFlags, ExceptionInfo};
-use firefly_rt::function::{self, DynamicCallee, ErlangResult, ModuleFunctionArity};
+use firefly_rt::function::{
+    self, list_to_args, ArgumentListError, DynamicCallee, ErlangResult, ModuleFunctionArity,
+    MAX_ARGS,
+};
 use firefly_rt::gc::{self, Gc};
-use firefly_rt::process::link::{Link, LinkEntry, LinkTreeEntry};
-use firefly_rt::process::monitor::{Monitor, MonitorEntry, MonitorFlags, MonitorTreeEntry};
+use firefly_rt::intrinsics;
+use firefly_rt::process::link::{make_exit_message, Link, LinkEntry, LinkTreeEntry};
+use firefly_rt::process::monitor::{
+    make_down_message, Monitor, MonitorEntry, MonitorFlags, MonitorTreeEntry, RemoteMonitorInfo,
+};
 use firefly_rt::process::signals::{
     self, Message, Signal, SignalEntry, SignalQueueFlags, SignalQueueLock,
 };
+use firefly_rt::process::trace::{make_mfa_fragment, TimestampKind, TraceFlags, TraceTimestamp};
 use firefly_rt::process::{
-    ContinueExitPhase, Process, ProcessFlags, ProcessLock, ProcessTimer, SpawnOpts, StatusFlags,
-    ARG0_REG, CP_REG, RETURN_REG,
+    make_spawn_reply, ContinueExitPhase, Process, ProcessFlags, ProcessLock, ProcessTimer,
+    SpawnInfo, SpawnOpts, StatusFlags, ARG0_REG, CP_REG, RETURN_REG,
 };
 use firefly_rt::scheduler::{Scheduler, SchedulerId};
-use firefly_rt::services::error_logger;
 use firefly_rt::services::registry::{self, Registrant, WeakAddress};
+use firefly_rt::services::safepoint;
 use firefly_rt::services::timers::{Timer, TimerError, TimerService};
+use firefly_rt::services::{distribution, error_logger};
 use firefly_rt::term::{
     atoms, BigInt, BinaryData, BitSlice, Closure, ClosureFlags, Cons, Map, MapError, MatchContext,
     OpaqueTerm, Pid, Reference, Term, Tuple, Value,
 };
-use firefly_rt::term::{LayoutBuilder, TermFragment, TermType};
+use firefly_rt::term::{
+    BinaryBuilder, BinaryPushError, CopyMode, LayoutBuilder, TermFragment, TermType,
+};
 use firefly_system::time::{Duration, Timeout};
 
 use intrusive_collections::UnsafeRef;
@@ -99,12 +109,14 @@ impl Scheduler for Emulator {
         let spawn_async = opts.spawn_async;
         let monitor = opts.monitor;
         let link = opts.link;
+        let reply = opts.reply;
+        let reply_tag = opts.tag;
         let spawn_ref_id = self.next_reference_id();
         let spawn_ref;
         if opts.monitor.map(|mo| mo.alias.is_some()).unwrap_or(false) {
             spawn_ref =
                 Some(Gc::new_in(Reference::new_pid(spawn_ref_id, parent.pid()), parent).unwrap());
-        } else if opts.monitor.is_some() {
+        } else if spawn_async || opts.monitor.is_some() {
             spawn_ref = Some(Gc::new_in(Reference::new(spawn_ref_id), parent).unwrap());
         } else {
             spawn_ref = None;
@@ -119,6 +131,17 @@ impl Scheduler for Emulator {
             trace!(target: "scheduler", "spawning process with mfa {} and args [{}], link={}, monitor={}", &mfa, argv_formatted, monitor.is_some(), link);
         }
 
+        // Record the function the parent is currently executing as the spawn site, if known
+        let spawned_from = if parent.ip > NORMAL_EXIT_IP {
+            self.code
+                .function_by_ip(parent.ip)
+                .mfa()
+                .map(|mfa| (*mfa).into())
+        } else {
+            None
+        };
+        let spawn_info = SpawnInfo::new(parent.as_ref(), spawned_from, &opts);
+
         let proc = Process::new(
             self.id(),
             Some(parent.pid()),
@@ -132,6 +155,7 @@ impl Scheduler for Emulator {
             args,
             self.injector.clone(),
             opts,
+            spawn_info,
         );
 
         {
@@ -158,7 +182,29 @@ impl Scheduler for Emulator {
                 parent.monitored.insert(monitor.clone());
                 spawned.monitored_by.push_back(monitor);
             }
-            assert!(!spawn_async, "asynchronous spawns are not implemented yet");
+
+            // Local spawn requests complete immediately, so we can reply right away. This must
+            // happen before the spawned process is scheduled, so that the reply is guaranteed to
+            // precede any messages sent to the parent by the spawned process.
+            if spawn_async && reply.reply_on_success() {
+                let spawn_ref = spawn_ref.as_deref().cloned().unwrap();
+                let message = make_spawn_reply(reply_tag, spawn_ref, Ok(spawned.pid()));
+                parent.send_fragment(spawned.addr(), message).ok();
+            }
+        }
+
+        let traced = TraceFlags::PROCS;
+        if parent.as_ref().trace().tracer_for(traced).is_some()
+            || proc.trace().tracer_for(traced).is_some()
+        {
+            let fragment = make_mfa_fragment(mfa, args);
+            let mfa: Term = fragment.term.into();
+            let mut child = proc.pid();
+            let mut parent_pid = parent.pid();
+            let child = Term::Pid(unsafe { Gc::from_raw(&mut child) });
+            let parent_pid = Term::Pid(unsafe { Gc::from_raw(&mut parent_pid) });
+            self.trace_event(parent.as_ref(), traced, atoms::Spawn, &[child, mfa.clone()]);
+            self.trace_event(&proc, traced, atoms::Spawned, &[parent_pid, mfa]);
         }
 
         registry::register_process(proc.clone());
@@ -171,6 +217,12 @@ impl Scheduler for Emulator {
     fn reschedule(&self, process: Arc<Process>) {
         self.runq.push(process);
     }
+
+    fn trace_timestamp(&self, kind: TimestampKind) -> TraceTimestamp {
+        // The clock is owned by this scheduler, so it must not be touched from other threads
+        assert_eq!(self.thread_id, std::thread::current().id());
+        self.trace_clock.now(kind)
+    }
 }
 
 const MAX_REDUCTIONS: usize = Process::MAX_REDUCTIONS;
@@ -179,14 +231,24 @@ const ERTS_SIGNAL_REDUCTIONS_COUNT_FACTOR: usize = 4;
 impl Emulator {
     /// Run the scheduler core loop indefinitely or until an error occurs
     pub(super) fn run(&self) -> Result<(), EmulatorError> {
+        // No process is locked by this scheduler between iterations, so that is where it stops
+        // when the world is paused
+        let safepoint = safepoint::register();
         loop {
+            safepoint.checkpoint();
+            #[cfg(unix)]
+            crate::sys::heart::beat();
             if !self.run_once()? {
                 // There are no processes available, sleep for a few seconds
                 // and try scheduling again. This avoids busy looping with no
                 // work.
                 if let Some(ms) = self.timers.borrow().skippable() {
                     trace!(target: "scheduler", "scheduler has no processes available to schedule, parking until next timer expires");
-                    std::thread::park_timeout(Duration::from_millis(ms as u64));
+                    #[cfg(unix)]
+                    let _idle = crate::sys::heart::idle();
+                    safepoint.idle(|| {
+                        std::thread::park_timeout(Duration::from_millis(ms as u64))
+                    });
                 }
             }
         }
@@ -409,7 +471,9 @@ impl Emulator {
                         assert!(!status.contains(StatusFlags::SUSPENDED));
 
                         // We're scheduled in, begin executing process
+                        self.trace_schedule(&process, true);
                         self.process_main(&mut process)?;
+                        self.trace_schedule(&process, false);
                         break 'schedule;
                     }
 
@@ -494,6 +558,10 @@ impl Emulator {
                         self.timers.borrow_mut().tick();
                     }
 
+                    // Submit any I/O operations started by processes during this tick as one batch
+                    #[cfg(unix)]
+                    crate::sys::io::flush();
+
                     // TODO: Handle other auxiliary work on a periodic basis, say every 2 *
                     // MAX_REDUCTIONS Things include timers (handled above),
                     // ports, async tasks, etc.
@@ -531,6 +599,9 @@ impl Emulator {
         // Resume executing user code in this process
         let mut reductions = process.reductions;
         trace!(target: "scheduler", "starting to execute process {}", process.pid());
+        if unlikely(process.call_time.is_some()) {
+            self.call_time_resume(process);
+        }
         let mut init_op;
         loop {
             // Load current opcode, and bump instruction pointer
@@ -660,6 +731,7 @@ impl Emulator {
                     // consumed during the cycle.
                     reductions += process.reductions - reductions;
                     if unlikely(process.reductions >= MAX_REDUCTIONS) {
+                        process.total_reductions += process.reductions as u64;
                         process.reductions = 0;
                         trace!(target: "process", "reduction budget exhausted, yielding..");
                         break;
@@ -687,12 +759,37 @@ impl Emulator {
             }
         }
 
+        if unlikely(process.call_time.is_some()) {
+            self.call_time_pause(process);
+        }
+
+        process.publish_reductions();
+
         self.reductions
             .fetch_add(reductions as u64, Ordering::Relaxed);
 
         Ok(())
     }
 
+    /// Starts a timer which sends `message` from `process` to `recipient` after `timeout`, see
+    /// `erlang:send_after/4`
+    ///
+    /// The timer lives in the timer wheel of this scheduler, but may be read or cancelled from
+    /// any scheduler via `firefly_rt::services::timers::{time_left, cancel}`.
+    pub(crate) fn send_after(
+        &self,
+        timer_ref: ReferenceId,
+        process: &ProcessLock,
+        message: TermFragment,
+        recipient: WeakAddress,
+        timeout: Timeout,
+    ) -> Result<(), TimerError> {
+        assert_eq!(self.thread_id, std::thread::current().id());
+        self.timers
+            .borrow_mut()
+            .send_after(timer_ref, process.strong(), message, recipient, timeout)
+    }
+
     pub(crate) fn handle_signals(
         &self,
         process: &mut ProcessLock,
@@ -755,7 +852,6 @@ impl Emulator {
                             {
                                 let monitor = cursor.get().unwrap();
                                 let message: signals::Message;
-                                assert!(reason.is_immediate());
                                 let flags = monitor.flags();
                                 if flags.contains(MonitorFlags::SPAWN_PENDING) {
                                     // Create a spawn_request() error message and replace the signal
@@ -809,101 +905,36 @@ impl Emulator {
                                     monitor.remove_flags(MonitorFlags::SPAWN_MASK);
                                 } else {
                                     // Create a DOWN message and replace the signal with it
-                                    let mut layout = LayoutBuilder::new();
-                                    layout += reason.layout();
-                                    let mut tag;
-                                    let origin;
-                                    if !flags.contains(MonitorFlags::TAG) {
-                                        // Registered name 2-tuple
-                                        layout.build_tuple(2);
-                                        origin = WeakAddress::System;
-                                        tag = Term::Atom(atoms::DOWN);
-                                    } else {
-                                        match monitor.origin().unwrap_or(WeakAddress::System) {
-                                            addr @ WeakAddress::Process(_) => {
-                                                origin = addr;
-                                                layout.build_pid();
-                                            }
-                                            addr @ WeakAddress::Port(_) => {
-                                                origin = addr;
-                                                layout.build_port();
-                                            }
-                                            addr @ WeakAddress::System => {
-                                                origin = addr;
-                                            }
-                                            _ => panic!("expected pid or port"),
-                                        }
-                                        if let Some(t) = monitor.tag() {
-                                            tag = t.into();
-                                            layout += tag.layout();
-                                        } else {
-                                            tag = Term::Atom(atoms::DOWN);
-                                        }
-                                    }
-                                    layout.build_reference();
-                                    layout.build_tuple(5);
-                                    let fragment_ptr = layout.into_fragment().unwrap();
-                                    let fragment = unsafe { fragment_ptr.as_ref() };
-                                    let reason =
-                                        unsafe { reason.unsafe_clone_to_heap(fragment).into() };
-
-                                    let from;
-                                    let ty;
-                                    if !flags.contains(MonitorFlags::TAG) {
-                                        let name = monitor.name().unwrap();
-                                        let node = monitor.node_name();
-                                        from = Term::Tuple(
-                                            Tuple::from_slice(
-                                                &[name.into(), node.into()],
-                                                fragment,
-                                            )
-                                            .unwrap(),
-                                        );
-                                    } else {
-                                        match origin {
-                                            WeakAddress::Process(ref pid) => {
-                                                from = Term::Pid(
-                                                    Gc::new_in(pid.clone(), fragment).unwrap(),
-                                                );
-                                            }
-                                            WeakAddress::Port(port) => {
-                                                from = match registry::get_by_port_id(port) {
-                                                    None => Term::Atom(atoms::System),
-                                                    Some(p) => Term::Port(p),
-                                                };
-                                            }
-                                            WeakAddress::System => {
-                                                from = Term::Atom(atoms::System);
-                                            }
-                                            _ => unreachable!(),
-                                        }
-                                    }
-                                    match &monitor.monitor {
-                                        Monitor::LocalPort { .. } => {
-                                            ty = atoms::Port;
-                                        }
-                                        Monitor::LocalProcess { .. }
-                                        | Monitor::ToExternalProcess { .. } => {
-                                            ty = atoms::Process;
-                                        }
-                                        Monitor::FromExternalProcess { .. } => {
-                                            ty = atoms::Process;
-                                        }
-                                        _ => panic!("unexpected monitor type"),
+                                    //
+                                    // The monitor is gone once triggered, though an alias created
+                                    // with it may outlive it
+                                    let tag = monitor.tag();
+                                    let ty = match &monitor.monitor {
+                                        Monitor::LocalPort { .. } => atoms::Port,
+                                        _ => atoms::Process,
+                                    };
+                                    let name =
+                                        monitor.name().map(|name| (name, monitor.node_name()));
+                                    let target = monitor.target().unwrap_or(WeakAddress::System);
+                                    let reference = match flags.alias() {
+                                        None => Reference::new(monitor_ref),
+                                        Some(_) => Reference::new_pid(monitor_ref, process.pid()),
+                                    };
+                                    let down = make_down_message(
+                                        tag,
+                                        reference,
+                                        ty,
+                                        name,
+                                        target.clone(),
+                                        reason,
+                                    );
+                                    let monitor = cursor.remove().unwrap();
+                                    if let Some(alias) = monitor.to_alias(process.pid()) {
+                                        process.monitored.insert(alias);
                                     }
-                                    tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
-                                    let mref = Gc::new_in(monitor_ref.clone(), fragment).unwrap();
-                                    let term = Tuple::from_slice(
-                                        &[tag.into(), mref.into(), ty.into(), from.into(), reason],
-                                        fragment,
-                                    )
-                                    .unwrap();
                                     message = Message {
-                                        sender: origin,
-                                        message: TermFragment {
-                                            term: term.into(),
-                                            fragment: Some(fragment_ptr),
-                                        },
+                                        sender: target,
+                                        message: down,
                                     };
                                 }
                                 count += 4;
@@ -1000,9 +1031,11 @@ impl Emulator {
                     if update {
                         process.set_group_leader(sig.group_leader);
                     }
-                    let sender = WeakAddress::Process(sig.sender);
-                    if let Some(Registrant::Process(p)) = sender.try_resolve() {
-                        self.send_group_leader_reply(p, sig.reference, update);
+                    if let Some(reference) = sig.reference {
+                        let sender = WeakAddress::Process(sig.sender);
+                        if let Some(Registrant::Process(p)) = sender.try_resolve() {
+                            self.send_group_leader_reply(p, reference, update);
+                        }
                     }
                 }
                 Signal::IsAlive(sig) => {
@@ -1013,11 +1046,14 @@ impl Emulator {
                 }
                 Signal::ProcessInfo(sig) => {
                     let is_alive = !status.contains(StatusFlags::EXITING);
-                    self.handle_process_info_signal(process, sig, is_alive);
+                    self.handle_process_info_signal(process, &mut signals, sig, is_alive);
                 }
                 Signal::Rpc(sig) => {
                     count += self.handle_rpc(process, sig);
                 }
+                Signal::SpawnReply(sig) => {
+                    count += self.handle_spawn_reply(process, &mut signals, sig);
+                }
                 Signal::Message(_) | Signal::Flush(_) => unreachable!(),
             }
         }
@@ -1116,11 +1152,13 @@ impl Emulator {
                     reason
                 };
                 if let Some(origin) = registry::get_by_process_id(*origin) {
+                    // The reason is copied for each monitor, as it remains on our heap
+                    let reason: Term = reason.into();
                     origin
                         .send_signal(SignalEntry::new(Signal::MonitorDown(
                             signals::MonitorDown {
                                 sender: Some(process.addr()),
-                                reason: TermFragment::new(reason.into()).unwrap(),
+                                reason: TermFragment::copy_from(&reason, CopyMode::Flat).unwrap(),
                                 monitor,
                             },
                         )))
@@ -1141,9 +1179,13 @@ impl Emulator {
         monitor: Arc<MonitorEntry>,
     ) {
         match &monitor.monitor {
-            Monitor::Suspend { origin, .. } | Monitor::LocalProcess { origin, .. } => {
-                if let Some(origin) = registry::get_by_process_id(*origin) {
-                    origin
+            Monitor::Suspend { target, .. } | Monitor::LocalProcess { target, .. } => {
+                // A process which monitors itself has nothing to tell
+                if *target == process.id() {
+                    return;
+                }
+                if let Some(target) = registry::get_by_process_id(*target) {
+                    target
                         .send_signal(SignalEntry::new(Signal::Demonitor(signals::Demonitor {
                             sender: process.addr(),
                             monitor,
@@ -1151,6 +1193,8 @@ impl Emulator {
                         .ok();
                 }
             }
+            // Aliases have no target, and remote monitors are not yet supported by distribution
+            Monitor::Alias { .. } | Monitor::ToExternalProcess { .. } => (),
             _ => unimplemented!(),
         }
     }
@@ -1226,8 +1270,8 @@ impl Emulator {
         let mut exit = false;
         let mut count = 1;
         let sender;
-        let reason_fragment: Option<NonNull<HeapFragment>>;
-        let mut reason: OpaqueTerm;
+        // The reason is owned here, so that its fragment is freed unless it is moved to the heap
+        let mut reason: TermFragment;
         let normal_kills;
         match signal.signal {
             Signal::ExitLink(sig) => {
@@ -1240,51 +1284,48 @@ impl Emulator {
                     ignore = true;
                 }
                 normal_kills = sig.normal_kills;
-                reason = sig.reason.term;
-                reason_fragment = sig.reason.fragment;
+                reason = sig.reason;
                 is_link_exit = true;
             }
             Signal::Exit(sig) => {
                 sender = sig.sender.unwrap();
                 normal_kills = sig.normal_kills;
-                reason = sig.reason.term;
-                reason_fragment = sig.reason.fragment;
+                reason = sig.reason;
             }
             _ => unreachable!(),
         }
 
         if !ignore {
-            if (is_link_exit || reason != atoms::Kill)
+            // The trap_exit flag is only ever changed by the process itself, so it is consistent
+            // with the order in which exit signals are received, whenever they were sent
+            if (is_link_exit || reason.term != atoms::Kill)
                 && process.flags.contains(ProcessFlags::TRAP_EXIT)
             {
-                signal.signal = Signal::Message(signals::Message {
-                    sender,
-                    message: TermFragment {
-                        term: reason.into(),
-                        fragment: reason_fragment,
-                    },
-                });
+                let message = make_exit_message(sender.clone(), reason.term.into());
+                signal.signal = Signal::Message(signals::Message { sender, message });
                 assert!(!exit);
                 unsafe {
                     signals.push_next_message(signal);
                 }
-            } else if !(reason == atoms::Normal && !normal_kills) {
+            } else if !(reason.term == atoms::Normal && !normal_kills) {
                 // terminate
                 exit = true;
-                if !is_link_exit && reason == atoms::Kill {
-                    reason = atoms::Killed.into();
+                if !is_link_exit && reason.term == atoms::Kill {
+                    reason.term = atoms::Killed.into();
                 }
             }
         }
 
         if exit {
             // set_self_exiting
-            if let Some(ptr) = reason_fragment {
+            if let Some(ptr) = reason.fragment.take() {
                 process
                     .heap_fragments
                     .push_back(unsafe { UnsafeRef::from_raw(ptr.as_ptr().cast_const()) });
             }
-            process.exception_info.value = reason.into();
+            process.exception_info.value = reason.term;
+            // The reason now belongs to the process
+            mem::forget(reason);
             process.exception_info.flags = ExceptionFlags::EXIT;
             process.exception_info.trace = None;
             process.stack.nocatch();
@@ -1303,11 +1344,32 @@ impl Emulator {
 
     fn handle_process_info_signal(
         &self,
-        _process: &mut ProcessLock,
-        _sig: signals::ProcessInfo,
-        _is_alive: bool,
+        process: &mut ProcessLock,
+        signals: &mut SignalQueueLock<'_>,
+        sig: signals::ProcessInfo,
+        is_alive: bool,
     ) {
-        todo!()
+        // Requests via distribution are not supported yet
+        let Some(sender) = sig.sender else { return; };
+        if is_alive && sig.need_msgq_len {
+            signals.flush_buffers();
+        }
+        let target = if is_alive {
+            Some((&*process, &*signals))
+        } else {
+            None
+        };
+        let reply =
+            crate::bifs::erlang::make_process_info_reply(target, sig.items.term, sig.reference);
+        sender.send_fragment(process.pid().into(), reply).ok();
+    }
+
+    /// Returns the bytecode function containing the instruction at `ip`, if it is in one
+    pub(crate) fn mfa_by_ip(&self, ip: usize) -> Option<ModuleFunctionArity> {
+        if ip <= TRAP_IP {
+            return None;
+        }
+        self.code.function_by_ip(ip).mfa().map(|mfa| (*mfa).into())
     }
 
     fn handle_rpc(&self, process: &mut ProcessLock, sig: signals::Rpc) -> usize {
@@ -1342,6 +1404,105 @@ impl Emulator {
         cost * ERTS_SIGNAL_REDUCTIONS_COUNT_FACTOR
     }
 
+    fn handle_spawn_reply(
+        &self,
+        process: &mut ProcessLock,
+        signals: &mut SignalQueueLock<'_>,
+        sig: signals::SpawnReply,
+    ) -> usize {
+        let pending = match process.monitored.entry(&sig.reference) {
+            MonitorTreeEntry::Occupied(mut cursor) => {
+                let flags = cursor.get().unwrap().flags();
+                if flags.contains(MonitorFlags::SPAWN_PENDING) {
+                    cursor.remove()
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let flags = pending
+            .as_ref()
+            .map(|monitor| monitor.flags())
+            .unwrap_or(MonitorFlags::SPAWN_ABANDONED);
+
+        let reference = match flags.alias() {
+            None => Reference::new(sig.reference),
+            Some(_) => Reference::new_pid(sig.reference, process.pid()),
+        };
+        let tag = pending
+            .as_ref()
+            .and_then(|monitor| monitor.tag())
+            .unwrap_or(atoms::SpawnReply.into());
+
+        match sig.result {
+            Err(reason) => {
+                if flags
+                    .intersects(MonitorFlags::SPAWN_ABANDONED | MonitorFlags::SPAWN_NO_REPLY_ERROR)
+                {
+                    return 2;
+                }
+                let message = make_spawn_reply(tag, reference, Err(reason));
+                unsafe {
+                    signals.push_next_message(SignalEntry::new(Signal::Message(Message {
+                        sender: WeakAddress::System,
+                        message,
+                    })));
+                }
+                4
+            }
+            Ok(spawned) if flags.contains(MonitorFlags::SPAWN_ABANDONED) => {
+                // The request was abandoned, so if the spawned process linked to us, it
+                // must be told that we are no longer interested in it
+                if sig.link {
+                    distribution::send_exit(process.pid(), spawned, atoms::Abandoned).ok();
+                }
+                2
+            }
+            Ok(spawned) => {
+                let mut count = 2;
+                if sig.link && flags.contains(MonitorFlags::SPAWN_LINK) {
+                    let link = LinkEntry::new(Link::ToExternalProcess {
+                        origin: process.id(),
+                        target: spawned.clone(),
+                    });
+                    process.links.link(link).ok();
+                    count += 2;
+                }
+                if flags.contains(MonitorFlags::SPAWN_MONITOR) {
+                    let dist = spawned
+                        .node()
+                        .and_then(|node| node.connection().map(Arc::downgrade))
+                        .unwrap_or_default();
+                    let monitor = MonitorEntry::new(Monitor::ToExternalProcess {
+                        origin: process.id(),
+                        target: spawned.clone(),
+                        info: RemoteMonitorInfo {
+                            reference: sig.reference,
+                            name_or_tag: TermFragment::new(Term::None).unwrap(),
+                            dist,
+                        },
+                    });
+                    monitor.set_flags(flags & MonitorFlags::ALIAS_MASK);
+                    process.monitored.insert(monitor);
+                    count += 2;
+                }
+                if !flags.contains(MonitorFlags::SPAWN_NO_REPLY_SUCCESS) {
+                    let sender = WeakAddress::Process(spawned.clone());
+                    let message = make_spawn_reply(tag, reference, Ok(spawned));
+                    unsafe {
+                        signals.push_next_message(SignalEntry::new(Signal::Message(Message {
+                            sender,
+                            message,
+                        })));
+                    }
+                    count += 4;
+                }
+                count
+            }
+        }
+    }
+
     fn send_group_leader_reply(&self, to: Arc<Process>, reference: Reference, success: bool) {
         let mut layout = LayoutBuilder::new();
         layout.build_reference().build_tuple(2);
@@ -1393,7 +1554,10 @@ impl Emulator {
         let mut priority = Priority::Max as usize;
         let mut gc_major = false;
         let mut gc_minor = false;
-        let mut reds = process.reductions;
+        // At least one task is executed, even if the process has used up its reductions, so that
+        // requests, e.g. to garbage collect a busy process, are always serviced eventually
+        let budget = MAX_REDUCTIONS.saturating_sub(process.reductions).max(1);
+        let mut reds = budget;
         let mut status = *statusp;
         loop {
             if status.contains(StatusFlags::EXITING) {
@@ -1417,8 +1581,9 @@ impl Emulator {
             let task_result: OpaqueTerm = match task.ty {
                 ty @ (SystemTaskType::GcMajor | SystemTaskType::GcMinor) => {
                     if process.flags.contains(ProcessFlags::DISABLE_GC) {
-                        // reds -= 1;
-                        todo!("save_gc_task");
+                        // Keep the task until garbage collection is enabled again
+                        process.system_tasks[priority].push_front(task);
+                        break;
                     }
                     let is_major = ty == SystemTaskType::GcMajor;
                     if (!gc_minor || (!gc_major && is_major))
@@ -1443,9 +1608,15 @@ impl Emulator {
             status = process.status(Ordering::Acquire);
         }
 
+        // Tasks are only enqueued while holding the process lock, so none can be missed here
+        if process.system_tasks.iter().all(|tasks| tasks.is_empty()) {
+            process.remove_status_flags(StatusFlags::SYS_TASKS, Ordering::Release);
+            status = process.status(Ordering::Acquire);
+        }
+
         *statusp = status;
 
-        process.reductions.saturating_sub(reds)
+        budget - reds
     }
 
     fn cleanup_sys_tasks(&self, process: &mut ProcessLock) {
@@ -1830,6 +2001,13 @@ impl Emulator {
 
         trace!(target: "process", "exception was uncaught, terminating process");
 
+        // As in BEAM, linked and monitoring processes are told where an error was raised, by an
+        // exit reason of `{Reason, Stacktrace}`, whereas exits keep their reason as-is
+        if flags.contains(ExceptionFlags::IS_ERROR) {
+            let trace = stacktrace_term(process).unwrap_or(OpaqueTerm::NIL);
+            value = Tuple::from_slice(&[value, trace], process).unwrap().into();
+        }
+
         self.terminate_process(process, value)
     }
 
@@ -1854,6 +2032,15 @@ impl Emulator {
             .ok();
         }
 
+        self.trace_event(
+            process.as_ref(),
+            TraceFlags::PROCS,
+            atoms::Exit,
+            &[reason.into()],
+        );
+
+        // This is the reason delivered to links and monitors as the process exits
+        process.exception_info.value = reason;
         process.remove_status_flags(StatusFlags::SUSPENDED, Ordering::Relaxed);
         process.set_status_flags(
             StatusFlags::EXITING | StatusFlags::ACTIVE,
@@ -1906,7 +2093,7 @@ trait Inst {
 const GC: ops::GarbageCollect = ops::GarbageCollect { fullsweep: false };
 const NORMAL_EXIT_IP: usize = 1;
 const CONTINUE_EXIT_IP: usize = 2;
-const TRAP_IP: usize = 4;
+pub(super) const TRAP_IP: usize = 4;
 
 impl Inst for Opcode<Atom> {
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
@@ -1959,6 +2146,7 @@ impl Inst for Opcode<Atom> {
             Self::LoadFloat(op) => op.dispatch(emulator, process),
             Self::LoadBinary(op) => op.dispatch(emulator, process),
             Self::LoadBitstring(op) => op.dispatch(emulator, process),
+            Self::LoadMap(op) => op.dispatch(emulator, process),
             Self::Not(op) => op.dispatch(emulator, process),
             Self::And(op) => op.dispatch(emulator, process),
             Self::AndAlso(op) => op.dispatch(emulator, process),
@@ -2046,11 +2234,15 @@ impl Inst for Opcode<Atom> {
 
 impl Inst for ops::Ret {
     #[inline(always)]
-    fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
+    fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         trace!(target: "process", "returning {}", process.stack.load(self.reg));
         process.stack.copy(self.reg, RETURN_REG);
         let ip = process.stack.pop_frame().unwrap_or(NORMAL_EXIT_IP);
         process.ip = ip;
+        if unlikely(call_trace::is_active()) && ip > TRAP_IP {
+            let id = emulator.code.function_by_ip(ip).id();
+            emulator.call_trace_return(process, id);
+        }
         Action::Continue
     }
 }
@@ -2234,38 +2426,16 @@ impl Inst for ops::CallApply3 {
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         // Move the argument list to the stack
         let arglist = process.stack.load(self.argv);
-        let mut arity = 0;
-        match arglist.into() {
-            Term::Nil => (),
-            Term::Cons(cons) => {
-                for (i, result) in cons.iter_raw().enumerate() {
-                    if result.is_err() {
-                        unsafe {
-                            process.stack.dealloc(arity);
-                        }
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = arglist;
-                        process.exception_info.trace = None;
-                        return emulator.handle_error(process);
-                    }
-                    arity += 1;
-                    unsafe {
-                        process.stack.alloca(1);
-                    }
-                    let reg = self.dest + 2 + (i as Register);
-                    process
-                        .stack
-                        .store(reg, unsafe { result.unwrap_unchecked() });
-                }
-            }
-            _ => {
-                process.exception_info.flags = ExceptionFlags::ERROR;
-                process.exception_info.reason = atoms::Badarg.into();
-                process.exception_info.value = arglist;
-                process.exception_info.trace = None;
-                return emulator.handle_error(process);
-            }
+        let argv = match list_to_args(arglist, MAX_ARGS) {
+            Ok(argv) => argv,
+            Err(err) => return handle_arglist_error(emulator, process, arglist, err),
+        };
+        let arity = argv.len();
+        unsafe {
+            process.stack.alloca(arity);
+        }
+        for (i, arg) in argv.iter().copied().enumerate() {
+            process.stack.store(self.dest + 2 + (i as Register), arg);
         }
 
         let module = process.stack.load(self.module);
@@ -2553,50 +2723,22 @@ impl Inst for ops::EnterApply3 {
         let module = process.stack.load(self.module);
         let function = process.stack.load(self.function);
         let arglist = process.stack.load(self.argv);
-        // Capture the size of this frame in case our callee has more arguments than
-        // the current frame has available slots
-        let mut available = process.stack.stack_pointer() - 2 - process.stack.frame_pointer();
-        let mut arity = 0;
-        match arglist.into() {
-            Term::Nil => (),
-            Term::Cons(cons) => {
-                for (i, result) in cons.iter_raw().enumerate() {
-                    if result.is_err() {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = arglist;
-                        process.exception_info.trace = None;
-                        return emulator.handle_error(process);
-                    }
-                    arity += 1;
-                    match available.checked_sub(1) {
-                        Some(new_available) => {
-                            available = new_available;
-                            process.stack.store(ARG0_REG + i as Register, unsafe {
-                                result.unwrap_unchecked()
-                            });
-                        }
-                        None => {
-                            // We've used up all the stack slots that were previously allocated,
-                            // we need to allocate more dynamically from this point on.
-                            unsafe {
-                                process.stack.alloca(1);
-                            }
-                            process.stack.store(ARG0_REG + i as Register, unsafe {
-                                result.unwrap_unchecked()
-                            });
-                        }
-                    }
-                }
-            }
-            _ => {
-                process.exception_info.flags = ExceptionFlags::ERROR;
-                process.exception_info.reason = atoms::Badarg.into();
-                process.exception_info.value = arglist;
-                process.exception_info.trace = None;
-                return emulator.handle_error(process);
+        let argv = match list_to_args(arglist, MAX_ARGS) {
+            Ok(argv) => argv,
+            Err(err) => return handle_arglist_error(emulator, process, arglist, err),
+        };
+        let arity = argv.len();
+        // If our callee has more arguments than the current frame has available slots,
+        // we need to allocate the remainder dynamically
+        let available = process.stack.stack_pointer() - 2 - process.stack.frame_pointer();
+        if arity > available {
+            unsafe {
+                process.stack.alloca(arity - available);
             }
         }
+        for (i, arg) in argv.iter().copied().enumerate() {
+            process.stack.store(ARG0_REG + i as Register, arg);
+        }
 
         match (module.into(), function.into()) {
             (Term::Atom(m), Term::Atom(f)) => {
@@ -3116,6 +3258,14 @@ impl Inst for ops::LoadBitstring {
         Action::Continue
     }
 }
+impl Inst for ops::LoadMap {
+    #[inline(always)]
+    fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
+        // The map was decoded into the literal area when the bytecode was loaded
+        process.stack.store(self.dest, crate::literals::get(self.value));
+        Action::Continue
+    }
+}
 impl Inst for ops::Cons {
     #[inline(always)]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
@@ -4158,9 +4308,19 @@ impl Inst for ops::Add {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
+        // Floats and small integers are immediates, so arithmetic on them only needs to touch the
+        // heap when an integer result is too large to be an immediate
+        let rhs = process.stack.load(self.rhs);
+        if let Some(result) = lhs.int_arith(rhs, |l, r| l + r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
+        if let Some(result) = lhs.float_arith(rhs, |l, r| l + r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
         let lterm: Term = lhs.into();
         if let Ok(l) = TryInto::<Number>::try_into(lterm) {
-            let rhs = process.stack.load(self.rhs);
             let rterm: Term = rhs.into();
             if let Ok(r) = TryInto::<Number>::try_into(rterm) {
                 match l + r {
@@ -4204,9 +4364,19 @@ impl Inst for ops::Sub {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
+        // Floats and small integers are immediates, so arithmetic on them only needs to touch the
+        // heap when an integer result is too large to be an immediate
+        let rhs = process.stack.load(self.rhs);
+        if let Some(result) = lhs.int_arith(rhs, |l, r| l - r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
+        if let Some(result) = lhs.float_arith(rhs, |l, r| l - r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
         let lterm: Term = lhs.into();
         if let Ok(l) = TryInto::<Number>::try_into(lterm) {
-            let rhs = process.stack.load(self.rhs);
             let rterm: Term = rhs.into();
             if let Ok(r) = TryInto::<Number>::try_into(rterm) {
                 match l - r {
@@ -4250,9 +4420,19 @@ impl Inst for ops::Mul {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
+        // Floats and small integers are immediates, so arithmetic on them only needs to touch the
+        // heap when an integer result is too large to be an immediate
+        let rhs = process.stack.load(self.rhs);
+        if let Some(result) = lhs.int_arith(rhs, |l, r| l * r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
+        if let Some(result) = lhs.float_arith(rhs, |l, r| l * r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
         let lterm: Term = lhs.into();
         if let Ok(l) = TryInto::<Number>::try_into(lterm) {
-            let rhs = process.stack.load(self.rhs);
             let rterm: Term = rhs.into();
             if let Ok(r) = TryInto::<Number>::try_into(rterm) {
                 match l * r {
@@ -4296,9 +4476,14 @@ impl Inst for ops::Divide {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
+        // Floats are immediates, so float arithmetic never needs to touch the heap
+        let rhs = process.stack.load(self.rhs);
+        if let Some(result) = lhs.float_arith(rhs, |l, r| l / r) {
+            process.stack.store(self.dest, result);
+            return Action::Continue;
+        }
         let lterm: Term = lhs.into();
         if let Ok(l) = TryInto::<Number>::try_into(lterm) {
-            let rhs = process.stack.load(self.rhs);
             let rterm: Term = rhs.into();
             if let Ok(r) = TryInto::<Number>::try_into(rterm) {
                 match l / r {
@@ -4358,9 +4543,9 @@ impl Inst for ops::IsEq {
             let rhs = process.stack.load(self.rhs);
             process.stack.store(self.dest, lhs.exact_eq(&rhs).into());
         } else {
-            let lhs: Term = process.stack.load(self.lhs).into();
-            let rhs: Term = process.stack.load(self.rhs).into();
-            process.stack.store(self.dest, (lhs == rhs).into());
+            let lhs = process.stack.load(self.lhs);
+            let rhs = process.stack.load(self.rhs);
+            process.stack.store(self.dest, intrinsics::eq(lhs, rhs).into());
         }
         Action::Continue
     }
@@ -4373,9 +4558,9 @@ impl Inst for ops::IsNeq {
             let rhs = process.stack.load(self.rhs);
             process.stack.store(self.dest, lhs.exact_ne(&rhs).into());
         } else {
-            let lhs: Term = process.stack.load(self.lhs).into();
-            let rhs: Term = process.stack.load(self.rhs).into();
-            process.stack.store(self.dest, (lhs != rhs).into());
+            let lhs = process.stack.load(self.lhs);
+            let rhs = process.stack.load(self.rhs);
+            process.stack.store(self.dest, intrinsics::ne(lhs, rhs).into());
         }
         Action::Continue
     }
@@ -4385,7 +4570,7 @@ impl Inst for ops::IsGt {
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
         let rhs = process.stack.load(self.rhs);
-        process.stack.store(self.dest, lhs.cmp(&rhs).is_gt().into());
+        process.stack.store(self.dest, intrinsics::gt(lhs, rhs).into());
         Action::Continue
     }
 }
@@ -4394,7 +4579,7 @@ impl Inst for ops::IsGte {
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
         let rhs = process.stack.load(self.rhs);
-        process.stack.store(self.dest, lhs.cmp(&rhs).is_ge().into());
+        process.stack.store(self.dest, intrinsics::gte(lhs, rhs).into());
         Action::Continue
     }
 }
@@ -4403,7 +4588,7 @@ impl Inst for ops::IsLt {
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
         let rhs = process.stack.load(self.rhs);
-        process.stack.store(self.dest, lhs.cmp(&rhs).is_lt().into());
+        process.stack.store(self.dest, intrinsics::lt(lhs, rhs).into());
         Action::Continue
     }
 }
@@ -4412,7 +4597,7 @@ impl Inst for ops::IsLte {
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let lhs = process.stack.load(self.lhs);
         let rhs = process.stack.load(self.rhs);
-        process.stack.store(self.dest, lhs.cmp(&rhs).is_le().into());
+        process.stack.store(self.dest, intrinsics::lte(lhs, rhs).into());
         Action::Continue
     }
 }
@@ -4459,45 +4644,49 @@ impl Inst for ops::LandingPad {
         Action::Continue
     }
 }
+/// Returns the stacktrace of the current exception as a term, i.e. a list of
+/// `{Module, Function, Arity | Args, Location}`, or `[]` if there is no current exception
+///
+/// The arguments of the top-level call are included in place of its arity when they are known.
+fn stacktrace_term(process: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    let Some(trace) = process.exception_info.trace.as_ref() else { return Ok(OpaqueTerm::NIL); };
+    let mut argv: Option<SmallVec<[OpaqueTerm; 8]>> = None;
+    match process.exception_info.args {
+        Some(term) => match term.into() {
+            Term::Nil => {
+                argv = Some(Default::default());
+            }
+            Term::Cons(cons) => {
+                let mut args = SmallVec::<[OpaqueTerm; 8]>::default();
+                for maybe_improper in cons.iter_raw() {
+                    match maybe_improper {
+                        Ok(t) => args.push(t),
+                        Err(t) => args.push(t),
+                    }
+                }
+                argv = Some(args);
+            }
+            _ => {
+                argv = Some(smallvec![term]);
+            }
+        },
+        _ => (),
+    }
+    trace.as_term(argv.as_deref()).map(|term| term.into())
+}
+
 impl Inst for ops::StackTrace {
     #[inline]
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         // We've actually encoded the trace pointer on the stack, but it will still
         // be in the current exception state, so we just go straight to the source
-        let term = match process.exception_info.trace.as_ref() {
-            None => OpaqueTerm::NIL,
-            Some(trace) => {
-                let mut argv: Option<SmallVec<[OpaqueTerm; 8]>> = None;
-                match process.exception_info.args {
-                    Some(term) => match term.into() {
-                        Term::Nil => {
-                            argv = Some(Default::default());
-                        }
-                        Term::Cons(cons) => {
-                            let mut args = SmallVec::<[OpaqueTerm; 8]>::default();
-                            for maybe_improper in cons.iter_raw() {
-                                match maybe_improper {
-                                    Ok(t) => args.push(t),
-                                    Err(t) => args.push(t),
-                                }
-                            }
-                            argv = Some(args);
-                        }
-                        _ => {
-                            argv = Some(smallvec![term]);
-                        }
-                    },
-                    _ => (),
-                }
-                if let Ok(trace_term) = trace.as_term(argv.as_deref()) {
-                    trace_term.into()
-                } else {
-                    return Action::Error(EmulatorError::SystemLimit);
-                }
+        match stacktrace_term(process) {
+            Ok(term) => {
+                process.stack.store(self.dest, term);
+                Action::Continue
             }
-        };
-        process.stack.store(self.dest, term);
-        Action::Continue
+            Err(_) => Action::Error(EmulatorError::SystemLimit),
+        }
     }
 }
 impl Inst for ops::Error1 {
@@ -4584,15 +4773,43 @@ impl Inst for ops::SendOp {
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
         let recipient_term = process.stack.load(self.recipient);
         match recipient_term.into() {
+            Term::Pid(pid) if !pid.is_local() => {
+                // Messages to remote processes, e.g. io requests to the group leader of a process
+                // spawned by a remote node, are handed off to distribution, and like local sends,
+                // are silently dropped if they cannot be delivered
+                let message: Term = process.stack.load(self.message).into();
+                emulator.trace_event(
+                    process.as_ref(),
+                    TraceFlags::SEND,
+                    atoms::Send,
+                    &[message.clone(), recipient_term.into()],
+                );
+                let message = TermFragment::copy_from(&message, CopyMode::Flat).unwrap();
+                distribution::send(process.pid(), pid.deref().clone(), message).ok();
+                Action::Continue
+            }
             Term::Pid(pid) => match registry::get_by_pid(pid.as_ref()) {
                 None => Action::Continue,
                 Some(recipient) => {
-                    recipient
-                        .send(
-                            process.pid().into(),
-                            process.stack.load(self.message).into(),
-                        )
-                        .ok();
+                    let message: Term = process.stack.load(self.message).into();
+                    emulator.trace_event(
+                        process.as_ref(),
+                        TraceFlags::SEND,
+                        atoms::Send,
+                        &[message.clone(), recipient_term.into()],
+                    );
+                    let delivered = recipient
+                        .clone()
+                        .send(process.pid().into(), message.clone())
+                        .is_ok();
+                    if delivered {
+                        emulator.trace_event(
+                            &recipient,
+                            TraceFlags::RECEIVE,
+                            atoms::Receive,
+                            &[message],
+                        );
+                    }
                     Action::Continue
                 }
             },
@@ -4622,7 +4839,9 @@ impl Inst for ops::RecvPeek {
                     assert!(available);
                     // If this message is "accepted", then the underlying fragment
                     // will be added to the process off-heap fragment list, which
-                    // will then be moved to the process heap during the next GC
+                    // will then be moved to the process heap during the next GC.
+                    //
+                    // With an on-heap message queue, the GC may have done this already
                     msg.message.term
                 }
             };
@@ -4702,6 +4921,15 @@ impl Inst for ops::RecvWait {
                 // Let the RecvTimeout instruction handle this
                 Action::Continue
             }
+            ProcessTimer::None if timeout == Timeout::IMMEDIATE => {
+                // `after 0` never waits, so there is no need to arm a timer which has already
+                // expired, the receive times out as soon as the queue has been searched
+                process.stack.store(self.dest, true.into());
+                process.signals().lock().end_receive();
+                // Skip over the RecvTimeout instruction
+                process.ip += 1;
+                Action::Continue
+            }
             ProcessTimer::None if timeout == Timeout::INFINITY => {
                 // Update the process flags to indicate that this process is no longer active and is
                 // suspended
@@ -4723,6 +4951,7 @@ impl Inst for ops::RecvWait {
                     // The timeout would expire immediately, so don't bother suspending
                     Err(TimerError::Expired(_)) => {
                         process.stack.store(self.dest, true.into());
+                        process.signals().lock().end_receive();
                         // Skip over the RecvTimeout instruction
                         process.ip += 1;
                         Action::Continue
@@ -4738,7 +4967,17 @@ impl Inst for ops::RecvTimeout {
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
         // We reach here when rescheduled after a RecvWait instruction, and we must update the
         // process based on how we were rescheduled
+        //
+        // Only the expiry of the receive timer sets the TIMEOUT flag, so if it is unset we were
+        // woken by a signal, most likely a message, and the timer (if any) is left armed while
+        // the receive goes back to searching the queue.
         let timed_out = process.flags.contains(ProcessFlags::TIMEOUT);
+        if timed_out {
+            // The receive is over, so reset the timer state, otherwise the next receive would
+            // time out immediately, and start the next receive from the front of the queue again
+            process.cancel_timer();
+            process.signals().lock().end_receive();
+        }
         process.stack.store(self.dest, timed_out.into());
         Action::Continue
     }
@@ -4755,6 +4994,7 @@ impl Inst for ops::RecvPop {
         let mut signals = process.signals().lock();
         let mut message = signals.remove_message();
         drop(signals);
+        crate::replay::record(process.id(), message.message.term.into());
         if let Some(fragment_ptr) = message.message.fragment.take() {
             unsafe {
                 process
@@ -4797,6 +5037,30 @@ impl Inst for ops::Await {
 impl Inst for ops::Trap {
     #[inline(always)]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
+        // A hibernating process (see `erlang:hibernate/3`) stays suspended on this instruction
+        // until there is a message in its queue, regardless of why it was woken up
+        if unlikely(
+            process
+                .flags
+                .intersects(ProcessFlags::HIBERNATE | ProcessFlags::HIBERNATED),
+        ) {
+            let has_messages = {
+                let mut signals = process.signals().lock();
+                signals.flush_buffers();
+                signals.messages().next().is_some()
+            };
+            if !has_messages {
+                process.flags.remove(ProcessFlags::HIBERNATE);
+                process.flags |= ProcessFlags::HIBERNATED;
+                process.ip = TRAP_IP;
+                process.set_status_flags(StatusFlags::SUSPENDED, Ordering::Release);
+                return Action::Suspend;
+            }
+            process
+                .flags
+                .remove(ProcessFlags::HIBERNATE | ProcessFlags::HIBERNATED);
+        }
+
         let op = ops::EnterStatic {
             callee: process.trap.take().unwrap(),
         };
@@ -4882,6 +5146,7 @@ impl Inst for ops::ContinueExit {
 
                     // This is the point at which the process is actually dead
                     registry::unregister_process(process.id()).unwrap();
+                    crate::replay::stop(process.id());
 
                     // All erlang resources have too be deallocated before this point,
                     // e.g. registered name, so monitoring and linked processes can be
@@ -5009,8 +5274,10 @@ impl Inst for ops::ContinueExit {
                             }
                             Signal::GroupLeader(sig) => {
                                 let sender = WeakAddress::Process(sig.sender);
-                                if let Some(Registrant::Process(p)) = sender.try_resolve() {
-                                    emulator.send_group_leader_reply(p, sig.reference, false);
+                                if let (Some(reference), Some(Registrant::Process(p))) =
+                                    (sig.reference, sender.try_resolve())
+                                {
+                                    emulator.send_group_leader_reply(p, reference, false);
                                 }
                             }
                             Signal::IsAlive(sig) => {
@@ -5020,7 +5287,7 @@ impl Inst for ops::ContinueExit {
                                 }
                             }
                             Signal::ProcessInfo(sig) => {
-                                emulator.handle_process_info_signal(process, sig, false);
+                                emulator.handle_process_info_signal(process, &mut sigq, sig, false);
                             }
                             Signal::Flush(_sig) => {
                                 assert!(sigq.flags().contains(SignalQueueFlags::FLUSHING));
@@ -5029,6 +5296,17 @@ impl Inst for ops::ContinueExit {
                             Signal::Rpc(sig) => {
                                 count += emulator.handle_rpc(process, sig);
                             }
+                            Signal::SpawnReply(sig) => {
+                                // We're exiting, so the spawned process is no longer wanted
+                                if let (Ok(spawned), true) = (sig.result, sig.link) {
+                                    distribution::send_exit(
+                                        process.pid(),
+                                        spawned,
+                                        atoms::Abandoned,
+                                    )
+                                    .ok();
+                                }
+                            }
                         }
                     }
                     process.reductions += 1 + (count / ERTS_SIGNAL_REDUCTIONS_COUNT_FACTOR);
@@ -5097,30 +5375,13 @@ impl Inst for ops::Exit2 {
         let reason = process.stack.load(self.reason);
         match pid.into() {
             Term::Pid(boxed) => {
-                let receiver_id = boxed.id();
-                let is_exiting_self = process.id() == receiver_id;
+                let is_exiting_self =
+                    crate::bifs::erlang::send_exit_signal(process, boxed.as_ref(), reason);
+                process.stack.store(self.dest, true.into());
                 if is_exiting_self {
-                    let is_self_trapping_exits = process.flags.contains(ProcessFlags::TRAP_EXIT);
-                    let is_suicide = reason == atoms::Kill || !is_self_trapping_exits;
-                    process
-                        .send_signal(SignalEntry::new(Signal::Exit(signals::Exit {
-                            sender: Some(process.addr()),
-                            reason: TermFragment::new(reason.into()).unwrap(),
-                            normal_kills: is_suicide,
-                        })))
-                        .ok();
                     // Force a yield to handle pending signals immediately
                     Action::Yield
                 } else {
-                    if let Some(receiver) = registry::get_by_pid(boxed.as_ref()) {
-                        receiver
-                            .send_signal(SignalEntry::new(Signal::Exit(signals::Exit {
-                                sender: Some(process.addr()),
-                                reason: TermFragment::new(reason.into()).unwrap(),
-                                normal_kills: false,
-                            })))
-                            .ok();
-                    }
                     Action::Continue
                 }
             }
@@ -5164,8 +5425,7 @@ impl Inst for ops::Halt {
 impl Inst for ops::BsInit {
     #[inline]
     fn dispatch(&self, _emulator: &Emulator, process: &mut ProcessLock) -> Action {
-        use firefly_binary::BitVec;
-        let buffer = Box::new(BitVec::new());
+        let buffer = Box::new(BinaryBuilder::new());
         let term = OpaqueTerm::code(Box::into_raw(buffer) as usize);
         process.stack.store(self.dest, term);
         Action::Continue
@@ -5174,12 +5434,9 @@ impl Inst for ops::BsInit {
 impl Inst for ops::BsPush {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
-        use firefly_binary::{BinaryEntrySpecifier, BitVec, Bitstring};
-        use firefly_number::{f16, ToPrimitive};
-
         let builder = process.stack.load(self.builder);
         assert!(builder.is_code());
-        let mut ptr = unsafe { NonNull::new_unchecked(builder.as_code() as *mut BitVec) };
+        let mut ptr = unsafe { NonNull::new_unchecked(builder.as_code() as *mut BinaryBuilder) };
         let buffer = unsafe { ptr.as_mut() };
         let value = process.stack.load(self.value);
 
@@ -5206,219 +5463,44 @@ impl Inst for ops::BsPush {
             },
         };
 
-        match self.spec {
-            BinaryEntrySpecifier::Integer {
-                signed,
-                unit,
-                endianness,
-            } => {
-                // Size MUST be a non-negative integer
-                let size = size.unwrap();
-                let num_bits = size * (unit as usize);
-                match value.into() {
-                    // Pushing with a size of zero has no effect
-                    Term::Int(_) | Term::BigInt(_) if num_bits == 0 => (),
-                    Term::Int(i) if signed => buffer.push_ap_number(i, num_bits, endianness),
-                    Term::Int(i) => buffer.push_ap_number(i as u64, num_bits, endianness),
-                    Term::BigInt(i) => {
-                        buffer.push_ap_bigint(i.deref(), num_bits, signed, endianness)
-                    }
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
-                }
-            }
-            BinaryEntrySpecifier::Float { unit, endianness } => {
-                // Size MUST be one of 16, 32, 64
-                let size = size.unwrap();
-                match size * unit as usize {
-                    16 | 32 | 64 => (),
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = size_term.unwrap();
-                        return emulator.handle_error(process);
-                    }
-                }
-                // Value can be any integer or float
-                match value.into() {
-                    Term::Float(f) if size == 16 => {
-                        buffer.push_number(f16::from_f64(f.inner()), endianness)
-                    }
-                    Term::Float(f) if size == 32 => {
-                        buffer.push_number(f.inner() as f32, endianness)
-                    }
-                    Term::Float(f) if size == 64 => buffer.push_number(f.inner(), endianness),
-                    Term::Int(i) if size == 16 => {
-                        buffer.push_number(f16::from_f64(i as f64), endianness)
-                    }
-                    Term::Int(i) if size == 32 => buffer.push_number(i as f32, endianness),
-                    Term::Int(i) if size == 64 => buffer.push_number(i as f64, endianness),
-                    Term::BigInt(i) => match i.to_f64() {
-                        Some(f) if size == 16 => buffer.push_number(f16::from_f64(f), endianness),
-                        Some(f) if size == 32 => buffer.push_number(f as f32, endianness),
-                        Some(f) if size == 64 => buffer.push_number(f, endianness),
-                        _ => {
-                            process.exception_info.flags = ExceptionFlags::ERROR;
-                            process.exception_info.reason = atoms::Badarg.into();
-                            process.exception_info.value = value;
-                            return emulator.handle_error(process);
-                        }
-                    },
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
-                }
-            }
-            BinaryEntrySpecifier::Binary { unit } => {
-                // Size must be a non-negative integer, or None to represent pushing all of the
-                // source value into the destination buffer Term must be a
-                // bitstring/binary
-                let term: Term = value.into();
-                match term.as_bitstring() {
-                    // Push all of a binary
-                    Some(bs) if unit == 8 && size.is_none() => {
-                        // The source value must be a binary
-                        if !bs.is_binary() {
-                            process.exception_info.flags = ExceptionFlags::ERROR;
-                            process.exception_info.reason = atoms::Badarg.into();
-                            process.exception_info.value = value;
-                            return emulator.handle_error(process);
-                        }
-                        buffer.extend(bs.bytes());
-                    }
-                    // Push `size` bytes of the given bitstring
-                    Some(bs) if unit == 8 => {
-                        // Selects precisely `size` bytes from the underlying data
-                        // The value must be at least as large as the requested size
-                        match bs.select_bytes(size.unwrap()) {
-                            Ok(selection) => {
-                                buffer.extend(selection.bytes());
-                            }
-                            _ => {
-                                process.exception_info.flags = ExceptionFlags::ERROR;
-                                process.exception_info.reason = atoms::Badarg.into();
-                                process.exception_info.value = value;
-                                return emulator.handle_error(process);
-                            }
-                        }
-                    }
-                    // Push all bits of a bitstring
-                    Some(bs) if size.is_none() => {
-                        // The source value may be either a binary or bitstring
-                        if bs.is_binary() {
-                            buffer.extend(bs.bytes());
-                        } else {
-                            buffer.extend(bs.bits());
-                        }
-                    }
-                    // Push `size * unit` bits of the given bitstring
-                    Some(bs) => {
-                        let size = size.unwrap();
-                        let bitsize = size * (unit as usize);
-                        // Selects precisely `bitsize` bits from the underlying data
-                        // The value must be at least as large as the requested size
-                        match bs.select_bits(bitsize) {
-                            Ok(selection) => {
-                                buffer.extend(selection.bits());
-                            }
-                            _ => {
-                                process.exception_info.flags = ExceptionFlags::ERROR;
-                                process.exception_info.reason = atoms::Badarg.into();
-                                process.exception_info.value = value;
-                                return emulator.handle_error(process);
-                            }
-                        }
-                    }
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
-                }
-            }
-            BinaryEntrySpecifier::Utf8 => {
-                let c: char = match value.try_into() {
-                    Ok(c) => c,
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
-                };
-                buffer.push_utf8(c);
-            }
-            BinaryEntrySpecifier::Utf16 { endianness } => {
-                let c: char = match value.try_into() {
-                    Ok(c) => c,
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
-                };
-                buffer.push_utf16(c, endianness);
+        match buffer.push(self.spec, value, size) {
+            Ok(_) => {
+                process.stack.store(self.dest, builder);
+                Action::Continue
             }
-            BinaryEntrySpecifier::Utf32 { endianness } => {
-                let c: char = match value.try_into() {
-                    Ok(c) => c,
-                    _ => {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = value;
-                        return emulator.handle_error(process);
-                    }
+            Err(err) => {
+                process.exception_info.flags = ExceptionFlags::ERROR;
+                process.exception_info.reason = atoms::Badarg.into();
+                process.exception_info.value = match err {
+                    BinaryPushError::InvalidSize => size_term.unwrap_or(value),
+                    BinaryPushError::BadValue => value,
                 };
-                buffer.push_utf32(c, endianness);
+                emulator.handle_error(process)
             }
         }
-
-        process.stack.store(self.dest, builder);
-        Action::Continue
     }
 }
 impl Inst for ops::BsFinish {
     #[inline]
     fn dispatch(&self, emulator: &Emulator, process: &mut ProcessLock) -> Action {
-        use firefly_binary::{BitVec, Bitstring};
-
         let builder = process.stack.load(self.builder);
         assert!(builder.is_code());
-        let ptr = unsafe { NonNull::new_unchecked(builder.as_code() as *mut BitVec) };
+        let ptr = unsafe { NonNull::new_unchecked(builder.as_code() as *mut BinaryBuilder) };
         let buffer = unsafe { ptr.as_ref() };
-        match buffer.byte_size() {
-            n if n <= 64 => {
-                let bytes = unsafe { buffer.as_bytes_unchecked() };
-                if let Ok(bin) = BinaryData::from_small_bytes(bytes, process) {
-                    // Release the buffer
-                    drop(unsafe { Box::from_raw(ptr.as_ptr()) });
-                    process.stack.store(self.dest, bin.into());
-                    Action::Continue
-                } else {
-                    let mut builder = LayoutBuilder::new();
-                    builder.build_heap_binary(n);
-                    process.gc_needed = builder.finish().size();
-                    process.ip -= 1;
-                    GC.dispatch(emulator, process)
-                }
-            }
-            _ => {
-                let bytes = unsafe { buffer.as_bytes_unchecked() };
-                let bin = BinaryData::from_bytes(bytes);
+        match buffer.finish(process) {
+            Ok(bin) => {
                 // Release the buffer
                 drop(unsafe { Box::from_raw(ptr.as_ptr()) });
                 process.stack.store(self.dest, bin.into());
                 Action::Continue
             }
+            Err(_) => {
+                let mut builder = LayoutBuilder::new();
+                builder.build_heap_binary(buffer.byte_size());
+                process.gc_needed = builder.finish().size();
+                process.ip -= 1;
+                GC.dispatch(emulator, process)
+            }
         }
     }
 }
@@ -6019,6 +6101,12 @@ impl Inst for ops::FuncInfo {
         }
         // Write NONE to all of the slots not occupied by arguments
         process.stack.zero(ARG0_REG + self.arity as Register);
+        if unlikely(call_trace::is_active()) {
+            emulator.call_trace_enter(process, self.id);
+        }
+        if unlikely(profile::is_active()) {
+            emulator.profile_sample(process, self.id);
+        }
         if log_enabled!(target: "process", log::Level::Trace) {
             let fun = emulator.code.function_by_id(self.id);
             let argv = process
@@ -6131,28 +6219,10 @@ impl Inst for ops::Spawn3 {
         }
 
         let arglist = process.stack.load(self.args);
-        let mut argv = SmallVec::<[OpaqueTerm; 6]>::default();
-        match arglist.into() {
-            Term::Nil => (),
-            Term::Cons(cons) => {
-                for result in cons.iter_raw() {
-                    if result.is_err() {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = arglist;
-                        process.exception_info.trace = None;
-                        return emulator.handle_error(process);
-                    }
-                    argv.push(unsafe { result.unwrap_unchecked() });
-                }
-            }
-            _ => {
-                process.exception_info.flags = ExceptionFlags::ERROR;
-                process.exception_info.reason = atoms::Badarg.into();
-                process.exception_info.value = arglist;
-                return emulator.handle_error(process);
-            }
-        }
+        let argv = match list_to_args(arglist, MAX_ARGS) {
+            Ok(argv) => argv,
+            Err(err) => return handle_arglist_error(emulator, process, arglist, err),
+        };
 
         let mut spawn_opts = SpawnOpts::default();
         spawn_opts.link = link;
@@ -6208,28 +6278,10 @@ impl Inst for ops::Spawn3Indirect {
             return emulator.handle_error(process);
         }
         let arglist = process.stack.load(self.args);
-        let mut argv = SmallVec::<[OpaqueTerm; 6]>::default();
-        match arglist.into() {
-            Term::Nil => (),
-            Term::Cons(cons) => {
-                for result in cons.iter_raw() {
-                    if result.is_err() {
-                        process.exception_info.flags = ExceptionFlags::ERROR;
-                        process.exception_info.reason = atoms::Badarg.into();
-                        process.exception_info.value = arglist;
-                        process.exception_info.trace = None;
-                        return emulator.handle_error(process);
-                    }
-                    argv.push(unsafe { result.unwrap_unchecked() });
-                }
-            }
-            _ => {
-                process.exception_info.flags = ExceptionFlags::ERROR;
-                process.exception_info.reason = atoms::Badarg.into();
-                process.exception_info.value = arglist;
-                return emulator.handle_error(process);
-            }
-        }
+        let argv = match list_to_args(arglist, MAX_ARGS) {
+            Ok(argv) => argv,
+            Err(err) => return handle_arglist_error(emulator, process, arglist, err),
+        };
 
         let mfa = ModuleFunctionArity {
             module: module.as_atom(),
@@ -6256,6 +6308,23 @@ impl Inst for ops::Spawn3Indirect {
     }
 }
 
+/// Raises the error corresponding to an argument list which could not be converted to `Arguments`
+fn handle_arglist_error(
+    emulator: &Emulator,
+    process: &mut ProcessLock,
+    arglist: OpaqueTerm,
+    err: ArgumentListError,
+) -> Action {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.reason = match err {
+        ArgumentListError::Improper => atoms::Badarg.into(),
+        ArgumentListError::TooManyArguments => atoms::SystemLimit.into(),
+    };
+    process.exception_info.value = arglist;
+    process.exception_info.trace = None;
+    emulator.handle_error(process)
+}
+
 fn clone_context_to_heap(
     context: MatchContext,
     process: &mut ProcessLock,
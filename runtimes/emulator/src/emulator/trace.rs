@@ -0,0 +1,128 @@
+use std::cell::Cell;
+use std::sync::atomic::Ordering;
+
+use firefly_rt::process::trace::{self, TimestampKind, TraceFlags, TraceTimestamp};
+use firefly_rt::process::{Process, ProcessLock, StatusFlags};
+use firefly_rt::services::registry;
+use firefly_rt::term::{atoms, Atom, Term};
+use firefly_system::time::{clock, MonotonicTime, UNIX_EPOCH};
+
+use super::Emulator;
+
+/// The source of timestamps for trace messages generated on a scheduler
+///
+/// Each scheduler has its own clock, so stamping an event only touches state owned by the
+/// scheduler, with the exception of `strict_monotonic_timestamp`, which needs a global counter to
+/// order events across schedulers. Timestamps handed out by a single scheduler never go backwards,
+/// and `timestamp` values are strictly increasing, like `erlang:now/0`.
+pub(super) struct TraceClock {
+    /// The last monotonic time handed out, in nanoseconds
+    last_monotonic: Cell<i64>,
+    /// The last system or CPU time handed out, in microseconds
+    last_micros: Cell<u64>,
+}
+impl TraceClock {
+    pub(super) const fn new() -> Self {
+        Self {
+            last_monotonic: Cell::new(i64::MIN),
+            last_micros: Cell::new(0),
+        }
+    }
+
+    pub(super) fn now(&self, kind: TimestampKind) -> TraceTimestamp {
+        match kind {
+            TimestampKind::System => {
+                let micros = clock::system_now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since| since.as_micros() as u64)
+                    .unwrap_or(0);
+                TraceTimestamp::Micros(self.next_micros(micros))
+            }
+            TimestampKind::Cpu => TraceTimestamp::Micros(self.next_micros(thread_cpu_micros())),
+            TimestampKind::Monotonic => TraceTimestamp::Monotonic(self.monotonic()),
+            TimestampKind::StrictMonotonic => TraceTimestamp::StrictMonotonic(
+                self.monotonic(),
+                crate::unique::get_signed_unique_monotonic_integer(),
+            ),
+        }
+    }
+
+    fn monotonic(&self) -> i64 {
+        let now = MonotonicTime::now().elapsed().as_nanos() as i64;
+        let now = now.max(self.last_monotonic.get());
+        self.last_monotonic.set(now);
+        now
+    }
+
+    fn next_micros(&self, now: u64) -> u64 {
+        let now = now.max(self.last_micros.get() + 1);
+        self.last_micros.set(now);
+        now
+    }
+}
+
+/// Returns the CPU time consumed by the current thread, in microseconds
+#[cfg(unix)]
+fn thread_cpu_micros() -> u64 {
+    let mut now = unsafe { std::mem::zeroed::<libc::timespec>() };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut now);
+    }
+    (now.tv_sec as u64) * 1_000_000 + (now.tv_nsec as u64) / 1_000
+}
+
+/// CPU time is not available on this platform, so the monotonic time is used instead
+#[cfg(not(unix))]
+fn thread_cpu_micros() -> u64 {
+    MonotonicTime::now().as_usecs()
+}
+
+impl Emulator {
+    /// Sends the trace message for `event` to the tracer of `traced`, if it is being traced
+    ///
+    /// `args` are the event-specific elements of the message, following the event name.
+    pub(crate) fn trace_event(
+        &self,
+        traced: &Process,
+        event: TraceFlags,
+        tag: Atom,
+        args: &[Term],
+    ) {
+        let Some((flags, tracer)) = traced.trace().tracer_for(event) else { return; };
+        let Some(tracer) = registry::get_by_process_id(tracer) else { return; };
+        let timestamp = flags.timestamp().map(|kind| self.trace_clock.now(kind));
+        let message = trace::make_trace_message(traced.pid(), tag, args, timestamp);
+        tracer.send_fragment(traced.addr(), message).ok();
+    }
+
+    /// Sends the `in` or `out` event for `traced` being scheduled in or out, if it is traced
+    ///
+    /// These carry the current function of the process, except while it is exiting, in which case
+    /// `in_exiting` and `out_exiting`, or `out_exited` once it is gone, are sent instead if it has
+    /// the `exiting` flag, as there is no longer a meaningful current function.
+    pub(crate) fn trace_schedule(&self, traced: &ProcessLock, scheduled_in: bool) {
+        let status = traced.status(Ordering::Acquire);
+        if status.contains(StatusFlags::EXITING) {
+            let tag = if scheduled_in {
+                atoms::InExiting
+            } else if status.contains(StatusFlags::FREE) {
+                atoms::OutExited
+            } else {
+                atoms::OutExiting
+            };
+            self.trace_event(traced.as_ref(), TraceFlags::EXITING, tag, &[Term::Int(0)]);
+            return;
+        }
+
+        let event = TraceFlags::RUNNING;
+        if traced.as_ref().trace().tracer_for(event).is_none() {
+            return;
+        }
+        let tag = if scheduled_in { atoms::In } else { atoms::Out };
+        let mfa = self
+            .mfa_by_ip(traced.ip)
+            .unwrap_or(traced.as_ref().initial_call);
+        let fragment = trace::make_function_fragment(mfa);
+        self.trace_event(traced.as_ref(), event, tag, &[fragment.term.into()]);
+    }
+}
@@ -20,8 +20,10 @@ extern crate firefly_crt;
 
 mod bifs;
 mod emulator;
+mod literals;
 mod nifs;
 mod queue;
+mod replay;
 mod sys;
 mod unique;
 
@@ -29,19 +31,76 @@ use std::convert::Infallible;
 use std::env;
 use std::panic;
 use std::process::ExitCode;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
-use crossbeam::deque::Injector;
-
+use firefly_alloc::allocators::carriers::{CarrierConfig, HugePages};
 use firefly_bytecode::{ByteCode, BytecodeReader, ReadError};
 use firefly_rt::scheduler;
-use firefly_rt::services::{self, distribution::NoDistribution};
+use firefly_rt::services;
+use firefly_rt::services::distribution::{DistributionConfig, NoDistribution};
+use firefly_rt::services::opaque_ids;
+use firefly_rt::services::warmup::{self, WarmupConfig};
 use firefly_rt::term::{atom::GlobalAtomTable, Atom};
 
 use self::emulator::{Emulator, EmulatorError};
 
-const NUM_SCHEDULERS: usize = 1;
+/// When an alternative malloc implementation is selected, all allocations are routed through it,
+/// not just those made by the runtime itself, so that memory is not split across allocators
+#[cfg(any(feature = "jemalloc", feature = "mimalloc"))]
+#[global_allocator]
+static GLOBAL: firefly_alloc::allocators::System = firefly_alloc::allocators::System;
+
+static NUM_SCHEDULERS: OnceLock<usize> = OnceLock::new();
+
+/// Returns the number of schedulers the runtime is running with
+///
+/// This is the number given with `+S`, or by default the number of processors available to this
+/// process, which takes into account any CPU quota imposed on it, e.g. by a container runtime.
+pub(crate) fn num_schedulers() -> usize {
+    *NUM_SCHEDULERS.get_or_init(|| {
+        let schedulers = schedulers_from_args(env::args()).unwrap_or_else(available_cpus);
+        schedulers.min(scheduler::MAX_SCHEDULERS)
+    })
+}
+
+#[cfg(unix)]
+pub(crate) fn available_cpus() -> usize {
+    firefly_system::arch::available_cpus()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn available_cpus() -> usize {
+    firefly_system::cgroup::limits().cpus().unwrap_or(1)
+}
+
+/// Parses the number of schedulers from the `+S Schedulers[:SchedulersOnline]` flag, as supported
+/// by ERTS
+///
+/// All schedulers are always online, so `SchedulersOnline` is accepted, but ignored.
+fn schedulers_from_args<I: Iterator<Item = String>>(mut args: I) -> Option<usize> {
+    while let Some(arg) = args.next() {
+        if arg == "+S" {
+            let value = args.next();
+            let schedulers = value.as_deref().map(|value| match value.split_once(':') {
+                Some((schedulers, _online)) => schedulers.parse::<usize>(),
+                None => value.parse::<usize>(),
+            });
+            match schedulers {
+                Some(Ok(n)) if n > 0 && n <= scheduler::MAX_SCHEDULERS => return Some(n),
+                _ => {
+                    eprintln!(
+                        "Ignoring invalid +S value, expected a number of schedulers from 1 to {}, got '{}'",
+                        scheduler::MAX_SCHEDULERS,
+                        value.as_deref().unwrap_or_default()
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+    None
+}
 
 #[macro_export]
 macro_rules! badarg {
@@ -67,6 +126,167 @@ macro_rules! unwrap_or_badarg {
     };
 }
 
+/// Parses the maximum number of atoms from the `+t Limit` flag, as supported by ERTS
+fn atom_limit_from_args<I: Iterator<Item = String>>(mut args: I) -> Option<usize> {
+    while let Some(arg) = args.next() {
+        if arg == "+t" {
+            let value = args.next();
+            match value.as_deref().map(str::parse::<usize>) {
+                Some(Ok(limit)) => return Some(limit),
+                _ => {
+                    eprintln!(
+                        "Ignoring invalid +t value, expected a number of atoms, got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parses what to do at boot to reduce latency later, from the following flags:
+///
+/// * `+P Number`, the maximum number of simultaneously alive processes, as supported by ERTS
+/// * `+Wa Atom`, an atom to create, which may be given any number of times
+/// * `+Wm Size`, megabytes of memory to fault in via the system allocator
+/// * `+Wsc true|false`, whether to fault in the super carrier
+fn warmup_config_from_args<I: Iterator<Item = String>>(mut args: I) -> WarmupConfig {
+    let mut config = WarmupConfig::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "+P" => {
+                let value = args.next();
+                match value.as_deref().map(str::parse::<usize>) {
+                    Some(Ok(limit)) if limit > 0 && limit < u32::MAX as usize => {
+                        config.processes = Some(limit)
+                    }
+                    _ => eprintln!(
+                        "Ignoring invalid +P value, expected a number of processes, got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            "+Wa" => match args.next() {
+                Some(name) => config.atoms.push(name),
+                None => eprintln!("Ignoring +Wa without an atom"),
+            },
+            "+Wm" => {
+                let value = args.next();
+                match value.as_deref().map(str::parse::<usize>) {
+                    Some(Ok(size)) => config.prefault = size * 1024 * 1024,
+                    _ => eprintln!(
+                        "Ignoring invalid +Wm value, expected a size in megabytes, got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            "+Wsc" => {
+                let value = args.next();
+                match value.as_deref() {
+                    Some("true") => config.prefault_super_carrier = true,
+                    Some("false") => config.prefault_super_carrier = false,
+                    _ => eprintln!(
+                        "Ignoring invalid +Wsc value, expected one of [true, false], got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            _ => continue,
+        }
+    }
+    config
+}
+
+/// Parses whether pids and references are rendered as opaque ids in log output from the
+/// following flags, returning the number of ids which can be resolved if they are:
+///
+/// * `-log_ids raw|opaque`, whether they are rendered as-is, the default, or as opaque ids
+/// * `-log_ids_capacity Count`, the number of opaque ids which can be resolved back, see
+/// `erts_debug:resolve_log_id/1`
+fn opaque_ids_from_args<I: Iterator<Item = String>>(mut args: I) -> Option<usize> {
+    let mut opaque = false;
+    let mut capacity = opaque_ids::DEFAULT_CAPACITY;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-log_ids" => {
+                let value = args.next();
+                match value.as_deref() {
+                    Some("raw") => opaque = false,
+                    Some("opaque") => opaque = true,
+                    _ => eprintln!(
+                        "Ignoring invalid -log_ids value, expected one of [raw, opaque], got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            "-log_ids_capacity" => {
+                let value = args.next();
+                match value.as_deref().map(str::parse::<usize>) {
+                    Some(Ok(count)) => capacity = count,
+                    _ => eprintln!(
+                        "Ignoring invalid -log_ids_capacity value, expected a number of ids, got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            _ => continue,
+        }
+    }
+    opaque.then_some(capacity)
+}
+
+/// Returns a random key for hashing opaque ids, which is never revealed outside of this process
+fn opaque_ids_key() -> u128 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    // The keys of `RandomState` are seeded from the OS, each hasher built from it shares them
+    let state = RandomState::new();
+    (0..2u64).fold(0, |key, i| {
+        let mut hasher = state.build_hasher();
+        hasher.write_u64(i);
+        (key << 64) | hasher.finish() as u128
+    })
+}
+
+/// Parses the configuration of large carriers from the following flags:
+///
+/// * `+MMscs Size`, the size of the super carrier in megabytes, as supported by ERTS
+/// * `+MMhp off|transparent|explicit`, whether large carriers are backed by huge pages
+fn carrier_config_from_args<I: Iterator<Item = String>>(mut args: I) -> CarrierConfig {
+    let mut config = CarrierConfig::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "+MMscs" => {
+                let value = args.next();
+                match value.as_deref().map(str::parse::<usize>) {
+                    Some(Ok(size)) => config.super_carrier_size = size * 1024 * 1024,
+                    _ => eprintln!(
+                        "Ignoring invalid +MMscs value, expected a size in megabytes, got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            "+MMhp" => {
+                let value = args.next();
+                match value.as_deref() {
+                    Some("off") => config.huge_pages = HugePages::Off,
+                    Some("transparent") => config.huge_pages = HugePages::Transparent,
+                    Some("explicit") => config.huge_pages = HugePages::Explicit,
+                    _ => eprintln!(
+                        "Ignoring invalid +MMhp value, expected one of [off, transparent, explicit], got '{}'",
+                        value.as_deref().unwrap_or_default()
+                    ),
+                }
+            }
+            _ => continue,
+        }
+    }
+    config
+}
+
 #[export_name = "firefly_entry"]
 pub fn main() -> i32 {
     use std::process::Termination;
@@ -87,19 +307,91 @@ pub fn main() -> i32 {
     } else {
         builder.format_timestamp(None);
     }
+    // Claim any state passed to us by systemd before anything else can open file descriptors
+    #[cfg(unix)]
+    sys::systemd::init();
+    // When running as a systemd service, log directly to the journal rather than to stderr
+    #[cfg(unix)]
+    if sys::systemd::is_journal_connected() {
+        sys::systemd::JournalLogger::new(builder.build())
+            .init()
+            .unwrap();
+    } else {
+        builder.init();
+    }
+    #[cfg(not(unix))]
     builder.init();
 
+    // The atom limit must be set before any atoms are created at runtime
+    if let Some(limit) = atom_limit_from_args(env::args()) {
+        firefly_rt::term::atom::set_atom_limit(limit);
+    }
+
+    // Large carriers must be configured before any process heaps are allocated
+    let carrier_config = carrier_config_from_args(env::args());
+    if let Ok(false) = firefly_alloc::allocators::carriers::init(carrier_config) {
+        eprintln!(
+            "Unable to reserve a super carrier of {} bytes, continuing without one",
+            carrier_config.super_carrier_size
+        );
+    }
+
+    // Do the work which would otherwise be done on first use, once the carriers are set up, and
+    // before the registry is used by anything else
+    let warmup_config = warmup_config_from_args(env::args());
+    match warmup::warmup(&warmup_config) {
+        Ok(stats) => log::debug!(target: "warmup", "warmed up: {:?}", stats),
+        Err(err) => eprintln!("Unable to create the atoms given with +Wa: {}", err),
+    }
+
     // Load bytecode first, since if it fails there is no point in going further
     let code = load_bytecode().expect("failed to load bytecode");
+    literals::init(&code).expect("failed to load literals");
 
     // Initialize the global environment
     sys::env::init(std::env::args_os()).unwrap();
 
     // Initialize global uniqueness data
-    self::unique::init(NUM_SCHEDULERS, 0, 0);
+    let num_schedulers = num_schedulers();
+    let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+    let dirty_config = sys::dirty::DirtyConfig::from_args(args, num_schedulers);
+    self::unique::init(num_schedulers, dirty_config.cpu, dirty_config.io);
+
+    // When running in a container with a memory limit, no single process can usefully grow its
+    // heap beyond that limit, and it is better to kill that process than to have the whole node
+    // killed for running out of memory
+    if let Some(limit) = firefly_system::cgroup::limits().memory_limit {
+        firefly_rt::process::MaxHeapSize::set_system_default(
+            limit.try_into().unwrap_or(usize::MAX),
+        );
+    }
+
+    // React to memory pressure by collecting process heaps before the node runs out of memory
+    sys::memory_pressure::init();
+
+    // Standard io must be redirected before the init process is spawned, as it inherits the io
+    // server as its group leader
+    let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+    if let Some(config) = sys::stdio_file::StdioFileConfig::from_args(args) {
+        let path = config.path.clone();
+        if let Err(err) = sys::stdio_file::init(config) {
+            eprintln!(
+                "Unable to open {} for standard io: {}, continuing without redirection",
+                path.display(),
+                err
+            );
+        }
+    }
 
     // Initialize the distribution service
-    services::distribution::init(NoDistribution::new());
+    let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+    let dist_config = DistributionConfig::from_args(args).unwrap_or_else(|err| {
+        eprintln!("{}, using default distribution configuration", err);
+        DistributionConfig::default()
+    });
+    services::distribution::init(NoDistribution::with_config(dist_config));
+    // Prepare the node to be checkpointed on request, e.g. for a restore-based fast start
+    sys::checkpoint::init();
 
     // Create a new multi-threaded async runtime
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -107,31 +399,88 @@ pub fn main() -> i32 {
         .build()
         .expect("unable to start scheduler");
 
+    // Select the I/O backend before anything can start performing I/O
+    #[cfg(unix)]
+    {
+        let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+        let backend = sys::io::init(&runtime, sys::io::IoConfig::from_args(args));
+        log::debug!(target: "io", "using the {:?} i/o backend", backend);
+    }
+
     // Set up the system signal handler
     if cfg!(not(target_family = "wasm")) {
         runtime.spawn_blocking(|| sys::signals::start_handler());
     }
+    // Start the heart watchdog, if requested
+    #[cfg(unix)]
+    {
+        let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+        match sys::heart::HeartConfig::from_env(args) {
+            Ok(None) => (),
+            Ok(Some(config)) => {
+                sys::heart::start(config, num_schedulers).expect("unable to start heart")
+            }
+            Err(err) => eprintln!("{}, heart is disabled", err),
+        }
+    }
+    // Hide pids and references in log output, if requested, before anything can be logged
+    let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+    if let Some(capacity) = opaque_ids_from_args(args) {
+        opaque_ids::enable(opaque_ids_key(), capacity);
+    }
+    // Start the native logger handler, if requested, before anything can be logged
+    let args = env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+    if let Some(config) = sys::log_file::LogFileConfig::from_args(args) {
+        let path = config.path.clone();
+        if let Err(err) = sys::log_file::start(config) {
+            eprintln!(
+                "Unable to open {} for logging: {}, logging to standard error instead",
+                path.display(),
+                err
+            );
+        }
+    }
+    // Start the dirty schedulers before any process can schedule work on them
+    sys::dirty::start(dirty_config).expect("unable to start dirty schedulers");
     // Set up the system dispatcher
     runtime.spawn(sys::dispatcher::start());
     // Get a clone of the async runtime handle to give to each scheduler
     let handle = runtime.handle().clone();
-    // Get the global work-stealing task queue shared by the schedulers
-    let injector = Arc::new(Injector::new());
+    // Group the schedulers by NUMA node, each group having its own work-stealing task queue
+    let bind = sys::numa::bind_from_args(env::args());
+    sys::numa::init(num_schedulers, bind);
     // Spawn a task for each instance of emulator acting as a scheduler
-    let mut handles = Vec::with_capacity(NUM_SCHEDULERS);
-    for i in 0..NUM_SCHEDULERS {
+    let mut handles = Vec::with_capacity(num_schedulers);
+    for i in 0..num_schedulers {
         let emu_handle = handle.clone();
-        let emu_injector = injector.clone();
         let emu_code = code.clone();
         handles.push(runtime.spawn_blocking(move || {
+            // Bind before the scheduler allocates anything, so that its memory is node-local
+            let group = sys::numa::group_of(i);
+            sys::numa::bind_current_thread(group);
+            let emu_injector = group.injector.clone();
+            let emu_remote = sys::numa::remote_injectors(group);
+            let emu_peers = sys::numa::peers(group);
             let emulator = scheduler::create(move |id| {
-                Ok::<_, Infallible>(Emulator::new(id, emu_code, emu_injector, emu_handle))
+                Ok::<_, Infallible>(Emulator::new(
+                    id,
+                    emu_code,
+                    emu_injector,
+                    emu_remote,
+                    emu_peers,
+                    emu_handle,
+                ))
             })
             .unwrap();
             let spawn_init = i == 0;
             emulator.start(spawn_init)
         }));
     }
+    // Let systemd know that startup is complete, if we're running as a service
+    #[cfg(unix)]
+    if let Err(err) = sys::systemd::notify(&[sys::systemd::Notify::Ready]) {
+        log::warn!("unable to notify systemd of readiness: {}", err);
+    }
 
     // Wait for all of the scheduler threads to terminate
     for handle in handles.drain(..) {
@@ -145,12 +494,16 @@ pub fn main() -> i32 {
             Ok(result) => match result {
                 Ok(_) => continue,
                 Err(EmulatorError::Halt(0)) => {
+                    #[cfg(unix)]
+                    sys::systemd::notify(&[sys::systemd::Notify::Stopping]).ok();
                     // Give some time for any outstanding background tasks to clean up
                     runtime.shutdown_timeout(Duration::from_millis(50));
 
                     return ExitCode::SUCCESS.report().to_i32();
                 }
                 Err(EmulatorError::Halt(n)) => {
+                    #[cfg(unix)]
+                    sys::systemd::notify(&[sys::systemd::Notify::Stopping]).ok();
                     // Give some time for any outstanding background tasks to clean up
                     runtime.shutdown_timeout(Duration::from_secs(5));
 
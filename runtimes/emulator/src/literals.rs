@@ -0,0 +1,57 @@
+//! This module provides the literal area, which holds the constant terms of the loaded bytecode
+//! which can't be represented as immediates or static binaries, i.e. map literals.
+//!
+//! The compiler encodes each such term in the external term format, and stores it in the binary
+//! table of the bytecode. When the bytecode is loaded, each of them is decoded once, into a heap
+//! fragment which is never freed, so that the `load_map` instruction only has to load a pointer
+//! to it, rather than building a new map every time it is evaluated. The maps are marked as
+//! literals, so that the garbage collector leaves them where they are, and so that they are never
+//! modified in place.
+use std::collections::HashMap;
+use std::mem;
+use std::sync::OnceLock;
+
+use firefly_binary::Binary;
+use firefly_bytecode::{ops, ByteCode, Opcode};
+use firefly_rt::etf::{DecodeError, Decoder};
+use firefly_rt::term::{atom::GlobalAtomTable, Atom, BinaryData, OpaqueTerm, Term};
+
+/// Maps the address of each encoded literal in the binary table to its decoded term
+static LITERALS: OnceLock<HashMap<usize, OpaqueTerm>> = OnceLock::new();
+
+/// Decodes the literals of `code` into the literal area
+///
+/// This must be called once at startup, after the bytecode is loaded, and before any of it runs.
+pub fn init(code: &ByteCode<Atom, GlobalAtomTable>) -> Result<(), DecodeError> {
+    let mut literals = HashMap::new();
+    for op in code.code.iter() {
+        let Opcode::LoadMap(ops::LoadMap { value, .. }) = op else { continue; };
+        let addr = *value as usize;
+        if literals.contains_key(&addr) {
+            continue;
+        }
+        let data: &'static BinaryData = unsafe { &*(*value as *const BinaryData) };
+        let fragment = Decoder::new(data.as_bytes())?.decode_fragment()?;
+        let term = fragment.term;
+        let Term::Map(mut map) = term.into() else { return Err(DecodeError::Invalid); };
+        // The fragment must outlive every process which may load the map
+        mem::forget(fragment);
+        unsafe {
+            map.mark_literal();
+        }
+        literals.insert(addr, term);
+    }
+    assert!(
+        LITERALS.set(literals).is_ok(),
+        "literal area was already initialized"
+    );
+    Ok(())
+}
+
+/// Returns the literal decoded from the encoded term at `data`
+///
+/// Panics if `data` wasn't decoded by [`init`].
+#[inline]
+pub fn get(data: *const firefly_bytecode::BinaryData) -> OpaqueTerm {
+    LITERALS.get().unwrap()[&(data as usize)]
+}
@@ -0,0 +1,42 @@
+//! The `firefly_clock` module, which controls the virtual clock used by the runtime, see
+//! `firefly_system::time::clock`.
+//!
+//! This is intended for tests of timeout-heavy code, which can freeze the clock and advance it
+//! past a timeout rather than waiting for the timeout to expire in real time.
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+use firefly_system::time::{clock, Duration};
+
+use crate::badarg;
+
+/// Freezes the clock, so that time only passes when `advance/1` is called
+#[export_name = "firefly_clock:freeze/0"]
+pub extern "C-unwind" fn freeze(_process: &mut ProcessLock) -> ErlangResult {
+    clock::freeze();
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+/// Restarts the clock, so that it follows the system clock again
+#[export_name = "firefly_clock:unfreeze/0"]
+pub extern "C-unwind" fn unfreeze(_process: &mut ProcessLock) -> ErlangResult {
+    clock::unfreeze();
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+/// Returns true if the clock is frozen
+#[export_name = "firefly_clock:is_frozen/0"]
+pub extern "C-unwind" fn is_frozen(_process: &mut ProcessLock) -> ErlangResult {
+    ErlangResult::Ok(clock::is_frozen().into())
+}
+
+/// Moves the clock forward by the given number of milliseconds
+///
+/// Timers which expire as a result fire the next time their scheduler's timer wheel ticks.
+#[export_name = "firefly_clock:advance/1"]
+pub extern "C-unwind" fn advance(process: &mut ProcessLock, millis: OpaqueTerm) -> ErlangResult {
+    let Term::Int(ms) = millis.into() else { badarg!(process, millis); };
+    let Ok(ms) = u64::try_from(ms) else { badarg!(process, millis); };
+    clock::advance(Duration::from_millis(ms));
+    ErlangResult::Ok(atoms::Ok.into())
+}
@@ -0,0 +1,72 @@
+//! The parts of the `crypto` module which do not depend on a cryptography library
+use std::borrow::Cow;
+use std::hint::black_box;
+
+use firefly_binary::Bitstring;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Compares two binaries of the same size in constant time, for comparing secrets such as tokens
+/// or MACs, where `=:=` would reveal how many leading bytes match through its timing
+///
+/// As with OTP, the binaries must be the same size, otherwise this raises `badarg`, so only their
+/// size, which is usually public anyway, can be learned from the time taken.
+#[export_name = "crypto:hash_equals/2"]
+pub extern "C-unwind" fn hash_equals2(
+    process: &mut ProcessLock,
+    lhs: OpaqueTerm,
+    rhs: OpaqueTerm,
+) -> ErlangResult {
+    let l: Term = lhs.into();
+    let r: Term = rhs.into();
+    let Some(l) = l.as_binary() else { badarg!(process, lhs); };
+    let Some(r) = r.as_binary() else { badarg!(process, rhs); };
+    let l = binary_bytes(l);
+    let r = binary_bytes(r);
+    if l.len() != r.len() {
+        badarg!(process, rhs);
+    }
+    ErlangResult::Ok(constant_time_eq(&l, &r).into())
+}
+
+fn binary_bytes(bin: &dyn Bitstring) -> Cow<'_, [u8]> {
+    if bin.is_aligned() {
+        Cow::Borrowed(unsafe { bin.as_bytes_unchecked() })
+    } else {
+        Cow::Owned(bin.bytes().collect())
+    }
+}
+
+/// Returns true if `a` and `b` are equal, taking the same time for any two slices of their length
+///
+/// Every byte is compared, the differences are accumulated without branching, and the inputs and
+/// the accumulator are passed through `black_box` so that the optimizer can neither stop at the
+/// first difference nor turn the loop back into an early-exit comparison.
+#[inline(never)]
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    debug_assert_eq!(a.len(), b.len());
+    let a = black_box(a);
+    let b = black_box(b);
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff = black_box(diff | (x ^ y));
+    }
+    black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_test() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"Secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(&[0; 64], &[0x80; 64]));
+    }
+}
@@ -0,0 +1,113 @@
+//! The parts of the `erts_debug` module which are supported by the runtime
+use std::thread;
+use std::time::Duration;
+
+use firefly_binary::Bitstring;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::opaque_ids::{self, Identity};
+use firefly_rt::term::{atoms, LayoutBuilder, OpaqueTerm, Reference, Term, TermFragment};
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+use crate::sys::dirty::{self, DirtyKind};
+
+/// Returns the number of heap words occupied by `term`, counting shared subterms once
+#[export_name = "erts_debug:size/1"]
+pub extern "C-unwind" fn size(_process: &mut ProcessLock, term: OpaqueTerm) -> ErlangResult {
+    let term: Term = term.into();
+    ErlangResult::Ok(Term::try_from(term.shared_size()).unwrap().into())
+}
+
+/// Returns the number of heap words `term` would occupy if it were copied, e.g. as a message
+#[export_name = "erts_debug:flat_size/1"]
+pub extern "C-unwind" fn flat_size(_process: &mut ProcessLock, term: OpaqueTerm) -> ErlangResult {
+    let term: Term = term.into();
+    ErlangResult::Ok(Term::try_from(term.flat_size()).unwrap().into())
+}
+
+/// Returns true if `a` and `b` are the same term, i.e. both are the same immediate, or both refer
+/// to the same term in memory, as is the case for a literal loaded twice
+#[export_name = "erts_debug:same/2"]
+pub extern "C-unwind" fn same(
+    _process: &mut ProcessLock,
+    a: OpaqueTerm,
+    b: OpaqueTerm,
+) -> ErlangResult {
+    ErlangResult::Ok((a == b).into())
+}
+
+/// Returns the pid or reference rendered as the opaque id `id` in log output, or `undefined` if
+/// it is unknown, e.g. as it was rendered too long ago, see `firefly_rt::services::opaque_ids`
+///
+/// The id may be given as a string or binary, as it appears in the log, or as just the hex digits.
+#[export_name = "erts_debug:resolve_log_id/1"]
+pub extern "C-unwind" fn resolve_log_id(process: &mut ProcessLock, id: OpaqueTerm) -> ErlangResult {
+    let name = match id.into() {
+        Term::Cons(cons) => cons.as_ref().to_string(),
+        term => term.as_binary().and_then(|bin| bin.as_str().map(str::to_owned)),
+    };
+    let Some(name) = name else { badarg!(process, id); };
+    let Some(identity) = opaque_ids::resolve(&name) else {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    };
+
+    let mut layout = LayoutBuilder::new();
+    match identity {
+        Identity::Pid(_) => layout.build_pid(),
+        Identity::Reference(_) => layout.build_reference(),
+    };
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    match identity {
+        Identity::Pid(pid) => ErlangResult::Ok(Gc::new_in(pid, process).unwrap().into()),
+        Identity::Reference(reference) => {
+            ErlangResult::Ok(Gc::new_in(reference, process).unwrap().into())
+        }
+    }
+}
+
+/// Sleeps for `ms` milliseconds on a dirty scheduler of the given kind, `dirty_cpu` or `dirty_io`,
+/// without holding up the calling process, which is sent `{Ref, ok}` once done
+///
+/// Returns `Ref`. This is meant for testing that blocking native code on a dirty scheduler does
+/// not prevent processes from running.
+#[export_name = "erts_debug:dirty_sleep/2"]
+pub extern "C-unwind" fn dirty_sleep(
+    process: &mut ProcessLock,
+    kind: OpaqueTerm,
+    ms: OpaqueTerm,
+) -> ErlangResult {
+    let dirty_kind = match kind.into() {
+        Term::Atom(a) if a == "dirty_cpu" => DirtyKind::Cpu,
+        Term::Atom(a) if a == "dirty_io" => DirtyKind::Io,
+        _ => badarg!(process, kind),
+    };
+    let Term::Int(millis) = ms.into() else { badarg!(process, ms); };
+    let Ok(millis) = u64::try_from(millis) else { badarg!(process, ms); };
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let reference = Reference::new(current_scheduler().next_reference_id());
+    let sleep = move || {
+        thread::sleep(Duration::from_millis(millis));
+        TermFragment {
+            term: atoms::Ok.into(),
+            fragment: None,
+        }
+    };
+    if dirty::schedule_dirty_reply(dirty_kind, process.pid(), reference.clone(), sleep).is_err() {
+        badarg!(process, kind);
+    }
+    ErlangResult::Ok(Gc::new_in(reference, process).unwrap().into())
+}
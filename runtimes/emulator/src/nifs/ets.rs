@@ -0,0 +1,90 @@
+//! The match spec functions of the `ets` module, see `firefly_rt::match_spec`
+//!
+//! NOTE: There are no ETS tables yet, these are the parts of the module which don't need them.
+use std::sync::Arc;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::match_spec::{Evaluator, MatchSpec, MatchSpecKind};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::scheduler::Scheduler;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+
+/// Compiles `spec`, returning the compiled form as a magic reference
+#[export_name = "ets:match_spec_compile/1"]
+pub extern "C-unwind" fn match_spec_compile1(
+    process: &mut ProcessLock,
+    spec: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(compiled) = MatchSpec::compile(spec.into(), MatchSpecKind::Table) else { badarg!(process, spec); };
+
+    let mut id = current_scheduler().next_reference_id();
+    id.set_magic();
+    let reference = Reference::new_magic(id, Arc::new(compiled));
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference();
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(Gc::new_in(reference, process).unwrap().into())
+}
+
+/// Returns true if `term` is a match spec compiled by `match_spec_compile/1`
+#[export_name = "ets:is_compiled_ms/1"]
+pub extern "C-unwind" fn is_compiled_ms1(
+    _process: &mut ProcessLock,
+    term: OpaqueTerm,
+) -> ErlangResult {
+    ErlangResult::Ok(to_match_spec(term).is_some().into())
+}
+
+/// Runs the compiled match spec `compiled` against each element of `list`, returning the results
+/// of the elements which matched, in order
+#[export_name = "ets:match_spec_run/2"]
+pub extern "C-unwind" fn match_spec_run2(
+    process: &mut ProcessLock,
+    list: OpaqueTerm,
+    compiled: OpaqueTerm,
+) -> ErlangResult {
+    let Some(spec) = to_match_spec(compiled) else { badarg!(process, compiled); };
+    let elements = match list.into() {
+        Term::Nil => return ErlangResult::Ok(OpaqueTerm::NIL),
+        Term::Cons(cons) => match cons.iter_raw().collect::<Result<Vec<_>, _>>() {
+            Ok(elements) => elements,
+            Err(_) => badarg!(process, list),
+        },
+        _ => badarg!(process, list),
+    };
+
+    let mut evaluator = Evaluator::new(Some(process.pid()));
+    let results = elements
+        .into_iter()
+        .filter_map(|element| evaluator.run(&spec, element))
+        .map(|matched| matched.result)
+        .collect::<Vec<_>>();
+    let results = evaluator.list(&results).unwrap();
+
+    // See match_spec_test/3 in firefly_rt, the results may reference both the list and the spec
+    let fragment = TermFragment::clone_from(&results.into()).unwrap();
+    drop(evaluator);
+
+    let results: Term = fragment.term.into();
+    let needed = copy_layout(&results, process, CopyMode::Flat).size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(unsafe { unsafe_copy_term(&results, process, CopyMode::Flat) }.into())
+}
+
+fn to_match_spec(term: OpaqueTerm) -> Option<Arc<MatchSpec>> {
+    match term.into() {
+        Term::Reference(reference) => reference.magic()?.downcast::<MatchSpec>().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,229 @@
+//! The native functions of the `inet` module
+//!
+//! Statistics are kept per port, see `firefly_rt::term::PortStats`, and are shared with the socket
+//! the port performs I/O on, if any, see `sys::io::Socket::with_stats`.
+//!
+//! Addresses are represented in Erlang as tuples of 4 bytes for IPv4, or 8 16-bit words for IPv6.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::registry;
+use firefly_rt::term::*;
+
+use smallvec::SmallVec;
+
+use crate::badarg;
+
+/// Returns `{ok, [{Stat, Value}]}` for all of the statistics of `port`
+#[export_name = "inet:getstat/1"]
+pub extern "C-unwind" fn getstat1(process: &mut ProcessLock, port: OpaqueTerm) -> ErlangResult {
+    let Term::Port(port) = port.into() else { badarg!(process, port); };
+    getstat(process, port, &PortStat::ALL)
+}
+
+/// Returns `{ok, [{Stat, Value}]}` for each of the statistics of `port` in `options`
+#[export_name = "inet:getstat/2"]
+pub extern "C-unwind" fn getstat2(
+    process: &mut ProcessLock,
+    port: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Port(port) = port.into() else { badarg!(process, port); };
+    let mut stats = SmallVec::<[PortStat; 13]>::new();
+    match options.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for option in cons.iter() {
+                let Ok(Term::Atom(option)) = option else { badarg!(process, options); };
+                match PortStat::try_from(option) {
+                    Ok(stat) => stats.push(stat),
+                    Err(_) => return einval(process),
+                }
+            }
+        }
+        _ => badarg!(process, options),
+    }
+    getstat(process, port, &stats)
+}
+
+fn getstat(process: &mut ProcessLock, port: Arc<Port>, stats: &[PortStat]) -> ErlangResult {
+    // The port may have been closed, in which case it is no longer registered
+    let port = if port.is_local() {
+        registry::get_by_port_id(port.id())
+    } else {
+        None
+    };
+    let Some(port) = port else { return einval(process); };
+    let counters = port.stats().get();
+
+    let mut layout = LayoutBuilder::new();
+    for _ in stats {
+        layout.build_tuple(2);
+    }
+    layout.build_list(stats.len()).build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let items = stats
+        .iter()
+        .map(|stat| {
+            // Counters saturate at the largest immediate integer, which is not reachable in practice
+            let value = counters.get(*stat).min(Int::MAX_SMALL as u64) as i64;
+            Tuple::from_slice(&[stat.atom().into(), Term::Int(value).into()], process)
+                .unwrap()
+                .into()
+        })
+        .collect::<SmallVec<[OpaqueTerm; 13]>>();
+    let items = Cons::from_slice(&items, process)
+        .unwrap()
+        .map(Term::Cons)
+        .unwrap_or(Term::Nil);
+    let result = Tuple::from_slice(&[atoms::Ok.into(), items.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
+
+/// Parses an IPv4 or IPv6 address, returning `{ok, Address}` or `{error, einval}`
+#[export_name = "inet:parse_address/1"]
+pub extern "C-unwind" fn parse_address1(
+    process: &mut ProcessLock,
+    string: OpaqueTerm,
+) -> ErlangResult {
+    let Some(string) = to_string(string) else { badarg!(process, string); };
+    match string.parse::<IpAddr>() {
+        Ok(addr) => ok_address(process, addr),
+        Err(_) => einval(process),
+    }
+}
+
+/// Parses an IPv4 address, returning `{ok, Address}` or `{error, einval}`
+#[export_name = "inet:parse_ipv4_address/1"]
+pub extern "C-unwind" fn parse_ipv4_address1(
+    process: &mut ProcessLock,
+    string: OpaqueTerm,
+) -> ErlangResult {
+    let Some(string) = to_string(string) else { badarg!(process, string); };
+    match string.parse::<Ipv4Addr>() {
+        Ok(addr) => ok_address(process, IpAddr::V4(addr)),
+        Err(_) => einval(process),
+    }
+}
+
+/// Parses an IPv6 address, returning `{ok, Address}` or `{error, einval}`
+///
+/// IPv4 addresses are accepted, and returned as IPv4-mapped IPv6 addresses.
+#[export_name = "inet:parse_ipv6_address/1"]
+pub extern "C-unwind" fn parse_ipv6_address1(
+    process: &mut ProcessLock,
+    string: OpaqueTerm,
+) -> ErlangResult {
+    let Some(string) = to_string(string) else { badarg!(process, string); };
+    let addr = match string.parse::<IpAddr>() {
+        Ok(IpAddr::V4(addr)) => addr.to_ipv6_mapped(),
+        Ok(IpAddr::V6(addr)) => addr,
+        Err(_) => return einval(process),
+    };
+    ok_address(process, IpAddr::V6(addr))
+}
+
+/// Formats an address as a string, returning `{error, einval}` if it is not a valid address
+///
+/// IPv6 addresses are formatted in the canonical form of RFC 5952, i.e. with the longest run of
+/// zeroes elided, and IPv4-mapped addresses with a trailing dotted quad.
+#[export_name = "inet:ntoa/1"]
+pub extern "C-unwind" fn ntoa1(process: &mut ProcessLock, addr: OpaqueTerm) -> ErlangResult {
+    let Some(addr) = to_ip_addr(addr) else { return einval(process); };
+    let formatted = addr.to_string();
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_list(formatted.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    ErlangResult::Ok(
+        Term::charlist_from_str_in(&formatted, process)
+            .unwrap()
+            .into(),
+    )
+}
+
+/// Converts an address tuple to an `IpAddr`
+fn to_ip_addr(term: OpaqueTerm) -> Option<IpAddr> {
+    let Term::Tuple(tuple) = term.into() else { return None; };
+    let mut parts = [0u16; 8];
+    if tuple.len() != 4 && tuple.len() != 8 {
+        return None;
+    }
+    let max = if tuple.len() == 4 {
+        u8::MAX as i64
+    } else {
+        u16::MAX as i64
+    };
+    for (part, element) in parts.iter_mut().zip(tuple.iter()) {
+        match element {
+            Term::Int(i) if (0..=max).contains(&i) => *part = i as u16,
+            _ => return None,
+        }
+    }
+    if tuple.len() == 4 {
+        let [a, b, c, d, ..] = parts;
+        Some(IpAddr::V4(Ipv4Addr::new(
+            a as u8, b as u8, c as u8, d as u8,
+        )))
+    } else {
+        let [a, b, c, d, e, f, g, h] = parts;
+        Some(IpAddr::V6(Ipv6Addr::new(a, b, c, d, e, f, g, h)))
+    }
+}
+
+/// Returns `{ok, Address}`, where `Address` is the tuple representation of `addr`
+fn ok_address(process: &mut ProcessLock, addr: IpAddr) -> ErlangResult {
+    let parts: Vec<OpaqueTerm> = match addr {
+        IpAddr::V4(addr) => addr
+            .octets()
+            .map(|octet| Term::Int(octet as i64).into())
+            .to_vec(),
+        IpAddr::V6(addr) => addr
+            .segments()
+            .map(|word| Term::Int(word as i64).into())
+            .to_vec(),
+    };
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(parts.len()).build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let addr = Tuple::from_slice(&parts, process).unwrap();
+    let result = Tuple::from_slice(&[atoms::Ok.into(), addr.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
+
+/// Addresses are parsed from strings, but not binaries
+fn to_string(term: OpaqueTerm) -> Option<String> {
+    match term.into() {
+        term @ (Term::Nil | Term::Cons(_)) => String::try_from(term).ok(),
+        _ => None,
+    }
+}
+
+/// Returns `{error, einval}`
+fn einval(process: &mut ProcessLock) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let result = Tuple::from_slice(&[atoms::Error.into(), atoms::Einval.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
@@ -0,0 +1,79 @@
+//! The `firefly_lcnt` module, which exposes the lock contention statistics gathered when the
+//! runtime is built with the `lcnt` feature, see `firefly_system::sync::lcnt`.
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+use firefly_system::sync::lcnt;
+
+/// Returns true if lock counting is enabled in this build of the runtime
+#[export_name = "firefly_lcnt:is_enabled/0"]
+pub extern "C-unwind" fn is_enabled(_process: &mut ProcessLock) -> ErlangResult {
+    ErlangResult::Ok(lcnt::is_enabled().into())
+}
+
+/// Returns `[{Class, Tries, Collisions, WaitTime}]` for every lock class acquired since the
+/// statistics were last cleared, where `WaitTime` is the total time in nanoseconds spent waiting
+/// on locks of that class
+///
+/// Raises `notsup` if lock counting is not enabled.
+#[export_name = "firefly_lcnt:collect/0"]
+pub extern "C-unwind" fn collect(process: &mut ProcessLock) -> ErlangResult {
+    if !lcnt::is_enabled() {
+        return notsup(process);
+    }
+
+    let stats = lcnt::collect();
+    let mut layout = LayoutBuilder::new();
+    for _ in stats.iter() {
+        layout.build_tuple(4);
+    }
+    layout.build_list(stats.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let count = |n: u64| -> OpaqueTerm { Term::try_from(n as i64).unwrap().into() };
+    let mut builder = ListBuilder::new(process);
+    for class in stats.iter().rev() {
+        let name = Atom::try_from(class.name).unwrap();
+        let elements = [
+            name.into(),
+            count(class.tries),
+            count(class.collisions),
+            count(class.wait_time.as_nanos() as u64),
+        ];
+        let tuple = Tuple::from_slice(&elements, process).unwrap();
+        builder.push(Term::Tuple(tuple)).unwrap();
+    }
+    ErlangResult::Ok(
+        builder
+            .finish()
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL),
+    )
+}
+
+/// Resets the statistics of all lock classes
+///
+/// Raises `notsup` if lock counting is not enabled.
+#[export_name = "firefly_lcnt:clear/0"]
+pub extern "C-unwind" fn clear(process: &mut ProcessLock) -> ErlangResult {
+    if !lcnt::is_enabled() {
+        return notsup(process);
+    }
+    lcnt::clear();
+    ErlangResult::Ok(atoms::Ok.into())
+}
+
+fn notsup(process: &mut ProcessLock) -> ErlangResult {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.reason = atoms::Notsup.into();
+    process.exception_info.value = atoms::Notsup.into();
+    process.exception_info.args = Some(OpaqueTerm::NIL);
+    process.exception_info.trace = None;
+    ErlangResult::Err
+}
@@ -1,58 +1,74 @@
-use firefly_alloc::heap::Heap;
-use firefly_rt::function::ErlangResult;
-use firefly_rt::gc::{garbage_collect, Gc, RootSet};
-use firefly_rt::process::ProcessLock;
+use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
+use firefly_rt::gc::{garbage_collect, RootSet};
+use firefly_rt::process::{ProcessLock, ARG0_REG};
 use firefly_rt::term::*;
 
 use crate::badarg;
 
+/// The number of elements reversed per reduction
+const ELEMENTS_PER_REDUCTION: usize = 16;
+
+/// Reverses `list` onto `tail`
+///
+/// Long lists are reversed a time slice at a time; when out of reductions, the elements reversed
+/// so far become the new `tail`, and this traps back to itself with the rest of `list`, so that
+/// other processes can run in between.
 #[export_name = "lists:reverse/2"]
 pub extern "C-unwind" fn reverse(
     process: &mut ProcessLock,
     mut list: OpaqueTerm,
     mut tail: OpaqueTerm,
 ) -> ErlangResult {
+    static REVERSE_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::Lists,
+        function: atoms::Reverse,
+        arity: 2,
+    };
+
     // If we get a non-empty list, we can return the tail directly
     if list.is_nil() {
         return ErlangResult::Ok(tail);
     }
+    if !list.is_nonempty_list() {
+        badarg!(process, list);
+    }
 
-    let heap_top = process.heap_top();
-    match list.into() {
-        Term::Cons(cons) => {
-            'retry: loop {
-                let mut builder = if tail.is_nil() {
-                    ListBuilder::new(process)
-                } else if tail.is_nonempty_list() {
-                    let tail = unsafe { Gc::from_raw(tail.as_ptr() as *mut Cons) };
-                    ListBuilder::prepend(tail, process)
-                } else {
-                    ListBuilder::new_improper(tail, process)
-                };
-                for item in cons.iter() {
-                    match item {
-                        Ok(term) => {
-                            if let Err(_) = unsafe { builder.push_unsafe(term) } {
-                                let mut roots = RootSet::default();
-                                roots += &mut list as *mut _;
-                                roots += &mut tail as *mut _;
-                                assert!(garbage_collect(process, roots).is_ok());
-                                continue 'retry;
-                            }
-                        }
-                        Err(_improper) => {
-                            // Reset the heap as we aren't going to use the cells we've allocated
-                            unsafe {
-                                process.reset_heap_top(heap_top);
-                            }
-                            badarg!(process, list);
-                        }
-                    }
-                }
-                // We are guaranteed to have a cons cell here
-                return ErlangResult::Ok(builder.finish().unwrap().into());
+    let mut heap_top = process.heap_top();
+    let mut reversed = 0;
+    while let Term::Cons(cons) = list.into() {
+        match Cons::new_in(Cons { head: cons.head, tail }, process) {
+            Ok(cell) => {
+                tail = cell.into();
+                list = cons.tail;
+            }
+            Err(_) => {
+                let mut roots = RootSet::default();
+                roots += &mut list as *mut _;
+                roots += &mut tail as *mut _;
+                assert!(garbage_collect(process, roots).is_ok());
+                heap_top = process.heap_top();
+                continue;
+            }
+        }
+
+        reversed += 1;
+        if reversed % ELEMENTS_PER_REDUCTION == 0 {
+            process.bump_reductions(1);
+            if process.should_yield() && list.is_nonempty_list() {
+                process.stack.store(ARG0_REG, list);
+                process.stack.store(ARG0_REG + 1, tail);
+                return ErlangResult::Trap(&REVERSE_TRAP_EXPORT);
             }
         }
-        _other => badarg!(process, list),
     }
+
+    if !list.is_nil() {
+        // Reset the heap as we aren't going to use the cells we've allocated
+        unsafe {
+            process.reset_heap_top(heap_top);
+        }
+        badarg!(process, list);
+    }
+
+    ErlangResult::Ok(tail)
 }
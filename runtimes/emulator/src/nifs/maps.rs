@@ -0,0 +1,60 @@
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, RootSet};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+macro_rules! badmap {
+    ($process:expr, $term:expr) => {
+        return {
+            $process.exception_info.flags = ExceptionFlags::ERROR;
+            $process.exception_info.reason = atoms::Badmap.into();
+            $process.exception_info.value = $term;
+            $process.exception_info.args = None;
+            $process.exception_info.trace = None;
+            $process.exception_info.cause = None;
+            ErlangResult::Err
+        }
+    };
+}
+
+/// Merges `map2` into `map1`, preferring the values of `map2` for keys present in both
+///
+/// See `SmallMap::merge` for the cases in which no new map is allocated, e.g. when merging a
+/// map of overrides which doesn't change anything.
+#[export_name = "maps:merge/2"]
+pub extern "C-unwind" fn merge(
+    process: &mut ProcessLock,
+    mut map1: OpaqueTerm,
+    mut map2: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Map(m1) = map1.into() else { badmap!(process, map1); };
+    let Term::Map(m2) = map2.into() else { badmap!(process, map2); };
+
+    // Make room for the largest map the union could be
+    let mut layout = LayoutBuilder::new();
+    layout.build_map((m1.size() + m2.size()).min(SMALL_MAP_LIMIT));
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        let mut roots = RootSet::default();
+        roots += &mut map1 as *mut OpaqueTerm;
+        roots += &mut map2 as *mut OpaqueTerm;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let Term::Map(m1) = map1.into() else { unreachable!() };
+    let Term::Map(m2) = map2.into() else { unreachable!() };
+    match m1.merge(&m2, process) {
+        Ok(merged) => ErlangResult::Ok(merged.into()),
+        Err(MapError::SizeLimit) => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::SystemLimit.into();
+            process.exception_info.value = atoms::SystemLimit.into();
+            process.exception_info.args = None;
+            process.exception_info.trace = None;
+            process.exception_info.cause = None;
+            ErlangResult::Err
+        }
+        Err(err) => panic!("unexpected error merging maps: {:?}", err),
+    }
+}
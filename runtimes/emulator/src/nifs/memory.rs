@@ -0,0 +1,57 @@
+//! The `firefly_memory` module, which finds the largest consumers of memory on the node, see
+//! `firefly_rt::process::memory_top`.
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::{memory_top, MemoryCategory, ProcessLock};
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Returns `[{Pid, Bytes}]` for the `N` processes using the most memory of `Category`, largest
+/// first, where `Category` is one of:
+///
+/// * `heap`, the heap, heap fragments, and stack of each process
+/// * `binary`, the reference-counted binaries referenced by each process
+///
+/// The usage of each process is as of its last garbage collection, so no process is interrupted.
+#[export_name = "firefly_memory:top/2"]
+pub extern "C-unwind" fn top2(
+    process: &mut ProcessLock,
+    category: OpaqueTerm,
+    n: OpaqueTerm,
+) -> ErlangResult {
+    let kind = match category.into() {
+        Term::Atom(a) if a == atoms::Heap => MemoryCategory::Heap,
+        Term::Atom(a) if a == atoms::Binary => MemoryCategory::Binary,
+        _ => badarg!(process, category),
+    };
+    let n = match n.into() {
+        Term::Int(n) if n >= 0 => n as usize,
+        _ => badarg!(process, n),
+    };
+
+    let top = memory_top(kind, n);
+
+    let mut layout = LayoutBuilder::new();
+    for _ in top.iter() {
+        layout.build_tuple(2);
+        layout.build_pid();
+    }
+    layout.build_list(top.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    // Lists are constructed back to front
+    let mut tail = OpaqueTerm::NIL;
+    for (consumer, used) in top.iter().rev() {
+        let pid = Gc::new_in(consumer.pid(), process).unwrap().into();
+        let used = Term::try_from(*used).unwrap().into();
+        let item = Tuple::from_slice(&[pid, used], process).unwrap();
+        let head = Term::Tuple(item).into();
+        tail = Cons::new_in(Cons { head, tail }, process).unwrap().into();
+    }
+    ErlangResult::Ok(tail)
+}
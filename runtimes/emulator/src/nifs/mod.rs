@@ -1,3 +1,17 @@
+pub mod clock;
+pub mod crypto;
+pub mod erts_debug;
+pub mod ets;
 pub mod file;
+pub mod inet;
+pub mod lcnt;
 pub mod lists;
+pub mod maps;
+pub mod memory;
+pub mod net_kernel;
+pub mod persistent_term;
+pub mod profile;
+pub mod recon;
+pub mod replay;
+pub mod send;
 pub mod unicode;
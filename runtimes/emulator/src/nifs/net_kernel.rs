@@ -0,0 +1,213 @@
+//! The native functions of the `net_kernel` module
+//!
+//! Distribution can be started at any time, not only at boot, in which case the current node goes
+//! from `nonode@nohost` to a named node with a freshly allocated creation, see
+//! `firefly_rt::services::distribution::next_creation`.
+//!
+//! Connections are hidden if either side of them is a hidden node, and their state, type and the
+//! capabilities negotiated for them can be inspected with `node_info/2`.
+//!
+//! NOTE: There is no `net_sup` supervision tree, so where `net_kernel` would return the pid of
+//! its supervisor, `undefined` is returned instead.
+use std::ffi::CStr;
+use std::net::IpAddr;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::distribution::{self, NodeStatus};
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Whether the host part of a node name is a short hostname, or a fully qualified one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum NameDomain {
+    Short,
+    Long,
+}
+
+/// Starts distribution with the current node named `name`, returning `{ok, undefined}`
+///
+/// If `name` has no host part, the hostname of this machine is appended to it, which is either
+/// truncated to the short hostname, or must be fully qualified, depending on the `name_domain`
+/// option. The tick options are validated, but have no effect yet.
+#[export_name = "net_kernel:start/2"]
+pub extern "C-unwind" fn start2(
+    process: &mut ProcessLock,
+    name: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Atom(node) = name.into() else { badarg!(process, name); };
+    let Term::Map(opts) = options.into() else { badarg!(process, options); };
+    let mut domain = NameDomain::Long;
+    for (key, value) in opts.iter() {
+        let Term::Atom(key) = key else { badarg!(process, options); };
+        let valid = match (key, value) {
+            (k, Term::Atom(v)) if k == atoms::NameDomain && v == atoms::Shortnames => {
+                domain = NameDomain::Short;
+                true
+            }
+            (k, Term::Atom(v)) if k == atoms::NameDomain && v == atoms::Longnames => {
+                domain = NameDomain::Long;
+                true
+            }
+            (k, Term::Int(ticktime)) if k == atoms::NetTicktime => ticktime > 0,
+            (k, Term::Int(intensity)) if k == atoms::NetTickintensity => {
+                (4..=1000).contains(&intensity)
+            }
+            (k, Term::Bool(_)) if k == atoms::DistListen || k == atoms::Hidden => true,
+            _ => false,
+        };
+        if !valid {
+            badarg!(process, options);
+        }
+    }
+
+    if distribution::is_started() {
+        return already_started(process);
+    }
+
+    let node = match node.as_str().split_once('@') {
+        Some((_, host)) => {
+            if !is_host_in_domain(host, domain) {
+                badarg!(process, name);
+            }
+            node.as_str().to_string()
+        }
+        None => match hostname(domain) {
+            Some(host) => format!("{}@{}", node.as_str(), host),
+            None => return error(process, atoms::InvalidHostname),
+        },
+    };
+    if !distribution::is_node_name(&node) {
+        badarg!(process, name);
+    }
+    let Ok(node) = Atom::try_from(node.as_str()) else { badarg!(process, name); };
+
+    match distribution::start(node, distribution::next_creation()) {
+        Ok(_) => ok(process, atoms::Undefined.into()),
+        // Another process started distribution first
+        Err(_) => already_started(process),
+    }
+}
+
+/// Stops distribution, returning the current node to `nonode@nohost`
+///
+/// Returns `{error, not_found}` if distribution is not started.
+#[export_name = "net_kernel:stop/0"]
+pub extern "C-unwind" fn stop0(process: &mut ProcessLock) -> ErlangResult {
+    match distribution::stop() {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(_) => error(process, atoms::NotFound),
+    }
+}
+
+/// Returns `{ok, Value}` for `item` of the connection to `node`, or `{error, bogus}` if we are not
+/// connected to it
+///
+/// The supported items are `state`, which is `up` or `pending`, `type`, which is `visible` or
+/// `hidden`, and `dist_flags`, the capabilities negotiated with the node as an integer, see
+/// `firefly_rt::services::distribution::DistFlags`.
+#[export_name = "net_kernel:node_info/2"]
+pub extern "C-unwind" fn node_info2(
+    process: &mut ProcessLock,
+    node: OpaqueTerm,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Atom(name) = node.into() else { badarg!(process, node); };
+    let Term::Atom(key) = item.into() else { badarg!(process, item); };
+    let current = distribution::current_node();
+    let connection = distribution::list()
+        .into_iter()
+        .find(|n| n.name() == name && n.id() != current.id())
+        .and_then(|n| n.connection().cloned());
+    let Some(connection) = connection else { return error(process, atoms::Bogus); };
+    let status = connection.status();
+    if status == NodeStatus::Disconnected {
+        return error(process, atoms::Bogus);
+    }
+    let value: OpaqueTerm = if key == atoms::State {
+        match status {
+            NodeStatus::Pending => atoms::Pending.into(),
+            _ => atoms::Up.into(),
+        }
+    } else if key == atoms::Type {
+        match status {
+            NodeStatus::Hidden => atoms::Hidden.into(),
+            _ => atoms::Visible.into(),
+        }
+    } else if key == atoms::DistFlags {
+        Term::Int(connection.flags().bits() as i64).into()
+    } else {
+        return error(process, atoms::InvalidKey);
+    };
+    ok(process, value)
+}
+
+/// Short names must not be qualified, while long names must be, unless they are IP addresses
+fn is_host_in_domain(host: &str, domain: NameDomain) -> bool {
+    match domain {
+        NameDomain::Short => !host.contains('.'),
+        NameDomain::Long => host.contains('.') || host.parse::<IpAddr>().is_ok(),
+    }
+}
+
+/// Returns the hostname of this machine in the form required by `domain`, if possible
+fn hostname(domain: NameDomain) -> Option<String> {
+    let mut buf = [0u8; 256];
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if result != 0 {
+        return None;
+    }
+    let host = CStr::from_bytes_until_nul(&buf).ok()?.to_str().ok()?;
+    let host = match domain {
+        NameDomain::Short => host.split('.').next().unwrap_or(host),
+        NameDomain::Long => host,
+    };
+    if host.is_empty() || !is_host_in_domain(host, domain) {
+        return None;
+    }
+    Some(host.to_string())
+}
+
+/// Returns `{ok, Value}`, where `value` is an immediate
+fn ok(process: &mut ProcessLock, value: OpaqueTerm) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let result = Tuple::from_slice(&[atoms::Ok.into(), value], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
+
+/// Returns `{error, Reason}`
+fn error(process: &mut ProcessLock, reason: Atom) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let result = Tuple::from_slice(&[atoms::Error.into(), reason.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
+
+/// Returns `{error, {already_started, undefined}}`
+fn already_started(process: &mut ProcessLock) -> ErlangResult {
+    let mut layout = LayoutBuilder::new();
+    layout.build_tuple(2).build_tuple(2);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let reason: [OpaqueTerm; 2] = [atoms::AlreadyStarted.into(), atoms::Undefined.into()];
+    let reason = Tuple::from_slice(&reason, process).unwrap();
+    let result = Tuple::from_slice(&[atoms::Error.into(), reason.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
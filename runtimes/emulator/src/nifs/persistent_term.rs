@@ -0,0 +1,85 @@
+//! The `persistent_term` module, see `firefly_rt::services::persistent_term`
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::persistent_term;
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Stores `value` under `key`, replacing any previous value
+#[export_name = "persistent_term:put/2"]
+pub extern "C-unwind" fn put(
+    process: &mut ProcessLock,
+    key: OpaqueTerm,
+    value: OpaqueTerm,
+) -> ErlangResult {
+    match persistent_term::put(&key.into(), &value.into()) {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(_) => {
+            process.exception_info.flags = ExceptionFlags::ERROR;
+            process.exception_info.reason = atoms::SystemLimit.into();
+            process.exception_info.value = atoms::SystemLimit.into();
+            process.exception_info.args = Some(value);
+            process.exception_info.trace = None;
+            ErlangResult::Err
+        }
+    }
+}
+
+/// Returns the value stored under `key`, raising `badarg` if there is none
+#[export_name = "persistent_term:get/1"]
+pub extern "C-unwind" fn get1(process: &mut ProcessLock, key: OpaqueTerm) -> ErlangResult {
+    match get(key) {
+        Some(value) => ErlangResult::Ok(value),
+        None => badarg!(process, key),
+    }
+}
+
+/// Returns the value stored under `key`, or `default` if there is none
+#[export_name = "persistent_term:get/2"]
+pub extern "C-unwind" fn get2(
+    _process: &mut ProcessLock,
+    key: OpaqueTerm,
+    default: OpaqueTerm,
+) -> ErlangResult {
+    ErlangResult::Ok(get(key).unwrap_or(default))
+}
+
+/// Erases the value stored under `key`, returning false if there was none
+#[export_name = "persistent_term:erase/1"]
+pub extern "C-unwind" fn erase(_process: &mut ProcessLock, key: OpaqueTerm) -> ErlangResult {
+    ErlangResult::Ok(persistent_term::erase(&key.into()).into())
+}
+
+/// Returns `#{count => Count, memory => Bytes}` for the persistent terms currently stored
+#[export_name = "persistent_term:info/0"]
+pub extern "C-unwind" fn info(process: &mut ProcessLock) -> ErlangResult {
+    let info = persistent_term::info();
+    let count: OpaqueTerm = Term::try_from(info.count).unwrap().into();
+    let memory: OpaqueTerm = Term::try_from(info.memory).unwrap().into();
+
+    let layout = {
+        let mut builder = LayoutBuilder::new();
+        builder.build_map(2);
+        builder.finish()
+    };
+    if layout.size() > process.heap_available() {
+        process.gc_needed = layout.size();
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let map = Map::from_iter(
+        [(atoms::Count.into(), count), (atoms::Memory.into(), memory)].into_iter(),
+        process,
+    )
+    .unwrap();
+    ErlangResult::Ok(map.into())
+}
+
+fn get(key: OpaqueTerm) -> Option<OpaqueTerm> {
+    let value = persistent_term::get(&key.into())?;
+    // Reference-counted values must be given their own reference
+    let value: Term = value.into();
+    Some(value.into())
+}
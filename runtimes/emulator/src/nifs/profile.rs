@@ -0,0 +1,66 @@
+//! The `firefly_profile` module, which controls the sampling CPU profiler, see
+//! `crate::emulator::profile`.
+use std::time::Duration;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+use crate::emulator::profile::{self, ProfileError};
+
+/// Starts sampling every 10 milliseconds, see `start/1`
+#[export_name = "firefly_profile:start/0"]
+pub extern "C-unwind" fn start0(process: &mut ProcessLock) -> ErlangResult {
+    start(process, profile::DEFAULT_INTERVAL)
+}
+
+/// Starts sampling the processes executing on each scheduler every `interval` milliseconds,
+/// discarding the samples of any previous run
+///
+/// Returns `{error, already_started}` if the profiler is already running.
+#[export_name = "firefly_profile:start/1"]
+pub extern "C-unwind" fn start1(process: &mut ProcessLock, interval: OpaqueTerm) -> ErlangResult {
+    match interval.into() {
+        Term::Int(ms) if ms > 0 => start(process, Duration::from_millis(ms as u64)),
+        _ => badarg!(process, interval),
+    }
+}
+
+fn start(process: &mut ProcessLock, interval: Duration) -> ErlangResult {
+    match profile::start(interval) {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(err) => error(process, err),
+    }
+}
+
+/// Stops sampling, the samples taken remain available to `dump/0`
+///
+/// Returns `{error, not_started}` if the profiler is not running.
+#[export_name = "firefly_profile:stop/0"]
+pub extern "C-unwind" fn stop(process: &mut ProcessLock) -> ErlangResult {
+    match profile::stop() {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(err) => error(process, err),
+    }
+}
+
+/// Returns the samples taken by the last run of the profiler as a binary of folded stacks, in the
+/// format consumed by `flamegraph.pl`
+///
+/// This may be called while the profiler is running.
+#[export_name = "firefly_profile:dump/0"]
+pub extern "C-unwind" fn dump(_process: &mut ProcessLock) -> ErlangResult {
+    let folded = current_scheduler().profile_folded_stacks();
+    ErlangResult::Ok(BinaryData::from_str(&folded).into())
+}
+
+fn error(process: &mut ProcessLock, err: ProfileError) -> ErlangResult {
+    let reason = match err {
+        ProfileError::AlreadyStarted => atoms::AlreadyStarted,
+        ProfileError::NotStarted => atoms::NotStarted,
+    };
+    let result = Tuple::from_slice(&[atoms::Error.into(), reason.into()], process).unwrap();
+    ErlangResult::Ok(result.into())
+}
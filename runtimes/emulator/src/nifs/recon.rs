@@ -0,0 +1,284 @@
+//! The `firefly_recon` module, diagnostics for finding the processes and ports responsible for the
+//! load on a running node, modeled after the `recon` library.
+//!
+//! The attributes processes are ranked by are read from figures each process publishes for itself
+//! (see `Process::reductions` and `Process::memory`), so ranking the processes on a node does not
+//! interrupt any of them. The exception is `bin_leak/1`, which garbage collects every process.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::{Process, ProcessLock, ARG0_REG};
+use firefly_rt::services::registry;
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// The attributes processes can be ranked by
+#[derive(Copy, Clone)]
+enum Attribute {
+    /// The total number of reductions executed, as of the end of the last time slice
+    Reductions,
+    /// The number of signals waiting in the signal queue, most of which are usually messages
+    MessageQueueLen,
+    /// The size of the heap and stack, as of the last garbage collection
+    Memory,
+    /// The size of the reference-counted binaries referenced, as of the last garbage collection
+    BinaryMemory,
+}
+impl Attribute {
+    fn from_term(term: OpaqueTerm) -> Option<Self> {
+        match term.into() {
+            Term::Atom(a) if a == atoms::Reductions => Some(Self::Reductions),
+            Term::Atom(a) if a == atoms::MessageQueueLen => Some(Self::MessageQueueLen),
+            Term::Atom(a) if a == atoms::Memory => Some(Self::Memory),
+            Term::Atom(a) if a == atoms::BinaryMemory => Some(Self::BinaryMemory),
+            _ => None,
+        }
+    }
+
+    fn get(self, process: &Process) -> usize {
+        match self {
+            Self::Reductions => process.reductions() as usize,
+            // This briefly takes the signal queue lock, which senders to the process contend for
+            // anyway, but never the main lock of the process
+            Self::MessageQueueLen => process.signals().lock().len(),
+            Self::Memory => process.memory().heap(),
+            Self::BinaryMemory => process.memory().binary(),
+        }
+    }
+}
+
+/// Returns `[{Pid, Value, Info}]` for the `N` processes with the largest value of `Attribute`,
+/// largest first, where `Attribute` is one of `reductions`, `message_queue_len`, `memory` or
+/// `binary_memory`, and `Info` is as returned by `info/1`
+///
+/// Since reductions accumulate over the lifetime of a process, `proc_window/3` is usually more
+/// useful for finding the processes which are busy right now.
+#[export_name = "firefly_recon:proc_count/2"]
+pub extern "C-unwind" fn proc_count2(
+    process: &mut ProcessLock,
+    attribute: OpaqueTerm,
+    n: OpaqueTerm,
+) -> ErlangResult {
+    let Some(attr) = Attribute::from_term(attribute) else { badarg!(process, attribute); };
+    let n = match n.into() {
+        Term::Int(n) if n >= 0 => n as usize,
+        _ => badarg!(process, n),
+    };
+
+    let top = registry::top_processes(n, |p| attr.get(p));
+
+    let mut layout = LayoutBuilder::new();
+    for _ in top.iter() {
+        layout.build_tuple(3);
+        layout.build_pid();
+        build_info(&mut layout);
+    }
+    layout.build_list(top.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    // Lists are constructed back to front
+    let mut tail = OpaqueTerm::NIL;
+    for (leader, value) in top.iter().rev() {
+        let pid = Gc::new_in(leader.pid(), process).unwrap().into();
+        let value = Term::try_from(*value).unwrap().into();
+        let info = make_info(leader, process);
+        let item = Tuple::from_slice(&[pid, value, info], process).unwrap();
+        let head = Term::Tuple(item).into();
+        tail = Cons::new_in(Cons { head, tail }, process).unwrap().into();
+    }
+    ErlangResult::Ok(tail)
+}
+
+/// Returns `[{Pid, Value}]` for every process on the node, where `Value` is the current value of
+/// `Attribute` for that process, see `proc_count/2`
+#[export_name = "firefly_recon:sample/1"]
+pub extern "C-unwind" fn sample1(process: &mut ProcessLock, attribute: OpaqueTerm) -> ErlangResult {
+    let Some(attr) = Attribute::from_term(attribute) else { badarg!(process, attribute); };
+
+    let samples = registry::processes()
+        .into_iter()
+        .map(|p| {
+            let value = attr.get(&p);
+            (p, value)
+        })
+        .collect::<Vec<_>>();
+
+    let mut layout = LayoutBuilder::new();
+    for _ in samples.iter() {
+        layout.build_tuple(2);
+        layout.build_pid();
+    }
+    layout.build_list(samples.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let mut tail = OpaqueTerm::NIL;
+    for (sampled, value) in samples.iter().rev() {
+        let pid = Gc::new_in(sampled.pid(), process).unwrap().into();
+        let value = Term::try_from(*value).unwrap().into();
+        let item = Tuple::from_slice(&[pid, value], process).unwrap();
+        let head = Term::Tuple(item).into();
+        tail = Cons::new_in(Cons { head, tail }, process).unwrap().into();
+    }
+    ErlangResult::Ok(tail)
+}
+
+/// Returns the information used to identify `Pid` in the results of this module, i.e. a list of
+/// its registered name, if it has one, and `{initial_call, {M, F, A}}`
+///
+/// Returns `undefined` if `Pid` is not alive.
+#[export_name = "firefly_recon:info/1"]
+pub extern "C-unwind" fn info1(process: &mut ProcessLock, pid_term: OpaqueTerm) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term); };
+    if !pid.is_local() {
+        badarg!(process, pid_term);
+    }
+    let Some(target) = registry::get_by_pid(&pid) else {
+        return ErlangResult::Ok(atoms::Undefined.into());
+    };
+
+    let mut layout = LayoutBuilder::new();
+    build_info(&mut layout);
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    ErlangResult::Ok(make_info(&target, process))
+}
+
+/// Returns `[{Pid, Delta, Info}]` for the `N` processes for which `Attribute` grew the most over
+/// the next `Milliseconds`, largest first, see `proc_count/2`
+///
+/// The calling process waits for the duration of the window.
+#[export_name = "firefly_recon:proc_window/3"]
+pub extern "C-unwind" fn proc_window3(
+    process: &mut ProcessLock,
+    attribute: OpaqueTerm,
+    n: OpaqueTerm,
+    ms: OpaqueTerm,
+) -> ErlangResult {
+    static PROC_WINDOW_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::ReconProcWindow,
+        arity: 3,
+    };
+
+    if Attribute::from_term(attribute).is_none() {
+        badarg!(process, attribute);
+    }
+    if !matches!(n.into(), Term::Int(n) if n >= 0) {
+        badarg!(process, n);
+    }
+    if !matches!(ms.into(), Term::Int(ms) if ms >= 0) {
+        badarg!(process, ms);
+    }
+
+    process.stack.store(ARG0_REG, attribute);
+    process.stack.store(ARG0_REG + 1, n);
+    process.stack.store(ARG0_REG + 2, ms);
+    ErlangResult::Trap(&PROC_WINDOW_TRAP_EXPORT)
+}
+
+/// Garbage collects every process on the node, and returns `[{Pid, Delta, Info}]` for the `N`
+/// processes which released the most binary memory by doing so, most first
+///
+/// A process which releases a lot of binary memory when collected is holding on to references
+/// to binaries it no longer uses, which is the usual cause of binaries leaking.
+#[export_name = "firefly_recon:bin_leak/1"]
+pub extern "C-unwind" fn bin_leak1(process: &mut ProcessLock, n: OpaqueTerm) -> ErlangResult {
+    static BIN_LEAK_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::ReconBinLeak,
+        arity: 1,
+    };
+
+    if !matches!(n.into(), Term::Int(n) if n >= 0) {
+        badarg!(process, n);
+    }
+
+    process.stack.store(ARG0_REG, n);
+    ErlangResult::Trap(&BIN_LEAK_TRAP_EXPORT)
+}
+
+/// Returns `[{Name, Count}]` for each command ports have been opened with, where `Count` is the
+/// number of open ports, most common first
+#[export_name = "firefly_recon:port_types/0"]
+pub extern "C-unwind" fn port_types0(process: &mut ProcessLock) -> ErlangResult {
+    let ports = registry::ports();
+    let mut counts = HashMap::<&str, usize>::new();
+    for port in ports.iter() {
+        if let Some(name) = port.name() {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+
+    let mut layout = LayoutBuilder::new();
+    for (name, _) in counts.iter() {
+        layout.build_tuple(2);
+        layout.build_list(name.chars().count());
+    }
+    layout.build_list(counts.len());
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+
+    let mut tail = OpaqueTerm::NIL;
+    for (name, count) in counts.iter().rev() {
+        let name = Cons::charlist_from_str(name, process)
+            .unwrap()
+            .map(Term::Cons)
+            .unwrap_or(Term::Nil)
+            .into();
+        let count = Term::try_from(*count).unwrap().into();
+        let item = Tuple::from_slice(&[name, count], process).unwrap();
+        let head = Term::Tuple(item).into();
+        tail = Cons::new_in(Cons { head, tail }, process).unwrap().into();
+    }
+    ErlangResult::Ok(tail)
+}
+
+/// Adds the space needed by the term constructed by `make_info` to `layout`
+fn build_info(layout: &mut LayoutBuilder) {
+    layout.build_list(2);
+    layout.build_tuple(2);
+    layout.build_tuple(3);
+}
+
+fn make_info(target: &Arc<Process>, process: &mut ProcessLock) -> OpaqueTerm {
+    let mfa = &target.initial_call;
+    let mfa = Tuple::from_slice(
+        &[
+            mfa.module.into(),
+            mfa.function.into(),
+            Term::Int(mfa.arity as i64).into(),
+        ],
+        process,
+    )
+    .unwrap();
+    let initial_call =
+        Tuple::from_slice(&[atoms::InitialCall.into(), mfa.into()], process).unwrap();
+    let tail = OpaqueTerm::NIL;
+    let head = Term::Tuple(initial_call).into();
+    let mut info = Cons::new_in(Cons { head, tail }, process).unwrap().into();
+    if let Some(name) = target.registered_name() {
+        let head = name.into();
+        info = Cons::new_in(Cons { head, tail: info }, process).unwrap().into();
+    }
+    info
+}
@@ -0,0 +1,122 @@
+//! The `firefly_replay` module, which records the messages received by a process, and replays
+//! them into another process, see `crate::replay`.
+use std::io;
+use std::path::PathBuf;
+
+use firefly_rt::etf::Decoder;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::registry::{self, WeakAddress};
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::replay::{self, Record, RecordMode};
+
+/// Starts recording the messages received by `pid` to `file`, in `hash` or `full` mode
+///
+/// Returns `ok`, or `{error, Reason}` if the recording file could not be created.
+#[export_name = "firefly_replay:record/3"]
+pub extern "C-unwind" fn record(
+    process: &mut ProcessLock,
+    pid: OpaqueTerm,
+    file: OpaqueTerm,
+    mode: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(id) = pid.into() else { badarg!(process, pid); };
+    if !id.is_local() || registry::get_by_pid(&id).is_none() {
+        badarg!(process, pid);
+    }
+    let Ok(path) = file_path(file) else { badarg!(process, file); };
+    let mode = match mode.into() {
+        Term::Atom(a) if a == atoms::Hash => RecordMode::Hash,
+        Term::Atom(a) if a == atoms::Full => RecordMode::Full,
+        _ => badarg!(process, mode),
+    };
+    match replay::start(id.id(), &path, mode) {
+        Ok(_) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(err) => error_tuple(process, io_error_reason(&err)),
+    }
+}
+
+/// Stops recording the messages received by `pid`, returning false if it was not being recorded
+#[export_name = "firefly_replay:stop/1"]
+pub extern "C-unwind" fn stop(process: &mut ProcessLock, pid: OpaqueTerm) -> ErlangResult {
+    let Term::Pid(id) = pid.into() else { badarg!(process, pid); };
+    ErlangResult::Ok(replay::stop(id.id()).into())
+}
+
+/// Sends the messages recorded in `file` to `pid`, in the order they were received
+///
+/// Returns `{ok, Count}`, or `{error, Reason}` if the recording could not be read. Recordings
+/// made in `hash` mode cannot be replayed, and return `{error, not_replayable}`.
+#[export_name = "firefly_replay:replay/2"]
+pub extern "C-unwind" fn replay(
+    process: &mut ProcessLock,
+    file: OpaqueTerm,
+    pid: OpaqueTerm,
+) -> ErlangResult {
+    let Ok(path) = file_path(file) else { badarg!(process, file); };
+    let Term::Pid(id) = pid.into() else { badarg!(process, pid); };
+    let Some(target) = registry::get_by_pid(&id) else { badarg!(process, pid); };
+
+    let records = match replay::read(&path) {
+        Ok((RecordMode::Full, records)) => records,
+        Ok((RecordMode::Hash, _)) => return error_tuple(process, atoms::NotReplayable),
+        Err(err) => return error_tuple(process, io_error_reason(&err)),
+    };
+    // Decode everything up front, so that a corrupt recording doesn't result in a partial replay
+    let mut fragments = Vec::with_capacity(records.len());
+    for record in records.iter() {
+        let Record::Full(bytes) = record else { unreachable!(); };
+        match Decoder::new(bytes).and_then(|decoder| decoder.decode_fragment()) {
+            Ok(fragment) => fragments.push(fragment),
+            Err(_) => return error_tuple(process, atoms::Einval),
+        }
+    }
+    let count = fragments.len();
+    let sender: WeakAddress = process.pid().into();
+    for fragment in fragments.drain(..) {
+        let result = target.clone().send_fragment(sender.clone(), fragment);
+        if result.is_err() {
+            // The target is exiting
+            break;
+        }
+    }
+
+    let count = Term::try_from(count).unwrap().into();
+    tagged_tuple(process, atoms::Ok, count)
+}
+
+fn file_path(file: OpaqueTerm) -> Result<PathBuf, ()> {
+    let file: Term = file.into();
+    String::try_from(file).map(PathBuf::from)
+}
+
+fn error_tuple(process: &mut ProcessLock, reason: Atom) -> ErlangResult {
+    tagged_tuple(process, atoms::Error, reason.into())
+}
+
+/// Builds `{Tag, Value}`, where `Value` must be an immediate
+fn tagged_tuple(process: &mut ProcessLock, tag: Atom, value: OpaqueTerm) -> ErlangResult {
+    let layout = {
+        let mut builder = LayoutBuilder::new();
+        builder.build_tuple(2);
+        builder.finish()
+    };
+    if layout.size() > process.heap_available() {
+        process.gc_needed = layout.size();
+        assert!(garbage_collect(process, Default::default()).is_ok());
+    }
+    let tuple = Tuple::from_slice(&[tag.into(), value], process).unwrap();
+    ErlangResult::Ok(tuple.into())
+}
+
+pub(crate) fn io_error_reason(err: &io::Error) -> Atom {
+    match err.kind() {
+        io::ErrorKind::NotFound => atoms::Enoent,
+        io::ErrorKind::PermissionDenied => atoms::Eacces,
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => atoms::Einval,
+        _ => atoms::Eio,
+    }
+}
@@ -0,0 +1,113 @@
+//! The `firefly_send` module, for delivering many messages to one process at once, see
+//! `firefly_rt::process::ProcessHandle::send_batch`, and one message to many processes at once.
+use std::ops::Deref;
+use std::sync::Arc;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::services::distribution::{self, EncodedMessage};
+use firefly_rt::services::registry::{self, Registrant};
+use firefly_rt::term::*;
+
+use crate::badarg;
+
+/// Sends each element of `Messages` to `Dest`, a pid or registered name, in order, and returns
+/// the number of messages sent
+///
+/// This behaves like sending each message with `!`, except that the queue of a local recipient is
+/// locked once for the whole batch, which makes delivering bursts of small messages, e.g.
+/// telemetry events, considerably cheaper. As with `!`, messages to a pid which is not alive are
+/// silently dropped, in which case this returns `0`, while a name which is not registered is an
+/// error. The messages sent are not traced.
+#[export_name = "firefly_send:batch/2"]
+pub extern "C-unwind" fn batch2(
+    process: &mut ProcessLock,
+    dest: OpaqueTerm,
+    messages: OpaqueTerm,
+) -> ErlangResult {
+    let (remote, recipient) = match dest.into() {
+        Term::Pid(pid) if !pid.is_local() => (Some(pid.deref().clone()), None),
+        Term::Pid(pid) => (None, registry::get_by_pid(&pid)),
+        Term::Atom(name) => match registry::get_by_name(name) {
+            Some(Registrant::Process(recipient)) => (None, Some(recipient)),
+            _ => badarg!(process, dest),
+        },
+        _ => badarg!(process, dest),
+    };
+
+    let mut fragments = Vec::new();
+    match messages.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for item in cons.iter() {
+                let Ok(message) = item else { badarg!(process, messages); };
+                fragments.push(TermFragment::copy_from(&message, CopyMode::Flat).unwrap());
+            }
+        }
+        _ => badarg!(process, messages),
+    }
+
+    let sent = match (remote, recipient) {
+        (Some(pid), _) => {
+            let count = fragments.len();
+            for message in fragments {
+                distribution::send(process.pid(), pid.clone(), message).ok();
+            }
+            count
+        }
+        (None, Some(recipient)) => recipient
+            .send_fragments(process.pid().into(), fragments)
+            .unwrap_or(0),
+        (None, None) => 0,
+    };
+    ErlangResult::Ok(Term::try_from(sent).unwrap().into())
+}
+
+/// Sends `Message` to each pid in `Dests`, and returns the number of processes it was sent to
+///
+/// The message is encoded once for all of the remote processes in `Dests`, however many nodes they
+/// are on, rather than once per process as when sending with `!`, which makes this well suited to
+/// fanning out events to subscribers in other clusters. Local processes receive a copy as usual.
+/// As with `!`, messages to local processes which are not alive are silently dropped, and are not
+/// counted, while remote processes are counted once the message is handed off to distribution.
+/// The messages sent are not traced.
+#[export_name = "firefly_send:multicast/2"]
+pub extern "C-unwind" fn multicast2(
+    process: &mut ProcessLock,
+    dests: OpaqueTerm,
+    message: OpaqueTerm,
+) -> ErlangResult {
+    let mut pids = Vec::new();
+    match dests.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for item in cons.iter() {
+                let Ok(Term::Pid(pid)) = item else { badarg!(process, dests); };
+                pids.push(pid);
+            }
+        }
+        _ => badarg!(process, dests),
+    }
+
+    let term: Term = message.into();
+    let mut encoded = None;
+    let mut sent = 0usize;
+    for pid in pids {
+        if pid.is_local() {
+            let Some(recipient) = registry::get_by_pid(&pid) else { continue; };
+            if recipient.send(process.pid().into(), term.clone()).is_ok() {
+                sent += 1;
+            }
+            continue;
+        }
+        if encoded.is_none() {
+            let Ok(message) = EncodedMessage::new(term.clone()) else { badarg!(process, message); };
+            encoded = Some(Arc::new(message));
+        }
+        let message = encoded.clone().unwrap();
+        if distribution::send_encoded(process.pid(), pid.deref().clone(), message).is_ok() {
+            sent += 1;
+        }
+    }
+    ErlangResult::Ok(Term::try_from(sent).unwrap().into())
+}
@@ -1,91 +1,446 @@
-use std::collections::VecDeque;
+use std::str;
 
-use firefly_binary::{BitVec, Bitstring, Encoding};
+use firefly_binary::Bitstring;
 use firefly_rt::function::ErlangResult;
-use firefly_rt::gc::garbage_collect;
+use firefly_rt::gc::{garbage_collect, RootSet};
 use firefly_rt::process::ProcessLock;
 use firefly_rt::term::*;
 
 use crate::badarg;
 
+#[export_name = "unicode:characters_to_list/1"]
+pub extern "C-unwind" fn characters_to_list1(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    characters_to(process, data, CharEncoding::Utf8, None)
+}
+
 #[export_name = "unicode:characters_to_list/2"]
 pub extern "C-unwind" fn characters_to_list(
     process: &mut ProcessLock,
     data: OpaqueTerm,
     encoding: OpaqueTerm,
 ) -> ErlangResult {
-    let Term::Atom(e) = encoding.into() else { badarg!(process, encoding) };
-    let Ok(encoding) = e.as_str().parse::<Encoding>() else { badarg!(process, encoding) };
+    let Some(encoding) = CharEncoding::from_term(encoding) else { badarg!(process, encoding) };
+    characters_to(process, data, encoding, None)
+}
 
-    // Convert the input iodata into a binary
-    let mut buffer = BitVec::new();
+#[export_name = "unicode:characters_to_binary/1"]
+pub extern "C-unwind" fn characters_to_binary1(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    characters_to(process, data, CharEncoding::Utf8, Some(CharEncoding::Utf8))
+}
 
-    let latin1 = encoding == Encoding::Latin1 || encoding == Encoding::Raw;
+#[export_name = "unicode:characters_to_binary/2"]
+pub extern "C-unwind" fn characters_to_binary2(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+    encoding: OpaqueTerm,
+) -> ErlangResult {
+    let Some(encoding) = CharEncoding::from_term(encoding) else { badarg!(process, encoding) };
+    characters_to(process, data, encoding, Some(CharEncoding::Utf8))
+}
 
-    let mut queue = VecDeque::new();
-    queue.push_back(data.into());
-    while let Some(term) = queue.pop_front() {
-        match term {
-            Term::Nil => continue,
-            Term::Cons(cons) => {
-                let mut items = flat_map_maybe_improper(&cons, vec![]);
-                queue.extend(items.drain(..));
+#[export_name = "unicode:characters_to_binary/3"]
+pub extern "C-unwind" fn characters_to_binary3(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+    in_encoding: OpaqueTerm,
+    out_encoding: OpaqueTerm,
+) -> ErlangResult {
+    let Some(in_enc) = CharEncoding::from_term(in_encoding) else { badarg!(process, in_encoding) };
+    let Some(out_enc) = CharEncoding::from_term(out_encoding) else { badarg!(process, out_encoding) };
+    characters_to(process, data, in_enc, Some(out_enc))
+}
+
+/// Converts `data` to a list of characters if `out_encoding` is `None`, otherwise to a binary in
+/// the given encoding.
+///
+/// The result is one of the following, as documented for `unicode:characters_to_binary/3`:
+///
+/// * The converted list/binary, if all of the input was converted
+/// * `{error, Converted, Rest}`, if the input contains invalid characters, or characters which
+/// cannot be represented in the output encoding, where `Rest` begins with the first such character
+/// * `{incomplete, Converted, Rest}`, if the input ends with an incomplete encoding of a character
+fn characters_to(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+    in_encoding: CharEncoding,
+    out_encoding: Option<CharEncoding>,
+) -> ErlangResult {
+    let mut converted = String::new();
+    let out_latin1 = out_encoding == Some(CharEncoding::Latin1);
+    let Ok(conversion) = convert(data, in_encoding, out_latin1, &mut converted) else { badarg!(process, data) };
+
+    let encoded = out_encoding.map(|encoding| encoding.encode(&converted));
+
+    // Make sure there is enough space on the heap for the result, so that we only need to GC once
+    let mut layout = LayoutBuilder::new();
+    match encoded.as_ref() {
+        None => layout.build_list(converted.chars().count()),
+        Some(bytes) => layout.build_binary(bytes.len()),
+    };
+    let (tag, mut rest) = match conversion {
+        Conversion::Complete => (None, vec![]),
+        Conversion::Error(rest) => (Some(atoms::Error), rest),
+        Conversion::Incomplete(rest) => (Some(atoms::Incomplete), rest),
+    };
+    if tag.is_some() {
+        layout.build_tuple(3);
+        for item in rest.iter() {
+            if let RestItem::Bytes(bytes) = item {
+                layout.build_binary(bytes.len());
             }
-            Term::Int(codepoint) if latin1 => {
-                // TODO: validate codepoint
-                let byte: u8 = codepoint.try_into().unwrap();
-                buffer.push_byte(byte);
+        }
+        if rest.len() > 1 {
+            layout.build_list(rest.len());
+        }
+    }
+    let needed = layout.finish().size();
+    if needed > process.heap_available() {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        for item in rest.iter_mut() {
+            if let RestItem::Term(ref mut term) = item {
+                roots += term as *mut OpaqueTerm;
             }
-            Term::Int(codepoint) if codepoint >= 0 && codepoint < u32::MAX as i64 => {
-                // TODO: validate codepoint
-                let c: char = (codepoint as u32).try_into().unwrap();
-                buffer.push_utf8(c);
+        }
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let converted = match encoded {
+        None => Cons::charlist_from_str(&converted, process)
+            .unwrap()
+            .map(Term::Cons)
+            .unwrap_or(Term::Nil)
+            .into(),
+        Some(bytes) => make_binary(&bytes, process),
+    };
+    let Some(tag) = tag else { return ErlangResult::Ok(converted); };
+
+    let mut rest = rest
+        .drain(..)
+        .map(|item| match item {
+            RestItem::Term(term) => term,
+            RestItem::Bytes(bytes) => make_binary(&bytes, process),
+        })
+        .collect::<Vec<_>>();
+    let rest = if rest.len() == 1 {
+        rest.pop().unwrap()
+    } else {
+        let mut builder = ListBuilder::new(process);
+        for item in rest.iter().rev() {
+            builder.push((*item).into()).unwrap();
+        }
+        builder
+            .finish()
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL)
+    };
+    let tuple = Tuple::from_slice(&[tag.into(), converted, rest], process).unwrap();
+    ErlangResult::Ok(tuple.into())
+}
+
+/// Converts the character data in `data` from `encoding` to a string, returning `None` if `data`
+/// is not entirely valid character data in that encoding
+pub(crate) fn characters_to_string(data: OpaqueTerm, encoding: OpaqueTerm) -> Option<String> {
+    let encoding = CharEncoding::from_term(encoding)?;
+    let mut out = String::new();
+    match convert(data, encoding, false, &mut out) {
+        Ok(Conversion::Complete) => Some(out),
+        _ => None,
+    }
+}
+
+/// Allocates a new binary containing `bytes`, space for which must have been reserved already
+fn make_binary(bytes: &[u8], process: &mut ProcessLock) -> OpaqueTerm {
+    if bytes.is_empty() {
+        Term::ConstantBinary(EMPTY_BIN).into()
+    } else if bytes.len() > BinaryData::MAX_HEAP_BYTES {
+        BinaryData::from_bytes(bytes).into()
+    } else {
+        BinaryData::from_small_bytes(bytes, process).unwrap().into()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Endianness {
+    Big,
+    Little,
+}
+
+/// The encodings of character data supported by the `unicode` module
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CharEncoding {
+    Latin1,
+    Utf8,
+    Utf16(Endianness),
+    Utf32(Endianness),
+}
+impl CharEncoding {
+    fn from_term(term: OpaqueTerm) -> Option<Self> {
+        match term.into() {
+            Term::Atom(encoding) => match encoding.as_str() {
+                "latin1" => Some(Self::Latin1),
+                "unicode" | "utf8" => Some(Self::Utf8),
+                "utf16" => Some(Self::Utf16(Endianness::Big)),
+                "utf32" => Some(Self::Utf32(Endianness::Big)),
+                _ => None,
+            },
+            Term::Tuple(tuple) if tuple.len() == 2 => {
+                let endianness = match tuple[1].into() {
+                    Term::Atom(e) if e.as_str() == "big" => Endianness::Big,
+                    Term::Atom(e) if e.as_str() == "little" => Endianness::Little,
+                    _ => return None,
+                };
+                match tuple[0].into() {
+                    Term::Atom(e) if e.as_str() == "utf16" => Some(Self::Utf16(endianness)),
+                    Term::Atom(e) if e.as_str() == "utf32" => Some(Self::Utf32(endianness)),
+                    _ => None,
+                }
             }
-            // TODO: Raise proper error
-            _invalid => badarg!(process, data),
+            _ => None,
+        }
+    }
+
+    /// Returns the character `codepoint`, if it is a valid integer character in this encoding
+    fn decode_int(self, codepoint: i64) -> Option<char> {
+        match self {
+            Self::Latin1 if codepoint > 0xFF => None,
+            _ => u32::try_from(codepoint).ok().and_then(char::from_u32),
         }
     }
 
-    // Then convert the binary to a list of codepoints
-    match buffer.as_str() {
-        Some(s) => loop {
-            match Cons::charlist_from_str(s, process) {
-                Ok(charlist) => {
-                    return ErlangResult::Ok(charlist.map(Term::Cons).unwrap_or(Term::Nil).into());
+    /// Decodes the first character in `bytes`, returning it along with its encoded size
+    fn decode_char(self, bytes: &[u8]) -> Decoded<(char, usize)> {
+        match self {
+            Self::Latin1 => Decoded::Ok((bytes[0] as char, 1)),
+            Self::Utf8 => {
+                let len = match bytes[0] {
+                    0x00..=0x7F => 1,
+                    0xC2..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF4 => 4,
+                    _ => return Decoded::Invalid,
+                };
+                match str::from_utf8(&bytes[..len.min(bytes.len())]) {
+                    Ok(s) => Decoded::Ok((s.chars().next().unwrap(), len)),
+                    Err(err) if err.error_len().is_none() => Decoded::Incomplete,
+                    Err(_) => Decoded::Invalid,
                 }
-                Err(_) => {
-                    // We don't need to pass any roots, because all our live data is in the BitVec
-                    assert!(garbage_collect(process, Default::default()).is_ok());
+            }
+            Self::Utf16(endianness) => {
+                let Some(unit) = read_u16(bytes, endianness) else { return Decoded::Incomplete; };
+                match unit {
+                    0xD800..=0xDBFF => {
+                        let Some(low) = read_u16(&bytes[2..], endianness) else { return Decoded::Incomplete; };
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Decoded::Invalid;
+                        }
+                        let codepoint =
+                            0x10000 + (((unit as u32) - 0xD800) << 10) + ((low as u32) - 0xDC00);
+                        Decoded::Ok((char::from_u32(codepoint).unwrap(), 4))
+                    }
+                    0xDC00..=0xDFFF => Decoded::Invalid,
+                    _ => Decoded::Ok((char::from_u32(unit as u32).unwrap(), 2)),
                 }
             }
-        },
-        None => loop {
-            // There must be valid latin1, but invalid unicode codepoints
-            assert!(latin1, "invalid unicode codepoints in binary");
-            match Cons::from_bytes(unsafe { buffer.as_bytes_unchecked() }, process) {
-                Ok(charlist) => {
-                    return ErlangResult::Ok(charlist.map(Term::Cons).unwrap_or(Term::Nil).into());
+            Self::Utf32(endianness) => {
+                if bytes.len() < 4 {
+                    return Decoded::Incomplete;
                 }
-                Err(_) => {
-                    // We don't need to pass any roots, because all our live data is in the BitVec
-                    assert!(garbage_collect(process, Default::default()).is_ok());
+                let raw = [bytes[0], bytes[1], bytes[2], bytes[3]];
+                let codepoint = match endianness {
+                    Endianness::Big => u32::from_be_bytes(raw),
+                    Endianness::Little => u32::from_le_bytes(raw),
+                };
+                match char::from_u32(codepoint) {
+                    Some(c) => Decoded::Ok((c, 4)),
+                    None => Decoded::Invalid,
                 }
             }
-        },
+        }
+    }
+
+    /// Encodes `s` in this encoding
+    ///
+    /// When encoding as latin1, all characters of `s` must be representable in latin1
+    fn encode(self, s: &str) -> Vec<u8> {
+        match self {
+            Self::Latin1 => s.chars().map(|c| c as u32 as u8).collect(),
+            Self::Utf8 => s.as_bytes().to_vec(),
+            Self::Utf16(endianness) => s
+                .encode_utf16()
+                .flat_map(|unit| match endianness {
+                    Endianness::Big => unit.to_be_bytes(),
+                    Endianness::Little => unit.to_le_bytes(),
+                })
+                .collect(),
+            Self::Utf32(endianness) => s
+                .chars()
+                .flat_map(|c| match endianness {
+                    Endianness::Big => (c as u32).to_be_bytes(),
+                    Endianness::Little => (c as u32).to_le_bytes(),
+                })
+                .collect(),
+        }
     }
 }
 
-fn flat_map_maybe_improper(cons: &Cons, acc: Vec<Term>) -> Vec<Term> {
-    cons.iter()
-        .fold(acc, |mut acc, maybe_improper| match maybe_improper {
-            Ok(Term::Cons(nested)) => flat_map_maybe_improper(&nested, acc),
-            Ok(term) => {
-                acc.push(term);
-                acc
+fn read_u16(bytes: &[u8], endianness: Endianness) -> Option<u16> {
+    let raw = [*bytes.get(0)?, *bytes.get(1)?];
+    match endianness {
+        Endianness::Big => Some(u16::from_be_bytes(raw)),
+        Endianness::Little => Some(u16::from_le_bytes(raw)),
+    }
+}
+
+enum Decoded<T> {
+    Ok(T),
+    Invalid,
+    Incomplete,
+}
+
+/// The outcome of converting character data
+enum Conversion {
+    /// All of the input was converted
+    Complete,
+    /// Conversion stopped at an invalid character, with which the unconverted data begins
+    Error(Vec<RestItem>),
+    /// The input ended with an incomplete character, which is the unconverted data
+    Incomplete(Vec<RestItem>),
+}
+
+/// An element of the unconverted remainder of the input
+enum RestItem {
+    /// An element of the input which was not visited
+    Term(OpaqueTerm),
+    /// The unconverted bytes of a binary in the input
+    Bytes(Vec<u8>),
+}
+
+/// Converts the character data in `data` from `encoding`, appending the characters to `out`
+///
+/// If `out_latin1` is set, conversion stops at the first character which cannot be represented
+/// in latin1. Returns `Err` if `data` is not character data.
+fn convert(
+    data: OpaqueTerm,
+    encoding: CharEncoding,
+    out_latin1: bool,
+    out: &mut String,
+) -> Result<Conversion, ()> {
+    // The elements yet to be visited, in reverse order
+    let mut stack = vec![data];
+    // The bytes of an incomplete character at the end of the last binary, which may be completed
+    // by the binary that follows it
+    let mut pending = Vec::<u8>::new();
+
+    let remaining = |mut first: Vec<RestItem>, stack: &[OpaqueTerm]| {
+        first.extend(
+            stack
+                .iter()
+                .rev()
+                .filter(|term| !term.is_nil())
+                .map(|term| RestItem::Term(*term)),
+        );
+        first
+    };
+
+    while let Some(term) = stack.pop() {
+        match term.into() {
+            Term::Nil => continue,
+            Term::Cons(cons) => {
+                match cons.tail.into() {
+                    Term::Nil => (),
+                    Term::Cons(_) => stack.push(cons.tail),
+                    tail if tail.as_binary().is_some() => stack.push(cons.tail),
+                    _ => return Err(()),
+                }
+                stack.push(cons.head);
             }
-            Err(improper) => {
-                acc.push(improper.tail);
-                acc
+            Term::Int(codepoint) => {
+                let valid = encoding
+                    .decode_int(codepoint)
+                    .filter(|c| !out_latin1 || (*c as u32) <= 0xFF);
+                match valid {
+                    Some(_) if !pending.is_empty() => {
+                        let first = vec![RestItem::Bytes(pending), RestItem::Term(term)];
+                        return Ok(Conversion::Error(remaining(first, &stack)));
+                    }
+                    Some(c) => out.push(c),
+                    None => {
+                        let mut first = vec![];
+                        if !pending.is_empty() {
+                            first.push(RestItem::Bytes(pending));
+                        }
+                        first.push(RestItem::Term(term));
+                        return Ok(Conversion::Error(remaining(first, &stack)));
+                    }
+                }
             }
-        })
+            binary => {
+                let Some(bin) = binary.as_binary() else { return Err(()); };
+                let owned;
+                let bytes = if pending.is_empty() && bin.is_aligned() {
+                    unsafe { bin.as_bytes_unchecked() }
+                } else {
+                    owned = pending.drain(..).chain(bin.bytes()).collect::<Vec<_>>();
+                    owned.as_slice()
+                };
+                let mut pos = 0;
+                while pos < bytes.len() {
+                    let decoded = match encoding {
+                        // Validate as much UTF-8 as possible in one go
+                        CharEncoding::Utf8 if !out_latin1 => {
+                            let valid = match str::from_utf8(&bytes[pos..]) {
+                                Ok(s) => s,
+                                Err(err) => unsafe {
+                                    str::from_utf8_unchecked(&bytes[pos..(pos + err.valid_up_to())])
+                                },
+                            };
+                            out.push_str(valid);
+                            pos += valid.len();
+                            if pos == bytes.len() {
+                                break;
+                            }
+                            encoding.decode_char(&bytes[pos..])
+                        }
+                        _ => encoding.decode_char(&bytes[pos..]),
+                    };
+                    match decoded {
+                        Decoded::Ok((c, _)) if out_latin1 && (c as u32) > 0xFF => {
+                            let first = vec![RestItem::Bytes(bytes[pos..].to_vec())];
+                            return Ok(Conversion::Error(remaining(first, &stack)));
+                        }
+                        Decoded::Ok((c, len)) => {
+                            out.push(c);
+                            pos += len;
+                        }
+                        Decoded::Invalid => {
+                            // Return the original binary if none of it was converted
+                            let first = if pos == 0 && bytes.len() == bin.byte_size() {
+                                RestItem::Term(term)
+                            } else {
+                                RestItem::Bytes(bytes[pos..].to_vec())
+                            };
+                            return Ok(Conversion::Error(remaining(vec![first], &stack)));
+                        }
+                        Decoded::Incomplete => {
+                            pending.extend_from_slice(&bytes[pos..]);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if pending.is_empty() {
+        Ok(Conversion::Complete)
+    } else {
+        Ok(Conversion::Incomplete(vec![RestItem::Bytes(pending)]))
+    }
 }
@@ -1,9 +1,9 @@
 use std::cell::UnsafeCell;
 use std::ops::Deref;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 
-use crossbeam::deque::{Injector, Steal, Worker};
+use crossbeam::deque::{Injector, Steal, Stealer, Worker};
 
 use firefly_rt::process::{Priority, Process, ProcessId};
 
@@ -55,10 +55,9 @@ impl Task for Process {
     #[inline]
     fn should_delay(&self) -> bool {
         // Delay a low-priority process up to 8 times
-        let mut process = self.lock();
-        process.schedule_count += 1;
-        if process.schedule_count == 8 {
-            process.schedule_count = 0;
+        let scheduled = self.schedule_count.fetch_add(1, Ordering::Relaxed);
+        if scheduled == 7 {
+            self.schedule_count.store(0, Ordering::Relaxed);
             false
         } else {
             true
@@ -94,6 +93,63 @@ pub trait TaskQueue {
     fn clear_statistics(&self);
 }
 
+/// A `TaskQueue` which other schedulers can steal tasks from
+pub trait StealableQueue: TaskQueue {
+    /// Returns a handle through which other threads can steal tasks from this queue
+    fn stealer(&self) -> Stealer<Self::Task>;
+}
+
+/// The scheduler-local queues of a group of schedulers, from which idle schedulers steal tasks
+///
+/// Each scheduler in the group registers the queues of its run queue here, one per priority, so
+/// that a scheduler which runs out of work can take half of the tasks of a peer which has more
+/// than it can run, rather than sitting idle while that peer falls behind.
+pub struct Peers<T> {
+    stealers: RwLock<Vec<[Stealer<T>; 3]>>,
+    /// The index of the peer to try first on the next steal, so that steals are spread out
+    next: AtomicUsize,
+}
+impl<T> Default for Peers<T> {
+    fn default() -> Self {
+        Self {
+            stealers: RwLock::new(vec![]),
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+impl<T> Peers<T> {
+    /// Registers the queues of a scheduler, from highest to lowest priority
+    fn register(&self, stealers: [Stealer<T>; 3]) {
+        self.stealers.write().unwrap().push(stealers);
+    }
+
+    /// Steals a batch of tasks from one of the peers into `dest`
+    ///
+    /// The highest priority tasks of a peer are stolen first. Returns `true` if anything was
+    /// stolen.
+    fn steal_into(&self, dest: &Worker<T>) -> bool {
+        let peers = self.stealers.read().unwrap();
+        let len = peers.len();
+        if len == 0 {
+            return false;
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in 0..len {
+            let stealers = &peers[(start + i) % len];
+            for stealer in stealers.iter() {
+                loop {
+                    match stealer.steal_batch(dest) {
+                        Steal::Empty => break,
+                        Steal::Retry => continue,
+                        Steal::Success(_) => return true,
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
 /// The run queue is used to handle prioritization across processes, ports, and system tasks
 ///
 /// Each type of task has its own set of queues, and potentially multiple priorities.
@@ -161,6 +217,16 @@ pub struct RunQueue<Q: TaskQueue> {
     ///
     /// All newly spawned tasks go in this queue, to be picked up by the next available scheduler
     global: Arc<Injector<<Q as TaskQueue>::Task>>,
+    /// The global queues of other groups of schedulers, i.e. those on other NUMA nodes
+    ///
+    /// These are only stolen from when there is no work available in `global`, so that tasks
+    /// tend to stay on the node their memory was allocated on.
+    remote: Vec<Arc<Injector<<Q as TaskQueue>::Task>>>,
+    /// The scheduler-local queues of the schedulers in each group, this scheduler's group first
+    ///
+    /// The local queues of peers are stolen from when there is no work available in the global
+    /// queue of the same group, before trying the groups which follow.
+    peers: Vec<Arc<Peers<<Q as TaskQueue>::Task>>>,
     /// This is a scheduler-local queue from which tasks can be stolen by other schedulers
     ///
     /// Tasks go in this queue when they are scheduled out by the scheduler, or when the scheduler
@@ -173,28 +239,61 @@ pub struct RunQueue<Q: TaskQueue> {
     /// Same as above, but for normal priority tasks
     normal: Q,
 }
-impl<Q: TaskQueue + Default> RunQueue<Q> {
+impl<Q: StealableQueue + Default> RunQueue<Q> {
     pub fn new(global: Arc<Injector<<Q as TaskQueue>::Task>>) -> Self {
-        Self {
+        Self::with_remote(global, vec![], vec![])
+    }
+
+    /// Creates a run queue which steals from `remote` and `peers` when idle, see the field docs
+    ///
+    /// The local queues of the new run queue are registered with the first of `peers`, if any.
+    pub fn with_remote(
+        global: Arc<Injector<<Q as TaskQueue>::Task>>,
+        remote: Vec<Arc<Injector<<Q as TaskQueue>::Task>>>,
+        peers: Vec<Arc<Peers<<Q as TaskQueue>::Task>>>,
+    ) -> Self {
+        let runq = Self {
             global,
+            remote,
+            peers,
             max: Q::default(),
             hi: Q::default(),
             normal: Q::default(),
+        };
+        if let Some(group) = runq.peers.first() {
+            group.register([
+                runq.max.stealer(),
+                runq.hi.stealer(),
+                runq.normal.stealer(),
+            ]);
         }
+        runq
     }
 }
 impl<Q: TaskQueue> RunQueue<Q> {
-    /// Steal tasks from the global queue into our local queues
+    /// Steal tasks from the global queue into our local queues, falling back to the local queues
+    /// of our peers, then to the global and local queues of other scheduler groups
     ///
     /// Returns `true` if there are tasks available after doing this.
     pub fn backfill(&self) -> bool {
         let inq = Worker::new_fifo();
-        loop {
-            match self.global.steal_batch(&inq) {
-                Steal::Empty => return false,
-                Steal::Retry => continue,
-                Steal::Success(_) => break,
-            }
+        let found = Self::steal_from(&self.global, &inq)
+            || self
+                .peers
+                .first()
+                .map(|group| group.steal_into(&inq))
+                .unwrap_or(false)
+            || self
+                .remote
+                .iter()
+                .any(|remote| Self::steal_from(remote, &inq))
+            || self
+                .peers
+                .iter()
+                .skip(1)
+                .any(|group| group.steal_into(&inq));
+        if !found {
+            return false;
         }
 
         while let Some(task) = inq.pop() {
@@ -203,6 +302,19 @@ impl<Q: TaskQueue> RunQueue<Q> {
 
         true
     }
+
+    fn steal_from(
+        queue: &Injector<<Q as TaskQueue>::Task>,
+        inq: &Worker<<Q as TaskQueue>::Task>,
+    ) -> bool {
+        loop {
+            match queue.steal_batch(inq) {
+                Steal::Empty => return false,
+                Steal::Retry => continue,
+                Steal::Success(_) => return true,
+            }
+        }
+    }
 }
 impl<Q: TaskQueue> TaskQueue for RunQueue<Q> {
     type Task = <Q as TaskQueue>::Task;
@@ -390,6 +502,12 @@ impl TaskQueue for LocalProcessQueue {
         }
     }
 }
+impl StealableQueue for LocalProcessQueue {
+    #[inline]
+    fn stealer(&self) -> Stealer<Self::Task> {
+        self.tasks.stealer()
+    }
+}
 
 #[cfg(test)]
 mod tests {
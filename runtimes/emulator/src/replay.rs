@@ -0,0 +1,175 @@
+//! Recording of the messages received by a process, so that they can be replayed later.
+//!
+//! When recording is enabled for a process, each message it receives, i.e. each message removed
+//! from its mailbox by a matching receive clause, is appended to a recording file, in the order
+//! received. Depending on the mode, either the full message is recorded in the external term
+//! format, or only its `phash2` hash. Hashes are cheaper to record, and suffice to compare the
+//! order in which messages were received across runs, but only full recordings can be replayed.
+//!
+//! Replaying a recording sends the recorded messages, in order, to a fresh process, so that a
+//! failure which depends on the interleaving of messages from different senders can be reproduced
+//! deterministically.
+//!
+//! A recording consists of a header, `FFRR` followed by a version byte and a mode byte, and then
+//! one record per message: a big-endian `u32` hash in hash mode, or a big-endian `u32` length
+//! followed by that many bytes of encoded term in full mode.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use firefly_rt::etf;
+use firefly_rt::process::ProcessId;
+use firefly_rt::term::{hash, Term};
+
+const MAGIC: &[u8; 4] = b"FFRR";
+const VERSION: u8 = 1;
+
+/// The number of processes being recorded, used to skip the lookup in the common case
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static RECORDERS: Mutex<Option<HashMap<ProcessId, Recorder>>> = Mutex::new(None);
+
+/// What is recorded for each received message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RecordMode {
+    /// Only the `phash2` of each message is recorded
+    Hash = 0,
+    /// Each message is recorded in full, in the external term format
+    Full = 1,
+}
+
+/// A single received message, as read back from a recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    Hash(u32),
+    /// The message, encoded in the external term format
+    Full(Vec<u8>),
+}
+
+struct Recorder {
+    mode: RecordMode,
+    file: BufWriter<File>,
+}
+
+/// Starts recording the messages received by the process `id` to a new file at `path`
+///
+/// If the process was already being recorded, the previous recording is finished first.
+pub fn start(id: ProcessId, path: &Path, mode: RecordMode) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&[VERSION, mode as u8])?;
+
+    let mut recorders = RECORDERS.lock().unwrap();
+    let previous = recorders
+        .get_or_insert_with(HashMap::default)
+        .insert(id, Recorder { mode, file });
+    match previous {
+        Some(mut previous) => previous.file.flush(),
+        None => {
+            ACTIVE.fetch_add(1, Ordering::Release);
+            Ok(())
+        }
+    }
+}
+
+/// Stops recording the messages received by the process `id`, returning false if it was not
+/// being recorded
+///
+/// This is also called when a process exits, so recordings are complete up to the point of exit.
+pub fn stop(id: ProcessId) -> bool {
+    if ACTIVE.load(Ordering::Acquire) == 0 {
+        return false;
+    }
+    let recorder = RECORDERS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|recorders| recorders.remove(&id));
+    match recorder {
+        Some(mut recorder) => {
+            ACTIVE.fetch_sub(1, Ordering::Release);
+            if let Err(err) = recorder.file.flush() {
+                log::warn!(target: "replay", "failed to finish recording for {}: {}", id, err);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Records the receipt of `message` by the process `id`, if it is being recorded
+#[inline]
+pub fn record(id: ProcessId, message: Term) {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    record_slow(id, message)
+}
+
+#[cold]
+fn record_slow(id: ProcessId, message: Term) {
+    let mut recorders = RECORDERS.lock().unwrap();
+    let Some(recorder) = recorders.as_mut().and_then(|r| r.get_mut(&id)) else { return; };
+    let result = match recorder.mode {
+        RecordMode::Hash => recorder
+            .file
+            .write_all(&hash::phash2(message).to_be_bytes()),
+        RecordMode::Full => match etf::encode(message) {
+            Ok(bytes) => recorder
+                .file
+                .write_all(&(bytes.len() as u32).to_be_bytes())
+                .and_then(|_| recorder.file.write_all(&bytes)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message cannot be encoded",
+            )),
+        },
+    };
+    if let Err(err) = result {
+        // A recording with gaps in it is useless, so give up on it entirely
+        log::warn!(target: "replay", "stopped recording for {}: {}", id, err);
+        recorders.as_mut().unwrap().remove(&id);
+        ACTIVE.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Reads back the recording at `path`
+pub fn read(path: &Path) -> io::Result<(RecordMode, Vec<Record>)> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid recording");
+    let Some(rest) = bytes.strip_prefix(MAGIC) else { return Err(invalid()); };
+    let mode = match rest {
+        [VERSION, 0, ..] => RecordMode::Hash,
+        [VERSION, 1, ..] => RecordMode::Full,
+        _ => return Err(invalid()),
+    };
+    let mut rest = &rest[2..];
+    let mut records = Vec::new();
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err(invalid());
+        }
+        let (prefix, tail) = rest.split_at(4);
+        let n = u32::from_be_bytes(prefix.try_into().unwrap());
+        match mode {
+            RecordMode::Hash => {
+                records.push(Record::Hash(n));
+                rest = tail;
+            }
+            RecordMode::Full => {
+                let n = n as usize;
+                if tail.len() < n {
+                    return Err(invalid());
+                }
+                records.push(Record::Full(tail[..n].to_vec()));
+                rest = &tail[n..];
+            }
+        }
+    }
+    Ok((mode, records))
+}
@@ -0,0 +1,68 @@
+//! This module registers the runtime's hooks for checkpointing a node with tools like CRIU, see
+//! `firefly_rt::services::checkpoint`.
+//!
+//! Before the world is paused, output queued on distribution connections is given a chance to be
+//! sent, so that messages sent before the checkpoint aren't lost, or sent twice, once by each
+//! node restored from it. Once paused, queued I/O is submitted, so that no operation is left
+//! half-started in the checkpoint. The memory pressure watcher registers its own hook, see
+//! `sys::memory_pressure`.
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use firefly_rt::services::checkpoint::{self, CheckpointHook};
+use firefly_rt::services::distribution;
+
+/// The maximum time to wait for distribution output to be sent before a checkpoint
+const DIST_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Registers the runtime's checkpoint hooks
+pub fn init() {
+    checkpoint::register(Arc::new(DistributionHook));
+    #[cfg(unix)]
+    checkpoint::register(Arc::new(IoHook));
+}
+
+struct DistributionHook;
+impl CheckpointHook for DistributionHook {
+    fn name(&self) -> &'static str {
+        "distribution"
+    }
+
+    fn prepare(&self) {
+        let deadline = Instant::now() + DIST_FLUSH_TIMEOUT;
+        for node in distribution::list() {
+            let Some(connection) = node.connection() else { continue; };
+            if connection.output_size() == 0 {
+                continue;
+            }
+            // The controller may be waiting for a notification before it sends anything
+            connection.request_data_notification();
+            while connection.output_size() > 0 {
+                if Instant::now() >= deadline {
+                    log::warn!(target: "checkpoint", "timed out flushing distribution output to {}, {} bytes remain", node.name(), connection.output_size());
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+struct IoHook;
+#[cfg(unix)]
+impl CheckpointHook for IoHook {
+    fn name(&self) -> &'static str {
+        "io"
+    }
+
+    fn release(&self) {
+        use super::io::{self, Backend};
+
+        io::flush();
+        if io::backend() == Backend::Uring {
+            log::warn!(target: "checkpoint", "io_uring instances can't be checkpointed by CRIU, start with `+IOu false` to use epoll instead");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! This module provides the dirty schedulers, pools of threads on which native work which may run
+//! for a long time, e.g. CPU-bound work on large inputs, or blocking file I/O, is done instead of
+//! on the normal schedulers, so that it can't hold up the processes scheduled on them.
+//!
+//! Work is submitted as a job with [`schedule_dirty`], to one of two pools: dirty CPU schedulers
+//! for work which keeps a processor busy, and dirty I/O schedulers for work which mostly waits.
+//! Jobs run concurrently with the normal schedulers, including while the world is paused (see
+//! `firefly_rt::services::safepoint`), so they must not touch the state of any process. A job
+//! which produces a result for a process delivers it as a message, see [`schedule_dirty_reply`].
+//!
+//! The size of each pool is set with `+SDcpu Schedulers[:Online]` and `+SDio Schedulers`, as
+//! supported by ERTS. By default, there is one dirty CPU scheduler per normal scheduler, and ten
+//! dirty I/O schedulers.
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+use std::thread;
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use firefly_rt::gc::Gc;
+use firefly_rt::scheduler;
+use firefly_rt::services::registry::{self, WeakAddress};
+use firefly_rt::services::safepoint;
+use firefly_rt::term::{LayoutBuilder, Pid, Reference, Term, TermFragment, Tuple};
+
+/// The default number of dirty I/O schedulers, as in ERTS
+const DEFAULT_DIRTY_IO: usize = 10;
+
+/// The kind of dirty scheduler a job should run on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DirtyKind {
+    /// For jobs which are bound by the processor, e.g. encoding or hashing large terms
+    Cpu,
+    /// For jobs which mostly wait, e.g. on blocking file I/O
+    Io,
+}
+
+/// A unit of work to be run on a dirty scheduler
+pub type DirtyJob = Box<dyn FnOnce() + Send + 'static>;
+
+/// The error returned by [`schedule_dirty`] when there is no pool of the requested kind
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NoDirtySchedulers(pub DirtyKind);
+impl fmt::Display for NoDirtySchedulers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            DirtyKind::Cpu => f.write_str("no dirty cpu schedulers are running"),
+            DirtyKind::Io => f.write_str("no dirty i/o schedulers are running"),
+        }
+    }
+}
+
+/// Configures the dirty schedulers at startup
+#[derive(Debug, Copy, Clone)]
+pub struct DirtyConfig {
+    /// The number of dirty CPU schedulers
+    pub cpu: usize,
+    /// The number of dirty I/O schedulers
+    pub io: usize,
+}
+impl DirtyConfig {
+    /// Parses the dirty scheduler configuration from the `+SDcpu` and `+SDio` flags, defaulting
+    /// to `schedulers` dirty CPU schedulers
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I, schedulers: usize) -> Self {
+        let mut config = Self {
+            cpu: schedulers,
+            io: DEFAULT_DIRTY_IO,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "+SDcpu" => {
+                    let value = args.next();
+                    // All dirty schedulers are always online, so the second number is ignored
+                    let count = value
+                        .as_deref()
+                        .map(|value| value.split(':').next().unwrap().parse::<usize>());
+                    match count {
+                        Some(Ok(count)) if count > 0 => config.cpu = count,
+                        _ => eprintln!(
+                            "Ignoring invalid +SDcpu value, expected a number of schedulers, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                "+SDio" => {
+                    let value = args.next();
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(count)) => config.io = count,
+                        _ => eprintln!(
+                            "Ignoring invalid +SDio value, expected a number of schedulers, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                _ => (),
+            }
+        }
+        config
+    }
+}
+
+/// The queues of jobs for the dirty CPU and I/O schedulers, if any were started
+struct Pools {
+    cpu: Option<Sender<DirtyJob>>,
+    io: Option<Sender<DirtyJob>>,
+}
+
+static POOLS: OnceLock<Pools> = OnceLock::new();
+
+/// Starts the dirty schedulers described by `config`
+///
+/// This must be called once at startup, before anything can schedule dirty jobs.
+pub fn start(config: DirtyConfig) -> io::Result<()> {
+    let pools = Pools {
+        cpu: start_pool("dirty_cpu", config.cpu)?,
+        io: start_pool("dirty_io", config.io)?,
+    };
+    assert!(
+        POOLS.set(pools).is_ok(),
+        "dirty schedulers were already started"
+    );
+    scheduler::set_dirty(config.cpu as u32, config.io as u32);
+    Ok(())
+}
+
+fn start_pool(name: &str, size: usize) -> io::Result<Option<Sender<DirtyJob>>> {
+    if size == 0 {
+        return Ok(None);
+    }
+    let (sender, receiver) = channel::unbounded();
+    for i in 0..size {
+        let receiver = receiver.clone();
+        thread::Builder::new()
+            .name(format!("{}_{}", name, i + 1))
+            .spawn(move || run(receiver))?;
+    }
+    Ok(Some(sender))
+}
+
+/// Runs jobs for as long as the pool exists
+fn run(receiver: Receiver<DirtyJob>) {
+    while let Ok(job) = receiver.recv() {
+        // A job which panics must not take a dirty scheduler with it
+        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+            log::error!(target: "dirty", "dirty job panicked on {}", thread::current().name().unwrap_or("dirty scheduler"));
+        }
+    }
+}
+
+/// Runs `job` on a dirty scheduler of the given kind, as soon as one is available
+///
+/// Jobs of the same kind are started in the order they are scheduled.
+pub fn schedule_dirty<F>(kind: DirtyKind, job: F) -> Result<(), NoDirtySchedulers>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let pools = POOLS.get().ok_or(NoDirtySchedulers(kind))?;
+    let sender = match kind {
+        DirtyKind::Cpu => pools.cpu.as_ref(),
+        DirtyKind::Io => pools.io.as_ref(),
+    };
+    let sender = sender.ok_or(NoDirtySchedulers(kind))?;
+    sender
+        .send(Box::new(job))
+        .map_err(|_| NoDirtySchedulers(kind))
+}
+
+/// Like [`schedule_dirty`], but sends the result of `job` to the process `to` as the message
+/// `{Ref, Result}`, where `Ref` is `reference`
+///
+/// The caller typically returns `reference` to the process, which then waits for the reply. If
+/// the process has exited by the time the job is done, the result is dropped.
+pub fn schedule_dirty_reply<F>(
+    kind: DirtyKind,
+    to: Pid,
+    reference: Reference,
+    job: F,
+) -> Result<(), NoDirtySchedulers>
+where
+    F: FnOnce() -> TermFragment + Send + 'static,
+{
+    schedule_dirty(kind, move || {
+        let result = job();
+        let message = make_reply(reference, result);
+        // The process may be running, so the reply is delivered via its signal queue
+        let _region = safepoint::enter_region();
+        if let Some(process) = registry::get_by_pid(&to) {
+            process.send_fragment(WeakAddress::System, message).ok();
+        }
+    })
+}
+
+/// Builds the message `{Ref, Result}`, the fragment of `result` is freed once it has been copied
+fn make_reply(reference: Reference, result: TermFragment) -> TermFragment {
+    let term: Term = result.term.into();
+    let mut layout = LayoutBuilder::new();
+    layout += term.layout();
+    layout.build_reference().build_tuple(2);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let term = unsafe { term.unsafe_clone_to_heap(fragment) };
+    let reference = Gc::new_in(reference, fragment).unwrap();
+    let message = Tuple::from_slice(&[reference.into(), term.into()], fragment).unwrap();
+
+    TermFragment {
+        term: message.into(),
+        fragment: Some(fragment_ptr),
+    }
+}
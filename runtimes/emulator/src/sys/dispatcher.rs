@@ -3,13 +3,18 @@ use std::ptr;
 use std::sync::atomic::{AtomicPtr, Ordering};
 use std::sync::{Arc, Weak};
 
-use firefly_rt::process::{signals::SignalEntry, Process};
+use firefly_rt::process::signals::{Signal, SignalEntry};
+use firefly_rt::process::Process;
 use firefly_rt::services::registry::{self, Registrant};
+use firefly_rt::services::safepoint;
 use firefly_rt::services::system::{self, SystemDispatcher, SystemMessage};
+use firefly_rt::term::format;
 use firefly_rt::term::{atoms, OpaqueTerm, Term};
 
 use tokio::sync::mpsc;
 
+use super::log_file;
+
 struct AsyncSystemDispatcher {
     sender: mpsc::UnboundedSender<SystemMessage>,
 }
@@ -157,6 +162,14 @@ impl ResolvedSystemLogger {
             return;
         }
 
+        // The native handler takes the place of the logger process when enabled
+        if log_file::is_enabled() {
+            if let Some(text) = format_log_event(&message) {
+                log_file::log(text);
+            }
+            return;
+        }
+
         if self.cache.is_none() {
             self.load();
         }
@@ -169,6 +182,12 @@ impl ResolvedSystemLogger {
                 self.logger = SystemLogger::Default;
                 self.cache = None;
             }
+        } else {
+            // There is no logger to handle this event yet, e.g. during startup, so rather than
+            // drop it on the floor, print it ourselves
+            if let Some(text) = format_log_event(&message) {
+                eprint!("{}", text);
+            }
         }
     }
 
@@ -206,12 +225,45 @@ impl ResolvedSystemLogger {
     }
 }
 
+/// Formats a log event of the form `{log, Level, Format, Args, Metadata}` as a report
+fn format_log_event(message: &SignalEntry) -> Option<String> {
+    let Signal::Message(ref message) = message.signal else { return None; };
+    let Term::Tuple(event) = message.message.term.into() else { return None; };
+    if event.len() != 5 {
+        return None;
+    }
+    let format = match event[2].into() {
+        Term::Nil => Some(String::new()),
+        Term::Cons(cons) => cons.to_string(),
+        _ => None,
+    };
+    let args = match event[3].into() {
+        Term::Nil => Some(Vec::new()),
+        Term::Cons(cons) => cons.iter().map(|arg| arg.ok()).collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+    let (Some(format), Some(args)) = (format, args) else { return None; };
+    let text = format::format(&format, &args).unwrap_or_else(|_| {
+        // Mirror the fallback used by the logger for bad format strings
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        format!("FORMAT ERROR: \"{}\" - [{}]\n", format, args.join(","))
+    });
+    let report = match event[1].into() {
+        Term::Atom(level) if level == atoms::Info => "INFO REPORT",
+        Term::Atom(level) if level == atoms::Warning => "WARNING REPORT",
+        _ => "ERROR REPORT",
+    };
+    Some(format!("={}====\n{}", report, text))
+}
+
 /// Runs the core system dispatcher loop, processing messages in the system message queue
 async fn run(mut receiver: mpsc::UnboundedReceiver<SystemMessage>) {
     let mut sys_logger = ResolvedSystemLogger::default();
     while let Some(message) = receiver.recv().await {
         match message {
             SystemMessage::ErrorLogger { message } => {
+                // The logger may be a process, so this must not happen while the world is paused
+                let _region = safepoint::enter_region();
                 sys_logger.send(message);
                 sys_logger.maybe_reload();
             }
@@ -0,0 +1,258 @@
+//! This module implements an append-only file with optional rotation.
+//!
+//! When rotation is enabled, a write which would grow the file beyond the configured size, or
+//! which occurs once the file has been open for longer than the configured interval, first moves
+//! the file aside, in the same manner as `logger_std_h`: the current file becomes `Path.0`,
+//! `Path.0` becomes `Path.1`, and so on, with the oldest file being removed once there are more
+//! than the configured number of rotated files. With zero rotated files, the file is truncated.
+//! Rotated files may be compressed with gzip, in which case they are named `Path.N.gz`.
+//!
+//! Writes are buffered until [`FileSink::flush`], which also syncs the file to disk according
+//! to the configured [`SyncPolicy`].
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Controls when, and how many times, a [`FileSink`] is rotated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RotationConfig {
+    /// The size in bytes beyond which the file is rotated
+    pub max_bytes: Option<u64>,
+    /// How long the file is written to before it is rotated
+    pub interval: Option<Duration>,
+    /// The number of rotated files to keep
+    pub max_files: usize,
+    /// Whether rotated files are compressed
+    pub compress: bool,
+}
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: Some(10 * 1024 * 1024),
+            interval: None,
+            max_files: 5,
+            compress: false,
+        }
+    }
+}
+
+/// Controls when the contents of a [`FileSink`] are synced to disk
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system
+    #[default]
+    Never,
+    /// Sync whenever the sink is flushed
+    Always,
+    /// Sync on flush, if at least this long has passed since the last sync
+    Interval(Duration),
+}
+impl SyncPolicy {
+    /// Parses a policy of the form `never`, `always`, or a number of milliseconds
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "never" => Some(Self::Never),
+            "always" => Some(Self::Always),
+            ms => ms
+                .parse::<u64>()
+                .ok()
+                .map(|ms| Self::Interval(Duration::from_millis(ms))),
+        }
+    }
+}
+
+pub struct FileSink {
+    path: PathBuf,
+    rotation: Option<RotationConfig>,
+    sync: SyncPolicy,
+    file: BufWriter<File>,
+    /// The current size of `file`, including buffered writes
+    size: u64,
+    /// When `file` was opened, for time-based rotation
+    opened: Instant,
+    last_sync: Instant,
+}
+impl FileSink {
+    /// Opens the file at `path` for appending, creating it if it does not exist
+    pub fn open<P: Into<PathBuf>>(path: P, rotation: Option<RotationConfig>) -> io::Result<Self> {
+        let path = path.into();
+        let file = Self::open_file(&path)?;
+        let size = file.metadata()?.len();
+        let now = Instant::now();
+        Ok(Self {
+            path,
+            rotation,
+            sync: SyncPolicy::default(),
+            file: BufWriter::new(file),
+            size,
+            opened: now,
+            last_sync: now,
+        })
+    }
+
+    /// Sets the policy for syncing the file to disk on flush
+    pub fn set_sync_policy(&mut self, sync: SyncPolicy) {
+        self.sync = sync;
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Appends `bytes` to the file, rotating it first if it would grow too large, or has been
+    /// open for too long
+    ///
+    /// Writes are never split across files, so a single write larger than the configured size
+    /// still ends up in one file.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(rotation) = self.rotation {
+            let size = self.size + bytes.len() as u64;
+            let too_large = rotation.max_bytes.map_or(false, |max| size > max);
+            let too_old = rotation
+                .interval
+                .map_or(false, |interval| self.opened.elapsed() >= interval);
+            if self.size > 0 && (too_large || too_old) {
+                self.rotate(rotation)?;
+            }
+        }
+        self.file.write_all(bytes)?;
+        self.size += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Writes out any buffered data, syncing it to disk if required by the sync policy
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let sync = match self.sync {
+            SyncPolicy::Never => false,
+            SyncPolicy::Always => true,
+            SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+        };
+        if sync {
+            self.file.get_ref().sync_data()?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self, rotation: RotationConfig) -> io::Result<()> {
+        self.file.flush()?;
+        self.opened = Instant::now();
+        if rotation.max_files == 0 {
+            self.file.get_ref().set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        // Make room for the current file by shifting the older ones along, dropping the oldest
+        let rotated = |n: usize, compressed: bool| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            if compressed {
+                name.push(".gz");
+            }
+            PathBuf::from(name)
+        };
+        let max_files = rotation.max_files;
+        match fs::remove_file(rotated(max_files - 1, rotation.compress)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => (),
+        }
+        for n in (0..(max_files - 1)).rev() {
+            let from = rotated(n, rotation.compress);
+            match fs::rename(from, rotated(n + 1, rotation.compress)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => (),
+            }
+        }
+        fs::rename(&self.path, rotated(0, false))?;
+        self.file = BufWriter::new(Self::open_file(&self.path)?);
+        self.size = 0;
+        if rotation.compress {
+            compress(&rotated(0, false), &rotated(0, true))?;
+        }
+        Ok(())
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+}
+
+/// Compresses the file at `from` to `to` with gzip, removing `from`
+fn compress(from: &Path, to: &Path) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let mut output = GzEncoder::new(File::create(to)?, Compression::default());
+    io::copy(&mut input, &mut output)?;
+    output.finish()?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn file_sink_rotation_test() {
+        let dir = std::env::temp_dir().join(format!("file_sink_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.log");
+        let rotation = RotationConfig {
+            max_bytes: Some(8),
+            max_files: 2,
+            ..RotationConfig::default()
+        };
+        let mut sink = FileSink::open(&path, Some(rotation)).unwrap();
+
+        sink.write(b"first\n").unwrap();
+        sink.write(b"second\n").unwrap();
+        sink.write(b"third\n").unwrap();
+        // A write larger than the limit is not split
+        sink.write(b"fourth and last\n").unwrap();
+        sink.flush().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"fourth and last\n");
+        assert_eq!(fs::read(dir.join("out.log.0")).unwrap(), b"third\n");
+        assert_eq!(fs::read(dir.join("out.log.1")).unwrap(), b"second\n");
+        assert!(!dir.join("out.log.2").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_sink_compression_test() {
+        let dir = std::env::temp_dir().join(format!("file_sink_gz_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.log");
+        let rotation = RotationConfig {
+            max_bytes: Some(8),
+            max_files: 1,
+            compress: true,
+            ..RotationConfig::default()
+        };
+        let mut sink = FileSink::open(&path, Some(rotation)).unwrap();
+        sink.set_sync_policy(SyncPolicy::Always);
+
+        sink.write(b"first\n").unwrap();
+        sink.write(b"second\n").unwrap();
+        sink.flush().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+        assert!(!dir.join("out.log.0").exists());
+        let mut rotated = String::new();
+        GzDecoder::new(File::open(dir.join("out.log.0.gz")).unwrap())
+            .read_to_string(&mut rotated)
+            .unwrap();
+        assert_eq!(rotated, "first\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
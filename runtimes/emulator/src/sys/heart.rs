@@ -0,0 +1,305 @@
+//! This module implements a heart-style watchdog for the runtime.
+//!
+//! When enabled with the `-heart` flag, a dedicated thread periodically checks that the
+//! schedulers are making progress. While they are, a heartbeat is forwarded to an external
+//! watchdog, if one is configured, so that a supervisor outside of the runtime can detect a
+//! hung system by the absence of heartbeats. If the schedulers stop making progress for longer
+//! than the configured timeout, the runtime considers itself hung, runs the configured reboot
+//! command (if any), and aborts.
+//!
+//! The watchdog is configured with the following environment variables, following ERTS where
+//! possible:
+//!
+//! * `HEART_BEAT_TIMEOUT`, the number of seconds without progress before the system is
+//! considered hung, defaults to 60, and must be at least 10
+//! * `HEART_COMMAND`, a shell command which is run when the system is considered hung, e.g. to
+//! restart the node or reboot the machine
+//! * `HEART_WATCHDOG`, the external watchdog to ping, one of `fd:N` (write to an inherited file
+//! descriptor), `unix:PATH` (write to a unix stream socket), or `systemd` (send `WATCHDOG=1` to
+//! the socket in `NOTIFY_SOCKET`). If unset, `systemd` is used when `NOTIFY_SOCKET` is present.
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{error, warn};
+
+use super::systemd::{self, Notify};
+
+/// The message written to file descriptor and socket watchdogs on each heartbeat
+const HEARTBEAT: &'static [u8] = b"heartbeat\n";
+
+/// Incremented by the schedulers on each iteration of their core loop
+static PROGRESS: AtomicU64 = AtomicU64::new(0);
+
+/// The number of schedulers which are currently parked waiting for work
+static IDLE: AtomicUsize = AtomicUsize::new(0);
+
+/// Records that the calling scheduler has made progress
+#[inline]
+pub fn beat() {
+    PROGRESS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Marks the calling scheduler as idle until the returned guard is dropped
+///
+/// An idle scheduler is not expected to make progress, so it cannot be hung.
+pub fn idle() -> IdleGuard {
+    IDLE.fetch_add(1, Ordering::AcqRel);
+    IdleGuard(())
+}
+
+pub struct IdleGuard(());
+impl Drop for IdleGuard {
+    fn drop(&mut self) {
+        IDLE.fetch_sub(1, Ordering::AcqRel);
+        beat();
+    }
+}
+
+/// The external watchdog which receives heartbeats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Watchdog {
+    /// Heartbeats are written to an inherited file descriptor, e.g. a pipe to a supervisor
+    Fd(i32),
+    /// Heartbeats are written to a unix stream socket at the given path
+    Unix(PathBuf),
+    /// Heartbeats are sent to systemd via the `sd_notify` protocol
+    Systemd(PathBuf),
+}
+
+/// The error produced when the heart configuration contains an invalid value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartConfigError {
+    pub var: &'static str,
+    pub value: String,
+}
+impl fmt::Display for HeartConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value for {}: '{}'", self.var, &self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartConfig {
+    /// How long the schedulers may go without making progress before the system is hung
+    pub timeout: Duration,
+    /// How often the schedulers are checked for progress, and heartbeats are sent
+    pub interval: Duration,
+    /// The shell command to run when the system is hung
+    pub command: Option<String>,
+    pub watchdog: Option<Watchdog>,
+}
+impl HeartConfig {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+    pub const MIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Derives the configuration from the given command-line arguments and the environment
+    ///
+    /// Returns `Ok(None)` if the `-heart` flag was not given.
+    pub fn from_env<I, S>(args: I) -> Result<Option<Self>, HeartConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if !args.into_iter().any(|arg| arg.as_ref() == "-heart") {
+            return Ok(None);
+        }
+        // NOTIFY_SOCKET is claimed from the environment during systemd initialization
+        Self::from_vars(|var| match var {
+            "NOTIFY_SOCKET" => {
+                systemd::notify_socket().map(|path| path.to_string_lossy().into_owned())
+            }
+            _ => env::var(var).ok(),
+        })
+        .map(Some)
+    }
+
+    fn from_vars<F>(var: F) -> Result<Self, HeartConfigError>
+    where
+        F: Fn(&'static str) -> Option<String>,
+    {
+        let invalid = |name: &'static str, value: String| HeartConfigError { var: name, value };
+
+        let timeout = match var("HEART_BEAT_TIMEOUT") {
+            None => Self::DEFAULT_TIMEOUT,
+            Some(value) => match value.parse::<u64>() {
+                Ok(secs) if Duration::from_secs(secs) >= Self::MIN_TIMEOUT => {
+                    Duration::from_secs(secs)
+                }
+                _ => return Err(invalid("HEART_BEAT_TIMEOUT", value)),
+            },
+        };
+        let mut interval = timeout / 4;
+
+        let watchdog = match var("HEART_WATCHDOG") {
+            None => var("NOTIFY_SOCKET").map(|path| Watchdog::Systemd(path.into())),
+            Some(value) if value == "systemd" => match var("NOTIFY_SOCKET") {
+                Some(path) => Some(Watchdog::Systemd(path.into())),
+                None => return Err(invalid("HEART_WATCHDOG", value)),
+            },
+            Some(value) => {
+                if let Some(fd) = value.strip_prefix("fd:") {
+                    match fd.parse::<i32>() {
+                        Ok(fd) if fd >= 0 => Some(Watchdog::Fd(fd)),
+                        _ => return Err(invalid("HEART_WATCHDOG", value)),
+                    }
+                } else if let Some(path) = value.strip_prefix("unix:") {
+                    Some(Watchdog::Unix(path.into()))
+                } else {
+                    return Err(invalid("HEART_WATCHDOG", value));
+                }
+            }
+        };
+
+        // systemd expects to be notified at least once per WATCHDOG_USEC, but recommends
+        // doing so twice as often to allow for scheduling delays
+        if let Some(Watchdog::Systemd(_)) = watchdog {
+            if let Some(usec) = var("WATCHDOG_USEC").and_then(|v| v.parse::<u64>().ok()) {
+                interval = interval.min(Duration::from_micros(usec) / 2);
+            }
+        }
+
+        Ok(Self {
+            timeout,
+            interval,
+            command: var("HEART_COMMAND").filter(|cmd| !cmd.is_empty()),
+            watchdog,
+        })
+    }
+}
+
+/// Starts the heart thread with the given configuration
+///
+/// `schedulers` is the number of schedulers expected to be reporting progress.
+pub fn start(config: HeartConfig, schedulers: usize) -> io::Result<()> {
+    thread::Builder::new()
+        .name("heart".into())
+        .spawn(move || run(config, schedulers))
+        .map(|_| ())
+}
+
+fn run(config: HeartConfig, schedulers: usize) {
+    let mut sink = config.watchdog.as_ref().map(Sink::new);
+    let mut last_progress = PROGRESS.load(Ordering::Relaxed);
+    let mut last_change = Instant::now();
+    loop {
+        thread::sleep(config.interval);
+
+        let progress = PROGRESS.load(Ordering::Relaxed);
+        let all_idle = IDLE.load(Ordering::Acquire) >= schedulers;
+        if progress != last_progress || all_idle {
+            last_progress = progress;
+            last_change = Instant::now();
+            if let Some(sink) = sink.as_mut() {
+                if let Err(err) = sink.send() {
+                    warn!(target: "heart", "unable to send heartbeat to watchdog: {}", err);
+                }
+            }
+            continue;
+        }
+
+        if last_change.elapsed() >= config.timeout {
+            hung(&config);
+        }
+    }
+}
+
+/// Called when the schedulers have not made progress within the configured timeout
+fn hung(config: &HeartConfig) -> ! {
+    error!(target: "heart", "heart: no progress in {}s, the system is considered hung", config.timeout.as_secs());
+    eprintln!(
+        "heart: no progress in {}s, the system is considered hung",
+        config.timeout.as_secs()
+    );
+    if let Some(command) = config.command.as_deref() {
+        match Command::new("/bin/sh").arg("-c").arg(command).spawn() {
+            Ok(_) => eprintln!("heart: executed reboot command '{}'", command),
+            Err(err) => eprintln!(
+                "heart: failed to execute reboot command '{}': {}",
+                command, err
+            ),
+        }
+    }
+    process::abort()
+}
+
+/// The destination of heartbeats for a configured watchdog
+enum Sink {
+    Fd(File),
+    Unix(PathBuf, Option<UnixStream>),
+    Systemd(PathBuf),
+}
+impl Sink {
+    fn new(watchdog: &Watchdog) -> Self {
+        match watchdog {
+            // SAFETY: The descriptor was handed to us explicitly for this purpose, so we own it
+            Watchdog::Fd(fd) => Self::Fd(unsafe { File::from_raw_fd(*fd) }),
+            Watchdog::Unix(path) => Self::Unix(path.clone(), None),
+            Watchdog::Systemd(path) => Self::Systemd(path.clone()),
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        match self {
+            Self::Fd(file) => file.write_all(HEARTBEAT),
+            Self::Unix(path, stream) => {
+                if stream.is_none() {
+                    *stream = Some(UnixStream::connect(&path)?);
+                }
+                let result = stream.as_mut().unwrap().write_all(HEARTBEAT);
+                // Reconnect on the next heartbeat if the watchdog went away
+                if result.is_err() {
+                    *stream = None;
+                }
+                result
+            }
+            Self::Systemd(path) => systemd::notify_to(path.as_os_str(), &[Notify::Watchdog]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn heart_config_from_vars_test() {
+        let config = HeartConfig::from_vars(|var| match var {
+            "HEART_BEAT_TIMEOUT" => Some("20".to_string()),
+            "HEART_COMMAND" => Some("reboot".to_string()),
+            "NOTIFY_SOCKET" => Some("/run/systemd/notify".to_string()),
+            "WATCHDOG_USEC" => Some("4000000".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(20));
+        assert_eq!(config.interval, Duration::from_secs(2));
+        assert_eq!(config.command.as_deref(), Some("reboot"));
+        assert_eq!(
+            config.watchdog,
+            Some(Watchdog::Systemd("/run/systemd/notify".into()))
+        );
+
+        let config = HeartConfig::from_vars(|var| match var {
+            "HEART_WATCHDOG" => Some("fd:3".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.timeout, HeartConfig::DEFAULT_TIMEOUT);
+        assert_eq!(config.watchdog, Some(Watchdog::Fd(3)));
+
+        assert!(HeartConfig::from_vars(|var| match var {
+            "HEART_BEAT_TIMEOUT" => Some("5".to_string()),
+            _ => None,
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Pools of receive buffers, into which sockets receive data directly.
+//!
+//! Each buffer is a reference-counted binary, so data received into it can be handed to a process
+//! without copying, as a sub-binary covering the bytes received. A pool keeps track of the buffers
+//! it has handed out, and reuses a buffer once every binary referencing it has been dropped. When
+//! all of its buffers are in use, a pool allocates a new one rather than waiting, and only keeps it
+//! if the pool is below capacity.
+//!
+//! The default pool is created at startup, and when the io_uring backend is in use, its buffers are
+//! registered with the ring as fixed buffers, which saves the kernel from mapping them on each
+//! operation. The buffers of a fixed pool are allocated up front and live for the lifetime of the
+//! runtime, so unlike other pools, a fixed pool cannot be reconfigured.
+use std::alloc::AllocError;
+use std::io;
+use std::sync::{Arc, OnceLock};
+
+use firefly_alloc::heap::Heap;
+use firefly_rt::gc::Gc;
+use firefly_rt::term::{BinaryData, BitSlice, OpaqueTerm, Term};
+use firefly_system::sync::Mutex;
+
+/// Configures the size and number of buffers in a [`BufferPool`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferPoolConfig {
+    /// The size of each buffer in bytes, which must be larger than `BinaryData::MAX_HEAP_BYTES`
+    pub buffer_size: usize,
+    /// The maximum number of buffers kept by the pool for reuse
+    pub capacity: usize,
+}
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 64 * 1024,
+            capacity: 32,
+        }
+    }
+}
+impl BufferPoolConfig {
+    fn validate(&self) -> io::Result<()> {
+        if self.buffer_size <= BinaryData::MAX_HEAP_BYTES || self.buffer_size > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid receive buffer size",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A shared pool of receive buffers
+#[derive(Clone)]
+pub struct BufferPool(Arc<PoolInner>);
+
+struct PoolInner {
+    state: Mutex<PoolState>,
+    fixed: bool,
+}
+
+struct PoolState {
+    config: BufferPoolConfig,
+    buffers: Vec<RecvBuffer>,
+}
+
+static DEFAULT_POOL: OnceLock<BufferPool> = OnceLock::new();
+
+/// Creates the default pool, see [`default_pool`]
+pub(super) fn init(config: BufferPoolConfig, fixed: bool) -> io::Result<()> {
+    config.validate()?;
+    let pool = if fixed {
+        BufferPool::new_fixed(config)
+    } else {
+        BufferPool::new(config)?
+    };
+    DEFAULT_POOL.set(pool).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "buffer pool initialized twice",
+        )
+    })
+}
+
+/// Returns the pool used by sockets which have not been given their own
+pub fn default_pool() -> &'static BufferPool {
+    DEFAULT_POOL.get_or_init(|| BufferPool::new(BufferPoolConfig::default()).unwrap())
+}
+
+impl BufferPool {
+    pub fn new(config: BufferPoolConfig) -> io::Result<Self> {
+        config.validate()?;
+        Ok(Self(Arc::new(PoolInner {
+            state: Mutex::new(PoolState {
+                config,
+                buffers: Vec::with_capacity(config.capacity),
+            }),
+            fixed: false,
+        })))
+    }
+
+    /// Creates a pool whose buffers are registered with io_uring, if in use
+    ///
+    /// If the buffers cannot be registered, the pool is still usable, but its buffers are not fixed.
+    fn new_fixed(config: BufferPoolConfig) -> Self {
+        #[cfg_attr(not(all(target_os = "linux", feature = "io_uring")), allow(unused_mut))]
+        let mut buffers = (0..config.capacity)
+            .map(|_| RecvBuffer::new(config.buffer_size))
+            .collect::<Vec<_>>();
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if super::backend() == super::Backend::Uring && config.capacity <= u16::MAX as usize {
+            let iovecs = buffers
+                .iter()
+                .map(|buffer| libc::iovec {
+                    iov_base: buffer.as_mut_ptr().cast(),
+                    iov_len: buffer.capacity(),
+                })
+                .collect::<Vec<_>>();
+            match super::uring::register_buffers(&iovecs) {
+                Ok(()) => {
+                    for (index, buffer) in buffers.iter_mut().enumerate() {
+                        buffer.fixed = Some(index as u16);
+                    }
+                }
+                Err(err) => {
+                    log::warn!(target: "io", "failed to register receive buffers with io_uring: {}", err)
+                }
+            }
+        }
+        Self(Arc::new(PoolInner {
+            state: Mutex::new(PoolState { config, buffers }),
+            fixed: true,
+        }))
+    }
+
+    /// Returns the current configuration of this pool
+    pub fn config(&self) -> BufferPoolConfig {
+        self.0.state.lock().config
+    }
+
+    /// Changes the size and number of buffers used by this pool
+    ///
+    /// Buffers which no longer fit the configuration are released as they become free. Fixed pools
+    /// cannot be reconfigured.
+    pub fn configure(&self, config: BufferPoolConfig) -> io::Result<()> {
+        if self.0.fixed {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "fixed buffer pools cannot be reconfigured",
+            ));
+        }
+        config.validate()?;
+        let mut state = self.0.state.lock();
+        state.config = config;
+        state
+            .buffers
+            .retain(|buffer| buffer.capacity() == config.buffer_size || !buffer.is_free());
+        Ok(())
+    }
+
+    /// Returns the number of buffers held by this pool, and how many of those are in use
+    pub fn usage(&self) -> (usize, usize) {
+        let state = self.0.state.lock();
+        let used = state.buffers.iter().filter(|b| !b.is_free()).count();
+        (state.buffers.len(), used)
+    }
+
+    /// Returns a buffer which is not referenced by anything other than this pool
+    pub fn acquire(&self) -> RecvBuffer {
+        let mut state = self.0.state.lock();
+        let buffer_size = state.config.buffer_size;
+        let mut i = 0;
+        while i < state.buffers.len() {
+            let buffer = &state.buffers[i];
+            if !buffer.is_free() {
+                i += 1;
+                continue;
+            }
+            if buffer.capacity() != buffer_size && buffer.fixed.is_none() {
+                state.buffers.swap_remove(i);
+                continue;
+            }
+            return buffer.share();
+        }
+
+        let buffer = RecvBuffer::new(buffer_size);
+        if !self.0.fixed && state.buffers.len() < state.config.capacity {
+            state.buffers.push(buffer.share());
+        }
+        buffer
+    }
+}
+
+/// A buffer to receive into, backed by a reference-counted binary
+pub struct RecvBuffer {
+    data: Arc<BinaryData>,
+    /// The index of this buffer in the io_uring fixed buffer table, if registered
+    fixed: Option<u16>,
+}
+impl RecvBuffer {
+    fn new(size: usize) -> Self {
+        Self {
+            data: BinaryData::with_capacity_large(size),
+            fixed: None,
+        }
+    }
+
+    /// Returns another handle to this buffer, for use by its pool
+    fn share(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            fixed: self.fixed,
+        }
+    }
+
+    /// A buffer is free once its pool holds the only reference to it
+    fn is_free(&self) -> bool {
+        Arc::strong_count(&self.data) == 1
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn fixed_index(&self) -> Option<u16> {
+        self.fixed
+    }
+
+    /// Returns a pointer to the start of the buffer, for use by the kernel
+    ///
+    /// A buffer is only ever written to by the operation which acquired it, and other handles to it
+    /// only check its reference count until it has been filled, so writing through this pointer does
+    /// not race with any reads of the data.
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        let data = Arc::as_ptr(&self.data).cast_mut();
+        unsafe { (*data)[..].as_mut_ptr() }
+    }
+
+    /// Marks the first `len` bytes of the buffer as received
+    pub fn filled(self, len: usize) -> Received {
+        assert!(len <= self.capacity());
+        Received {
+            data: self.data,
+            len,
+        }
+    }
+}
+
+/// Data received into a [`RecvBuffer`]
+pub struct Received {
+    data: Arc<BinaryData>,
+    len: usize,
+}
+impl Received {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    /// Returns the data received as a binary term, without copying it
+    ///
+    /// If the buffer was filled, this is the buffer itself, otherwise it is a sub-binary of it, which
+    /// is allocated on `heap`.
+    pub fn into_term<H: ?Sized + Heap>(self, heap: &H) -> Result<Term, AllocError> {
+        if self.len == self.data.len() {
+            return Ok(Term::RcBinary(self.data));
+        }
+        let mut empty = Gc::<BitSlice>::new_uninit_in(heap)?;
+        let owner: OpaqueTerm = Term::RcBinary(self.data.clone()).into();
+        let slice = unsafe { BitSlice::new(owner, &self.data[..self.len], 0, self.len * 8) };
+        // The slice holds its own reference to the buffer
+        owner.maybe_decrement_refcount();
+        unsafe {
+            empty.write(slice);
+            Ok(Term::RefBinary(empty.assume_init()))
+        }
+    }
+}
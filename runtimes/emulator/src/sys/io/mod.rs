@@ -0,0 +1,432 @@
+//! This module provides the asynchronous I/O primitives used by the file and socket subsystems.
+//!
+//! By default, I/O is performed using readiness notifications from the async runtime's reactor
+//! (i.e. epoll on Linux), with file operations, which are always "ready", run on the blocking
+//! thread pool. When built with the `io_uring` feature, and running on a kernel which supports it,
+//! operations are instead submitted to an io_uring instance, and complete without further system
+//! calls or thread hand-offs. Operations queued on the ring are submitted in batches, each time a
+//! scheduler ticks, or by the completion task if no scheduler gets there first.
+//!
+//! The io_uring backend can be disabled at startup with `+IOu false`. If the ring cannot be set up,
+//! e.g. because the kernel is too old or io_uring is forbidden by a seccomp policy, the epoll
+//! backend is used instead.
+//!
+//! Since the kernel may write into buffers after the operation which owns them is cancelled, all
+//! operations take ownership of their buffer, and hand it back on completion.
+//!
+//! Sockets can also receive directly into reference-counted binaries taken from a [`BufferPool`],
+//! see [`Socket::recv_binary`]. The size and number of buffers in the default pool are set with
+//! `+IObs <bytes>` and `+IObc <count>`.
+//!
+//! Outgoing connections over IPv4 or IPv6 are made with [`connect`], or [`connect_host`], which
+//! races the addresses of a dual-stack host as described in RFC 8305.
+use std::fs;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::sync::{Arc, OnceLock};
+
+use firefly_rt::term::PortStats;
+use firefly_system::sync::Mutex;
+
+use tokio::io::unix::AsyncFd;
+use tokio::runtime::Runtime;
+
+mod buffers;
+mod net;
+#[cfg(all(target_os = "linux", feature = "io_uring"))]
+mod uring;
+
+pub use self::buffers::{default_pool, BufferPool, BufferPoolConfig, Received, RecvBuffer};
+pub use self::net::{connect, connect_any, connect_host, ConnectOptions};
+
+/// The I/O backends supported by the runtime
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// Readiness-based I/O via the async runtime's reactor
+    Epoll,
+    /// Completion-based I/O via io_uring
+    Uring,
+}
+
+/// Configures the I/O subsystem at startup
+#[derive(Debug, Copy, Clone)]
+pub struct IoConfig {
+    /// Whether to use io_uring, if the runtime was built with support for it
+    pub uring: bool,
+    /// The number of submission queue entries of the ring
+    pub uring_entries: u32,
+    /// The configuration of the default receive buffer pool
+    pub recv_buffers: BufferPoolConfig,
+}
+impl Default for IoConfig {
+    fn default() -> Self {
+        Self {
+            uring: true,
+            uring_entries: 256,
+            recv_buffers: BufferPoolConfig::default(),
+        }
+    }
+}
+impl IoConfig {
+    /// Parses the I/O configuration from the `+IOu true|false`, `+IObs <bytes>` and
+    /// `+IObc <count>` flags
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut config = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "+IOu" => {
+                    let value = args.next();
+                    match value.as_deref() {
+                        Some("true") => config.uring = true,
+                        Some("false") => config.uring = false,
+                        _ => eprintln!(
+                            "Ignoring invalid +IOu value, expected one of [true, false], got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                "+IObs" => {
+                    let value = args.next();
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(size)) => config.recv_buffers.buffer_size = size,
+                        _ => eprintln!(
+                            "Ignoring invalid +IObs value, expected a size in bytes, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                "+IObc" => {
+                    let value = args.next();
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(count)) => config.recv_buffers.capacity = count,
+                        _ => eprintln!(
+                            "Ignoring invalid +IObc value, expected a buffer count, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                _ => (),
+            }
+        }
+        config
+    }
+}
+
+static BACKEND: OnceLock<Backend> = OnceLock::new();
+
+/// Initializes the I/O subsystem, returning the backend selected
+///
+/// This must be called once at startup, after the async runtime is created, but before any
+/// scheduler is started.
+pub fn init(runtime: &Runtime, config: IoConfig) -> Backend {
+    let backend = select_backend(runtime, config);
+    BACKEND
+        .set(backend)
+        .expect("i/o subsystem initialized twice");
+    if let Err(err) = buffers::init(config.recv_buffers, backend == Backend::Uring) {
+        log::warn!(target: "io", "invalid receive buffer configuration, using defaults: {}", err);
+    }
+    backend
+}
+
+#[cfg(all(target_os = "linux", feature = "io_uring"))]
+fn select_backend(runtime: &Runtime, config: IoConfig) -> Backend {
+    if !config.uring {
+        return Backend::Epoll;
+    }
+    match uring::init(runtime, config.uring_entries) {
+        Ok(()) => Backend::Uring,
+        Err(err) => {
+            log::warn!(target: "io", "io_uring is unavailable, falling back to epoll: {}", err);
+            Backend::Epoll
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "io_uring")))]
+fn select_backend(_runtime: &Runtime, _config: IoConfig) -> Backend {
+    Backend::Epoll
+}
+
+/// Returns the backend in use
+#[inline]
+pub fn backend() -> Backend {
+    BACKEND.get().copied().unwrap_or(Backend::Epoll)
+}
+
+/// Submits any operations which have been queued since the last flush
+///
+/// This is called by each scheduler on every tick, and is cheap when nothing is queued.
+#[inline]
+pub fn flush() {
+    #[cfg(all(target_os = "linux", feature = "io_uring"))]
+    if backend() == Backend::Uring {
+        uring::flush();
+    }
+}
+
+/// The result of an operation, along with the buffer it was given
+pub type BufResult = (io::Result<usize>, Vec<u8>);
+
+/// A file opened for use with the I/O subsystem
+#[derive(Clone)]
+pub struct File(Arc<fs::File>);
+impl File {
+    pub fn new(file: fs::File) -> Self {
+        Self(Arc::new(file))
+    }
+
+    /// Reads up to `buf.capacity()` bytes at `offset` into `buf`, replacing its contents
+    pub async fn read_at(&self, buf: Vec<u8>, offset: u64) -> BufResult {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if backend() == Backend::Uring {
+            return uring::read_at(self.0.as_raw_fd(), buf, offset).await;
+        }
+        let file = self.0.clone();
+        blocking(buf, move |buf| {
+            buf.resize(buf.capacity(), 0);
+            let result = file.read_at(buf.as_mut_slice(), offset);
+            buf.truncate(*result.as_ref().unwrap_or(&0));
+            result
+        })
+        .await
+    }
+
+    /// Writes the contents of `buf` at `offset`, returning the number of bytes written
+    pub async fn write_at(&self, buf: Vec<u8>, offset: u64) -> BufResult {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if backend() == Backend::Uring {
+            return uring::write_at(self.0.as_raw_fd(), buf, offset).await;
+        }
+        let file = self.0.clone();
+        blocking(buf, move |buf| file.write_at(buf.as_slice(), offset)).await
+    }
+}
+
+/// Runs `op` on `buf` using the blocking thread pool
+async fn blocking<F>(buf: Vec<u8>, op: F) -> BufResult
+where
+    F: FnOnce(&mut Vec<u8>) -> io::Result<usize> + Send + 'static,
+{
+    let mut buf = buf;
+    let task = tokio::task::spawn_blocking(move || {
+        let result = op(&mut buf);
+        (result, buf)
+    });
+    match task.await {
+        Ok(result) => result,
+        Err(err) => (Err(io::Error::new(io::ErrorKind::Other, err)), Vec::new()),
+    }
+}
+
+/// A non-blocking socket registered for use with the I/O subsystem
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+    /// The pool used by `recv_binary`, if other than the default
+    buffers: Mutex<Option<BufferPool>>,
+    stats: Arc<PortStats>,
+}
+impl Socket {
+    /// Registers `fd`, which must be in non-blocking mode
+    pub fn new(fd: OwnedFd) -> io::Result<Self> {
+        Self::with_stats(fd, Arc::new(PortStats::new()))
+    }
+
+    /// Like `new`, but recording statistics in `stats`, which is typically shared with the port
+    /// which owns the socket, so that they can be read with `inet:getstat/2`
+    pub fn with_stats(fd: OwnedFd, stats: Arc<PortStats>) -> io::Result<Self> {
+        Ok(Self {
+            fd: AsyncFd::new(fd)?,
+            buffers: Mutex::new(None),
+            stats,
+        })
+    }
+
+    /// Returns the statistics recorded for this socket
+    #[inline]
+    pub fn stats(&self) -> &Arc<PortStats> {
+        &self.stats
+    }
+
+    fn record_recv(&self, result: Result<usize, &io::Error>) {
+        match result {
+            Ok(size) => self.stats.record_recv(size),
+            Err(_) => self.stats.record_recv_error(),
+        }
+    }
+
+    fn record_send(&self, result: Result<usize, &io::Error>) {
+        match result {
+            Ok(size) => self.stats.record_send(size),
+            Err(_) => self.stats.record_send_error(),
+        }
+    }
+
+    #[inline]
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Sets the pool this socket receives binaries into, or resets it to the default pool
+    pub fn set_recv_buffers(&self, pool: Option<BufferPool>) {
+        *self.buffers.lock() = pool;
+    }
+
+    /// Returns the pool this socket receives binaries into
+    pub fn recv_buffers(&self) -> BufferPool {
+        self.buffers
+            .lock()
+            .clone()
+            .unwrap_or_else(|| default_pool().clone())
+    }
+
+    /// Receives into a buffer from this socket's pool, returning the data received
+    ///
+    /// The data can be converted to a binary term without copying, see [`Received::into_term`].
+    pub async fn recv_binary(&self) -> io::Result<Received> {
+        let result = self.recv_into_buffer().await;
+        self.record_recv(result.as_ref().map(Received::len));
+        result
+    }
+
+    async fn recv_into_buffer(&self) -> io::Result<Received> {
+        let buffer = self.recv_buffers().acquire();
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if backend() == Backend::Uring {
+            let (result, buffer) = match uring::recv_buffer(self.as_raw_fd(), buffer).await {
+                // See `recv`
+                (Err(err), buffer) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.fd.readable().await?.clear_ready();
+                    uring::recv_buffer(self.as_raw_fd(), buffer).await
+                }
+                result => result,
+            };
+            return result.map(|len| buffer.filled(len));
+        }
+        loop {
+            let mut guard = self.fd.readable().await?;
+            match guard.try_io(|fd| {
+                let result = unsafe {
+                    libc::recv(
+                        fd.as_raw_fd(),
+                        buffer.as_mut_ptr().cast(),
+                        buffer.capacity(),
+                        0,
+                    )
+                };
+                cvt(result)
+            }) {
+                Ok(result) => return result.map(|len| buffer.filled(len)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Receives up to `buf.capacity()` bytes into `buf`, replacing its contents
+    pub async fn recv(&self, buf: Vec<u8>) -> BufResult {
+        let (result, buf) = self.recv_into(buf).await;
+        self.record_recv(result.as_ref().copied());
+        (result, buf)
+    }
+
+    async fn recv_into(&self, mut buf: Vec<u8>) -> BufResult {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if backend() == Backend::Uring {
+            match uring::recv(self.as_raw_fd(), buf).await {
+                // The ring does not wait for readiness on a non-blocking socket on older kernels, so
+                // in that case we wait for it here, and then try again on the ring
+                (Err(err), returned) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if let Err(err) = self
+                        .fd
+                        .readable()
+                        .await
+                        .map(|mut guard| guard.clear_ready())
+                    {
+                        return (Err(err), returned);
+                    }
+                    return uring::recv(self.as_raw_fd(), returned).await;
+                }
+                result => return result,
+            }
+        }
+        buf.resize(buf.capacity(), 0);
+        loop {
+            let mut guard = match self.fd.readable().await {
+                Ok(guard) => guard,
+                Err(err) => {
+                    buf.clear();
+                    return (Err(err), buf);
+                }
+            };
+            match guard.try_io(|fd| {
+                let result =
+                    unsafe { libc::recv(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+                cvt(result)
+            }) {
+                Ok(result) => {
+                    buf.truncate(*result.as_ref().unwrap_or(&0));
+                    return (result, buf);
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Sends the contents of `buf`, returning the number of bytes sent
+    pub async fn send(&self, buf: Vec<u8>) -> BufResult {
+        let (result, buf) = self.send_from(buf).await;
+        self.record_send(result.as_ref().copied());
+        (result, buf)
+    }
+
+    async fn send_from(&self, buf: Vec<u8>) -> BufResult {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if backend() == Backend::Uring {
+            match uring::send(self.as_raw_fd(), buf).await {
+                (Err(err), returned) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if let Err(err) = self
+                        .fd
+                        .writable()
+                        .await
+                        .map(|mut guard| guard.clear_ready())
+                    {
+                        return (Err(err), returned);
+                    }
+                    return uring::send(self.as_raw_fd(), returned).await;
+                }
+                result => return result,
+            }
+        }
+        loop {
+            let mut guard = match self.fd.writable().await {
+                Ok(guard) => guard,
+                Err(err) => return (Err(err), buf),
+            };
+            match guard.try_io(|fd| {
+                let result = unsafe {
+                    libc::send(fd.as_raw_fd(), buf.as_ptr().cast(), buf.len(), SEND_FLAGS)
+                };
+                cvt(result)
+            }) {
+                Ok(result) => return (result, buf),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// Broken connections are reported as errors rather than by raising SIGPIPE
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const SEND_FLAGS: libc::c_int = 0;
+
+/// Converts the result of a system call returning a byte count into an `io::Result`
+fn cvt(result: isize) -> io::Result<usize> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result as usize)
+    }
+}
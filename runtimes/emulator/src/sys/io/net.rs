@@ -0,0 +1,242 @@
+//! Outgoing TCP connections, over IPv4 or IPv6.
+//!
+//! Connecting to a hostname uses the "Happy Eyeballs" algorithm of RFC 8305: the addresses the
+//! name resolves to are interleaved by family, starting with the family of the first address
+//! returned by the resolver, and a connection attempt is started for each of them in turn. Each
+//! attempt is given a head start of `ConnectOptions::attempt_delay` before the next one begins, or
+//! less if it fails first, and the first connection to be established wins, cancelling the others.
+//! This avoids long stalls on hosts where one of the families is configured, but not routable.
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+use tokio::task::JoinSet;
+
+use super::Socket;
+
+/// Options for outgoing connections
+#[derive(Debug, Copy, Clone)]
+pub struct ConnectOptions {
+    /// If set, controls whether IPv6 sockets are restricted to IPv6 traffic, i.e. `IPV6_V6ONLY`
+    pub v6only: Option<bool>,
+    /// How long a connection attempt runs before the next one is started
+    pub attempt_delay: Duration,
+}
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            v6only: None,
+            // The recommended value from RFC 8305, section 8
+            attempt_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Connects to `addr`
+pub async fn connect(addr: SocketAddr, options: &ConnectOptions) -> io::Result<Socket> {
+    let domain = match addr {
+        SocketAddr::V4(_) => libc::AF_INET,
+        SocketAddr::V6(_) => libc::AF_INET6,
+    };
+    let fd = cvt(unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) })?;
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    set_flag(&fd, libc::F_GETFD, libc::F_SETFD, libc::FD_CLOEXEC)?;
+    set_flag(&fd, libc::F_GETFL, libc::F_SETFL, libc::O_NONBLOCK)?;
+    if let (SocketAddr::V6(_), Some(v6only)) = (addr, options.v6only) {
+        setsockopt(
+            &fd,
+            libc::IPPROTO_IPV6,
+            libc::IPV6_V6ONLY,
+            v6only as libc::c_int,
+        )?;
+    }
+
+    let (storage, len) = to_sockaddr(addr);
+    let result = unsafe {
+        libc::connect(
+            fd.as_raw_fd(),
+            (&storage as *const libc::sockaddr_storage).cast(),
+            len,
+        )
+    };
+    if result < 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(err);
+        }
+    }
+
+    // The socket becomes writable once the connection is established or has failed
+    let socket = Socket::new(fd)?;
+    socket.fd.writable().await?.retain_ready();
+    let mut error: libc::c_int = 0;
+    let mut error_len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    cvt(unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            (&mut error as *mut libc::c_int).cast(),
+            &mut error_len,
+        )
+    })?;
+    if error != 0 {
+        return Err(io::Error::from_raw_os_error(error));
+    }
+    Ok(socket)
+}
+
+/// Connects to `port` on `host`, which may be a hostname, or an IPv4 or IPv6 address
+///
+/// See the module documentation for how multiple addresses are handled.
+pub async fn connect_host(host: &str, port: u16, options: &ConnectOptions) -> io::Result<Socket> {
+    let addrs = tokio::net::lookup_host((host, port))
+        .await?
+        .collect::<Vec<_>>();
+    connect_any(addrs, options).await
+}
+
+/// Connects to the first of `addrs` to accept a connection, racing them as described in RFC 8305
+pub async fn connect_any(addrs: Vec<SocketAddr>, options: &ConnectOptions) -> io::Result<Socket> {
+    let mut addrs = interleave(addrs).into_iter();
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+    loop {
+        if let Some(addr) = addrs.next() {
+            let options = *options;
+            attempts.spawn(async move { connect(addr, &options).await });
+        }
+        if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+            }));
+        }
+        let finished = if addrs.len() > 0 {
+            tokio::select! {
+                finished = attempts.join_next() => finished,
+                _ = tokio::time::sleep(options.attempt_delay) => continue,
+            }
+        } else {
+            attempts.join_next().await
+        };
+        // When an attempt fails, the next one is started right away
+        match finished {
+            Some(Ok(Ok(socket))) => return Ok(socket),
+            Some(Ok(Err(err))) => last_error = Some(err),
+            Some(Err(err)) => last_error = Some(io::Error::new(io::ErrorKind::Other, err)),
+            None => (),
+        }
+    }
+}
+
+/// Orders `addrs` so that address families alternate, starting with the family of the first
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first().copied() else { return addrs; };
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first.is_ipv6());
+    preferred.reverse();
+    other.reverse();
+    let mut interleaved = Vec::with_capacity(preferred.len() + other.len());
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+    interleaved
+}
+
+fn to_sockaddr(addr: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr = libc::in_addr {
+                s_addr: u32::from_ne_bytes(addr.ip().octets()),
+            };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr = libc::in6_addr {
+                s6_addr: addr.ip().octets(),
+            };
+            sin6.sin6_scope_id = addr.scope_id();
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, len as libc::socklen_t)
+}
+
+fn set_flag(fd: &OwnedFd, get: libc::c_int, set: libc::c_int, flag: libc::c_int) -> io::Result<()> {
+    let flags = cvt(unsafe { libc::fcntl(fd.as_raw_fd(), get) })?;
+    cvt(unsafe { libc::fcntl(fd.as_raw_fd(), set, flags | flag) })?;
+    Ok(())
+}
+
+fn setsockopt(
+    fd: &OwnedFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    cvt(unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            level,
+            name,
+            (&value as *const libc::c_int).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    })?;
+    Ok(())
+}
+
+fn cvt(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interleave_by_family() {
+        let addrs = [
+            "[::1]:80",
+            "[::2]:80",
+            "127.0.0.1:80",
+            "[::3]:80",
+            "127.0.0.2:80",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect::<Vec<SocketAddr>>();
+        let interleaved = interleave(addrs)
+            .into_iter()
+            .map(|addr| addr.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            interleaved,
+            [
+                "[::1]:80",
+                "127.0.0.1:80",
+                "[::2]:80",
+                "127.0.0.2:80",
+                "[::3]:80"
+            ]
+        );
+    }
+}
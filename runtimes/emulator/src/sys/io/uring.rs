@@ -0,0 +1,345 @@
+//! The io_uring backend of the I/O subsystem.
+//!
+//! There is a single ring shared by all schedulers. Operations are pushed onto its submission
+//! queue as they are started, but are only submitted to the kernel by [`flush`], which is called
+//! on every scheduler tick, so that operations started in the same tick share a system call. The
+//! ring signals completions on an eventfd, which is watched by a task on the async runtime; that
+//! task reaps completions, wakes the futures waiting on them, and also flushes any operations
+//! which were queued while all schedulers were idle.
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::task::{Context, Poll, Waker};
+
+use firefly_system::sync::Mutex;
+use io_uring::{opcode, squeue, types, IoUring};
+use tokio::io::unix::AsyncFd;
+use tokio::runtime::Runtime;
+use tokio::sync::Notify;
+
+use super::buffers::RecvBuffer;
+use super::BufResult;
+
+static RING: OnceLock<Ring> = OnceLock::new();
+
+struct Ring {
+    inner: Mutex<RingInner>,
+    /// The number of operations pushed onto the submission queue but not yet submitted
+    queued: AtomicUsize,
+    /// Notifies the completion task that operations were queued
+    queued_notify: Notify,
+}
+
+struct RingInner {
+    uring: IoUring,
+    ops: HashMap<u64, Op>,
+    next_id: u64,
+}
+
+/// The state of an operation which has been started
+struct Op {
+    /// The buffer is owned by the ring until the operation completes, as the kernel may write
+    /// into it even if the future waiting on the operation is dropped
+    buf: OpBuf,
+    state: OpState,
+}
+
+/// The buffers which operations can be performed on
+enum OpBuf {
+    Vec(Vec<u8>),
+    Recv(RecvBuffer),
+}
+impl OpBuf {
+    fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::Vec(buf) => buf,
+            Self::Recv(_) => unreachable!(),
+        }
+    }
+
+    fn into_recv_buffer(self) -> RecvBuffer {
+        match self {
+            Self::Recv(buf) => buf,
+            Self::Vec(_) => unreachable!(),
+        }
+    }
+}
+
+enum OpState {
+    Waiting(Option<Waker>),
+    Completed(i32),
+    /// The future waiting on the operation was dropped, so the buffer is freed on completion
+    Abandoned,
+}
+
+/// Sets up the ring and starts the completion task on `runtime`
+pub fn init(runtime: &Runtime, entries: u32) -> io::Result<()> {
+    let uring = IoUring::new(entries)?;
+    let eventfd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+    if eventfd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let eventfd = unsafe { OwnedFd::from_raw_fd(eventfd) };
+    uring.submitter().register_eventfd(eventfd.as_raw_fd())?;
+
+    let ring = Ring {
+        inner: Mutex::new(RingInner {
+            uring,
+            ops: HashMap::new(),
+            next_id: 0,
+        }),
+        queued: AtomicUsize::new(0),
+        queued_notify: Notify::new(),
+    };
+    if RING.set(ring).is_err() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "io_uring backend initialized twice",
+        ));
+    }
+
+    let _guard = runtime.enter();
+    let eventfd = AsyncFd::new(eventfd)?;
+    runtime.spawn(complete(eventfd));
+    Ok(())
+}
+
+#[inline]
+fn ring() -> &'static Ring {
+    RING.get().expect("io_uring backend is not initialized")
+}
+
+/// Submits all queued operations to the kernel
+pub fn flush() {
+    let ring = ring();
+    if ring.queued.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    let inner = ring.inner.lock();
+    submit(ring, &inner);
+}
+
+fn submit(ring: &Ring, inner: &RingInner) {
+    let queued = ring.queued.swap(0, Ordering::AcqRel);
+    if queued == 0 {
+        return;
+    }
+    if let Err(err) = inner.uring.submit() {
+        // Any unsubmitted entries remain in the queue, and will be retried on the next flush
+        log::warn!(target: "io", "failed to submit {} operations to io_uring: {}", queued, err);
+        ring.queued.fetch_add(queued, Ordering::AcqRel);
+    }
+}
+
+/// Waits for completions to be signaled on `eventfd`, or for operations to be queued, and handles
+/// them, for as long as the runtime is running
+async fn complete(eventfd: AsyncFd<OwnedFd>) {
+    let ring = ring();
+    loop {
+        tokio::select! {
+            readable = eventfd.readable() => match readable {
+                Ok(mut guard) => {
+                    // Reset the eventfd counter, we don't care about its value
+                    let fd = guard.get_inner().as_raw_fd();
+                    let mut value = 0u64;
+                    unsafe {
+                        libc::read(fd, (&mut value as *mut u64).cast(), 8);
+                    }
+                    guard.clear_ready();
+                }
+                Err(err) => {
+                    log::error!(target: "io", "stopped handling io_uring completions: {}", err);
+                    return;
+                }
+            },
+            // Operations queued while the schedulers are idle would otherwise not be submitted
+            _ = ring.queued_notify.notified() => (),
+        }
+        let mut inner = ring.inner.lock();
+        submit(ring, &inner);
+        reap(&mut inner);
+    }
+}
+
+/// Handles all available completions
+fn reap(inner: &mut RingInner) {
+    let RingInner {
+        ref mut uring,
+        ref mut ops,
+        ..
+    } = inner;
+    for cqe in uring.completion() {
+        let id = cqe.user_data();
+        let Some(op) = ops.get_mut(&id) else { continue; };
+        match core::mem::replace(&mut op.state, OpState::Completed(cqe.result())) {
+            OpState::Waiting(waker) => {
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+            OpState::Abandoned => {
+                ops.remove(&id);
+            }
+            OpState::Completed(_) => unreachable!(),
+        }
+    }
+}
+
+/// Registers `buffers` with the ring as fixed buffers, see [`recv_buffer`]
+///
+/// The buffers must remain valid for as long as the ring exists.
+pub fn register_buffers(buffers: &[libc::iovec]) -> io::Result<()> {
+    let inner = ring().inner.lock();
+    unsafe { inner.uring.submitter().register_buffers(buffers) }
+}
+
+/// Queues an operation on the ring, built by `build` from a pointer to `buf` and its capacity
+fn start<F>(buf: OpBuf, build: F) -> Completion
+where
+    F: FnOnce(*mut u8, u32) -> squeue::Entry,
+{
+    let ring = ring();
+    let mut inner = ring.inner.lock();
+    let id = inner.next_id;
+    inner.next_id = inner.next_id.wrapping_add(1);
+    let mut op = Op {
+        buf,
+        state: OpState::Waiting(None),
+    };
+    let (ptr, len) = match op.buf {
+        OpBuf::Vec(ref mut buf) => (buf.as_mut_ptr(), buf.capacity()),
+        OpBuf::Recv(ref buf) => (buf.as_mut_ptr(), buf.capacity()),
+    };
+    let entry = build(ptr, len.min(u32::MAX as usize) as u32).user_data(id);
+    inner.ops.insert(id, op);
+
+    let mut pushed = unsafe { inner.uring.submission().push(&entry).is_ok() };
+    if !pushed {
+        // The submission queue is full, so submit what is queued to make room
+        submit(ring, &inner);
+        pushed = unsafe { inner.uring.submission().push(&entry).is_ok() };
+    }
+    if !pushed {
+        let op = inner.ops.get_mut(&id).unwrap();
+        op.state = OpState::Completed(-libc::EBUSY);
+    } else if ring.queued.fetch_add(1, Ordering::AcqRel) == 0 {
+        ring.queued_notify.notify_one();
+    }
+    Completion { id, done: false }
+}
+
+pub async fn read_at(fd: RawFd, mut buf: Vec<u8>, offset: u64) -> BufResult {
+    buf.clear();
+    let (result, buf) = start(OpBuf::Vec(buf), |ptr, len| {
+        opcode::Read::new(types::Fd(fd), ptr, len)
+            .offset(offset as _)
+            .build()
+    })
+    .await;
+    (result, buf.into_vec())
+}
+
+pub async fn write_at(fd: RawFd, buf: Vec<u8>, offset: u64) -> BufResult {
+    let len = buf.len() as u32;
+    let (result, buf) = start(OpBuf::Vec(buf), |ptr, _| {
+        opcode::Write::new(types::Fd(fd), ptr, len)
+            .offset(offset as _)
+            .build()
+    })
+    .await;
+    (result, buf.into_vec())
+}
+
+pub async fn recv(fd: RawFd, mut buf: Vec<u8>) -> BufResult {
+    buf.clear();
+    let (result, buf) = start(OpBuf::Vec(buf), |ptr, len| {
+        opcode::Recv::new(types::Fd(fd), ptr, len).build()
+    })
+    .await;
+    (result, buf.into_vec())
+}
+
+/// Receives into `buffer`, using its fixed buffer index if it was registered with the ring
+pub async fn recv_buffer(fd: RawFd, buffer: RecvBuffer) -> (io::Result<usize>, RecvBuffer) {
+    let fixed = buffer.fixed_index();
+    let (result, buffer) = start(OpBuf::Recv(buffer), |ptr, len| match fixed {
+        // A read on a socket is a recv without flags
+        Some(index) => opcode::ReadFixed::new(types::Fd(fd), ptr, len, index).build(),
+        None => opcode::Recv::new(types::Fd(fd), ptr, len).build(),
+    })
+    .await;
+    (result, buffer.into_recv_buffer())
+}
+
+pub async fn send(fd: RawFd, buf: Vec<u8>) -> BufResult {
+    let len = buf.len() as u32;
+    let (result, buf) = start(OpBuf::Vec(buf), |ptr, _| {
+        opcode::Send::new(types::Fd(fd), ptr, len)
+            .flags(libc::MSG_NOSIGNAL)
+            .build()
+    })
+    .await;
+    (result, buf.into_vec())
+}
+
+/// A future which resolves when the operation `id` completes
+struct Completion {
+    id: u64,
+    done: bool,
+}
+impl Future for Completion {
+    type Output = (io::Result<usize>, OpBuf);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = ring().inner.lock();
+        let op = inner.ops.get_mut(&self.id).unwrap();
+        match op.state {
+            OpState::Waiting(ref mut waker) => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            OpState::Completed(result) => {
+                let mut op = inner.ops.remove(&self.id).unwrap();
+                self.done = true;
+                if result < 0 {
+                    if let OpBuf::Vec(ref mut buf) = op.buf {
+                        buf.clear();
+                    }
+                    Poll::Ready((Err(io::Error::from_raw_os_error(-result)), op.buf))
+                } else {
+                    // For reads, the kernel has initialized this many bytes of the buffer
+                    let written = result as usize;
+                    match op.buf {
+                        OpBuf::Vec(ref mut buf) if buf.is_empty() => unsafe {
+                            buf.set_len(written);
+                        },
+                        _ => (),
+                    }
+                    Poll::Ready((Ok(written), op.buf))
+                }
+            }
+            OpState::Abandoned => unreachable!(),
+        }
+    }
+}
+impl Drop for Completion {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut inner = ring().inner.lock();
+        match inner.ops.get(&self.id).map(|op| &op.state) {
+            Some(OpState::Completed(_)) => {
+                inner.ops.remove(&self.id);
+            }
+            Some(_) => {
+                inner.ops.get_mut(&self.id).unwrap().state = OpState::Abandoned;
+            }
+            None => (),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! This module implements the native logger handler, which writes the log events generated by the
+//! runtime to a file, rather than relaying them to the `logger` process, for deployments which
+//! do not run one, e.g. headless ones.
+//!
+//! It is enabled with the `-log_file Path` flag, and configured with the following flags:
+//!
+//! * `-log_file_max_bytes Bytes`, `-log_file_rotate_interval Seconds`, `-log_file_max_files Count`
+//! and `-log_file_compress true|false`, which control rotation, see [`RotationConfig`]. Rotation is
+//! disabled unless at least one of these flags is given.
+//! * `-log_file_sync never|always|Milliseconds`, the [`SyncPolicy`], defaults to `never`
+//! * `-log_file_drop_qlen Count`, the number of events which may be waiting to be written, beyond
+//! which new events are dropped, defaults to 200. Once the writer catches up, the number of events
+//! dropped is logged.
+//!
+//! Events are written by a dedicated thread, so logging never blocks on the file.
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use super::file_sink::{FileSink, RotationConfig, SyncPolicy};
+
+static SENDER: OnceLock<SyncSender<String>> = OnceLock::new();
+
+/// The number of events dropped since the writer last caught up
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// How often buffered events are flushed when no new events arrive
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileConfig {
+    pub path: PathBuf,
+    pub rotation: Option<RotationConfig>,
+    pub sync: SyncPolicy,
+    pub drop_qlen: usize,
+}
+impl LogFileConfig {
+    pub const DEFAULT_DROP_QLEN: usize = 200;
+
+    /// Parses the configuration from the given command-line arguments
+    ///
+    /// Returns `None` if the `-log_file` flag was not given.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Option<Self> {
+        let mut path = None;
+        let mut max_bytes = None;
+        let mut interval = None;
+        let mut max_files = None;
+        let mut compress = None;
+        let mut sync = SyncPolicy::default();
+        let mut drop_qlen = Self::DEFAULT_DROP_QLEN;
+        while let Some(arg) = args.next() {
+            let flag = arg.as_str();
+            if flag == "-log_file" {
+                path = args.next().map(PathBuf::from);
+                continue;
+            }
+            if !flag.starts_with("-log_file_") {
+                continue;
+            }
+            let value = args.next().unwrap_or_default();
+            let valid = match flag {
+                "-log_file_max_bytes" => value.parse::<u64>().ok().filter(|n| *n > 0).map(|n| {
+                    max_bytes = Some(n);
+                }),
+                "-log_file_rotate_interval" => {
+                    value.parse::<u64>().ok().filter(|n| *n > 0).map(|n| {
+                        interval = Some(Duration::from_secs(n));
+                    })
+                }
+                "-log_file_max_files" => value.parse::<usize>().ok().map(|n| max_files = Some(n)),
+                "-log_file_compress" => value.parse::<bool>().ok().map(|b| compress = Some(b)),
+                "-log_file_sync" => SyncPolicy::parse(&value).map(|policy| sync = policy),
+                "-log_file_drop_qlen" => value.parse::<usize>().ok().map(|n| drop_qlen = n),
+                _ => continue,
+            };
+            if valid.is_none() {
+                eprintln!("Ignoring invalid {} value '{}'", flag, &value);
+            }
+        }
+        let rotated =
+            max_bytes.is_some() || interval.is_some() || max_files.is_some() || compress.is_some();
+        let rotation = rotated.then(|| {
+            let default = RotationConfig::default();
+            RotationConfig {
+                max_bytes: max_bytes.or(default.max_bytes),
+                interval,
+                max_files: max_files.unwrap_or(default.max_files),
+                compress: compress.unwrap_or(default.compress),
+            }
+        });
+        Some(Self {
+            path: path?,
+            rotation,
+            sync,
+            drop_qlen,
+        })
+    }
+}
+
+/// Opens the log file and starts the thread which writes to it
+///
+/// This must be called at most once, before the system dispatcher is started.
+pub fn start(config: LogFileConfig) -> io::Result<()> {
+    let mut sink = FileSink::open(config.path, config.rotation)?;
+    sink.set_sync_policy(config.sync);
+    let (sender, receiver) = mpsc::sync_channel(config.drop_qlen);
+    if SENDER.set(sender).is_err() {
+        panic!("tried to start the log file twice!");
+    }
+    thread::Builder::new()
+        .name("log_file".into())
+        .spawn(move || run(sink, receiver))
+        .map(|_| ())
+}
+
+/// Returns true if log events are written to a file
+#[inline]
+pub fn is_enabled() -> bool {
+    SENDER.get().is_some()
+}
+
+/// Queues `text` to be written to the log file, or drops it if too many events are queued
+pub fn log(text: String) {
+    let Some(sender) = SENDER.get() else { return; };
+    match sender.try_send(text) {
+        Ok(_) | Err(TrySendError::Disconnected(_)) => (),
+        Err(TrySendError::Full(_)) => {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn run(mut sink: FileSink, receiver: Receiver<String>) {
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(text) => {
+                write(&mut sink, &text);
+                // Write everything which is already queued before flushing
+                for text in receiver.try_iter() {
+                    write(&mut sink, &text);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let dropped = DROPPED.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            let text = format!(
+                "=WARNING REPORT====\nlog_file: {} events dropped due to overload\n",
+                dropped
+            );
+            write(&mut sink, &text);
+        }
+        if let Err(err) = sink.flush() {
+            eprintln!("unable to flush {}: {}", sink.path().display(), err);
+        }
+    }
+}
+
+fn write(sink: &mut FileSink, text: &str) {
+    if let Err(err) = sink.write(text.as_bytes()) {
+        // Don't lose the event entirely
+        eprintln!("unable to write to {}: {}", sink.path().display(), err);
+        eprint!("{}", text);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn log_file_config_test() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            LogFileConfig::from_args(args(&["-log_file_sync", "always"]).into_iter()),
+            None
+        );
+
+        let config = LogFileConfig::from_args(
+            args(&[
+                "-log_file",
+                "system.log",
+                "-log_file_rotate_interval",
+                "3600",
+                "-log_file_compress",
+                "true",
+                "-log_file_sync",
+                "500",
+                "-log_file_drop_qlen",
+                "bogus",
+            ])
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(config.path, PathBuf::from("system.log"));
+        assert_eq!(
+            config.rotation,
+            Some(RotationConfig {
+                interval: Some(Duration::from_secs(3600)),
+                compress: true,
+                ..RotationConfig::default()
+            })
+        );
+        assert_eq!(
+            config.sync,
+            SyncPolicy::Interval(Duration::from_millis(500))
+        );
+        assert_eq!(config.drop_qlen, LogFileConfig::DEFAULT_DROP_QLEN);
+    }
+}
@@ -0,0 +1,210 @@
+//! This module connects the runtime to sources of memory pressure notifications, and reacts to
+//! them by proactively reclaiming memory.
+//!
+//! On Linux, pressure stall information (PSI) is used to detect memory pressure, preferring the
+//! cgroup the runtime belongs to, so that limits imposed by a container are respected, and falling
+//! back to the system-wide pressure of the host. Pressure may also be reported explicitly via
+//! `firefly_rt::services::memory_pressure::notify`.
+//!
+//! In response to any pressure, a full sweep collection is scheduled for every live process. Under
+//! critical pressure, memory which has been freed is also returned to the operating system where
+//! the allocator supports it.
+//!
+//! PSI triggers can't be checkpointed, so they are closed before a checkpoint is taken, and opened
+//! again afterwards, possibly for a different cgroup, if the node was restored elsewhere.
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+
+#[cfg(target_os = "linux")]
+use firefly_rt::services::checkpoint::{self, CheckpointHook};
+use firefly_rt::services::memory_pressure::{self, PressureLevel};
+use firefly_rt::services::registry;
+use firefly_rt::services::safepoint;
+
+/// Registers the runtime's memory pressure handler, and starts watching for pressure reported by
+/// the operating system, if supported on this platform
+pub fn init() {
+    memory_pressure::register(on_pressure);
+
+    #[cfg(target_os = "linux")]
+    {
+        start_psi();
+        checkpoint::register(Arc::new(PsiCheckpointHook));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn start_psi() {
+    if let Err(err) = psi::start() {
+        log::debug!(target: "memory_pressure", "pressure stall information is unavailable: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct PsiCheckpointHook;
+#[cfg(target_os = "linux")]
+impl CheckpointHook for PsiCheckpointHook {
+    fn name(&self) -> &'static str {
+        "memory_pressure"
+    }
+
+    // The watcher must be stopped before the world is paused, as it may be blocked delivering a
+    // report to the schedulers
+    fn prepare(&self) {
+        psi::stop();
+    }
+
+    fn restore(&self) {
+        start_psi();
+    }
+}
+
+fn on_pressure(level: PressureLevel) {
+    let mut scheduled = 0;
+    {
+        let _region = safepoint::enter_region();
+        for process in registry::processes() {
+            if crate::bifs::erlang::schedule_system_gc(process) {
+                scheduled += 1;
+            }
+        }
+    }
+    log::info!(target: "memory_pressure", "scheduled full sweep collection of {} processes", scheduled);
+
+    if level == PressureLevel::Critical {
+        firefly_system::alloc::release_free_memory();
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod psi {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Write};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+
+    use firefly_rt::services::memory_pressure::{self, PressureLevel, PressureSource};
+    use firefly_system::sync::{const_mutex, Mutex};
+
+    /// The PSI triggers for each pressure level, see the kernel's `Documentation/accounting/psi.rst`
+    ///
+    /// Moderate pressure is reported when some tasks are stalled on memory for 150ms in any 1s
+    /// window, and critical pressure when all tasks are stalled for 100ms in any 1s window.
+    const TRIGGERS: [(PressureLevel, &'static str); 2] = [
+        (PressureLevel::Moderate, "some 150000 1000000"),
+        (PressureLevel::Critical, "full 100000 1000000"),
+    ];
+
+    /// The running watcher thread, if any
+    struct Watcher {
+        /// An eventfd which is written to in order to stop the thread
+        stop: Arc<File>,
+        thread: JoinHandle<()>,
+    }
+
+    static WATCHER: Mutex<Option<Watcher>> = const_mutex(None);
+
+    /// Starts a thread which waits for PSI trigger events and reports them as memory pressure
+    pub fn start() -> io::Result<()> {
+        let mut watcher = WATCHER.lock();
+        if watcher.is_some() {
+            return Ok(());
+        }
+
+        let cgroup = firefly_system::cgroup::unified_dir().map(|dir| dir.join("memory.pressure"));
+        let (triggers, source) = match cgroup.map(|path| open_triggers(&path)) {
+            Some(Ok(triggers)) => (triggers, PressureSource::Cgroup),
+            _ => (
+                open_triggers(Path::new("/proc/pressure/memory"))?,
+                PressureSource::Host,
+            ),
+        };
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let stop = Arc::new(unsafe { File::from_raw_fd(fd) });
+
+        let stopped = stop.clone();
+        let thread = thread::Builder::new()
+            .name("memory_pressure".into())
+            .spawn(move || watch(triggers, source, stopped))?;
+        *watcher = Some(Watcher { stop, thread });
+        Ok(())
+    }
+
+    /// Stops the thread started by [`start`], if running, closing its triggers
+    pub fn stop() {
+        let Some(watcher) = WATCHER.lock().take() else { return; };
+        (&*watcher.stop).write_all(&1u64.to_ne_bytes()).ok();
+        watcher.thread.join().ok();
+    }
+
+    /// Opens a file descriptor for each trigger in `TRIGGERS`, each of which must be registered on
+    /// its own file descriptor
+    fn open_triggers(path: &Path) -> io::Result<Vec<(PressureLevel, File)>> {
+        TRIGGERS
+            .iter()
+            .map(|(level, trigger)| {
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+                    .open(path)?;
+                file.write_all(trigger.as_bytes())?;
+                Ok((*level, file))
+            })
+            .collect()
+    }
+
+    fn watch(triggers: Vec<(PressureLevel, File)>, source: PressureSource, stop: Arc<File>) {
+        let mut fds = triggers
+            .iter()
+            .map(|(_, file)| libc::pollfd {
+                fd: file.as_raw_fd(),
+                events: libc::POLLPRI,
+                revents: 0,
+            })
+            .collect::<Vec<_>>();
+        fds.push(libc::pollfd {
+            fd: stop.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        });
+
+        loop {
+            let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                log::warn!(target: "memory_pressure", "stopped watching for memory pressure: {}", err);
+                return;
+            }
+            if fds[triggers.len()].revents != 0 {
+                // Stopped via `stop`, which closes the triggers as they are dropped
+                return;
+            }
+            // Report the most severe level which triggered
+            let mut level = None;
+            for (pollfd, (trigger_level, _)) in fds.iter_mut().zip(triggers.iter()) {
+                if pollfd.revents & libc::POLLERR != 0 {
+                    // The monitored cgroup is gone
+                    log::warn!(target: "memory_pressure", "stopped watching for memory pressure: trigger was removed");
+                    return;
+                }
+                if pollfd.revents & libc::POLLPRI != 0 {
+                    level = level.max(Some(*trigger_level));
+                }
+                pollfd.revents = 0;
+            }
+            if let Some(level) = level {
+                memory_pressure::notify(level, source);
+            }
+        }
+    }
+}
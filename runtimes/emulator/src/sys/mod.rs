@@ -1,4 +1,17 @@
+pub mod checkpoint;
+pub mod dirty;
 pub mod dispatcher;
 pub mod env;
+pub mod file_sink;
+#[cfg(unix)]
+pub mod heart;
+#[cfg(unix)]
+pub mod io;
+pub mod log_file;
+pub mod memory_pressure;
+pub mod numa;
 #[cfg(not(target_family = "wasm"))]
 pub mod signals;
+pub mod stdio_file;
+#[cfg(unix)]
+pub mod systemd;
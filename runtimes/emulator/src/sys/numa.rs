@@ -0,0 +1,127 @@
+//! This module groups schedulers by NUMA node, see `firefly_system::numa`.
+//!
+//! Each group of schedulers shares a global run queue, in which processes spawned on a scheduler
+//! in the group are placed, and to which they return when woken. Idle schedulers steal from the
+//! global run queue of their own group first, then from the local run queues of their peers in the
+//! group, and only then from other groups, so processes tend to stay on the node their heaps were
+//! allocated on.
+use std::sync::{Arc, OnceLock};
+
+use crossbeam::deque::Injector;
+
+use firefly_rt::process::Process;
+use firefly_system::numa::{self, NumaNode};
+
+use crate::queue::Peers;
+
+static GROUPS: OnceLock<Vec<SchedulerGroup>> = OnceLock::new();
+
+/// A group of schedulers sharing a global run queue
+pub struct SchedulerGroup {
+    /// The NUMA node to which the schedulers in this group are bound, if bound
+    pub node: Option<&'static NumaNode>,
+    /// The zero-based indices of the schedulers in this group
+    pub schedulers: Vec<usize>,
+    pub injector: Arc<Injector<Arc<Process>>>,
+    /// The local run queues of the schedulers in this group
+    pub peers: Arc<Peers<Arc<Process>>>,
+}
+
+/// Parses the scheduler bind type from the `+sbt Type` flag, as supported by ERTS
+///
+/// Schedulers are grouped by NUMA node unless the bind type is `u` (unbound). The other bind
+/// types supported by ERTS are accepted, but all bind schedulers to a node rather than a specific
+/// processor.
+pub fn bind_from_args<I: Iterator<Item = String>>(mut args: I) -> bool {
+    while let Some(arg) = args.next() {
+        if arg == "+sbt" {
+            return args.next().as_deref() != Some("u");
+        }
+    }
+    true
+}
+
+/// Assigns `num_schedulers` schedulers to groups, in proportion to the number of processors on
+/// each NUMA node
+///
+/// If `bind` is false, or the system is not NUMA, all schedulers are placed in one unbound group.
+pub fn init(num_schedulers: usize, bind: bool) -> &'static [SchedulerGroup] {
+    GROUPS.get_or_init(|| {
+        let nodes = numa::topology();
+        if !bind || nodes.len() < 2 {
+            return vec![SchedulerGroup {
+                node: None,
+                schedulers: (0..num_schedulers).collect(),
+                injector: Arc::new(Injector::new()),
+                peers: Arc::new(Peers::default()),
+            }];
+        }
+
+        let mut groups = nodes
+            .iter()
+            .map(|node| SchedulerGroup {
+                node: Some(node),
+                schedulers: vec![],
+                injector: Arc::new(Injector::new()),
+                peers: Arc::new(Peers::default()),
+            })
+            .collect::<Vec<_>>();
+        let total_cpus = nodes.iter().map(|node| node.cpus.len()).sum::<usize>();
+        for scheduler in 0..num_schedulers {
+            // Spread the schedulers evenly over the processors of all nodes
+            let cpu = scheduler * total_cpus / num_schedulers;
+            let mut first_cpu = 0;
+            for (node, group) in nodes.iter().zip(groups.iter_mut()) {
+                if cpu < first_cpu + node.cpus.len() {
+                    group.schedulers.push(scheduler);
+                    break;
+                }
+                first_cpu += node.cpus.len();
+            }
+        }
+        groups.retain(|group| !group.schedulers.is_empty());
+        groups
+    })
+}
+
+/// Returns the scheduler groups, if initialized
+pub fn groups() -> &'static [SchedulerGroup] {
+    GROUPS.get().map(|groups| groups.as_slice()).unwrap_or(&[])
+}
+
+/// Returns the group to which the scheduler with the given zero-based index belongs
+pub fn group_of(scheduler: usize) -> &'static SchedulerGroup {
+    groups()
+        .iter()
+        .find(|group| group.schedulers.contains(&scheduler))
+        .expect("scheduler groups must be initialized before schedulers are started")
+}
+
+/// Returns the run queues of all groups other than `group`
+pub fn remote_injectors(group: &SchedulerGroup) -> Vec<Arc<Injector<Arc<Process>>>> {
+    groups()
+        .iter()
+        .filter(|other| !Arc::ptr_eq(&other.injector, &group.injector))
+        .map(|other| other.injector.clone())
+        .collect()
+}
+
+/// Returns the local run queues of all groups, starting with those of `group`
+pub fn peers(group: &SchedulerGroup) -> Vec<Arc<Peers<Arc<Process>>>> {
+    let mut peers = vec![group.peers.clone()];
+    peers.extend(
+        groups()
+            .iter()
+            .filter(|other| !Arc::ptr_eq(&other.peers, &group.peers))
+            .map(|other| other.peers.clone()),
+    );
+    peers
+}
+
+/// Binds the calling thread, on which `group`'s scheduler will run, to the node of that group
+pub fn bind_current_thread(group: &SchedulerGroup) {
+    let Some(node) = group.node else { return; };
+    if let Err(err) = numa::bind_current_thread(node) {
+        log::warn!(target: "numa", "unable to bind scheduler to numa node {}: {}", node.id, err);
+    }
+}
@@ -9,13 +9,15 @@ use signal_hook::consts::TERM_SIGNALS;
 use signal_hook::flag;
 use signal_hook::iterator::Signals;
 
-use firefly_alloc::fragment::HeapFragment;
-use firefly_alloc::heap::Heap;
 use firefly_rt::services::registry::{self, Registrant, WeakAddress};
+use firefly_rt::services::safepoint;
 use firefly_rt::term::{atoms, Atom, LayoutBuilder, Term, TermFragment, Tuple};
 
 use smallvec::SmallVec;
 
+#[cfg(not(windows))]
+use super::systemd::{self, Notify};
+
 #[cfg(not(windows))]
 const ALLOWED_SIGNALS: &'static [libc::c_int] =
     &[SIGHUP, SIGUSR1, SIGUSR2, SIGCHLD, SIGTSTP, SIGABRT, SIGALRM];
@@ -73,8 +75,17 @@ pub fn start_handler() {
             SIGSTOP => signal_notify_requested(atoms::Sigstop),
             SIGTSTP => signal_notify_requested(atoms::Sigtstp),
             SIGQUIT => signal_notify_requested(atoms::Sigquit),
-            SIGTERM => signal_notify_requested(atoms::Sigterm),
-            SIGHUP => signal_notify_requested(atoms::Sighup),
+            SIGTERM => {
+                let _ = systemd::notify(&[Notify::Stopping]);
+                signal_notify_requested(atoms::Sigterm);
+            }
+            SIGHUP => {
+                // Configuration reloads are handled asynchronously by `erl_signal_server`, so
+                // the best we can tell systemd is that the reload has been dispatched
+                let _ = systemd::notify(&[Notify::Reloading]);
+                signal_notify_requested(atoms::Sighup);
+                let _ = systemd::notify(&[Notify::Ready]);
+            }
             SIGABRT => signal_notify_requested(atoms::Sigabrt),
             SIGALRM => signal_notify_requested(atoms::Sigalrm),
             _ => (), // ignore
@@ -93,33 +104,21 @@ pub fn start_handler() {
 /// Send `{notify, Signal}` to `erl_signal_server` process
 #[inline(never)]
 fn signal_notify_requested(signal: Atom) {
+    let _region = safepoint::enter_region();
     if let Some(Registrant::Process(proc)) = registry::get_by_name(atoms::ErlSignalServer) {
-        let mut locked = proc.lock();
-        let message = {
-            let mut builder = LayoutBuilder::new();
-            builder.build_tuple(2);
-            let layout = builder.finish();
-            if locked.heap_available() >= layout.size() {
-                let term = Term::Tuple(
-                    Tuple::from_slice(&[atoms::Notify.into(), signal.into()], &mut locked).unwrap(),
-                );
-                TermFragment {
-                    term: term.into(),
-                    fragment: None,
-                }
-            } else {
-                let fragment_ptr = HeapFragment::new(layout, None).unwrap();
-                let fragment = unsafe { fragment_ptr.as_ref() };
-                let term = Term::Tuple(
-                    Tuple::from_slice(&[atoms::Notify.into(), signal.into()], fragment).unwrap(),
-                );
-                TermFragment {
-                    term: term.into(),
-                    fragment: Some(fragment_ptr),
-                }
-            }
+        // The signal server may be running, so the message is built off its heap and delivered
+        // via its signal queue like any other
+        let mut builder = LayoutBuilder::new();
+        builder.build_tuple(2);
+        let fragment_ptr = builder.into_fragment().unwrap();
+        let fragment = unsafe { fragment_ptr.as_ref() };
+        let term = Term::Tuple(
+            Tuple::from_slice(&[atoms::Notify.into(), signal.into()], fragment).unwrap(),
+        );
+        let message = TermFragment {
+            term: term.into(),
+            fragment: Some(fragment_ptr),
         };
-
-        locked.send_fragment(WeakAddress::System, message).ok();
+        proc.send_fragment(WeakAddress::System, message).ok();
     }
 }
@@ -0,0 +1,129 @@
+//! This module implements redirection of standard io to a file, for headless deployments.
+//!
+//! A native io server is spawned before the init process, and made its group leader, so that
+//! output from every process which inherits its group leader from init, e.g. via `io:format/2`,
+//! is written by it. When enabled with the `-stdio_file Path` flag, that output is appended to
+//! `Path` rather than written to standard output. The io server runs
+//! `erts_internal:file_io_server/0`, which hands the characters of each output request to
+//! [`write`].
+//!
+//! The file is rotated when it would grow beyond `-stdio_file_max_bytes Bytes`, keeping
+//! `-stdio_file_max_files Count` rotated files, see [`FileSink`]. Rotation is disabled unless
+//! at least one of these flags is given.
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use super::file_sink::{FileSink, RotationConfig};
+
+static SINK: OnceLock<Mutex<FileSink>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StdioFileConfig {
+    pub path: PathBuf,
+    pub rotation: Option<RotationConfig>,
+}
+impl StdioFileConfig {
+    /// Parses the configuration from the given command-line arguments
+    ///
+    /// Returns `None` if the `-stdio_file` flag was not given.
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Option<Self> {
+        let mut path = None;
+        let mut rotation = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-stdio_file" => path = args.next().map(PathBuf::from),
+                "-stdio_file_max_bytes" => {
+                    let value = args.next();
+                    match value.as_deref().map(str::parse::<u64>) {
+                        Some(Ok(size)) if size > 0 => {
+                            rotation.get_or_insert_with(RotationConfig::default).max_bytes = Some(size)
+                        }
+                        _ => eprintln!(
+                            "Ignoring invalid -stdio_file_max_bytes value, expected a size in bytes, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                "-stdio_file_max_files" => {
+                    let value = args.next();
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(count)) => {
+                            rotation.get_or_insert_with(RotationConfig::default).max_files = count
+                        }
+                        _ => eprintln!(
+                            "Ignoring invalid -stdio_file_max_files value, expected a file count, got '{}'",
+                            value.as_deref().unwrap_or_default()
+                        ),
+                    }
+                }
+                _ => (),
+            }
+        }
+        Some(Self {
+            path: path?,
+            rotation,
+        })
+    }
+}
+
+/// Opens the file standard io is redirected to
+///
+/// This must be called at most once, before the init process is spawned.
+pub fn init(config: StdioFileConfig) -> io::Result<()> {
+    let sink = FileSink::open(config.path, config.rotation)?;
+    if SINK.set(Mutex::new(sink)).is_err() {
+        panic!("tried to init the stdio file twice!");
+    }
+    Ok(())
+}
+
+/// Returns true if standard io is redirected to a file
+#[inline]
+pub fn is_enabled() -> bool {
+    SINK.get().is_some()
+}
+
+/// Appends `bytes` to the file standard io is redirected to
+pub fn write(bytes: &[u8]) -> io::Result<()> {
+    let Some(sink) = SINK.get() else { return Err(io::ErrorKind::NotFound.into()); };
+    let mut sink = sink.lock().unwrap();
+    sink.write(bytes)?;
+    sink.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stdio_file_config_test() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            StdioFileConfig::from_args(args(&["-heart"]).into_iter()),
+            None
+        );
+
+        let config = StdioFileConfig::from_args(args(&["-stdio_file", "out.log"]).into_iter());
+        assert_eq!(
+            config,
+            Some(StdioFileConfig {
+                path: "out.log".into(),
+                rotation: None,
+            })
+        );
+
+        let config = StdioFileConfig::from_args(
+            args(&["-stdio_file_max_files", "3", "-stdio_file", "out.log"]).into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.rotation,
+            Some(RotationConfig {
+                max_files: 3,
+                ..RotationConfig::default()
+            })
+        );
+    }
+}
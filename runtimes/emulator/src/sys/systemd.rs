@@ -0,0 +1,321 @@
+//! This module implements optional integration with systemd, which is only active when the
+//! runtime is started by systemd, as indicated by the environment.
+//!
+//! * Service state changes are reported via `sd_notify(3)`, so that `Type=notify` and
+//! `Type=notify-reload` services work as expected
+//! * Listening sockets passed via socket activation (see `sd_listen_fds(3)`) are collected at
+//! startup, so that they can be handed to the socket layer rather than being bound anew
+//! * When standard error is connected to the journal, log records are written to the journal
+//! directly using its native protocol, so that they retain their level and source location
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::{Mutex, OnceLock};
+
+use log::{Level, Log, Metadata, Record};
+
+/// The first file descriptor passed by socket activation, see `SD_LISTEN_FDS_START`
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The path to the socket on which journald accepts log records using its native protocol
+const JOURNAL_SOCKET: &'static str = "/run/systemd/journal/socket";
+
+static NOTIFY_SOCKET: OnceLock<Option<OsString>> = OnceLock::new();
+static LISTEN_FDS: Mutex<Vec<ListenFd>> = Mutex::new(Vec::new());
+
+/// A service state change to report to systemd
+#[derive(Debug, Copy, Clone)]
+pub enum Notify<'a> {
+    /// Startup is complete
+    Ready,
+    /// The service is reloading its configuration, and will send `Ready` when done
+    Reloading,
+    /// The service is beginning to shut down
+    Stopping,
+    /// Keep-alive ping for the service watchdog
+    Watchdog,
+    /// A free-form description of the service state
+    Status(&'a str),
+}
+
+/// Performs one-time initialization of systemd integration
+///
+/// This must be called early during startup, before any file descriptors are opened, so that
+/// socket activation file descriptors are claimed before they can be confused with anything
+/// else. The environment variables used to pass them are removed, so that they are not
+/// inherited by child processes.
+pub fn init() {
+    NOTIFY_SOCKET.get_or_init(|| env::var_os("NOTIFY_SOCKET"));
+    env::remove_var("NOTIFY_SOCKET");
+
+    let fds = listen_fds();
+    for var in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        env::remove_var(var);
+    }
+    *LISTEN_FDS.lock().unwrap() = fds;
+}
+
+/// Returns the path of the socket to which service notifications are sent, if running under
+/// systemd
+pub fn notify_socket() -> Option<&'static OsStr> {
+    NOTIFY_SOCKET
+        .get_or_init(|| env::var_os("NOTIFY_SOCKET"))
+        .as_deref()
+}
+
+/// Reports the given state changes to systemd
+///
+/// Returns `Ok(false)` if not running under systemd, in which case this is a no-op.
+pub fn notify(states: &[Notify<'_>]) -> io::Result<bool> {
+    match notify_socket() {
+        None => Ok(false),
+        Some(socket) => notify_to(socket, states).map(|_| true),
+    }
+}
+
+/// Reports the given state changes to the notification socket at `socket`
+pub fn notify_to(socket: &OsStr, states: &[Notify<'_>]) -> io::Result<()> {
+    let mut message = String::new();
+    for state in states {
+        match state {
+            Notify::Ready => message.push_str("READY=1\n"),
+            Notify::Reloading => {
+                // Required by `Type=notify-reload`, so that systemd can tell which reload
+                // request this notification corresponds to
+                let mut now = unsafe { mem::zeroed::<libc::timespec>() };
+                unsafe {
+                    libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now);
+                }
+                let usec = (now.tv_sec as u64) * 1_000_000 + (now.tv_nsec as u64) / 1_000;
+                message.push_str(&format!("RELOADING=1\nMONOTONIC_USEC={}\n", usec));
+            }
+            Notify::Stopping => message.push_str("STOPPING=1\n"),
+            Notify::Watchdog => message.push_str("WATCHDOG=1\n"),
+            Notify::Status(status) => {
+                // Status must be a single line
+                let status = status.replace('\n', " ");
+                message.push_str(&format!("STATUS={}\n", status));
+            }
+        }
+    }
+    send_datagram(socket, message.as_bytes())
+}
+
+/// A listening socket passed to the runtime by socket activation
+#[derive(Debug)]
+pub struct ListenFd {
+    pub fd: OwnedFd,
+    /// The name assigned to the socket with `FileDescriptorName=`, if any
+    pub name: Option<String>,
+}
+
+/// Takes ownership of the socket activation file descriptor with the given name, if present
+///
+/// If `name` is `None`, the first remaining file descriptor is returned. Each file descriptor can
+/// only be taken once.
+#[allow(dead_code)]
+pub fn take_listen_fd(name: Option<&str>) -> Option<OwnedFd> {
+    let mut fds = LISTEN_FDS.lock().unwrap();
+    let index = match name {
+        None if fds.is_empty() => return None,
+        None => 0,
+        Some(name) => fds.iter().position(|fd| fd.name.as_deref() == Some(name))?,
+    };
+    Some(fds.remove(index).fd)
+}
+
+/// Collects the file descriptors passed by socket activation, if any were passed to this process
+fn listen_fds() -> Vec<ListenFd> {
+    let Some(pid) = env::var("LISTEN_PID").ok().and_then(|pid| pid.parse::<u32>().ok()) else { return Vec::new(); };
+    if pid != std::process::id() {
+        return Vec::new();
+    }
+    let Some(count) = env::var("LISTEN_FDS").ok().and_then(|n| n.parse::<RawFd>().ok()) else { return Vec::new(); };
+    let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let mut names = names.split(':');
+
+    (LISTEN_FDS_START..(LISTEN_FDS_START + count))
+        .map(|fd| {
+            // These must not be inherited by ports or other child processes
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFD);
+                if flags >= 0 {
+                    libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+                }
+            }
+            let name = names
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string());
+            ListenFd {
+                fd: unsafe { OwnedFd::from_raw_fd(fd) },
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Sends `message` as a single datagram to the unix socket at `path`
+///
+/// Paths beginning with `@` refer to sockets in the abstract namespace.
+fn send_datagram(path: &OsStr, message: &[u8]) -> io::Result<()> {
+    let path = path.as_bytes();
+    let mut addr = unsafe { mem::zeroed::<libc::sockaddr_un>() };
+    if path.is_empty() || path.len() >= addr.sun_path.len() {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, src) in addr.sun_path.iter_mut().zip(path.iter()) {
+        *dst = *src as libc::c_char;
+    }
+    if path[0] == b'@' {
+        addr.sun_path[0] = 0;
+    }
+    let len = mem::size_of::<libc::sa_family_t>() + path.len();
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let sent = unsafe {
+        libc::sendto(
+            fd.as_raw_fd(),
+            message.as_ptr() as *const libc::c_void,
+            message.len(),
+            0,
+            &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+            len as libc::socklen_t,
+        )
+    };
+    if sent < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns true if standard error is connected to the journal
+///
+/// systemd sets `JOURNAL_STREAM` to the device and inode of the stream it connects to standard
+/// output/error, which we compare against standard error, since that is where logs would
+/// otherwise be written.
+pub fn is_journal_connected() -> bool {
+    let Some(stream) = env::var_os("JOURNAL_STREAM") else { return false; };
+    let Some((dev, ino)) = stream.to_str().and_then(|s| s.split_once(':')) else { return false; };
+    let mut stat = unsafe { mem::zeroed::<libc::stat>() };
+    if unsafe { libc::fstat(libc::STDERR_FILENO, &mut stat) } != 0 {
+        return false;
+    }
+    dev.parse::<u64>().ok() == Some(stat.st_dev as u64)
+        && ino.parse::<u64>().ok() == Some(stat.st_ino as u64)
+}
+
+/// A logger which writes records to the journal using its native protocol
+///
+/// Filtering is delegated to `filter`, so that the usual `ERTS_TRACE` configuration still
+/// applies. If a record cannot be written to the journal, e.g. because it is too large for a
+/// single datagram, it is written to standard error via `filter` instead.
+pub struct JournalLogger {
+    filter: env_logger::Logger,
+    identifier: String,
+}
+impl JournalLogger {
+    pub fn new(filter: env_logger::Logger) -> Self {
+        let identifier = env::args()
+            .next()
+            .and_then(|arg0| {
+                std::path::Path::new(&arg0)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "firefly".to_string());
+        Self { filter, identifier }
+    }
+
+    /// Installs this logger as the global logger
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.filter.filter();
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    fn encode(&self, record: &Record) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256);
+        let priority = match record.level() {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        add_field(&mut buf, "PRIORITY", format!("{}", priority).as_bytes());
+        add_field(&mut buf, "SYSLOG_IDENTIFIER", self.identifier.as_bytes());
+        add_field(&mut buf, "MESSAGE", format!("{}", record.args()).as_bytes());
+        add_field(&mut buf, "TARGET", record.target().as_bytes());
+        if let Some(file) = record.file() {
+            add_field(&mut buf, "CODE_FILE", file.as_bytes());
+        }
+        if let Some(line) = record.line() {
+            add_field(&mut buf, "CODE_LINE", format!("{}", line).as_bytes());
+        }
+        if let Some(module) = record.module_path() {
+            add_field(&mut buf, "CODE_MODULE", module.as_bytes());
+        }
+        buf
+    }
+}
+impl Log for JournalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+        let message = self.encode(record);
+        if send_datagram(OsStr::new(JOURNAL_SOCKET), &message).is_err() {
+            self.filter.log(record);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Appends a field to a journal record
+///
+/// Values containing newlines must use the binary form, in which the value is length-prefixed.
+fn add_field(buf: &mut Vec<u8>, name: &str, value: &[u8]) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value);
+    buf.push(b'\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn journal_add_field_test() {
+        let mut buf = Vec::new();
+        add_field(&mut buf, "MESSAGE", b"hello");
+        assert_eq!(buf.as_slice(), b"MESSAGE=hello\n");
+
+        let mut buf = Vec::new();
+        add_field(&mut buf, "MESSAGE", b"a\nb");
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(buf, expected);
+    }
+}
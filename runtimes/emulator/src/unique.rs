@@ -45,15 +45,20 @@ pub fn get_raw_unique_monotonic_integer() -> u64 {
 ///
 /// If `positive` is true, the integer will be a positive value
 pub fn get_unique_monotonic_integer(positive: bool) -> Int {
-    let raw = get_raw_unique_monotonic_integer();
     if positive {
-        (raw + 1).into()
+        (get_raw_unique_monotonic_integer() + 1).into()
     } else {
-        let value = (raw as i64) + UNIQUE_MONOTONIC_OFFSET;
-        value.into()
+        get_signed_unique_monotonic_integer().into()
     }
 }
 
+/// Get the next unique monotonic integer from the global state, as a signed value
+///
+/// This is the same value as `get_unique_monotonic_integer(false)`, without converting to an `Int`.
+pub fn get_signed_unique_monotonic_integer() -> i64 {
+    (get_raw_unique_monotonic_integer() as i64) + UNIQUE_MONOTONIC_OFFSET
+}
+
 /// Make a unique integer from raw parts
 pub fn make_unique_integer(value0: u64, value1: u64, positive: bool) -> Int {
     let mut unique = [0u64; 2];
@@ -0,0 +1,21 @@
+-module(init).
+
+-export([boot/1, count/1]).
+
+boot(_) ->
+    1 = erlang:trace_pattern({init, count, 1}, true, [call_count, call_time]),
+    ok = count(10),
+    {call_count, 11} = erlang:trace_info({init, count, 1}, call_count),
+    {call_time, [{Pid, 11, _, _}]} = erlang:trace_info({init, count, 1}, call_time),
+    Pid = self(),
+    1 = erlang:trace_pattern({init, count, 1}, pause, [call_count, call_time]),
+    ok = count(10),
+    {call_count, 11} = erlang:trace_info({init, count, 1}, call_count),
+    1 = erlang:trace_pattern({init, count, 1}, false, [call_count, call_time]),
+    {call_count, false} = erlang:trace_info({init, count, 1}, call_count),
+    erlang:display(ok).
+
+count(0) ->
+    ok;
+count(N) ->
+    count(N - 1).
@@ -0,0 +1,16 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    Mac = <<"0123456789abcdef0123456789abcdef">>,
+    erlang:display(crypto:hash_equals(Mac, <<"0123456789abcdef0123456789abcdef">>)),
+    erlang:display(crypto:hash_equals(Mac, <<"1123456789abcdef0123456789abcdef">>)),
+    erlang:display(crypto:hash_equals(Mac, <<"0123456789abcdef0123456789abcdeF">>)),
+    erlang:display(crypto:hash_equals(<<>>, <<>>)),
+    %% Sub-binaries which are not byte aligned are compared by value
+    <<_:4, Unaligned:2/binary, _:4>> = <<0:4, "ab", 0:4>>,
+    erlang:display(crypto:hash_equals(Unaligned, <<"ab">>)),
+    erlang:display(catch crypto:hash_equals(Mac, <<"short">>)),
+    erlang:display(catch crypto:hash_equals(Mac, "not a binary")),
+    ok.
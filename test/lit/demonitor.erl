@@ -0,0 +1,46 @@
+-module(init).
+
+-export([boot/1, wait/0]).
+
+boot(_) ->
+    %% A monitor which is still active is removed, and reported as such with `info`
+    Pid = spawn(init, wait, []),
+    Ref = monitor(process, Pid),
+    erlang:display(demonitor(Ref, [info])),
+    erlang:display(demonitor(Ref, [info])),
+    erlang:display(demonitor(Ref)),
+    Pid ! stop,
+    %% Without `flush`, a 'DOWN' message which was already delivered stays in the mailbox
+    {Pid2, Ref2} = spawn_monitor(fun() -> ok end),
+    wait_for_down(Ref2),
+    erlang:display(demonitor(Ref2, [info])),
+    receive {'DOWN', Ref2, process, Pid2, normal} -> erlang:display(kept) end,
+    %% With `flush`, it is removed, without touching other messages
+    {_Pid3, Ref3} = spawn_monitor(fun() -> ok end),
+    wait_for_down(Ref3),
+    self() ! other,
+    erlang:display(demonitor(Ref3, [flush, info])),
+    erlang:display(process_info(self(), messages)),
+    receive other -> ok end,
+    %% Monitors with a custom tag are flushed too
+    Pid4 = spawn(init, wait, []),
+    Ref4 = monitor(process, Pid4, [{tag, gone}]),
+    Pid4 ! stop,
+    wait_for_down(Ref4),
+    erlang:display(demonitor(Ref4, [flush])),
+    erlang:display(process_info(self(), message_queue_len)),
+    erlang:display(catch demonitor(Ref4, [bogus])),
+    ok.
+
+wait() ->
+    receive stop -> ok end.
+
+%% Waits until the 'DOWN' message for `Ref` is in the mailbox, without receiving it
+wait_for_down(Ref) ->
+    {messages, Messages} = process_info(self(), messages),
+    case [M || M <- Messages, element(2, M) =:= Ref] of
+        [] ->
+            receive after 1 -> wait_for_down(Ref) end;
+        [_] ->
+            ok
+    end.
@@ -0,0 +1,20 @@
+-module(init).
+
+-export([boot/1, ping/1]).
+
+boot(_) ->
+    erlang:display(erlang:system_info(dirty_cpu_schedulers) =:= erlang:system_info(schedulers)),
+    erlang:display(erlang:system_info(dirty_io_schedulers)),
+    %% Processes keep running while a dirty scheduler is blocked
+    Ref = erts_debug:dirty_sleep(dirty_cpu, 200),
+    Self = self(),
+    spawn(init, ping, [Self]),
+    receive pong -> erlang:display(pong) end,
+    receive {Ref, Result} -> erlang:display(Result) end,
+    IoRef = erts_debug:dirty_sleep(dirty_io, 10),
+    receive {IoRef, IoResult} -> erlang:display(IoResult) end,
+    erlang:display(catch erts_debug:dirty_sleep(dirty_gpu, 10)),
+    ok.
+
+ping(Parent) ->
+    Parent ! pong.
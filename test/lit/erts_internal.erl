@@ -1,19 +1,226 @@
 -module(erts_internal).
 
 -export([is_process_alive/1, is_process_alive/2]).
+-export([group_leader/2, group_leader/3]).
+-export([process_info/2, process_info/3, process_info_fallback/2]).
+-export([spawn_opt/5]).
+-export([hibernate_resume/3]).
+-export([recon_proc_window/3, recon_bin_leak/1]).
+-export([file_io_server/0, file_io_put_chars/2]).
 
 -spec erts_internal:is_process_alive(Pid) -> boolean() when
       Pid :: pid().
 is_process_alive(Pid) ->
     Ref = make_ref(),
-    erts_internal:is_process_alive(Pid, Ref),
+    case erts_internal:is_process_alive(Pid, Ref) of
+        ok ->
+            receive
+                {Ref, Res} ->
+                    Res
+            end;
+        false ->
+            false
+    end.
+
+-spec erts_internal:is_process_alive(Pid, Ref) -> 'ok' | 'false' when
+      Pid :: pid(),
+      Ref :: reference().
+is_process_alive(_Pid, _Ref) ->
+    erlang:nif_error(undefined).
+
+-spec erts_internal:group_leader(GroupLeader, Pid) -> 'true' when
+      GroupLeader :: pid(),
+      Pid :: pid().
+group_leader(GroupLeader, Pid) ->
+    Ref = make_ref(),
+    Result = case erts_internal:group_leader(GroupLeader, Pid, Ref) of
+                 ok ->
+                     receive
+                         {Ref, Res} ->
+                             Res
+                     end;
+                 Error ->
+                     Error
+             end,
+    case Result of
+        badarg ->
+            erlang:error(badarg, [GroupLeader, Pid]);
+        _ ->
+            Result
+    end.
+
+-spec erts_internal:group_leader(GroupLeader, Pid, Ref) -> 'ok' | 'badarg' when
+      GroupLeader :: pid(),
+      Pid :: pid(),
+      Ref :: reference().
+group_leader(_GroupLeader, _Pid, _Ref) ->
+    erlang:nif_error(undefined).
+
+-spec erts_internal:process_info(Pid, ItemSpec) -> term() when
+      Pid :: pid(),
+      ItemSpec :: atom() | tuple() | [atom() | tuple()].
+process_info(Pid, ItemSpec) ->
+    Ref = make_ref(),
+    case erts_internal:process_info(Pid, ItemSpec, Ref) of
+        ok ->
+            process_info_reply(Pid, Ref);
+        Error ->
+            Error
+    end.
+
+%% The target answers the request the next time it handles signals, but if it is slow to be
+%% scheduled, the request is answered on its behalf while it waits in the run queue
+process_info_reply(Pid, Ref) ->
     receive
         {Ref, Res} ->
             Res
+    after
+        100 ->
+            case erts_internal:process_info_fallback(Pid, Ref) of
+                busy ->
+                    process_info_reply(Pid, Ref);
+                _ ->
+                    receive
+                        {Ref, Res} ->
+                            Res
+                    end
+            end
     end.
 
--spec erts_internal:is_process_alive(Pid, Ref) -> 'ok' when
+-spec erts_internal:process_info(Pid, ItemSpec, Ref) -> 'ok' | 'undefined' when
       Pid :: pid(),
+      ItemSpec :: atom() | tuple() | [atom() | tuple()],
       Ref :: reference().
-is_process_alive(_Pid, _Ref) ->
+process_info(_Pid, _ItemSpec, _Ref) ->
+    erlang:nif_error(undefined).
+
+-spec erts_internal:process_info_fallback(Pid, Ref) -> 'ok' | 'pending' | 'busy' when
+      Pid :: pid(),
+      Ref :: reference().
+process_info_fallback(_Pid, _Ref) ->
+    erlang:nif_error(undefined).
+
+%% The function a process hibernated by erlang:hibernate/3 resumes in, once it has a message
+-spec erts_internal:hibernate_resume(Module, Function, Args) -> term() when
+      Module :: module(),
+      Function :: atom(),
+      Args :: [term()].
+hibernate_resume(Module, Function, Args) ->
+    erlang:apply(Module, Function, Args).
+
+%% Implements firefly_recon:proc_window/3, which has already validated the arguments
+-spec erts_internal:recon_proc_window(Attribute, N, Milliseconds) -> [{pid(), integer(), list()}] when
+      Attribute :: atom(),
+      N :: non_neg_integer(),
+      Milliseconds :: non_neg_integer().
+recon_proc_window(Attribute, N, Milliseconds) ->
+    Before = maps:from_list(firefly_recon:sample(Attribute)),
+    receive after Milliseconds -> ok end,
+    Deltas = [{Pid, Value - maps:get(Pid, Before, 0)} || {Pid, Value} <- firefly_recon:sample(Attribute)],
+    recon_top(lists:reverse(lists:keysort(2, Deltas)), N).
+
+%% Implements firefly_recon:bin_leak/1, which has already validated the arguments
+-spec erts_internal:recon_bin_leak(N) -> [{pid(), integer(), list()}] when
+      N :: non_neg_integer().
+recon_bin_leak(N) ->
+    Before = firefly_recon:sample(binary_memory),
+    _ = [erlang:garbage_collect(Pid) || {Pid, _} <- Before],
+    After = maps:from_list(firefly_recon:sample(binary_memory)),
+    Deltas = [{Pid, maps:get(Pid, After) - Value} || {Pid, Value} <- Before, maps:is_key(Pid, After)],
+    recon_top(lists:keysort(2, Deltas), N).
+
+%% Takes the first N processes of a sorted list of samples which are still alive, with their info
+recon_top(_Samples, 0) ->
+    [];
+recon_top([], _N) ->
+    [];
+recon_top([{Pid, Value} | Rest], N) ->
+    case firefly_recon:info(Pid) of
+        undefined ->
+            recon_top(Rest, N);
+        Info ->
+            [{Pid, Value, Info} | recon_top(Rest, N - 1)]
+    end.
+
+%% Spawns a process on a remote node for spawn_opt/3,5, which have already validated the arguments
+-spec erts_internal:spawn_opt(Node, Module, Function, Args, Options) -> pid() | {pid(), reference()} when
+      Node :: node(),
+      Module :: module(),
+      Function :: atom(),
+      Args :: [term()],
+      Options :: [term()].
+spawn_opt(Node, Module, Function, Args, Options) ->
+    Ref = erlang:spawn_request(Node, Module, Function, Args, [{reply, yes} | Options]),
+    receive
+        {spawn_reply, Ref, ok, Pid} ->
+            case spawn_opt_monitor(Options) of
+                true ->
+                    {Pid, Ref};
+                false ->
+                    Pid
+            end;
+        {spawn_reply, Ref, error, noconnection} ->
+            %% Like a spawn which succeeded on a node which then went down, so that links and
+            %% monitors requested by the caller are triggered with the same reason
+            erlang:spawn_opt(erlang, exit, [noconnection], Options);
+        {spawn_reply, Ref, error, badopt} ->
+            erlang:error(badarg, [Node, Module, Function, Args, Options]);
+        {spawn_reply, Ref, error, Reason} ->
+            erlang:error(Reason, [Node, Module, Function, Args, Options])
+    end.
+
+spawn_opt_monitor([]) ->
+    false;
+spawn_opt_monitor([monitor | _]) ->
+    true;
+spawn_opt_monitor([{monitor, _} | _]) ->
+    true;
+spawn_opt_monitor([_ | Rest]) ->
+    spawn_opt_monitor(Rest).
+
+%% The io server standard io is redirected to by -stdio_file, which only supports output
+-spec erts_internal:file_io_server() -> no_return().
+file_io_server() ->
+    receive
+        {io_request, From, ReplyAs, Request} when is_pid(From) ->
+            From ! {io_reply, ReplyAs, file_io_request(Request)},
+            file_io_server();
+        _ ->
+            file_io_server()
+    end.
+
+file_io_request({put_chars, Encoding, Chars}) ->
+    erts_internal:file_io_put_chars(Encoding, Chars);
+file_io_request({put_chars, Encoding, M, F, A}) ->
+    try apply(M, F, A) of
+        Chars ->
+            erts_internal:file_io_put_chars(Encoding, Chars)
+    catch
+        _:_ ->
+            {error, F}
+    end;
+file_io_request({put_chars, Chars}) ->
+    file_io_request({put_chars, latin1, Chars});
+file_io_request({put_chars, M, F, A}) ->
+    file_io_request({put_chars, latin1, M, F, A});
+file_io_request({requests, Requests}) ->
+    file_io_requests(Requests, ok);
+file_io_request({setopts, _Opts}) ->
+    ok;
+file_io_request(getopts) ->
+    [{binary, false}, {encoding, unicode}];
+file_io_request(_) ->
+    {error, request}.
+
+file_io_requests([], Result) ->
+    Result;
+file_io_requests([Request | Rest], ok) ->
+    file_io_requests(Rest, file_io_request(Request));
+file_io_requests(_, Result) ->
+    Result.
+
+-spec erts_internal:file_io_put_chars(Encoding, Chars) -> 'ok' | {'error', term()} when
+      Encoding :: 'unicode' | 'latin1',
+      Chars :: unicode:chardata().
+file_io_put_chars(_Encoding, _Chars) ->
     erlang:nif_error(undefined).
@@ -0,0 +1,56 @@
+-module(init).
+
+-export([boot/1, wait/0, trap/1]).
+
+boot(_) ->
+    %% A normal exit is ignored by a process which is not trapping exits
+    {Ignored, IgnoredRef} = spawn_monitor(init, wait, []),
+    erlang:display(exit(Ignored, normal)),
+    erlang:display(is_process_alive(Ignored)),
+    Ignored ! stop,
+    erlang:display(down(IgnoredRef)),
+    %% Any other reason terminates it
+    {Crashed, CrashedRef} = spawn_monitor(init, wait, []),
+    exit(Crashed, crashed),
+    erlang:display(down(CrashedRef)),
+    %% Including when sent via a dynamic call
+    {Applied, AppliedRef} = spawn_monitor(init, wait, []),
+    erlang:display(apply(erlang, exit, [Applied, applied])),
+    erlang:display(down(AppliedRef)),
+    %% A trapping process receives the exit as a message, normal or not
+    Self = self(),
+    {Trapper, TrapperRef} = spawn_monitor(init, trap, [Self]),
+    receive {Trapper, trapping} -> ok end,
+    exit(Trapper, normal),
+    exit(Trapper, shutdown),
+    receive {Trapper, Received} -> erlang:display(Received) end,
+    %% Unless the reason is kill, which can not be trapped
+    exit(Trapper, kill),
+    erlang:display(down(TrapperRef)),
+    %% A process exiting itself with reason normal exits, unless trapping
+    process_flag(trap_exit, true),
+    exit(Self, normal),
+    receive {'EXIT', Self, Reason} -> erlang:display({self, Reason}) end,
+    ok.
+
+down(Ref) ->
+    receive
+        {'DOWN', Ref, process, _, Reason} ->
+            Reason
+    after
+        1000 ->
+            timeout
+    end.
+
+wait() ->
+    receive
+        stop ->
+            ok
+    end.
+
+trap(Parent) ->
+    process_flag(trap_exit, true),
+    Parent ! {self(), trapping},
+    Reasons = [receive {'EXIT', Parent, Reason} -> Reason end || _ <- [1, 2]],
+    Parent ! {self(), Reasons},
+    wait().
@@ -0,0 +1,33 @@
+-module(init).
+
+-export([boot/1, crash/1]).
+
+boot(_) ->
+    %% Errors are reported with the stacktrace of where they were raised
+    {Pid, Ref} = spawn_monitor(init, crash, [error]),
+    receive
+        {'DOWN', Ref, process, Pid, {badarith, [{init, crash, 1, _} | _]}} ->
+            erlang:display(badarith)
+    end,
+    %% Throws which are not caught become errors
+    {Pid2, Ref2} = spawn_monitor(init, crash, [throw]),
+    receive
+        {'DOWN', Ref2, process, Pid2, {{nocatch, oops}, Trace}} ->
+            erlang:display(is_list(Trace))
+    end,
+    %% Exits keep their reason as-is
+    {Pid3, Ref3} = spawn_monitor(init, crash, [exit]),
+    receive {'DOWN', Ref3, process, Pid3, Reason3} -> erlang:display(Reason3) end,
+    %% Linked processes which trap exits see the same reason
+    process_flag(trap_exit, true),
+    Pid4 = spawn_link(init, crash, [error]),
+    receive {'EXIT', Pid4, {Reason4, [_ | _]}} -> erlang:display(Reason4) end,
+    ok.
+
+crash(error) ->
+    One = list_to_integer("1"),
+    One + one;
+crash(throw) ->
+    throw(oops);
+crash(exit) ->
+    exit(shutdown).
@@ -0,0 +1,18 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    erlang:display(list_to_float("1.5")),
+    erlang:display(list_to_float("-0.25e+2")),
+    erlang:display(binary_to_float(<<"+7.0E-3">>)),
+    erlang:display(list_to_float("4.9e-324")),
+    %% Only the grammar OTP accepts is parsed
+    [erlang:display(catch list_to_float(S))
+     || S <- ["1", "1.", ".5", "1e3", "1.0e", " 1.0", "1,5", "1.0e400", "1.0e-400", "inf"]],
+    erlang:display(catch binary_to_float(<<"1.0 ">>)),
+    %% Printing round trips through parsing
+    F = 0.1 + 0.2,
+    erlang:display(list_to_float(float_to_list(F, [short])) =:= F),
+    erlang:display(binary_to_float(float_to_binary(F)) =:= F),
+    ok.
@@ -0,0 +1,26 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    Pid = spawn(fun () -> _ = lists:seq(1, 10000), receive stop -> ok end end),
+    %% Synchronous requests wait for the target to collect
+    erlang:display(erlang:garbage_collect(Pid)),
+    erlang:display(erlang:garbage_collect(Pid, [{type, minor}])),
+    %% Asynchronous requests reply once the collection is done
+    Ref = make_ref(),
+    erlang:display(erlang:garbage_collect(Pid, [{async, Ref}])),
+    receive {garbage_collect, Ref, Result} -> erlang:display(Result) end,
+    %% The target keeps running, and can be asked again
+    erlang:display(erlang:garbage_collect(Pid, [{async, again}, {type, major}])),
+    receive {garbage_collect, again, Again} -> erlang:display(Again) end,
+    erlang:display(erlang:garbage_collect(self(), [])),
+    %% Processes which are not alive cannot be collected
+    MRef = monitor(process, Pid),
+    Pid ! stop,
+    receive {'DOWN', MRef, process, Pid, _} -> ok end,
+    erlang:display(erlang:garbage_collect(Pid)),
+    erlang:display(erlang:garbage_collect(Pid, [{async, dead}])),
+    receive {garbage_collect, dead, Dead} -> erlang:display(Dead) end,
+    erlang:display(catch erlang:garbage_collect(self(), [bad])),
+    ok.
@@ -0,0 +1,37 @@
+-module(init).
+
+-export([boot/1, leader/1]).
+
+boot(_) ->
+    Self = self(),
+    %% Processes inherit the group leader of their parent
+    GL = group_leader(),
+    Child = spawn(fun () -> Self ! {self(), group_leader()}, child(Self) end),
+    receive {Child, Inherited} -> erlang:display(Inherited =:= GL) end,
+    erlang:display(process_info(Child, group_leader) =:= {group_leader, GL}),
+    %% The group leader of another process can be changed
+    Leader = spawn(init, leader, [Self]),
+    erlang:display(group_leader(Leader, Child)),
+    erlang:display(process_info(Child, group_leader) =:= {group_leader, Leader}),
+    %% io requests are sent to the group leader of the process making them
+    Child ! {put_chars, <<"hello\n">>},
+    receive {Leader, Request} -> erlang:display(Request) end,
+    receive {Child, Reply} -> erlang:display(Reply) end,
+    erlang:display(catch group_leader(not_a_pid, Child)),
+    ok.
+
+child(Parent) ->
+    receive
+        {put_chars, Chars} ->
+            Ref = make_ref(),
+            group_leader() ! {io_request, self(), Ref, {put_chars, unicode, Chars}},
+            receive {io_reply, Ref, Reply} -> Parent ! {self(), Reply} end
+    end.
+
+leader(Parent) ->
+    receive
+        {io_request, From, ReplyAs, Request} ->
+            Parent ! {self(), Request},
+            From ! {io_reply, ReplyAs, ok},
+            leader(Parent)
+    end.
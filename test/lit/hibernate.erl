@@ -0,0 +1,47 @@
+-module(init).
+
+-export([boot/1, sleep/1, wake/2]).
+
+boot(_) ->
+    Self = self(),
+    Pid = spawn(init, sleep, [Self]),
+    receive
+        {Pid, hibernating} ->
+            ok
+    after
+        5000 ->
+            erlang:display(timeout)
+    end,
+    %% Give the process time to hibernate, it has no messages, so it won't wake up
+    receive after 100 -> ok end,
+    %% Garbage left by the process before hibernating is gone, and its stack is discarded
+    {heap_size, HeapSize} = process_info(Pid, heap_size),
+    erlang:display(HeapSize < 1000),
+    {stack_size, StackSize} = process_info(Pid, stack_size),
+    erlang:display(StackSize < 10),
+    %% Non-message signals do not wake the process
+    Ref = monitor(process, Pid),
+    erlang:display(is_process_alive(Pid)),
+    Pid ! {Self, hello},
+    receive
+        {Pid, woke, State, hello} ->
+            erlang:display(State)
+    end,
+    %% Returning from the function hibernation resumed in exits the process
+    receive
+        {'DOWN', Ref, process, Pid, Reason} ->
+            erlang:display(Reason)
+    end.
+
+sleep(Parent) ->
+    Garbage = lists:seq(1, 10000),
+    erlang:display(length(Garbage)),
+    Parent ! {self(), hibernating},
+    erlang:hibernate(init, wake, [Parent, state]).
+
+wake(Parent, State) ->
+    receive
+        {Parent, Msg} ->
+            Parent ! {self(), woke, State, Msg},
+            ok
+    end.
@@ -0,0 +1,30 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    %% The largest and smallest immediate integers
+    Max = 16#7ffffffffffff,
+    Min = -16#8000000000000,
+    erlang:display(Max + 1),
+    erlang:display(Min - 1),
+    erlang:display(Max * Max),
+    erlang:display(Min * -1),
+    erlang:display((Max + 1) - 1 =:= Max),
+    erlang:display(Max * 2 - Max =:= Max),
+    erlang:display(checksum(lists:seq(1, 10000), 1, 0)),
+    erlang:display(fnv(lists:seq(1, 10000), 16#811c9dc5)),
+    erlang:display(catch Max + foo),
+    ok.
+
+checksum([], A, B) ->
+    (B bsl 16) bor A;
+checksum([Byte | Rest], A0, B0) ->
+    A = (A0 + (Byte band 16#ff)) rem 65521,
+    B = (B0 + A) rem 65521,
+    checksum(Rest, A, B).
+
+fnv([], Hash) ->
+    Hash;
+fnv([Byte | Rest], Hash) ->
+    fnv(Rest, ((Hash bxor (Byte band 16#ff)) * 16#01000193) band 16#ffffffff).
@@ -0,0 +1,25 @@
+-module(init).
+
+-export([boot/1, wait/0]).
+
+boot(_) ->
+    erlang:display(is_process_alive(self())),
+    %% A process waiting in receive is alive
+    Pid = spawn(init, wait, []),
+    erlang:display(is_process_alive(Pid)),
+    erlang:display(process_info(Pid, status)),
+    %% Once killed, it is no longer alive, even if it hasn't finished exiting yet
+    Ref = monitor(process, Pid),
+    exit(Pid, kill),
+    erlang:display(is_process_alive(Pid)),
+    receive {'DOWN', Ref, process, Pid, killed} -> ok end,
+    erlang:display(is_process_alive(Pid)),
+    erlang:display(process_info(Pid, status)),
+    %% Processes which are exiting normally also aren't alive
+    Pid2 = spawn(fun() -> ok end),
+    erlang:display(is_process_alive(Pid2)),
+    erlang:display(catch is_process_alive(foo)),
+    ok.
+
+wait() ->
+    receive stop -> ok end.
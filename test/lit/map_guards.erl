@@ -0,0 +1,32 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    Map = #{a => 1, b => 2},
+    %% In guards, a missing key or a non-map fails the guard rather than raising
+    erlang:display(has_key(a, Map)),
+    erlang:display(has_key(c, Map)),
+    erlang:display(has_key(a, not_a_map)),
+    erlang:display(get_or_default(b, Map)),
+    erlang:display(get_or_default(c, Map)),
+    erlang:display(get_or_default(b, [])),
+    erlang:display(size_of(Map)),
+    erlang:display(size_of(not_a_map)),
+    %% Outside of guards, the same BIFs raise
+    erlang:display(is_map_key(c, Map)),
+    erlang:display(map_get(a, Map)),
+    erlang:display(catch map_get(c, Map)),
+    erlang:display(catch map_get(a, not_a_map)),
+    erlang:display(catch is_map_key(a, not_a_map)),
+    erlang:display(catch map_size(not_a_map)),
+    ok.
+
+has_key(Key, Map) when is_map_key(Key, Map) -> true;
+has_key(_Key, _Map) -> false.
+
+get_or_default(Key, Map) when map_get(Key, Map) > 1 -> map_get(Key, Map);
+get_or_default(_Key, _Map) -> default.
+
+size_of(Map) when map_size(Map) > 0 -> map_size(Map);
+size_of(_Map) -> none.
@@ -0,0 +1,30 @@
+-module(init).
+
+-export([boot/1, config/0, spawned/1]).
+
+boot(_) ->
+    %% Map literals are built once when the module is loaded, so every evaluation is the same map
+    Config = config(),
+    erlang:display(Config),
+    erlang:display(erts_debug:same(Config, config())),
+    erlang:display(maps:get(port, Config)),
+    erlang:display(maps:get(<<"name">>, Config)),
+    %% Updating a literal map produces a new map, leaving the literal as it was
+    Updated = Config#{port := 9090, debug => true},
+    erlang:display(Updated),
+    erlang:display(config()),
+    %% Literal maps survive garbage collection, and can be sent to other processes
+    erlang:garbage_collect(),
+    erlang:display(Config =:= config()),
+    Self = self(),
+    spawn(init, spawned, [Self]),
+    receive {config, Received} -> erlang:display(Received =:= Config) end,
+    %% Maps containing non-constant values are still built at runtime
+    erlang:display(#{pid => is_pid(Self), list => [1, 2]}),
+    ok.
+
+config() ->
+    #{port => 8080, timeout => 5.0e3, <<"name">> => <<"firefly">>, big => 1099511627776}.
+
+spawned(Parent) ->
+    Parent ! {config, config()}.
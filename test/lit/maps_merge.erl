@@ -0,0 +1,20 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    %% Config merging: defaults overridden by user settings
+    Defaults = #{port => 8080, host => "localhost", timeout => 5000},
+    erlang:display(maps:merge(Defaults, #{port => 9090})),
+    %% Overrides which change nothing, or replace everything, leave the map as-is
+    Same = maps:merge(Defaults, #{timeout => 5000}),
+    erlang:display(Same =:= Defaults),
+    erlang:display(maps:merge(#{a => 1}, #{a => 2, b => 3})),
+    %% Changeset-like merges add keys which sort after or before all the others
+    Changes = maps:merge(#{changes => #{}, valid => true}, #{zz_errors => []}),
+    erlang:display(Changes),
+    erlang:display(maps:merge(#{z => 1}, #{a => 2})),
+    erlang:display(maps:merge(#{}, #{a => 1})),
+    erlang:display(catch maps:merge(not_a_map, #{})),
+    erlang:display(catch maps:merge(#{}, not_a_map)),
+    ok.
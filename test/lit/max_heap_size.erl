@@ -0,0 +1,26 @@
+-module(init).
+
+-export([boot/1, grow/1]).
+
+boot(_) ->
+    Self = self(),
+    %% A process whose heap grows beyond its limit is killed, which cannot be trapped
+    Limit = #{size => 10000, kill => true, error_logger => false},
+    {Pid, Ref} = spawn_opt(init, grow, [Self], [monitor, {max_heap_size, Limit}]),
+    receive {'DOWN', Ref, process, Pid, Reason} -> erlang:display(Reason) end,
+    %% Without kill, the process is only reported and keeps running
+    Report = #{size => 10000, kill => false, error_logger => false},
+    {Pid2, Ref2} = spawn_opt(init, grow, [Self], [monitor, {max_heap_size, Report}]),
+    receive {Pid2, Len} -> erlang:display(Len) end,
+    receive {'DOWN', Ref2, process, Pid2, Reason2} -> erlang:display(Reason2) end,
+    %% The limit is in words, and the previous limit is returned when it is changed
+    erlang:display(process_flag(max_heap_size, 1000000)),
+    erlang:display(process_flag(max_heap_size, Report)),
+    erlang:display(process_flag(max_heap_size, 0)),
+    erlang:display(catch process_flag(max_heap_size, -1)),
+    ok.
+
+grow(Parent) ->
+    process_flag(trap_exit, true),
+    List = lists:seq(1, 100000),
+    Parent ! {self(), length(List)}.
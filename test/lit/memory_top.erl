@@ -0,0 +1,36 @@
+-module(init).
+
+-export([boot/1, hold/2]).
+
+boot(_) ->
+    Self = self(),
+    %% A process holding a large heap, and one holding a large off-heap binary
+    Big = spawn(init, hold, [Self, list]),
+    Bin = spawn(init, hold, [Self, binary]),
+    receive {Big, ready} -> ok end,
+    receive {Bin, ready} -> ok end,
+    [{Big, Heap}] = firefly_memory:top(heap, 1),
+    erlang:display(Heap > 800000),
+    [{Bin, Binary}] = firefly_memory:top(binary, 1),
+    erlang:display(Binary >= 1000000),
+    erlang:display(length(firefly_memory:top(heap, 2))),
+    erlang:display(firefly_memory:top(heap, 0)),
+    erlang:display(catch firefly_memory:top(ets, 1)),
+    Big ! {Self, stop},
+    Bin ! {Self, stop},
+    ok.
+
+hold(Parent, Kind) ->
+    Term = make(Kind),
+    %% Make sure the memory in use is published
+    erlang:garbage_collect(),
+    Parent ! {self(), ready},
+    receive
+        {_From, stop} ->
+            erlang:display(is_list(Term) orelse is_binary(Term))
+    end.
+
+make(list) ->
+    lists:seq(1, 100000);
+make(binary) ->
+    list_to_binary(lists:duplicate(1000000, $a)).
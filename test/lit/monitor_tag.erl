@@ -0,0 +1,28 @@
+-module(init).
+
+-export([boot/1, wait/0]).
+
+boot(_) ->
+    %% A monitor of a pid with a custom tag reports the pid under that tag
+    Pid = spawn(init, wait, []),
+    Ref = monitor(process, Pid, [{tag, {my_tag, 1}}]),
+    Pid ! stop,
+    receive {{my_tag, 1}, Ref, process, Pid, normal} -> erlang:display(tagged_pid) end,
+    %% A monitor by name with a custom tag still reports {Name, Node}
+    Pid2 = spawn(init, wait, []),
+    true = register(tagged, Pid2),
+    Ref2 = monitor(process, tagged, [{tag, named_down}]),
+    Pid2 ! stop,
+    Node = node(),
+    receive {named_down, Ref2, process, {tagged, Node}, normal} -> erlang:display(tagged_name) end,
+    %% The tag is used when the target does not exist too
+    Ref3 = monitor(process, missing, [{tag, missing_down}]),
+    receive {missing_down, Ref3, process, {missing, Node}, noproc} -> erlang:display(noproc) end,
+    %% Other monitors are unaffected
+    {Pid4, Ref4} = spawn_monitor(fun() -> ok end),
+    receive {'DOWN', Ref4, process, Pid4, normal} -> erlang:display(untagged) end,
+    erlang:display(catch monitor(process, self(), [{tag}])),
+    ok.
+
+wait() ->
+    receive stop -> ok end.
@@ -0,0 +1,52 @@
+-module(init).
+
+-export([boot/1, spin/0]).
+
+boot(_) ->
+    Self = self(),
+    Pid = spawn(fun () ->
+        erlang:process_flag(trap_exit, true),
+        link(Self),
+        put(key, value),
+        Self ! ready,
+        receive
+            stop ->
+                ok
+        end
+    end),
+    receive
+        ready ->
+            ok
+    end,
+    Pid ! hello,
+    Pid ! world,
+    {message_queue_len, 2} = process_info(Pid, message_queue_len),
+    {messages, [hello, world]} = process_info(Pid, messages),
+    [{links, [Self]}, {trap_exit, true}] = process_info(Pid, [links, trap_exit]),
+    {{dictionary, key}, value} = process_info(Pid, {dictionary, key}),
+    [] = process_info(Pid, registered_name),
+    {status, running} = process_info(Self, status),
+    {status, Status} = process_info(Pid, status),
+    erlang:display({status, Status}),
+    true = is_list(process_info(Pid)),
+    %% The target may be answered for while it is queued behind busy processes, the result is the
+    %% same either way
+    Spinners = [spawn(init, spin, []) || _ <- lists:seq(1, 16)],
+    {messages, [hello, world]} = process_info(Pid, messages),
+    {{dictionary, key}, value} = process_info(Pid, {dictionary, key}),
+    [Spinner ! stop || Spinner <- Spinners],
+    Ref = monitor(process, Pid),
+    Pid ! stop,
+    receive
+        {'DOWN', Ref, process, Pid, _} ->
+            erlang:display(process_info(Pid, status))
+    end.
+
+spin() ->
+    receive
+        stop ->
+            ok
+    after
+        0 ->
+            spin()
+    end.
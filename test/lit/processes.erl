@@ -0,0 +1,32 @@
+-module(init).
+
+-export([boot/1, wait/0]).
+
+boot(_) ->
+    Self = self(),
+    Count = erlang:system_info(process_count),
+    Pid = spawn(init, wait, []),
+    Processes = processes(),
+    erlang:display(lists:member(Self, Processes)),
+    erlang:display(lists:member(Pid, Processes)),
+    erlang:display(length(Processes) =:= Count + 1),
+    erlang:display(erlang:system_info(process_count) =:= Count + 1),
+    erlang:display(erlang:system_info(process_limit) >= length(Processes)),
+    Ref = monitor(process, Pid),
+    Pid ! stop,
+    receive
+        {'DOWN', Ref, process, Pid, normal} ->
+            %% Once gone, the pid of the exited process is never reported again, even after
+            %% its slot in the process table is reused
+            Spawned = [spawn(init, wait, []) || _ <- lists:seq(1, 10)],
+            erlang:display(lists:member(Pid, processes())),
+            erlang:display(is_process_alive(Pid)),
+            [S ! stop || S <- Spawned],
+            ok
+    end.
+
+wait() ->
+    receive
+        stop ->
+            ok
+    end.
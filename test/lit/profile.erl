@@ -0,0 +1,17 @@
+-module(init).
+
+-export([boot/1, count/1]).
+
+boot(_) ->
+    ok = firefly_profile:start(1),
+    {error, already_started} = firefly_profile:start(),
+    ok = count(1000000),
+    ok = firefly_profile:stop(),
+    {error, not_started} = firefly_profile:stop(),
+    Folded = firefly_profile:dump(),
+    erlang:display(is_binary(Folded)).
+
+count(0) ->
+    ok;
+count(N) ->
+    count(N - 1).
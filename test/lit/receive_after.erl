@@ -0,0 +1,31 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    Self = self(),
+    %% after 0 only searches the queue
+    erlang:display(receive nothing -> nothing after 0 -> empty end),
+    self() ! present,
+    erlang:display(receive present -> present after 0 -> empty end),
+    %% A finite timeout expires when no message matches
+    self() ! unmatched,
+    erlang:display(receive nothing -> nothing after 10 -> timeout end),
+    %% The next receive does not time out early because of the previous one
+    spawn(fun () -> timer_sleep(50), Self ! late end),
+    erlang:display(receive late -> late after 1000 -> timeout end),
+    %% A message arriving while waiting cancels the timer, even if others do not match
+    spawn(fun () -> Self ! ignored, Self ! wanted end),
+    erlang:display(receive wanted -> wanted after 1000 -> timeout end),
+    erlang:display(receive nothing -> nothing after 100 -> timeout end),
+    %% after infinity waits for a message
+    spawn(fun () -> Self ! forever end),
+    erlang:display(receive forever -> forever after infinity -> timeout end),
+    %% Messages skipped by a timed out receive are still in the queue, in order
+    erlang:display(receive M1 -> M1 end),
+    erlang:display(receive M2 -> M2 end),
+    erlang:display(catch receive nothing -> nothing after bad -> timeout end),
+    ok.
+
+timer_sleep(Ms) ->
+    receive after Ms -> ok end.
@@ -0,0 +1,48 @@
+-module(init).
+
+-export([boot/1, spin/1, hoard/1]).
+
+boot(_) ->
+    Self = self(),
+    true = register(recon_test, Self),
+    Busy = spawn(init, spin, [0]),
+    Leaky = spawn(init, hoard, [Self]),
+    receive {Leaky, ready} -> ok end,
+    %% The busy process keeps executing over the whole window
+    [{Busy, Reds, Info}] = firefly_recon:proc_window(reductions, 1, 100),
+    erlang:display(Reds > 0),
+    erlang:display(Info),
+    %% Messages nobody receives pile up
+    [Leaky ! junk || _ <- lists:seq(1, 100)],
+    [{Leaky, Len, _}] = firefly_recon:proc_count(message_queue_len, 1),
+    erlang:display(Len >= 100),
+    %% The binary dropped by the hoarding process is only released by a collection
+    [{Leaky, Freed, _} | _] = firefly_recon:bin_leak(1),
+    erlang:display(Freed < 0),
+    erlang:display(firefly_recon:info(Self)),
+    erlang:display(length(firefly_recon:sample(memory)) >= 3),
+    erlang:display(firefly_recon:proc_count(memory, 0)),
+    erlang:display(catch firefly_recon:proc_count(heap, 1)),
+    Busy ! stop,
+    Leaky ! stop,
+    ok.
+
+spin(N) ->
+    receive
+        stop ->
+            ok
+    after
+        0 ->
+            spin(N + 1)
+    end.
+
+hoard(Parent) ->
+    Bin = list_to_binary(lists:duplicate(1000000, $a)),
+    %% Publish the memory used while the binary is still referenced, it is garbage afterwards
+    erlang:garbage_collect(),
+    erlang:display(byte_size(Bin)),
+    Parent ! {self(), ready},
+    receive
+        stop ->
+            ok
+    end.
@@ -0,0 +1,21 @@
+%% RUN: @firefly compile --bin -o @tempfile @file && @tempfile
+
+%% CHECK: {point,1,2,3}
+%% CHECK: {point,10,20,3}
+%% CHECK: {{point,10,2,3},{point,10,2,30}}
+-module(init).
+
+-export([boot/1]).
+
+-record(point, {x, y, z}).
+
+boot(_Args) ->
+    P0 = #point{x = 1, y = 2, z = 3},
+    %% A chain of updates, only the first of which needs to copy the tuple
+    P1 = P0#point{y = 20, x = 10},
+    erlang:display(P0),
+    erlang:display(P1),
+    %% The intermediate tuple is still referenced, so it must not be updated in place
+    P2 = erlang:setelement(2, P0, 10),
+    P3 = erlang:setelement(4, P2, 30),
+    erlang:display({P2, P3}).
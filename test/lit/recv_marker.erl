@@ -0,0 +1,45 @@
+-module(init).
+
+-export([boot/1, server/0]).
+
+boot(_) ->
+    Server = spawn(init, server, []),
+    %% Fill the mailbox with messages no reply receive will match
+    [self() ! {junk, N} || N <- lists:seq(1, 10000)],
+    %% Each call only looks at the messages queued after its reference was created
+    Replies = [call(Server, N) || N <- lists:seq(1, 1000)],
+    erlang:display(lists:sum(Replies)),
+    %% A reply which was sent before the receive started is still found
+    Ref = make_ref(),
+    self() ! {Ref, early},
+    receive {Ref, Early} -> erlang:display(Early) end,
+    %% Replies tagged with a monitor reference
+    Mref = monitor(process, Server),
+    Server ! {Mref, self(), 2},
+    receive
+        {Mref, Reply} -> erlang:display(Reply);
+        {'DOWN', Mref, _, _, Reason} -> erlang:display(Reason)
+    end,
+    demonitor(Mref, [flush]),
+    %% Receives which may match older messages still see all of them
+    receive {junk, First} -> erlang:display(First) end,
+    erlang:display(length(element(2, process_info(self(), messages)))),
+    Server ! stop,
+    ok.
+
+call(Server, N) ->
+    Ref = make_ref(),
+    Server ! {Ref, self(), N},
+    receive
+        {Ref, Reply} ->
+            Reply
+    end.
+
+server() ->
+    receive
+        {Ref, From, N} ->
+            From ! {Ref, N * 2},
+            server();
+        stop ->
+            ok
+    end.
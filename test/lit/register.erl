@@ -0,0 +1,35 @@
+-module(init).
+
+-export([boot/1, wait/0]).
+
+boot(_) ->
+    Self = self(),
+    erlang:display(register(boot_proc, Self)),
+    erlang:display(whereis(boot_proc) =:= Self),
+    erlang:display(lists:member(boot_proc, registered())),
+    %% A name may only be registered once, and a process may only have one name
+    Other = spawn(init, wait, []),
+    erlang:display(catch register(boot_proc, Other)),
+    erlang:display(catch register(other_name, Self)),
+    erlang:display(catch register(undefined, Other)),
+    erlang:display(catch register("name", Other)),
+    %% Unregistering frees the name for someone else
+    erlang:display(unregister(boot_proc)),
+    erlang:display(whereis(boot_proc)),
+    erlang:display(catch unregister(boot_proc)),
+    erlang:display(register(boot_proc, Other)),
+    erlang:display(whereis(boot_proc) =:= Other),
+    %% The name is gone by the time monitors of the process fire
+    Ref = monitor(process, Other),
+    Other ! stop,
+    receive {'DOWN', Ref, process, _, _} -> ok end,
+    erlang:display(whereis(boot_proc)),
+    erlang:display(lists:member(boot_proc, registered())),
+    erlang:display(register(boot_proc, Self)),
+    ok.
+
+wait() ->
+    receive
+        stop ->
+            ok
+    end.
@@ -0,0 +1,27 @@
+-module(init).
+
+-export([boot/1, collect/2]).
+
+boot(_) ->
+    Self = self(),
+    Events = [{event, N} || N <- lists:seq(1, 10000)],
+    %% A batch is delivered in order, like the same messages sent one at a time
+    Collector = spawn(init, collect, [Self, length(Events)]),
+    erlang:display(firefly_send:batch(Collector, Events)),
+    receive {Collector, Received} -> erlang:display(Received =:= Events) end,
+    %% Registered names are resolved like with !
+    register(batch_test, Self),
+    erlang:display(firefly_send:batch(batch_test, [a, b])),
+    erlang:display(receive M1 -> M1 end),
+    erlang:display(receive M2 -> M2 end),
+    erlang:display(firefly_send:batch(Self, [])),
+    %% Messages to processes which are not alive are dropped
+    {Dead, Ref} = spawn_monitor(fun () -> ok end),
+    receive {'DOWN', Ref, process, Dead, _} -> ok end,
+    erlang:display(firefly_send:batch(Dead, [a, b])),
+    erlang:display(catch firefly_send:batch(not_registered, [a])),
+    erlang:display(catch firefly_send:batch(Self, [a | b])),
+    ok.
+
+collect(Parent, N) ->
+    Parent ! {self(), [receive M -> M end || _ <- lists:seq(1, N)]}.
@@ -0,0 +1,24 @@
+-module(init).
+
+-export([boot/1, subscriber/1]).
+
+boot(_) ->
+    Self = self(),
+    Subscribers = [spawn(init, subscriber, [Self]) || _ <- lists:seq(1, 3)],
+    erlang:display(firefly_send:multicast(Subscribers, {event, 1})),
+    erlang:display([receive {Sub, Event} -> Event end || Sub <- Subscribers]),
+    %% Processes which are not alive are skipped
+    {Dead, Ref} = spawn_monitor(fun () -> ok end),
+    receive {'DOWN', Ref, process, Dead, _} -> ok end,
+    erlang:display(firefly_send:multicast([Dead, Self], hello)),
+    erlang:display(receive M -> M end),
+    erlang:display(firefly_send:multicast([], hello)),
+    erlang:display(catch firefly_send:multicast([Self, not_a_pid], hello)),
+    erlang:display(catch firefly_send:multicast(Self, hello)),
+    ok.
+
+subscriber(Parent) ->
+    receive
+        Event ->
+            Parent ! {self(), Event}
+    end.
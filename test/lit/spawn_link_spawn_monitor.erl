@@ -0,0 +1,27 @@
+-module(init).
+
+-export([boot/1, exit_now/1]).
+
+boot(_) ->
+    %% The child exits before it can be observed, so these only pass if the monitor
+    %% and link are in place before it is scheduled
+    {Pid, Ref} = spawn_monitor(init, exit_now, [crashed]),
+    receive
+        {'DOWN', Ref, process, Pid, Reason} ->
+            erlang:display({monitor, Reason})
+    after
+        10 ->
+            erlang:display({monitor, timeout})
+    end,
+    process_flag(trap_exit, true),
+    Linked = spawn_link(fun () -> exit(linked) end),
+    receive
+        {'EXIT', Linked, LinkReason} ->
+            erlang:display({link, LinkReason})
+    after
+        10 ->
+            erlang:display({link, timeout})
+    end.
+
+exit_now(Reason) ->
+    exit(Reason).
@@ -0,0 +1,21 @@
+-module(init).
+
+-export([boot/1, spin/1]).
+
+boot(_) ->
+    %% A process which never receives still handles requests between reductions
+    Pid = spawn(init, spin, [0]),
+    erlang:display(erlang:garbage_collect(Pid)),
+    Ref = make_ref(),
+    erlang:display(erlang:garbage_collect(Pid, [{async, Ref}, {type, minor}])),
+    receive {garbage_collect, Ref, Result} -> erlang:display(Result) end,
+    %% Requests are signals, so one made after an exit signal is never executed
+    MRef = monitor(process, Pid),
+    exit(Pid, kill),
+    erlang:display(erlang:garbage_collect(Pid, [{async, killed}])),
+    receive {garbage_collect, killed, Killed} -> erlang:display(Killed) end,
+    receive {'DOWN', MRef, process, Pid, Reason} -> erlang:display(Reason) end,
+    ok.
+
+spin(N) ->
+    spin(N + 1).
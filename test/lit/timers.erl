@@ -0,0 +1,45 @@
+-module(init).
+
+-export([boot/1, cancel_elsewhere/2]).
+
+boot(_) ->
+    %% send_after delivers the message as-is, start_timer wraps it in a timeout tuple
+    erlang:send_after(10, self(), hello),
+    receive hello -> erlang:display(hello) end,
+    Ref = erlang:start_timer(10, self(), wrapped),
+    receive {timeout, Ref, wrapped} -> erlang:display(wrapped) end,
+    %% Timers to names are resolved when they fire
+    erlang:register(timers_test, self()),
+    erlang:send_after(0, timers_test, by_name),
+    receive by_name -> erlang:display(by_name) end,
+    %% Absolute timers fire at the given monotonic time
+    Deadline = erlang:monotonic_time(millisecond) + 20,
+    erlang:start_timer(Deadline, self(), absolute, [{abs, true}]),
+    receive {timeout, _, absolute} -> erlang:display(erlang:monotonic_time(millisecond) >= Deadline) end,
+    %% A pending timer can be read and cancelled, after which it is gone
+    Long = erlang:send_after(60000, self(), never),
+    Left = erlang:read_timer(Long),
+    erlang:display(Left > 59000 andalso Left =< 60000),
+    erlang:display(erlang:cancel_timer(Long) > 0),
+    erlang:display(erlang:read_timer(Long)),
+    erlang:display(erlang:cancel_timer(Long)),
+    %% Asynchronous reads and cancellations reply with a message
+    Async = erlang:send_after(60000, self(), never),
+    ok = erlang:read_timer(Async, [{async, true}]),
+    receive {read_timer, Async, Time} -> erlang:display(is_integer(Time)) end,
+    ok = erlang:cancel_timer(Async, [{async, true}]),
+    receive {cancel_timer, Async, Result} -> erlang:display(is_integer(Result)) end,
+    erlang:display(erlang:cancel_timer(Async, [{info, false}])),
+    %% Timers can be cancelled by any process, on any scheduler
+    Other = erlang:send_after(60000, self(), never),
+    Self = self(),
+    spawn(init, cancel_elsewhere, [Self, Other]),
+    receive {cancelled, Cancelled} -> erlang:display(is_integer(Cancelled)) end,
+    receive never -> erlang:display(never) after 50 -> erlang:display(no_stray_messages) end,
+    erlang:display(catch erlang:send_after(-1, self(), bad)),
+    erlang:display(catch erlang:send_after(10, self(), bad, [{abs, maybe}])),
+    erlang:display(catch erlang:cancel_timer(not_a_ref)),
+    ok.
+
+cancel_elsewhere(Parent, Ref) ->
+    Parent ! {cancelled, erlang:cancel_timer(Ref)}.
@@ -0,0 +1,43 @@
+-module(init).
+
+-export([boot/1, worker/1]).
+
+boot(_) ->
+    Self = self(),
+    {Pid, Ref} = spawn_monitor(init, worker, [Self]),
+    %% Collections are reported with the heap sizes before and after
+    erlang:display(erlang:trace(Pid, true, [garbage_collection])),
+    Pid ! collect,
+    receive {trace, Pid, gc_major_start, Start} -> erlang:display(heap_keys(Start)) end,
+    receive {trace, Pid, gc_major_end, End} -> erlang:display(heap_keys(End)) end,
+    receive {Pid, collected} -> ok end,
+    erlang:display(erlang:trace(Pid, false, [garbage_collection])),
+    %% Scheduling events carry the current function of the process
+    erlang:display(erlang:trace(Pid, true, [running, exiting])),
+    Pid ! ping,
+    receive {trace, Pid, in, InMFA} -> erlang:display(InMFA) end,
+    receive {trace, Pid, out, OutMFA} -> erlang:display(OutMFA) end,
+    receive {Pid, pong} -> ok end,
+    %% An exiting process no longer has a current function
+    Pid ! stop,
+    receive {trace, Pid, in, _} -> ok end,
+    receive {trace, Pid, out_exited, Exited} -> erlang:display(Exited) end,
+    receive {'DOWN', Ref, process, Pid, Reason} -> erlang:display(Reason) end,
+    erlang:display(catch erlang:trace(self(), true, [not_a_flag])),
+    ok.
+
+heap_keys(Info) ->
+    lists:sort([Key || {Key, _} <- Info]).
+
+worker(Parent) ->
+    receive
+        collect ->
+            erlang:garbage_collect(),
+            Parent ! {self(), collected},
+            worker(Parent);
+        ping ->
+            Parent ! {self(), pong},
+            worker(Parent);
+        stop ->
+            ok
+    end.
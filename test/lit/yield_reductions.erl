@@ -0,0 +1,18 @@
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    erlang:display(erlang:yield()),
+    erlang:display(erlang:bump_reductions(10)),
+    erlang:display(catch erlang:bump_reductions(0)),
+    %% Reversing a long list is accounted for in reductions, and spans several time slices
+    List = lists:seq(1, 100000),
+    {reductions, Before} = process_info(self(), reductions),
+    Reversed = lists:reverse(List, [tail]),
+    {reductions, After} = process_info(self(), reductions),
+    erlang:display(After - Before >= 100000 div 16),
+    erlang:display({hd(Reversed), lists:nth(100000, Reversed), lists:last(Reversed)}),
+    erlang:display(length(Reversed)),
+    erlang:display(catch lists:reverse([a, b | c], [])),
+    ok.